@@ -1,16 +1,24 @@
-use crate::game::{RelScore, State, StateIdent, WithNegInf, WithPosInf};
+use crate::game::{Neutral, RelScore, RelTerminal, State, StateIdent, WithNegInf, WithPosInf};
 use crate::{
-    ai::Ai,
+    ai::{
+        Ai, Bound, IterationStats, Line, SearchEvent, SearchedPosition, Subscribers,
+        evaluator::{Evaluator, Handwritten},
+        strength::{Noisy, Strength, mix64},
+    },
     game::{Game, GameLogic},
 };
 use std::cmp::Ordering;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{
+    Arc, Mutex, MutexGuard,
+    atomic::{AtomicU8, Ordering as AtomicOrdering},
+};
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod multithreaded;
 pub mod singlethreaded;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum TranspositionTableEntryFlag {
     Exact,
     LowerBound,
@@ -63,11 +71,59 @@ impl PvExtensionCounter {
 
 const MAX_QUIESCENCE_DEPTH: usize = 100;
 
+// Late move reductions apply to quiet moves after this many have been searched, with at least this much depth left
+const LMR_AFTER_MOVES: usize = 3;
+const LMR_MIN_DEPTH: usize = 3;
+
+/*
+Which of the search's optional prunings and reductions are used, so that the search can be compared with and without one.
+They are all used unless switched off, as when testing whether a new one helps.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchFeatures {
+    pub late_move_reductions: bool,
+    pub futility_pruning: bool,
+    pub delta_pruning: bool,
+    // Captures which lose material in the exchange are tried last, and not at all in quiescence
+    pub exchange_evaluation: bool,
+    // A move which gives check is searched a ply deeper
+    pub check_extensions: bool,
+}
+
+impl Default for SearchFeatures {
+    fn default() -> Self {
+        Self {
+            late_move_reductions: true,
+            futility_pruning: true,
+            delta_pruning: true,
+            exchange_evaluation: true,
+            check_extensions: true,
+        }
+    }
+}
+
+impl SearchFeatures {
+    // Switch a feature on or off by its short name, as given on the command line
+    pub fn with(mut self, name: &str, used: bool) -> Result<Self, String> {
+        match name {
+            "lmr" => self.late_move_reductions = used,
+            "futility" => self.futility_pruning = used,
+            "delta" => self.delta_pruning = used,
+            "see" => self.exchange_evaluation = used,
+            "check" => self.check_extensions = used,
+            _ => return Err(format!("Unknown search feature {name}")),
+        }
+        Ok(self)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ScoreQuality {
     depth: usize,
     quiescence_depth: usize,
     pv_extension_counter: PvExtensionCounter,
+    // How many more plies check extensions may add, so that a long run of checks can't make the search endless
+    check_extensions: usize,
 }
 
 impl ScoreQuality {
@@ -120,7 +176,10 @@ impl PartialOrd for ScoreQuality {
                         .cmp(&(other.depth + other.quiescence_depth)),
                 ),
             ),
-            Some(self.pv_extension_counter.cmp(&other.pv_extension_counter)),
+            combine_cmp(
+                Some(self.pv_extension_counter.cmp(&other.pv_extension_counter)),
+                Some(self.check_extensions.cmp(&other.check_extensions)),
+            ),
         )
     }
 }
@@ -153,7 +212,7 @@ impl Ord for SubjectiveScoreQuality {
             if !cmp.is_eq() {
                 return cmp;
             }
-            Ordering::Equal
+            self.q.check_extensions.cmp(&other.q.check_extensions)
         }
     }
 }
@@ -173,6 +232,8 @@ impl Iterator for ScoreQualityGenerator {
             depth: self.depth,
             quiescence_depth: self.quiescence_depth,
             pv_extension_counter: self.pv_extension_counter,
+            // As many as the nominal depth, so no line is searched more than twice as deep
+            check_extensions: self.depth,
         };
         if self.quiescence_depth < MAX_QUIESCENCE_DEPTH {
             if self.quiescence_depth < 100 {
@@ -202,18 +263,36 @@ impl ScoreQuality {
         }
     }
 
+    // One ply shallower, for late move reductions
+    fn reduced(self) -> Self {
+        debug_assert!(self.depth > 0);
+        Self {
+            depth: self.depth - 1,
+            ..self
+        }
+    }
+
+    // One ply deeper, for check extensions
+    fn extended(self) -> Option<Self> {
+        (self.check_extensions > 0).then(|| Self {
+            depth: self.depth + 1,
+            check_extensions: self.check_extensions - 1,
+            ..self
+        })
+    }
+
     fn decrement(self) -> Option<Self> {
         if self.depth > 0 {
             Some(Self {
                 depth: self.depth - 1,
-                quiescence_depth: self.quiescence_depth,
                 pv_extension_counter: self.pv_extension_counter.increment(),
+                ..self
             })
         } else if self.quiescence_depth > 0 {
             Some(Self {
-                depth: self.depth,
                 quiescence_depth: self.quiescence_depth - 1,
                 pv_extension_counter: self.pv_extension_counter.increment(),
+                ..self
             })
         } else {
             None
@@ -221,7 +300,7 @@ impl ScoreQuality {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct TranspositionTableEntry<G: GameLogic + Send> {
     score_quality: ScoreQuality,
     score: RelScore<G::HeuristicScore>,
@@ -232,19 +311,38 @@ struct TranspositionTableEntry<G: GameLogic + Send> {
 #[derive(Debug)]
 struct TranspositionTableItem<G: GameLogic + Send> {
     state: G::StateIdent,
-    score: Option<TranspositionTableEntry<G>>,
+    // The `TranspositionTable::generation` of the search which last stored it
+    generation: u8,
+    entry: TranspositionTableEntry<G>,
 }
 
 impl<G: GameLogic + Send> TranspositionTableItem<G> {
-    fn blank(state: G::StateIdent) -> Self {
-        Self { state, score: None }
+    // Entries of the current search and from deeper searches are more worth keeping
+    fn worth(&self, generation: u8) -> (bool, usize, usize) {
+        (
+            self.generation == generation,
+            self.entry.score_quality.depth,
+            self.entry.score_quality.quiescence_depth(),
+        )
     }
 }
 
+/*
+Positions hash to a bucket of `BUCKET_SIZE` slots.
+Each bucket has its own lock, so threads only wait for each other when they want the same bucket at the same time.
+All but the last slot are depth-preferred: a new position replaces the least worthy of them, but only if it's at least as worthy itself.
+Otherwise it goes in the last slot, which is always replaced, so that recent positions are kept too.
+Entries from earlier searches are replaced first, so that the table follows the game rather than filling up with old positions.
+ */
+const BUCKET_SIZE: usize = 4;
+
+type TranspositionTableBucket<G> = [Option<TranspositionTableItem<G>>; BUCKET_SIZE];
+
 #[derive(Debug)]
 struct TranspositionTable<G: GameLogic + Send> {
     n: u64,
-    entries: Vec<Option<TranspositionTableItem<G>>>,
+    buckets: Vec<Mutex<TranspositionTableBucket<G>>>,
+    generation: AtomicU8,
 }
 
 impl<G: GameLogic + Send> TranspositionTable<G> {
@@ -252,23 +350,31 @@ impl<G: GameLogic + Send> TranspositionTable<G> {
         debug_assert!(n <= 64);
         Self {
             n,
-            entries: (0..(1usize << n)).map(|_| None).collect(),
+            buckets: (0..(1usize << n))
+                .map(|_| Mutex::new(std::array::from_fn(|_| None)))
+                .collect(),
+            generation: AtomicU8::new(0),
         }
     }
 
+    // Called at the start of each search, to age the entries of earlier ones
+    fn new_search(&self) {
+        self.generation.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+
     fn idx_hash(&self, state: &G::StateIdent) -> usize {
         let hash64 = state.hash64();
         (hash64 & ((1 << self.n) - 1)) as usize
     }
 
-    fn maybe_get(&self, state: G::StateIdent) -> Option<&Option<TranspositionTableEntry<G>>> {
-        let idx = self.idx_hash(&state);
-        let entry_opt = &self.entries[idx];
-        if let Some(entry) = entry_opt {
-            #[allow(clippy::if_same_then_else)]
-            if entry.state.hash64() != state.hash64() {
-                return None;
-            } else if entry.state != state {
+    fn maybe_get(&self, state: G::StateIdent) -> Option<TranspositionTableEntry<G>> {
+        let hash64 = state.hash64();
+        let bucket = self.buckets[self.idx_hash(&state)].lock().unwrap();
+        for item in bucket.iter().flatten() {
+            if item.state.hash64() != hash64 {
+                continue;
+            }
+            if item.state != state {
                 #[cfg(false)]
                 {
                     // For debugging bad hashes
@@ -300,37 +406,144 @@ impl<G: GameLogic + Send> TranspositionTable<G> {
                     }
 
                     println!("Diff");
-                    println!("{}", print_debug_diff_count(&entry.state, &state));
+                    println!("{}", print_debug_diff_count(&item.state, &state));
                 }
 
-                return None;
+                continue;
             }
-        } else {
-            return None;
+            return Some(item.entry.clone());
         }
-        Some(&entry_opt.as_ref().unwrap().score)
+        None
     }
 
-    fn get(&mut self, state: G::StateIdent) -> &mut Option<TranspositionTableEntry<G>> {
-        let idx = self.idx_hash(&state);
-        let entry_opt = &mut self.entries[idx];
-        if let Some(entry) = entry_opt {
-            #[allow(clippy::if_same_then_else)]
-            if entry.state.hash64() != state.hash64() {
-                *entry_opt = Some(TranspositionTableItem::blank(state));
-            } else if entry.state != state {
-                *entry_opt = Some(TranspositionTableItem::blank(state));
+    fn store(&self, state: G::StateIdent, entry: TranspositionTableEntry<G>) {
+        let generation = self.generation.load(AtomicOrdering::Relaxed);
+        let mut bucket = self.buckets[self.idx_hash(&state)].lock().unwrap();
+        if let Some(item) = bucket.iter_mut().flatten().find(|item| item.state == state) {
+            item.generation = generation;
+            if item.entry.score_quality < entry.score_quality {
+                item.entry = entry;
+            }
+            return;
+        }
+        let item = TranspositionTableItem {
+            state,
+            generation,
+            entry,
+        };
+        let (depth_preferred, always_replace) = bucket.split_at_mut(BUCKET_SIZE - 1);
+        let victim = depth_preferred
+            .iter_mut()
+            .min_by_key(|slot| slot.as_ref().map(|existing| existing.worth(generation)));
+        match victim {
+            Some(victim)
+                if victim.as_ref().is_none_or(|existing| {
+                    existing.worth(generation) <= item.worth(generation)
+                }) =>
+            {
+                *victim = Some(item)
+            }
+            _ => always_replace[0] = Some(item),
+        }
+    }
+}
+
+// How often quiet moves in each `GameLogic::move_bucket` have caused a cutoff, weighted towards deeper searches
+#[derive(Debug, Default)]
+struct HistoryTable {
+    scores: Vec<u64>,
+}
+
+impl HistoryTable {
+    fn get(&self, bucket: Option<usize>) -> u64 {
+        bucket
+            .and_then(|bucket| self.scores.get(bucket).copied())
+            .unwrap_or(0)
+    }
+
+    fn reward(&mut self, bucket: usize, depth: usize) {
+        if self.scores.len() <= bucket {
+            self.scores.resize(bucket + 1, 0);
+        }
+        self.scores[bucket] += (depth * depth) as u64;
+        // Age the table so that it follows the current game rather than old ones
+        if self.scores[bucket] > 1 << 20 {
+            for score in &mut self.scores {
+                *score /= 2;
             }
-        } else {
-            *entry_opt = Some(TranspositionTableItem::blank(state));
         }
-        &mut entry_opt.as_mut().unwrap().score
+    }
+}
+
+/*
+The best move found at each root position searched, and how deep the search went, to carry over to later sessions.
+Positions are keyed by the hash of their `State::position_ident` and moves by their `GameLogic::move_name`, so that they can be saved as text.
+Each line of the text is `<hash in hex> <depth> <move>`.
+ */
+#[derive(Debug, Default)]
+struct Learning {
+    moves: HashMap<u64, (usize, String)>,
+}
+
+impl Learning {
+    fn key<G: GameLogic>(state: &G::State) -> u64 {
+        state.clone().position_ident().hash64()
+    }
+
+    fn learn<G: GameLogic>(&mut self, logic: &G, state: &G::State, depth: usize, mv: &G::Move) {
+        let learned = self.moves.entry(Self::key::<G>(state)).or_default();
+        if learned.0 <= depth {
+            *learned = (depth, logic.move_name(mv));
+        }
+    }
+
+    // The legal move named like the learned one
+    fn best_move<G: GameLogic>(&self, logic: &G, state: &mut G::State) -> Option<G::Move> {
+        let (_, learned) = self.moves.get(&Self::key::<G>(state))?;
+        logic
+            .generate_moves(state)
+            .into_iter()
+            .find(|mv| logic.move_name(mv) == *learned)
+    }
+
+    fn export(&self) -> String {
+        let mut lines = self
+            .moves
+            .iter()
+            .map(|(key, (depth, mv))| format!("{key:016x} {depth} {mv}\n"))
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines.concat()
+    }
+
+    // Adds to what's already learned, keeping the deeper search of each position
+    fn import(&mut self, text: &str) -> Result<(), String> {
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let invalid = || format!("Invalid line: {line}");
+            let mut parts = line.splitn(3, ' ');
+            let key = u64::from_str_radix(parts.next().ok_or_else(invalid)?, 16)
+                .map_err(|_| invalid())?;
+            let depth = parts
+                .next()
+                .ok_or_else(invalid)?
+                .parse::<usize>()
+                .map_err(|_| invalid())?;
+            let mv = parts.next().ok_or_else(invalid)?.to_string();
+            let learned = self.moves.entry(key).or_default();
+            if learned.0 <= depth {
+                *learned = (depth, mv);
+            }
+        }
+        Ok(())
     }
 }
 
 #[derive(Debug)]
 struct AlphaBetaPersistent<G: GameLogic + Send> {
     transpositions: TranspositionTable<G>,
+    // One for each thread, indexed by `thread_num`, so that the threads don't wait for each other to order moves
+    histories: Vec<Mutex<HistoryTable>>,
+    learning: Mutex<Learning>,
 }
 
 impl<G: GameLogic + Send> AlphaBetaPersistent<G> {
@@ -352,27 +565,271 @@ impl<G: GameLogic + Send> AlphaBetaPersistent<G> {
 
         log::info!("\tAvailable space {} MB", available_bytes / (1024 * 1024));
         let available_bytes = (available_bytes * 90) / 100;
-        let bytes_per_entry = std::mem::size_of::<Option<TranspositionTableItem<G>>>() as u64;
-        let max_tt_entries = available_bytes / bytes_per_entry;
+        let bytes_per_bucket = std::mem::size_of::<Mutex<TranspositionTableBucket<G>>>() as u64;
+        let max_tt_buckets = available_bytes / bytes_per_bucket;
         let mut n = 0;
-        while (1 << (n + 1)) <= max_tt_entries {
+        while (1 << (n + 1)) <= max_tt_buckets {
             n += 1;
         }
         log::info!(
             "\tAllocating {} entries in {} MB...",
-            (1 << n),
-            (bytes_per_entry * (1 << n)) / (1024 * 1024),
+            BUCKET_SIZE * (1 << n),
+            (bytes_per_bucket * (1 << n)) / (1024 * 1024),
         );
-        let p = Self {
-            transpositions: TranspositionTable::new(n),
-        };
+        let p = Self::with_transpositions(n);
         log::info!("\tDone");
         p
     }
+
+    // With room for 2^n buckets in the transposition table
+    fn with_transpositions(n: u64) -> Self {
+        Self {
+            transpositions: TranspositionTable::new(n),
+            histories: (0..num_cpus::get())
+                .map(|_| Mutex::new(HistoryTable::default()))
+                .collect(),
+            learning: Mutex::new(Learning::default()),
+        }
+    }
+
+    fn history(&self, thread_num: usize) -> MutexGuard<'_, HistoryTable> {
+        self.histories[thread_num % self.histories.len()]
+            .lock()
+            .unwrap()
+    }
+
+    fn searched_position(&self, state: &G::State) -> Option<SearchedPosition<G>> {
+        let entry = self.transpositions.maybe_get(state.clone().ident())?;
+        Some(SearchedPosition {
+            score: score_label(&entry.score),
+            depth: entry.score_quality.depth,
+            bound: match entry.flag {
+                TranspositionTableEntryFlag::Exact => Bound::Exact,
+                TranspositionTableEntryFlag::LowerBound => Bound::Lower,
+                TranspositionTableEntryFlag::UpperBound => Bound::Upper,
+            },
+            best_move: entry.best_move,
+        })
+    }
+}
+
+// Settings from the `Ai` trait, which apply from the next `set_game`
+#[derive(Debug, Clone)]
+struct SearchOptions<G: GameLogic> {
+    multi_pv: usize,
+    evaluator: Arc<dyn Evaluator<G>>,
+    subscribers: Arc<Subscribers<G>>,
+    strength: Strength,
+    features: SearchFeatures,
+}
+
+impl<G: GameLogic> SearchOptions<G> {
+    fn new() -> Self {
+        Self {
+            multi_pv: 1,
+            evaluator: Arc::new(Handwritten),
+            subscribers: Arc::new(Subscribers::default()),
+            strength: Strength::FULL,
+            features: SearchFeatures::default(),
+        }
+    }
+
+    // A weakened strength needs the alternatives to choose its mistakes from
+    fn lines(&self) -> usize {
+        self.multi_pv.max(self.strength.candidates)
+    }
+
+    fn search_evaluator(&self) -> Arc<dyn Evaluator<G>> {
+        if self.strength.noise == 0 {
+            self.evaluator.clone()
+        } else {
+            Arc::new(Noisy {
+                inner: self.evaluator.clone(),
+                noise: self.strength.noise,
+            })
+        }
+    }
+}
+
+// What happened during a search, for `IterationStats`
+#[derive(Debug, Clone, Copy, Default)]
+struct SearchCounters {
+    nodes: usize,
+    quiescence_nodes: usize,
+    tt_probes: usize,
+    tt_hits: usize,
+    // Nodes which got as far as searching their moves
+    expanded_nodes: usize,
+    cutoffs: usize,
+}
+
+fn ratio(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
 }
 
 trait StopCondition: Clone {
-    fn stop(&self) -> bool;
+    // `nodes` is how many nodes this search has visited so far, for conditions too slow to check at every node
+    fn stop(&self, nodes: usize) -> bool;
+}
+
+// Shuffle so different threads look at different things
+fn shuffle<T>(vec: &mut [T], mut seed: usize) {
+    fn next_u32(seed: &mut usize) -> u32 {
+        *seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        (*seed >> 16) as u32
+    }
+    let len = vec.len();
+    for i in (1..len).rev() {
+        let j = (next_u32(&mut seed) as usize) % (i + 1);
+        vec.swap(i, j);
+    }
+}
+
+/*
+The moves to try at a node, generated a stage at a time so that a cutoff skips generating the rest.
+The best move from the transposition table goes first, once a generated stage shows it is legal.
+Within a stage, moves are sorted by `GameLogic::move_ordering_hint` and then by the history table.
+ */
+struct StagedMoves<G: GameLogic + Send> {
+    quiescence: bool,
+    exchange_evaluation: bool,
+    thread_num: usize,
+    probable_best_move: Option<G::Move>,
+    shuffle: bool,
+    // Root moves left out when looking for alternatives to the best move
+    excluded: Vec<G::Move>,
+    next_stage: usize,
+    finished: bool,
+    pending: VecDeque<G::Move>,
+}
+
+impl<G: GameLogic + Send> StagedMoves<G> {
+    fn new(
+        quiescence: bool,
+        exchange_evaluation: bool,
+        thread_num: usize,
+        probable_best_move: Option<G::Move>,
+        excluded: &[G::Move],
+    ) -> Self {
+        let probable_best_move = probable_best_move.filter(|mv| !excluded.contains(mv));
+        Self {
+            quiescence,
+            exchange_evaluation,
+            thread_num,
+            shuffle: probable_best_move.is_some(),
+            probable_best_move,
+            excluded: excluded.to_vec(),
+            next_stage: 0,
+            finished: false,
+            pending: VecDeque::new(),
+        }
+    }
+
+    // Returns false once every stage has been generated
+    fn generate_stage(
+        &mut self,
+        logic: &G,
+        state: &mut G::State,
+        persistent: &AlphaBetaPersistent<G>,
+    ) -> bool {
+        if self.finished {
+            return false;
+        }
+        let moves = if self.quiescence {
+            self.finished = true;
+            logic.generate_quiescence_moves(state)
+        } else if let Some(moves) = logic.generate_move_stage(state, self.next_stage) {
+            self.next_stage += 1;
+            moves
+        } else {
+            self.finished = true;
+            return false;
+        };
+        // Whether each move loses material in the exchange on the square it moves to
+        let mut moves = moves
+            .into_iter()
+            .map(|mv| {
+                let losing = self.exchange_evaluation
+                    && logic
+                        .exchange_value(state, &mv)
+                        .is_some_and(|value| value < 0);
+                (mv, losing)
+            })
+            .filter(|(_, losing)| !(self.quiescence && *losing))
+            .collect::<Vec<_>>();
+        if self.shuffle {
+            shuffle(&mut moves, self.thread_num);
+        }
+        // A stable sort, so moves with equal keys stay shuffled
+        let history = persistent.history(self.thread_num);
+        moves.sort_by_key(|(mv, losing)| {
+            (
+                *losing,
+                std::cmp::Reverse(logic.move_ordering_hint(mv)),
+                std::cmp::Reverse(history.get(logic.move_bucket(mv))),
+            )
+        });
+        self.pending.extend(moves.into_iter().map(|(mv, _)| mv));
+        true
+    }
+
+    fn next(
+        &mut self,
+        logic: &G,
+        state: &mut G::State,
+        persistent: &AlphaBetaPersistent<G>,
+    ) -> Option<G::Move> {
+        if let Some(mv) = self.probable_best_move.take() {
+            // The entry may come from a position with the same ident but different move history, in which case its move could be stale
+            while !self.pending.contains(&mv) && self.generate_stage(logic, state, persistent) {}
+            if let Some(idx) = self.pending.iter().position(|other| *other == mv) {
+                return self.pending.remove(idx);
+            }
+        }
+        loop {
+            while self.pending.is_empty() {
+                if !self.generate_stage(logic, state, persistent) {
+                    return None;
+                }
+            }
+            let mv = self.pending.pop_front().unwrap();
+            if !self.excluded.contains(&mv) {
+                return Some(mv);
+            }
+        }
+    }
+}
+
+// The best score the side to move could plausibly reach in one move from `score`, if the game gives a bound
+fn optimistic_score<G: GameLogic>(
+    logic: &G,
+    score: &RelScore<G::HeuristicScore>,
+) -> Option<WithNegInf<RelScore<G::HeuristicScore>>> {
+    match (score, logic.max_plausible_gain()) {
+        (RelScore::Heuristic(value), Some(gain)) => Some(WithNegInf::Finite(RelScore::Heuristic(
+            value.clone() + gain,
+        ))),
+        _ => None,
+    }
+}
+
+// The quality to search a child with, a ply deeper if the move into it gave check. Quiescence is left alone so that it stays short.
+fn check_extended<G: GameLogic>(
+    logic: &G,
+    features: SearchFeatures,
+    child: &G::State,
+    quiescence: bool,
+    quality: ScoreQuality,
+) -> ScoreQuality {
+    if features.check_extensions && !quiescence && logic.in_check(child) {
+        quality.extended().unwrap_or(quality)
+    } else {
+        quality
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -381,20 +838,40 @@ fn negamax_alphabeta_score<S: StopCondition, G: GameLogic + Send>(
     stop: S,
     thread_num: usize,
     logic: &G,
+    evaluator: &dyn Evaluator<G>,
+    features: SearchFeatures,
     state: &mut G::State,
-    persistent: Arc<Mutex<AlphaBetaPersistent<G>>>,
+    persistent: Arc<AlphaBetaPersistent<G>>,
     score_quality: ScoreQuality,
     depth_from_root: usize,
-    node_count: &mut usize,
+    counters: &mut SearchCounters,
+    path: &mut Vec<u64>,
     mut alpha: WithNegInf<RelScore<G::HeuristicScore>>,
     beta: WithPosInf<RelScore<G::HeuristicScore>>,
+    excluded_root_moves: &[G::Move],
 ) -> Result<(RelScore<G::HeuristicScore>, Option<G::Move>), ()> {
-    if stop.stop() {
+    if stop.stop(counters.nodes) {
         return Err(());
     }
-    *node_count += 1;
+    counters.nodes += 1;
     let player = logic.turn(state);
 
+    // A position played earlier in the game or on the way here is scored as a draw, since neither side gets anywhere by going round in a circle
+    let position_hash = state.clone().position_ident().hash64();
+    if depth_from_root >= 1 && path.contains(&position_hash) {
+        return Ok((RelScore::Terminal(RelTerminal::Draw, 0), None));
+    }
+
+    // Mate-distance pruning: no score here beats winning at once or is worse than losing at once, so a window outside of those is already decided
+    if depth_from_root >= 1 {
+        if alpha >= WithNegInf::Finite(RelScore::quickest_win()) {
+            return Ok((alpha.unwrap_finite(), None));
+        }
+        if beta <= WithPosInf::Finite(RelScore::quickest_loss()) {
+            return Ok((beta.unwrap_finite(), None));
+        }
+    }
+
     let orig_alpha = alpha.clone();
 
     // Transposition Table lookup
@@ -402,14 +879,17 @@ fn negamax_alphabeta_score<S: StopCondition, G: GameLogic + Send>(
     The condition `depth_from_root >= 2` is added so that the search is not blind (via transposition table entries) to stumbling into a draw when in a winning position.
     The problem is explained here https://talkchess.com/viewtopic.php?t=20080
      */
-    let probable_best_move = if depth_from_root >= 2
-        && let Some(Some(tt_entry)) = persistent
-            .lock()
-            .unwrap()
+    let tt_entry = if depth_from_root >= 2 {
+        counters.tt_probes += 1;
+        persistent
             .transpositions
             .maybe_get(state.clone().ident())
-        && tt_entry.score_quality >= score_quality
-    {
+            .filter(|tt_entry| tt_entry.score_quality >= score_quality)
+    } else {
+        None
+    };
+    let probable_best_move = if let Some(tt_entry) = tt_entry {
+        counters.tt_hits += 1;
         match tt_entry.flag {
             TranspositionTableEntryFlag::Exact => {
                 return Ok((tt_entry.score.clone(), tt_entry.best_move.clone()));
@@ -426,13 +906,32 @@ fn negamax_alphabeta_score<S: StopCondition, G: GameLogic + Send>(
             }
         }
         tt_entry.best_move.clone()
+    } else if depth_from_root == 0 {
+        persistent.learning.lock().unwrap().best_move(logic, state)
     } else {
         None
     };
 
     // Alpha-Beta search
-    let (moves, mut best_score) = if score_quality.depth == 0 {
-        let stand_pat = logic.score(state).into_rel(player);
+    // Futility pruning: with one move to go, if no plausible move reaches alpha then only the noisy moves are worth a look
+    let score_quality = if features.futility_pruning
+        && score_quality.depth == 1
+        && depth_from_root >= 1
+        && !logic.in_check(state)
+        && optimistic_score(logic, &evaluator.evaluate(logic, state).into_rel(player))
+            .is_some_and(|best| best <= alpha)
+    {
+        score_quality.reduced()
+    } else {
+        score_quality
+    };
+
+    let quiescence = score_quality.depth == 0;
+    if quiescence {
+        counters.quiescence_nodes += 1;
+    }
+    let mut best_score = if quiescence {
+        let stand_pat = evaluator.evaluate(logic, state).into_rel(player);
         let stand_pat_with_neg_inf = WithNegInf::Finite(stand_pat.clone());
         if alpha < stand_pat_with_neg_inf {
             alpha = stand_pat_with_neg_inf.clone();
@@ -443,75 +942,103 @@ fn negamax_alphabeta_score<S: StopCondition, G: GameLogic + Send>(
         if score_quality.quiescence_depth == 0 {
             return Ok((stand_pat, None));
         }
-        (
-            logic.generate_quiescence_moves(state),
-            stand_pat_with_neg_inf,
-        )
+        // Delta pruning: if even the largest plausible gain falls short of alpha then skip generating moves
+        if features.delta_pruning
+            && optimistic_score(logic, &stand_pat).is_some_and(|best| best < alpha)
+        {
+            return Ok((stand_pat, None));
+        }
+        stand_pat_with_neg_inf
     } else {
-        (logic.generate_moves(state), WithNegInf::NegInf)
+        WithNegInf::NegInf
     };
 
-    if moves.is_empty() {
-        return Ok((logic.score(state).into_rel(player), None));
-    }
-
-    let ordered_moves = if let Some(probable_best_move) = probable_best_move {
-        vec![probable_best_move.clone()]
-            .into_iter()
-            .chain({
-                let mut moves = moves
-                    .into_iter()
-                    .filter(|mv| mv != &probable_best_move)
-                    .collect::<Vec<_>>();
-
-                // Shuffle so different threads look at different things
-                fn shuffle<T>(vec: &mut [T], mut seed: usize) {
-                    fn next_u32(seed: &mut usize) -> u32 {
-                        *seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
-                        (*seed >> 16) as u32
-                    }
-                    let len = vec.len();
-                    for i in (1..len).rev() {
-                        let j = (next_u32(&mut seed) as usize) % (i + 1);
-                        vec.swap(i, j);
-                    }
-                }
-                shuffle(&mut moves, thread_num);
-
-                moves
-            })
-            .collect()
-    } else {
-        moves
+    let mut moves = StagedMoves::<G>::new(
+        quiescence,
+        features.exchange_evaluation,
+        thread_num,
+        probable_best_move,
+        excluded_root_moves,
+    );
+    let Some(first_move) = moves.next(logic, state, &persistent) else {
+        return Ok((evaluator.evaluate(logic, state).into_rel(player), None));
     };
 
-    if depth_from_root == 2 {
+    counters.expanded_nodes += 1;
+    // The path takes over from the state's own tracking of repetitions, which is slower and would make scores depend on the game's history
+    if depth_from_root == 0 {
         state.set_ignore_repetitions(true);
     }
+    path.push(position_hash);
     let mut best_move_idx = None;
+    let mut ordered_moves = vec![];
     'SEARCH: {
-        let n = ordered_moves.len();
         let mut ordered_scores = vec![];
-        for (idx, mv) in ordered_moves.iter().enumerate() {
+        let mut next_move = Some(first_move);
+        while let Some(mv) = next_move {
+            let idx = ordered_moves.len();
+            // Late move reductions: search late quiet moves less deeply, as the ordering says they are unlikely to be best
+            let reduce = features.late_move_reductions
+                && !quiescence
+                && idx >= LMR_AFTER_MOVES
+                && score_quality.depth >= LMR_MIN_DEPTH
+                && logic.is_quiet_move(&mv)
+                && !logic.gives_check(state, &mv);
             #[cfg(debug_assertions)]
             let state_before = (*state).clone();
-            logic.make_move(state, mv);
-            debug_assert_ne!(logic.turn(state), player);
-            let (score, _) = negamax_alphabeta_score::<S, G>(
-                stop.clone(),
-                thread_num,
+            logic.make_move(state, &mv);
+            // Usually the turn passes to the opponent but some games let a player move again
+            let same_player = logic.turn(state) == player;
+            let (child_alpha, child_beta) = if same_player {
+                (
+                    alpha.clone().map(|v| v.dec_time()),
+                    beta.clone().map(|v| v.dec_time()),
+                )
+            } else {
+                (
+                    -beta.clone().map(|v| v.dec_time()),
+                    -alpha.clone().map(|v| v.dec_time()),
+                )
+            };
+            let full_quality = check_extended(
                 logic,
+                features,
                 state,
-                persistent.clone(),
+                quiescence,
                 score_quality.decrement().unwrap(),
-                depth_from_root + 1,
-                node_count,
-                -beta.clone().map(|v| v.dec_time()),
-                -alpha.clone().map(|v| v.dec_time()),
-            )?;
-            let score = -score;
-            let score = score.inc_time();
-            logic.unmake_move(state, mv);
+            );
+            let mut child_quality = if reduce {
+                full_quality.reduced()
+            } else {
+                full_quality
+            };
+            let score = loop {
+                let (score, _) = negamax_alphabeta_score::<S, G>(
+                    stop.clone(),
+                    thread_num,
+                    logic,
+                    evaluator,
+                    features,
+                    state,
+                    persistent.clone(),
+                    child_quality,
+                    depth_from_root + 1,
+                    counters,
+                    path,
+                    child_alpha.clone(),
+                    child_beta.clone(),
+                    &[],
+                )?;
+                let score = if same_player { score } else { -score };
+                let score = score.inc_time();
+                // A reduced search which beats alpha is repeated at full depth
+                if child_quality != full_quality && WithNegInf::Finite(score.clone()) > alpha {
+                    child_quality = full_quality;
+                } else {
+                    break score;
+                }
+            };
+            logic.unmake_move(state, &mv);
             #[cfg(debug_assertions)]
             assert_eq!(*state, state_before);
 
@@ -523,13 +1050,26 @@ fn negamax_alphabeta_score<S: StopCondition, G: GameLogic + Send>(
             if alpha < score {
                 alpha = score.clone();
             }
+            let cutoff = alpha >= beta;
+            if cutoff
+                && !quiescence
+                && logic.move_ordering_hint(&mv) == 0
+                && let Some(bucket) = logic.move_bucket(&mv)
+            {
+                persistent
+                    .history(thread_num)
+                    .reward(bucket, score_quality.depth);
+            }
+            ordered_moves.push(mv);
             ordered_scores.push(score);
-            if alpha >= beta {
+            if cutoff {
+                counters.cutoffs += 1;
                 break 'SEARCH;
             }
+            next_move = moves.next(logic, state, &persistent);
         }
 
-        debug_assert_eq!(n, ordered_moves.len());
+        let n = ordered_moves.len();
         debug_assert_eq!(n, ordered_scores.len());
 
         // PV extensions
@@ -550,20 +1090,35 @@ fn negamax_alphabeta_score<S: StopCondition, G: GameLogic + Send>(
                 }
 
                 logic.make_move(state, &ordered_moves[best_move_idx]);
-                debug_assert_ne!(logic.turn(state), player);
+                let same_player = logic.turn(state) == player;
+                let (child_alpha, child_beta) = if same_player {
+                    (
+                        alpha.clone().map(|v| v.dec_time()),
+                        beta.clone().map(|v| v.dec_time()),
+                    )
+                } else {
+                    (
+                        -beta.clone().map(|v| v.dec_time()),
+                        -alpha.clone().map(|v| v.dec_time()),
+                    )
+                };
                 let (score, _) = negamax_alphabeta_score::<S, G>(
                     stop.clone(),
                     thread_num,
                     logic,
+                    evaluator,
+                    features,
                     state,
                     persistent.clone(),
-                    score_quality,
+                    check_extended(logic, features, state, quiescence, score_quality),
                     depth_from_root + 1,
-                    node_count,
-                    -beta.clone().map(|v| v.dec_time()),
-                    -alpha.clone().map(|v| v.dec_time()),
+                    counters,
+                    path,
+                    child_alpha,
+                    child_beta,
+                    &[],
                 )?;
-                let score = -score;
+                let score = if same_player { score } else { -score };
                 let score = score.inc_time();
                 let score = WithNegInf::Finite(score);
                 logic.unmake_move(state, &ordered_moves[best_move_idx]);
@@ -594,21 +1149,21 @@ fn negamax_alphabeta_score<S: StopCondition, G: GameLogic + Send>(
     }
     let best_move = best_move_idx.map(|idx| ordered_moves[idx].clone());
 
-    if depth_from_root == 2 {
+    path.pop();
+    if depth_from_root == 0 {
         state.set_ignore_repetitions(false);
     }
 
     // Transposition Table store
+    // A search which left out some root moves may have missed the best one, so it's not stored
 
-    let mut persistent = persistent.lock().unwrap();
+    if !excluded_root_moves.is_empty() {
+        return Ok((best_score.unwrap_finite(), best_move));
+    }
 
-    let tt_entry_opt = persistent.transpositions.get(state.clone().ident());
-    if tt_entry_opt
-        .as_ref()
-        .map(|tt_entry| tt_entry.score_quality < score_quality)
-        .unwrap_or(true)
-    {
-        *tt_entry_opt = Some(TranspositionTableEntry {
+    persistent.transpositions.store(
+        state.clone().ident(),
+        TranspositionTableEntry {
             score_quality,
             score: best_score.clone().unwrap_finite(),
             best_move: best_move.clone(),
@@ -621,76 +1176,287 @@ fn negamax_alphabeta_score<S: StopCondition, G: GameLogic + Send>(
                     TranspositionTableEntryFlag::Exact
                 }
             },
+        },
+    );
+
+    Ok((best_score.unwrap_finite(), best_move))
+}
+
+/*
+Follow the best moves stored in the transposition table from `state`, starting with `best_move`, to find the line the search expects.
+Stops at a missing or illegal entry, a repeated position, or after as many moves as the search looked ahead.
+ */
+fn principal_variation<G: GameLogic + Send>(
+    logic: &G,
+    state: &G::State,
+    persistent: &AlphaBetaPersistent<G>,
+    best_move: G::Move,
+    score_quality: ScoreQuality,
+) -> Vec<G::Move> {
+    let mut state = state.clone();
+    let mut seen = vec![state.clone().ident()];
+    logic.make_move(&mut state, &best_move);
+    let mut pv = vec![best_move];
+    while pv.len() < score_quality.pv_depth() {
+        let ident = state.clone().ident();
+        if seen.contains(&ident) {
+            break;
+        }
+        let mv = persistent
+            .transpositions
+            .maybe_get(ident.clone())
+            .and_then(|entry| entry.best_move);
+        // The entry may come from a position with the same ident but different move history
+        let Some(mv) = mv.filter(|mv| logic.generate_moves(&mut state).contains(mv)) else {
+            break;
+        };
+        seen.push(ident);
+        logic.make_move(&mut state, &mv);
+        pv.push(mv);
+    }
+    pv
+}
+
+// The hashes of the positions played before the game's current one, which the search's path starts with
+fn game_path<G: GameLogic>(game: &Game<G>) -> Vec<u64> {
+    game.position_history()
+        .iter()
+        .map(|ident| ident.hash64())
+        .collect()
+}
+
+// Search the root again and again, each time leaving out the moves already found, to get the best `lines` root moves
+#[allow(clippy::too_many_arguments)]
+fn multi_pv_search<S: StopCondition, G: GameLogic + Send>(
+    stop: S,
+    thread_num: usize,
+    logic: &G,
+    evaluator: &dyn Evaluator<G>,
+    features: SearchFeatures,
+    state: &mut G::State,
+    persistent: Arc<AlphaBetaPersistent<G>>,
+    score_quality: ScoreQuality,
+    counters: &mut SearchCounters,
+    history: &[u64],
+    best: SearchFindings<G>,
+    lines: usize,
+) -> Result<Vec<SearchFindings<G>>, ()> {
+    let mut candidate_lines = vec![best];
+    while candidate_lines.len() < lines {
+        let excluded = candidate_lines
+            .iter()
+            .map(|finding| finding.pv[0].clone())
+            .collect::<Vec<_>>();
+        let (score, Some(best_move)) = negamax_alphabeta_score::<S, G>(
+            stop.clone(),
+            thread_num,
+            logic,
+            evaluator,
+            features,
+            state,
+            persistent.clone(),
+            score_quality,
+            0,
+            counters,
+            &mut history.to_vec(),
+            WithNegInf::NegInf,
+            WithPosInf::PosInf,
+            &excluded,
+        )?
+        else {
+            break;
+        };
+        candidate_lines.push(SearchFindings {
+            score_quality,
+            score,
+            pv: principal_variation(logic, state, &persistent, best_move, score_quality),
         });
     }
+    Ok(candidate_lines)
+}
 
-    Ok((best_score.unwrap_finite(), best_move))
+fn score_label<T: std::fmt::Debug>(score: &RelScore<T>) -> String {
+    match (score, score.moves_to_end()) {
+        (RelScore::Heuristic(score), _) => format!("{:?}", score),
+        (RelScore::Terminal(RelTerminal::Win, _), Some(moves)) => format!("Mate in {moves}"),
+        (RelScore::Terminal(RelTerminal::Lose, _), Some(moves)) => format!("Mated in {moves}"),
+        (RelScore::Terminal(..), _) => "Draw".to_string(),
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct SearchFindings<G: GameLogic> {
     score_quality: ScoreQuality,
     score: RelScore<G::HeuristicScore>,
-    best_move: G::Move,
+    // The expected line of play, starting with the best move
+    pv: Vec<G::Move>,
 }
 
 #[derive(Debug)]
 struct AllSearchFindings<G: GameLogic> {
     all_findings: Vec<SearchFindings<G>>,
+    // The best few root moves at one score quality, each with its own score and line
+    candidate_lines: Vec<SearchFindings<G>>,
+    // The opponent's expected reply, when searching the position after it
+    pondering: Option<G::Move>,
+    statistics: Vec<IterationStats>,
+    // Decides whether a weakened AI makes a mistake, fixed for the position so that asking again gives the same move
+    seed: u64,
+}
+
+impl<G: GameLogic> SearchFindings<G> {
+    // The line with the extension and quiescence depths of the search as a note
+    fn line(&self) -> Line<G::Move> {
+        let note = format!(
+            "E={} {}/{}{}",
+            self.score_quality.pv_depth(),
+            if self.score_quality.pv_extension_counter.extend_after < usize::MAX / 2 {
+                format!("{}", self.score_quality.pv_extension_counter.extend_after)
+            } else {
+                "∞".to_string()
+            },
+            if self.score_quality.pv_extension_counter.reset_to < usize::MAX / 2 {
+                format!("{}", self.score_quality.pv_extension_counter.reset_to)
+            } else {
+                "∞".to_string()
+            },
+            if self.score_quality.quiescence_depth == MAX_QUIESCENCE_DEPTH {
+                "".to_string()
+            } else {
+                format!(" Q={}", self.score_quality.quiescence_depth())
+            },
+        );
+        Line {
+            depth: Some(self.score_quality.depth),
+            score: Some(score_label(&self.score)),
+            note: Some(note),
+            moves: self.pv.clone(),
+        }
+    }
 }
 
 impl<G: GameLogic> AllSearchFindings<G> {
     pub fn new() -> Self {
         Self {
             all_findings: vec![],
+            candidate_lines: vec![],
+            pondering: None,
+            statistics: vec![],
+            seed: mix64(chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64),
+        }
+    }
+
+    pub fn pondering(expected: G::Move) -> Self {
+        Self {
+            pondering: Some(expected),
+            ..Self::new()
         }
     }
 
-    pub fn best_moves(&self) -> Vec<(String, G::Move)> {
+    // The opponent played the expected reply, so the findings are for the position to play in
+    pub fn stop_pondering(&mut self) {
+        self.pondering = None;
+    }
+
+    pub fn best_moves(&self) -> Vec<Line<G::Move>> {
         self.all_findings
             .iter()
-            .map(|finding| {
-                (
-                    format!(
-                        "D={} E={} {}/{}{} S={}",
-                        finding.score_quality.depth,
-                        finding.score_quality.pv_depth(),
-                        if finding.score_quality.pv_extension_counter.extend_after < usize::MAX / 2
-                        {
-                            format!(
-                                "{}",
-                                finding.score_quality.pv_extension_counter.extend_after
-                            )
-                        } else {
-                            "∞".to_string()
-                        },
-                        if finding.score_quality.pv_extension_counter.reset_to < usize::MAX / 2 {
-                            format!("{}", finding.score_quality.pv_extension_counter.reset_to)
-                        } else {
-                            "∞".to_string()
-                        },
-                        if finding.score_quality.quiescence_depth == MAX_QUIESCENCE_DEPTH {
-                            "".to_string()
-                        } else {
-                            format!(" Q={}", finding.score_quality.quiescence_depth())
-                        },
-                        match &finding.score {
-                            RelScore::Heuristic(score) => format!("{:?}", score),
-                            RelScore::Terminal(terminal, time) => {
-                                match terminal {
-                                    crate::game::RelTerminal::Lose => format!("Lose({time})"),
-                                    crate::game::RelTerminal::Draw => format!("Draw({time})"),
-                                    crate::game::RelTerminal::Win => format!("Win({time})"),
-                                }
-                            }
-                        }
-                    ),
-                    finding.best_move.clone(),
-                )
+            .map(|finding| match &self.pondering {
+                None => finding.line(),
+                Some(expected) => {
+                    let line = finding.line();
+                    Line {
+                        note: Some(format!("Pondering {expected}")),
+                        moves: std::iter::once(expected.clone())
+                            .chain(line.moves)
+                            .collect(),
+                        ..line
+                    }
+                }
             })
             .collect()
     }
 
+    pub fn candidate_lines(&self) -> Vec<Line<G::Move>> {
+        if self.pondering.is_some() {
+            // The alternatives while pondering are for the wrong player
+            return vec![];
+        }
+        self.candidate_lines
+            .iter()
+            .map(|finding| finding.line())
+            .collect()
+    }
+
+    // The move to play, which at a weakened strength is sometimes one of the other candidates
+    pub fn best_move(&self, strength: &Strength) -> Option<G::Move> {
+        let candidate_lines = self.candidate_lines();
+        let choice = strength.choose(candidate_lines.len(), self.seed);
+        if choice > 0
+            && let Some(line) = candidate_lines.get(choice)
+            && let Some(mv) = line.moves.first()
+        {
+            return Some(mv.clone());
+        }
+        self.best_moves()
+            .into_iter()
+            .find_map(|line| line.moves.into_iter().next())
+    }
+
+    pub fn statistics(&self) -> Vec<IterationStats> {
+        self.statistics.clone()
+    }
+
+    // A draw is taken unless the deepest search so far expects to do better
+    pub fn accepts_draw(&self) -> bool {
+        if self.pondering.is_some() {
+            return false;
+        }
+        self.all_findings
+            .first()
+            .is_some_and(|finding| match &finding.score {
+                RelScore::Heuristic(score) => *score <= G::HeuristicScore::neutral(),
+                RelScore::Terminal(terminal, _) => *terminal != RelTerminal::Win,
+            })
+    }
+
+    // Record a completed iteration, if it's deeper than any so far
+    pub fn add_statistics(
+        &mut self,
+        depth: usize,
+        counters: &SearchCounters,
+        nodes_per_second: f64,
+    ) {
+        let previous = self.statistics.last();
+        if previous.is_some_and(|previous| previous.depth >= depth) {
+            return;
+        }
+        let branching_factor = previous
+            .filter(|previous| previous.depth + 1 == depth && previous.nodes > 0)
+            .map(|previous| counters.nodes as f64 / previous.nodes as f64);
+        self.statistics.push(IterationStats {
+            depth,
+            nodes: counters.nodes,
+            nodes_per_second,
+            tt_hit_rate: ratio(counters.tt_hits, counters.tt_probes),
+            cutoff_rate: ratio(counters.cutoffs, counters.expanded_nodes),
+            quiescence_share: ratio(counters.quiescence_nodes, counters.nodes),
+            branching_factor,
+        });
+    }
+
+    // Keep the alternatives from the deepest search so far, best first
+    pub fn update_candidate_lines(&mut self, candidate_lines: Vec<SearchFindings<G>>) {
+        let quality = |lines: &[SearchFindings<G>]| {
+            lines.first().map(|finding| SubjectiveScoreQuality {
+                q: finding.score_quality,
+            })
+        };
+        if quality(&candidate_lines) >= quality(&self.candidate_lines) {
+            self.candidate_lines = candidate_lines;
+        }
+    }
+
     pub fn update(&mut self, new_findings: SearchFindings<G>) {
         self.all_findings.push(new_findings);
         'LOOP: loop {
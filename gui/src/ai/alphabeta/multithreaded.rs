@@ -1,30 +1,138 @@
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize};
 
 use super::*;
 
-impl StopCondition for Arc<AtomicBool> {
-    fn stop(&self) -> bool {
-        self.load(std::sync::atomic::Ordering::Relaxed)
+#[derive(Debug, Clone)]
+struct SearchStop {
+    stopped: Arc<AtomicBool>,
+    // Set when the search is replaced by another, after which it mustn't send any events
+    superseded: Arc<AtomicBool>,
+    // In milliseconds since the epoch, i64::MAX for no deadline
+    deadline: Arc<AtomicI64>,
+    // The threads wait where they are after this time, in milliseconds since the epoch, until `think` is called again
+    think_until: Arc<AtomicI64>,
+    // The deepest iteration any thread has finished, with the full quiescence depth
+    completed_depth: Arc<AtomicUsize>,
+    // Abandon the iteration once another thread has finished this depth, as it would add nothing. 0 to never abandon.
+    iteration_depth: usize,
+}
+
+// How many nodes a thread searches between looking at the clock for the deadline and pausing
+const NODES_PER_CLOCK_CHECK: usize = 1024;
+
+impl SearchStop {
+    fn new() -> Self {
+        Self {
+            stopped: Arc::new(AtomicBool::new(false)),
+            superseded: Arc::new(AtomicBool::new(false)),
+            deadline: Arc::new(AtomicI64::new(i64::MAX)),
+            think_until: Arc::new(AtomicI64::new(i64::MIN)),
+            completed_depth: Arc::new(AtomicUsize::new(0)),
+            iteration_depth: 0,
+        }
+    }
+
+    // Stopped for good, as opposed to just abandoning the current iteration
+    fn ended(&self) -> bool {
+        self.stopped.load(std::sync::atomic::Ordering::Relaxed)
+            || chrono::Utc::now().timestamp_millis()
+                >= self.deadline.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn wait_while_paused(&self) {
+        while !self.ended()
+            && chrono::Utc::now().timestamp_millis()
+                >= self.think_until.load(std::sync::atomic::Ordering::Relaxed)
+        {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    fn redundant(&self, depth: usize) -> bool {
+        depth != 0
+            && self
+                .completed_depth
+                .load(std::sync::atomic::Ordering::Relaxed)
+                >= depth
+    }
+
+    fn for_iteration(&self, score_quality: ScoreQuality) -> Self {
+        Self {
+            iteration_depth: score_quality.depth,
+            ..self.clone()
+        }
     }
 }
 
+impl StopCondition for SearchStop {
+    fn stop(&self, nodes: usize) -> bool {
+        // Looking at the clock is too slow to do at every node
+        if !nodes.is_multiple_of(NODES_PER_CLOCK_CHECK) {
+            return self.stopped.load(std::sync::atomic::Ordering::Relaxed)
+                || self.redundant(self.iteration_depth);
+        }
+        self.wait_while_paused();
+        self.ended() || self.redundant(self.iteration_depth)
+    }
+}
+
+/*
+Lazy SMP: every thread runs its own iterative deepening, sharing what it finds through the transposition table.
+Helper threads skip some depths in a pattern which differs between threads, so that they spread out over the next few depths.
+Any thread still on a depth which another has finished gives up on it and moves on.
+The pattern is the one used by Stockfish.
+ */
+const SKIP_SIZE: [usize; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [usize; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+// Extra time to keep searching for after `think` returns, so the threads don't stall between calls made once a frame
+const THINK_GRACE: chrono::TimeDelta = chrono::TimeDelta::milliseconds(100);
+
+fn skip_depth(thread_num: usize, depth: usize) -> bool {
+    if thread_num == 0 {
+        return false;
+    }
+    let i = (thread_num - 1) % SKIP_SIZE.len();
+    !((depth + SKIP_PHASE[i]) / SKIP_SIZE[i]).is_multiple_of(2)
+}
+
 #[derive(Debug)]
 struct AlphaBetaSearch<G: GameLogic + Send> {
-    stop: Arc<AtomicBool>,
+    game: Game<G>,
+    stop: SearchStop,
     search_findings: Arc<Mutex<AllSearchFindings<G>>>,
-    persistent: Arc<Mutex<AlphaBetaPersistent<G>>>,
+    persistent: Arc<AlphaBetaPersistent<G>>,
+    options: SearchOptions<G>,
 }
 
 impl<G: GameLogic + Send> Drop for AlphaBetaSearch<G> {
     fn drop(&mut self) {
-        self.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        self.stop
+            .superseded
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        self.stop
+            .stopped
+            .store(true, std::sync::atomic::Ordering::Relaxed);
     }
 }
 
 impl<G: GameLogic + Send> AlphaBetaSearch<G> {
-    fn new(game: Game<G>, persistent: Arc<Mutex<AlphaBetaPersistent<G>>>) -> Self {
-        let stop = Arc::new(AtomicBool::new(false));
-        let search_findings = Arc::new(Mutex::new(AllSearchFindings::new()));
+    fn new(
+        game: Game<G>,
+        persistent: Arc<AlphaBetaPersistent<G>>,
+        options: SearchOptions<G>,
+        pondering: Option<G::Move>,
+    ) -> Self {
+        persistent.transpositions.new_search();
+        let stop = SearchStop::new();
+        let search_findings = Arc::new(Mutex::new(match pondering {
+            Some(expected) => AllSearchFindings::pondering(expected),
+            None => AllSearchFindings::new(),
+        }));
+        let total_node_count = Arc::new(AtomicUsize::new(0));
+        let start_time = chrono::Utc::now();
+        let evaluator = options.search_evaluator();
+        options.subscribers.send(SearchEvent::Started);
 
         let n = num_cpus::get();
         log::info!("Thinking on {} Threads...", n);
@@ -33,8 +141,12 @@ impl<G: GameLogic + Send> AlphaBetaSearch<G> {
             let persistent = persistent.clone();
             let search_findings = search_findings.clone();
             let logic = game.logic().clone();
-            let total_node_count = Arc::new(Mutex::<usize>::new(0));
-            let mut state = game.state().clone();
+            let total_node_count = total_node_count.clone();
+            let root_state = game.state().clone();
+            let mut state = root_state.clone();
+            let history = game_path(&game);
+            let options = options.clone();
+            let evaluator = evaluator.clone();
             std::thread::spawn(move || {
                 let pvec = match i {
                     0 => PvExtensionCounter::new(0, 1),
@@ -46,49 +158,141 @@ impl<G: GameLogic + Send> AlphaBetaSearch<G> {
                     6 => PvExtensionCounter::new(2, 3),
                     _ => PvExtensionCounter::new(usize::MAX, usize::MAX),
                 };
+                let send = |event| {
+                    if !stop.superseded.load(std::sync::atomic::Ordering::Relaxed) {
+                        options.subscribers.send(event);
+                    }
+                };
 
                 for score_quality in ScoreQuality::generate(pvec) {
-                    if stop.load(std::sync::atomic::Ordering::Relaxed) {
+                    stop.wait_while_paused();
+                    if stop.ended() {
                         break;
                     }
-                    let mut node_count = 0;
-                    if let Ok((score, best_move_at_depth)) =
-                        negamax_alphabeta_score::<Arc<AtomicBool>, _>(
+                    let full_quiescence = score_quality.quiescence_depth == MAX_QUIESCENCE_DEPTH;
+                    if full_quiescence
+                        && (skip_depth(i, score_quality.depth)
+                            || stop.redundant(score_quality.depth))
+                    {
+                        continue;
+                    }
+                    let mut counters = SearchCounters::default();
+                    let result = negamax_alphabeta_score::<SearchStop, _>(
+                        stop.for_iteration(score_quality),
+                        i,
+                        &logic,
+                        &*evaluator,
+                        options.features,
+                        &mut state,
+                        persistent.clone(),
+                        score_quality,
+                        0,
+                        &mut counters,
+                        &mut history.clone(),
+                        WithNegInf::NegInf,
+                        WithPosInf::PosInf,
+                        &[],
+                    );
+                    let Ok((score, best_move_at_depth)) = result else {
+                        // An abandoned search leaves the state part way down the tree
+                        state = root_state.clone();
+                        continue;
+                    };
+                    let node_count = counters.nodes
+                        + total_node_count
+                            .fetch_add(counters.nodes, std::sync::atomic::Ordering::Relaxed);
+                    send(SearchEvent::Nodes(node_count));
+                    if full_quiescence
+                        && stop
+                            .completed_depth
+                            .fetch_max(score_quality.depth, std::sync::atomic::Ordering::Relaxed)
+                            < score_quality.depth
+                    {
+                        let seconds = (chrono::Utc::now() - start_time).as_seconds_f64();
+                        search_findings.lock().unwrap().add_statistics(
+                            score_quality.depth,
+                            &counters,
+                            node_count as f64 / seconds.max(0.001),
+                        );
+                        send(SearchEvent::Depth(score_quality.depth));
+                    }
+                    let pv = best_move_at_depth.map(|best_move| {
+                        principal_variation(&logic, &state, &persistent, best_move, score_quality)
+                    });
+                    let best = pv.map(|pv| SearchFindings {
+                        score_quality,
+                        score,
+                        pv,
+                    });
+                    if let Some(best) = &best {
+                        let mut current_best = search_findings.lock().unwrap();
+                        let previous = current_best.best_moves();
+                        current_best.update(best.clone());
+                        let best_moves = current_best.best_moves();
+                        if best_moves != previous {
+                            send(SearchEvent::NewBestMove(best_moves));
+                        }
+                        persistent.learning.lock().unwrap().learn(
+                            &logic,
+                            &state,
+                            score_quality.depth,
+                            &best.pv[0],
+                        );
+                    }
+                    // One thread looks for the alternatives, while the others carry on deepening
+                    let mut multi_pv_counters = SearchCounters::default();
+                    if i == 0
+                        && options.lines() > 1
+                        && let Some(best) = best
+                        && let Ok(candidate_lines) = multi_pv_search(
                             stop.clone(),
                             i,
                             &logic,
+                            &*evaluator,
+                            options.features,
                             &mut state,
                             persistent.clone(),
                             score_quality,
-                            0,
-                            &mut node_count,
-                            WithNegInf::NegInf,
-                            WithPosInf::PosInf,
+                            &mut multi_pv_counters,
+                            &history,
+                            best,
+                            options.lines(),
                         )
                     {
-                        let mut current_best = search_findings.lock().unwrap();
-                        let mut total_node_count = total_node_count.lock().unwrap();
-                        *total_node_count += node_count;
-                        if let Some(best_move) = best_move_at_depth {
-                            current_best.update(SearchFindings {
-                                score_quality,
-                                score,
-                                best_move,
-                            });
-                        }
+                        search_findings
+                            .lock()
+                            .unwrap()
+                            .update_candidate_lines(candidate_lines);
                     }
+                    total_node_count.fetch_add(
+                        multi_pv_counters.nodes,
+                        std::sync::atomic::Ordering::Relaxed,
+                    );
+                    // A weakened AI stops here, once the thread looking for the alternatives has them
+                    if i == 0
+                        && full_quiescence
+                        && options.strength.reached(score_quality.depth, node_count)
+                    {
+                        stop.stopped
+                            .store(true, std::sync::atomic::Ordering::Relaxed);
+                    }
+                }
+                if i == 0 {
+                    send(SearchEvent::Finished);
                 }
             });
         }
 
         Self {
+            game,
             stop: stop.clone(),
             search_findings: search_findings.clone(),
             persistent: persistent.clone(),
+            options,
         }
     }
 
-    fn end(self) -> Arc<Mutex<AlphaBetaPersistent<G>>> {
+    fn end(self) -> Arc<AlphaBetaPersistent<G>> {
         self.persistent.clone()
     }
 }
@@ -98,40 +302,398 @@ impl<G: GameLogic + Send> AlphaBetaSearch<G> {
 pub enum AlphaBeta<G: GameLogic + Send> {
     Temp,
     Idle {
-        persistent: Arc<Mutex<AlphaBetaPersistent<G>>>,
+        persistent: Arc<AlphaBetaPersistent<G>>,
+        options: SearchOptions<G>,
     },
     Running {
         search: AlphaBetaSearch<G>,
     },
+    // Searching the position after the opponent's expected reply, to carry on with if they play it
+    Pondering {
+        search: AlphaBetaSearch<G>,
+    },
+}
+
+impl<G: GameLogic + Send> AlphaBeta<G> {
+    fn persistent(&self) -> &Arc<AlphaBetaPersistent<G>> {
+        match self {
+            AlphaBeta::Idle { persistent, .. } => persistent,
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => &search.persistent,
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
+
+    // End any search, keeping what it learned
+    fn end(&mut self) -> (Arc<AlphaBetaPersistent<G>>, SearchOptions<G>) {
+        match std::mem::replace(self, AlphaBeta::Temp) {
+            AlphaBeta::Idle {
+                persistent,
+                options,
+            } => (persistent, options),
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                let options = search.options.clone();
+                (search.end(), options)
+            }
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
 }
 
 impl<G: GameLogic + Send> Ai<G> for AlphaBeta<G> {
     fn new() -> Self {
         Self::Idle {
-            persistent: Arc::new(Mutex::new(AlphaBetaPersistent::new())),
+            persistent: Arc::new(AlphaBetaPersistent::new()),
+            options: SearchOptions::new(),
         }
     }
 
     fn set_game(&mut self, game: Game<G>) {
-        let old = std::mem::replace(self, AlphaBeta::Temp);
-        *self = match old {
-            AlphaBeta::Idle { persistent } => Self::Running {
-                search: AlphaBetaSearch::new(game, persistent),
-            },
-            AlphaBeta::Running { search } => Self::Running {
-                search: AlphaBetaSearch::new(game, search.end()),
-            },
-            AlphaBeta::Temp => unreachable!(),
+        if let AlphaBeta::Pondering { search } = self
+            && search.game.move_history() == game.move_history()
+        {
+            // The opponent played the expected move, so the search is already on the right position
+            let AlphaBeta::Pondering { search } = std::mem::replace(self, AlphaBeta::Temp) else {
+                unreachable!()
+            };
+            let best_moves = {
+                let mut search_findings = search.search_findings.lock().unwrap();
+                search_findings.stop_pondering();
+                search_findings.best_moves()
+            };
+            search
+                .options
+                .subscribers
+                .send(SearchEvent::NewBestMove(best_moves));
+            *self = Self::Running { search };
+            return;
+        }
+        let (persistent, options) = self.end();
+        *self = Self::Running {
+            search: AlphaBetaSearch::new(game, persistent, options, None),
+        };
+    }
+
+    fn ponder(&mut self, game: Game<G>) {
+        // The reply predicted by the search of the position before the last move, if that search predicted the last move too
+        let expected = match self {
+            AlphaBeta::Running { search } => {
+                let best_moves = search.search_findings.lock().unwrap().best_moves();
+                best_moves
+                    .into_iter()
+                    .next()
+                    .and_then(|line| match line.moves.as_slice() {
+                        [mv, reply, ..]
+                            if game.move_history().split_last()
+                                == Some((mv, search.game.move_history())) =>
+                        {
+                            Some(reply.clone())
+                        }
+                        _ => None,
+                    })
+            }
+            _ => None,
+        };
+        let Some(expected) = expected else {
+            self.set_game(game);
+            return;
+        };
+        let (persistent, options) = self.end();
+        let mut pondered = game;
+        pondered.make_move(expected.clone());
+        *self = Self::Pondering {
+            search: AlphaBetaSearch::new(pondered, persistent, options, Some(expected)),
         };
     }
 
-    fn think(&mut self, _max_time: chrono::TimeDelta) {}
+    // The threads search in the background, so this only lets them carry on for a while longer
+    fn think(&mut self, max_time: chrono::TimeDelta) {
+        match self {
+            AlphaBeta::Idle { .. } => {}
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                search.stop.think_until.store(
+                    (chrono::Utc::now() + max_time + THINK_GRACE).timestamp_millis(),
+                    std::sync::atomic::Ordering::Relaxed,
+                );
+            }
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
 
-    fn best_moves(&self) -> Vec<(String, G::Move)> {
+    fn stop_thinking_after(&mut self, time: chrono::TimeDelta) {
+        match self {
+            AlphaBeta::Idle { .. } => {}
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                search.stop.deadline.store(
+                    (chrono::Utc::now() + time).timestamp_millis(),
+                    std::sync::atomic::Ordering::Relaxed,
+                );
+            }
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
+
+    fn stop_thinking(&mut self) {
+        match self {
+            AlphaBeta::Idle { .. } => {}
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                search
+                    .stop
+                    .stopped
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
+
+    fn subscribe(&mut self) -> std::sync::mpsc::Receiver<SearchEvent<G>> {
+        match self {
+            AlphaBeta::Idle { options, .. } => options.subscribers.subscribe(),
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                search.options.subscribers.subscribe()
+            }
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
+
+    fn export_learning(&self) -> Option<String> {
+        Some(self.persistent().learning.lock().unwrap().export())
+    }
+
+    fn import_learning(&mut self, text: &str) -> Result<(), String> {
+        self.persistent().learning.lock().unwrap().import(text)
+    }
+
+    fn best_moves(&self) -> Vec<Line<G::Move>> {
+        match self {
+            AlphaBeta::Idle { .. } => vec![],
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                search.search_findings.lock().unwrap().best_moves()
+            }
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
+
+    fn best_move(&self) -> Option<G::Move> {
+        match self {
+            AlphaBeta::Idle { .. } => None,
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => search
+                .search_findings
+                .lock()
+                .unwrap()
+                .best_move(&search.options.strength),
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
+
+    fn statistics(&self) -> Vec<IterationStats> {
+        match self {
+            AlphaBeta::Idle { .. } => vec![],
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                search.search_findings.lock().unwrap().statistics()
+            }
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
+
+    fn accepts_draw(&self) -> bool {
+        match self {
+            AlphaBeta::Idle { .. } => false,
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                search.search_findings.lock().unwrap().accepts_draw()
+            }
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
+
+    fn set_multi_pv(&mut self, lines: usize) {
+        match self {
+            AlphaBeta::Idle { options, .. } => options.multi_pv = lines,
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                search.options.multi_pv = lines
+            }
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
+
+    fn set_strength(&mut self, strength: Strength) {
+        match self {
+            AlphaBeta::Idle { options, .. } => options.strength = strength,
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                search.options.strength = strength
+            }
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
+
+    fn set_features(&mut self, features: SearchFeatures) {
+        match self {
+            AlphaBeta::Idle { options, .. } => options.features = features,
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                search.options.features = features
+            }
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
+
+    fn set_evaluator(&mut self, evaluator: Arc<dyn Evaluator<G>>) {
+        match self {
+            AlphaBeta::Idle { options, .. } => options.evaluator = evaluator,
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                search.options.evaluator = evaluator
+            }
+            AlphaBeta::Temp => unreachable!(),
+        }
+    }
+
+    fn searched_position(&self, state: &G::State) -> Option<SearchedPosition<G>> {
+        self.persistent().searched_position(state)
+    }
+
+    fn candidate_lines(&self) -> Vec<Line<G::Move>> {
         match self {
             AlphaBeta::Idle { .. } => vec![],
-            AlphaBeta::Running { search } => search.search_findings.lock().unwrap().best_moves(),
+            AlphaBeta::Running { search } | AlphaBeta::Pondering { search } => {
+                search.search_findings.lock().unwrap().candidate_lines()
+            }
             AlphaBeta::Temp => unreachable!(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            MutexGuard,
+            atomic::Ordering,
+            mpsc::{Receiver, RecvTimeoutError},
+        },
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+    use crate::grid::connect_four::ConnectFour;
+
+    // The tests time the search, so they take turns rather than share the processors
+    static ONE_AT_A_TIME: Mutex<()> = Mutex::new(());
+
+    fn take_turn() -> MutexGuard<'static, ()> {
+        ONE_AT_A_TIME.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    // A full size transposition table takes most of the free memory
+    fn persistent() -> Arc<AlphaBetaPersistent<ConnectFour>> {
+        Arc::new(AlphaBetaPersistent::with_transpositions(16))
+    }
+
+    // Counts the nodes a search visits, to see whether it is getting anywhere
+    #[derive(Debug, Clone)]
+    struct Counted(SearchStop, Arc<AtomicUsize>);
+
+    impl StopCondition for Counted {
+        fn stop(&self, nodes: usize) -> bool {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            self.0.stop(nodes)
+        }
+    }
+
+    // Starts one iteration far too deep to finish during a test, giving the nodes visited so far and whether it returned a score once it returns
+    fn deep_iteration(stop: SearchStop) -> (Arc<AtomicUsize>, Receiver<bool>) {
+        let visited = Arc::new(AtomicUsize::new(0));
+        let stop = Counted(stop, visited.clone());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let game = Game::new(ConnectFour);
+            let options = SearchOptions::<ConnectFour>::new();
+            let score_quality = ScoreQuality::generate(PvExtensionCounter::new(0, 1))
+                .find(|score_quality| {
+                    score_quality.depth == 30
+                        && score_quality.quiescence_depth == MAX_QUIESCENCE_DEPTH
+                })
+                .unwrap();
+            let result = negamax_alphabeta_score(
+                stop,
+                0,
+                game.logic(),
+                &*options.search_evaluator(),
+                options.features,
+                &mut game.state().clone(),
+                persistent(),
+                score_quality,
+                0,
+                &mut SearchCounters::default(),
+                &mut game_path(&game),
+                WithNegInf::NegInf,
+                WithPosInf::PosInf,
+                &[],
+            );
+            let _ = sender.send(result.is_ok());
+        });
+        (visited, receiver)
+    }
+
+    fn millis_from_now(millis: i64) -> i64 {
+        (chrono::Utc::now() + chrono::TimeDelta::milliseconds(millis)).timestamp_millis()
+    }
+
+    #[test]
+    fn deadline_mid_iteration() {
+        let _turn = take_turn();
+        let stop = SearchStop::new();
+        stop.think_until.store(i64::MAX, Ordering::Relaxed);
+        stop.deadline.store(millis_from_now(200), Ordering::Relaxed);
+        let (visited, finished) = deep_iteration(stop);
+        assert_eq!(finished.recv_timeout(Duration::from_secs(2)), Ok(false));
+        assert!(visited.load(Ordering::Relaxed) > NODES_PER_CLOCK_CHECK);
+    }
+
+    #[test]
+    fn pause_mid_iteration() {
+        let _turn = take_turn();
+        let stop = SearchStop::new();
+        stop.think_until
+            .store(millis_from_now(200), Ordering::Relaxed);
+        let (visited, finished) = deep_iteration(stop.clone());
+        assert_eq!(
+            finished.recv_timeout(Duration::from_secs(1)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        let paused_at = visited.load(Ordering::Relaxed);
+        assert!(paused_at > NODES_PER_CLOCK_CHECK);
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(visited.load(Ordering::Relaxed), paused_at);
+
+        // Thinking again carries on from where it was
+        stop.think_until
+            .store(millis_from_now(1000), Ordering::Relaxed);
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(visited.load(Ordering::Relaxed) > paused_at);
+        stop.stopped.store(true, Ordering::Relaxed);
+        assert_eq!(finished.recv_timeout(Duration::from_secs(2)), Ok(false));
+    }
+
+    #[test]
+    fn stop_thinking_after_mid_iteration() {
+        let _turn = take_turn();
+        let mut ai = AlphaBeta::Idle {
+            persistent: persistent(),
+            options: SearchOptions::new(),
+        };
+        let events = ai.subscribe();
+        ai.set_game(Game::new(ConnectFour));
+        ai.think(chrono::TimeDelta::seconds(60));
+        // Long enough to be well into the deeper iterations
+        std::thread::sleep(Duration::from_secs(1));
+        let stopped_at = Instant::now();
+        ai.stop_thinking_after(chrono::TimeDelta::milliseconds(100));
+        // `think` returns at once while the threads search in the background, so wait for them to say they have finished
+        loop {
+            match events.recv_timeout(Duration::from_secs(5)) {
+                Ok(SearchEvent::Finished) => break,
+                Ok(_) => {}
+                Err(err) => panic!("the search didn't finish: {err}"),
+            }
+        }
+        assert!(stopped_at.elapsed() < Duration::from_millis(600));
+    }
+}
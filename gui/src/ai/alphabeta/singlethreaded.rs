@@ -7,12 +7,19 @@ pub struct AlphaBeta<G: GameLogic + Send> {
     score_quality_generator: ScoreQualityGenerator,
     score_quality: Option<ScoreQuality>,
     node_count: usize,
+    // How long `think` has spent on the current position
+    thinking_time: chrono::TimeDelta,
     search_findings: AllSearchFindings<G>,
-    persistent: Arc<Mutex<AlphaBetaPersistent<G>>>,
+    persistent: Arc<AlphaBetaPersistent<G>>,
+    options: SearchOptions<G>,
+    // Set by `stop_thinking_after`
+    deadline: Option<chrono::DateTime<chrono::Utc>>,
+    // Whether `SearchEvent::Finished` has been sent for the current position
+    finished: bool,
 }
 
 impl StopCondition for chrono::DateTime<chrono::Utc> {
-    fn stop(&self) -> bool {
+    fn stop(&self, _nodes: usize) -> bool {
         chrono::Utc::now() > *self
     }
 }
@@ -28,8 +35,12 @@ impl<G: GameLogic + Send> Ai<G> for AlphaBeta<G> {
             score_quality_generator,
             score_quality,
             node_count: 0,
+            thinking_time: chrono::TimeDelta::zero(),
             search_findings: AllSearchFindings::new(),
-            persistent: Arc::new(Mutex::new(AlphaBetaPersistent::new())),
+            persistent: Arc::new(AlphaBetaPersistent::new()),
+            options: SearchOptions::new(),
+            deadline: None,
+            finished: false,
         }
     }
 
@@ -37,48 +48,184 @@ impl<G: GameLogic + Send> Ai<G> for AlphaBeta<G> {
         self.score_quality_generator = ScoreQuality::generate(self.pv_extension_counter);
         self.score_quality = self.score_quality_generator.next();
         self.node_count = 0;
+        self.thinking_time = chrono::TimeDelta::zero();
         self.search_findings = AllSearchFindings::new();
+        self.deadline = None;
+        self.finished = false;
+        self.persistent.transpositions.new_search();
         self.game = Some(game);
+        self.options.subscribers.send(SearchEvent::Started);
     }
 
     fn think(&mut self, max_time: chrono::TimeDelta) {
         if let Some(game) = &self.game {
-            let stop = chrono::Utc::now() + max_time;
+            let think_start = chrono::Utc::now();
+            let mut stop = think_start + max_time;
+            if let Some(deadline) = self.deadline {
+                stop = stop.min(deadline);
+            }
             let mut state = game.state().clone();
-            while !stop.stop() {
+            let history = game_path(game);
+            let evaluator = self.options.search_evaluator();
+            while self.score_quality.is_some() && chrono::Utc::now() <= stop {
                 if let Some(score_quality) = self.score_quality {
-                    let mut node_count = 0;
+                    let mut counters = SearchCounters::default();
                     if let Ok((score, best_move_at_depth)) =
                         negamax_alphabeta_score::<chrono::DateTime<chrono::Utc>, _>(
                             stop,
                             0,
                             game.logic(),
+                            &*evaluator,
+                            self.options.features,
                             &mut state,
                             self.persistent.clone(),
                             score_quality,
                             0,
-                            &mut node_count,
+                            &mut counters,
+                            &mut history.clone(),
                             WithNegInf::NegInf,
                             WithPosInf::PosInf,
+                            &[],
                         )
                     {
                         let current_best = &mut self.search_findings;
-                        self.node_count += node_count;
+                        let subscribers = &self.options.subscribers;
+                        self.node_count += counters.nodes;
+                        subscribers.send(SearchEvent::Nodes(self.node_count));
+                        if score_quality.quiescence_depth == MAX_QUIESCENCE_DEPTH {
+                            let seconds = (self.thinking_time + (chrono::Utc::now() - think_start))
+                                .as_seconds_f64();
+                            current_best.add_statistics(
+                                score_quality.depth,
+                                &counters,
+                                self.node_count as f64 / seconds.max(0.001),
+                            );
+                            subscribers.send(SearchEvent::Depth(score_quality.depth));
+                        }
                         if let Some(best_move) = best_move_at_depth {
-                            current_best.update(SearchFindings {
+                            let best = SearchFindings {
                                 score_quality,
                                 score,
-                                best_move,
-                            });
+                                pv: principal_variation(
+                                    game.logic(),
+                                    &state,
+                                    &self.persistent,
+                                    best_move,
+                                    score_quality,
+                                ),
+                            };
+                            let previous = current_best.best_moves();
+                            current_best.update(best.clone());
+                            let best_moves = current_best.best_moves();
+                            if best_moves != previous {
+                                subscribers.send(SearchEvent::NewBestMove(best_moves));
+                            }
+                            self.persistent.learning.lock().unwrap().learn(
+                                game.logic(),
+                                game.state(),
+                                score_quality.depth,
+                                &best.pv[0],
+                            );
+                            let mut multi_pv_counters = SearchCounters::default();
+                            if self.options.lines() > 1
+                                && let Ok(candidate_lines) = multi_pv_search(
+                                    stop,
+                                    0,
+                                    game.logic(),
+                                    &*evaluator,
+                                    self.options.features,
+                                    &mut state,
+                                    self.persistent.clone(),
+                                    score_quality,
+                                    &mut multi_pv_counters,
+                                    &history,
+                                    best,
+                                    self.options.lines(),
+                                )
+                            {
+                                current_best.update_candidate_lines(candidate_lines);
+                            }
+                            self.node_count += multi_pv_counters.nodes;
                         }
                         self.score_quality = self.score_quality_generator.next();
+                        // A weakened AI stops here
+                        if score_quality.quiescence_depth == MAX_QUIESCENCE_DEPTH
+                            && self
+                                .options
+                                .strength
+                                .reached(score_quality.depth, self.node_count)
+                        {
+                            self.score_quality = None;
+                        }
                     }
                 }
             }
+            self.thinking_time += chrono::Utc::now() - think_start;
+            if !self.finished
+                && (self.score_quality.is_none()
+                    || self
+                        .deadline
+                        .is_some_and(|deadline| chrono::Utc::now() > deadline))
+            {
+                self.finished = true;
+                self.options.subscribers.send(SearchEvent::Finished);
+            }
         }
     }
 
-    fn best_moves(&self) -> Vec<(String, G::Move)> {
+    fn stop_thinking_after(&mut self, time: chrono::TimeDelta) {
+        self.deadline = Some(chrono::Utc::now() + time);
+    }
+
+    fn subscribe(&mut self) -> std::sync::mpsc::Receiver<SearchEvent<G>> {
+        self.options.subscribers.subscribe()
+    }
+
+    fn export_learning(&self) -> Option<String> {
+        Some(self.persistent.learning.lock().unwrap().export())
+    }
+
+    fn import_learning(&mut self, text: &str) -> Result<(), String> {
+        self.persistent.learning.lock().unwrap().import(text)
+    }
+
+    fn best_moves(&self) -> Vec<Line<G::Move>> {
         self.search_findings.best_moves()
     }
+
+    fn best_move(&self) -> Option<G::Move> {
+        self.search_findings.best_move(&self.options.strength)
+    }
+
+    fn statistics(&self) -> Vec<IterationStats> {
+        self.search_findings.statistics()
+    }
+
+    fn accepts_draw(&self) -> bool {
+        self.search_findings.accepts_draw()
+    }
+
+    fn set_multi_pv(&mut self, lines: usize) {
+        self.options.multi_pv = lines;
+    }
+
+    fn set_strength(&mut self, strength: Strength) {
+        self.options.strength = strength;
+    }
+
+    fn set_features(&mut self, features: SearchFeatures) {
+        self.options.features = features;
+    }
+
+    fn set_evaluator(&mut self, evaluator: Arc<dyn Evaluator<G>>) {
+        self.options.evaluator = evaluator;
+    }
+
+    fn searched_position(&self, state: &G::State) -> Option<SearchedPosition<G>> {
+        self.persistent.searched_position(state)
+    }
+
+    fn candidate_lines(&self) -> Vec<Line<G::Move>> {
+        self.search_findings.candidate_lines()
+    }
 }
@@ -0,0 +1,197 @@
+use std::sync::mpsc::Receiver;
+
+use crate::{
+    ai::{Ai, SearchEvent, strength::mix64},
+    game::{AbsScore, Game, GameLogic, Player},
+};
+
+// One side of a match
+pub struct Contestant<G: GameLogic> {
+    pub name: String,
+    pub ai: Box<dyn Ai<G>>,
+    // How long to think about each move, though a move is played as soon as the search finishes
+    pub move_time: chrono::TimeDelta,
+}
+
+// Why a game of a match ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ending {
+    // Won or drawn by the rules of the game
+    Rules,
+    // Drawn for going on too long
+    MoveLimit,
+    // Lost by the side to move, whose AI had no move to play
+    NoMove,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameResult<G: GameLogic> {
+    // The contestant who played first
+    pub first: usize,
+    // The contestant who won, or None for a draw
+    pub winner: Option<usize>,
+    pub ending: Ending,
+    pub game: Game<G>,
+}
+
+/*
+Two AIs playing a number of games against each other, taking turns to play first.
+Each start position is played twice in a row so that both AIs play each side of it, and games are drawn if they reach `max_moves` moves without ending.
+ */
+pub struct Match<G: GameLogic> {
+    contestants: [Contestant<G>; 2],
+    events: [Receiver<SearchEvent<G>>; 2],
+    starts: Vec<Game<G>>,
+    game: Game<G>,
+    games: usize,
+    pub max_moves: usize,
+    results: Vec<GameResult<G>>,
+    move_start_time: chrono::DateTime<chrono::Utc>,
+    // The search for the current move has finished, so there's no point waiting for the rest of the move time
+    search_finished: bool,
+}
+
+impl<G: GameLogic> Match<G> {
+    pub fn new(mut contestants: [Contestant<G>; 2], starts: Vec<Game<G>>, games: usize) -> Self {
+        assert!(!starts.is_empty());
+        let events = [contestants[0].ai.subscribe(), contestants[1].ai.subscribe()];
+        let mut result = Self {
+            contestants,
+            events,
+            game: starts[0].clone(),
+            starts,
+            games,
+            max_moves: 500,
+            results: vec![],
+            move_start_time: chrono::Utc::now(),
+            search_finished: false,
+        };
+        result.start_move();
+        result
+    }
+
+    pub fn contestants(&self) -> &[Contestant<G>; 2] {
+        &self.contestants
+    }
+
+    // The game being played, or the last game once the match is over
+    pub fn game(&self) -> &Game<G> {
+        match (self.is_finished(), self.results.last()) {
+            (true, Some(result)) => &result.game,
+            _ => &self.game,
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    pub fn results(&self) -> &[GameResult<G>] {
+        &self.results
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.results.len() >= self.games
+    }
+
+    // Which contestant plays `player` in the current game
+    pub fn contestant_idx(&self, player: Player) -> usize {
+        let offset = match player {
+            Player::First => 0,
+            Player::Second => 1,
+        };
+        (self.results.len() + offset) % 2
+    }
+
+    // The wins, draws and losses of `contestant`
+    pub fn score(&self, contestant: usize) -> (usize, usize, usize) {
+        let mut score = (0, 0, 0);
+        for result in &self.results {
+            match result.winner {
+                Some(winner) if winner == contestant => score.0 += 1,
+                None => score.1 += 1,
+                Some(_) => score.2 += 1,
+            }
+        }
+        score
+    }
+
+    // Have the contestant to move start thinking afresh, as after the match has been paused
+    pub fn start_move(&mut self) {
+        let idx = self.contestant_idx(self.game.turn());
+        let contestant = &mut self.contestants[idx];
+        contestant.ai.set_game(self.game.clone());
+        contestant.ai.stop_thinking_after(contestant.move_time);
+        self.move_start_time = chrono::Utc::now();
+        self.search_finished = false;
+    }
+
+    // How far through its move time the contestant to move is
+    pub fn move_progress(&self) -> f32 {
+        let idx = self.contestant_idx(self.game.turn());
+        (chrono::Utc::now() - self.move_start_time).as_seconds_f32()
+            / self.contestants[idx].move_time.as_seconds_f32().max(0.001)
+    }
+
+    // Let the contestant to move think for up to `max_time`, then play its move if its time is up
+    pub fn step(&mut self, max_time: chrono::TimeDelta) {
+        if self.is_finished() {
+            return;
+        }
+        // Dice are rolled and cards drawn by the arena, the same way each time the match is played
+        let seed = (self.results.len() as u64) << 32 | self.game.num_moves() as u64;
+        if self.game.play_chance(mix64(seed) as f64 / u64::MAX as f64) {
+            self.after_move();
+            return;
+        }
+        let idx = self.contestant_idx(self.game.turn());
+        self.contestants[idx].ai.think(max_time);
+        while let Ok(event) = self.events[idx].try_recv() {
+            match event {
+                SearchEvent::Started => self.search_finished = false,
+                SearchEvent::Finished => self.search_finished = true,
+                _ => {}
+            }
+        }
+        if !self.search_finished && self.move_progress() < 1.0 {
+            return;
+        }
+        let Some(mv) = self.contestants[idx].ai.best_move() else {
+            self.end_game(Some(1 - idx), Ending::NoMove);
+            return;
+        };
+        self.game.make_move(mv);
+        self.after_move();
+    }
+
+    fn after_move(&mut self) {
+        if self.game.is_finished() {
+            let winner = match self.game.score() {
+                AbsScore::FirstPlayerWin => Some(self.contestant_idx(Player::First)),
+                AbsScore::SecondPlayerWin => Some(self.contestant_idx(Player::Second)),
+                AbsScore::Draw | AbsScore::Heuristic(_) => None,
+            };
+            self.end_game(winner, Ending::Rules);
+        } else if self.game.num_moves() >= self.max_moves {
+            self.end_game(None, Ending::MoveLimit);
+        } else {
+            self.start_move();
+        }
+    }
+
+    fn end_game(&mut self, winner: Option<usize>, ending: Ending) {
+        // Counting from zero, the next game is numbered by how many have been played including this one
+        let next_game = self.results.len() + 1;
+        let next_start = self.starts[(next_game / 2) % self.starts.len()].clone();
+        let game = std::mem::replace(&mut self.game, next_start);
+        self.results.push(GameResult {
+            first: self.contestant_idx(Player::First),
+            winner,
+            ending,
+            game,
+        });
+        if !self.is_finished() {
+            self.start_move();
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use std::fmt::Debug;
+
+use crate::game::{AbsScore, GameLogic};
+
+/*
+A way of scoring positions for the searches, so that a learned evaluation can stand in for `GameLogic::score`.
+It must still report wins, losses and draws like `GameLogic::score` does.
+ */
+pub trait Evaluator<G: GameLogic>: Debug + Send + Sync {
+    fn evaluate(&self, logic: &G, state: &mut G::State) -> AbsScore<G::HeuristicScore>;
+}
+
+// The game's own hand written evaluation
+#[derive(Debug, Default)]
+pub struct Handwritten;
+
+impl<G: GameLogic> Evaluator<G> for Handwritten {
+    fn evaluate(&self, logic: &G, state: &mut G::State) -> AbsScore<G::HeuristicScore> {
+        logic.score(state)
+    }
+}
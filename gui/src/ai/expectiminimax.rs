@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use crate::{
+    ai::{Ai, Line, SearchEvent, Subscribers, strength::Strength},
+    game::{AbsScore, Game, GameLogic, Player, State, StateIdent},
+};
+
+// Above any heuristic score, so that a win is worth more than any advantage
+const WIN: f64 = 1e9;
+
+// Positions remembered between calls to `think` are forgotten once there are this many
+const MAX_REMEMBERED: usize = 1 << 20;
+
+const MAX_DEPTH: usize = 64;
+
+/*
+Expectiminimax: the player to move picks the best move, while at a chance event each outcome counts by its probability.
+Scores are averaged at chance events, so they are kept as numbers for the first player rather than as a game's own scores.
+Searches one more move ahead at a time, remembering the value of each position it has finished so that a search cut short by `think` returning picks up where it left off.
+Without alpha-beta pruning it looks less far ahead than `AlphaBeta`, but it is the one to use for a game with chance events.
+ */
+#[derive(Debug)]
+pub struct Expectiminimax<G: GameLogic> {
+    game: Option<Game<G>>,
+    // The depth being searched, having finished all shallower ones
+    depth: usize,
+    // The best move found by the deepest finished search, with that depth and its value for the player to move
+    best: Option<(usize, f64, G::Move)>,
+    // The value of each position searched, keyed by its hash, with how many moves ahead it was searched
+    remembered: HashMap<u64, (usize, f64)>,
+    nodes: usize,
+    strength: Strength,
+    deadline: Option<chrono::DateTime<chrono::Utc>>,
+    finished: bool,
+    subscribers: Subscribers<G>,
+}
+
+impl<G: GameLogic<HeuristicScore = i64>> Expectiminimax<G> {
+    // The value of `state` for the first player, looking `depth` moves ahead, or Err if `stop` passes first
+    fn value(
+        &mut self,
+        logic: &G,
+        state: &mut G::State,
+        depth: usize,
+        stop: chrono::DateTime<chrono::Utc>,
+    ) -> Result<f64, ()> {
+        if chrono::Utc::now() > stop {
+            return Err(());
+        }
+        let hash = state.clone().ident().hash64();
+        if let Some((remembered_depth, value)) = self.remembered.get(&hash)
+            && *remembered_depth >= depth
+        {
+            return Ok(*value);
+        }
+        self.nodes += 1;
+
+        let value = if let Some(outcomes) = logic.chance_outcomes(state) {
+            // Chance events don't count as moves ahead
+            let mut expected = 0.0;
+            for (outcome, probability) in outcomes {
+                logic.make_move(state, &outcome);
+                let value = self.value(logic, state, depth, stop);
+                logic.unmake_move(state, &outcome);
+                expected += probability * value?;
+            }
+            expected
+        } else {
+            let moves = logic.generate_moves(state);
+            if depth == 0 || moves.is_empty() {
+                match logic.score(state) {
+                    // A sooner win, with more of the search left, is better
+                    AbsScore::FirstPlayerWin => WIN + depth as f64,
+                    AbsScore::SecondPlayerWin => -WIN - depth as f64,
+                    AbsScore::Draw => 0.0,
+                    AbsScore::Heuristic(score) => score as f64,
+                }
+            } else {
+                let first = logic.turn(state) == Player::First;
+                let mut best = if first {
+                    f64::NEG_INFINITY
+                } else {
+                    f64::INFINITY
+                };
+                for mv in moves {
+                    logic.make_move(state, &mv);
+                    let value = self.value(logic, state, depth - 1, stop);
+                    logic.unmake_move(state, &mv);
+                    let value = value?;
+                    best = if first {
+                        best.max(value)
+                    } else {
+                        best.min(value)
+                    };
+                }
+                best
+            }
+        };
+
+        if self.remembered.len() >= MAX_REMEMBERED {
+            self.remembered.clear();
+        }
+        self.remembered.insert(hash, (depth, value));
+        Ok(value)
+    }
+
+    // The best move `depth` moves ahead with its value for the player to move
+    fn search_root(
+        &mut self,
+        game: &Game<G>,
+        depth: usize,
+        stop: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<(f64, G::Move)>, ()> {
+        let logic = game.logic();
+        let mut state = game.state().clone();
+        let sign = match logic.turn(&state) {
+            Player::First => 1.0,
+            Player::Second => -1.0,
+        };
+        let mut best: Option<(f64, G::Move)> = None;
+        for mv in logic.generate_moves(&mut state) {
+            logic.make_move(&mut state, &mv);
+            let value = self.value(logic, &mut state, depth - 1, stop);
+            logic.unmake_move(&mut state, &mv);
+            let value = sign * value?;
+            if best
+                .as_ref()
+                .is_none_or(|(best_value, _)| value > *best_value)
+            {
+                best = Some((value, mv));
+            }
+        }
+        Ok(best)
+    }
+
+    fn finish(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            self.subscribers.send(SearchEvent::Finished);
+        }
+    }
+}
+
+impl<G: GameLogic<HeuristicScore = i64>> Ai<G> for Expectiminimax<G> {
+    fn new() -> Self {
+        Self {
+            game: None,
+            depth: 1,
+            best: None,
+            remembered: HashMap::new(),
+            nodes: 0,
+            strength: Strength::FULL,
+            deadline: None,
+            finished: false,
+            subscribers: Subscribers::default(),
+        }
+    }
+
+    fn set_game(&mut self, game: Game<G>) {
+        self.depth = 1;
+        self.best = None;
+        self.remembered.clear();
+        self.nodes = 0;
+        self.deadline = None;
+        self.finished = false;
+        self.subscribers.send(SearchEvent::Started);
+        // There's nothing to choose at a chance event
+        let nothing_to_choose =
+            game.is_finished() || game.logic().chance_outcomes(game.state()).is_some();
+        self.game = Some(game);
+        if nothing_to_choose {
+            self.finish();
+        }
+    }
+
+    fn subscribe(&mut self) -> std::sync::mpsc::Receiver<SearchEvent<G>> {
+        self.subscribers.subscribe()
+    }
+
+    fn think(&mut self, max_time: chrono::TimeDelta) {
+        let Some(game) = self.game.clone() else {
+            return;
+        };
+        let mut stop = chrono::Utc::now() + max_time;
+        if let Some(deadline) = self.deadline {
+            stop = stop.min(deadline);
+        }
+        while !self.finished && chrono::Utc::now() <= stop {
+            let Ok(best) = self.search_root(&game, self.depth, stop) else {
+                break;
+            };
+            self.best = best.map(|(value, mv)| (self.depth, value, mv));
+            self.subscribers.send(SearchEvent::Nodes(self.nodes));
+            self.subscribers.send(SearchEvent::Depth(self.depth));
+            self.subscribers
+                .send(SearchEvent::NewBestMove(self.best_moves()));
+            if self.depth >= MAX_DEPTH || self.strength.reached(self.depth, self.nodes) {
+                self.finish();
+            }
+            self.depth += 1;
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| chrono::Utc::now() > deadline)
+        {
+            self.finish();
+        }
+    }
+
+    fn stop_thinking_after(&mut self, time: chrono::TimeDelta) {
+        self.deadline = Some(chrono::Utc::now() + time);
+    }
+
+    fn best_moves(&self) -> Vec<Line<G::Move>> {
+        self.best
+            .iter()
+            .map(|(depth, value, mv)| {
+                let score = if value.abs() >= WIN {
+                    if *value > 0.0 { "Win" } else { "Lose" }.to_string()
+                } else {
+                    format!("{value:.1}")
+                };
+                Line {
+                    depth: Some(*depth),
+                    score: Some(score),
+                    ..Line::new(vec![mv.clone()])
+                }
+            })
+            .collect()
+    }
+
+    fn accepts_draw(&self) -> bool {
+        self.best
+            .as_ref()
+            .is_some_and(|(_, value, _)| *value <= 0.0)
+    }
+
+    fn set_strength(&mut self, strength: Strength) {
+        self.strength = strength;
+    }
+}
@@ -1,15 +1,195 @@
-use crate::game::{Game, GameLogic};
+use std::sync::{
+    Arc, Mutex,
+    mpsc::{Receiver, Sender},
+};
+
+use crate::{
+    ai::{alphabeta::SearchFeatures, evaluator::Evaluator, strength::Strength},
+    game::{Game, GameLogic},
+};
 
 pub mod alphabeta;
+pub mod arena;
+pub mod evaluator;
+pub mod expectiminimax;
 pub mod null;
 pub mod random;
+pub mod sprt;
+pub mod strength;
+pub mod time;
+pub mod tournament;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod uci;
+
+// Progress of an AI's thinking, sent to everyone who subscribed
+#[derive(Debug, Clone)]
+pub enum SearchEvent<G: GameLogic> {
+    // Thinking about a new position has started, so everything sent before no longer applies
+    Started,
+    // The lines which `best_moves` now returns
+    NewBestMove(Vec<Line<G::Move>>),
+    // The deepest complete search of the position so far
+    Depth(usize),
+    // How many positions have been looked at for the position so far
+    Nodes(usize),
+    // Thinking about the position has ended, and nothing more will be found until the next `set_game`
+    Finished,
+}
+
+// A line of play the AI expects, starting with a candidate move followed by the expected replies
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line<M> {
+    // How many moves ahead it was searched, for AIs which search to a depth
+    pub depth: Option<usize>,
+    // What the AI makes of the line for the player to move, such as "12" or "Mate in 3"
+    pub score: Option<String>,
+    // Anything else about how it was found, such as the reply being pondered
+    pub note: Option<String>,
+    pub moves: Vec<M>,
+}
+
+impl<M> Line<M> {
+    pub fn new(moves: Vec<M>) -> Self {
+        Self {
+            depth: None,
+            score: None,
+            note: None,
+            moves,
+        }
+    }
+}
+
+// How one iteration of a search went
+#[derive(Debug, Clone)]
+pub struct IterationStats {
+    pub depth: usize,
+    pub nodes: usize,
+    // Over the whole search so far, on all threads
+    pub nodes_per_second: f64,
+    // The share of transposition table lookups which found a good enough entry
+    pub tt_hit_rate: f64,
+    // The share of nodes with moves to search which were cut off before searching them all
+    pub cutoff_rate: f64,
+    pub quiescence_share: f64,
+    // How many times as many nodes this iteration took as the one before
+    pub branching_factor: Option<f64>,
+}
+
+// How a stored score relates to the true score of a position, for the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    // The true score is at least this good
+    Lower,
+    // The true score is at most this good
+    Upper,
+}
+
+// What a search remembers about one position, for exploring the tree it searched
+#[derive(Debug, Clone)]
+pub struct SearchedPosition<G: GameLogic> {
+    // The score for the side to move, as text
+    pub score: String,
+    pub depth: usize,
+    pub bound: Bound,
+    pub best_move: Option<G::Move>,
+}
+
+// Where an AI sends its events
+#[derive(Debug)]
+pub struct Subscribers<G: GameLogic> {
+    senders: Mutex<Vec<Sender<SearchEvent<G>>>>,
+}
+
+impl<G: GameLogic> Default for Subscribers<G> {
+    fn default() -> Self {
+        Self {
+            senders: Mutex::new(vec![]),
+        }
+    }
+}
+
+impl<G: GameLogic> Subscribers<G> {
+    pub fn subscribe(&self) -> Receiver<SearchEvent<G>> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.senders.lock().unwrap().push(sender);
+        receiver
+    }
+
+    // Forgets anyone who has dropped their receiver
+    pub fn send(&self, event: SearchEvent<G>) {
+        self.senders
+            .lock()
+            .unwrap()
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
+}
 
 pub trait Ai<G: GameLogic> {
-    fn new() -> Self;
+    fn new() -> Self
+    where
+        Self: Sized;
     fn set_game(&mut self, game: Game<G>);
+    // Receive the AI's progress as it thinks, rather than polling `best_moves`.
+    // AIs which don't report their progress never send anything.
+    fn subscribe(&mut self) -> Receiver<SearchEvent<G>> {
+        std::sync::mpsc::channel().1
+    }
+    // Like `set_game`, but think about the position after the expected reply while the opponent decides.
+    // If the next `set_game` is for that position the thinking carries on rather than starting over.
+    fn ponder(&mut self, game: Game<G>) {
+        self.set_game(game);
+    }
     fn think(&mut self, max_time: chrono::TimeDelta);
-    fn best_moves(&self) -> Vec<(String, G::Move)>;
-    fn best_move(&self) -> Option<(String, G::Move)> {
-        self.best_moves().into_iter().next()
+    // Stop thinking about the current position once `time` has passed, however often `think` is called
+    #[allow(unused_variables)]
+    fn stop_thinking_after(&mut self, time: chrono::TimeDelta) {}
+    // Stop thinking about the current position for good, keeping what has been found so far
+    fn stop_thinking(&mut self) {
+        self.stop_thinking_after(chrono::TimeDelta::zero());
+    }
+    // The lines of play the AI expects, best first
+    fn best_moves(&self) -> Vec<Line<G::Move>>;
+    fn best_move(&self) -> Option<G::Move> {
+        self.best_moves()
+            .into_iter()
+            .find_map(|line| line.moves.into_iter().next())
+    }
+    // Whether to take the opponent's offer of a draw rather than play on, by what the search of the current position has found so far
+    fn accepts_draw(&self) -> bool {
+        false
+    }
+    // Also look for the best few alternative moves, each with its own score and line, from the next `set_game`
+    #[allow(unused_variables)]
+    fn set_multi_pv(&mut self, lines: usize) {}
+    // Score positions with `evaluator` instead of `GameLogic::score`, from the next `set_game`
+    #[allow(unused_variables)]
+    fn set_evaluator(&mut self, evaluator: Arc<dyn Evaluator<G>>) {}
+    // Play less well, from the next `set_game`
+    #[allow(unused_variables)]
+    fn set_strength(&mut self, strength: Strength) {}
+    // Switch parts of the search on or off, from the next `set_game`
+    #[allow(unused_variables)]
+    fn set_features(&mut self, features: SearchFeatures) {}
+    // What the AI has learned from its searches as text, to save and import in a later session
+    fn export_learning(&self) -> Option<String> {
+        None
+    }
+    #[allow(unused_variables)]
+    fn import_learning(&mut self, text: &str) -> Result<(), String> {
+        Err("This AI doesn't learn".to_string())
+    }
+    // Statistics for each completed iteration of the search of the current position, shallowest first
+    fn statistics(&self) -> Vec<IterationStats> {
+        vec![]
+    }
+    // What the AI remembers of searching `state`, which need not be a position of the current game
+    #[allow(unused_variables)]
+    fn searched_position(&self, state: &G::State) -> Option<SearchedPosition<G>> {
+        None
+    }
+    // The alternatives asked for by `set_multi_pv`, best first
+    fn candidate_lines(&self) -> Vec<Line<G::Move>> {
+        vec![]
     }
 }
@@ -1,5 +1,5 @@
 use crate::{
-    ai::Ai,
+    ai::{Ai, Line},
     game::{Game, GameLogic},
 };
 use std::marker::PhantomData;
@@ -27,7 +27,7 @@ impl<G: GameLogic> Ai<G> for NullAi<G> {
 
     fn think(&mut self, _max_time: chrono::Duration) {}
 
-    fn best_moves(&self) -> Vec<(String, G::Move)> {
+    fn best_moves(&self) -> Vec<Line<G::Move>> {
         vec![]
     }
 }
@@ -1,7 +1,7 @@
 use std::cell::RefCell;
 
 use crate::{
-    ai::Ai,
+    ai::{Ai, Line, SearchEvent, Subscribers},
     game::{Game, GameLogic},
 };
 
@@ -31,6 +31,7 @@ pub struct Random<G: GameLogic> {
     game: Option<Game<G>>,
     rng: RefCell<SimpleRng>,
     best_move: Option<G::Move>,
+    subscribers: Subscribers<G>,
 }
 
 impl<G: GameLogic> Default for Random<G> {
@@ -39,6 +40,7 @@ impl<G: GameLogic> Default for Random<G> {
             game: None,
             rng: RefCell::new(SimpleRng::new(12345)), // fixed seed for determinism
             best_move: None,
+            subscribers: Subscribers::default(),
         }
     }
 }
@@ -60,17 +62,28 @@ impl<G: GameLogic> Ai<G> for Random<G> {
     fn set_game(&mut self, game: Game<G>) {
         self.best_move = random_move(&mut self.rng.borrow_mut(), &game);
         self.game = Some(game);
+        self.subscribers.send(SearchEvent::Started);
+        self.subscribers
+            .send(SearchEvent::NewBestMove(self.best_moves()));
+        self.subscribers.send(SearchEvent::Finished);
+    }
+
+    fn subscribe(&mut self) -> std::sync::mpsc::Receiver<SearchEvent<G>> {
+        self.subscribers.subscribe()
     }
 
     fn think(&mut self, _max_time: chrono::Duration) {
         // No thinking needed, random AI is instant
     }
 
-    fn best_moves(&self) -> Vec<(String, G::Move)> {
+    fn best_moves(&self) -> Vec<Line<G::Move>> {
         self.best_move
             .iter()
             .cloned()
-            .map(|mv| ("Random".to_string(), mv))
+            .map(|mv| Line {
+                note: Some("Random".to_string()),
+                ..Line::new(vec![mv])
+            })
             .collect()
     }
 }
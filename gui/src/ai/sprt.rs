@@ -0,0 +1,149 @@
+use crate::{
+    ai::{
+        arena::{Contestant, Match},
+        tournament::Record,
+    },
+    game::{Game, GameLogic},
+};
+
+// What a sequential probability ratio test concluded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    // The change is worth at least `elo1`
+    Accepted,
+    // The change is worth no more than `elo0`
+    Rejected,
+}
+
+/*
+A sequential probability ratio test between the hypotheses that a change is worth `elo0` and that it is worth `elo1`.
+Games are played until the evidence for one over the other is strong enough, so a clear improvement or regression is spotted after few games while a close call takes many.
+`alpha` is the chance of accepting a change worth only `elo0`, and `beta` the chance of rejecting one worth `elo1`.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl Sprt {
+    // The log likelihood ratios at which to stop, rejecting below the first and accepting above the second
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /*
+    The log likelihood ratio of the `elo1` hypothesis over the `elo0` one, given how the changed AI did.
+    This is the generalised test which treats the mean game result as normally distributed with the variance seen so far, so draws need no model of their own.
+    It is zero until the results vary, as there is nothing to go on.
+     */
+    pub fn llr(&self, record: &Record) -> f64 {
+        let (Some(score), Some(variance)) = (record.score(), record.variance()) else {
+            return 0.0;
+        };
+        if variance == 0.0 {
+            return 0.0;
+        }
+        let expected_score = |elo: f64| 1.0 / (1.0 + 10f64.powf(-elo / 400.0));
+        let (score0, score1) = (expected_score(self.elo0), expected_score(self.elo1));
+        record.games() as f64 * (score1 - score0) * (2.0 * score - score0 - score1)
+            / (2.0 * variance)
+    }
+
+    pub fn verdict(&self, record: &Record) -> Option<Verdict> {
+        let llr = self.llr(record);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Some(Verdict::Accepted)
+        } else if llr <= lower {
+            Some(Verdict::Rejected)
+        } else {
+            None
+        }
+    }
+}
+
+/*
+A match between a changed AI and the AI without the change, stopped as soon as the test reaches a verdict.
+The match may run out of games first, in which case the test is inconclusive.
+ */
+pub struct SprtTest<G: GameLogic> {
+    sprt: Sprt,
+    // The changed AI is the first contestant
+    contest: Match<G>,
+    verdict: Option<Verdict>,
+}
+
+impl<G: GameLogic> SprtTest<G> {
+    pub fn new(
+        sprt: Sprt,
+        changed: Contestant<G>,
+        baseline: Contestant<G>,
+        starts: Vec<Game<G>>,
+        max_games: usize,
+    ) -> Self {
+        Self {
+            sprt,
+            contest: Match::new([changed, baseline], starts, max_games),
+            verdict: None,
+        }
+    }
+
+    pub fn sprt(&self) -> &Sprt {
+        &self.sprt
+    }
+
+    pub fn contest(&self) -> &Match<G> {
+        &self.contest
+    }
+
+    // How the changed AI has done against the baseline
+    pub fn record(&self) -> Record {
+        let (wins, draws, losses) = self.contest.score(0);
+        Record {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    pub fn llr(&self) -> f64 {
+        self.sprt.llr(&self.record())
+    }
+
+    pub fn verdict(&self) -> Option<Verdict> {
+        self.verdict
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.verdict.is_some() || self.contest.is_finished()
+    }
+
+    // Play on for up to about `max_time`, checking for a verdict whenever a game ends
+    pub fn step(&mut self, max_time: chrono::TimeDelta) {
+        if self.is_finished() {
+            return;
+        }
+        let games = self.contest.results().len();
+        self.contest.step(max_time);
+        if self.contest.results().len() > games {
+            self.verdict = self.sprt.verdict(&self.record());
+        }
+    }
+}
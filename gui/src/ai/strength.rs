@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use crate::{
+    ai::evaluator::Evaluator,
+    game::{AbsScore, GameLogic, State, StateIdent},
+};
+
+/*
+How well an AI plays, so that beginners have a chance.
+A weaker AI doesn't look as far ahead, misjudges positions by a little noise, and sometimes plays one of its next best moves instead of the best one.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Strength {
+    pub name: &'static str,
+    // Stop thinking once a search this deep is done
+    pub max_depth: Option<usize>,
+    // Stop thinking once about this many positions have been looked at
+    pub max_nodes: Option<usize>,
+    // Up to this much is added to or taken from each heuristic score, in the game's own units
+    pub noise: i64,
+    // How often to play one of the next best moves instead of the best one
+    pub mistake_chance: f64,
+    // How many of the best moves a mistake is chosen from, including the best one
+    pub candidates: usize,
+}
+
+impl Strength {
+    pub const FULL: Strength = Strength {
+        name: "Full strength",
+        max_depth: None,
+        max_nodes: None,
+        noise: 0,
+        mistake_chance: 0.0,
+        candidates: 1,
+    };
+
+    // Weakest first. The ratings are only a rough guide, and are for chess.
+    pub const LEVELS: [Strength; 5] = [
+        Strength {
+            name: "Beginner (~800)",
+            max_depth: Some(1),
+            max_nodes: Some(2_000),
+            noise: 200,
+            mistake_chance: 0.4,
+            candidates: 4,
+        },
+        Strength {
+            name: "Novice (~1200)",
+            max_depth: Some(2),
+            max_nodes: Some(20_000),
+            noise: 100,
+            mistake_chance: 0.25,
+            candidates: 3,
+        },
+        Strength {
+            name: "Club (~1600)",
+            max_depth: Some(4),
+            max_nodes: Some(200_000),
+            noise: 40,
+            mistake_chance: 0.1,
+            candidates: 2,
+        },
+        Strength {
+            name: "Expert (~2000)",
+            max_depth: Some(6),
+            max_nodes: None,
+            noise: 10,
+            mistake_chance: 0.0,
+            candidates: 1,
+        },
+        Strength::FULL,
+    ];
+
+    // Whether a search which has got this far should stop
+    pub fn reached(&self, depth: usize, nodes: usize) -> bool {
+        self.max_depth.is_some_and(|max_depth| depth >= max_depth)
+            || self.max_nodes.is_some_and(|max_nodes| nodes >= max_nodes)
+    }
+
+    // Which of `candidates` lines to play, given a random `roll`, where 0 is the best
+    pub fn choose(&self, candidates: usize, roll: u64) -> usize {
+        let candidates = candidates.min(self.candidates);
+        if candidates <= 1 || (roll % 1000) as f64 >= self.mistake_chance * 1000.0 {
+            return 0;
+        }
+        1 + (mix64(roll) % (candidates as u64 - 1)) as usize
+    }
+}
+
+impl Default for Strength {
+    fn default() -> Self {
+        Strength::FULL
+    }
+}
+
+// Scramble the bits of `x`, as in SplitMix64
+pub fn mix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+// Another evaluator with noise added to its heuristic scores.
+// The noise depends only on the position, so a position scores the same wherever it turns up in the search.
+#[derive(Debug)]
+pub struct Noisy<G: GameLogic> {
+    pub inner: Arc<dyn Evaluator<G>>,
+    pub noise: i64,
+}
+
+impl<G: GameLogic> Evaluator<G> for Noisy<G> {
+    fn evaluate(&self, logic: &G, state: &mut G::State) -> AbsScore<G::HeuristicScore> {
+        match self.inner.evaluate(logic, state) {
+            AbsScore::Heuristic(score) => {
+                let roll = mix64(state.clone().position_ident().hash64());
+                let noise = (roll % (2 * self.noise as u64 + 1)) as i64 - self.noise;
+                AbsScore::Heuristic(score + noise.into())
+            }
+            terminal => terminal,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::TimeDelta;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    pub base: TimeDelta,
+    // Added after each move
+    pub increment: TimeDelta,
+    // The base time is added again after every this many moves, for controls like 40 moves in 90 minutes
+    pub moves: Option<usize>,
+}
+
+// One player's side of a chess clock
+#[derive(Debug, Clone)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: TimeDelta,
+    moves_played: usize,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        assert_ne!(control.moves, Some(0));
+        Self {
+            control,
+            remaining: control.base,
+            moves_played: 0,
+        }
+    }
+
+    pub fn remaining(&self) -> TimeDelta {
+        self.remaining
+    }
+
+    // Carry on from a saved game
+    pub fn set_remaining(&mut self, remaining: TimeDelta) {
+        self.remaining = remaining;
+    }
+
+    pub fn increment(&self) -> TimeDelta {
+        self.control.increment
+    }
+
+    // Moves until the base time is added again, if it ever is
+    pub fn moves_to_go(&self) -> Option<usize> {
+        self.control
+            .moves
+            .map(|moves| moves - self.moves_played % moves)
+    }
+
+    // Charge a move which took `time`
+    pub fn spend(&mut self, time: TimeDelta) {
+        self.remaining = self.remaining - time + self.control.increment;
+        self.moves_played += 1;
+        if self.moves_to_go() == self.control.moves {
+            self.remaining += self.control.base;
+        }
+    }
+}
+
+/*
+Decides how long to think about each move given what's left on the clock.
+The remaining time is shared evenly between the moves left in the time control, and most of the increment is used too.
+ */
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    // Assumed when the time control doesn't say how many moves are left
+    pub expected_moves: usize,
+    // Kept back in case making the move takes longer than planned
+    pub safety_margin: TimeDelta,
+}
+
+impl Default for TimeManager {
+    fn default() -> Self {
+        Self {
+            expected_moves: 30,
+            safety_margin: TimeDelta::milliseconds(100),
+        }
+    }
+}
+
+impl TimeManager {
+    pub fn allocate(&self, clock: &Clock) -> TimeDelta {
+        let available = (clock.remaining() - self.safety_margin).max(TimeDelta::zero());
+        let moves = clock.moves_to_go().unwrap_or(self.expected_moves).max(1);
+        (available / moves as i32 + clock.increment() * 3 / 4).min(available)
+    }
+}
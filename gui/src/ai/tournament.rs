@@ -0,0 +1,271 @@
+use crate::{
+    ai::{
+        Ai,
+        arena::{Contestant, Match},
+    },
+    game::{Game, GameLogic},
+};
+
+// An AI taking part in a tournament, made afresh for each of its matches
+pub struct Entrant<G: GameLogic> {
+    pub name: String,
+    pub new_ai: Box<dyn Fn() -> Box<dyn Ai<G>>>,
+    pub move_time: chrono::TimeDelta,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // Every entrant plays every other
+    RoundRobin,
+    // The first entrant plays each of the others, who don't play each other
+    Gauntlet,
+}
+
+// Games won, drawn and lost
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Record {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Record {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    // The share of the points available which were won, counting a draw as half a win
+    pub fn score(&self) -> Option<f64> {
+        (self.games() > 0)
+            .then(|| (self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64)
+    }
+
+    // The same games from the opponent's side
+    pub fn reversed(&self) -> Self {
+        Self {
+            wins: self.losses,
+            draws: self.draws,
+            losses: self.wins,
+        }
+    }
+
+    // The variance of the individual game results about the score
+    pub fn variance(&self) -> Option<f64> {
+        let score = self.score()?;
+        Some(
+            (self.wins as f64 * (1.0 - score).powi(2)
+                + self.draws as f64 * (0.5 - score).powi(2)
+                + self.losses as f64 * score.powi(2))
+                / self.games() as f64,
+        )
+    }
+
+    /*
+    The Elo rating difference over the opponents implied by the score, and the half-width of its 95% confidence interval.
+    The interval comes from the spread of the individual game results, assuming the games are independent.
+    A score of all wins or all losses gives an infinite difference.
+     */
+    pub fn elo_difference(&self) -> Option<(f64, f64)> {
+        let score = self.score()?;
+        let games = self.games() as f64;
+        let variance = self.variance()?;
+        let margin = 1.96 * (variance / games).sqrt();
+        let elo = |score: f64| -400.0 * (1.0 / score.clamp(0.0, 1.0) - 1.0).log10();
+        Some((
+            elo(score),
+            (elo(score + margin) - elo(score - margin)) / 2.0,
+        ))
+    }
+}
+
+// An Elo difference and its error bars as text, e.g. "+35 ± 20"
+pub fn format_elo((difference, margin): (f64, f64)) -> String {
+    let number = |x: f64| {
+        if x.is_finite() {
+            format!("{x:.0}")
+        } else {
+            "∞".to_string()
+        }
+    };
+    let sign = if difference >= 0.0 { "+" } else { "-" };
+    format!("{sign}{} ± {}", number(difference.abs()), number(margin))
+}
+
+/*
+A series of matches between AI configurations, each match playing every start position with both colours.
+Only one match is played at a time, so the AIs don't compete for the CPU.
+ */
+pub struct Tournament<G: GameLogic> {
+    entrants: Vec<Entrant<G>>,
+    starts: Vec<Game<G>>,
+    games_per_match: usize,
+    // The entrants of each match, in the order they are played
+    pairings: Vec<(usize, usize)>,
+    // How the first entrant of each finished pairing did against the second
+    records: Vec<Record>,
+    current: Option<Match<G>>,
+}
+
+impl<G: GameLogic> Tournament<G> {
+    pub fn new(
+        entrants: Vec<Entrant<G>>,
+        format: Format,
+        starts: Vec<Game<G>>,
+        games_per_match: usize,
+    ) -> Self {
+        let n = entrants.len();
+        let pairings = match format {
+            Format::RoundRobin => (0..n)
+                .flat_map(|a| (a + 1..n).map(move |b| (a, b)))
+                .collect(),
+            Format::Gauntlet => (1..n).map(|b| (0, b)).collect(),
+        };
+        Self {
+            entrants,
+            starts,
+            games_per_match,
+            pairings,
+            records: vec![],
+            current: None,
+        }
+    }
+
+    pub fn entrants(&self) -> &[Entrant<G>] {
+        &self.entrants
+    }
+
+    pub fn pairings(&self) -> &[(usize, usize)] {
+        &self.pairings
+    }
+
+    // How the first entrant of each pairing did against the second, including the games so far of the current match
+    pub fn records(&self) -> Vec<Record> {
+        let mut records = self.records.clone();
+        if let Some(contest) = &self.current {
+            let (wins, draws, losses) = contest.score(0);
+            records.push(Record {
+                wins,
+                draws,
+                losses,
+            });
+        }
+        records.resize(self.pairings.len(), Record::default());
+        records
+    }
+
+    // Each entrant's results against everyone they've played
+    pub fn standings(&self) -> Vec<Record> {
+        let mut standings = vec![Record::default(); self.entrants.len()];
+        for (&(a, b), record) in self.pairings.iter().zip(self.records()) {
+            for (entrant, record) in [(a, record), (b, record.reversed())] {
+                standings[entrant].wins += record.wins;
+                standings[entrant].draws += record.draws;
+                standings[entrant].losses += record.losses;
+            }
+        }
+        standings
+    }
+
+    // The match being played, if any
+    pub fn current_match(&self) -> Option<&Match<G>> {
+        self.current.as_ref()
+    }
+
+    // Which pairing the current or next match is
+    pub fn match_idx(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.records.len() >= self.pairings.len()
+    }
+
+    // Have the AI to move in the current match start thinking afresh, as after the tournament has been paused
+    pub fn restart_move(&mut self) {
+        if let Some(contest) = &mut self.current {
+            contest.start_move();
+        }
+    }
+
+    // Play on for up to about `max_time`, starting the next match when one ends
+    pub fn step(&mut self, max_time: chrono::TimeDelta) {
+        if self.is_finished() {
+            return;
+        }
+        let contest = self.current.get_or_insert_with(|| {
+            let (a, b) = self.pairings[self.records.len()];
+            let contestant = |entrant: &Entrant<G>| Contestant {
+                name: entrant.name.clone(),
+                ai: (entrant.new_ai)(),
+                move_time: entrant.move_time,
+            };
+            Match::new(
+                [contestant(&self.entrants[a]), contestant(&self.entrants[b])],
+                self.starts.clone(),
+                self.games_per_match,
+            )
+        });
+        contest.step(max_time);
+        if contest.is_finished() {
+            let (wins, draws, losses) = contest.score(0);
+            self.records.push(Record {
+                wins,
+                draws,
+                losses,
+            });
+            self.current = None;
+        }
+    }
+
+    // The standings and the result of each match as a plain text table
+    pub fn report(&self) -> String {
+        let width = self
+            .entrants
+            .iter()
+            .map(|entrant| entrant.name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(5);
+        let mut report = format!("{:width$}  Games   Won Drawn  Lost  Score  Elo\n", "Name");
+        let mut standings = self
+            .entrants
+            .iter()
+            .zip(self.standings())
+            .collect::<Vec<_>>();
+        standings.sort_by(|(_, a), (_, b)| {
+            b.score()
+                .unwrap_or(0.0)
+                .total_cmp(&a.score().unwrap_or(0.0))
+        });
+        for (entrant, record) in standings {
+            report.push_str(&format!(
+                "{:width$}  {:5} {:5} {:5} {:5}  {:>4}  {}\n",
+                entrant.name,
+                record.games(),
+                record.wins,
+                record.draws,
+                record.losses,
+                record
+                    .score()
+                    .map_or("-".to_string(), |score| format!("{:.0}%", score * 100.0)),
+                record.elo_difference().map_or("-".to_string(), format_elo),
+            ));
+        }
+        report.push('\n');
+        for (&(a, b), record) in self.pairings.iter().zip(self.records()) {
+            if record.games() == 0 {
+                continue;
+            }
+            report.push_str(&format!(
+                "{} v {}: +{} ={} -{}  {}\n",
+                self.entrants[a].name,
+                self.entrants[b].name,
+                record.wins,
+                record.draws,
+                record.losses,
+                record.elo_difference().map_or("-".to_string(), format_elo),
+            ));
+        }
+        report
+    }
+}
@@ -0,0 +1,304 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{Receiver, RecvTimeoutError},
+};
+
+use crate::{
+    ai::{Ai, Line, SearchEvent, Subscribers, strength::Strength},
+    game::{Game, GameLogic},
+};
+
+// Games which an external engine speaking the Universal Chess Interface can play
+pub trait UciGame: GameLogic {
+    // The arguments of the `position` command for `game`, or None if engines can't play this game
+    fn uci_position(game: &Game<Self>) -> Option<String>;
+    // Options to set before searching positions of this game
+    fn uci_options(&self) -> Vec<(&'static str, String)> {
+        vec![]
+    }
+    fn uci_move(&self, mv: &Self::Move) -> String;
+    // The legal move in `state` written as `text`
+    fn move_from_uci(&self, state: &Self::State, text: &str) -> Option<Self::Move>;
+}
+
+// The command run to start the engine, when `UCI_ENGINE` isn't set
+const DEFAULT_ENGINE: &str = "stockfish";
+
+/*
+Runs an external engine, such as Stockfish, as a subprocess and talks to it over UCI.
+The engine is named by the `UCI_ENGINE` environment variable, and found on the path if it isn't a full path.
+Positions are searched with `go infinite` until told to stop, the engine's lines being translated back into moves as they come in.
+ */
+#[derive(Debug)]
+pub struct UciEngine<G: UciGame> {
+    engine: Result<Engine, String>,
+    game: Option<Game<G>>,
+    multi_pv: usize,
+    strength: Strength,
+    // The latest line for each multi-pv index
+    lines: Vec<Line<G::Move>>,
+    depth: usize,
+    searching: bool,
+    // `stop` has been sent for the current search
+    stopping: bool,
+    stop_time: Option<chrono::DateTime<chrono::Utc>>,
+    // How many `bestmove`s of searches abandoned for a newer position are still to come
+    stale_searches: usize,
+    subscribers: Subscribers<G>,
+}
+
+#[derive(Debug)]
+struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    output: Receiver<String>,
+}
+
+impl Engine {
+    fn spawn(command: &str) -> Result<Self, String> {
+        let mut child = Command::new(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| format!("Couldn't start {command}: {error}"))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, output) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = Self {
+            child,
+            stdin,
+            output,
+        };
+        engine.send("uci");
+        Ok(engine)
+    }
+
+    // Engines queue commands, so there is no need to wait for a reply
+    fn send(&mut self, command: &str) {
+        let _ = writeln!(self.stdin, "{command}");
+        let _ = self.stdin.flush();
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// The value after `name` in the words of an `info` line
+fn info_value<'a>(words: &[&'a str], name: &str) -> Option<&'a str> {
+    words
+        .iter()
+        .position(|word| *word == name)
+        .and_then(|idx| words.get(idx + 1).copied())
+}
+
+impl<G: UciGame> UciEngine<G> {
+    fn receive(&mut self, line: &str) {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["bestmove", ..] => {
+                if self.stale_searches > 0 {
+                    self.stale_searches -= 1;
+                } else if self.searching {
+                    self.searching = false;
+                    self.subscribers.send(SearchEvent::Finished);
+                }
+            }
+            ["info", rest @ ..] if self.stale_searches == 0 && self.searching => {
+                self.receive_info(rest)
+            }
+            _ => {}
+        }
+    }
+
+    fn receive_info(&mut self, words: &[&str]) {
+        let Some(game) = &self.game else { return };
+        if let Some(nodes) = info_value(words, "nodes").and_then(|nodes| nodes.parse().ok()) {
+            self.subscribers.send(SearchEvent::Nodes(nodes));
+        }
+        let Some(pv_idx) = words.iter().position(|word| *word == "pv") else {
+            return;
+        };
+        let depth = info_value(words, "depth").and_then(|depth| depth.parse::<usize>().ok());
+        let score = match (info_value(words, "cp"), info_value(words, "mate")) {
+            (Some(cp), _) => Some(cp.to_string()),
+            (None, Some(mate)) => Some(format!("Mate({mate})")),
+            (None, None) => None,
+        };
+        // Play the line out to translate each move in the position it is made from
+        let mut state = game.state().clone();
+        let mut line = vec![];
+        for text in &words[pv_idx + 1..] {
+            let Some(mv) = game.logic().move_from_uci(&state, text) else {
+                break;
+            };
+            game.logic().make_move(&mut state, &mv);
+            line.push(mv);
+        }
+        let multi_pv = info_value(words, "multipv")
+            .and_then(|idx| idx.parse::<usize>().ok())
+            .unwrap_or(1)
+            .max(1);
+        if self.lines.len() < multi_pv {
+            self.lines.resize(multi_pv, Line::new(vec![]));
+        }
+        self.lines[multi_pv - 1] = Line {
+            depth,
+            score,
+            ..Line::new(line)
+        };
+        self.subscribers
+            .send(SearchEvent::NewBestMove(self.best_moves()));
+        if let Some(depth) = depth
+            && depth > self.depth
+        {
+            self.depth = depth;
+            self.subscribers.send(SearchEvent::Depth(depth));
+        }
+    }
+
+    fn stop(&mut self) {
+        if self.searching
+            && !self.stopping
+            && let Ok(engine) = &mut self.engine
+        {
+            engine.send("stop");
+            self.stopping = true;
+        }
+    }
+}
+
+impl<G: UciGame> Ai<G> for UciEngine<G> {
+    fn new() -> Self {
+        let command = std::env::var("UCI_ENGINE").unwrap_or(DEFAULT_ENGINE.to_string());
+        Self {
+            engine: Engine::spawn(&command),
+            game: None,
+            multi_pv: 1,
+            strength: Strength::FULL,
+            lines: vec![],
+            depth: 0,
+            searching: false,
+            stopping: false,
+            stop_time: None,
+            stale_searches: 0,
+            subscribers: Subscribers::default(),
+        }
+    }
+
+    fn set_game(&mut self, game: Game<G>) {
+        if self.searching {
+            self.stop();
+            self.stale_searches += 1;
+            self.searching = false;
+        }
+        self.lines = vec![];
+        self.depth = 0;
+        self.stopping = false;
+        self.stop_time = None;
+        self.subscribers.send(SearchEvent::Started);
+        let position = G::uci_position(&game);
+        let finished = game.is_finished();
+        self.game = Some(game);
+        let (Ok(engine), Some(position), false) = (&mut self.engine, position, finished) else {
+            self.subscribers.send(SearchEvent::Finished);
+            return;
+        };
+        let logic = self.game.as_ref().unwrap().logic();
+        for (name, value) in logic.uci_options() {
+            engine.send(&format!("setoption name {name} value {value}"));
+        }
+        engine.send(&format!("setoption name MultiPV value {}", self.multi_pv));
+        engine.send(&format!("position {position}"));
+        let mut go = "go".to_string();
+        if let Some(depth) = self.strength.max_depth {
+            go.push_str(&format!(" depth {depth}"));
+        }
+        if let Some(nodes) = self.strength.max_nodes {
+            go.push_str(&format!(" nodes {nodes}"));
+        }
+        if go == "go" {
+            go.push_str(" infinite");
+        }
+        engine.send(&go);
+        self.searching = true;
+    }
+
+    fn subscribe(&mut self) -> Receiver<SearchEvent<G>> {
+        self.subscribers.subscribe()
+    }
+
+    fn think(&mut self, max_time: chrono::TimeDelta) {
+        let end_time = chrono::Utc::now() + max_time;
+        loop {
+            if self
+                .stop_time
+                .is_some_and(|stop_time| chrono::Utc::now() >= stop_time)
+            {
+                self.stop();
+            }
+            let Ok(engine) = &self.engine else { return };
+            let wait = (end_time - chrono::Utc::now()).to_std().unwrap_or_default();
+            match engine.output.recv_timeout(wait) {
+                Ok(line) => self.receive(&line),
+                Err(RecvTimeoutError::Timeout) => return,
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.engine = Err("The engine quit".to_string());
+                    if self.searching {
+                        self.searching = false;
+                        self.subscribers.send(SearchEvent::Finished);
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    fn stop_thinking_after(&mut self, time: chrono::TimeDelta) {
+        self.stop_time = Some(chrono::Utc::now() + time);
+    }
+
+    fn stop_thinking(&mut self) {
+        self.stop();
+    }
+
+    fn best_moves(&self) -> Vec<Line<G::Move>> {
+        match &self.engine {
+            Ok(_) => self.lines.iter().take(1).cloned().collect(),
+            Err(error) => vec![Line {
+                note: Some(error.clone()),
+                ..Line::new(vec![])
+            }],
+        }
+    }
+
+    fn set_multi_pv(&mut self, lines: usize) {
+        self.multi_pv = lines.max(1);
+    }
+
+    fn set_strength(&mut self, strength: Strength) {
+        self.strength = strength;
+    }
+
+    fn candidate_lines(&self) -> Vec<Line<G::Move>> {
+        if self.multi_pv > 1 {
+            self.lines.clone()
+        } else {
+            vec![]
+        }
+    }
+}
@@ -0,0 +1,11 @@
+// Play on Lichess as a bot account, with the account's token in LICHESS_TOKEN
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    if let Err(err) = chess::grid::chess::lichess::run_bot() {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,18 @@
+// Passes messages between the two players of each online game, run as `relay [port]`
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let port = match std::env::args().nth(1) {
+        Some(port) => port.parse().unwrap_or_else(|_| {
+            eprintln!("Usage: relay [port]");
+            std::process::exit(2);
+        }),
+        None => chess::net::relay::DEFAULT_PORT,
+    };
+    if let Err(err) = chess::net::relay::run(port) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,12 @@
+// Play any of the games in the terminal, run as `terminal <game> [options]`
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Err(err) = chess::terminal::run(&args) {
+        eprintln!("{err}");
+        std::process::exit(2);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
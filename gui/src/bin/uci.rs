@@ -0,0 +1,8 @@
+// A headless chess engine speaking UCI on stdin and stdout
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    chess::grid::chess::uci::run();
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -1,4 +1,4 @@
-use crate::{demo::cube::CubeRenderer, root::AppState};
+use crate::{demo::cube::CubeRenderer, grid::theme::Appearance, root::AppState};
 mod cube;
 mod texture_to_egui;
 
@@ -22,6 +22,7 @@ impl AppState for State {
         &mut self,
         ctx: &egui::Context,
         _frame: &mut eframe::Frame,
+        _appearance: &Appearance,
     ) -> Option<Box<dyn AppState>> {
         egui::CentralPanel::default()
             .show(ctx, |ui| {
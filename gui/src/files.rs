@@ -0,0 +1,115 @@
+// Saving and opening files on native and in the browser
+
+pub fn save_text(file_name: &str, extension: &str, contents: &str) {
+    save_bytes(file_name, extension, contents.as_bytes());
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_bytes(file_name: &str, extension: &str, contents: &[u8]) {
+    if let Some(path) = rfd::FileDialog::new()
+        .set_file_name(file_name)
+        .add_filter(extension, &[extension])
+        .save_file()
+        && let Err(err) = std::fs::write(&path, contents)
+    {
+        log::error!("Failed to write {}: {}", path.display(), err);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_bytes(file_name: &str, _extension: &str, contents: &[u8]) {
+    use eframe::wasm_bindgen::{JsCast as _, JsValue};
+
+    let download = || -> Result<(), JsValue> {
+        let parts = js_sys::Array::new();
+        parts.push(&js_sys::Uint8Array::from(contents));
+        let blob = web_sys::Blob::new_with_u8_array_sequence(&parts)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| JsValue::from_str("No document"))?;
+        let anchor = document
+            .create_element("a")?
+            .dyn_into::<web_sys::HtmlAnchorElement>()?;
+        anchor.set_href(&url);
+        anchor.set_download(file_name);
+        anchor.click();
+        web_sys::Url::revoke_object_url(&url)
+    };
+    if let Err(err) = download() {
+        log::error!("Failed to download {}: {:?}", file_name, err);
+    }
+}
+
+/*
+Ask for a text file to upload, whose contents are sent once the browser has read it.
+Nothing is sent if no file is chosen.
+ */
+#[cfg(target_arch = "wasm32")]
+pub fn upload_text(extension: &str) -> std::sync::mpsc::Receiver<String> {
+    use eframe::wasm_bindgen::{JsCast as _, JsValue, closure::Closure};
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let upload = || -> Result<(), JsValue> {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| JsValue::from_str("No document"))?;
+        let input = document
+            .create_element("input")?
+            .dyn_into::<web_sys::HtmlInputElement>()?;
+        input.set_type("file");
+        input.set_accept(&format!(".{extension}"));
+        let picker = input.clone();
+        let on_change = Closure::once(move || {
+            if let Some(file) = picker.files().and_then(|files| files.get(0)) {
+                wasm_bindgen_futures::spawn_local(async move {
+                    match wasm_bindgen_futures::JsFuture::from(file.text()).await {
+                        Ok(text) => {
+                            if let Some(text) = text.as_string() {
+                                let _ = sender.send(text);
+                            }
+                        }
+                        Err(err) => log::error!("Failed to read {}: {:?}", file.name(), err),
+                    }
+                });
+            }
+        });
+        input.set_onchange(Some(on_change.as_ref().unchecked_ref()));
+        // Left for the browser to call once a file is chosen, long after this returns
+        on_change.forget();
+        input.click();
+        Ok(())
+    };
+    if let Err(err) = upload() {
+        log::error!("Failed to upload a file: {:?}", err);
+    }
+    receiver
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open_bytes(extensions: &[&str]) -> Option<Vec<u8>> {
+    let path = rfd::FileDialog::new()
+        .add_filter(extensions.join(", "), extensions)
+        .pick_file()?;
+    match std::fs::read(&path) {
+        Ok(contents) => Some(contents),
+        Err(err) => {
+            log::error!("Failed to read {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open_text(extension: &str) -> Option<String> {
+    let path = rfd::FileDialog::new()
+        .add_filter(extension, &[extension])
+        .pick_file()?;
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => Some(contents),
+        Err(err) => {
+            log::error!("Failed to read {}: {}", path.display(), err);
+            None
+        }
+    }
+}
@@ -4,7 +4,7 @@ use std::{
     ops::Neg,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum Player {
     First,
     Second,
@@ -24,7 +24,16 @@ pub trait Neutral {
 }
 
 pub trait HeuristicScore:
-    PartialEq + Eq + PartialOrd + Ord + std::ops::Neg<Output = Self> + Neutral
+    Sized
+    + PartialEq
+    + Eq
+    + PartialOrd
+    + Ord
+    + std::ops::Neg<Output = Self>
+    + std::ops::Add<Output = Self>
+    // Whole numbers in the game's own units, such as for the noise added by weaker AIs
+    + From<i64>
+    + Neutral
 {
 }
 
@@ -57,6 +66,11 @@ pub enum RelTerminal {
     Win,
 }
 
+/*
+A score from the point of view of the player to move.
+The time of a terminal score is how many plies away the end of the game is, counted from the position being scored.
+It goes up by one for each ply a score is passed back up the search, so a quicker win and a slower loss score higher.
+ */
 #[derive(Debug, Clone)]
 pub enum RelScore<T> {
     Heuristic(T),
@@ -64,6 +78,25 @@ pub enum RelScore<T> {
 }
 
 impl<T> RelScore<T> {
+    // Winning then and there, which no score from a position can beat
+    pub fn quickest_win() -> Self {
+        RelScore::Terminal(RelTerminal::Win, 0)
+    }
+
+    // Losing then and there, which no score from a position can be worse than
+    pub fn quickest_loss() -> Self {
+        RelScore::Terminal(RelTerminal::Lose, 0)
+    }
+
+    // How many moves the player to move makes before winning, or makes before losing, if the score is a win or a loss
+    pub fn moves_to_end(&self) -> Option<isize> {
+        match self {
+            RelScore::Terminal(RelTerminal::Win, time) => Some((time + 1) / 2),
+            RelScore::Terminal(RelTerminal::Lose, time) => Some(time / 2),
+            _ => None,
+        }
+    }
+
     pub fn inc_time(self) -> Self {
         match self {
             RelScore::Heuristic(score) => RelScore::Heuristic(score),
@@ -277,16 +310,40 @@ mod tests {
 
         assert!(RelScore::Heuristic(-1) < RelScore::Terminal(RelTerminal::Draw, 0));
     }
+
+    #[test]
+    fn terminal_scores() {
+        // Winning sooner and losing later is better
+        assert!(RelScore::<i32>::Terminal(RelTerminal::Win, 3) < RelScore::quickest_win());
+        assert!(RelScore::<i32>::Terminal(RelTerminal::Lose, 2) > RelScore::quickest_loss());
+        assert_eq!(
+            RelScore::<i32>::Terminal(RelTerminal::Win, 5).moves_to_end(),
+            Some(3)
+        );
+        assert_eq!(
+            RelScore::<i32>::Terminal(RelTerminal::Lose, 4).moves_to_end(),
+            Some(2)
+        );
+        assert_eq!(RelScore::Heuristic(1).moves_to_end(), None);
+    }
 }
 
 #[cfg(debug_assertions)]
 pub trait State<G: GameLogic>: Debug + Clone + Send + PartialEq + Eq {
     fn ident(self) -> G::StateIdent;
+    // The ident of the position alone, ignoring any history tracked by the state
+    fn position_ident(self) -> G::StateIdent {
+        self.ident()
+    }
     fn set_ignore_repetitions(&mut self, ignore_repetitions: bool);
 }
 #[cfg(not(debug_assertions))]
 pub trait State<G: GameLogic>: Debug + Clone + Send {
     fn ident(self) -> G::StateIdent;
+    // The ident of the position alone, ignoring any history tracked by the state
+    fn position_ident(self) -> G::StateIdent {
+        self.ident()
+    }
     fn set_ignore_repetitions(&mut self, ignore_repetitions: bool);
 }
 
@@ -295,7 +352,7 @@ pub trait StateIdent<G: GameLogic>: Debug + Clone + PartialEq + Eq + Send + NoAl
 }
 
 // A 2 player turn-based game.
-// Turn switches every move.
+// Usually the turn switches every move, but a player may move several times in a row.
 // First is winning if score is positive
 // Second is winning if score is negative
 pub trait GameLogic: Debug + Clone + 'static {
@@ -310,23 +367,200 @@ pub trait GameLogic: Debug + Clone + 'static {
 
     // The game ends when `generate_moves` returns no moves.
     fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move>;
+    /*
+    For games with luck, the outcomes of the dice roll or card draw which comes next, each with its probability, which add up to one.
+    `generate_moves` gives the same outcomes so that they are played like any other move, and AIs which don't know about chance treat them as the player to move's choice.
+    None when a player is to choose the move.
+     */
+    #[allow(unused_variables)]
+    fn chance_outcomes(&self, state: &Self::State) -> Option<Vec<(Self::Move, f64)>> {
+        None
+    }
     // A subset of self.generate_moves(..) with only very active moves
     #[allow(unused_variables)]
     fn generate_quiescence_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
         vec![]
     }
+    // `generate_moves` split into stages, such as captures then quiet moves, so that a search which cuts off early can skip the later stages.
+    // Together the stages give the same moves as `generate_moves`, each once. Returns None once `stage` is past the last stage.
+    fn generate_move_stage(
+        &self,
+        state: &mut Self::State,
+        stage: usize,
+    ) -> Option<Vec<Self::Move>> {
+        (stage == 0).then(|| self.generate_moves(state))
+    }
+    // Higher for moves the search should try sooner, such as capturing a valuable piece with a cheap one
+    #[allow(unused_variables)]
+    fn move_ordering_hint(&self, mv: &Self::Move) -> i64 {
+        0
+    }
+    // The material a capture wins once both sides have made every recapture on its square worth making, negative if it loses material. None for moves which aren't captures, or if the game can't tell.
+    #[allow(unused_variables)]
+    fn exchange_value(&self, state: &mut Self::State, mv: &Self::Move) -> Option<i64> {
+        None
+    }
+    // A bound on how much the heuristic score can improve in one move, for pruning hopeless branches. None if there's no useful bound.
+    fn max_plausible_gain(&self) -> Option<Self::HeuristicScore> {
+        None
+    }
+    // A move which captures or promotes nothing, so that searching it less deeply is unlikely to miss much
+    #[allow(unused_variables)]
+    fn is_quiet_move(&self, mv: &Self::Move) -> bool {
+        false
+    }
+    // Whether the move takes any of the opponent's pieces
+    #[allow(unused_variables)]
+    fn is_capture(&self, state: &Self::State, mv: &Self::Move) -> bool {
+        false
+    }
+    // Whether the move attacks the opponent's king, or whatever the game's equivalent is
+    #[allow(unused_variables)]
+    fn gives_check(&self, state: &mut Self::State, mv: &Self::Move) -> bool {
+        false
+    }
+    // Whether the player to move is in check, or whatever the game's equivalent is
+    #[allow(unused_variables)]
+    fn in_check(&self, state: &Self::State) -> bool {
+        false
+    }
+    // A small key for the history of which quiet moves caused cutoffs, such as the from and to squares
+    #[allow(unused_variables)]
+    fn move_bucket(&self, mv: &Self::Move) -> Option<usize> {
+        None
+    }
     fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore>;
+    // The heuristic score of a position which isn't over split into named terms which add up to it, such as material and mobility. Empty if the game doesn't say.
+    #[allow(unused_variables)]
+    fn explain_score(&self, state: &mut Self::State) -> Vec<(&'static str, Self::HeuristicScore)> {
+        vec![]
+    }
+    // Why the rules ended the game in this position, such as "Checkmate", or None if the game doesn't say or isn't over
+    #[allow(unused_variables)]
+    fn end_reason(&self, state: &mut Self::State) -> Option<&'static str> {
+        None
+    }
+    // A name for `mv` which no other move shares, for saving moves and sending them over the network. Games whose moves display alike say otherwise.
+    fn move_name(&self, mv: &Self::Move) -> String {
+        mv.to_string()
+    }
 
     fn make_move(&self, state: &mut Self::State, mv: &Self::Move);
     fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move);
+
+    // The number of move sequences of length `depth`, for checking move generation against known totals
+    fn perft(&self, state: &mut Self::State, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.generate_moves(state);
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for mv in moves {
+            self.make_move(state, &mv);
+            nodes += self.perft(state, depth - 1);
+            self.unmake_move(state, &mv);
+        }
+        nodes
+    }
+
+    // `perft` split by the first move
+    fn perft_divide(&self, state: &mut Self::State, depth: usize) -> Vec<(Self::Move, u64)> {
+        if depth == 0 {
+            return vec![];
+        }
+        self.generate_moves(state)
+            .into_iter()
+            .map(|mv| {
+                self.make_move(state, &mv);
+                let nodes = self.perft(state, depth - 1);
+                self.unmake_move(state, &mv);
+                (mv, nodes)
+            })
+            .collect()
+    }
+}
+
+// How a finished game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum GameResult {
+    // Won by the rules of the game, such as by checkmate
+    Won(Player),
+    // Drawn by the rules of the game, such as by stalemate or threefold repetition
+    Drawn,
+    // The player gave up
+    Resigned(Player),
+    // One player offered a draw and the other accepted
+    AgreedDraw,
+    // The player ran out of time
+    Flagged(Player),
+}
+
+impl GameResult {
+    // The player who won, or None for a draw
+    pub fn winner(self) -> Option<Player> {
+        match self {
+            GameResult::Won(player) => Some(player),
+            GameResult::Resigned(player) | GameResult::Flagged(player) => Some(player.flip()),
+            GameResult::Drawn | GameResult::AgreedDraw => None,
+        }
+    }
+}
+
+// Something which happened in a game, for the UI to react to such as by playing a sound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameEvent {
+    Move,
+    Capture,
+    // The move left the player to move in check
+    Check,
+    Ended(GameResult),
+}
+
+/*
+A game as written to a save file: its moves, how it ended and when it was saved.
+Moves are kept by their `GameLogic::move_name`, so that any game can be saved, and are matched against the legal moves again when loaded.
+ */
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SavedGame {
+    // The rules as `Debug` writes them, so that a save is only loaded into the game it came from
+    pub rules: String,
+    pub moves: Vec<String>,
+    // Only a result the rules can't work out again from the moves, such as a resignation
+    pub ended: Option<GameResult>,
+    pub draw_offer: Option<Player>,
+    pub clocks: Option<SavedClocks>,
+    // As RFC 3339
+    pub saved_at: String,
+}
+
+// The state of the clocks of a saved game
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SavedClocks {
+    pub minutes: u32,
+    // Seconds added after each move
+    pub increment: u32,
+    // Each player's time left in milliseconds, the first player's first
+    pub remaining: [i64; 2],
 }
 
 #[derive(Debug, Clone)]
 pub struct Game<G: GameLogic> {
     logic: G,
     state: G::State,
-    turn: Player,
     move_history: Vec<G::Move>,
+    // The ident of the position before each move in `move_history`
+    position_history: Vec<G::StateIdent>,
+    // The moves taken back by `undo_move`, the most recently taken back last, until a different move is played
+    undone: Vec<G::Move>,
+    // How the game ended if a player resigned, agreed a draw or ran out of time, rather than the rules ending it
+    ended: Option<GameResult>,
+    // The player whose offer of a draw stands until the other accepts, declines or moves
+    draw_offer: Option<Player>,
+    // What has happened since `take_events` was last called, in order
+    events: Vec<GameEvent>,
 }
 
 impl<G: GameLogic> Game<G> {
@@ -335,8 +569,12 @@ impl<G: GameLogic> Game<G> {
         Self {
             logic,
             state,
-            turn: Player::First,
             move_history: vec![],
+            position_history: vec![],
+            undone: vec![],
+            ended: None,
+            draw_offer: None,
+            events: vec![],
         }
     }
 
@@ -349,33 +587,255 @@ impl<G: GameLogic> Game<G> {
     }
 
     pub fn turn(&self) -> Player {
-        self.turn
+        self.logic.turn(&self.state)
     }
 
     pub fn is_finished(&self) -> bool {
-        self.logic
-            .generate_moves(&mut self.state.clone())
-            .is_empty()
+        self.ended.is_some() || self.is_over_by_rules()
+    }
+
+    fn is_over_by_rules(&self) -> bool {
+        self.is_threefold_repetition()
+            || self
+                .logic
+                .generate_moves(&mut self.state.clone())
+                .is_empty()
+    }
+
+    // How the game ended, or None while it goes on
+    pub fn result(&self) -> Option<GameResult> {
+        if let Some(ended) = self.ended {
+            return Some(ended);
+        }
+        if !self.is_over_by_rules() {
+            return None;
+        }
+        Some(match self.score() {
+            AbsScore::FirstPlayerWin => GameResult::Won(Player::First),
+            AbsScore::SecondPlayerWin => GameResult::Won(Player::Second),
+            AbsScore::Draw | AbsScore::Heuristic(_) => GameResult::Drawn,
+        })
+    }
+
+    // How many times the current position has occurred before
+    pub fn repetitions(&self) -> usize {
+        let ident = self.state.clone().position_ident();
+        self.position_history
+            .iter()
+            .filter(|prev| **prev == ident)
+            .count()
+    }
+
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetitions() >= 2
+    }
+
+    // The score of the current position, taking the history of the game into account
+    pub fn score(&self) -> AbsScore<G::HeuristicScore> {
+        if let Some(ended) = self.ended {
+            match ended.winner() {
+                Some(Player::First) => AbsScore::FirstPlayerWin,
+                Some(Player::Second) => AbsScore::SecondPlayerWin,
+                None => AbsScore::Draw,
+            }
+        } else if self.is_threefold_repetition() {
+            AbsScore::Draw
+        } else {
+            self.logic.score(&mut self.state.clone())
+        }
     }
 
     pub fn num_moves(&self) -> usize {
         self.move_history.len()
     }
 
+    pub fn move_history(&self) -> &[G::Move] {
+        &self.move_history
+    }
+
+    // The ident of the position before each move played
+    pub fn position_history(&self) -> &[G::StateIdent] {
+        &self.position_history
+    }
+
     pub fn make_move(&mut self, mv: G::Move) {
         debug_assert!(self.logic.generate_moves(&mut self.state).contains(&mv));
+        debug_assert!(self.ended.is_none());
+        // Moving instead of answering an offer of a draw declines it
+        if self.draw_offer != Some(self.turn()) {
+            self.draw_offer = None;
+        }
+        self.position_history
+            .push(self.state.clone().position_ident());
+        self.events
+            .push(if self.logic.is_capture(&self.state, &mv) {
+                GameEvent::Capture
+            } else {
+                GameEvent::Move
+            });
         self.logic.make_move(&mut self.state, &mv);
-        self.turn = self.turn.flip();
+        // Playing the move which was taken back keeps those taken back before it to redo
+        if self.undone.last() == Some(&mv) {
+            self.undone.pop();
+        } else {
+            self.undone.clear();
+        }
         self.move_history.push(mv);
+        if self.logic.in_check(&self.state) {
+            self.events.push(GameEvent::Check);
+        }
+        if let Some(result) = self.result() {
+            self.events.push(GameEvent::Ended(result));
+        }
+    }
+
+    // What has happened since this was last called, most recent last
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn save(&self) -> SavedGame {
+        SavedGame {
+            rules: format!("{:?}", self.logic),
+            moves: self
+                .move_history
+                .iter()
+                .map(|mv| self.logic.move_name(mv))
+                .collect(),
+            ended: self.ended,
+            draw_offer: self.draw_offer,
+            clocks: None,
+            saved_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    // Play the moves of `saved` again, failing if it's of other rules or a move isn't legal
+    pub fn load(logic: G, saved: &SavedGame) -> Result<Self, String> {
+        if format!("{logic:?}") != saved.rules {
+            return Err("The saved game is of a different game".to_string());
+        }
+        let mut game = Self::new(logic);
+        for text in &saved.moves {
+            if game.is_finished() {
+                return Err("There are moves after the end of the game".to_string());
+            }
+            let mv = game
+                .move_named(text)
+                .ok_or_else(|| format!("{text} is not a legal move"))?;
+            game.make_move(mv);
+        }
+        game.ended = saved.ended;
+        game.draw_offer = saved.draw_offer;
+        // Loading isn't something happening in the game
+        game.events.clear();
+        Ok(game)
+    }
+
+    // The legal move, or the outcome of a chance event, whose `move_name` is `text`
+    pub fn move_named(&self, text: &str) -> Option<G::Move> {
+        let moves: Vec<G::Move> = match self.logic.chance_outcomes(&self.state) {
+            Some(outcomes) => outcomes.into_iter().map(|(mv, _)| mv).collect(),
+            None => self.logic.generate_moves(&mut self.state.clone()),
+        };
+        let found = moves
+            .iter()
+            .find(|mv| self.logic.move_name(mv) == text)
+            .cloned();
+        // Games saved before moves had their own names saved them as they display
+        found.or_else(|| moves.into_iter().find(|mv| mv.to_string() == text))
+    }
+
+    fn end(&mut self, result: GameResult) {
+        self.ended = Some(result);
+        self.events.push(GameEvent::Ended(result));
     }
 
     pub fn can_undo_move(&self) -> bool {
         !self.move_history.is_empty()
     }
 
+    // At a chance event, play the outcome which `roll`, between 0 and 1, falls on. False if a player is to move instead.
+    pub fn play_chance(&mut self, roll: f64) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        let Some(outcomes) = self.logic.chance_outcomes(&self.state) else {
+            return false;
+        };
+        let mut total = 0.0;
+        let mut chosen = None;
+        for (outcome, probability) in outcomes {
+            total += probability;
+            chosen = Some(outcome);
+            if roll < total {
+                break;
+            }
+        }
+        match chosen {
+            Some(outcome) => {
+                self.make_move(outcome);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn resign(&mut self, player: Player) {
+        debug_assert!(!self.is_finished());
+        self.end(GameResult::Resigned(player));
+    }
+
+    pub fn flag(&mut self, player: Player) {
+        debug_assert!(!self.is_finished());
+        self.end(GameResult::Flagged(player));
+    }
+
+    pub fn offer_draw(&mut self, player: Player) {
+        debug_assert!(!self.is_finished());
+        self.draw_offer = Some(player);
+    }
+
+    pub fn draw_offer(&self) -> Option<Player> {
+        self.draw_offer
+    }
+
+    // The game is drawn if there's an offer to accept
+    pub fn accept_draw(&mut self) {
+        if self.draw_offer.take().is_some() {
+            self.end(GameResult::AgreedDraw);
+        }
+    }
+
+    pub fn decline_draw(&mut self) {
+        self.draw_offer = None;
+    }
+
+    // Taking back a move also takes back a resignation, agreed draw or loss on time, and any offer of a draw
     pub fn undo_move(&mut self) {
+        self.ended = None;
+        self.draw_offer = None;
         let mv = self.move_history.pop().unwrap();
+        self.position_history.pop();
         self.logic.unmake_move(&mut self.state, &mv);
-        self.turn = self.turn.flip();
+        self.undone.push(mv);
+    }
+
+    pub fn can_redo_move(&self) -> bool {
+        self.ended.is_none() && !self.undone.is_empty()
+    }
+
+    // Play again the last move taken back
+    pub fn redo_move(&mut self) {
+        let mv = self.undone.last().unwrap().clone();
+        self.make_move(mv);
+    }
+
+    // The game as it was after its first `num_moves` moves
+    pub fn rewound(&self, num_moves: usize) -> Game<G> {
+        let mut game = self.clone();
+        while game.num_moves() > num_moves {
+            game.undo_move();
+        }
+        game
     }
 }
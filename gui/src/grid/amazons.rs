@@ -0,0 +1,420 @@
+use std::fmt::Display;
+
+use egui::{Color32, Painter, Rect};
+
+use crate::{
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{GridGame, MoveSelectionAction, Piece},
+};
+
+const SIZE: usize = 10;
+const DIRECTIONS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+type Pos = (usize, usize);
+
+fn bit((row, col): Pos) -> u128 {
+    1 << (SIZE * row + col)
+}
+
+fn player_idx(player: Player) -> usize {
+    match player {
+        Player::First => 0,
+        Player::Second => 1,
+    }
+}
+
+// The squares a queen on `from` can reach without crossing an occupied square
+fn queen_moves(from: Pos, occupied: u128) -> impl Iterator<Item = Pos> {
+    DIRECTIONS.into_iter().flat_map(move |(drow, dcol)| {
+        std::iter::successors(Some(from), move |(row, col)| {
+            let r = row.checked_add_signed(drow).filter(|r| *r < SIZE)?;
+            let c = col.checked_add_signed(dcol).filter(|c| *c < SIZE)?;
+            (occupied & bit((r, c)) == 0).then_some((r, c))
+        })
+        .skip(1)
+    })
+}
+
+/*
+The Game of the Amazons on a 10x10 board.
+Each player has four amazons. A turn is moving one amazon like a chess queen and then shooting an arrow from where
+it lands, also like a queen. The square the arrow lands on is blocked for the rest of the game.
+The player who can't make a move loses. White is the first player and starts at the bottom of the board.
+ */
+#[derive(Debug, Clone)]
+pub struct Amazons;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState {
+    amazons: [u128; 2],
+    arrows: u128,
+    move_num: usize,
+}
+
+impl BoardState {
+    fn get(&self, pos: Pos) -> Option<Player> {
+        if self.amazons[0] & bit(pos) != 0 {
+            Some(Player::First)
+        } else if self.amazons[1] & bit(pos) != 0 {
+            Some(Player::Second)
+        } else {
+            None
+        }
+    }
+
+    fn occupied(&self) -> u128 {
+        self.amazons[0] | self.amazons[1] | self.arrows
+    }
+
+    fn positions(&self, player: Player) -> impl Iterator<Item = Pos> {
+        let amazons = self.amazons[player_idx(player)];
+        (0..SIZE)
+            .flat_map(|row| (0..SIZE).map(move |col| (row, col)))
+            .filter(move |pos| amazons & bit(*pos) != 0)
+    }
+
+    // How many squares `player`'s amazons can move to
+    fn mobility(&self, player: Player) -> i64 {
+        let occupied = self.occupied();
+        self.positions(player)
+            .map(|pos| queen_moves(pos, occupied).count() as i64)
+            .sum()
+    }
+}
+
+impl State<Amazons> for BoardState {
+    fn ident(self) -> BoardStateIdent {
+        BoardStateIdent {
+            amazons: self.amazons,
+            arrows: self.arrows,
+            move_num: self.move_num,
+        }
+    }
+
+    // Positions can't repeat since every move adds an arrow
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+#[derive(Debug, Clone)]
+pub struct BoardStateIdent {
+    amazons: [u128; 2],
+    arrows: u128,
+    move_num: usize,
+}
+
+impl PartialEq for BoardStateIdent {
+    fn eq(&self, other: &Self) -> bool {
+        self.amazons == other.amazons
+            && self.arrows == other.arrows
+            && self.move_num % 2 == other.move_num % 2
+    }
+}
+
+impl Eq for BoardStateIdent {}
+
+impl NoAlloc for BoardStateIdent {}
+
+impl StateIdent<Amazons> for BoardStateIdent {
+    fn hash64(&self) -> u64 {
+        let fold = |x: u128| (x as u64) ^ ((x >> 64) as u64).rotate_left(17);
+        (fold(self.amazons[0]).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ fold(self.amazons[1])
+                .wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+                .rotate_left(29)
+            ^ fold(self.arrows).wrapping_mul(0x1656_67B1_9E37_79F9))
+        .wrapping_add(self.move_num as u64 % 2)
+    }
+}
+
+// An amazon moving and then the arrow it shoots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    from: Pos,
+    to: Pos,
+    arrow: Pos,
+}
+
+fn square_name((row, col): Pos) -> String {
+    format!("{}{}", (b'a' + col as u8) as char, SIZE - row)
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}/{}",
+            square_name(self.from),
+            square_name(self.to),
+            square_name(self.arrow)
+        )
+    }
+}
+
+impl GameLogic for Amazons {
+    type State = BoardState;
+    type StateIdent = BoardStateIdent;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        let white = [(6, 0), (9, 3), (9, 6), (6, 9)];
+        let black = [(3, 0), (0, 3), (0, 6), (3, 9)];
+        BoardState {
+            amazons: [
+                white.into_iter().map(bit).fold(0, |a, b| a | b),
+                black.into_iter().map(bit).fold(0, |a, b| a | b),
+            ],
+            arrows: 0,
+            move_num: 0,
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        if state.move_num.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        let occupied = state.occupied();
+        let mut moves = vec![];
+        for from in state.positions(self.turn(state)) {
+            for to in queen_moves(from, occupied) {
+                // The arrow can be shot back through the square the amazon left
+                for arrow in queen_moves(to, (occupied ^ bit(from)) | bit(to)) {
+                    moves.push(Move { from, to, arrow });
+                }
+            }
+        }
+        moves
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        // Having nowhere to move loses, and an amazon that can move can always shoot back where it came from
+        let turn = self.turn(state);
+        if state.mobility(turn) == 0 {
+            return match turn {
+                Player::First => AbsScore::SecondPlayerWin,
+                Player::Second => AbsScore::FirstPlayerWin,
+            };
+        }
+        AbsScore::Heuristic(state.mobility(Player::First) - state.mobility(Player::Second))
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        let idx = player_idx(self.turn(state));
+        debug_assert!(state.amazons[idx] & bit(mv.from) != 0);
+        state.amazons[idx] ^= bit(mv.from) | bit(mv.to);
+        debug_assert!(state.occupied() & bit(mv.arrow) == 0);
+        state.arrows |= bit(mv.arrow);
+        state.move_num += 1;
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        state.move_num -= 1;
+        let idx = player_idx(self.turn(state));
+        state.arrows &= !bit(mv.arrow);
+        state.amazons[idx] ^= bit(mv.from) | bit(mv.to);
+    }
+}
+
+// An amazon is picked, then where it moves to, then where it shoots
+#[derive(Debug, Clone)]
+pub enum MoveSelectionState {
+    Initial,
+    Selected { from: Pos },
+    Moved { from: Pos, to: Pos },
+}
+
+impl GridGame for Amazons {
+    const ROWS: usize = SIZE;
+    const COLS: usize = SIZE;
+
+    fn piece(&self, state: &Self::State, row: usize, col: usize) -> Piece {
+        match state.get((row, col)) {
+            Some(Player::First) => Piece::WhiteQueen,
+            Some(Player::Second) => Piece::BlackQueen,
+            None => Piece::Empty,
+        }
+    }
+
+    type MoveSelectionState = MoveSelectionState;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        MoveSelectionState::Initial
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        for ((row, col), color) in [
+            (mv.from, Color32::ORANGE.gamma_multiply(0.25)),
+            (mv.to, Color32::ORANGE.gamma_multiply(0.5)),
+            (mv.arrow, Color32::RED.gamma_multiply(0.5)),
+        ] {
+            let rect = cell_to_rect(row, col).shrink(0.06 * cell_size);
+            painter.rect_filled(rect, 0.2 * cell_size, color);
+        }
+    }
+
+    fn update_move_selection(
+        &self,
+        turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        let MoveSelectionAction::ClickSquare { row, col } = action else {
+            *move_selection_state = MoveSelectionState::Initial;
+            return None;
+        };
+        let clicked = (row, col);
+        let moves = self.generate_moves(&mut state.clone());
+        match *move_selection_state {
+            MoveSelectionState::Selected { from }
+                if moves.iter().any(|mv| mv.from == from && mv.to == clicked) =>
+            {
+                *move_selection_state = MoveSelectionState::Moved { from, to: clicked };
+                return None;
+            }
+            MoveSelectionState::Moved { from, to } => {
+                if let Some(mv) = moves
+                    .into_iter()
+                    .find(|mv| mv.from == from && mv.to == to && mv.arrow == clicked)
+                {
+                    *move_selection_state = MoveSelectionState::Initial;
+                    return Some(mv);
+                }
+                // The amazon has to shoot once it has moved
+                return None;
+            }
+            _ => {}
+        }
+        *move_selection_state = if state.get(clicked) == Some(turn) {
+            MoveSelectionState::Selected { from: clicked }
+        } else {
+            MoveSelectionState::Initial
+        };
+        None
+    }
+
+    fn draw_move_selection_on_grid(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        move_selection_state: &Self::MoveSelectionState,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        let highlight = |(row, col): Pos, color: Color32| {
+            let rect = cell_to_rect(row, col).shrink(0.06 * cell_size);
+            painter.rect_filled(rect, 0.2 * cell_size, color);
+        };
+
+        // Squares hit by arrows are blocked off
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                if state.arrows & bit((row, col)) != 0 {
+                    highlight((row, col), Color32::from_gray(40));
+                }
+            }
+        }
+
+        let selected = Color32::CYAN
+            .lerp_to_gamma(Color32::BLUE, 0.5)
+            .gamma_multiply(0.5);
+        let target = Color32::CYAN
+            .lerp_to_gamma(Color32::GREEN, 0.5)
+            .gamma_multiply(0.5);
+        let moves = self.generate_moves(&mut state.clone());
+        match *move_selection_state {
+            MoveSelectionState::Initial => {}
+            MoveSelectionState::Selected { from } => {
+                highlight(from, selected);
+                let mut targets = moves
+                    .iter()
+                    .filter(|mv| mv.from == from)
+                    .map(|mv| mv.to)
+                    .collect::<Vec<_>>();
+                targets.dedup();
+                for to in targets {
+                    highlight(to, target);
+                }
+            }
+            MoveSelectionState::Moved { from, to } => {
+                highlight(from, selected.gamma_multiply(0.5));
+                highlight(to, selected);
+                for mv in moves {
+                    if mv.from == from && mv.to == to {
+                        highlight(mv.arrow, Color32::RED.gamma_multiply(0.5));
+                    }
+                }
+            }
+        }
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        _ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_and_arrows() {
+        let logic = Amazons;
+        let mut state = logic.initial_state();
+        let moves = logic.generate_moves(&mut state);
+        assert_eq!(moves.len(), 2176);
+        let before = state.clone();
+        let mv = *moves
+            .iter()
+            .find(|mv| mv.to_string() == "d1-d7/d1")
+            .unwrap();
+        logic.make_move(&mut state, &mv);
+        assert_eq!(state.get((3, 3)), Some(Player::First));
+        assert_eq!(state.arrows, bit((9, 3)));
+        logic.unmake_move(&mut state, &mv);
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn trapped_amazons_lose() {
+        let logic = Amazons;
+        // White's only amazon is walled into the corner by arrows
+        let mut state = BoardState {
+            amazons: [bit((0, 0)), bit((9, 9))],
+            arrows: bit((0, 1)) | bit((1, 0)) | bit((1, 1)),
+            move_num: 0,
+        };
+        assert!(logic.generate_moves(&mut state).is_empty());
+        assert!(matches!(logic.score(&mut state), AbsScore::SecondPlayerWin));
+    }
+}
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use crate::{
+    ai::{Ai, SearchEvent, alphabeta::singlethreaded::AlphaBeta, strength::Strength},
+    game::{Game, GameLogic},
+    grid::chess::{Chess, positions::StartPosition},
+};
+
+const USAGE: &str = "Usage: --bench [--depth <depth>] [--nodes <nodes per position>]";
+
+// How far into a game each of the positions of a game's suite is
+const SUITE_PLIES: [usize; 4] = [0, 6, 12, 20];
+
+// Chess positions searched as well as those reached from the start
+const CHESS_POSITIONS: [&str; 4] = [
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "2r3k1/5ppp/p3p3/1p1n4/3P4/P4N2/1P3PPP/2R3K1 b - - 0 25",
+];
+
+// The positions after playing a fixed sequence of moves from the start for each of `SUITE_PLIES`
+fn suite<G: GameLogic>(logic: G) -> Vec<Game<G>> {
+    let mut game = Game::new(logic);
+    let mut positions = vec![];
+    for ply in 0..=SUITE_PLIES[SUITE_PLIES.len() - 1] {
+        if SUITE_PLIES.contains(&ply) {
+            positions.push(game.clone());
+        }
+        let mut moves = game.logic().generate_moves(&mut game.state().clone());
+        if moves.is_empty() {
+            break;
+        }
+        // Sorted so that the suite doesn't depend on the order moves are generated in
+        moves.sort_by_cached_key(|mv| mv.to_string());
+        game.make_move(moves[(ply * 7 + 3) % moves.len()].clone());
+    }
+    positions
+}
+
+// Search each position with a fresh single threaded search, returning the total nodes and time
+fn bench_positions<G: GameLogic + Send>(
+    positions: Vec<Game<G>>,
+    strength: Strength,
+) -> (usize, chrono::TimeDelta) {
+    let mut ai = AlphaBeta::new();
+    ai.set_strength(strength);
+    let events = ai.subscribe();
+    let mut total_nodes = 0;
+    let mut total_time = chrono::TimeDelta::zero();
+    for game in positions {
+        let start = chrono::Utc::now();
+        ai.set_game(game);
+        let mut nodes = 0;
+        let mut finished = false;
+        while !finished {
+            ai.think(chrono::TimeDelta::hours(1));
+            while let Ok(event) = events.try_recv() {
+                match event {
+                    SearchEvent::Nodes(n) => nodes = n,
+                    SearchEvent::Finished => finished = true,
+                    _ => {}
+                }
+            }
+        }
+        total_time += chrono::Utc::now() - start;
+        total_nodes += nodes;
+    }
+    (total_nodes, total_time)
+}
+
+/*
+Search a fixed suite of positions of every game to a fixed depth or number of nodes, for noticing when the search or move generation gets slower.
+Invoked from the command line as `--bench`, printing the nodes, time and speed for each game and then in total.
+The single threaded search is used so that the node counts are the same from run to run, and a search stops after the first iteration to reach the node limit.
+ */
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut strength = Strength {
+        name: "Bench",
+        max_depth: Some(4),
+        max_nodes: Some(20_000),
+        ..Strength::FULL
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .and_then(|value| value.parse::<usize>().ok())
+            .filter(|value| *value > 0)
+            .ok_or(USAGE)?;
+        match arg.as_str() {
+            "--depth" => strength.max_depth = Some(value),
+            "--nodes" => strength.max_nodes = Some(value),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    let mut chess = suite(Chess::Standard);
+    for fen in CHESS_POSITIONS {
+        chess.push(Game::new(Chess::Custom(Arc::new(StartPosition::from_fen(
+            fen,
+        )?))));
+    }
+    let results = [
+        ("Chess", bench_positions(chess, strength)),
+        (
+            "Connect Four",
+            bench_positions(suite(super::connect_four::ConnectFour), strength),
+        ),
+        (
+            "Checkers",
+            bench_positions(suite(super::checkers::Checkers), strength),
+        ),
+        (
+            "Reversi",
+            bench_positions(suite(super::reversi::Reversi), strength),
+        ),
+        (
+            "Gomoku",
+            bench_positions(
+                suite(super::gomoku::Gomoku::<15> {
+                    forbidden_moves: false,
+                }),
+                strength,
+            ),
+        ),
+        (
+            "Hex",
+            bench_positions(suite(super::hex::Hex::<11>), strength),
+        ),
+        (
+            "Nine Men's Morris",
+            bench_positions(suite(super::morris::Morris), strength),
+        ),
+        (
+            "Breakthrough",
+            bench_positions(suite(super::breakthrough::Breakthrough), strength),
+        ),
+        (
+            "Kalah",
+            bench_positions(suite(super::kalah::Kalah), strength),
+        ),
+        (
+            "Shogi",
+            bench_positions(suite(super::shogi::Shogi), strength),
+        ),
+        ("Go", bench_positions(suite(super::go::Go), strength)),
+        (
+            "Lines of Action",
+            bench_positions(suite(super::lines_of_action::LinesOfAction), strength),
+        ),
+        (
+            "Amazons",
+            bench_positions(suite(super::amazons::Amazons), strength),
+        ),
+    ];
+
+    let line = |name: &str, nodes: usize, time: chrono::TimeDelta| {
+        let seconds = time.as_seconds_f64();
+        println!(
+            "{name:20} {nodes:>12} nodes {seconds:>9.3}s {:>10.0} nps",
+            nodes as f64 / seconds.max(0.001)
+        );
+    };
+    let mut total_nodes = 0;
+    let mut total_time = chrono::TimeDelta::zero();
+    for (name, (nodes, time)) in results {
+        line(name, nodes, time);
+        total_nodes += nodes;
+        total_time += time;
+    }
+    println!();
+    line("Total", total_nodes, total_time);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suite_positions() {
+        let positions = suite(super::super::connect_four::ConnectFour);
+        assert_eq!(
+            positions
+                .iter()
+                .map(|game| game.num_moves())
+                .collect::<Vec<_>>(),
+            SUITE_PLIES
+        );
+        for fen in CHESS_POSITIONS {
+            assert!(StartPosition::from_fen(fen).is_ok(), "{fen}");
+        }
+    }
+}
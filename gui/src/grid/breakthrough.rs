@@ -0,0 +1,408 @@
+use std::fmt::Display;
+
+use egui::{Color32, Painter, Rect};
+
+use crate::{
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{GridGame, MoveSelectionAction, Piece},
+};
+
+const SIZE: usize = 8;
+
+fn bit(row: usize, col: usize) -> u64 {
+    1 << (SIZE * row + col)
+}
+
+fn player_idx(player: Player) -> usize {
+    match player {
+        Player::First => 0,
+        Player::Second => 1,
+    }
+}
+
+// The row a player's pawns are racing to reach
+fn goal_row(player: Player) -> usize {
+    match player {
+        Player::First => 0,
+        Player::Second => SIZE - 1,
+    }
+}
+
+/*
+Breakthrough on an 8x8 board.
+Each player starts with two rows of pawns which move one square straight or diagonally forward and capture
+diagonally forward. The first pawn to reach the far row wins, as does capturing every opposing pawn.
+White is the first player and starts at the bottom of the board.
+ */
+#[derive(Debug, Clone)]
+pub struct Breakthrough;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState {
+    pawns: [u64; 2],
+    move_num: usize,
+}
+
+impl BoardState {
+    fn get(&self, row: usize, col: usize) -> Option<Player> {
+        if self.pawns[0] & bit(row, col) != 0 {
+            Some(Player::First)
+        } else if self.pawns[1] & bit(row, col) != 0 {
+            Some(Player::Second)
+        } else {
+            None
+        }
+    }
+
+    fn winner(&self) -> Option<Player> {
+        let row_mask = |row: usize| 0xff << (SIZE * row);
+        if self.pawns[0] & row_mask(goal_row(Player::First)) != 0 || self.pawns[1] == 0 {
+            Some(Player::First)
+        } else if self.pawns[1] & row_mask(goal_row(Player::Second)) != 0 || self.pawns[0] == 0 {
+            Some(Player::Second)
+        } else {
+            None
+        }
+    }
+}
+
+impl State<Breakthrough> for BoardState {
+    fn ident(self) -> BoardStateIdent {
+        BoardStateIdent {
+            pawns: self.pawns,
+            move_num: self.move_num,
+        }
+    }
+
+    // Positions can't repeat since pawns only move forwards
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+#[derive(Debug, Clone)]
+pub struct BoardStateIdent {
+    pawns: [u64; 2],
+    move_num: usize,
+}
+
+impl PartialEq for BoardStateIdent {
+    fn eq(&self, other: &Self) -> bool {
+        self.pawns == other.pawns && self.move_num % 2 == other.move_num % 2
+    }
+}
+
+impl Eq for BoardStateIdent {}
+
+impl NoAlloc for BoardStateIdent {}
+
+impl StateIdent<Breakthrough> for BoardStateIdent {
+    fn hash64(&self) -> u64 {
+        (self.pawns[0].wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ self.pawns[1]
+                .wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+                .rotate_left(29))
+        .wrapping_add(self.move_num as u64 % 2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    from: (usize, usize),
+    to: (usize, usize),
+    capture: bool,
+}
+
+fn square_name((row, col): (usize, usize)) -> String {
+    format!("{}{}", (b'a' + col as u8) as char, SIZE - row)
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sep = if self.capture { "x" } else { "-" };
+        write!(
+            f,
+            "{}{}{}",
+            square_name(self.from),
+            sep,
+            square_name(self.to)
+        )
+    }
+}
+
+impl GameLogic for Breakthrough {
+    type State = BoardState;
+    type StateIdent = BoardStateIdent;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        let row_mask = |row: usize| 0xffu64 << (SIZE * row);
+        BoardState {
+            pawns: [
+                row_mask(SIZE - 2) | row_mask(SIZE - 1),
+                row_mask(0) | row_mask(1),
+            ],
+            move_num: 0,
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        if state.move_num.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        if state.winner().is_some() {
+            return vec![];
+        }
+        let turn = self.turn(state);
+        let forward = match turn {
+            Player::First => -1,
+            Player::Second => 1,
+        };
+        let mut moves = vec![];
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                if state.get(row, col) != Some(turn) {
+                    continue;
+                }
+                // The goal row hasn't been reached so there is always a row in front
+                let to_row = row.strict_add_signed(forward);
+                for dcol in [-1, 0, 1] {
+                    let Some(to_col) = col.checked_add_signed(dcol).filter(|c| *c < SIZE) else {
+                        continue;
+                    };
+                    match state.get(to_row, to_col) {
+                        None => moves.push(Move {
+                            from: (row, col),
+                            to: (to_row, to_col),
+                            capture: false,
+                        }),
+                        Some(player) if player != turn && dcol != 0 => moves.push(Move {
+                            from: (row, col),
+                            to: (to_row, to_col),
+                            capture: true,
+                        }),
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+        // Search captures and the most advanced pawns first
+        moves.sort_by_key(|mv| (!mv.capture, mv.to.0.abs_diff(goal_row(turn))));
+        moves
+    }
+
+    // Captures and moves onto the goal row
+    fn generate_quiescence_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        let turn = self.turn(state);
+        self.generate_moves(state)
+            .into_iter()
+            .filter(|mv| mv.capture || mv.to.0 == goal_row(turn))
+            .collect()
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        match state.winner() {
+            Some(Player::First) => return AbsScore::FirstPlayerWin,
+            Some(Player::Second) => return AbsScore::SecondPlayerWin,
+            None => {}
+        }
+        // Every pawn being blocked head on loses
+        if self.generate_moves(state).is_empty() {
+            return match self.turn(state) {
+                Player::First => AbsScore::SecondPlayerWin,
+                Player::Second => AbsScore::FirstPlayerWin,
+            };
+        }
+
+        // Pawns are worth more the further they have advanced
+        let mut total = 0;
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                total += match state.get(row, col) {
+                    Some(Player::First) => 100 + (SIZE - 1 - row).pow(2) as i64,
+                    Some(Player::Second) => -100 - row.pow(2) as i64,
+                    None => 0,
+                };
+            }
+        }
+        AbsScore::Heuristic(total)
+    }
+
+    fn is_capture(&self, _state: &Self::State, mv: &Self::Move) -> bool {
+        mv.capture
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        let idx = player_idx(self.turn(state));
+        debug_assert!(state.pawns[idx] & bit(mv.from.0, mv.from.1) != 0);
+        debug_assert_eq!(
+            mv.capture,
+            state.pawns[1 - idx] & bit(mv.to.0, mv.to.1) != 0
+        );
+        state.pawns[idx] ^= bit(mv.from.0, mv.from.1) | bit(mv.to.0, mv.to.1);
+        if mv.capture {
+            state.pawns[1 - idx] &= !bit(mv.to.0, mv.to.1);
+        }
+        state.move_num += 1;
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        state.move_num -= 1;
+        let idx = player_idx(self.turn(state));
+        state.pawns[idx] ^= bit(mv.from.0, mv.from.1) | bit(mv.to.0, mv.to.1);
+        if mv.capture {
+            state.pawns[1 - idx] |= bit(mv.to.0, mv.to.1);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MoveSelectionState {
+    Initial,
+    Selected { row: usize, col: usize },
+}
+
+impl GridGame for Breakthrough {
+    const ROWS: usize = SIZE;
+    const COLS: usize = SIZE;
+
+    fn piece(&self, state: &Self::State, row: usize, col: usize) -> Piece {
+        match state.get(row, col) {
+            Some(Player::First) => Piece::WhitePawn,
+            Some(Player::Second) => Piece::BlackPawn,
+            None => Piece::Empty,
+        }
+    }
+
+    type MoveSelectionState = MoveSelectionState;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        MoveSelectionState::Initial
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        for ((row, col), strength) in [(mv.from, 0.25), (mv.to, 0.5)] {
+            let rect = cell_to_rect(row, col).shrink(0.06 * cell_size);
+            painter.rect_filled(
+                rect,
+                0.2 * cell_size,
+                Color32::ORANGE.gamma_multiply(strength),
+            );
+        }
+    }
+
+    fn update_move_selection(
+        &self,
+        turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        let MoveSelectionAction::ClickSquare { row, col } = action else {
+            *move_selection_state = MoveSelectionState::Initial;
+            return None;
+        };
+        if let MoveSelectionState::Selected {
+            row: from_row,
+            col: from_col,
+        } = *move_selection_state
+            && let Some(mv) = self
+                .generate_moves(&mut state.clone())
+                .into_iter()
+                .find(|mv| mv.from == (from_row, from_col) && mv.to == (row, col))
+        {
+            *move_selection_state = MoveSelectionState::Initial;
+            return Some(mv);
+        }
+        *move_selection_state = if state.get(row, col) == Some(turn) {
+            MoveSelectionState::Selected { row, col }
+        } else {
+            MoveSelectionState::Initial
+        };
+        None
+    }
+
+    fn draw_move_selection_on_grid(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        move_selection_state: &Self::MoveSelectionState,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        let MoveSelectionState::Selected { row, col } = *move_selection_state else {
+            return;
+        };
+        let highlight = |(row, col): (usize, usize), color: Color32| {
+            let rect = cell_to_rect(row, col).shrink(0.06 * cell_size);
+            painter.rect_filled(rect, 0.2 * cell_size, color);
+        };
+        highlight(
+            (row, col),
+            Color32::CYAN
+                .lerp_to_gamma(Color32::BLUE, 0.5)
+                .gamma_multiply(0.5),
+        );
+        for mv in self.generate_moves(&mut state.clone()) {
+            if mv.from == (row, col) {
+                highlight(
+                    mv.to,
+                    if mv.capture {
+                        Color32::RED.gamma_multiply(0.5)
+                    } else {
+                        Color32::CYAN
+                            .lerp_to_gamma(Color32::GREEN, 0.5)
+                            .gamma_multiply(0.5)
+                    },
+                );
+            }
+        }
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        _ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn race_to_the_far_row() {
+        let logic = Breakthrough;
+        let mut state = logic.initial_state();
+        assert_eq!(logic.generate_moves(&mut state).len(), 22);
+        state.pawns = [bit(1, 3), bit(5, 0)];
+        // Every move from the second row reaches the goal
+        let moves = logic.generate_moves(&mut state);
+        assert_eq!(moves.len(), 3);
+        assert_eq!(logic.generate_quiescence_moves(&mut state).len(), 3);
+        let mv = moves[0];
+        logic.make_move(&mut state, &mv);
+        assert!(logic.generate_moves(&mut state).is_empty());
+        assert!(matches!(logic.score(&mut state), AbsScore::FirstPlayerWin));
+        logic.unmake_move(&mut state, &mv);
+        assert_eq!(state.pawns, [bit(1, 3), bit(5, 0)]);
+    }
+}
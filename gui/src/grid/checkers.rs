@@ -0,0 +1,575 @@
+use std::fmt::Display;
+
+use egui::{Color32, Painter, Rect, Stroke};
+
+use crate::{
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{GridGame, MoveSelectionAction, Piece},
+};
+
+const SIZE: usize = 8;
+// A draw is declared after this many plies without a capture or a man moving
+const QUIET_PLY_LIMIT: usize = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Square {
+    Empty,
+    Man(Player),
+    King(Player),
+}
+
+impl Square {
+    fn owner(self) -> Option<Player> {
+        match self {
+            Square::Empty => None,
+            Square::Man(player) | Square::King(player) => Some(player),
+        }
+    }
+}
+
+/*
+English draughts on an 8x8 board.
+Black is the first player and starts at the bottom of the board. Captures are compulsory and
+a capturing piece must keep jumping while it can, so a whole chain of jumps is a single move.
+ */
+#[derive(Debug, Clone)]
+pub struct Checkers;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState {
+    squares: [[Square; SIZE]; SIZE],
+    move_num: usize,
+    quiet_plies: usize,
+}
+
+impl BoardState {
+    fn get(&self, (row, col): (usize, usize)) -> Square {
+        self.squares[row][col]
+    }
+
+    fn set(&mut self, (row, col): (usize, usize), square: Square) {
+        self.squares[row][col] = square;
+    }
+}
+
+impl State<Checkers> for BoardState {
+    fn ident(self) -> BoardStateIdent {
+        BoardStateIdent {
+            squares: self.squares,
+            move_num: self.move_num,
+        }
+    }
+
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+#[derive(Debug, Clone)]
+pub struct BoardStateIdent {
+    squares: [[Square; SIZE]; SIZE],
+    move_num: usize,
+}
+
+impl PartialEq for BoardStateIdent {
+    fn eq(&self, other: &Self) -> bool {
+        self.squares == other.squares && self.move_num % 2 == other.move_num % 2
+    }
+}
+
+impl Eq for BoardStateIdent {}
+
+impl NoAlloc for BoardStateIdent {}
+
+impl StateIdent<Checkers> for BoardStateIdent {
+    fn hash64(&self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325u64 ^ (self.move_num as u64 % 2);
+        for row in &self.squares {
+            for square in row {
+                let value = match square {
+                    Square::Empty => 0,
+                    Square::Man(Player::First) => 1,
+                    Square::Man(Player::Second) => 2,
+                    Square::King(Player::First) => 3,
+                    Square::King(Player::Second) => 4,
+                };
+                hash = (hash ^ value).wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        hash
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Move {
+    // The starting square followed by each square the piece lands on
+    path: Vec<(usize, usize)>,
+    piece: Square,
+    captured: Vec<((usize, usize), Square)>,
+    promotes: bool,
+    prev_quiet_plies: usize,
+}
+
+impl Move {
+    fn from(&self) -> (usize, usize) {
+        self.path[0]
+    }
+
+    fn to(&self) -> (usize, usize) {
+        *self.path.last().unwrap()
+    }
+}
+
+fn square_name((row, col): (usize, usize)) -> String {
+    format!("{}{}", (b'a' + col as u8) as char, SIZE - row)
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sep = if self.captured.is_empty() { "-" } else { "x" };
+        let path = self
+            .path
+            .iter()
+            .map(|pos| square_name(*pos))
+            .collect::<Vec<_>>();
+        write!(f, "{}", path.join(sep))
+    }
+}
+
+impl Checkers {
+    // The row a man of `player` is crowned on
+    fn king_row(player: Player) -> usize {
+        match player {
+            Player::First => 0,
+            Player::Second => SIZE - 1,
+        }
+    }
+
+    fn directions(piece: Square) -> &'static [(isize, isize)] {
+        match piece {
+            Square::Man(Player::First) => &[(-1, -1), (-1, 1)],
+            Square::Man(Player::Second) => &[(1, -1), (1, 1)],
+            Square::King(_) => &[(-1, -1), (-1, 1), (1, -1), (1, 1)],
+            Square::Empty => &[],
+        }
+    }
+
+    fn step((row, col): (usize, usize), (drow, dcol): (isize, isize)) -> Option<(usize, usize)> {
+        let row = row.checked_add_signed(drow)?;
+        let col = col.checked_add_signed(dcol)?;
+        (row < SIZE && col < SIZE).then_some((row, col))
+    }
+
+    // Extend a chain of jumps as far as it will go, pushing each complete chain to `moves`
+    fn extend_captures(
+        &self,
+        board: &mut BoardState,
+        piece: Square,
+        path: &mut Vec<(usize, usize)>,
+        captured: &mut Vec<((usize, usize), Square)>,
+        moves: &mut Vec<Move>,
+    ) {
+        let turn = piece.owner().unwrap();
+        let pos = *path.last().unwrap();
+        let mut extended = false;
+        for dir in Self::directions(piece) {
+            let Some(over) = Self::step(pos, *dir) else {
+                continue;
+            };
+            let Some(land) = Self::step(over, *dir) else {
+                continue;
+            };
+            let over_content = board.get(over);
+            if over_content.owner() != Some(turn.flip()) || board.get(land) != Square::Empty {
+                continue;
+            }
+            extended = true;
+            board.set(over, Square::Empty);
+            path.push(land);
+            captured.push((over, over_content));
+            // Reaching the far row as a man ends the move
+            if matches!(piece, Square::Man(_)) && land.0 == Self::king_row(turn) {
+                moves.push(Move {
+                    path: path.clone(),
+                    piece,
+                    captured: captured.clone(),
+                    promotes: true,
+                    prev_quiet_plies: board.quiet_plies,
+                });
+            } else {
+                self.extend_captures(board, piece, path, captured, moves);
+            }
+            captured.pop();
+            path.pop();
+            board.set(over, over_content);
+        }
+        if !extended && !captured.is_empty() {
+            moves.push(Move {
+                path: path.clone(),
+                piece,
+                captured: captured.clone(),
+                promotes: false,
+                prev_quiet_plies: board.quiet_plies,
+            });
+        }
+    }
+
+    fn captures(&self, board: &BoardState, turn: Player) -> Vec<Move> {
+        let mut board = board.clone();
+        let mut moves = vec![];
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                let piece = board.get((row, col));
+                if piece.owner() == Some(turn) {
+                    // Lift the piece so a chain of jumps can pass back through its starting square
+                    board.set((row, col), Square::Empty);
+                    self.extend_captures(
+                        &mut board,
+                        piece,
+                        &mut vec![(row, col)],
+                        &mut vec![],
+                        &mut moves,
+                    );
+                    board.set((row, col), piece);
+                }
+            }
+        }
+        moves
+    }
+
+    fn simple_moves(&self, board: &BoardState, turn: Player) -> Vec<Move> {
+        let mut moves = vec![];
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                let piece = board.get((row, col));
+                if piece.owner() != Some(turn) {
+                    continue;
+                }
+                for dir in Self::directions(piece) {
+                    if let Some(to) = Self::step((row, col), *dir)
+                        && board.get(to) == Square::Empty
+                    {
+                        moves.push(Move {
+                            path: vec![(row, col), to],
+                            piece,
+                            captured: vec![],
+                            promotes: matches!(piece, Square::Man(_))
+                                && to.0 == Self::king_row(turn),
+                            prev_quiet_plies: board.quiet_plies,
+                        });
+                    }
+                }
+            }
+        }
+        moves
+    }
+}
+
+impl GameLogic for Checkers {
+    type State = BoardState;
+    type StateIdent = BoardStateIdent;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        let mut squares = [[Square::Empty; SIZE]; SIZE];
+        for (row, squares) in squares.iter_mut().enumerate() {
+            for (col, square) in squares.iter_mut().enumerate() {
+                if (row + col) % 2 == 1 {
+                    if row < 3 {
+                        *square = Square::Man(Player::Second);
+                    } else if row >= SIZE - 3 {
+                        *square = Square::Man(Player::First);
+                    }
+                }
+            }
+        }
+        BoardState {
+            squares,
+            move_num: 0,
+            quiet_plies: 0,
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        if state.move_num.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        if state.quiet_plies >= QUIET_PLY_LIMIT {
+            return vec![];
+        }
+        let turn = self.turn(state);
+        let captures = self.captures(state, turn);
+        if captures.is_empty() {
+            self.simple_moves(state, turn)
+        } else {
+            captures
+        }
+    }
+
+    fn generate_quiescence_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        if state.quiet_plies >= QUIET_PLY_LIMIT {
+            return vec![];
+        }
+        self.captures(state, self.turn(state))
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        if state.quiet_plies >= QUIET_PLY_LIMIT {
+            return AbsScore::Draw;
+        }
+        // The player to move loses when they have no moves
+        if self.generate_moves(state).is_empty() {
+            return match self.turn(state) {
+                Player::First => AbsScore::SecondPlayerWin,
+                Player::Second => AbsScore::FirstPlayerWin,
+            };
+        }
+
+        let mut total = 0;
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                total += match state.get((row, col)) {
+                    Square::Empty => 0,
+                    // Men are worth a little more the closer they are to being crowned
+                    Square::Man(Player::First) => 100 + 2 * (SIZE - 1 - row) as i64,
+                    Square::Man(Player::Second) => -100 - 2 * row as i64,
+                    Square::King(Player::First) => 250,
+                    Square::King(Player::Second) => -250,
+                };
+            }
+        }
+        AbsScore::Heuristic(total)
+    }
+
+    fn is_capture(&self, _state: &Self::State, mv: &Self::Move) -> bool {
+        !mv.captured.is_empty()
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        debug_assert_eq!(state.quiet_plies, mv.prev_quiet_plies);
+        debug_assert_eq!(state.get(mv.from()), mv.piece);
+        state.set(mv.from(), Square::Empty);
+        for (pos, _) in &mv.captured {
+            state.set(*pos, Square::Empty);
+        }
+        let turn = self.turn(state);
+        state.set(
+            mv.to(),
+            if mv.promotes {
+                Square::King(turn)
+            } else {
+                mv.piece
+            },
+        );
+        if mv.captured.is_empty() && matches!(mv.piece, Square::King(_)) {
+            state.quiet_plies += 1;
+        } else {
+            state.quiet_plies = 0;
+        }
+        state.move_num += 1;
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        state.move_num -= 1;
+        state.quiet_plies = mv.prev_quiet_plies;
+        state.set(mv.to(), Square::Empty);
+        for (pos, square) in &mv.captured {
+            state.set(*pos, *square);
+        }
+        state.set(mv.from(), mv.piece);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MoveSelectionState {
+    Initial,
+    // The squares clicked so far, starting with the piece
+    Path(Vec<(usize, usize)>),
+}
+
+impl GridGame for Checkers {
+    const ROWS: usize = SIZE;
+    const COLS: usize = SIZE;
+
+    fn piece(&self, state: &Self::State, row: usize, col: usize) -> Piece {
+        match state.get((row, col)) {
+            Square::Empty => Piece::Empty,
+            Square::Man(Player::First) => Piece::BlackChecker,
+            Square::Man(Player::Second) => Piece::WhiteChecker,
+            Square::King(Player::First) => Piece::BlackCheckerKing,
+            Square::King(Player::Second) => Piece::WhiteCheckerKing,
+        }
+    }
+
+    type MoveSelectionState = MoveSelectionState;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        MoveSelectionState::Initial
+    }
+
+    fn player_name(&self, player: Player) -> &'static str {
+        match player {
+            Player::First => "Black",
+            Player::Second => "White",
+        }
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        for (row, col) in &mv.path {
+            let rect = cell_to_rect(*row, *col).shrink(0.06 * cell_size);
+            painter.rect_filled(rect, 0.2 * cell_size, Color32::ORANGE.gamma_multiply(0.5));
+        }
+        for step in mv.path.windows(2) {
+            let from = cell_to_rect(step[0].0, step[0].1).center();
+            let to = cell_to_rect(step[1].0, step[1].1).center();
+            painter.arrow(
+                from,
+                to - from,
+                Stroke::new(0.05 * cell_size, Color32::ORANGE),
+            );
+        }
+    }
+
+    fn update_move_selection(
+        &self,
+        turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        let MoveSelectionAction::ClickSquare { row, col } = action else {
+            *move_selection_state = MoveSelectionState::Initial;
+            return None;
+        };
+        let moves = self.generate_moves(&mut state.clone());
+        if let MoveSelectionState::Path(path) = move_selection_state {
+            let mut extended = path.clone();
+            extended.push((row, col));
+            if let Some(mv) = moves.iter().find(|mv| mv.path == extended) {
+                *move_selection_state = MoveSelectionState::Initial;
+                return Some(mv.clone());
+            }
+            if moves.iter().any(|mv| mv.path.starts_with(&extended)) {
+                *move_selection_state = MoveSelectionState::Path(extended);
+                return None;
+            }
+        }
+        *move_selection_state = if state.get((row, col)).owner() == Some(turn) {
+            MoveSelectionState::Path(vec![(row, col)])
+        } else {
+            MoveSelectionState::Initial
+        };
+        None
+    }
+
+    fn draw_move_selection_on_grid(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        move_selection_state: &Self::MoveSelectionState,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        let highlight = |(row, col): (usize, usize), color: Color32| {
+            let rect = cell_to_rect(row, col).shrink(0.06 * cell_size);
+            painter.rect_filled(rect, 0.2 * cell_size, color);
+        };
+        if let MoveSelectionState::Path(path) = move_selection_state {
+            for pos in path {
+                highlight(
+                    *pos,
+                    Color32::CYAN
+                        .lerp_to_gamma(Color32::BLUE, 0.5)
+                        .gamma_multiply(0.5),
+                );
+            }
+            // The squares which continue the path
+            for mv in self.generate_moves(&mut state.clone()) {
+                if mv.path.len() > path.len() && mv.path.starts_with(path) {
+                    highlight(
+                        mv.path[path.len()],
+                        if mv.captured.is_empty() {
+                            Color32::CYAN
+                                .lerp_to_gamma(Color32::GREEN, 0.5)
+                                .gamma_multiply(0.5)
+                        } else {
+                            Color32::RED.gamma_multiply(0.5)
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        _ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forced_multi_jump() {
+        let logic = Checkers;
+        let mut board = BoardState {
+            squares: [[Square::Empty; SIZE]; SIZE],
+            move_num: 0,
+            quiet_plies: 0,
+        };
+        board.set((7, 0), Square::Man(Player::First));
+        board.set((5, 4), Square::Man(Player::First));
+        board.set((4, 3), Square::Man(Player::Second));
+        board.set((2, 3), Square::Man(Player::Second));
+        // a1 could move, but the capture is compulsory and must be taken in full
+        let moves = logic.generate_moves(&mut board);
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].to_string(), "e3xc5xe7");
+
+        let before = board.clone();
+        logic.make_move(&mut board, &moves[0]);
+        assert_eq!(board.get((1, 4)), Square::Man(Player::First));
+        logic.unmake_move(&mut board, &moves[0]);
+        assert_eq!(board, before);
+    }
+
+    #[test]
+    fn crowning() {
+        let logic = Checkers;
+        let mut board = BoardState {
+            squares: [[Square::Empty; SIZE]; SIZE],
+            move_num: 0,
+            quiet_plies: 0,
+        };
+        board.set((1, 2), Square::Man(Player::First));
+        board.set((7, 0), Square::Man(Player::Second));
+        let mv = logic
+            .generate_moves(&mut board)
+            .into_iter()
+            .find(|mv| mv.to() == (0, 1))
+            .unwrap();
+        logic.make_move(&mut board, &mv);
+        assert_eq!(board.get((0, 1)), Square::King(Player::First));
+    }
+}
@@ -0,0 +1,71 @@
+use crate::grid::chess::Pos;
+
+/*
+Bitboards use bit `8 * row + col` for each square of the 8x8 grid, with row 0 being black's back rank.
+ */
+
+const NO_SQUARE: u8 = u8::MAX;
+
+// The bitboard square of each mailbox index
+const SQUARES: [u8; 10 * 12] = {
+    let mut squares = [NO_SQUARE; 10 * 12];
+    let mut row = 0;
+    while row < 8 {
+        let mut col = 0;
+        while col < 8 {
+            squares[10 * (row + 2) + col + 1] = (8 * row + col) as u8;
+            col += 1;
+        }
+        row += 1;
+    }
+    squares
+};
+
+pub fn square(pos: Pos) -> Option<usize> {
+    match SQUARES[pos.idx] {
+        NO_SQUARE => None,
+        square => Some(square as usize),
+    }
+}
+
+pub fn pos(square: usize) -> Pos {
+    Pos::from_grid(square / 8, square % 8)
+}
+
+pub fn bit(pos: Pos) -> u64 {
+    match square(pos) {
+        Some(square) => 1 << square,
+        None => 0,
+    }
+}
+
+// The square reached from `square` by stepping `(row, col)`, if it's on the grid
+pub fn step(square: usize, (row, col): (isize, isize)) -> Option<usize> {
+    let row = (square / 8) as isize + row;
+    let col = (square % 8) as isize + col;
+    ((0..8).contains(&row) && (0..8).contains(&col)).then_some((8 * row + col) as usize)
+}
+
+// The first square in `blockers` met by sliding from `square` in steps of `dir`
+pub fn first_blocker(square: usize, dir: (isize, isize), blockers: u64) -> Option<usize> {
+    let mut square = square;
+    loop {
+        square = step(square, dir)?;
+        if blockers & (1 << square) != 0 {
+            return Some(square);
+        }
+    }
+}
+
+// The squares of a bitboard, in increasing order
+pub fn squares(mut bits: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if bits == 0 {
+            None
+        } else {
+            let square = bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            Some(square)
+        }
+    })
+}
@@ -4,6 +4,9 @@ pub struct ZobristHashConstants {
     pub black_can_castle_left: u64,
     pub black_can_castle_right: u64,
     pub repetition: u64,
+    pub side_to_move: u64,
+    pub en_croissant_file: [u64; 8],
+    pub checks: [[u64; 4]; 2],
     pub board_content: [[[u64; 256]; 8]; 8],
 }
 
@@ -13,6 +16,31 @@ pub const ZOBRIST_HASH_CONSTANTS: ZobristHashConstants = ZobristHashConstants {
     black_can_castle_left: 0x669317FED07D3550u64,
     black_can_castle_right: 0x6DDEFBC31888FC78u64,
     repetition: 0x9E87CCA42AB1F878u64,
+    side_to_move: 0xA64CC5163D9C93A5u64,
+    en_croissant_file: [
+        0x09AC23A7D2B5E0C9u64,
+        0x17147019A1D0D0F5u64,
+        0x185CBFB41583C593u64,
+        0x1353DDA962FAA60Fu64,
+        0xC221CC823BB5E8E7u64,
+        0xC20501A1614517F7u64,
+        0x3B23C9D2FE9E3BBDu64,
+        0x5CCA78262F7BEBDFu64,
+    ],
+    checks: [
+        [
+            0x0468AA665378565Eu64,
+            0x0F2718CC286F1906u64,
+            0x912E794348DE1CF0u64,
+            0xB839482775294419u64,
+        ],
+        [
+            0x7B18F31E3B4AE376u64,
+            0xE2243E3476AB905Du64,
+            0xB153676420A0A9E7u64,
+            0x386CBF3F1DD65B52u64,
+        ],
+    ],
     board_content: [
         [
             [
@@ -0,0 +1,292 @@
+use crate::{
+    grid::{
+        Piece,
+        chess::{Chess, positions::StartPosition},
+        theme::{Appearance, PieceSet, PieceTextures},
+        ui::{BoardLayout, paint_piece},
+    },
+    root::AppState,
+};
+use egui::{Color32, Stroke};
+
+// The pieces which can be put down, in FEN letters with capitals for white
+const PIECES: [char; 12] = ['K', 'Q', 'R', 'B', 'N', 'P', 'k', 'q', 'r', 'b', 'n', 'p'];
+
+// The castling rights in the order FEN lists them
+const CASTLING: [(char, &str); 4] = [
+    ('K', "White O-O"),
+    ('Q', "White O-O-O"),
+    ('k', "Black O-O"),
+    ('q', "Black O-O-O"),
+];
+
+fn piece(letter: char) -> Piece {
+    match letter {
+        'K' => Piece::WhiteKing,
+        'Q' => Piece::WhiteQueen,
+        'R' => Piece::WhiteRook,
+        'B' => Piece::WhiteBishop,
+        'N' => Piece::WhiteKnight,
+        'P' => Piece::WhitePawn,
+        'k' => Piece::BlackKing,
+        'q' => Piece::BlackQueen,
+        'r' => Piece::BlackRook,
+        'b' => Piece::BlackBishop,
+        'n' => Piece::BlackKnight,
+        'p' => Piece::BlackPawn,
+        _ => Piece::Empty,
+    }
+}
+
+fn piece_name(letter: char) -> String {
+    let colour = if letter.is_ascii_uppercase() {
+        "White"
+    } else {
+        "Black"
+    };
+    let kind = match letter.to_ascii_lowercase() {
+        'k' => "King",
+        'q' => "Queen",
+        'r' => "Rook",
+        'b' => "Bishop",
+        'n' => "Knight",
+        _ => "Pawn",
+    };
+    format!("{colour} {kind}")
+}
+
+/*
+Setting up a chess position by hand, to start a game or an analysis from.
+Clicking a square puts down the chosen piece and right clicking clears it.
+The position is checked just as a FEN typed into the menu is, and once it's allowed it goes back to the menu as the custom position.
+ */
+pub struct State {
+    // Gone back to when done, keeping everything chosen there
+    menu: crate::menu::State,
+    // Rows from black's back rank down, with capitals for white pieces as in FEN
+    board: [[char; 8]; 8],
+    black_to_move: bool,
+    // In the order of `CASTLING`
+    castling: [bool; 4],
+    // The piece put down by clicking, or None to clear squares
+    brush: Option<char>,
+    // The text box for reading in a FEN
+    fen_input: String,
+    fen_error: Option<String>,
+    pieces: PieceTextures,
+}
+
+impl State {
+    pub fn new(ctx: &egui::Context, menu: crate::menu::State, fen: &str) -> Self {
+        let mut state = Self {
+            menu,
+            board: [[' '; 8]; 8],
+            black_to_move: false,
+            castling: [false; 4],
+            brush: Some('K'),
+            fen_input: fen.to_string(),
+            fen_error: None,
+            pieces: PieceTextures::new(ctx, PieceSet::default()),
+        };
+        if state.load_fen(fen).is_err() {
+            state
+                .load_fen(crate::grid::chess::positions::PRESETS[0].1)
+                .unwrap();
+        }
+        state
+    }
+
+    fn load_fen(&mut self, fen: &str) -> Result<(), String> {
+        let position = StartPosition::from_fen(fen)?;
+        // The start position uses capitals for black
+        for (row, rank) in position.board.iter().enumerate() {
+            for (col, c) in rank.iter().enumerate() {
+                self.board[row][col] = if c.is_ascii_uppercase() {
+                    c.to_ascii_lowercase()
+                } else {
+                    c.to_ascii_uppercase()
+                };
+            }
+        }
+        self.black_to_move = position.black_to_move;
+        let fen = position.fen();
+        let rights = fen.split_whitespace().nth(2).unwrap_or("-");
+        for (idx, (letter, _)) in CASTLING.into_iter().enumerate() {
+            self.castling[idx] = rights.contains(letter);
+        }
+        Ok(())
+    }
+
+    fn fen(&self) -> String {
+        let placement = self
+            .board
+            .iter()
+            .map(|rank| {
+                let mut text = String::new();
+                let mut empty = 0;
+                for c in rank {
+                    if *c == ' ' {
+                        empty += 1;
+                    } else {
+                        if empty > 0 {
+                            text.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        text.push(*c);
+                    }
+                }
+                if empty > 0 {
+                    text.push_str(&empty.to_string());
+                }
+                text
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        let mut castling = CASTLING
+            .into_iter()
+            .zip(self.castling)
+            .filter(|(_, allowed)| *allowed)
+            .map(|((letter, _), _)| letter)
+            .collect::<String>();
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        format!(
+            "{placement} {} {castling} - 0 1",
+            if self.black_to_move { 'b' } else { 'w' }
+        )
+    }
+}
+
+impl AppState for State {
+    fn update(
+        &mut self,
+        ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+        appearance: &Appearance,
+    ) -> Option<Box<dyn AppState>> {
+        self.pieces.choose(ctx, appearance.pieces);
+        let fen = self.fen();
+        let checked = StartPosition::from_fen(&fen);
+        let mut done = None;
+
+        egui::SidePanel::left("left panel").show(ctx, |ui| {
+            ui.heading("Edit Position");
+
+            ui.label("Click to put down:");
+            egui::Grid::new("editor_pieces_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    for (idx, letter) in PIECES.into_iter().enumerate() {
+                        ui.selectable_value(&mut self.brush, Some(letter), piece_name(letter));
+                        if idx % 2 == 1 {
+                            ui.end_row();
+                        }
+                    }
+                    ui.selectable_value(&mut self.brush, None, "Clear square");
+                    ui.end_row();
+                });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.black_to_move, false, "White to move");
+                ui.radio_value(&mut self.black_to_move, true, "Black to move");
+            });
+            for (idx, (_, name)) in CASTLING.into_iter().enumerate() {
+                ui.checkbox(&mut self.castling[idx], name);
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Starting Position").clicked() {
+                    self.load_fen(crate::grid::chess::positions::PRESETS[0].1)
+                        .unwrap();
+                }
+                if ui.button("Clear Board").clicked() {
+                    self.board = [[' '; 8]; 8];
+                    self.castling = [false; 4];
+                }
+            });
+
+            ui.separator();
+            ui.label("FEN:");
+            ui.label(&fen);
+            if ui.button("Copy FEN").clicked() {
+                ctx.copy_text(fen.clone());
+            }
+            ui.add(egui::TextEdit::singleline(&mut self.fen_input).desired_width(300.0));
+            if ui.button("Load FEN").clicked() {
+                let input = self.fen_input.clone();
+                self.fen_error = self.load_fen(&input).err();
+            }
+            if let Some(error) = &self.fen_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+
+            ui.separator();
+            if let Err(error) = &checked {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(checked.is_ok(), egui::Button::new("Done"))
+                    .clicked()
+                {
+                    done = Some(Some(fen.clone()));
+                }
+                if ui.button("Cancel").clicked() {
+                    done = Some(None);
+                }
+            });
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let layout = BoardLayout::new(&Chess::Standard, ui.available_rect_before_wrap());
+            let painter = ui.painter();
+            let palette = appearance.palette(ui.visuals());
+            let border = Stroke::new(2.0 / ctx.pixels_per_point(), Color32::BLACK);
+            for (row, rank) in self.board.iter().enumerate() {
+                for (col, c) in rank.iter().enumerate() {
+                    let rect = layout.cell_rect(row, col);
+                    let color = if (row + col) % 2 == 0 {
+                        palette.light
+                    } else {
+                        palette.dark
+                    };
+                    painter.rect_filled(rect, 0.0, color);
+                    painter.rect_stroke(rect, 0.0, border, egui::StrokeKind::Inside);
+                    paint_piece(painter, rect, piece(*c), self.pieces.textures());
+                }
+            }
+
+            let (pointer, left, right) = ui.input(|i| {
+                (
+                    i.pointer.interact_pos(),
+                    i.pointer.primary_down(),
+                    i.pointer.secondary_down(),
+                )
+            });
+            // Holding the button down paints every square passed over
+            if (left || right)
+                && !ui.ctx().wants_pointer_input()
+                && let Some((row, col)) =
+                    pointer.and_then(|pos| layout.cell_at(&Chess::Standard, pos))
+            {
+                self.board[row][col] = match self.brush {
+                    Some(letter) if left => letter,
+                    _ => ' ',
+                };
+            }
+        });
+
+        ctx.request_repaint();
+
+        done.map(|fen| {
+            let menu = std::mem::take(&mut self.menu);
+            Box::new(match fen {
+                Some(fen) => menu.with_custom_position(fen),
+                None => menu,
+            }) as Box<dyn AppState>
+        })
+    }
+}
@@ -0,0 +1,786 @@
+use std::{
+    io::{BufRead, BufReader},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, TryRecvError, channel},
+    },
+};
+
+use serde_json::Value;
+
+use crate::{
+    ai::{
+        Ai, SearchEvent,
+        alphabeta::multithreaded::AlphaBeta,
+        null::NullAi,
+        time::{Clock, TimeControl, TimeManager},
+        uci::UciGame,
+    },
+    game::{Game, GameLogic, Player, SavedClocks},
+    grid::{
+        chess::{Chess, positions::StartPosition},
+        theme::Appearance,
+    },
+    net::protocol::{Connection, Message},
+    root::AppState,
+};
+
+const SERVER: &str = "https://lichess.org";
+
+// Where the token is looked for if none is typed in, and by the bot
+const TOKEN_VARIABLE: &str = "LICHESS_TOKEN";
+
+/*
+Talks to the Lichess API as the account whose personal access token it's given.
+Playing your own games needs a token with the `board:play` scope, and playing as a bot one with `bot:play` for a bot account.
+ */
+#[derive(Clone)]
+pub struct Client {
+    agent: ureq::Agent,
+    token: String,
+}
+
+impl Client {
+    pub fn new(token: &str) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().build(),
+            token: token.trim().to_string(),
+        }
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        self.agent
+            .request(method, &format!("{SERVER}{path}"))
+            .set("Authorization", &format!("Bearer {}", self.token))
+    }
+
+    fn get(&self, path: &str) -> Result<Value, String> {
+        read_response(self.request("GET", path).call())?
+            .into_json()
+            .map_err(|err| format!("Unreadable reply from Lichess: {err}"))
+    }
+
+    fn post(&self, path: &str) -> Result<(), String> {
+        read_response(self.request("POST", path).call()).map(|_| ())
+    }
+
+    fn post_form(&self, path: &str, form: &[(&str, &str)]) -> Result<(), String> {
+        read_response(self.request("POST", path).send_form(form)).map(|_| ())
+    }
+
+    // The events of a stream, one JSON object to a line, skipping the blank lines sent to keep it open
+    fn stream(
+        &self,
+        path: &str,
+    ) -> Result<impl Iterator<Item = Result<Value, String>> + use<>, String> {
+        let reader = BufReader::new(read_response(self.request("GET", path).call())?.into_reader());
+        Ok(reader
+            .lines()
+            .filter(|line| line.as_ref().is_ok_and(|line| !line.trim().is_empty()))
+            .map(|line| {
+                let line = line.map_err(|err| format!("Lost the connection to Lichess: {err}"))?;
+                serde_json::from_str(&line)
+                    .map_err(|err| format!("Unreadable event from Lichess: {err}"))
+            }))
+    }
+
+    // The id of the account the token belongs to, as games name their players
+    pub fn account(&self) -> Result<String, String> {
+        self.get("/api/account")?["id"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| "Lichess didn't say whose account this is".to_string())
+    }
+}
+
+fn read_response(response: Result<ureq::Response, ureq::Error>) -> Result<ureq::Response, String> {
+    match response {
+        Ok(response) => Ok(response),
+        // Lichess explains what went wrong in the body
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            let reason = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|body| body["error"].as_str().map(str::to_string))
+                .unwrap_or(body);
+            Err(format!("Lichess refused ({status}): {reason}"))
+        }
+        Err(err) => Err(format!("Can't reach Lichess: {err}")),
+    }
+}
+
+// The rules of a Lichess game, for those variants played here too
+fn logic_for(variant: &str, initial_fen: &str) -> Result<Chess, String> {
+    let from_start = initial_fen == "startpos";
+    match variant {
+        "standard" if from_start => Ok(Chess::Standard),
+        "standard" | "fromPosition" => Ok(Chess::Custom(Arc::new(StartPosition::from_fen(
+            initial_fen,
+        )?))),
+        "chess960" => initial_fen
+            .split('/')
+            .next()
+            .and_then(Chess::fischer960_from_back_rank)
+            .ok_or_else(|| format!("Not a Chess960 starting position: {initial_fen}")),
+        "kingOfTheHill" if from_start => Ok(Chess::KingOfTheHill),
+        "threeCheck" if from_start => Ok(Chess::ThreeCheck),
+        "horde" if from_start => Ok(Chess::Horde),
+        _ => Err(format!(
+            "{variant} games from {initial_fen} can't be played here"
+        )),
+    }
+}
+
+// The game a `gameFull` event describes, before any of its moves
+fn new_game(full: &Value) -> Result<Game<Chess>, String> {
+    let variant = full["variant"]["key"].as_str().unwrap_or("standard");
+    let fen = full["initialFen"].as_str().unwrap_or("startpos");
+    Ok(Game::new(logic_for(variant, fen)?))
+}
+
+// The side `account` plays in a `gameFull` event
+fn side_of(full: &Value, account: &str) -> Result<Player, String> {
+    let id = |colour: &str| full[colour]["id"].as_str().map(str::to_lowercase);
+    if id("white").as_deref() == Some(account) {
+        Ok(Player::First)
+    } else if id("black").as_deref() == Some(account) {
+        Ok(Player::Second)
+    } else {
+        Err("This account isn't playing in that game".to_string())
+    }
+}
+
+fn colour(player: Player) -> &'static str {
+    match player {
+        Player::First => "white",
+        Player::Second => "black",
+    }
+}
+
+// A player's time left in milliseconds as given in a `gameState` event
+fn time_left(state: &Value, player: Player) -> Option<i64> {
+    state[&format!("{}time", &colour(player)[..1])].as_i64()
+}
+
+// Whether a player is offering a draw in a `gameState` event
+fn offers_draw(state: &Value, player: Player) -> bool {
+    state[&format!("{}draw", &colour(player)[..1])]
+        .as_bool()
+        .unwrap_or(false)
+}
+
+fn is_over(state: &Value) -> bool {
+    state["status"]
+        .as_str()
+        .is_some_and(|status| status != "created" && status != "started")
+}
+
+/*
+Follows a Lichess game from the `gameState` events of its stream, turning what the other player does into the messages the game screen understands.
+The player here is told about the other player's moves, draw offers and how the game ended, but not their own, which the game screen has already made.
+ */
+struct Follower {
+    game: Game<Chess>,
+    remote: Player,
+    // Whether the other player's draw offer has already been passed on
+    draw_offered: bool,
+}
+
+impl Follower {
+    fn update(&mut self, state: &Value) -> Result<Vec<Message>, String> {
+        let mut messages = vec![];
+        let moves = state["moves"].as_str().unwrap_or_default();
+        for text in moves.split_whitespace().skip(self.game.num_moves()) {
+            let mover = self.game.turn();
+            let mv = self
+                .game
+                .logic()
+                .move_from_uci(self.game.state(), text)
+                .ok_or_else(|| format!("Lichess sent a move which isn't legal here: {text}"))?;
+            let name = self.game.logic().move_name(&mv);
+            self.game.make_move(mv);
+            if mover == self.remote {
+                messages.push(Message::Move {
+                    mv: name,
+                    remaining: time_left(state, mover),
+                });
+                self.draw_offered = false;
+            }
+        }
+
+        let offered = offers_draw(state, self.remote);
+        if offered && !self.draw_offered {
+            messages.push(Message::OfferDraw);
+        }
+        self.draw_offered = offered;
+
+        let status = state["status"].as_str().unwrap_or("started");
+        // Whether the game was won by the player here, rather than by the other player or drawn
+        let won = state["winner"].as_str() == Some(colour(self.remote.flip()));
+        match status {
+            "created" | "started" => {}
+            // Played out in the game here as well
+            "mate" | "stalemate" | "variantEnd" => {}
+            "resign" if won => messages.push(Message::Resign),
+            "outoftime" if won => messages.push(Message::OutOfTime),
+            // Ended by the player here, which the game screen already knows about
+            "resign" | "outoftime" => {}
+            "draw" => messages.push(Message::AcceptDraw),
+            _ => messages.push(Message::Bye {
+                reason: Some(format!("The game ended on Lichess: {status}")),
+            }),
+        }
+        Ok(messages)
+    }
+
+    // The UCI Lichess takes for the move the game screen sent as `name`
+    fn uci_move(&self, name: &str) -> Result<String, String> {
+        let mv = self
+            .game
+            .move_named(name)
+            .ok_or_else(|| format!("{name} isn't legal in the game on Lichess"))?;
+        Ok(self.game.logic().uci_move(&mv))
+    }
+}
+
+/*
+One of the player's own Lichess games, played through the Board API.
+Moves are sent as UCI, worked out from the names the game screen sends them by, and leaving the game screen leaves the game running on Lichess.
+ */
+pub struct LichessConnection {
+    client: Client,
+    id: String,
+    follower: Arc<Mutex<Follower>>,
+    incoming: Receiver<Result<Message, String>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl LichessConnection {
+    fn board(&self, action: &str) -> Result<(), String> {
+        self.client
+            .post(&format!("/api/board/game/{}/{action}", self.id))
+    }
+}
+
+impl Drop for LichessConnection {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Connection for LichessConnection {
+    fn send(&mut self, message: &Message) -> Result<(), String> {
+        match message {
+            Message::Move { mv, .. } => {
+                let uci = self.follower.lock().unwrap().uci_move(mv)?;
+                self.board(&format!("move/{uci}"))
+            }
+            Message::OfferDraw | Message::AcceptDraw => self.board("draw/yes"),
+            Message::DeclineDraw => self.board("draw/no"),
+            Message::Resign => self.board("resign"),
+            // Lichess keeps the clocks and says when they run out
+            _ => Ok(()),
+        }
+    }
+
+    fn receive(&mut self) -> Option<Result<Message, String>> {
+        match self.incoming.try_recv() {
+            Ok(message) => Some(message),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                Some(Err("Lost the connection to Lichess".to_string()))
+            }
+        }
+    }
+}
+
+// A game joined through the Board API, ready to play
+struct Joined {
+    game: Game<Chess>,
+    remote: Player,
+    clocks: Option<SavedClocks>,
+    connection: LichessConnection,
+}
+
+// Follow game `id` in the background, sending it once it's been loaded or why it couldn't be
+fn join(client: Client, account: String, id: String) -> Receiver<Result<Joined, String>> {
+    let (sender, receiver) = channel();
+    std::thread::spawn(move || {
+        let (messages, incoming) = channel();
+        let closed = Arc::new(AtomicBool::new(false));
+        let opened = (|| {
+            let mut events = client.stream(&format!("/api/board/game/stream/{id}"))?;
+            let full = events
+                .next()
+                .ok_or_else(|| "Lichess sent nothing about the game".to_string())??;
+            let state = &full["state"];
+            if is_over(state) {
+                return Err("The game is already over".to_string());
+            }
+            let remote = side_of(&full, &account)?.flip();
+            let mut follower = Follower {
+                game: new_game(&full)?,
+                remote,
+                draw_offered: false,
+            };
+            follower.update(state)?;
+            let clocks = full["clock"]["initial"]
+                .as_i64()
+                .map(|initial| SavedClocks {
+                    minutes: (initial / 60_000) as u32,
+                    increment: (full["clock"]["increment"].as_i64().unwrap_or(0) / 1000) as u32,
+                    remaining: [Player::First, Player::Second]
+                        .map(|player| time_left(state, player).unwrap_or(initial)),
+                });
+            let joined = Joined {
+                game: follower.game.clone(),
+                remote,
+                clocks,
+                connection: LichessConnection {
+                    client: client.clone(),
+                    id: id.clone(),
+                    follower: Arc::new(Mutex::new(follower)),
+                    incoming,
+                    closed: closed.clone(),
+                },
+            };
+            Ok((joined, events))
+        })();
+        let (joined, events) = match opened {
+            Ok(opened) => opened,
+            Err(err) => {
+                let _ = sender.send(Err(err));
+                return;
+            }
+        };
+        let follower = joined.connection.follower.clone();
+        if sender.send(Ok(joined)).is_err() {
+            return;
+        }
+        for event in events {
+            if closed.load(Ordering::Relaxed) {
+                return;
+            }
+            let update = event.and_then(|event| match event["type"].as_str() {
+                Some("gameState") => follower.lock().unwrap().update(&event),
+                _ => Ok(vec![]),
+            });
+            let update = match update {
+                Ok(update) => update,
+                Err(err) => {
+                    let _ = messages.send(Err(err));
+                    return;
+                }
+            };
+            for message in update {
+                if messages.send(Ok(message)).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    receiver
+}
+
+// One of the account's games in progress, as listed by Lichess
+struct Playing {
+    id: String,
+    opponent: String,
+    variant: String,
+    my_turn: bool,
+}
+
+fn playing(client: &Client) -> Result<Vec<Playing>, String> {
+    let games = client.get("/api/account/playing")?;
+    Ok(games["nowPlaying"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|game| {
+            Some(Playing {
+                id: game["gameId"].as_str()?.to_string(),
+                opponent: game["opponent"]["username"]
+                    .as_str()
+                    .unwrap_or("Anonymous")
+                    .to_string(),
+                variant: game["variant"]["name"]
+                    .as_str()
+                    .unwrap_or("Standard")
+                    .to_string(),
+                my_turn: game["isMyTurn"].as_bool().unwrap_or(false),
+            })
+        })
+        .collect())
+}
+
+// The player's account and its games in progress
+struct Account {
+    id: String,
+    games: Vec<Playing>,
+}
+
+// The account, fetched in the background
+fn fetch(client: Client) -> Receiver<Result<Account, String>> {
+    let (sender, receiver) = channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(client.account().and_then(|id| {
+            let games = playing(&client)?;
+            Ok(Account { id, games })
+        }));
+    });
+    receiver
+}
+
+// Choosing one of the player's Lichess games to play here
+pub struct State {
+    token: String,
+    account: Option<Account>,
+    fetching: Option<Receiver<Result<Account, String>>>,
+    joining: Option<Receiver<Result<Joined, String>>>,
+    error: Option<String>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            token: std::env::var(TOKEN_VARIABLE).unwrap_or_default(),
+            account: None,
+            fetching: None,
+            joining: None,
+            error: None,
+        }
+    }
+}
+
+impl State {
+    fn refresh(&mut self) {
+        self.error = None;
+        self.fetching = Some(fetch(Client::new(&self.token)));
+    }
+
+    fn poll(&mut self, ctx: &egui::Context) -> Option<Box<dyn AppState>> {
+        if let Some(fetched) = self
+            .fetching
+            .as_ref()
+            .and_then(|fetching| fetching.try_recv().ok())
+        {
+            self.fetching = None;
+            match fetched {
+                Ok(account) => self.account = Some(account),
+                Err(err) => self.error = Some(err),
+            }
+        }
+        let joined = self.joining.as_ref()?.try_recv().ok()?;
+        self.joining = None;
+        match joined {
+            Ok(joined) => Some(Box::new(
+                crate::grid::ui::State::<Chess, NullAi<Chess>>::new(ctx, joined.game).with_remote(
+                    Box::new(joined.connection),
+                    joined.remote,
+                    false,
+                    joined.clocks.as_ref(),
+                ),
+            )),
+            Err(err) => {
+                self.error = Some(err);
+                None
+            }
+        }
+    }
+}
+
+impl AppState for State {
+    fn update(
+        &mut self,
+        ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+        _appearance: &Appearance,
+    ) -> Option<Box<dyn AppState>> {
+        let mut change_state = self.poll(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Lichess");
+            ui.horizontal(|ui| {
+                ui.label("Token");
+                ui.add(egui::TextEdit::singleline(&mut self.token).password(true))
+                    .on_hover_text(format!(
+                        "A personal access token with the board:play scope, made in your Lichess preferences. \
+Read from {TOKEN_VARIABLE} if that's set, and never saved."
+                    ));
+                if ui
+                    .add_enabled(
+                        self.fetching.is_none() && !self.token.trim().is_empty(),
+                        egui::Button::new("Show my games"),
+                    )
+                    .clicked()
+                {
+                    self.refresh();
+                }
+            });
+            if let Some(account) = &self.account {
+                ui.label(format!("Games in progress for {}", account.id));
+                if account.games.is_empty() {
+                    ui.label("None. Start one on Lichess, then show your games again.");
+                }
+                for game in &account.games {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{} against {}{}",
+                            game.variant,
+                            game.opponent,
+                            if game.my_turn { ", your move" } else { "" }
+                        ));
+                        if ui
+                            .add_enabled(self.joining.is_none(), egui::Button::new("Play"))
+                            .clicked()
+                        {
+                            self.error = None;
+                            self.joining = Some(join(
+                                Client::new(&self.token),
+                                account.id.clone(),
+                                game.id.clone(),
+                            ));
+                        }
+                    });
+                }
+            }
+            if self.fetching.is_some() || self.joining.is_some() {
+                ui.spinner();
+            }
+            if let Some(error) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            if ui.button("Back to Menu").clicked() {
+                change_state = Some(Box::new(crate::menu::State::default()));
+            }
+        });
+
+        if self.fetching.is_some() || self.joining.is_some() {
+            ctx.request_repaint();
+        }
+
+        change_state
+    }
+}
+
+// How many games the bot plays at once, turning other challenges away until one finishes
+const BOT_GAMES: usize = 1;
+
+// Think about `game` for the time its clocks allow, and return the move to play
+fn think(
+    ai: &mut AlphaBeta<Chess>,
+    events: &Receiver<SearchEvent<Chess>>,
+    game: &Game<Chess>,
+    clock: Option<Clock>,
+) -> Option<crate::grid::chess::Move> {
+    while events.try_recv().is_ok() {}
+    ai.set_game(game.clone());
+    let time = clock.map_or(chrono::TimeDelta::seconds(10), |clock| {
+        TimeManager::default().allocate(&clock)
+    });
+    ai.stop_thinking_after(time);
+    loop {
+        ai.think(chrono::TimeDelta::milliseconds(10));
+        if events
+            .try_iter()
+            .any(|event| matches!(event, SearchEvent::Finished))
+        {
+            return ai.best_move();
+        }
+    }
+}
+
+// Play game `id` as the bot until it ends
+fn play_bot_game(client: &Client, account: &str, id: &str) -> Result<(), String> {
+    let mut events = client.stream(&format!("/api/bot/game/stream/{id}"))?;
+    let full = events
+        .next()
+        .ok_or_else(|| "Lichess sent nothing about the game".to_string())??;
+    let me = side_of(&full, account)?;
+    let start = new_game(&full)?;
+    let increment = full["clock"]["increment"].as_i64();
+    let mut ai = AlphaBeta::new();
+    let search_events = ai.subscribe();
+
+    let mut state = full["state"].clone();
+    loop {
+        if is_over(&state) {
+            return Ok(());
+        }
+        let mut game = start.clone();
+        for text in state["moves"]
+            .as_str()
+            .unwrap_or_default()
+            .split_whitespace()
+        {
+            let mv = game
+                .logic()
+                .move_from_uci(game.state(), text)
+                .ok_or_else(|| format!("Lichess sent a move which isn't legal here: {text}"))?;
+            game.make_move(mv);
+        }
+        if !game.is_finished() && game.turn() == me {
+            let clock = time_left(&state, me).map(|remaining| {
+                Clock::new(TimeControl {
+                    base: chrono::TimeDelta::milliseconds(remaining),
+                    increment: chrono::TimeDelta::milliseconds(increment.unwrap_or(0)),
+                    moves: None,
+                })
+            });
+            if let Some(mv) = think(&mut ai, &search_events, &game, clock) {
+                let uci = game.logic().uci_move(&mv);
+                client.post(&format!("/api/bot/game/{id}/move/{uci}"))?;
+            }
+        }
+        if offers_draw(&state, me.flip()) {
+            let answer = if ai.accepts_draw() { "yes" } else { "no" };
+            client.post(&format!("/api/bot/game/{id}/draw/{answer}"))?;
+        }
+
+        // Wait for the next change to the game, skipping chat and the like
+        state = loop {
+            let event = events
+                .next()
+                .ok_or_else(|| "The game's stream ended".to_string())??;
+            if event["type"].as_str() == Some("gameState") {
+                break event;
+            }
+        };
+    }
+}
+
+/*
+Play on Lichess as a bot account, with the token in the LICHESS_TOKEN environment variable.
+Challenges in the variants played here are accepted as long as fewer than `BOT_GAMES` games are going on, and each game is played by its own search, thinking for as long as its clock allows.
+ */
+pub fn run_bot() -> Result<(), String> {
+    let token = std::env::var(TOKEN_VARIABLE)
+        .map_err(|_| format!("Set {TOKEN_VARIABLE} to the bot account's token"))?;
+    let client = Client::new(&token);
+    let account = client.account()?;
+    println!("Playing on Lichess as {account}");
+    let games = Arc::new(Mutex::new(0usize));
+    for event in client.stream("/api/stream/event")? {
+        let event = event?;
+        match event["type"].as_str() {
+            Some("challenge") => {
+                let challenge = &event["challenge"];
+                let Some(id) = challenge["id"].as_str() else {
+                    continue;
+                };
+                // Lichess also tells the challenger about their own challenges
+                if challenge["challenger"]["id"].as_str() == Some(account.as_str()) {
+                    continue;
+                }
+                let variant = challenge["variant"]["key"].as_str().unwrap_or("standard");
+                let fen = challenge["initialFen"].as_str().unwrap_or("startpos");
+                let reason = if *games.lock().unwrap() >= BOT_GAMES {
+                    Some("later")
+                } else if logic_for(variant, fen).is_err() {
+                    Some("variant")
+                } else {
+                    None
+                };
+                let answered = match reason {
+                    Some(reason) => client.post_form(
+                        &format!("/api/challenge/{id}/decline"),
+                        &[("reason", reason)],
+                    ),
+                    None => client.post(&format!("/api/challenge/{id}/accept")),
+                };
+                if let Err(err) = answered {
+                    println!("Couldn't answer challenge {id}: {err}");
+                }
+            }
+            Some("gameStart") => {
+                let Some(id) = event["game"]["gameId"].as_str().map(str::to_string) else {
+                    continue;
+                };
+                println!("Playing game {id}");
+                *games.lock().unwrap() += 1;
+                let (client, account, games) = (client.clone(), account.clone(), games.clone());
+                std::thread::spawn(move || {
+                    match play_bot_game(&client, &account, &id) {
+                        Ok(()) => println!("Game {id} is over"),
+                        Err(err) => println!("Game {id} stopped: {err}"),
+                    }
+                    *games.lock().unwrap() -= 1;
+                });
+            }
+            _ => {}
+        }
+    }
+    Err("Lost the connection to Lichess".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follow_game() {
+        let full: Value = serde_json::from_str(
+            r#"{"type":"gameFull","id":"abc","variant":{"key":"chess960"},"initialFen":"bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1",
+            "white":{"id":"me"},"black":{"id":"them"},"state":{"moves":"","wtime":60000,"btime":60000,"status":"started"}}"#,
+        )
+        .unwrap();
+        assert_eq!(side_of(&full, "me"), Ok(Player::First));
+        let mut follower = Follower {
+            game: new_game(&full).unwrap(),
+            remote: Player::Second,
+            draw_offered: false,
+        };
+        assert!(matches!(follower.game.logic(), Chess::Fischer960(_)));
+        assert_eq!(follower.update(&full["state"]), Ok(vec![]));
+
+        // Only the other player's moves and offers are passed on
+        let state: Value = serde_json::from_str(
+            r#"{"moves":"e2e4 e7e5","wtime":59000,"btime":58000,"status":"started","bdraw":true}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            follower.update(&state),
+            Ok(vec![
+                Message::Move {
+                    mv: "e7e5".to_string(),
+                    remaining: Some(58000),
+                },
+                Message::OfferDraw,
+            ])
+        );
+        let state: Value =
+            serde_json::from_str(r#"{"moves":"e2e4 e7e5","status":"resign","winner":"white"}"#)
+                .unwrap();
+        assert_eq!(follower.update(&state), Ok(vec![Message::Resign]));
+
+        // Promotions keep their piece both ways
+        let full: Value = serde_json::from_str(
+            r#"{"type":"gameFull","id":"def","variant":{"key":"fromPosition"},"initialFen":"7k/1P6/8/8/8/8/6p1/K7 w - - 0 1",
+            "white":{"id":"me"},"black":{"id":"them"},"state":{"moves":"","status":"started"}}"#,
+        )
+        .unwrap();
+        let mut follower = Follower {
+            game: new_game(&full).unwrap(),
+            remote: Player::Second,
+            draw_offered: false,
+        };
+        assert_eq!(follower.uci_move("b7b8n"), Ok("b7b8n".to_string()));
+        let state: Value =
+            serde_json::from_str(r#"{"moves":"b7b8n g2g1n","status":"started"}"#).unwrap();
+        assert_eq!(
+            follower.update(&state),
+            Ok(vec![Message::Move {
+                mv: "g2g1n".to_string(),
+                remaining: None,
+            }])
+        );
+        assert_eq!(follower.game.move_history()[0].uci(), "b7b8n");
+
+        assert!(matches!(
+            logic_for("standard", "startpos"),
+            Ok(Chess::Standard)
+        ));
+        assert!(logic_for("atomic", "startpos").is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use std::sync::LazyLock;
+
+use crate::grid::chess::bitboard;
+
+/*
+Magic bitboards for rook and bishop moves.
+For each square, the blockers on the squares a slider could pass through are multiplied by a magic number,
+and the top bits of the product index a table of the squares it attacks.
+The magic numbers are found by trial at startup.
+ */
+
+const ROOK_DIRS: [(isize, isize); 4] = [(0, 1), (-1, 0), (0, -1), (1, 0)];
+const BISHOP_DIRS: [(isize, isize); 4] = [(1, 1), (-1, 1), (1, -1), (-1, -1)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slider {
+    Rook,
+    Bishop,
+}
+
+impl Slider {
+    fn dirs(self) -> [(isize, isize); 4] {
+        match self {
+            Self::Rook => ROOK_DIRS,
+            Self::Bishop => BISHOP_DIRS,
+        }
+    }
+
+    // The slider whose directions are exactly `offsets`, if any
+    pub fn from_offsets(offsets: &[(isize, isize)]) -> Option<Self> {
+        [Self::Rook, Self::Bishop].into_iter().find(|slider| {
+            let dirs = slider.dirs();
+            offsets.len() == dirs.len() && dirs.iter().all(|dir| offsets.contains(dir))
+        })
+    }
+
+    // The squares attacked from `square`, including the first blocker in each direction
+    pub fn attacks(self, square: usize, blockers: u64) -> u64 {
+        let magics = match self {
+            Self::Rook => &*ROOK_MAGICS,
+            Self::Bishop => &*BISHOP_MAGICS,
+        };
+        let magic = &magics[square];
+        let idx = ((blockers & magic.mask).wrapping_mul(magic.magic) >> magic.shift) as usize;
+        magic.attacks[idx]
+    }
+}
+
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    attacks: Vec<u64>,
+}
+
+// Attacks found by walking each ray
+fn slow_attacks(dirs: [(isize, isize); 4], square: usize, blockers: u64) -> u64 {
+    let mut attacks = 0;
+    for dir in dirs {
+        let mut current = square;
+        while let Some(next) = bitboard::step(current, dir) {
+            attacks |= 1 << next;
+            if blockers & (1 << next) != 0 {
+                break;
+            }
+            current = next;
+        }
+    }
+    attacks
+}
+
+// The squares whose blockers matter: each ray without its last square, since nothing lies beyond it
+fn relevant_mask(dirs: [(isize, isize); 4], square: usize) -> u64 {
+    let mut mask = 0;
+    for dir in dirs {
+        let mut current = square;
+        while let Some(next) = bitboard::step(current, dir) {
+            if bitboard::step(next, dir).is_none() {
+                break;
+            }
+            mask |= 1 << next;
+            current = next;
+        }
+    }
+    mask
+}
+
+fn find_magics(slider: Slider) -> Vec<Magic> {
+    let dirs = slider.dirs();
+    let mut rng = 0x9E3779B97F4A7C15u64;
+    let mut random = move || {
+        // xorshift64*
+        rng ^= rng >> 12;
+        rng ^= rng << 25;
+        rng ^= rng >> 27;
+        rng.wrapping_mul(0x2545F4914F6CDD1D)
+    };
+    (0..64)
+        .map(|square| {
+            let mask = relevant_mask(dirs, square);
+            let bits = mask.count_ones();
+            let shift = 64 - bits;
+            // Every subset of the mask, by the carry-rippler trick
+            let mut subsets = vec![];
+            let mut subset = 0u64;
+            loop {
+                subsets.push((subset, slow_attacks(dirs, square, subset)));
+                subset = subset.wrapping_sub(mask) & mask;
+                if subset == 0 {
+                    break;
+                }
+            }
+            let mut attacks = vec![0u64; 1 << bits];
+            let mut filled = vec![false; 1 << bits];
+            loop {
+                // Magic numbers with few bits set work best
+                let magic = random() & random() & random();
+                if (mask.wrapping_mul(magic) >> 56).count_ones() < 6 {
+                    continue;
+                }
+                filled.iter_mut().for_each(|f| *f = false);
+                let ok = subsets.iter().all(|(subset, subset_attacks)| {
+                    let idx = (subset.wrapping_mul(magic) >> shift) as usize;
+                    if !filled[idx] {
+                        filled[idx] = true;
+                        attacks[idx] = *subset_attacks;
+                        true
+                    } else {
+                        attacks[idx] == *subset_attacks
+                    }
+                });
+                if ok {
+                    return Magic {
+                        mask,
+                        magic,
+                        shift,
+                        attacks,
+                    };
+                }
+            }
+        })
+        .collect()
+}
+
+static ROOK_MAGICS: LazyLock<Vec<Magic>> = LazyLock::new(|| find_magics(Slider::Rook));
+static BISHOP_MAGICS: LazyLock<Vec<Magic>> = LazyLock::new(|| find_magics(Slider::Bishop));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magics_match_rays() {
+        let mut rng = 12345u64;
+        for _ in 0..1000 {
+            rng = rng
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let blockers = rng & (rng >> 7) & (rng << 3);
+            let square = (rng >> 58) as usize;
+            for slider in [Slider::Rook, Slider::Bishop] {
+                assert_eq!(
+                    slider.attacks(square, blockers),
+                    slow_attacks(slider.dirs(), square, blockers)
+                );
+            }
+        }
+    }
+}
@@ -1,14 +1,27 @@
 import random
 
+
+def value():
+    return f"0x{random.getrandbits(64):016X}u64"
+
+
 with open("table_values.rs", "w") as f:
-    f.write("[\n")
+    f.write(f"side_to_move: {value()},\n")
+    f.write("en_croissant_file: [\n")
+    for _ in range(8):
+        f.write(f"    {value()},\n")
+    f.write("],\n")
+    f.write("checks: [\n")
+    for _ in range(2):
+        f.write("    [" + ", ".join(value() for _ in range(4)) + "],\n")
+    f.write("],\n")
+    f.write("board_content: [\n")
     for _ in range(8):
         f.write("    [\n")
         for _ in range(8):
             f.write("    [\n")
             for _ in range(256):
-                val = random.getrandbits(64)
-                f.write(f"        0x{val:016X}u64,\n")
+                f.write(f"        {value()},\n")
             f.write("    ],\n")
         f.write("    ],\n")
-    f.write("]\n")
\ No newline at end of file
+    f.write("]\n")
@@ -1,8 +1,11 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc};
 
 use crate::{
-    game::{AbsScore, GameLogic, HeuristicScore, Neutral, NoAlloc, Player, State, StateIdent},
-    grid::GridGame,
+    ai::evaluator::Evaluator,
+    game::{
+        AbsScore, Game, GameLogic, HeuristicScore, Neutral, NoAlloc, Player, State, StateIdent,
+    },
+    grid::{GridGame, Piece, notation::NotationStyle},
 };
 
 #[derive(Debug, Clone)]
@@ -10,12 +13,229 @@ pub enum Chess {
     Standard,
     Berolina,
     Grasshopper,
+    // A king reaching one of the four centre squares wins
+    KingOfTheHill,
+    // Giving check three times wins
+    ThreeCheck,
+    // White has 36 pawns and no king, and must checkmate. Black must capture every pawn
+    Horde,
+    // Fischer random chess, identified by its starting position number 0..960
+    Fischer960(u16),
+    // Gardner's minichess on a 5x5 board, without castling or pawn double steps
+    Gardner,
+    // Standard rules from a custom starting position
+    Custom(Arc<positions::StartPosition>),
 }
 
+// The starting position number of standard chess in Fischer random numbering
+pub const FISCHER960_STANDARD: u16 = 518;
+
 impl Chess {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Standard => "Standard",
+            Self::Berolina => "Berolina",
+            Self::Grasshopper => "Grasshopper",
+            Self::KingOfTheHill => "King of the Hill",
+            Self::ThreeCheck => "Three-check",
+            Self::Horde => "Horde",
+            Self::Fischer960(_) => "Chess960",
+            Self::Gardner => "Gardner",
+            Self::Custom(_) => "From Position",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::Standard,
+            Self::Berolina,
+            Self::Grasshopper,
+            Self::KingOfTheHill,
+            Self::ThreeCheck,
+            Self::Horde,
+            Self::Fischer960(FISCHER960_STANDARD),
+            Self::Gardner,
+        ]
+        .into_iter()
+        .find(|variant| variant.name().eq_ignore_ascii_case(name))
+    }
+
+    /*
+    Smaller boards are anchored to the bottom left of the 8x8 mailbox,
+    so a1 is the same square for every variant and square names don't change.
+     */
+    pub fn rows(&self) -> usize {
+        match self {
+            Self::Gardner => 5,
+            _ => 8,
+        }
+    }
+
+    pub fn cols(&self) -> usize {
+        match self {
+            Self::Gardner => 5,
+            _ => 8,
+        }
+    }
+
+    // The mailbox row of black's back rank
+    fn top_row(&self) -> usize {
+        8 - self.rows()
+    }
+
+    // Every square on the board, from black's back rank down
+    fn squares(&self) -> impl Iterator<Item = Pos> + use<> {
+        let cols = self.cols();
+        (self.top_row()..8).flat_map(move |row| (0..cols).map(move |col| Pos::from_grid(row, col)))
+    }
+
+    // The square drawn at (row, col) of the grid
+    fn grid_to_pos(&self, row: usize, col: usize) -> Pos {
+        Pos::from_grid(row + self.top_row(), col)
+    }
+
+    fn pos_to_grid(&self, pos: Pos) -> Option<(usize, usize)> {
+        let (row, col) = pos.to_grid()?;
+        (row >= self.top_row() && col < self.cols()).then(|| (row - self.top_row(), col))
+    }
+
+    fn is_end_row(&self, pos: Pos) -> bool {
+        pos.to_grid()
+            .is_some_and(|(row, _)| row == self.top_row() || row == 7)
+    }
+
+    // Pawns may step two squares from their starting rows, except in minichess
+    fn is_pawn_row(&self, pos: Pos) -> bool {
+        !matches!(self, Self::Gardner)
+            && pos
+                .to_grid()
+                .is_some_and(|(row, _)| row == self.top_row() + 1 || row == 6)
+    }
+
+    /*
+    Black's back rank, from the a-file to the last file.
+    Fischer random positions are numbered as in Scharnagl's scheme, where 518 is the standard setup.
+     */
+    pub fn back_rank(&self) -> Vec<char> {
+        match self {
+            Self::Standard
+            | Self::Berolina
+            | Self::Grasshopper
+            | Self::KingOfTheHill
+            | Self::ThreeCheck
+            | Self::Horde
+            // Custom positions castle from the standard squares
+            | Self::Custom(_) => vec!['R', 'N', 'B', 'Q', 'K', 'B', 'N', 'R'],
+            Self::Gardner => vec!['R', 'N', 'B', 'Q', 'K'],
+            Self::Fischer960(num) => {
+                let mut rank = [' '; 8];
+                let num = *num as usize % 960;
+                rank[2 * (num % 4) + 1] = 'B';
+                let num = num / 4;
+                rank[2 * (num % 4)] = 'B';
+                let num = num / 4;
+                let mut place = |piece: char, mut n: usize| {
+                    for square in rank.iter_mut() {
+                        if *square == ' ' {
+                            if n == 0 {
+                                *square = piece;
+                                return;
+                            }
+                            n -= 1;
+                        }
+                    }
+                    unreachable!()
+                };
+                place('Q', num % 6);
+                let (first, second) = [
+                    (0, 1),
+                    (0, 2),
+                    (0, 3),
+                    (0, 4),
+                    (1, 2),
+                    (1, 3),
+                    (1, 4),
+                    (2, 3),
+                    (2, 4),
+                    (3, 4),
+                ][num / 6];
+                // Place the second knight first so the index of the first is unaffected
+                place('N', second);
+                place('N', first);
+                for piece in ['R', 'K', 'R'] {
+                    place(piece, 0);
+                }
+                rank.to_vec()
+            }
+        }
+    }
+
+    // The Fischer random position with the given back rank e.g. "RNBQKBNR"
+    pub fn fischer960_from_back_rank(back_rank: &str) -> Option<Self> {
+        let back_rank = back_rank.to_ascii_uppercase().chars().collect::<Vec<_>>();
+        (0..960)
+            .map(Self::Fischer960)
+            .find(|variant| variant.back_rank().as_slice() == back_rank.as_slice())
+    }
+
+    // The columns of the left rook, the king and the right rook at the start of the game, if castling is allowed
+    fn castling_cols(&self) -> Option<(usize, usize, usize)> {
+        if matches!(self, Self::Gardner) {
+            return None;
+        }
+        let back_rank = self.back_rank();
+        let king = back_rank.iter().position(|c| *c == 'K').unwrap();
+        let left = back_rank.iter().position(|c| *c == 'R').unwrap();
+        let right = back_rank.iter().rposition(|c| *c == 'R').unwrap();
+        debug_assert!(left < king && king < right);
+        Some((left, king, right))
+    }
+
+    fn is_hill(pos: Pos) -> bool {
+        matches!(pos.to_grid(), Some((3 | 4, 3 | 4)))
+    }
+
+    // The player whose king has reached the centre in King of the Hill
+    fn hill_winner(&self, board: &BoardState) -> Option<Player> {
+        if !matches!(self, Self::KingOfTheHill) {
+            None
+        } else if board.white_king.is_some_and(Self::is_hill) {
+            Some(Player::First)
+        } else if board.black_king.is_some_and(Self::is_hill) {
+            Some(Player::Second)
+        } else {
+            None
+        }
+    }
+
+    // The player who has given check three times in Three-check
+    fn three_check_winner(&self, board: &BoardState) -> Option<Player> {
+        if !matches!(self, Self::ThreeCheck) {
+            None
+        } else if board.white_checks >= 3 {
+            Some(Player::First)
+        } else if board.black_checks >= 3 {
+            Some(Player::Second)
+        } else {
+            None
+        }
+    }
+
+    // Black wins Horde by capturing all of white's pieces
+    fn horde_captured(&self, board: &BoardState) -> bool {
+        matches!(self, Self::Horde) && board.board.colours[colour_idx(Player::First)] == 0
+    }
+
     fn possible_promotions(&self) -> Vec<u8> {
         match self {
-            Self::Standard | Self::Berolina => {
+            Self::Standard
+            | Self::Berolina
+            | Self::KingOfTheHill
+            | Self::ThreeCheck
+            | Self::Horde
+            | Self::Fischer960(_)
+            | Self::Gardner
+            | Self::Custom(_) => {
                 vec![square::QUEEN, square::BISHOP, square::KNIGHT, square::ROOK]
             }
             Self::Grasshopper => vec![
@@ -29,8 +249,32 @@ impl Chess {
     }
 }
 
+mod bitboard;
 mod constants;
+pub mod editor;
+mod magic;
+pub mod nnue;
 use constants::*;
+use params::{EvalFeatures, EvalParams};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lichess;
+pub mod openings;
+pub mod params;
+pub mod perft;
+pub mod pgn;
+mod pieces;
+pub mod positions;
+pub mod puzzles;
+mod san;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sprt;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod texel;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tournament;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod uci;
 
 mod square {
     use crate::{game::Player, grid::Piece};
@@ -43,6 +287,7 @@ mod square {
     pub const QUEEN: u8 = 6;
     pub const KING: u8 = 7;
     pub const GRASSHOPPER: u8 = 8;
+    pub const NUM_PIECES: usize = 9;
     const PIECE_MASK: u8 = 31;
     const OWNER: u8 = 32;
     const OCCUPIED: u8 = 64;
@@ -164,7 +409,17 @@ mod square {
 
         pub fn from_piece_raw(turn: Player, piece_raw: u8) -> Self {
             debug_assert!(
-                [PAWN, ROOK, KNIGHT, BISHOP, QUEEN, KING, GRASSHOPPER].contains(&piece_raw)
+                [
+                    PAWN,
+                    BEROLINA_PAWN,
+                    ROOK,
+                    KNIGHT,
+                    BISHOP,
+                    QUEEN,
+                    KING,
+                    GRASSHOPPER
+                ]
+                .contains(&piece_raw)
             );
             let mut state = piece_raw | OCCUPIED;
             if turn == Player::Second {
@@ -229,6 +484,8 @@ mod square {
     }
 }
 use egui::{Color32, Painter, Rect, Stroke};
+use magic::Slider;
+use pieces::{Attacks, Movement};
 use square::SquareContents;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -272,12 +529,25 @@ impl Pos {
         }
     }
 
-    pub fn is_end_row(&self) -> bool {
-        (21 <= self.idx && self.idx <= 28) || (91 <= self.idx && self.idx <= 98)
+    // e.g. "e4"
+    pub fn algebraic(&self) -> String {
+        match self.to_grid() {
+            Some((r, c)) => format!("{}{}", (b'a' + c as u8) as char, 8 - r),
+            None => "??".to_string(),
+        }
     }
 
-    pub fn is_pawn_row(&self) -> bool {
-        (31 <= self.idx && self.idx <= 38) || (81 <= self.idx && self.idx <= 88)
+    pub fn from_algebraic(s: &str) -> Option<Self> {
+        let mut chars = s.chars();
+        let file = chars.next()?;
+        let rank = chars.next()?;
+        if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+            return None;
+        }
+        Some(Self::from_grid(
+            8 - (rank as usize - '0' as usize),
+            file as usize - 'a' as usize,
+        ))
     }
 }
 
@@ -336,18 +606,26 @@ impl std::ops::Sub<DPos> for DPos {
 
 #[derive(Debug, Clone)]
 struct BoardContent {
+    // The squares of each player, and of each kind of piece indexed by `piece_raw`
+    colours: [u64; 2],
+    kinds: [u64; square::NUM_PIECES],
+    // Squares of the 8x8 grid which the variant doesn't use
+    outside: u64,
     /*
     A 12x10 grid. The outer squares are for edge-detection.
     The inner 8x8 grid is the standard chess board.
+    Only kept to cross-check the bitboards.
      */
-    pieces: [SquareContents; 10 * 12],
+    #[cfg(debug_assertions)]
+    mailbox: [SquareContents; 10 * 12],
     hash_bits: u64,
     num_repetitions: usize,
 }
 
 impl PartialEq for BoardContent {
     fn eq(&self, other: &Self) -> bool {
-        self.pieces == other.pieces
+        self.colours == other.colours
+            && self.kinds == other.kinds
             && self.hash_bits == other.hash_bits
             && (self.num_repetitions >= 2) == (other.num_repetitions >= 2)
     }
@@ -355,11 +633,25 @@ impl PartialEq for BoardContent {
 
 impl Eq for BoardContent {}
 
+fn colour_idx(player: Player) -> usize {
+    match player {
+        Player::First => 0,
+        Player::Second => 1,
+    }
+}
+
 impl BoardContent {
-    fn new() -> Self {
+    // An empty board, with the parts of the mailbox that `logic` doesn't use marked as outside
+    fn new(logic: &Chess) -> Self {
         let mut s = Self {
-            pieces: std::array::from_fn(|idx| {
-                if (Pos { idx }).to_grid().is_none() {
+            colours: [0; 2],
+            kinds: [0; square::NUM_PIECES],
+            outside: bitboard::squares(u64::MAX)
+                .filter(|square| logic.pos_to_grid(bitboard::pos(*square)).is_none())
+                .fold(0, |outside, square| outside | (1 << square)),
+            #[cfg(debug_assertions)]
+            mailbox: std::array::from_fn(|idx| {
+                if logic.pos_to_grid(Pos { idx }).is_none() {
                     SquareContents::outside()
                 } else {
                     SquareContents::empty()
@@ -372,12 +664,34 @@ impl BoardContent {
         s
     }
 
+    fn occupied(&self) -> u64 {
+        self.colours[0] | self.colours[1]
+    }
+
+    fn pieces(&self, player: Player, piece_raw: u8) -> u64 {
+        self.colours[colour_idx(player)] & self.kinds[piece_raw as usize]
+    }
+
+    // The squares holding any of the kinds of piece set in `mask`
+    fn kinds_in(&self, mask: u32) -> u64 {
+        let mut bits = 0;
+        for (piece_raw, kind) in self.kinds.iter().enumerate() {
+            if mask & (1 << piece_raw) != 0 {
+                bits |= kind;
+            }
+        }
+        bits
+    }
+
     fn compute_hash64(&self) -> u64 {
         let mut hash_bits = 0u64;
         for row in 0..8usize {
             for col in 0..8usize {
-                hash_bits ^= ZOBRIST_HASH_CONSTANTS.board_content[row][col]
-                    [self.get(Pos::from_grid(row, col)).state as usize];
+                let content = self.get(Pos::from_grid(row, col));
+                if !content.is_outside() {
+                    hash_bits ^=
+                        ZOBRIST_HASH_CONSTANTS.board_content[row][col][content.state as usize];
+                }
             }
         }
         hash_bits
@@ -391,12 +705,41 @@ impl BoardContent {
         self.hash_bits ^= ZOBRIST_HASH_CONSTANTS.board_content[row][col]
             [self.get(Pos::from_grid(row, col)).state as usize];
         self.hash_bits ^= ZOBRIST_HASH_CONSTANTS.board_content[row][col][content.state as usize];
-        self.pieces[pos.idx] = content;
+        let bit = bitboard::bit(pos);
+        let prev = self.get(pos);
+        if !prev.is_empty() {
+            self.colours[colour_idx(prev.owner().unwrap())] &= !bit;
+            self.kinds[prev.piece_raw() as usize] &= !bit;
+        }
+        if !content.is_empty() {
+            self.colours[colour_idx(content.owner().unwrap())] |= bit;
+            self.kinds[content.piece_raw() as usize] |= bit;
+        }
+        #[cfg(debug_assertions)]
+        {
+            self.mailbox[pos.idx] = content;
+        }
         debug_assert_eq!(self.hash_bits, self.compute_hash64());
     }
 
     fn get(&self, pos: Pos) -> SquareContents {
-        self.pieces[pos.idx]
+        let bit = bitboard::bit(pos);
+        let content = if bit == 0 || self.outside & bit != 0 {
+            SquareContents::outside()
+        } else if self.occupied() & bit == 0 {
+            SquareContents::empty()
+        } else {
+            let owner = if self.colours[0] & bit != 0 {
+                Player::First
+            } else {
+                Player::Second
+            };
+            let piece_raw = self.kinds.iter().position(|kind| kind & bit != 0).unwrap();
+            SquareContents::from_piece_raw(owner, piece_raw as u8)
+        };
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(content, self.mailbox[pos.idx]);
+        content
     }
 
     fn hash64(&self) -> u64 {
@@ -508,27 +851,46 @@ pub struct BoardState {
 
     repetitions: BoardRepetitionsState,
 
-    white_king: Pos,
-    black_king: Pos,
+    // None for an army without a king, as in Horde
+    white_king: Option<Pos>,
+    black_king: Option<Pos>,
 
     // bit field of WHITE_CAN_CASTLE_LEFT, WHITE_CAN_CASTLE_RIGHT, BLACK_CAN_CASTLE_LEFT, BLACK_CAN_CASTLE_RIGHT
     castling_rights: castling::Rights,
 
     move_num: usize,
 
+    // Plies since the last capture or pawn move, for the fifty-move rule
+    halfmove_clock: usize,
+
+    // Checks given by each player, for Three-check
+    white_checks: usize,
+    black_checks: usize,
+
     en_croissant_info: Option<EnCroissantInfo>,
+
+    // Zobrist hash of the side to move, castling rights, en croissant file and check counts, on top of the board's own hash
+    hash_bits: u64,
 }
 
 impl State<Chess> for BoardState {
     fn ident(self) -> BoardStateIdent {
         BoardStateIdent {
+            en_croissant_file: self.en_croissant_file(),
             board: self.board,
             castling_rights: self.castling_rights,
             move_num: self.move_num,
-            en_croissant_info: self.en_croissant_info,
+            white_checks: self.white_checks,
+            black_checks: self.black_checks,
+            hash_bits: self.hash_bits,
         }
     }
 
+    fn position_ident(mut self) -> BoardStateIdent {
+        self.board.num_repetitions = 0;
+        self.ident()
+    }
+
     fn set_ignore_repetitions(&mut self, ignore_repetitions: bool) {
         self.repetitions.ignore_repetitions = ignore_repetitions;
     }
@@ -539,28 +901,17 @@ pub struct BoardStateIdent {
     board: BoardContent,
     castling_rights: castling::Rights,
     move_num: usize,
-    en_croissant_info: Option<EnCroissantInfo>,
+    white_checks: usize,
+    black_checks: usize,
+    en_croissant_file: Option<usize>,
+    hash_bits: u64,
 }
 
 impl NoAlloc for BoardStateIdent {}
 
 impl StateIdent<Chess> for BoardStateIdent {
     fn hash64(&self) -> u64 {
-        let mut hash_bits = self.board.hash64();
-        if let Some(EnCroissantInfo {
-            phantom_capture,
-            actual_capture,
-            double_move_num,
-        }) = self.en_croissant_info
-        {
-            hash_bits = hash_bits
-                .wrapping_add(phantom_capture.idx as u64)
-                .wrapping_add(actual_capture.idx as u64)
-                .wrapping_add((double_move_num as u64).rotate_left(24));
-        };
-        hash_bits = hash_bits.wrapping_add((self.move_num as u64) % 2);
-        hash_bits ^= self.castling_rights.hash64();
-        hash_bits
+        self.board.hash64() ^ self.hash_bits
     }
 }
 
@@ -569,26 +920,66 @@ impl PartialEq for BoardStateIdent {
         self.board == other.board
             && self.move_num % 2 == other.move_num % 2
             && self.castling_rights == other.castling_rights
-            && self.en_croissant_info == other.en_croissant_info
+            && self.white_checks == other.white_checks
+            && self.black_checks == other.black_checks
+            && self.en_croissant_file == other.en_croissant_file
     }
 }
 
 impl Eq for BoardStateIdent {}
 
 impl BoardState {
+    // The file on which a pawn can be taken en croissant, if the last move was a double push
+    fn en_croissant_file(&self) -> Option<usize> {
+        self.en_croissant_info
+            .as_ref()
+            .filter(|info| info.double_move_num + 1 == self.move_num)
+            .map(|info| info.phantom_capture.to_grid().unwrap().1)
+    }
+
+    fn en_croissant_hash(&self) -> u64 {
+        self.en_croissant_file()
+            .map_or(0, |col| ZOBRIST_HASH_CONSTANTS.en_croissant_file[col])
+    }
+
+    fn checks_hash(&self) -> u64 {
+        ZOBRIST_HASH_CONSTANTS.checks[0][self.white_checks.min(3)]
+            ^ ZOBRIST_HASH_CONSTANTS.checks[1][self.black_checks.min(3)]
+    }
+
+    // The part of `hash_bits` which doesn't depend on whose turn it is
+    fn rights_hash(&self) -> u64 {
+        self.castling_rights.hash64() ^ self.en_croissant_hash() ^ self.checks_hash()
+    }
+
+    // `hash_bits` computed from scratch, to check the incremental updates
+    fn compute_hash64(&self) -> u64 {
+        let mut hash_bits = self.rights_hash();
+        if self.move_num % 2 == 1 {
+            hash_bits ^= ZOBRIST_HASH_CONSTANTS.side_to_move;
+        }
+        hash_bits
+    }
+
     #[cfg(debug_assertions)]
     fn validate(&self) {
-        let white_king = self.board.get(self.white_king);
-        assert!(!white_king.is_outside());
-        assert!(!white_king.is_empty());
-        assert!(white_king.piece_raw() == square::KING);
-        assert_eq!(white_king.owner(), Some(Player::First));
+        assert_eq!(self.hash_bits, self.compute_hash64());
+
+        if let Some(white_king) = self.white_king {
+            let white_king = self.board.get(white_king);
+            assert!(!white_king.is_outside());
+            assert!(!white_king.is_empty());
+            assert!(white_king.piece_raw() == square::KING);
+            assert_eq!(white_king.owner(), Some(Player::First));
+        }
 
-        let black_king = self.board.get(self.black_king);
-        assert!(!black_king.is_outside());
-        assert!(!black_king.is_empty());
-        assert!(black_king.piece_raw() == square::KING);
-        assert_eq!(black_king.owner(), Some(Player::Second));
+        if let Some(black_king) = self.black_king {
+            let black_king = self.board.get(black_king);
+            assert!(!black_king.is_outside());
+            assert!(!black_king.is_empty());
+            assert!(black_king.piece_raw() == square::KING);
+            assert_eq!(black_king.owner(), Some(Player::Second));
+        }
     }
 
     fn set(&mut self, pos: Pos, content: SquareContents) {
@@ -604,6 +995,7 @@ impl BoardState {
 pub enum Move {
     Teleport {
         prev_castling_rights: castling::Rights,
+        prev_halfmove_clock: usize,
         from: Pos,
         from_content: SquareContents,
         to: Pos,
@@ -612,6 +1004,7 @@ pub enum Move {
         king_move: bool,
     },
     PawnDoublePush {
+        prev_halfmove_clock: usize,
         from: Pos,
         from_content: SquareContents,
         croissant: Pos,
@@ -621,6 +1014,7 @@ pub enum Move {
         to_content: SquareContents,
     },
     PawnEnCroissantCapture {
+        prev_halfmove_clock: usize,
         from: Pos,
         from_content: SquareContents,
         to: Pos,
@@ -629,6 +1023,7 @@ pub enum Move {
         capture_content: SquareContents,
     },
     PromotePawn {
+        prev_halfmove_clock: usize,
         from: Pos,
         from_content: SquareContents,
         to: Pos,
@@ -654,21 +1049,146 @@ impl Display for Move {
             Move::Teleport { from, to, .. }
             | Move::PawnDoublePush { from, to, .. }
             | Move::PawnEnCroissantCapture { from, to, .. }
-            | Move::PromotePawn { from, to, .. }
+            | Move::PromotePawn { from, to, .. } => write!(f, "{from} -> {to}"),
+            // A Fischer random king may castle onto a square it could also step to, so castling has its own name
+            Move::Castle { king_to, .. } => {
+                if king_to.to_grid().unwrap().1 == 6 {
+                    write!(f, "O-O")
+                } else {
+                    write!(f, "O-O-O")
+                }
+            }
+        }
+    }
+}
+
+impl Move {
+    pub fn from(&self) -> Pos {
+        match self {
+            Move::Teleport { from, .. }
+            | Move::PawnDoublePush { from, .. }
+            | Move::PawnEnCroissantCapture { from, .. }
+            | Move::PromotePawn { from, .. }
             | Move::Castle {
-                king_from: from,
-                king_to: to,
+                king_from: from, ..
+            } => *from,
+        }
+    }
+
+    pub fn to(&self) -> Pos {
+        match self {
+            Move::Teleport { to, .. }
+            | Move::PawnDoublePush { to, .. }
+            | Move::PawnEnCroissantCapture { to, .. }
+            | Move::PromotePawn { to, .. }
+            | Move::Castle { king_to: to, .. } => *to,
+        }
+    }
+
+    pub fn is_capture(&self) -> bool {
+        match self {
+            Move::Teleport { capture, .. } => *capture,
+            Move::PawnDoublePush { .. } | Move::Castle { .. } => false,
+            Move::PawnEnCroissantCapture { .. } => true,
+            Move::PromotePawn { to_content, .. } => !to_content.is_empty(),
+        }
+    }
+
+    fn moved_piece_raw(&self) -> u8 {
+        match self {
+            Move::Teleport { from_content, .. }
+            | Move::PawnDoublePush { from_content, .. }
+            | Move::PawnEnCroissantCapture { from_content, .. }
+            | Move::PromotePawn { from_content, .. } => from_content.piece_raw(),
+            Move::Castle { .. } => square::KING,
+        }
+    }
+
+    // Coordinate notation as used by UCI e.g. "e2e4" or "e7e8q"
+    pub fn uci(&self) -> String {
+        let to = match self {
+            /*
+            A Fischer random king which doesn't move two squares castles by "capturing" its rook,
+            since moving to its square could also be a plain king move, or no move at all.
+             */
+            Move::Castle {
+                king_from,
+                king_to,
+                rook_from,
                 ..
-            } => write!(f, "{from} -> {to}"),
+            } if king_from
+                .to_grid()
+                .unwrap()
+                .1
+                .abs_diff(king_to.to_grid().unwrap().1)
+                != 2 =>
+            {
+                *rook_from
+            }
+            _ => self.to(),
+        };
+        let mut uci = format!("{}{}", self.from().algebraic(), to.algebraic());
+        if let Move::PromotePawn {
+            promote_content, ..
+        } = self
+        {
+            uci.push(match promote_content.piece_raw() {
+                square::QUEEN => 'q',
+                square::ROOK => 'r',
+                square::BISHOP => 'b',
+                square::KNIGHT => 'n',
+                square::GRASSHOPPER => 'g',
+                _ => unreachable!(),
+            });
         }
+        uci
     }
 }
 
 impl Chess {
+    // Find the legal move in `board` written as `text` in coordinate notation
+    pub fn move_from_uci(&self, board: &BoardState, text: &str) -> Option<Move> {
+        let text = text.trim().to_lowercase();
+        self.legal_moves::<false>(self.turn(board), &mut board.clone())
+            .into_iter()
+            .find(|mv| mv.uci() == text)
+    }
+
+    /*
+    Neither player can possibly checkmate: bare kings, a single minor piece, or only bishops all on the same colour.
+    Grasshoppers and pawns are always treated as sufficient material.
+     */
+    fn is_insufficient_material(&self, board: &BoardState) -> bool {
+        // A bare king can still walk to the centre, and a lone minor piece can still give checks
+        if matches!(self, Self::KingOfTheHill | Self::ThreeCheck | Self::Horde) {
+            return false;
+        }
+        let mut knights = 0;
+        let mut bishop_colours = [false, false];
+        for pos in self.squares() {
+            let content = board.get(pos);
+            if content.is_empty() {
+                continue;
+            }
+            let (row, col) = pos.to_grid().unwrap();
+            match content.piece_raw() {
+                square::KING => {}
+                square::KNIGHT => knights += 1,
+                square::BISHOP => bishop_colours[(row + col) % 2] = true,
+                _ => return false,
+            }
+        }
+        let minors = knights + bishop_colours.iter().filter(|c| **c).count();
+        // A lone knight, or bishops which all share one colour
+        (knights == 0 && minors <= 1) || (knights == 1 && minors == 1)
+    }
+
     fn is_check(&self, player: Player, board: &BoardState) -> bool {
-        let king_pos = match player {
+        let Some(king_pos) = (match player {
             Player::First => board.white_king,
             Player::Second => board.black_king,
+        }) else {
+            return false;
         };
         !self.attackers(player, board, king_pos).is_empty()
     }
@@ -692,149 +1212,14 @@ impl Chess {
     //     attackers
     // }
 
+    // The pieces of the other player which attack `pos`
     fn attackers(&self, turn: Player, board: &BoardState, pos: Pos) -> Vec<Pos> {
-        let mut attackers = vec![];
-
-        let forward = DPos::from_grid(
-            match turn {
-                Player::First => -1,
-                Player::Second => 1,
-            },
-            0,
-        );
-        const LEFT: DPos = DPos::from_grid(0, -1);
-        const RIGHT: DPos = DPos::from_grid(0, 1);
-
-        // check for knights
-        for other_pos in [
-            pos + DPos::from_grid(1, 2),
-            pos + DPos::from_grid(-1, 2),
-            pos + DPos::from_grid(-2, 1),
-            pos + DPos::from_grid(-2, -1),
-            pos + DPos::from_grid(-1, -2),
-            pos + DPos::from_grid(1, -2),
-            pos + DPos::from_grid(2, -1),
-            pos + DPos::from_grid(2, 1),
-        ] {
-            let other_pos_content = board.get(other_pos);
-            if !other_pos_content.is_outside()
-                && let Some(other_owner) = other_pos_content.owner()
-                && other_owner == turn.flip()
-                && other_pos_content.piece_raw() == square::KNIGHT
-            {
-                attackers.push(other_pos);
-            }
-        }
-
-        // check for kings
-        for other_pos in [
-            pos + DPos::from_grid(0, 1),
-            pos + DPos::from_grid(-1, 1),
-            pos + DPos::from_grid(-1, 0),
-            pos + DPos::from_grid(-1, -1),
-            pos + DPos::from_grid(0, -1),
-            pos + DPos::from_grid(1, -1),
-            pos + DPos::from_grid(1, 0),
-            pos + DPos::from_grid(1, 1),
-        ] {
-            let other_pos_content = board.get(other_pos);
-            if !other_pos_content.is_outside()
-                && let Some(other_owner) = other_pos_content.owner()
-                && other_owner == turn.flip()
-                && other_pos_content.piece_raw() == square::KING
-            {
-                attackers.push(other_pos);
-            }
-        }
-
-        // check for pawns
-        for other_pos in [pos + LEFT + forward, pos + RIGHT + forward] {
-            let other_pos_content = board.get(other_pos);
-            if !other_pos_content.is_outside()
-                && let Some(other_owner) = other_pos_content.owner()
-                && other_owner == turn.flip()
-                && other_pos_content.piece_raw() == square::PAWN
-            {
-                attackers.push(other_pos);
-            }
-        }
-
-        // check for berolina pawns
-        {
-            let other_pos = pos + forward;
-            let other_pos_content = board.get(other_pos);
-            if !other_pos_content.is_outside()
-                && let Some(other_owner) = other_pos_content.owner()
-                && other_owner == turn.flip()
-                && other_pos_content.piece_raw() == square::BEROLINA_PAWN
-            {
-                attackers.push(other_pos);
-            }
-        }
-
-        // check for sliding attacks
-        for (dir, possible_enemies) in [
-            (DPos::from_grid(0, 1), [square::QUEEN, square::ROOK]),
-            (DPos::from_grid(-1, 1), [square::QUEEN, square::BISHOP]),
-            (DPos::from_grid(-1, 0), [square::QUEEN, square::ROOK]),
-            (DPos::from_grid(-1, -1), [square::QUEEN, square::BISHOP]),
-            (DPos::from_grid(0, -1), [square::QUEEN, square::ROOK]),
-            (DPos::from_grid(1, -1), [square::QUEEN, square::BISHOP]),
-            (DPos::from_grid(1, 0), [square::QUEEN, square::ROOK]),
-            (DPos::from_grid(1, 1), [square::QUEEN, square::BISHOP]),
-        ] {
-            let mut other_pos = pos;
-            loop {
-                other_pos = other_pos + dir;
-                let other_pos_content = board.get(other_pos);
-                if other_pos_content.is_outside() {
-                    break;
-                }
-                if let Some(other_owner) = other_pos_content.owner() {
-                    if other_owner == turn {
-                        break;
-                    } else {
-                        if other_pos_content.piece_raw() == possible_enemies[0]
-                            || other_pos_content.piece_raw() == possible_enemies[1]
-                        {
-                            attackers.push(other_pos);
-                        }
-                        break;
-                    }
-                }
-            }
-        }
-
-        // Check for grasshopper attacks
-        for dir in [
-            DPos::from_grid(0, 1),
-            DPos::from_grid(-1, 1),
-            DPos::from_grid(-1, 0),
-            DPos::from_grid(-1, -1),
-            DPos::from_grid(0, -1),
-            DPos::from_grid(1, -1),
-            DPos::from_grid(1, 0),
-            DPos::from_grid(1, 1),
-        ] {
-            let adj_pos = pos + dir;
-            let adj_pos_content = board.get(adj_pos);
-            if !adj_pos_content.is_outside() && !adj_pos_content.is_empty() {
-                let mut slide_pos = adj_pos + dir;
-                loop {
-                    let slide_pos_content = board.get(slide_pos);
-                    if slide_pos_content.is_outside() {
-                        break;
-                    }
-                    if let Some(owner) = slide_pos_content.owner() {
-                        if owner != turn && slide_pos_content.piece_raw() == square::GRASSHOPPER {
-                            attackers.push(slide_pos);
-                        }
-                        break;
-                    }
-                    slide_pos = slide_pos + dir;
-                }
-            }
-        }
+        let Some(square) = bitboard::square(pos) else {
+            return vec![];
+        };
+        let attackers = bitboard::squares(Self::attacks_on(turn.flip(), &board.board, square))
+            .map(bitboard::pos)
+            .collect::<Vec<_>>();
 
         // #[cfg(debug_assertions)]
         // {
@@ -869,6 +1254,111 @@ impl Chess {
         attackers
     }
 
+    // The squares of `by`'s pieces which attack `square`, found by looking back along each way of attacking
+    fn attacks_on(by: Player, content: &BoardContent, square: usize) -> u64 {
+        let attacks = Attacks::of(by);
+        let enemies = content.colours[colour_idx(by)];
+        let blockers = content.occupied() | content.outside;
+        let mut found = 0u64;
+
+        for (piece_raw, from_squares) in &attacks.leaps {
+            found |= from_squares[square] & content.pieces(by, *piece_raw);
+        }
+
+        for (slider, mask) in &attacks.sliders {
+            found |= slider.attacks(square, blockers) & enemies & content.kinds_in(*mask);
+        }
+
+        for (back, mask) in &attacks.rides {
+            if let Some(other) = bitboard::first_blocker(square, *back, blockers) {
+                found |= (1 << other) & enemies & content.kinds_in(*mask);
+            }
+        }
+
+        // A hopper lands just beyond the first piece in its way
+        for (back, mask) in &attacks.hops {
+            if let Some(hurdle) = bitboard::step(square, *back)
+                && content.occupied() & (1 << hurdle) != 0
+                && let Some(other) = bitboard::first_blocker(hurdle, *back, blockers)
+            {
+                found |= (1 << other) & enemies & content.kinds_in(*mask);
+            }
+        }
+
+        found
+    }
+
+    // The material `mv` wins once both sides have made every capture on its square worth making, least valuable piece first
+    fn static_exchange(&self, board: &BoardState, mv: &Move) -> i64 {
+        let value = |piece_raw: u8| pieces::kind(piece_raw).value;
+        let remove = |content: &mut BoardContent, square: usize| {
+            for bits in content.colours.iter_mut().chain(content.kinds.iter_mut()) {
+                *bits &= !(1 << square);
+            }
+        };
+        let (Some(target), Some(from)) = (bitboard::square(mv.to()), bitboard::square(mv.from()))
+        else {
+            return 0;
+        };
+        let mut content = board.board.clone();
+        let (captured, mut on_target) = match mv {
+            Move::Teleport {
+                from_content,
+                to_content,
+                capture: true,
+                ..
+            } => (
+                value(to_content.piece_raw()),
+                value(from_content.piece_raw()),
+            ),
+            Move::PawnEnCroissantCapture {
+                from_content,
+                capture,
+                capture_content,
+                ..
+            } => {
+                if let Some(capture) = bitboard::square(*capture) {
+                    remove(&mut content, capture);
+                }
+                (
+                    value(capture_content.piece_raw()),
+                    value(from_content.piece_raw()),
+                )
+            }
+            Move::PromotePawn {
+                from_content,
+                to_content,
+                promote_content,
+                ..
+            } if !to_content.is_empty() => (
+                value(to_content.piece_raw()) + value(promote_content.piece_raw())
+                    - value(from_content.piece_raw()),
+                value(promote_content.piece_raw()),
+            ),
+            _ => return 0,
+        };
+        remove(&mut content, from);
+
+        // What each capture in turn gains for the side making it, if the exchange were to stop there
+        let mut gains = vec![captured];
+        let mut side = self.turn(board).flip();
+        while let Some(attacker) = bitboard::squares(Self::attacks_on(side, &content, target))
+            .min_by_key(|square| value(board.get(bitboard::pos(*square)).piece_raw()))
+        {
+            gains.push(on_target - gains[gains.len() - 1]);
+            on_target = value(board.get(bitboard::pos(attacker)).piece_raw());
+            remove(&mut content, attacker);
+            side = side.flip();
+        }
+        // Each side stops capturing as soon as carrying on would lose
+        while gains.len() > 1 {
+            let gain = gains.pop().unwrap();
+            let previous = gains.last_mut().unwrap();
+            *previous = -(-*previous).max(gain);
+        }
+        gains[0]
+    }
+
     fn pseudolegal_moves<const NOISY_ONLY: bool>(
         &self,
         turn: Player,
@@ -877,18 +1367,11 @@ impl Chess {
         #[cfg(debug_assertions)]
         board.validate();
 
-        if board.board.num_repetitions >= 2 {
+        if board.board.num_repetitions >= 2 || board.halfmove_clock >= 100 {
             return vec![];
         }
 
         let mut moves = vec![];
-        let forward = DPos::from_grid(
-            match turn {
-                Player::First => -1,
-                Player::Second => 1,
-            },
-            0,
-        );
         const LEFT: DPos = DPos::from_grid(0, -1);
         const RIGHT: DPos = DPos::from_grid(0, 1);
 
@@ -912,6 +1395,7 @@ impl Chess {
                             break;
                         } else {
                             moves.push(Move::Teleport {
+                                prev_halfmove_clock: board.halfmove_clock,
                                 prev_castling_rights: board.castling_rights,
                                 from,
                                 from_content,
@@ -926,6 +1410,7 @@ impl Chess {
                     None => {
                         if !NOISY_ONLY {
                             moves.push(Move::Teleport {
+                                prev_halfmove_clock: board.halfmove_clock,
                                 prev_castling_rights: board.castling_rights,
                                 from,
                                 from_content,
@@ -963,6 +1448,7 @@ impl Chess {
                             Some(owner) => {
                                 if owner != turn {
                                     moves.push(Move::Teleport {
+                                        prev_halfmove_clock: board.halfmove_clock,
                                         prev_castling_rights: board.castling_rights,
                                         from,
                                         from_content,
@@ -976,6 +1462,7 @@ impl Chess {
                             None => {
                                 if !NOISY_ONLY {
                                     moves.push(Move::Teleport {
+                                        prev_halfmove_clock: board.halfmove_clock,
                                         prev_castling_rights: board.castling_rights,
                                         from,
                                         from_content,
@@ -1006,11 +1493,12 @@ impl Chess {
             let one_step = from + dir;
             let one_step_content = board.get(one_step);
             if !one_step_content.is_outside() && one_step_content.is_empty() {
-                if one_step.is_end_row() {
+                if logic.is_end_row(one_step) {
                     for promote_piece_raw in logic.possible_promotions() {
                         let promote_content =
                             SquareContents::from_piece_raw(turn, promote_piece_raw);
                         moves.push(Move::PromotePawn {
+                            prev_halfmove_clock: board.halfmove_clock,
                             from,
                             from_content,
                             to: one_step,
@@ -1020,6 +1508,7 @@ impl Chess {
                     }
                 } else if !NOISY_ONLY {
                     moves.push(Move::Teleport {
+                        prev_halfmove_clock: board.halfmove_clock,
                         prev_castling_rights: board.castling_rights,
                         from,
                         from_content,
@@ -1029,8 +1518,12 @@ impl Chess {
                         king_move: false,
                     });
 
-                    // Pawn move 2 ahead
-                    if from.is_pawn_row() {
+                    // Pawn move 2 ahead, which the horde may also do from the first rank
+                    if logic.is_pawn_row(from)
+                        || (matches!(logic, Chess::Horde)
+                            && turn == Player::First
+                            && from.to_grid().is_some_and(|(row, _)| row == 7))
+                    {
                         let two_step = one_step + dir;
                         let two_step_content = board.get(two_step);
                         if !two_step_content.is_outside() && two_step_content.is_empty() {
@@ -1051,6 +1544,7 @@ impl Chess {
                             }
 
                             moves.push(Move::PawnDoublePush {
+                                prev_halfmove_clock: board.halfmove_clock,
                                 from,
                                 from_content,
                                 croissant: one_step,
@@ -1078,12 +1572,13 @@ impl Chess {
             let one_step_content = board.get(one_step);
             if !one_step_content.is_outside() {
                 if one_step_content.owner() == Some(turn.flip()) {
-                    if one_step.is_end_row() {
+                    if logic.is_end_row(one_step) {
                         // Promotion
                         for promote_piece_raw in logic.possible_promotions() {
                             let promote_content =
                                 SquareContents::from_piece_raw(turn, promote_piece_raw);
                             moves.push(Move::PromotePawn {
+                                prev_halfmove_clock: board.halfmove_clock,
                                 from,
                                 from_content,
                                 to: one_step,
@@ -1093,6 +1588,7 @@ impl Chess {
                         }
                     } else {
                         moves.push(Move::Teleport {
+                            prev_halfmove_clock: board.halfmove_clock,
                             prev_castling_rights: board.castling_rights,
                             from,
                             from_content,
@@ -1112,6 +1608,7 @@ impl Chess {
                 {
                     let actual_capture_content = board.get(actual_capture);
                     moves.push(Move::PawnEnCroissantCapture {
+                        prev_halfmove_clock: board.halfmove_clock,
                         from,
                         from_content,
                         capture: actual_capture,
@@ -1123,265 +1620,159 @@ impl Chess {
             }
         }
 
-        for row in 0..8 {
-            for col in 0..8 {
-                let from = Pos::from_grid(row, col);
-                let from_content = board.get(from);
-                if let Some(owner) = from_content.owner()
-                    && owner == turn
-                {
-                    let piece_raw = from_content.piece_raw();
-                    match piece_raw {
-                        square::BEROLINA_PAWN => {
-                            pawn_takes::<NOISY_ONLY>(self, board, turn, &mut moves, from, forward);
-                            pawn_moves::<NOISY_ONLY>(
-                                self,
-                                board,
-                                turn,
-                                &mut moves,
-                                from,
-                                forward + LEFT,
-                            );
-                            pawn_moves::<NOISY_ONLY>(
-                                self,
-                                board,
-                                turn,
-                                &mut moves,
-                                from,
-                                forward + RIGHT,
-                            );
-                        }
-
-                        square::PAWN => {
-                            pawn_moves::<NOISY_ONLY>(self, board, turn, &mut moves, from, forward);
-                            pawn_takes::<NOISY_ONLY>(
-                                self,
-                                board,
-                                turn,
-                                &mut moves,
-                                from,
-                                forward + LEFT,
-                            );
-                            pawn_takes::<NOISY_ONLY>(
-                                self,
-                                board,
-                                turn,
-                                &mut moves,
-                                from,
-                                forward + RIGHT,
-                            );
-                        }
-                        square::KNIGHT => {
-                            for to in [
-                                from + DPos::from_grid(1, 2),
-                                from + DPos::from_grid(-1, 2),
-                                from + DPos::from_grid(-2, 1),
-                                from + DPos::from_grid(-2, -1),
-                                from + DPos::from_grid(-1, -2),
-                                from + DPos::from_grid(1, -2),
-                                from + DPos::from_grid(2, -1),
-                                from + DPos::from_grid(2, 1),
-                            ] {
+        for from in bitboard::squares(board.board.colours[colour_idx(turn)]).map(bitboard::pos) {
+            let from_content = board.get(from);
+            if let Some(owner) = from_content.owner() {
+                let kind = pieces::kind(from_content.piece_raw());
+                for movement in kind.movement {
+                    match *movement {
+                        Movement::Leap(offsets) => {
+                            for offset in offsets {
+                                let to = from + pieces::offset(turn, *offset);
                                 let to_content = board.get(to);
                                 if !to_content.is_outside() && to_content.owner() != Some(owner) {
                                     let capture = to_content.owner().is_some();
                                     if !NOISY_ONLY || capture {
                                         moves.push(Move::Teleport {
+                                            prev_halfmove_clock: board.halfmove_clock,
                                             prev_castling_rights: board.castling_rights,
                                             from,
                                             from_content,
                                             to,
                                             to_content,
                                             capture,
-                                            king_move: false,
+                                            king_move: kind.royal,
                                         })
                                     }
                                 }
                             }
                         }
-                        square::KING => {
-                            for to in [
-                                from + DPos::from_grid(0, 1),
-                                from + DPos::from_grid(-1, 1),
-                                from + DPos::from_grid(-1, 0),
-                                from + DPos::from_grid(-1, -1),
-                                from + DPos::from_grid(0, -1),
-                                from + DPos::from_grid(1, -1),
-                                from + DPos::from_grid(1, 0),
-                                from + DPos::from_grid(1, 1),
-                            ] {
+                        Movement::Ride(offsets)
+                            if let Some(slider) = Slider::from_offsets(offsets) =>
+                        {
+                            let content = &board.board;
+                            let targets = slider.attacks(
+                                bitboard::square(from).unwrap(),
+                                content.occupied() | content.outside,
+                            ) & !content.colours[colour_idx(turn)]
+                                & !content.outside;
+                            for to in bitboard::squares(targets).map(bitboard::pos) {
                                 let to_content = board.get(to);
-                                if !to_content.is_outside() && to_content.owner() != Some(owner) {
-                                    let capture = to_content.owner().is_some();
-                                    if !NOISY_ONLY || capture {
-                                        moves.push(Move::Teleport {
-                                            prev_castling_rights: board.castling_rights,
-                                            from,
-                                            from_content,
-                                            to,
-                                            to_content,
-                                            capture,
-                                            king_move: true,
-                                        })
-                                    }
+                                let capture = !to_content.is_empty();
+                                if !NOISY_ONLY || capture {
+                                    moves.push(Move::Teleport {
+                                        prev_halfmove_clock: board.halfmove_clock,
+                                        prev_castling_rights: board.castling_rights,
+                                        from,
+                                        from_content,
+                                        to,
+                                        to_content,
+                                        capture,
+                                        king_move: false,
+                                    });
                                 }
                             }
                         }
-                        square::ROOK => {
-                            for dir in [
-                                DPos::from_grid(0, 1),
-                                DPos::from_grid(-1, 0),
-                                DPos::from_grid(0, -1),
-                                DPos::from_grid(1, 0),
-                            ] {
+                        Movement::Ride(offsets) => {
+                            for offset in offsets {
                                 sliding_moves::<NOISY_ONLY>(
                                     board,
                                     turn,
                                     &mut moves,
                                     from,
                                     from_content,
-                                    dir,
+                                    pieces::offset(turn, *offset),
                                 );
                             }
                         }
-                        square::BISHOP => {
-                            for dir in [
-                                DPos::from_grid(1, 1),
-                                DPos::from_grid(-1, 1),
-                                DPos::from_grid(1, -1),
-                                DPos::from_grid(-1, -1),
-                            ] {
-                                sliding_moves::<NOISY_ONLY>(
+                        Movement::Hop(offsets) => {
+                            for offset in offsets {
+                                sliding_jump_moves::<NOISY_ONLY>(
                                     board,
                                     turn,
                                     &mut moves,
                                     from,
                                     from_content,
-                                    dir,
+                                    pieces::offset(turn, *offset),
                                 );
                             }
                         }
-                        square::QUEEN => {
-                            for dir in [
-                                DPos::from_grid(0, 1),
-                                DPos::from_grid(-1, 0),
-                                DPos::from_grid(0, -1),
-                                DPos::from_grid(1, 0),
-                                DPos::from_grid(1, 1),
-                                DPos::from_grid(-1, 1),
-                                DPos::from_grid(1, -1),
-                                DPos::from_grid(-1, -1),
-                            ] {
-                                sliding_moves::<NOISY_ONLY>(
+                        Movement::PawnPush(offsets) => {
+                            for offset in offsets {
+                                pawn_moves::<NOISY_ONLY>(
+                                    self,
                                     board,
                                     turn,
                                     &mut moves,
                                     from,
-                                    from_content,
-                                    dir,
+                                    pieces::offset(turn, *offset),
                                 );
                             }
                         }
-                        square::GRASSHOPPER => {
-                            for dir in [
-                                DPos::from_grid(0, 1),
-                                DPos::from_grid(-1, 0),
-                                DPos::from_grid(0, -1),
-                                DPos::from_grid(1, 0),
-                                DPos::from_grid(1, 1),
-                                DPos::from_grid(-1, 1),
-                                DPos::from_grid(1, -1),
-                                DPos::from_grid(-1, -1),
-                            ] {
-                                sliding_jump_moves::<NOISY_ONLY>(
+                        Movement::PawnCapture(offsets) => {
+                            for offset in offsets {
+                                pawn_takes::<NOISY_ONLY>(
+                                    self,
                                     board,
                                     turn,
                                     &mut moves,
                                     from,
-                                    from_content,
-                                    dir,
+                                    pieces::offset(turn, *offset),
                                 );
                             }
                         }
-                        _ => {
-                            unreachable!()
-                        }
                     }
                 }
             }
         }
 
         // Castling
-        if !NOISY_ONLY {
+        if !NOISY_ONLY && let Some((left_rook_col, king_col, right_rook_col)) = self.castling_cols()
+        {
             let castle_row = match turn {
                 Player::First => 7,
                 Player::Second => 0,
             };
-            let king_from = Pos::from_grid(castle_row, 4);
+            let king_from = Pos::from_grid(castle_row, king_col);
             let king_from_content = board.get(king_from);
-            if board.castling_rights.has(match turn {
-                Player::First => castling::WHITE_CAN_CASTLE_LEFT,
-                Player::Second => castling::BLACK_CAN_CASTLE_LEFT,
-            }) {
-                debug_assert!(!king_from_content.is_empty());
-                debug_assert_eq!(king_from_content.piece_raw(), square::KING);
-                debug_assert_eq!(king_from_content.owner(), Some(turn));
-                let rook_from = Pos::from_grid(castle_row, 0);
-                let rook_from_content = board.get(rook_from);
-                debug_assert!(!rook_from_content.is_empty());
-                debug_assert_eq!(rook_from_content.owner(), Some(turn));
-                debug_assert_eq!(rook_from_content.piece_raw(), square::ROOK);
-                let rook_mid = Pos::from_grid(castle_row, 1);
-                let rook_mid_content = board.get(rook_mid);
-                let king_to = Pos::from_grid(castle_row, 2);
-                let king_to_content = board.get(king_to);
-                let rook_to = Pos::from_grid(castle_row, 3);
-                let rook_to_content = board.get(rook_to);
-                if rook_mid_content.is_empty()
-                    && king_to_content.is_empty()
-                    && rook_to_content.is_empty()
-                {
-                    moves.push(Move::Castle {
-                        prev_castling_rights: board.castling_rights,
-                        king_from,
-                        king_from_content,
-                        king_to,
-                        king_to_content,
-                        rook_from,
-                        rook_from_content,
-                        rook_to,
-                        rook_to_content,
-                    });
+            for (right, rook_col, king_to_col, rook_to_col) in
+                [(false, left_rook_col, 2, 3), (true, right_rook_col, 6, 5)]
+            {
+                if !board.castling_rights.has(match (turn, right) {
+                    (Player::First, false) => castling::WHITE_CAN_CASTLE_LEFT,
+                    (Player::First, true) => castling::WHITE_CAN_CASTLE_RIGHT,
+                    (Player::Second, false) => castling::BLACK_CAN_CASTLE_LEFT,
+                    (Player::Second, true) => castling::BLACK_CAN_CASTLE_RIGHT,
+                }) {
+                    continue;
                 }
-            }
-
-            if board.castling_rights.has(match turn {
-                Player::First => castling::WHITE_CAN_CASTLE_RIGHT,
-                Player::Second => castling::BLACK_CAN_CASTLE_RIGHT,
-            }) {
                 debug_assert!(!king_from_content.is_empty());
                 debug_assert_eq!(king_from_content.piece_raw(), square::KING);
                 debug_assert_eq!(king_from_content.owner(), Some(turn));
-                let rook_from = Pos::from_grid(castle_row, 7);
+                let rook_from = Pos::from_grid(castle_row, rook_col);
                 let rook_from_content = board.get(rook_from);
                 debug_assert!(!rook_from_content.is_empty());
                 debug_assert_eq!(rook_from_content.owner(), Some(turn));
                 debug_assert_eq!(rook_from_content.piece_raw(), square::ROOK);
-                let king_to = Pos::from_grid(castle_row, 6);
-                let king_to_content = board.get(king_to);
-                let rook_to = Pos::from_grid(castle_row, 5);
-                let rook_to_content = board.get(rook_to);
-                if king_to_content.is_empty() && rook_to_content.is_empty() {
+                let king_to = Pos::from_grid(castle_row, king_to_col);
+                let rook_to = Pos::from_grid(castle_row, rook_to_col);
+                // Every square the king and rook pass over must be empty apart from the king and rook themselves
+                let lo = king_col.min(rook_col).min(king_to_col).min(rook_to_col);
+                let hi = king_col.max(rook_col).max(king_to_col).max(rook_to_col);
+                if (lo..=hi)
+                    .filter(|col| *col != king_col && *col != rook_col)
+                    .all(|col| board.get(Pos::from_grid(castle_row, col)).is_empty())
+                {
                     moves.push(Move::Castle {
                         prev_castling_rights: board.castling_rights,
                         king_from,
                         king_from_content,
                         king_to,
-                        king_to_content,
+                        king_to_content: SquareContents::empty(),
                         rook_from,
                         rook_from_content,
                         rook_to,
-                        rook_to_content,
+                        rook_to_content: SquareContents::empty(),
                     });
                 }
             }
@@ -1395,8 +1786,29 @@ impl Chess {
         turn: Player,
         board: &mut BoardState,
     ) -> Vec<Move> {
+        self.legal_moves_where::<NOISY_ONLY>(turn, board, |_| true)
+    }
+
+    // The legal moves among the pseudolegal moves picked out by `wanted`, so that legality is only checked for those
+    fn legal_moves_where<const NOISY_ONLY: bool>(
+        &self,
+        turn: Player,
+        board: &mut BoardState,
+        wanted: impl Fn(&Move) -> bool,
+    ) -> Vec<Move> {
+        if self.is_insufficient_material(board)
+            || self.hill_winner(board).is_some()
+            || self.three_check_winner(board).is_some()
+            || self.horde_captured(board)
+        {
+            return vec![];
+        }
         let mut legal_moves = vec![];
-        for mv in self.pseudolegal_moves::<false>(turn, board) {
+        for mv in self
+            .pseudolegal_moves::<false>(turn, board)
+            .into_iter()
+            .filter(|mv| wanted(mv))
+        {
             // TODO this is slow
             // Don't want to clone or modify the board here
             // But can use this for debug mode
@@ -1407,16 +1819,19 @@ impl Chess {
             self.unmake_move(board, &mv);
 
             if let Move::Castle {
-                king_from, rook_to, ..
+                king_from, king_to, ..
             } = mv
             {
-                // Can't castle through check
-                if !self.attackers(turn, board, rook_to).is_empty() {
-                    is_legal = false;
-                }
-                // Can't castle when in check
-                if !self.attackers(turn, board, king_from).is_empty() {
-                    is_legal = false;
+                // Can't castle out of or through check
+                let (row, from_col) = king_from.to_grid().unwrap();
+                let (_, to_col) = king_to.to_grid().unwrap();
+                for col in from_col.min(to_col)..=from_col.max(to_col) {
+                    if !self
+                        .attackers(turn, board, Pos::from_grid(row, col))
+                        .is_empty()
+                    {
+                        is_legal = false;
+                    }
                 }
             }
 
@@ -1435,6 +1850,133 @@ impl Chess {
         }
         legal_moves
     }
+
+    // What the hand written evaluation counts in a position which isn't over
+    pub fn eval_features(&self, board: &mut BoardState) -> EvalFeatures {
+        let mobility = self.pseudolegal_moves::<false>(Player::First, board).len() as i64
+            - self.pseudolegal_moves::<false>(Player::Second, board).len() as i64;
+
+        let mut pieces = [0; pieces::PIECES.len()];
+        let mut pawn_rows = [0; 8];
+        let mut piece_count = 0;
+        for pos in bitboard::squares(board.board.occupied()).map(bitboard::pos) {
+            let (row, _) = pos.to_grid().unwrap();
+            let content = board.get(pos);
+            debug_assert!(!content.is_outside());
+            if !content.is_empty() {
+                piece_count += 1;
+                let piece = content.piece_raw();
+                let sign = match content.owner() {
+                    Some(Player::First) => 1,
+                    Some(Player::Second) => -1,
+                    None => unreachable!(),
+                };
+                let idx = pieces::PIECES
+                    .iter()
+                    .position(|kind| kind.piece_raw == piece)
+                    .unwrap();
+                pieces[idx] += sign;
+                match piece {
+                    square::PAWN | square::BEROLINA_PAWN => {
+                        // Indexed by the distance to the promotion row
+                        pawn_rows[match content.owner().unwrap() {
+                            Player::First => row - self.top_row(),
+                            Player::Second => 7 - row,
+                        }] += sign;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut king_position = 0;
+        if piece_count <= 6 {
+            // End game
+
+            let last_row = self.rows() as i64 - 1;
+            let last_col = self.cols() as i64 - 1;
+            let dist_from_corner = |(row, col): (usize, usize)| -> i64 {
+                let row = row as i64;
+                let col = col as i64;
+                std::cmp::min(row, last_row - row) + std::cmp::min(col, last_col - col)
+            };
+
+            if let Some(white_king) = board.white_king {
+                king_position += dist_from_corner(self.pos_to_grid(white_king).unwrap());
+            }
+            if let Some(black_king) = board.black_king {
+                king_position -= dist_from_corner(self.pos_to_grid(black_king).unwrap());
+            }
+        }
+
+        let (mut pawn_shield, mut open_king_files, mut king_attackers) = (0, 0, 0);
+        if piece_count > 6 {
+            for (player, king, sign) in [
+                (Player::First, board.white_king, 1),
+                (Player::Second, board.black_king, -1),
+            ] {
+                if let Some(king) = king {
+                    let (shield, open_files, attackers) = self.king_safety(player, board, king);
+                    pawn_shield += sign * shield;
+                    open_king_files += sign * open_files;
+                    king_attackers += sign * attackers;
+                }
+            }
+        }
+
+        EvalFeatures {
+            pieces,
+            pawn_rows,
+            mobility,
+            king_position,
+            pawn_shield,
+            open_king_files,
+            king_attackers,
+        }
+    }
+
+    // How well `player`'s king on `king` is sheltered: its pawn shield, the open files by it and the enemy pieces attacking around it
+    fn king_safety(&self, player: Player, board: &BoardState, king: Pos) -> (i64, i64, i64) {
+        let (row, col) = king.to_grid().unwrap();
+        let (row, col) = (row as isize, col as isize);
+        let forward = match player {
+            Player::First => -1,
+            Player::Second => 1,
+        };
+        let square_at = |row: isize, col: isize| -> Option<usize> {
+            let square = bitboard::step(0, (row, col))?;
+            (board.board.outside & (1 << square) == 0).then_some(square)
+        };
+        let own_pawns = board.board.pieces(player, square::PAWN)
+            | board.board.pieces(player, square::BEROLINA_PAWN);
+
+        let mut shield = 0;
+        let mut open_files = 0;
+        let mut attackers = 0u64;
+        for col in col - 1..=col + 1 {
+            if square_at(row, col).is_none() {
+                continue;
+            }
+            for distance in 1..=2 {
+                if let Some(square) = square_at(row + forward * distance, col)
+                    && own_pawns & (1 << square) != 0
+                {
+                    shield += 1;
+                }
+            }
+            if own_pawns & (0x0101_0101_0101_0101 << col) == 0 {
+                open_files += 1;
+            }
+            for row in row - 1..=row + 1 {
+                if let Some(square) = square_at(row, col) {
+                    for attacker in self.attackers(player, board, bitboard::pos(square)) {
+                        attackers |= bitboard::bit(attacker);
+                    }
+                }
+            }
+        }
+        (shield, open_files, attackers.count_ones() as i64)
+    }
 }
 
 impl Neutral for i64 {
@@ -1452,7 +1994,9 @@ impl GameLogic for Chess {
     type HeuristicScore = i64;
 
     fn turn(&self, state: &Self::State) -> Player {
-        if state.move_num % 2 == 0 {
+        // `move_num` counts the moves played, so a custom position with black to move starts on an odd ply
+        let black_first = matches!(self, Chess::Custom(position) if position.black_to_move);
+        if (state.move_num + usize::from(black_first)) % 2 == 0 {
             Player::First
         } else {
             Player::Second
@@ -1461,7 +2005,7 @@ impl GameLogic for Chess {
 
     fn initial_state(&self) -> Self::State {
         let (board, castling_rights) = match self {
-            Chess::Standard => (
+            Chess::Standard | Chess::KingOfTheHill | Chess::ThreeCheck => (
                 vec![
                     vec!['R', 'N', 'B', 'Q', 'K', 'B', 'N', 'R'],
                     vec!['P', 'P', 'P', 'P', 'P', 'P', 'P', 'P'],
@@ -1500,18 +2044,66 @@ impl GameLogic for Chess {
                 ],
                 castling::Rights::full(),
             ),
+            Chess::Horde => {
+                let mut castling_rights = castling::Rights::full();
+                castling_rights
+                    .remove(castling::WHITE_CAN_CASTLE_LEFT | castling::WHITE_CAN_CASTLE_RIGHT);
+                (
+                    vec![
+                        vec!['R', 'N', 'B', 'Q', 'K', 'B', 'N', 'R'],
+                        vec!['P', 'P', 'P', 'P', 'P', 'P', 'P', 'P'],
+                        vec![' ', ' ', ' ', ' ', ' ', ' ', ' ', ' '],
+                        vec![' ', 'p', 'p', ' ', ' ', 'p', 'p', ' '],
+                        vec!['p', 'p', 'p', 'p', 'p', 'p', 'p', 'p'],
+                        vec!['p', 'p', 'p', 'p', 'p', 'p', 'p', 'p'],
+                        vec!['p', 'p', 'p', 'p', 'p', 'p', 'p', 'p'],
+                        vec!['p', 'p', 'p', 'p', 'p', 'p', 'p', 'p'],
+                    ],
+                    castling_rights,
+                )
+            }
+            Chess::Fischer960(_) => {
+                let back_rank = self.back_rank();
+                (
+                    vec![
+                        back_rank.to_vec(),
+                        vec!['P'; 8],
+                        vec![' '; 8],
+                        vec![' '; 8],
+                        vec![' '; 8],
+                        vec![' '; 8],
+                        vec!['p'; 8],
+                        back_rank.iter().map(|c| c.to_ascii_lowercase()).collect(),
+                    ],
+                    castling::Rights::full(),
+                )
+            }
+            Chess::Gardner => (
+                vec![
+                    vec!['R', 'N', 'B', 'Q', 'K'],
+                    vec!['P', 'P', 'P', 'P', 'P'],
+                    vec![' ', ' ', ' ', ' ', ' '],
+                    vec!['p', 'p', 'p', 'p', 'p'],
+                    vec!['r', 'n', 'b', 'q', 'k'],
+                ],
+                castling::Rights::none(),
+            ),
+            Chess::Custom(position) => (
+                position.board.iter().map(|row| row.to_vec()).collect(),
+                position.castling_rights,
+            ),
         };
-        debug_assert_eq!(board.len(), 8);
+        debug_assert_eq!(board.len(), self.rows());
         for row in &board {
-            debug_assert_eq!(row.len(), 8);
+            debug_assert_eq!(row.len(), self.cols());
         }
-        let mut board_content = BoardContent::new();
+        let mut board_content = BoardContent::new(self);
         let mut white_king = None;
         let mut black_king = None;
         #[allow(clippy::needless_range_loop)]
-        for row in 0..8 {
-            for col in 0..8 {
-                let pos = Pos::from_grid(row, col);
+        for row in 0..self.rows() {
+            for col in 0..self.cols() {
+                let pos = self.grid_to_pos(row, col);
                 board_content.set(
                     pos,
                     match board[row][col] {
@@ -1555,19 +2147,25 @@ impl GameLogic for Chess {
             }
         }
 
-        Self::State {
+        let mut state = Self::State {
             board: board_content,
             repetitions: BoardRepetitionsState {
                 ignore_repetitions: false,
                 prev_boards: vec![],
                 repetition_max: 0,
             },
-            white_king: white_king.unwrap(),
-            black_king: black_king.unwrap(),
+            white_king,
+            black_king,
             castling_rights,
             move_num: 0,
+            halfmove_clock: 0,
+            white_checks: 0,
+            black_checks: 0,
             en_croissant_info: None,
-        }
+            hash_bits: 0,
+        };
+        state.hash_bits = state.compute_hash64();
+        state
     }
 
     fn generate_moves(&self, board: &mut Self::State) -> Vec<Self::Move> {
@@ -1578,9 +2176,91 @@ impl GameLogic for Chess {
         self.legal_moves::<true>(self.turn(board), board)
     }
 
+    fn generate_move_stage(
+        &self,
+        board: &mut Self::State,
+        stage: usize,
+    ) -> Option<Vec<Self::Move>> {
+        // Captures and promotions first since they are the likeliest to cause a cutoff
+        let tactical = |mv: &Move| mv.is_capture() || matches!(mv, Move::PromotePawn { .. });
+        let turn = self.turn(board);
+        match stage {
+            0 => Some(self.legal_moves_where::<false>(turn, board, tactical)),
+            1 => Some(self.legal_moves_where::<false>(turn, board, |mv| !tactical(mv))),
+            _ => None,
+        }
+    }
+
+    // Most valuable victim first, and among those the least valuable attacker
+    fn move_ordering_hint(&self, mv: &Self::Move) -> i64 {
+        let victim = match mv {
+            Move::Teleport {
+                to_content,
+                capture: true,
+                ..
+            } => pieces::kind(to_content.piece_raw()).value,
+            Move::PawnEnCroissantCapture {
+                capture_content, ..
+            } => pieces::kind(capture_content.piece_raw()).value,
+            Move::PromotePawn {
+                to_content,
+                promote_content,
+                ..
+            } => {
+                let captured = if to_content.is_empty() {
+                    0
+                } else {
+                    pieces::kind(to_content.piece_raw()).value
+                };
+                captured + pieces::kind(promote_content.piece_raw()).value
+            }
+            _ => return 0,
+        };
+        // Piece values are well below the multiplier, so a bigger victim always comes first
+        100_000 * victim - pieces::kind(mv.moved_piece_raw()).value
+    }
+
+    fn exchange_value(&self, board: &mut Self::State, mv: &Self::Move) -> Option<i64> {
+        (!self.is_quiet_move(mv)).then(|| self.static_exchange(board, mv))
+    }
+
+    fn max_plausible_gain(&self) -> Option<Self::HeuristicScore> {
+        match self {
+            // A quiet move onto the hill wins outright
+            Chess::KingOfTheHill => None,
+            // Taking a queen, with room for the positional terms to swing
+            _ => Some(pieces::kind(square::QUEEN).value + 300),
+        }
+    }
+
+    fn is_quiet_move(&self, mv: &Self::Move) -> bool {
+        !mv.is_capture() && !matches!(mv, Move::PromotePawn { .. })
+    }
+
+    fn gives_check(&self, board: &mut Self::State, mv: &Self::Move) -> bool {
+        let turn = self.turn(board);
+        self.make_move(board, mv);
+        let check = self.is_check(turn.flip(), board);
+        self.unmake_move(board, mv);
+        check
+    }
+
+    fn in_check(&self, board: &Self::State) -> bool {
+        self.is_check(self.turn(board), board)
+    }
+
+    fn move_bucket(&self, mv: &Self::Move) -> Option<usize> {
+        Some(64 * bitboard::square(mv.from())? + bitboard::square(mv.to())?)
+    }
+
+    fn is_capture(&self, _board: &Self::State, mv: &Self::Move) -> bool {
+        mv.is_capture()
+    }
+
     fn make_move(&self, board: &mut Self::State, mv: &Self::Move) {
         #[cfg(debug_assertions)]
         board.validate();
+        let prev_rights_hash = board.rights_hash();
 
         if !board.repetitions.ignore_repetitions {
             debug_assert_eq!(board.repetitions.prev_boards.len(), board.move_num);
@@ -1617,13 +2297,13 @@ impl GameLogic for Chess {
                 if *king_move {
                     match from_content.owner() {
                         Some(Player::First) => {
-                            board.white_king = *to;
+                            board.white_king = Some(*to);
                             board.castling_rights.remove(
                                 castling::WHITE_CAN_CASTLE_LEFT | castling::WHITE_CAN_CASTLE_RIGHT,
                             );
                         }
                         Some(Player::Second) => {
-                            board.black_king = *to;
+                            board.black_king = Some(*to);
                             board.castling_rights.remove(
                                 castling::BLACK_CAN_CASTLE_LEFT | castling::BLACK_CAN_CASTLE_RIGHT,
                             );
@@ -1632,27 +2312,27 @@ impl GameLogic for Chess {
                     }
                 }
 
-                {
-                    const BLACK_LEFT: Pos = Pos::from_grid(0, 0);
-                    const BLACK_RIGHT: Pos = Pos::from_grid(0, 7);
-                    const WHITE_LEFT: Pos = Pos::from_grid(7, 0);
-                    const WHITE_RIGHT: Pos = Pos::from_grid(7, 7);
-                    if *from == BLACK_LEFT || *to == BLACK_LEFT {
+                if let Some((left, _, right)) = self.castling_cols() {
+                    let black_left = Pos::from_grid(0, left);
+                    let black_right = Pos::from_grid(0, right);
+                    let white_left = Pos::from_grid(7, left);
+                    let white_right = Pos::from_grid(7, right);
+                    if *from == black_left || *to == black_left {
                         board
                             .castling_rights
                             .remove(castling::BLACK_CAN_CASTLE_LEFT);
                     }
-                    if *from == BLACK_RIGHT || *to == BLACK_RIGHT {
+                    if *from == black_right || *to == black_right {
                         board
                             .castling_rights
                             .remove(castling::BLACK_CAN_CASTLE_RIGHT);
                     }
-                    if *from == WHITE_LEFT || *to == WHITE_LEFT {
+                    if *from == white_left || *to == white_left {
                         board
                             .castling_rights
                             .remove(castling::WHITE_CAN_CASTLE_LEFT);
                     }
-                    if *from == WHITE_RIGHT || *to == WHITE_RIGHT {
+                    if *from == white_right || *to == white_right {
                         board
                             .castling_rights
                             .remove(castling::WHITE_CAN_CASTLE_RIGHT);
@@ -1697,6 +2377,7 @@ impl GameLogic for Chess {
                 capture_content,
                 to,
                 to_content,
+                ..
             } => {
                 debug_assert!(!from_content.is_outside());
                 debug_assert!(from_content.owner().is_some());
@@ -1734,15 +2415,12 @@ impl GameLogic for Chess {
                 debug_assert!(rook_to_content.is_empty());
                 debug_assert_eq!(king_from_content.piece_raw(), square::KING);
                 debug_assert_eq!(rook_from_content.piece_raw(), square::ROOK);
-                debug_assert_ne!(king_from, king_to);
                 debug_assert_ne!(king_from, rook_from);
-                debug_assert_ne!(king_from, rook_to);
-                debug_assert_ne!(king_to, rook_from);
                 debug_assert_ne!(king_to, rook_to);
-                debug_assert_ne!(rook_from, rook_to);
+                // In Fischer random the king and rook may land on each other's starting squares
                 board.set(*king_from, SquareContents::empty());
-                board.set(*king_to, *king_from_content);
                 board.set(*rook_from, SquareContents::empty());
+                board.set(*king_to, *king_from_content);
                 board.set(*rook_to, *rook_from_content);
                 if !board.repetitions.ignore_repetitions {
                     board.repetitions.repetition_max = board.move_num + 1;
@@ -1751,13 +2429,13 @@ impl GameLogic for Chess {
                 let prev_castling_rights = board.castling_rights;
                 match king_from_content.owner() {
                     Some(Player::First) => {
-                        board.white_king = *king_to;
+                        board.white_king = Some(*king_to);
                         board.castling_rights.remove(
                             castling::WHITE_CAN_CASTLE_LEFT | castling::WHITE_CAN_CASTLE_RIGHT,
                         );
                     }
                     Some(Player::Second) => {
-                        board.black_king = *king_to;
+                        board.black_king = Some(*king_to);
                         board.castling_rights.remove(
                             castling::BLACK_CAN_CASTLE_LEFT | castling::BLACK_CAN_CASTLE_RIGHT,
                         );
@@ -1776,6 +2454,7 @@ impl GameLogic for Chess {
                 to,
                 to_content,
                 promote_content,
+                ..
             } => {
                 debug_assert_ne!(from, to);
                 debug_assert!(!from_content.is_outside());
@@ -1786,27 +2465,27 @@ impl GameLogic for Chess {
                 board.set(*from, SquareContents::empty());
                 board.set(*to, *promote_content);
 
-                {
-                    const BLACK_LEFT: Pos = Pos::from_grid(0, 0);
-                    const BLACK_RIGHT: Pos = Pos::from_grid(0, 7);
-                    const WHITE_LEFT: Pos = Pos::from_grid(7, 0);
-                    const WHITE_RIGHT: Pos = Pos::from_grid(7, 7);
-                    if *to == BLACK_LEFT {
+                if let Some((left, _, right)) = self.castling_cols() {
+                    let black_left = Pos::from_grid(0, left);
+                    let black_right = Pos::from_grid(0, right);
+                    let white_left = Pos::from_grid(7, left);
+                    let white_right = Pos::from_grid(7, right);
+                    if *to == black_left {
                         board
                             .castling_rights
                             .remove(castling::BLACK_CAN_CASTLE_LEFT);
                     }
-                    if *to == BLACK_RIGHT {
+                    if *to == black_right {
                         board
                             .castling_rights
                             .remove(castling::BLACK_CAN_CASTLE_RIGHT);
                     }
-                    if *to == WHITE_LEFT {
+                    if *to == white_left {
                         board
                             .castling_rights
                             .remove(castling::WHITE_CAN_CASTLE_LEFT);
                     }
-                    if *to == WHITE_RIGHT {
+                    if *to == white_right {
                         board
                             .castling_rights
                             .remove(castling::WHITE_CAN_CASTLE_RIGHT);
@@ -1834,7 +2513,46 @@ impl GameLogic for Chess {
             });
         }
 
+        board.halfmove_clock = match mv {
+            Move::Teleport {
+                prev_halfmove_clock,
+                from_content,
+                capture,
+                ..
+            } => {
+                debug_assert_eq!(board.halfmove_clock, *prev_halfmove_clock);
+                if *capture
+                    || from_content.piece_raw() == square::PAWN
+                    || from_content.piece_raw() == square::BEROLINA_PAWN
+                {
+                    0
+                } else {
+                    board.halfmove_clock + 1
+                }
+            }
+            Move::PawnDoublePush { .. }
+            | Move::PawnEnCroissantCapture { .. }
+            | Move::PromotePawn { .. } => 0,
+            Move::Castle { .. } => board.halfmove_clock + 1,
+        };
+
+        if matches!(self, Self::ThreeCheck) {
+            let turn = self.turn(board);
+            if self.is_check(turn.flip(), board) {
+                match turn {
+                    Player::First => board.white_checks += 1,
+                    Player::Second => board.black_checks += 1,
+                }
+                // Positions with different check counts are never repetitions
+                if !board.repetitions.ignore_repetitions {
+                    board.repetitions.repetition_max = board.move_num + 1;
+                }
+            }
+        }
+
         board.move_num += 1;
+        board.hash_bits ^=
+            ZOBRIST_HASH_CONSTANTS.side_to_move ^ prev_rights_hash ^ board.rights_hash();
 
         // Check for threefold repetition
         board.board.num_repetitions = board.repetitions.num_repetitions(&board.board);
@@ -1847,8 +2565,39 @@ impl GameLogic for Chess {
         #[cfg(debug_assertions)]
         board.validate();
         debug_assert!(board.move_num > 0);
+        let prev_rights_hash = board.rights_hash();
         board.move_num -= 1;
 
+        if matches!(self, Self::ThreeCheck) {
+            let turn = self.turn(board);
+            if self.is_check(turn.flip(), board) {
+                match turn {
+                    Player::First => board.white_checks -= 1,
+                    Player::Second => board.black_checks -= 1,
+                }
+            }
+        }
+
+        board.halfmove_clock = match mv {
+            Move::Teleport {
+                prev_halfmove_clock,
+                ..
+            }
+            | Move::PawnDoublePush {
+                prev_halfmove_clock,
+                ..
+            }
+            | Move::PawnEnCroissantCapture {
+                prev_halfmove_clock,
+                ..
+            }
+            | Move::PromotePawn {
+                prev_halfmove_clock,
+                ..
+            } => *prev_halfmove_clock,
+            Move::Castle { .. } => board.halfmove_clock - 1,
+        };
+
         if let Move::Teleport {
             from,
             from_content,
@@ -1858,8 +2607,8 @@ impl GameLogic for Chess {
             && *king_move
         {
             match from_content.owner() {
-                Some(Player::First) => board.white_king = *from,
-                Some(Player::Second) => board.black_king = *from,
+                Some(Player::First) => board.white_king = Some(*from),
+                Some(Player::Second) => board.black_king = Some(*from),
                 None => unreachable!(),
             }
         }
@@ -1896,6 +2645,7 @@ impl GameLogic for Chess {
                 to_content,
                 capture,
                 capture_content,
+                ..
             } => {
                 board.set(*from, *from_content);
                 board.set(*capture, *capture_content);
@@ -1923,13 +2673,13 @@ impl GameLogic for Chess {
                 debug_assert_eq!(king_from_content.piece_raw(), square::KING);
                 debug_assert_eq!(rook_from_content.piece_raw(), square::ROOK);
                 board.set(*king_to, *king_to_content);
-                board.set(*king_from, *king_from_content);
                 board.set(*rook_to, *rook_to_content);
+                board.set(*king_from, *king_from_content);
                 board.set(*rook_from, *rook_from_content);
                 board.castling_rights = *prev_castling_rights;
                 match king_from_content.owner() {
-                    Some(Player::First) => board.white_king = *king_from,
-                    Some(Player::Second) => board.black_king = *king_from,
+                    Some(Player::First) => board.white_king = Some(*king_from),
+                    Some(Player::Second) => board.black_king = Some(*king_from),
                     None => unreachable!(),
                 }
             }
@@ -1969,12 +2719,30 @@ impl GameLogic for Chess {
             board.repetitions.repetition_max = repetition_max;
         }
 
+        board.hash_bits ^=
+            ZOBRIST_HASH_CONSTANTS.side_to_move ^ prev_rights_hash ^ board.rights_hash();
+
         #[cfg(debug_assertions)]
         board.validate();
     }
 
     fn score(&self, board: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
-        if board.board.num_repetitions >= 2 {
+        match self
+            .hill_winner(board)
+            .or_else(|| self.three_check_winner(board))
+        {
+            Some(Player::First) => return AbsScore::FirstPlayerWin,
+            Some(Player::Second) => return AbsScore::SecondPlayerWin,
+            None => {}
+        }
+        if self.horde_captured(board) {
+            return AbsScore::SecondPlayerWin;
+        }
+
+        if board.board.num_repetitions >= 2
+            || board.halfmove_clock >= 100
+            || self.is_insufficient_material(board)
+        {
             return AbsScore::Draw;
         }
 
@@ -1990,80 +2758,47 @@ impl GameLogic for Chess {
                 AbsScore::Draw
             }
         } else {
-            let mut total: Self::HeuristicScore = 0;
-
-            total +=
-                self.pseudolegal_moves::<false>(Player::First, board).len() as Self::HeuristicScore;
-            total -= self.pseudolegal_moves::<false>(Player::Second, board).len()
-                as Self::HeuristicScore;
-
-            let mut piece_count = 0;
-            for row in 0..8 {
-                for col in 0..8 {
-                    let pos = Pos::from_grid(row, col);
-                    let content = board.get(pos);
-                    debug_assert!(!content.is_outside());
-                    if !content.is_empty() {
-                        piece_count += 1;
-                        let piece = content.piece_raw();
-                        let mut score = match piece {
-                            square::PAWN => 100,
-                            square::BEROLINA_PAWN => 100,
-                            square::ROOK => 500,
-                            square::KNIGHT => 300,
-                            square::BISHOP => 300,
-                            square::QUEEN => 900,
-                            square::KING => 10000,
-                            square::GRASSHOPPER => 40,
-                            _ => unreachable!(),
-                        };
-                        match piece {
-                            square::PAWN | square::BEROLINA_PAWN => {
-                                score +=
-                                    [0, 0, 5, 5, 10, 110, 400, 0][match content.owner().unwrap() {
-                                        Player::First => 7 - row,
-                                        Player::Second => row,
-                                    }];
-                            }
-                            _ => {}
-                        }
-                        match content.owner() {
-                            Some(Player::First) => {
-                                total += score;
-                            }
-                            Some(Player::Second) => {
-                                total -= score;
-                            }
-                            None => unreachable!(),
-                        }
-                    }
-                }
-            }
-            if piece_count <= 6 {
-                // End game
+            AbsScore::Heuristic(EvalParams::DEFAULT.evaluate(&self.eval_features(board)))
+        }
+    }
 
-                let dist_from_corner = |(row, col): (usize, usize)| -> i64 {
-                    let row = row as i64;
-                    let col = col as i64;
-                    std::cmp::min(row, 7 - row) + std::cmp::min(col, 7 - col)
-                };
+    // In the same order as `score` decides
+    fn end_reason(&self, board: &mut Self::State) -> Option<&'static str> {
+        if self.hill_winner(board).is_some() {
+            Some("King of the hill")
+        } else if self.three_check_winner(board).is_some() {
+            Some("Three checks")
+        } else if self.horde_captured(board) {
+            Some("Horde captured")
+        } else if board.board.num_repetitions >= 2 {
+            Some("Threefold repetition")
+        } else if board.halfmove_clock >= 100 {
+            Some("Fifty-move rule")
+        } else if self.is_insufficient_material(board) {
+            Some("Insufficient material")
+        } else if !self
+            .legal_moves::<false>(self.turn(board), board)
+            .is_empty()
+        {
+            None
+        } else if self.in_check(board) {
+            Some("Checkmate")
+        } else {
+            Some("Stalemate")
+        }
+    }
 
-                total += dist_from_corner(
-                    Pos {
-                        idx: board.white_king.idx,
-                    }
-                    .to_grid()
-                    .unwrap(),
-                );
-                total -= dist_from_corner(
-                    Pos {
-                        idx: board.black_king.idx,
-                    }
-                    .to_grid()
-                    .unwrap(),
-                );
-            }
-            AbsScore::Heuristic(total)
+    // Promotions to different pieces display alike, so moves go by their coordinates instead
+    fn move_name(&self, mv: &Self::Move) -> String {
+        mv.uci()
+    }
+
+    fn explain_score(&self, state: &mut Self::State) -> Vec<(&'static str, Self::HeuristicScore)> {
+        match self.score(state) {
+            AbsScore::Heuristic(_) => EvalParams::DEFAULT
+                .terms(&self.eval_features(state))
+                .to_vec(),
+            _ => vec![],
         }
     }
 }
@@ -2079,8 +2814,16 @@ impl GridGame for Chess {
     const ROWS: usize = 8;
     const COLS: usize = 8;
 
+    fn rows(&self) -> usize {
+        Chess::rows(self)
+    }
+
+    fn cols(&self) -> usize {
+        Chess::cols(self)
+    }
+
     fn piece(&self, state: &Self::State, row: usize, col: usize) -> super::Piece {
-        state.get(Pos::from_grid(row, col)).piece()
+        state.get(self.grid_to_pos(row, col)).piece()
     }
 
     type MoveSelectionState = MoveSelectionState;
@@ -2089,6 +2832,32 @@ impl GridGame for Chess {
         MoveSelectionState::Initial
     }
 
+    // Kings are never taken so aren't counted
+    fn material(&self, piece: Piece) -> Option<(Player, i64)> {
+        let value = match piece {
+            Piece::WhitePawn
+            | Piece::WhiteBerolinaPawn
+            | Piece::BlackPawn
+            | Piece::BlackBerolinaPawn => 1,
+            Piece::WhiteGrasshopper | Piece::BlackGrasshopper => 2,
+            Piece::WhiteKnight | Piece::BlackKnight | Piece::WhiteBishop | Piece::BlackBishop => 3,
+            Piece::WhiteRook | Piece::BlackRook => 5,
+            Piece::WhiteQueen | Piece::BlackQueen => 9,
+            _ => return None,
+        };
+        Some((piece.letter()?.1, value))
+    }
+
+    fn checked_cell(&self, board: &Self::State) -> Option<(usize, usize)> {
+        let king = match self.turn(board) {
+            Player::First => board.white_king,
+            Player::Second => board.black_king,
+        }?;
+        self.in_check(board)
+            .then(|| self.pos_to_grid(king))
+            .flatten()
+    }
+
     fn show_move(
         &self,
         _turn: Player,
@@ -2098,8 +2867,10 @@ impl GridGame for Chess {
         cell_to_rect: impl Fn(usize, usize) -> egui::Rect,
         painter: &Painter,
     ) {
-        let pos_to_rect =
-            |pos: Pos| -> Option<Rect> { pos.to_grid().map(|(row, col)| cell_to_rect(row, col)) };
+        let pos_to_rect = |pos: Pos| -> Option<Rect> {
+            self.pos_to_grid(pos)
+                .map(|(row, col)| cell_to_rect(row, col))
+        };
 
         let highlight = |pos: Pos, color: Color32| {
             if let Some(rect) = pos_to_rect(pos) {
@@ -2139,7 +2910,7 @@ impl GridGame for Chess {
         let moves = self.legal_moves::<false>(turn, &mut board.clone());
         match (action, move_selection_state.clone()) {
             (super::MoveSelectionAction::ClickSquare { row, col }, MoveSelectionState::Initial) => {
-                let pos = Pos::from_grid(row, col);
+                let pos = self.grid_to_pos(row, col);
                 let pos_content = board.get(pos);
                 if pos_content.owner() == Some(turn) {
                     *move_selection_state = MoveSelectionState::PieceSelected { row, col };
@@ -2155,9 +2926,9 @@ impl GridGame for Chess {
                     col: piece_col,
                 },
             ) => {
-                let pos = Pos::from_grid(row, col);
+                let pos = self.grid_to_pos(row, col);
                 let pos_content = board.get(pos);
-                let piece_pos = Pos::from_grid(piece_row, piece_col);
+                let piece_pos = self.grid_to_pos(piece_row, piece_col);
                 for mv in moves {
                     match mv {
                         Move::Teleport { from, to, .. }
@@ -2168,9 +2939,15 @@ impl GridGame for Chess {
                             }
                         }
                         Move::Castle {
-                            king_from, king_to, ..
+                            king_from,
+                            king_to,
+                            rook_from,
+                            ..
                         } => {
-                            if king_from == piece_pos && king_to == pos {
+                            // Castle by moving the king to its destination or onto its rook
+                            if king_from == piece_pos
+                                && (rook_from == pos || (king_to == pos && king_to != king_from))
+                            {
                                 return Some(mv);
                             }
                         }
@@ -2206,7 +2983,7 @@ impl GridGame for Chess {
         painter: &Painter,
     ) {
         let highlight = |pos: Pos, color: Color32| {
-            if let Some((row, col)) = pos.to_grid() {
+            if let Some((row, col)) = self.pos_to_grid(pos) {
                 let rect = cell_to_rect(row, col)
                     .shrink(0.03 * cell_size)
                     .shrink(0.03 * cell_size);
@@ -2231,7 +3008,7 @@ impl GridGame for Chess {
         match move_selection_state {
             MoveSelectionState::Initial => {}
             MoveSelectionState::PieceSelected { row, col } => {
-                let selected_pos = Pos::from_grid(*row, *col);
+                let selected_pos = self.grid_to_pos(*row, *col);
                 highlight(
                     selected_pos,
                     Color32::CYAN
@@ -2263,10 +3040,20 @@ impl GridGame for Chess {
                             }
                         }
                         Move::Castle {
-                            king_from, king_to, ..
+                            king_from,
+                            king_to,
+                            rook_from,
+                            ..
                         } => {
                             if king_from == selected_pos {
-                                draw_move(king_to, false);
+                                draw_move(
+                                    if king_to == king_from {
+                                        rook_from
+                                    } else {
+                                        king_to
+                                    },
+                                    false,
+                                );
                             }
                         }
                     }
@@ -2305,14 +3092,7 @@ impl GridGame for Chess {
                             && from == *selected_from
                             && to == *selected_to
                             && ui
-                                .button(match promote_content.piece_raw() {
-                                    square::QUEEN => "Queen",
-                                    square::ROOK => "Rook",
-                                    square::BISHOP => "Bishop",
-                                    square::KNIGHT => "Knight",
-                                    square::GRASSHOPPER => "Grasshopper",
-                                    _ => "Unknown",
-                                })
+                                .button(pieces::kind(promote_content.piece_raw()).name)
                                 .clicked()
                         {
                             return Some(mv);
@@ -2325,4 +3105,513 @@ impl GridGame for Chess {
                 .unwrap(),
         }
     }
+
+    fn highlight_square(&self, row: usize, col: usize) -> Option<Color32> {
+        if matches!(self, Self::KingOfTheHill) && Self::is_hill(self.grid_to_pos(row, col)) {
+            Some(Color32::GOLD.gamma_multiply(0.4))
+        } else {
+            None
+        }
+    }
+
+    fn show_state_info(&self, state: &Self::State, ui: &mut egui::Ui) {
+        if matches!(self, Self::ThreeCheck) {
+            ui.label(format!(
+                "Checks: White {}/3, Black {}/3",
+                state.white_checks, state.black_checks
+            ));
+        }
+        ui.label(format!("Fifty-move rule: {}/100", state.halfmove_clock));
+    }
+
+    fn notation_styles(&self) -> &'static [NotationStyle] {
+        &NotationStyle::ALL
+    }
+
+    fn move_notation(&self, state: &Self::State, mv: &Self::Move, style: NotationStyle) -> String {
+        match style {
+            NotationStyle::Standard => self.san(state, mv),
+            NotationStyle::Long => self.long_algebraic(state, mv),
+            NotationStyle::Coordinate => mv.uci(),
+        }
+    }
+
+    // Any notation style, with or without the check mark
+    fn typed_names(&self, state: &Self::State, mv: &Self::Move) -> Vec<String> {
+        let mut names = vec![];
+        for style in NotationStyle::ALL {
+            let name = self.move_notation(state, mv, style);
+            let bare = name.trim_end_matches(['+', '#']).to_string();
+            names.push(name);
+            if !names.contains(&bare) {
+                names.push(bare);
+            }
+        }
+        names
+    }
+
+    fn export_game(
+        &self,
+        game: &Game<Self>,
+        style: NotationStyle,
+    ) -> Option<(&'static str, String)> {
+        Some(("pgn", pgn::export(game, style)))
+    }
+
+    fn evaluator_extensions(&self) -> &'static [&'static str] {
+        &["nnue", "txt"]
+    }
+
+    // Either a network or a config file of `EvalParams`
+    fn load_evaluator(&self, bytes: &[u8]) -> Result<Arc<dyn Evaluator<Self>>, String> {
+        if bytes.starts_with(b"NNUE") {
+            return Ok(Arc::new(nnue::Network::from_bytes(bytes)?));
+        }
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| "Not an NNUE network or a parameter file".to_string())?;
+        Ok(Arc::new(EvalParams::from_config(text)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn promotion() {
+        let mut game = pgn::import("1. e4 d5 2. exd5 c6 3. dxc6 Qd7 4. cxb7 Kd8").unwrap();
+        let logic = game.logic().clone();
+        let mut state = game.state().clone();
+        let before = state.clone();
+        let promotions = logic
+            .generate_moves(&mut state)
+            .into_iter()
+            .filter(|mv| matches!(mv, Move::PromotePawn { .. }))
+            .collect::<Vec<_>>();
+        // b8 and a8 each with 4 choices
+        assert_eq!(promotions.len(), 8);
+        for mv in &promotions {
+            logic.make_move(&mut state, mv);
+            logic.unmake_move(&mut state, mv);
+            assert_eq!(state, before);
+        }
+        let mv = logic.move_from_san(&state, "bxa8=N").unwrap();
+        game.make_move(mv);
+        assert_eq!(
+            game.logic().piece(game.state(), 0, 0),
+            crate::grid::Piece::WhiteKnight
+        );
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let game = pgn::import("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1").unwrap();
+        assert_eq!(game.repetitions(), 1);
+        assert!(!game.is_finished());
+        let game = pgn::import("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8").unwrap();
+        assert!(game.is_threefold_repetition());
+        assert!(matches!(game.score(), AbsScore::Draw));
+        assert!(matches!(
+            game.logic().score(&mut game.state().clone()),
+            AbsScore::Draw
+        ));
+    }
+
+    #[test]
+    fn halfmove_clock() {
+        let mut game = pgn::import("1. Nf3 Nf6 2. e4 Nc6 3. Bc4").unwrap();
+        assert_eq!(game.state().halfmove_clock, 2);
+        game.undo_move();
+        assert_eq!(game.state().halfmove_clock, 1);
+        let game = pgn::import("1. Nf3 Nf6 2. e4 Nxe4").unwrap();
+        assert_eq!(game.state().halfmove_clock, 0);
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let logic = Chess::Standard;
+        let mut board = logic.initial_state();
+        assert!(!logic.is_insufficient_material(&board));
+        for row in 0..8 {
+            for col in 0..8 {
+                let pos = Pos::from_grid(row, col);
+                let piece = board.get(pos);
+                if !piece.is_empty() && piece.piece_raw() != square::KING {
+                    board.set(pos, SquareContents::empty());
+                }
+            }
+        }
+        assert!(logic.is_insufficient_material(&board));
+    }
+
+    #[test]
+    fn fischer960_positions() {
+        assert_eq!(
+            Chess::Fischer960(FISCHER960_STANDARD).back_rank(),
+            Chess::Standard.back_rank()
+        );
+        let mut back_ranks = (0..960)
+            .map(|num| Chess::Fischer960(num).back_rank())
+            .collect::<Vec<_>>();
+        for back_rank in &back_ranks {
+            let bishops = (0..8).filter(|col| back_rank[*col] == 'B');
+            assert_eq!(bishops.map(|col| col % 2).sum::<usize>(), 1);
+            let king = back_rank.iter().position(|c| *c == 'K').unwrap();
+            assert!(back_rank[..king].contains(&'R') && back_rank[king..].contains(&'R'));
+        }
+        back_ranks.sort();
+        back_ranks.dedup();
+        assert_eq!(back_ranks.len(), 960);
+    }
+
+    #[test]
+    fn save_and_load_underpromotion() {
+        let mut game = pgn::import("1. e4 d5 2. exd5 c6 3. dxc6 Qd7 4. cxb7 Kd8").unwrap();
+        let mv = game.logic().move_from_uci(game.state(), "b7a8n").unwrap();
+        game.make_move(mv);
+        let saved = game.save();
+        assert_eq!(saved.moves.last().map(String::as_str), Some("b7a8n"));
+        let loaded = Game::load(Chess::Standard, &saved).unwrap();
+        assert_eq!(loaded.move_history(), game.move_history());
+        assert_eq!(
+            loaded.state().get(Pos::from_algebraic("a8").unwrap()),
+            SquareContents::white_knight()
+        );
+    }
+
+    #[test]
+    fn fischer960_castle_one_square() {
+        // The king castles from f1 to g1, which it could also step to
+        let num = (0..960)
+            .find(|num| {
+                Chess::Fischer960(*num).back_rank() == ['R', 'N', 'B', 'B', 'Q', 'K', 'N', 'R']
+            })
+            .unwrap();
+        let mut game = Game::new(Chess::Fischer960(num));
+        for text in ["g1f3", "g8f6"] {
+            let mv = game.logic().move_from_uci(game.state(), text).unwrap();
+            game.make_move(mv);
+        }
+        let castle = game.logic().move_from_uci(game.state(), "f1h1").unwrap();
+        let step = game.logic().move_from_uci(game.state(), "f1g1").unwrap();
+        assert!(matches!(castle, Move::Castle { .. }));
+        assert!(matches!(step, Move::Teleport { .. }));
+        assert_eq!(castle.to_string(), "O-O");
+        assert_ne!(step.to_string(), castle.to_string());
+        assert_eq!(castle.uci(), "f1h1");
+
+        game.make_move(castle);
+        let board = game.state();
+        assert_eq!(
+            board.get(Pos::from_algebraic("g1").unwrap()),
+            SquareContents::white_king()
+        );
+        assert_eq!(
+            board.get(Pos::from_algebraic("f1").unwrap()),
+            SquareContents::white_rook()
+        );
+    }
+
+    #[test]
+    fn attackers_match_captures() {
+        for logic in [Chess::Standard, Chess::Berolina, Chess::Grasshopper] {
+            let mut board = logic.initial_state();
+            let mut seed = 12345usize;
+            for _ in 0..40 {
+                for turn in [Player::First, Player::Second] {
+                    let captures = logic.pseudolegal_moves::<true>(turn.flip(), &board);
+                    for pos in logic.squares() {
+                        if board.get(pos).owner() != Some(turn) {
+                            continue;
+                        }
+                        let mut expected = captures
+                            .iter()
+                            .cloned()
+                            .filter_map(|mv| match mv {
+                                Move::Teleport {
+                                    from,
+                                    to,
+                                    capture: true,
+                                    ..
+                                } if to == pos => Some(from),
+                                Move::PromotePawn { from, to, .. }
+                                    if to == pos && !board.get(to).is_empty() =>
+                                {
+                                    Some(from)
+                                }
+                                _ => None,
+                            })
+                            .map(|from| from.idx)
+                            .collect::<Vec<_>>();
+                        expected.sort();
+                        expected.dedup();
+                        let mut actual = logic
+                            .attackers(turn, &board, pos)
+                            .into_iter()
+                            .map(|from| from.idx)
+                            .collect::<Vec<_>>();
+                        actual.sort();
+                        assert_eq!(actual, expected, "{} {}", logic.name(), pos.algebraic());
+                    }
+                }
+                let moves = logic.generate_moves(&mut board);
+                if moves.is_empty() {
+                    break;
+                }
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                logic.make_move(&mut board, &moves[(seed >> 33) % moves.len()]);
+            }
+        }
+    }
+
+    #[test]
+    fn zobrist_hash() {
+        let ident_after = |moves: &[&str]| {
+            let mut game = Game::new(Chess::Standard);
+            for text in moves {
+                let mv = game.logic().move_from_uci(game.state(), text).unwrap();
+                game.make_move(mv);
+            }
+            game.state().clone().position_ident()
+        };
+        let start = ident_after(&[]);
+        // Transpositions share a hash, while the side to move and castling rights change it
+        assert_eq!(
+            ident_after(&["e2e3", "e7e6", "d2d3"]).hash64(),
+            ident_after(&["d2d3", "e7e6", "e2e3"]).hash64()
+        );
+        assert_eq!(
+            ident_after(&["g1f3", "g8f6", "f3g1", "f6g8"]).hash64(),
+            start.hash64()
+        );
+        assert_ne!(
+            ident_after(&["g1f3", "g8f6", "f3g1"]).hash64(),
+            start.hash64()
+        );
+        assert_ne!(
+            ident_after(&["e2e4", "e7e5", "e1e2", "e8e7", "e2e1", "e7e8"]).hash64(),
+            ident_after(&["e2e4", "e7e5"]).hash64()
+        );
+        // Identical boards differ by the file on which a pawn can be taken en croissant
+        assert_ne!(
+            ident_after(&["e2e4", "d7d5", "e4e5", "f7f5"]).hash64(),
+            ident_after(&["e2e4", "f7f5", "e4e5", "d7d5"]).hash64()
+        );
+    }
+
+    #[test]
+    fn explain_score() {
+        let game = pgn::import("1. e4 d5 2. exd5 Nf6 3. Nc3").unwrap();
+        let logic = game.logic().clone();
+        let terms = logic.explain_score(&mut game.state().clone());
+        assert_eq!(
+            terms.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+            [
+                "Material",
+                "Pawn advancement",
+                "Mobility",
+                "King position",
+                "King safety"
+            ]
+        );
+        // White is a pawn up
+        assert_eq!(terms[0].1, pieces::kind(square::PAWN).value);
+        let AbsScore::Heuristic(total) = game.score() else {
+            panic!("the game isn't over");
+        };
+        assert_eq!(total, terms.iter().map(|(_, value)| value).sum::<i64>());
+    }
+
+    #[test]
+    fn king_safety() {
+        let features = |fen: &str| {
+            let logic = Chess::Custom(Arc::new(positions::StartPosition::from_fen(fen).unwrap()));
+            logic.eval_features(&mut logic.initial_state())
+        };
+        // Both castled, but white's pawns in front of the king have gone and black's queen bears down on it
+        let exposed = features("r4rk1/ppp2ppp/2nq1b2/8/8/2N5/PPP5/R2QR1K1 w - - 0 1");
+        assert_eq!(exposed.pawn_shield, -3);
+        assert_eq!(exposed.open_king_files, 3);
+        assert_eq!(exposed.king_attackers, 1);
+        let start = features("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(
+            (
+                start.pawn_shield,
+                start.open_king_files,
+                start.king_attackers
+            ),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn static_exchange() {
+        let exchange = |fen: &str, uci: &str| {
+            let logic = Chess::Custom(Arc::new(positions::StartPosition::from_fen(fen).unwrap()));
+            let mut state = logic.initial_state();
+            let mv = logic.move_from_uci(&state, uci).unwrap();
+            logic.exchange_value(&mut state, &mv)
+        };
+        let value = |piece_raw| pieces::kind(piece_raw).value;
+        // The pawn is defended by another
+        assert_eq!(
+            exchange("4k3/8/3p4/4p3/8/8/4Q3/4K3 w - - 0 1", "e2e5"),
+            Some(value(square::PAWN) - value(square::QUEEN))
+        );
+        // The second rook behind the first wins the exchange
+        assert_eq!(
+            exchange("k7/4r3/8/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2e5"),
+            Some(value(square::PAWN))
+        );
+        // Black is better off not recapturing with the queen, as the rook would take it
+        assert_eq!(
+            exchange("k3q3/8/8/4n3/3P4/8/8/4R1K1 w - - 0 1", "d4e5"),
+            Some(value(square::KNIGHT))
+        );
+        assert_eq!(exchange("k7/8/8/8/8/8/4P3/6K1 w - - 0 1", "e2e4"), None);
+    }
+
+    #[test]
+    fn gardner() {
+        let mut game = Game::new(Chess::Gardner);
+        // Five single pawn pushes and two knight moves
+        assert_eq!(
+            game.logic().generate_moves(&mut game.state().clone()).len(),
+            7
+        );
+        assert!(game.logic().move_from_uci(game.state(), "b2b4").is_none());
+        assert!(game.logic().move_from_uci(game.state(), "b2b3").is_some());
+        assert_eq!(
+            game.logic().piece(game.state(), 0, 0),
+            crate::grid::Piece::BlackRook
+        );
+        assert_eq!(
+            game.logic().piece(game.state(), 4, 4),
+            crate::grid::Piece::WhiteKing
+        );
+        assert!(matches!(Chess::from_name("Gardner"), Some(Chess::Gardner)));
+        for text in ["b2b3", "c4b3", "a2b3", "a4a3", "c2c3", "a3a2", "e2e3"] {
+            let mv = game.logic().move_from_uci(game.state(), text).unwrap();
+            game.make_move(mv);
+        }
+        // The pawn on a2 is blocked by the rook on a1, but can promote by taking the knight on b1
+        let promotions = game
+            .logic()
+            .generate_moves(&mut game.state().clone())
+            .into_iter()
+            .filter(|mv| matches!(mv, Move::PromotePawn { .. }))
+            .count();
+        assert_eq!(promotions, 4);
+    }
+
+    #[test]
+    fn king_of_the_hill() {
+        let mut game = Game::new(Chess::KingOfTheHill);
+        for text in ["e2e4", "a7a6", "e1e2", "a6a5", "e2e3", "a5a4", "e3d4"] {
+            let mv = game.logic().move_from_uci(game.state(), text).unwrap();
+            game.make_move(mv);
+        }
+        assert!(game.is_finished());
+        assert!(matches!(game.score(), AbsScore::FirstPlayerWin));
+    }
+
+    #[test]
+    fn three_check() {
+        let mut game = Game::new(Chess::ThreeCheck);
+        for text in [
+            "e2e4", "d7d5", "f1b5", "c7c6", "b5c6", "b8c6", "d1h5", "a7a6", "h5f7",
+        ] {
+            let mv = game.logic().move_from_uci(game.state(), text).unwrap();
+            game.make_move(mv);
+        }
+        assert!(game.is_finished());
+        assert!(matches!(game.score(), AbsScore::FirstPlayerWin));
+        game.undo_move();
+        assert_eq!(game.state().white_checks, 2);
+        assert!(!game.is_finished());
+    }
+
+    #[test]
+    fn horde() {
+        let logic = Chess::Horde;
+        let mut board = logic.initial_state();
+        assert_eq!(
+            (0..8)
+                .flat_map(|row| (0..8).map(move |col| Pos::from_grid(row, col)))
+                .filter(|pos| board.get(*pos).owner() == Some(Player::First))
+                .count(),
+            36
+        );
+        assert!(matches!(logic.score(&mut board), AbsScore::Heuristic(_)));
+        for row in 2..8 {
+            for col in 0..8 {
+                board.set(Pos::from_grid(row, col), SquareContents::empty());
+            }
+        }
+        assert!(logic.generate_moves(&mut board).is_empty());
+        assert!(matches!(logic.score(&mut board), AbsScore::SecondPlayerWin));
+    }
+
+    #[test]
+    fn move_stages() {
+        let game = pgn::import("1. e4 d5 2. exd5 c6 3. dxc6 Qd7 4. cxb7 Kd8").unwrap();
+        let logic = game.logic().clone();
+        let mut state = game.state().clone();
+        let mut staged = vec![];
+        let mut stage = 0;
+        while let Some(moves) = logic.generate_move_stage(&mut state, stage) {
+            // Captures and promotions come before the quiet moves
+            if stage == 0 {
+                assert!(!moves.is_empty());
+                assert!(
+                    moves
+                        .iter()
+                        .all(|mv| mv.is_capture() || matches!(mv, Move::PromotePawn { .. }))
+                );
+            }
+            staged.extend(moves);
+            stage += 1;
+        }
+        let all = logic.generate_moves(&mut state);
+        assert_eq!(staged.len(), all.len());
+        assert!(all.iter().all(|mv| staged.contains(mv)));
+    }
+
+    #[test]
+    fn move_classification() {
+        let logic = Chess::Custom(Arc::new(
+            positions::StartPosition::from_fen("4k3/8/2p5/3q4/4P3/8/8/3QK3 w - - 0 1").unwrap(),
+        ));
+        let state = logic.initial_state();
+        let hint =
+            |text: &str| logic.move_ordering_hint(&logic.move_from_uci(&state, text).unwrap());
+        // Pawn takes queen, then queen takes queen, then the quiet moves
+        assert!(hint("e4d5") > hint("d1d5"));
+        assert!(hint("d1d5") > 0);
+        assert_eq!(hint("e4e5"), 0);
+
+        let mut state = state.clone();
+        let mut gives_check = |text: &str| {
+            let mv = logic.move_from_uci(&state, text).unwrap();
+            (logic.is_quiet_move(&mv), logic.gives_check(&mut state, &mv))
+        };
+        assert_eq!(gives_check("d1h5"), (true, true));
+        assert_eq!(gives_check("e4e5"), (true, false));
+        assert_eq!(gives_check("e4d5"), (false, false));
+    }
+
+    #[test]
+    fn typed_names() {
+        let logic = Chess::Custom(Arc::new(
+            positions::StartPosition::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap(),
+        ));
+        let state = logic.initial_state();
+        let mv = logic.move_from_uci(&state, "d1d8").unwrap();
+        assert_eq!(
+            logic.typed_names(&state, &mv),
+            ["Qd8+", "Qd8", "Qd1-d8+", "Qd1-d8", "d1d8"]
+        );
+    }
 }
@@ -0,0 +1,183 @@
+use crate::{
+    ai::evaluator::Evaluator,
+    game::{AbsScore, GameLogic, Player},
+    grid::chess::{BoardState, Chess, bitboard, square},
+};
+
+// The kinds of piece the network has inputs for. Variant pieces are left out.
+const KINDS: [u8; 6] = [
+    square::PAWN,
+    square::KNIGHT,
+    square::BISHOP,
+    square::ROOK,
+    square::QUEEN,
+    square::KING,
+];
+const FEATURES: usize = 2 * KINDS.len() * 64;
+// The hidden layer is clipped to 0..=QA, and the output is scaled from QA * QB down to centipawns
+const QA: i64 = 255;
+const QB: i64 = 64;
+const SCALE: i64 = 400;
+
+/*
+A small NNUE-style network scoring a position from white's point of view.
+There is one input for each (colour, kind of piece, square), with white's pieces first and squares numbered as in `bitboard`.
+These feed a single clipped ReLU hidden layer, which feeds the output.
+
+Files are little endian:
+    magic        b"NNUE"
+    hidden size  u32
+    weights      i16 x FEATURES x hidden size, all of the weights of the first input before the second
+    biases       i16 x hidden size
+    out weights  i16 x hidden size
+    out bias     i32
+ */
+pub struct Network {
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    hidden_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+impl std::fmt::Debug for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Network")
+            .field("hidden", &self.hidden)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Network {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut rest = bytes
+            .strip_prefix(b"NNUE")
+            .ok_or("Not an NNUE network file")?;
+        let mut take = |n: usize| -> Result<&[u8], String> {
+            if rest.len() < n {
+                return Err("The network file is truncated".to_string());
+            }
+            let (taken, remaining) = rest.split_at(n);
+            rest = remaining;
+            Ok(taken)
+        };
+        let hidden = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+        if hidden == 0 || hidden > 4096 {
+            return Err(format!("Unsupported hidden layer size {hidden}"));
+        }
+        let mut i16s = |n: usize| -> Result<Vec<i16>, String> {
+            Ok(take(2 * n)?
+                .chunks_exact(2)
+                .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+                .collect())
+        };
+        let feature_weights = i16s(FEATURES * hidden)?;
+        let hidden_biases = i16s(hidden)?;
+        let output_weights = i16s(hidden)?;
+        let output_bias = i32::from_le_bytes(take(4)?.try_into().unwrap());
+        if !rest.is_empty() {
+            return Err("Unexpected data after the network".to_string());
+        }
+        Ok(Self {
+            hidden,
+            feature_weights,
+            hidden_biases,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    // The inputs which are on for the pieces of `board`
+    fn active_features(board: &BoardState) -> impl Iterator<Item = usize> + '_ {
+        [Player::First, Player::Second]
+            .into_iter()
+            .enumerate()
+            .flat_map(move |(colour, player)| {
+                KINDS.iter().enumerate().flat_map(move |(kind, piece_raw)| {
+                    bitboard::squares(board.board.pieces(player, *piece_raw))
+                        .map(move |square| (colour * KINDS.len() + kind) * 64 + square)
+                })
+            })
+    }
+
+    // Centipawns in white's favour
+    pub fn evaluate(&self, board: &BoardState) -> i64 {
+        let mut accumulator = self
+            .hidden_biases
+            .iter()
+            .map(|bias| *bias as i64)
+            .collect::<Vec<_>>();
+        for feature in Self::active_features(board) {
+            let weights = &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden];
+            for (acc, weight) in accumulator.iter_mut().zip(weights) {
+                *acc += *weight as i64;
+            }
+        }
+        let output = accumulator
+            .into_iter()
+            .zip(&self.output_weights)
+            .map(|(acc, weight)| acc.clamp(0, QA) * *weight as i64)
+            .sum::<i64>()
+            + self.output_bias as i64;
+        output * SCALE / (QA * QB)
+    }
+}
+
+impl Evaluator<Chess> for Network {
+    // Wins, losses and draws are still decided by the rules, the network only replaces the heuristic
+    fn evaluate(&self, logic: &Chess, state: &mut BoardState) -> AbsScore<i64> {
+        match logic.score(state) {
+            AbsScore::Heuristic(_) => AbsScore::Heuristic(Network::evaluate(self, state)),
+            terminal => terminal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network_bytes(
+        hidden: usize,
+        feature_weight: impl Fn(usize) -> i16,
+        output_weight: i16,
+    ) -> Vec<u8> {
+        let mut bytes = b"NNUE".to_vec();
+        bytes.extend((hidden as u32).to_le_bytes());
+        for feature in 0..FEATURES {
+            for _ in 0..hidden {
+                bytes.extend(feature_weight(feature).to_le_bytes());
+            }
+        }
+        bytes.extend(std::iter::repeat_n(0u8, 2 * hidden));
+        for _ in 0..hidden {
+            bytes.extend(output_weight.to_le_bytes());
+        }
+        bytes.extend(0i32.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn load_and_evaluate() {
+        assert!(Network::from_bytes(b"").is_err());
+        assert!(Network::from_bytes(b"NNUE\x01\x00\x00\x00").is_err());
+        let mut bytes = network_bytes(1, |_| 0, 64);
+        bytes.push(0);
+        assert!(Network::from_bytes(&bytes).is_err());
+
+        // Only white pawns count, at 10 each
+        let network = Network::from_bytes(&network_bytes(
+            1,
+            |feature| if feature < 64 { 10 } else { 0 },
+            64,
+        ))
+        .unwrap();
+        let logic = Chess::Standard;
+        let mut state = logic.initial_state();
+        assert_eq!(network.evaluate(&state), 8 * 10 * 64 * SCALE / (QA * QB));
+        assert!(matches!(
+            Evaluator::evaluate(&network, &logic, &mut state),
+            AbsScore::Heuristic(125)
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{game::Game, grid::chess::Chess};
+
+/// A named line of moves in coordinate notation played from the initial position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opening {
+    pub name: String,
+    pub moves: Vec<String>,
+}
+
+impl Opening {
+    pub fn new(name: &str, moves: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            moves: moves.split_whitespace().map(|mv| mv.to_string()).collect(),
+        }
+    }
+
+    // Play the opening out from the initial position of `logic`
+    pub fn play(&self, logic: Chess) -> Result<Game<Chess>, String> {
+        let mut game = Game::new(logic);
+        for text in &self.moves {
+            match game.logic().move_from_uci(game.state(), text) {
+                Some(mv) => game.make_move(mv),
+                None => {
+                    return Err(format!(
+                        "Illegal move {text} in opening \"{}\" after {} moves",
+                        self.name,
+                        game.num_moves()
+                    ));
+                }
+            }
+        }
+        Ok(game)
+    }
+}
+
+// A small book of common openings, a few moves deep
+pub fn noob_book() -> Vec<Opening> {
+    vec![
+        Opening::new("Italian Game", "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5"),
+        Opening::new("Ruy Lopez", "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6"),
+        Opening::new("Scotch Game", "e2e4 e7e5 g1f3 b8c6 d2d4 e5d4 f3d4"),
+        Opening::new(
+            "Sicilian Najdorf",
+            "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6",
+        ),
+        Opening::new("French Defence", "e2e4 e7e6 d2d4 d7d5"),
+        Opening::new("Caro-Kann Defence", "e2e4 c7c6 d2d4 d7d5"),
+        Opening::new("Scandinavian Defence", "e2e4 d7d5 e4d5 d8d5"),
+        Opening::new("Pirc Defence", "e2e4 d7d6 d2d4 g8f6 b1c3 g7g6"),
+        Opening::new("Queen's Gambit Declined", "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6"),
+        Opening::new("Queen's Gambit Accepted", "d2d4 d7d5 c2c4 d5c4"),
+        Opening::new("Slav Defence", "d2d4 d7d5 c2c4 c7c6"),
+        Opening::new(
+            "King's Indian Defence",
+            "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6",
+        ),
+        Opening::new("Nimzo-Indian Defence", "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4"),
+        Opening::new("Dutch Defence", "d2d4 f7f5 g2g3 g8f6 f1g2"),
+        Opening::new("London System", "d2d4 d7d5 c1f4 g8f6 e2e3"),
+        Opening::new("English Opening", "c2c4 e7e5 b1c3 g8f6"),
+        Opening::new("Reti Opening", "g1f3 d7d5 c2c4"),
+    ]
+}
+
+/*
+Parse a user supplied book.
+One opening per line in the form `Name: e2e4 e7e5 ...`. The name is optional.
+Blank lines and lines starting with `#` are ignored.
+ */
+pub fn parse_book(text: &str) -> Result<Vec<Opening>, String> {
+    let mut openings = vec![];
+    for (line_num, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, moves) = match line.split_once(':') {
+            Some((name, moves)) => (name.trim().to_string(), moves),
+            None => (format!("Line {}", line_num + 1), line),
+        };
+        let opening = Opening::new(&name, moves);
+        if opening.moves.is_empty() {
+            return Err(format!("No moves on line {}", line_num + 1));
+        }
+        openings.push(opening);
+    }
+    Ok(openings)
+}
+
+// The position after each of the openings which are legal for `logic`
+pub fn opening_positions(logic: &Chess, openings: &[Opening]) -> Vec<Game<Chess>> {
+    openings
+        .iter()
+        .filter_map(|opening| opening.play(logic.clone()).ok())
+        .collect()
+}
+
+/*
+The positions engine matches start from, from the standard starting position: the openings of the book at `path` if there is one, else the built in book.
+Each is played twice by the match, once with each engine going first.
+A user's book is turned away if any of its lines isn't legal, rather than quietly playing fewer openings.
+ */
+#[cfg(not(target_arch = "wasm32"))]
+pub fn match_starts(path: Option<&str>) -> Result<Vec<Game<Chess>>, String> {
+    let Some(path) = path else {
+        return Ok(opening_positions(&Chess::Standard, &noob_book()));
+    };
+    let text =
+        std::fs::read_to_string(path).map_err(|err| format!("Couldn't read {path}: {err}"))?;
+    let starts = parse_book(&text)?
+        .iter()
+        .map(|opening| opening.play(Chess::Standard))
+        .collect::<Result<Vec<_>, _>>()?;
+    if starts.is_empty() {
+        return Err(format!("There are no openings in {path}"));
+    }
+    Ok(starts)
+}
+
+// Pick an opening which is legal for `logic` using `seed`
+pub fn random_opening(logic: &Chess, openings: &[Opening], seed: usize) -> Option<Game<Chess>> {
+    let games = opening_positions(logic, openings);
+    if games.is_empty() {
+        None
+    } else {
+        Some(games[seed % games.len()].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noob_book_is_legal() {
+        for opening in noob_book() {
+            let game = opening.play(Chess::Standard).unwrap();
+            assert_eq!(game.num_moves(), opening.moves.len());
+        }
+    }
+
+    #[test]
+    fn user_book() {
+        let book =
+            parse_book("# Two openings\n\nItalian Game: e2e4 e7e5 g1f3 b8c6 f1c4\n  d2d4 d7d5\n")
+                .unwrap();
+        assert_eq!(
+            book,
+            vec![
+                Opening::new("Italian Game", "e2e4 e7e5 g1f3 b8c6 f1c4"),
+                Opening::new("Line 4", "d2d4 d7d5"),
+            ]
+        );
+        assert!(parse_book("Empty:").is_err());
+        assert!(
+            Opening::new("Illegal", "e2e5")
+                .play(Chess::Standard)
+                .is_err()
+        );
+    }
+}
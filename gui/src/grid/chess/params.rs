@@ -0,0 +1,216 @@
+use crate::{
+    ai::evaluator::Evaluator,
+    game::{AbsScore, GameLogic},
+    grid::chess::{BoardState, Chess, pieces::PIECES},
+};
+
+/*
+What the hand written evaluation counts in a position, each from white's point of view.
+The evaluation is these counts weighted by `EvalParams`, so that the weights can be fitted to positions whose counts are worked out once.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalFeatures {
+    // How many more of each kind of piece white has, indexed like `PIECES`
+    pub pieces: [i64; PIECES.len()],
+    // How many more pawns white has at each distance from promotion
+    pub pawn_rows: [i64; 8],
+    // How many more pseudolegal moves white has
+    pub mobility: i64,
+    // How much further white's king is from the corners, counted only in the end game
+    pub king_position: i64,
+    // How many more pawns white has in front of its king, counted only before the end game, as are the others for the king's safety
+    pub pawn_shield: i64,
+    // How many more files on or next to its king white has none of its own pawns on
+    pub open_king_files: i64,
+    // How many more enemy pieces attack the squares on and around white's king
+    pub king_attackers: i64,
+}
+
+/*
+The weights of the hand written chess evaluation, in centipawns.
+`Chess::score` uses the defaults, and others can be loaded from a config file or fitted by `texel` to play with instead.
+A config file has a line `name = value` for each weight to change, with the pawn advancement as eight values, and `#` starting a comment.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalParams {
+    // Indexed like `PIECES`
+    pub piece_values: [i64; PIECES.len()],
+    // Added to a pawn's value, indexed by its distance to the promotion row
+    pub pawn_advancement: [i64; 8],
+    // For each pseudolegal move
+    pub mobility: i64,
+    // For each step of the king from the corners in the end game
+    pub king_position: i64,
+    // For each pawn up to two rows in front of the king, on its file or the next
+    pub pawn_shield: i64,
+    // For each file on or next to the king without a pawn of its own
+    pub open_king_file: i64,
+    // For each enemy piece attacking the king or a square next to it
+    pub king_attacker: i64,
+}
+
+impl EvalParams {
+    pub const DEFAULT: EvalParams = EvalParams {
+        piece_values: {
+            let mut values = [0; PIECES.len()];
+            let mut i = 0;
+            while i < PIECES.len() {
+                values[i] = PIECES[i].value;
+                i += 1;
+            }
+            values
+        },
+        pawn_advancement: [0, 400, 110, 10, 5, 5, 0, 0],
+        mobility: 1,
+        king_position: 1,
+        pawn_shield: 12,
+        open_king_file: -15,
+        king_attacker: -10,
+    };
+
+    // The evaluation of a position with `features` as named terms which add up to it
+    pub fn terms(&self, features: &EvalFeatures) -> [(&'static str, i64); 5] {
+        let dot = |weights: &[i64], counts: &[i64]| -> i64 {
+            weights.iter().zip(counts).map(|(w, c)| w * c).sum()
+        };
+        [
+            ("Material", dot(&self.piece_values, &features.pieces)),
+            (
+                "Pawn advancement",
+                dot(&self.pawn_advancement, &features.pawn_rows),
+            ),
+            ("Mobility", self.mobility * features.mobility),
+            ("King position", self.king_position * features.king_position),
+            (
+                "King safety",
+                self.pawn_shield * features.pawn_shield
+                    + self.open_king_file * features.open_king_files
+                    + self.king_attacker * features.king_attackers,
+            ),
+        ]
+    }
+
+    pub fn evaluate(&self, features: &EvalFeatures) -> i64 {
+        self.terms(features)
+            .into_iter()
+            .map(|(_, value)| value)
+            .sum()
+    }
+
+    // The name of each weight, as in a config file, with the weight. Kings are left out as their value never matters.
+    pub fn weights_mut(&mut self) -> Vec<(String, &mut i64)> {
+        let mut weights = vec![];
+        for (kind, value) in PIECES.iter().zip(&mut self.piece_values) {
+            if !kind.royal {
+                weights.push((kind.name.to_lowercase().replace(' ', "_"), value));
+            }
+        }
+        for (distance, value) in self.pawn_advancement.iter_mut().enumerate() {
+            weights.push((format!("pawn_advancement[{distance}]"), value));
+        }
+        weights.push(("mobility".to_string(), &mut self.mobility));
+        weights.push(("king_position".to_string(), &mut self.king_position));
+        weights.push(("pawn_shield".to_string(), &mut self.pawn_shield));
+        weights.push(("open_king_file".to_string(), &mut self.open_king_file));
+        weights.push(("king_attacker".to_string(), &mut self.king_attacker));
+        weights
+    }
+
+    pub fn from_config(text: &str) -> Result<Self, String> {
+        let mut params = Self::DEFAULT;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| format!("Line {}: {message}", number + 1);
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected name = value"))?;
+            let (name, value) = (name.trim(), value.trim());
+            let values = value
+                .split_whitespace()
+                .map(|value| value.parse::<i64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| error(&format!("{value:?} isn't a whole number")))?;
+            if name == "pawn_advancement" {
+                params.pawn_advancement = values
+                    .try_into()
+                    .map_err(|_| error("pawn_advancement needs 8 values"))?;
+                continue;
+            }
+            let [value] = values[..] else {
+                return Err(error(&format!("{name} needs one value")));
+            };
+            let weight = params
+                .weights_mut()
+                .into_iter()
+                .find(|(weight_name, _)| weight_name == name)
+                .ok_or_else(|| error(&format!("unknown parameter {name}")))?
+                .1;
+            *weight = value;
+        }
+        Ok(params)
+    }
+
+    pub fn to_config(&self) -> String {
+        let mut text = "# Chess evaluation parameters, in centipawns\n".to_string();
+        for (kind, value) in PIECES.iter().zip(&self.piece_values) {
+            if !kind.royal {
+                text.push_str(&format!(
+                    "{} = {value}\n",
+                    kind.name.to_lowercase().replace(' ', "_")
+                ));
+            }
+        }
+        text.push_str(&format!(
+            "pawn_advancement = {}\n",
+            self.pawn_advancement
+                .map(|value| value.to_string())
+                .join(" ")
+        ));
+        text.push_str(&format!("mobility = {}\n", self.mobility));
+        text.push_str(&format!("king_position = {}\n", self.king_position));
+        text.push_str(&format!("pawn_shield = {}\n", self.pawn_shield));
+        text.push_str(&format!("open_king_file = {}\n", self.open_king_file));
+        text.push_str(&format!("king_attacker = {}\n", self.king_attacker));
+        text
+    }
+}
+
+impl Evaluator<Chess> for EvalParams {
+    // Wins, losses and draws are still decided by the rules, only the heuristic is reweighted
+    fn evaluate(&self, logic: &Chess, state: &mut BoardState) -> AbsScore<i64> {
+        match logic.score(state) {
+            AbsScore::Heuristic(_) => {
+                AbsScore::Heuristic(EvalParams::evaluate(self, &logic.eval_features(state)))
+            }
+            terminal => terminal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config() {
+        let params = EvalParams::from_config(&EvalParams::DEFAULT.to_config()).unwrap();
+        assert_eq!(params, EvalParams::DEFAULT);
+
+        let params = EvalParams::from_config(
+            "# Cheaper knights\nknight = 280\n\npawn_advancement = 0 300 100 10 5 5 0 0\nmobility = 2 # more",
+        )
+        .unwrap();
+        assert_eq!(params.piece_values[3], 280);
+        assert_eq!(params.pawn_advancement[1], 300);
+        assert_eq!(params.mobility, 2);
+        assert_eq!(params.king_position, 1);
+
+        assert!(EvalParams::from_config("king = 5").is_err());
+        assert!(EvalParams::from_config("pawn = lots").is_err());
+        assert!(EvalParams::from_config("pawn_advancement = 1 2 3").is_err());
+        assert!(EvalParams::from_config("mobility").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use crate::{
+    game::GameLogic,
+    grid::chess::{Chess, positions::StartPosition},
+};
+
+/*
+Count the positions reachable in a fixed number of moves, for comparing move generation against other engines.
+Invoked from the command line as `--perft <depth> [fen]`, printing the count after each first move and then the total.
+ */
+pub fn run(args: &[String]) -> Result<(), String> {
+    let depth = args
+        .first()
+        .ok_or("Usage: --perft <depth> [fen]")?
+        .parse::<usize>()
+        .map_err(|e| format!("Invalid depth: {e}"))?;
+    let logic = if args.len() > 1 {
+        Chess::Custom(Arc::new(StartPosition::from_fen(&args[1..].join(" "))?))
+    } else {
+        Chess::Standard
+    };
+    let mut state = logic.initial_state();
+    let start = std::time::Instant::now();
+    let mut divide = logic.perft_divide(&mut state, depth);
+    divide.sort_by_key(|(mv, _)| mv.uci());
+    for (mv, nodes) in &divide {
+        println!("{}: {nodes}", mv.uci());
+    }
+    let total = divide.iter().map(|(_, nodes)| nodes).sum::<u64>();
+    println!();
+    println!("Nodes searched: {total}");
+    println!("Time: {:.3}s", start.elapsed().as_secs_f64());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Totals from https://www.chessprogramming.org/Perft_Results
+    const POSITIONS: &[(&str, &[u64])] = &[
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            &[20, 400, 8902, 197281],
+        ),
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862],
+        ),
+        (
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238],
+        ),
+        (
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467],
+        ),
+        (
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379],
+        ),
+    ];
+
+    fn check(max_nodes: u64) {
+        for (fen, counts) in POSITIONS {
+            let logic = Chess::Custom(Arc::new(StartPosition::from_fen(fen).unwrap()));
+            let mut state = logic.initial_state();
+            for (depth, count) in counts.iter().enumerate() {
+                if *count <= max_nodes {
+                    assert_eq!(
+                        logic.perft(&mut state, depth + 1),
+                        *count,
+                        "{fen} depth {}",
+                        depth + 1
+                    );
+                }
+            }
+            assert_eq!(state, logic.initial_state());
+        }
+    }
+
+    #[test]
+    fn shallow() {
+        check(3000);
+    }
+
+    // Too slow for debug builds, run with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn deep() {
+        check(u64::MAX);
+    }
+
+    #[test]
+    fn divide_sums_to_perft() {
+        let logic = Chess::Standard;
+        let mut state = logic.initial_state();
+        let divide = logic.perft_divide(&mut state, 2);
+        assert_eq!(divide.len(), 20);
+        assert!(divide.iter().all(|(_, nodes)| *nodes == 20));
+        assert!(run(&["0".to_string()]).is_ok());
+        assert!(run(&["x".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+use std::sync::Arc;
+
+use crate::{
+    game::{AbsScore, Game, GameResult},
+    grid::{
+        GridGame,
+        chess::{Chess, positions::StartPosition},
+        notation::{MoveFormatter, NotationStyle},
+    },
+};
+
+fn result_tag(game: &Game<Chess>) -> &'static str {
+    match game.score() {
+        AbsScore::FirstPlayerWin => "1-0",
+        AbsScore::SecondPlayerWin => "0-1",
+        AbsScore::Draw => "1/2-1/2",
+        AbsScore::Heuristic(_) => "*",
+    }
+}
+
+// The moves are written in `style`, which other programs may only read if it's SAN
+pub fn export(game: &Game<Chess>, style: NotationStyle) -> String {
+    let result = result_tag(game);
+    let mut headers = vec![
+        ("Event", "Casual Game".to_string()),
+        ("Site", "Two Player Games".to_string()),
+        ("Date", chrono::Local::now().format("%Y.%m.%d").to_string()),
+        ("Round", "-".to_string()),
+        ("White", "?".to_string()),
+        ("Black", "?".to_string()),
+        ("Result", result.to_string()),
+    ];
+    if !matches!(game.logic(), Chess::Standard) {
+        headers.push(("Variant", game.logic().name().to_string()));
+    }
+    if let Some(GameResult::Flagged(_)) = game.result() {
+        headers.push(("Termination", "time forfeit".to_string()));
+    }
+    match game.logic() {
+        Chess::Fischer960(_) => {
+            headers.push(("SetUp", "1".to_string()));
+            headers.push(("FEN", fischer960_fen(game.logic())));
+        }
+        Chess::Custom(position) => {
+            headers.push(("SetUp", "1".to_string()));
+            headers.push(("FEN", position.fen()));
+        }
+        _ => {}
+    }
+
+    let mut pgn = String::new();
+    for (key, value) in headers {
+        pgn.push_str(&format!("[{key} \"{value}\"]\n"));
+    }
+    pgn.push('\n');
+
+    // Movetext, wrapped to 80 columns
+    let mut line = String::new();
+    let mut push_token = |pgn: &mut String, token: String| {
+        if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    };
+    // Games from a position with black to move start on an odd ply
+    let first_ply = match game.logic() {
+        Chess::Custom(position) => usize::from(position.black_to_move),
+        _ => 0,
+    };
+    let moves = MoveFormatter::new(game.logic(), style).write_history(game);
+    for (i, notation) in moves.into_iter().enumerate() {
+        let ply = first_ply + i;
+        if ply % 2 == 0 {
+            push_token(&mut pgn, format!("{}.", ply / 2 + 1));
+        } else if i == 0 {
+            push_token(&mut pgn, format!("{}...", ply / 2 + 1));
+        }
+        push_token(&mut pgn, notation);
+    }
+    // Endings which the moves don't show are given as a comment
+    let colour = |player| game.logic().player_name(player);
+    match game.result() {
+        Some(GameResult::Resigned(player)) => {
+            push_token(&mut pgn, format!("{{{} resigns}}", colour(player)))
+        }
+        Some(GameResult::AgreedDraw) => push_token(&mut pgn, "{Draw agreed}".to_string()),
+        Some(GameResult::Flagged(player)) => {
+            push_token(&mut pgn, format!("{{{} forfeits on time}}", colour(player)))
+        }
+        _ => {}
+    }
+    push_token(&mut pgn, result.to_string());
+    pgn.push_str(&line);
+    pgn.push('\n');
+    pgn
+}
+
+// The starting FEN of a Fischer random game
+pub fn fischer960_fen(logic: &Chess) -> String {
+    let black = logic.back_rank().iter().collect::<String>().to_lowercase();
+    let white = black.to_uppercase();
+    format!("{black}/pppppppp/8/8/8/8/PPPPPPPP/{white} w KQkq - 0 1")
+}
+
+fn parse_header(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (key, value) = inner.split_once(' ')?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((key.to_string(), value.replace("\\\"", "\"")))
+}
+
+// Remove comments, variations and numeric annotation glyphs
+fn strip_movetext(movetext: &str) -> String {
+    let mut out = String::new();
+    let mut brace_comment = false;
+    let mut line_comment = false;
+    let mut variation_depth = 0usize;
+    for c in movetext.chars() {
+        if line_comment {
+            if c == '\n' {
+                line_comment = false;
+                out.push(' ');
+            }
+        } else if brace_comment {
+            if c == '}' {
+                brace_comment = false;
+                out.push(' ');
+            }
+        } else {
+            match c {
+                '{' => brace_comment = true,
+                ';' => line_comment = true,
+                '(' => variation_depth += 1,
+                ')' => {
+                    variation_depth = variation_depth.saturating_sub(1);
+                    out.push(' ');
+                }
+                _ => {
+                    if variation_depth == 0 {
+                        out.push(c);
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+// Parse the first game in `text`
+pub fn import(text: &str) -> Result<Game<Chess>, String> {
+    let mut logic = Chess::Standard;
+    let mut fen = None;
+    let mut movetext = String::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && movetext.trim().is_empty() {
+            let (key, value) =
+                parse_header(trimmed).ok_or_else(|| format!("Invalid header: {trimmed}"))?;
+            // Standard chess from a custom position, which is given by the FEN header
+            if key == "Variant" && value != "From Position" {
+                logic = Chess::from_name(&value)
+                    .ok_or_else(|| format!("Unsupported variant: {value}"))?;
+            }
+            if key == "FEN" {
+                fen = Some(value);
+            }
+        } else {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+
+    // Custom positions are only supported for standard chess and the starting positions of Fischer random chess
+    if let Some(fen) = fen {
+        logic = match logic {
+            Chess::Fischer960(_) => fen
+                .split('/')
+                .next()
+                .and_then(Chess::fischer960_from_back_rank)
+                .filter(|logic| fischer960_fen(logic) == fen.trim())
+                .ok_or_else(|| "Not a Chess960 starting position".to_string())?,
+            Chess::Standard => Chess::Custom(Arc::new(StartPosition::from_fen(&fen)?)),
+            _ => {
+                return Err(format!(
+                    "Games from a custom start position are not supported in {}",
+                    logic.name()
+                ));
+            }
+        };
+    }
+
+    let mut game = Game::new(logic);
+    for token in strip_movetext(&movetext).split_whitespace() {
+        if token.starts_with('$') {
+            continue;
+        }
+        if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token) {
+            break;
+        }
+        // Move numbers such as `12.` or `12...`, possibly glued to the move
+        let token = match token.rfind('.') {
+            Some(idx) if token[..idx].chars().all(|c| c.is_ascii_digit() || c == '.') => {
+                &token[idx + 1..]
+            }
+            _ => token,
+        };
+        if token.is_empty() {
+            continue;
+        }
+        let mv = game
+            .logic()
+            .move_from_san(game.state(), token)
+            .ok_or_else(|| {
+                format!(
+                    "Illegal or unrecognised move {token} at ply {}",
+                    game.num_moves() + 1
+                )
+            })?;
+        game.make_move(mv);
+    }
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let pgn = "[Event \"Test\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 {Ruy Lopez} a6 (3... Nf6) 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O *";
+        let game = import(pgn).unwrap();
+        assert_eq!(game.num_moves(), 16);
+        let again = import(&export(&game, NotationStyle::Standard)).unwrap();
+        assert_eq!(game.move_history(), again.move_history());
+    }
+
+    #[test]
+    fn long_algebraic_roundtrip() {
+        let game = import("1. e4 d5 2. exd5 Qxd5 3. Nc3 Qa5 4. Bc4 Nf6 5. Nf3 Bg4 6. O-O").unwrap();
+        for style in [NotationStyle::Long, NotationStyle::Coordinate] {
+            let pgn = export(&game, style);
+            let again = import(&pgn).unwrap();
+            assert_eq!(game.move_history(), again.move_history());
+        }
+        assert!(export(&game, NotationStyle::Long).contains("2. e4xd5 Qd8xd5 3. Nb1-c3"));
+    }
+
+    #[test]
+    fn fischer960_roundtrip() {
+        let mut game = Game::new(Chess::Fischer960(0));
+        for text in ["f2f3", "f7f6", "f1f2", "f8f7", "g1h1"] {
+            let mv = game.logic().move_from_uci(game.state(), text).unwrap();
+            game.make_move(mv);
+        }
+        let pgn = export(&game, NotationStyle::Standard);
+        assert!(pgn.contains("O-O"));
+        let again = import(&pgn).unwrap();
+        assert!(matches!(again.logic(), Chess::Fischer960(0)));
+        assert_eq!(game.move_history(), again.move_history());
+    }
+
+    #[test]
+    fn custom_position_roundtrip() {
+        let position = StartPosition::from_fen("4k3/8/r7/4PK2/8/8/8/7R b - - 0 1").unwrap();
+        let mut game = Game::new(Chess::Custom(Arc::new(position)));
+        for text in ["a6b6", "h1h7", "b6b1"] {
+            let mv = game.logic().move_from_uci(game.state(), text).unwrap();
+            game.make_move(mv);
+        }
+        let pgn = export(&game, NotationStyle::Standard);
+        assert!(pgn.contains("1... Rb6 2. Rh7 Rb1"));
+        let again = import(&pgn).unwrap();
+        assert_eq!(game.move_history(), again.move_history());
+    }
+
+    #[test]
+    fn checkmate() {
+        let game = import("1. f3 e5 2. g4 Qh4# 0-1").unwrap();
+        let moves = MoveFormatter::new(game.logic(), NotationStyle::Standard).write_history(&game);
+        assert_eq!(moves.last().unwrap(), "Qh4#");
+        assert_eq!(result_tag(&game), "0-1");
+    }
+
+    #[test]
+    fn resignation() {
+        let mut game = import("1. e4 e5").unwrap();
+        game.resign(crate::game::Player::First);
+        let pgn = export(&game, NotationStyle::Standard);
+        assert!(pgn.contains("{White resigns} 0-1"));
+        assert!(!pgn.contains("Termination"));
+        let again = import(&pgn).unwrap();
+        assert_eq!(game.move_history(), again.move_history());
+    }
+}
@@ -0,0 +1,239 @@
+use std::sync::LazyLock;
+
+use crate::{
+    game::Player,
+    grid::chess::{DPos, bitboard, magic::Slider, square},
+};
+
+/*
+The ways a piece can move, as offsets (row, col) from white's point of view. Black's moves are mirrored top to bottom.
+Offsets must stay within two rows and two columns, so that they can't jump over the padding of the mailbox.
+ */
+#[derive(Debug, Clone, Copy)]
+pub enum Movement {
+    // Jump straight to each offset, like a knight or a king
+    Leap(&'static [(isize, isize)]),
+    // Slide along each direction until blocked, like a rook
+    Ride(&'static [(isize, isize)]),
+    // Slide along each direction to the first piece and land just beyond it, like a grasshopper
+    Hop(&'static [(isize, isize)]),
+    // Step onto an empty square, two steps from the pawn row, promoting on the last row
+    PawnPush(&'static [(isize, isize)]),
+    // Step only to capture, including en croissant, promoting on the last row
+    PawnCapture(&'static [(isize, isize)]),
+}
+
+#[derive(Debug)]
+pub struct PieceKind {
+    pub piece_raw: u8,
+    pub name: &'static str,
+    pub movement: &'static [Movement],
+    pub value: i64,
+    // Moving it counts as a king move for castling rights
+    pub royal: bool,
+}
+
+const ORTHOGONAL: &[(isize, isize)] = &[(0, 1), (-1, 0), (0, -1), (1, 0)];
+const DIAGONAL: &[(isize, isize)] = &[(1, 1), (-1, 1), (1, -1), (-1, -1)];
+const ADJACENT: &[(isize, isize)] = &[
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const KNIGHT: &[(isize, isize)] = &[
+    (1, 2),
+    (-1, 2),
+    (-2, 1),
+    (-2, -1),
+    (-1, -2),
+    (1, -2),
+    (2, -1),
+    (2, 1),
+];
+
+pub const PIECES: &[PieceKind] = &[
+    PieceKind {
+        piece_raw: square::PAWN,
+        name: "Pawn",
+        movement: &[
+            Movement::PawnPush(&[(-1, 0)]),
+            Movement::PawnCapture(&[(-1, -1), (-1, 1)]),
+        ],
+        value: 100,
+        royal: false,
+    },
+    PieceKind {
+        piece_raw: square::BEROLINA_PAWN,
+        name: "Berolina Pawn",
+        movement: &[
+            Movement::PawnCapture(&[(-1, 0)]),
+            Movement::PawnPush(&[(-1, -1), (-1, 1)]),
+        ],
+        value: 100,
+        royal: false,
+    },
+    PieceKind {
+        piece_raw: square::ROOK,
+        name: "Rook",
+        movement: &[Movement::Ride(ORTHOGONAL)],
+        value: 500,
+        royal: false,
+    },
+    PieceKind {
+        piece_raw: square::KNIGHT,
+        name: "Knight",
+        movement: &[Movement::Leap(KNIGHT)],
+        value: 300,
+        royal: false,
+    },
+    PieceKind {
+        piece_raw: square::BISHOP,
+        name: "Bishop",
+        movement: &[Movement::Ride(DIAGONAL)],
+        value: 300,
+        royal: false,
+    },
+    PieceKind {
+        piece_raw: square::QUEEN,
+        name: "Queen",
+        movement: &[Movement::Ride(ORTHOGONAL), Movement::Ride(DIAGONAL)],
+        value: 900,
+        royal: false,
+    },
+    PieceKind {
+        piece_raw: square::KING,
+        name: "King",
+        movement: &[Movement::Leap(ADJACENT)],
+        value: 10000,
+        royal: true,
+    },
+    PieceKind {
+        piece_raw: square::GRASSHOPPER,
+        name: "Grasshopper",
+        movement: &[Movement::Hop(ADJACENT)],
+        value: 40,
+        royal: false,
+    },
+];
+
+pub fn kind(piece_raw: u8) -> &'static PieceKind {
+    PIECES
+        .iter()
+        .find(|kind| kind.piece_raw == piece_raw)
+        .unwrap()
+}
+
+// The mailbox step for an offset of `player`'s piece
+pub fn offset(player: Player, (row, col): (isize, isize)) -> DPos {
+    match player {
+        Player::First => DPos::from_grid(row, col),
+        Player::Second => DPos::from_grid(-row, col),
+    }
+}
+
+/*
+Every way a piece of one player can attack a square.
+Leapers are listed by `piece_raw` with the bitboard of squares they attack each square from.
+Riders along all the rook or bishop directions are looked up with magic bitboards.
+Other riders and hoppers are grouped by the direction from the attacked square back towards the attacker.
+Each has a mask with bit `piece_raw` set for each kind of piece which attacks that way.
+ */
+#[derive(Debug, Default)]
+pub struct Attacks {
+    pub leaps: Vec<(u8, [u64; 64])>,
+    pub sliders: Vec<(Slider, u32)>,
+    pub rides: Vec<((isize, isize), u32)>,
+    pub hops: Vec<((isize, isize), u32)>,
+}
+
+impl Attacks {
+    fn new(player: Player) -> Self {
+        let mirror = |(row, col): (isize, isize)| match player {
+            Player::First => (row, col),
+            Player::Second => (-row, col),
+        };
+        let mut attacks = Self::default();
+        for kind in PIECES {
+            for movement in kind.movement {
+                match *movement {
+                    Movement::Leap(offsets) | Movement::PawnCapture(offsets) => {
+                        let mut from_squares = [0u64; 64];
+                        for from in 0..64 {
+                            for offset in offsets {
+                                if let Some(to) = bitboard::step(from, mirror(*offset)) {
+                                    from_squares[to] |= 1 << from;
+                                }
+                            }
+                        }
+                        match attacks.leaps.iter_mut().find(|(p, _)| *p == kind.piece_raw) {
+                            Some((_, existing)) => {
+                                for (existing, new) in existing.iter_mut().zip(from_squares) {
+                                    *existing |= new;
+                                }
+                            }
+                            None => attacks.leaps.push((kind.piece_raw, from_squares)),
+                        }
+                    }
+                    Movement::Ride(offsets) if let Some(slider) = Slider::from_offsets(offsets) => {
+                        match attacks.sliders.iter_mut().find(|(s, _)| *s == slider) {
+                            Some((_, mask)) => *mask |= 1 << kind.piece_raw,
+                            None => attacks.sliders.push((slider, 1 << kind.piece_raw)),
+                        }
+                    }
+                    Movement::Ride(offsets) | Movement::Hop(offsets) => {
+                        let list = if matches!(movement, Movement::Ride(_)) {
+                            &mut attacks.rides
+                        } else {
+                            &mut attacks.hops
+                        };
+                        for (row, col) in offsets {
+                            let back = mirror((-row, -col));
+                            match list.iter_mut().find(|(dir, _)| *dir == back) {
+                                Some((_, mask)) => *mask |= 1 << kind.piece_raw,
+                                None => list.push((back, 1 << kind.piece_raw)),
+                            }
+                        }
+                    }
+                    Movement::PawnPush(_) => {}
+                }
+            }
+        }
+        attacks
+    }
+
+    pub fn of(player: Player) -> &'static Self {
+        static ATTACKS: LazyLock<[Attacks; 2]> =
+            LazyLock::new(|| [Attacks::new(Player::First), Attacks::new(Player::Second)]);
+        match player {
+            Player::First => &ATTACKS[0],
+            Player::Second => &ATTACKS[1],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_fit_the_mailbox() {
+        for kind in PIECES {
+            assert_eq!(kind.piece_raw, self::kind(kind.piece_raw).piece_raw);
+            for movement in kind.movement {
+                let (Movement::Leap(offsets)
+                | Movement::Ride(offsets)
+                | Movement::Hop(offsets)
+                | Movement::PawnPush(offsets)
+                | Movement::PawnCapture(offsets)) = *movement;
+                for (row, col) in offsets {
+                    assert!(row.abs() <= 2 && col.abs() <= 2, "{}", kind.name);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use crate::{
+    game::GameLogic,
+    grid::chess::{Chess, castling},
+};
+
+/// A custom starting position for standard chess, such as an odds game or an endgame study.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartPosition {
+    // Rows from black's back rank down, with capitals for black pieces as in `Chess::initial_state`
+    pub(super) board: [[char; 8]; 8],
+    pub(super) castling_rights: castling::Rights,
+    pub(super) black_to_move: bool,
+}
+
+// Named positions offered in the menu, as FEN
+pub const PRESETS: &[(&str, &str)] = &[
+    (
+        "Standard",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    ),
+    (
+        "Queen odds",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1",
+    ),
+    (
+        "Rook odds",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w Kkq - 0 1",
+    ),
+    (
+        "Knight odds",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1",
+    ),
+    (
+        "Pawn and move",
+        "rnbqkbnr/ppppp1pp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    ),
+    ("King and queen vs king", "8/8/8/4k3/8/8/8/3QK3 w - - 0 1"),
+    (
+        "King and rook vs king and rook",
+        "4k2r/8/8/8/8/8/8/R3K3 w - - 0 1",
+    ),
+    ("Lucena position", "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1"),
+    ("Philidor position", "4k3/8/r7/4PK2/8/8/8/7R b - - 0 1"),
+    ("Réti study", "7K/8/k1P5/7p/8/8/8/8 w - - 0 1"),
+];
+
+impl StartPosition {
+    /*
+    Parse the board, side to move and castling fields of a FEN.
+    The side to move defaults to white and castling rights default to whatever the kings and rooks still allow.
+    The en passant square and the move counters are ignored.
+     */
+    pub fn from_fen(fen: &str) -> Result<Self, String> {
+        let mut fields = fen.split_whitespace();
+        let placement = fields.next().ok_or("The position is empty")?;
+        let ranks = placement.split('/').collect::<Vec<_>>();
+        if ranks.len() != 8 {
+            return Err(format!("Expected 8 ranks but found {}", ranks.len()));
+        }
+        let mut board = [[' '; 8]; 8];
+        for (row, rank) in ranks.into_iter().enumerate() {
+            let mut col = 0;
+            for c in rank.chars() {
+                if let Some(skip) = c.to_digit(10) {
+                    col += skip as usize;
+                } else if "pnbrqkPNBRQK".contains(c) {
+                    if col < 8 {
+                        // FEN capitals are white, but the board uses capitals for black
+                        board[row][col] = if c.is_ascii_uppercase() {
+                            c.to_ascii_lowercase()
+                        } else {
+                            c.to_ascii_uppercase()
+                        };
+                    }
+                    col += 1;
+                } else {
+                    return Err(format!("Unknown piece {c:?}"));
+                }
+            }
+            if col != 8 {
+                return Err(format!("Rank {} does not have 8 squares", 8 - row));
+            }
+        }
+
+        for king in ['k', 'K'] {
+            if board.iter().flatten().filter(|c| **c == king).count() != 1 {
+                return Err("Each side needs exactly one king".to_string());
+            }
+        }
+        if board[0]
+            .iter()
+            .chain(board[7].iter())
+            .any(|c| *c == 'p' || *c == 'P')
+        {
+            return Err("Pawns can't start on the first or last rank".to_string());
+        }
+
+        let black_to_move = match fields.next() {
+            None | Some("w") => false,
+            Some("b") => true,
+            Some(other) => return Err(format!("Unknown side to move {other:?}")),
+        };
+
+        // The squares which must be untouched for each castling right
+        let requirements = [
+            ('K', castling::WHITE_CAN_CASTLE_RIGHT, 'k', 7, 7),
+            ('Q', castling::WHITE_CAN_CASTLE_LEFT, 'k', 7, 0),
+            ('k', castling::BLACK_CAN_CASTLE_RIGHT, 'K', 0, 7),
+            ('q', castling::BLACK_CAN_CASTLE_LEFT, 'K', 0, 0),
+        ];
+        let possible = |king: char, row: usize, rook_col: usize| {
+            let rook = if king == 'k' { 'r' } else { 'R' };
+            board[row][4] == king && board[row][rook_col] == rook
+        };
+        let mut castling_rights = castling::Rights::full();
+        match fields.next() {
+            None => {
+                for (_, right, king, row, rook_col) in requirements {
+                    if !possible(king, row, rook_col) {
+                        castling_rights.remove(right);
+                    }
+                }
+            }
+            Some(field) => {
+                if field != "-" && !field.chars().all(|c| "KQkq".contains(c)) {
+                    return Err(format!("Unknown castling rights {field:?}"));
+                }
+                for (letter, right, king, row, rook_col) in requirements {
+                    if !field.contains(letter) {
+                        castling_rights.remove(right);
+                    } else if !possible(king, row, rook_col) {
+                        return Err(format!(
+                            "Castling right {letter} needs the king and rook on their starting squares"
+                        ));
+                    }
+                }
+            }
+        }
+
+        let position = Self {
+            board,
+            castling_rights,
+            black_to_move,
+        };
+        let logic = Chess::Custom(Arc::new(position.clone()));
+        let state = logic.initial_state();
+        if logic.is_check(logic.turn(&state).flip(), &state) {
+            return Err("The side not to move is in check".to_string());
+        }
+        Ok(position)
+    }
+
+    pub fn fen(&self) -> String {
+        let mut placement = vec![];
+        for row in &self.board {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for c in row {
+                if *c == ' ' {
+                    empty += 1;
+                } else {
+                    if empty > 0 {
+                        rank.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    rank.push(if c.is_ascii_uppercase() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        c.to_ascii_uppercase()
+                    });
+                }
+            }
+            if empty > 0 {
+                rank.push_str(&empty.to_string());
+            }
+            placement.push(rank);
+        }
+        let mut castling = [
+            ('K', castling::WHITE_CAN_CASTLE_RIGHT),
+            ('Q', castling::WHITE_CAN_CASTLE_LEFT),
+            ('k', castling::BLACK_CAN_CASTLE_RIGHT),
+            ('q', castling::BLACK_CAN_CASTLE_LEFT),
+        ]
+        .into_iter()
+        .filter(|(_, right)| self.castling_rights.has(*right))
+        .map(|(letter, _)| letter)
+        .collect::<String>();
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        format!(
+            "{} {} {castling} - 0 1",
+            placement.join("/"),
+            if self.black_to_move { 'b' } else { 'w' }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    #[test]
+    fn presets() {
+        for (name, fen) in PRESETS {
+            let position = StartPosition::from_fen(fen).unwrap_or_else(|e| panic!("{name}: {e}"));
+            assert_eq!(&position.fen(), fen);
+            let game = Game::new(Chess::Custom(Arc::new(position)));
+            assert!(!game.is_finished(), "{name}");
+        }
+        let standard = StartPosition::from_fen(PRESETS[0].1).unwrap();
+        assert_eq!(
+            Game::new(Chess::Custom(Arc::new(standard))).state(),
+            Game::new(Chess::Standard).state()
+        );
+    }
+
+    #[test]
+    fn invalid_positions() {
+        for fen in [
+            "8/8/8/8/8/8/8/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/3K4/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/P3K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+            "4k3/4R3/8/8/8/8/8/4K3 w - - 0 1",
+        ] {
+            assert!(StartPosition::from_fen(fen).is_err(), "{fen}");
+        }
+        // Rights are inferred from the pieces when the field is missing
+        let position = StartPosition::from_fen("r3k3/8/8/8/8/8/8/4K2R").unwrap();
+        assert_eq!(position.fen(), "r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1");
+    }
+}
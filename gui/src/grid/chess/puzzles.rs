@@ -0,0 +1,510 @@
+use std::sync::Arc;
+
+use crate::{
+    game::{Game, GameLogic, Player},
+    grid::{
+        GridGame, MoveSelectionAction,
+        chess::{Chess, Move, positions::StartPosition},
+        theme::{Appearance, PieceSet, PieceTextures},
+        ui::{Blindfold, BoardLayout, paint_board},
+    },
+    root::{AppState, Remembered},
+};
+
+/*
+The puzzles built in, one a line as `title | FEN | moves`.
+The moves alternate between the player and the replies made for them, in SAN or coordinate notation, and the last one mates.
+ */
+const BUILT_IN: &str = "
+Back rank | 6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1 | Rd8#
+Scholar's mate | r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4 | Qxf7#
+Fool's mate | rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2 | Qh4#
+Smothered | 6rk/6pp/8/6N1/8/8/8/6K1 w - - 0 1 | Nf7#
+Queen and king | k7/8/1K6/8/8/8/7Q/8 w - - 0 1 | Qh8#
+Back rank, from black | 3r2k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1 | Rd1#
+Deflection | 1r4k1/5ppp/8/8/3Q4/8/5PPP/3R2K1 w - - 0 1 | Qd8+ Rxd8 Rxd8#
+Philidor's legacy | r6k/6pp/7N/8/8/1Q6/6PP/6K1 w - - 0 1 | Qg8+ Rxg8 Nf7#
+";
+
+// How long before the reply to a correct move is played, so that it can be seen
+const REPLY_DELAY: chrono::TimeDelta = chrono::TimeDelta::milliseconds(500);
+
+// A position and the line which solves it
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    pub title: String,
+    pub logic: Chess,
+    pub solution: Vec<Move>,
+}
+
+impl Puzzle {
+    // How many of the player's moves it takes to mate
+    pub fn mate_in(&self) -> usize {
+        self.solution.len().div_ceil(2)
+    }
+}
+
+/*
+Read puzzles one a line as `title | FEN | moves`, skipping blank lines and those starting with `#`.
+Each solution is checked to be legal and to end in mate.
+ */
+pub fn parse(text: &str) -> Result<Vec<Puzzle>, String> {
+    let mut puzzles = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |reason: String| format!("Line {}: {reason}", number + 1);
+        let [title, fen, moves] = line.split('|').map(str::trim).collect::<Vec<_>>()[..] else {
+            return Err(error("Expected `title | FEN | moves`".to_string()));
+        };
+        let logic = Chess::Custom(Arc::new(StartPosition::from_fen(fen).map_err(error)?));
+        let mut state = logic.initial_state();
+        let mut solution = vec![];
+        for text in moves.split_whitespace() {
+            let mv = logic
+                .move_from_san(&state, text)
+                .ok_or_else(|| error(format!("Illegal move {text}")))?;
+            logic.make_move(&mut state, &mv);
+            solution.push(mv);
+        }
+        if solution.is_empty() {
+            return Err(error("No moves given".to_string()));
+        }
+        if !is_mate(&logic, &mut state) {
+            return Err(error("The moves don't end in mate".to_string()));
+        }
+        puzzles.push(Puzzle {
+            title: title.to_string(),
+            logic,
+            solution,
+        });
+    }
+    if puzzles.is_empty() {
+        return Err("No puzzles found".to_string());
+    }
+    Ok(puzzles)
+}
+
+pub fn built_in() -> Vec<Puzzle> {
+    parse(BUILT_IN).unwrap()
+}
+
+fn is_mate(logic: &Chess, state: &mut <Chess as GameLogic>::State) -> bool {
+    logic.in_check(state) && logic.generate_moves(state).is_empty()
+}
+
+// How many puzzles in a row have been solved without a mistake, kept between sessions
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Streak {
+    pub current: usize,
+    pub best: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Status {
+    Solving,
+    // The move tried which wasn't the solution, in SAN
+    Wrong(String),
+    Solved,
+    // The solution was shown rather than found
+    GaveUp,
+}
+
+/*
+Solving the puzzles one after another.
+Each move made must be the one in the solution, except the last which may be any move that mates, and the replies are made automatically.
+A puzzle counts towards the streak only if it's solved without a wrong move.
+ */
+pub struct State {
+    puzzles: Vec<Puzzle>,
+    current: usize,
+    game: Game<Chess>,
+    move_selection: <Chess as GridGame>::MoveSelectionState,
+    // When the next move of the solution is played for the other side
+    reply_at: Option<chrono::DateTime<chrono::Utc>>,
+    status: Status,
+    // A wrong move was made or the solution shown, so solving this puzzle doesn't count
+    missed: bool,
+    streak: Streak,
+    load_error: Option<String>,
+    #[cfg(target_arch = "wasm32")]
+    upload: Option<std::sync::mpsc::Receiver<String>>,
+    pieces: PieceTextures,
+}
+
+impl State {
+    pub fn new(ctx: &egui::Context) -> Self {
+        let puzzles = built_in();
+        let game = Game::new(puzzles[0].logic.clone());
+        Self {
+            move_selection: game.logic().initial_move_selection(),
+            puzzles,
+            current: 0,
+            game,
+            reply_at: None,
+            status: Status::Solving,
+            missed: false,
+            streak: Streak::default(),
+            load_error: None,
+            #[cfg(target_arch = "wasm32")]
+            upload: None,
+            pieces: PieceTextures::new(ctx, PieceSet::default()),
+        }
+    }
+
+    fn puzzle(&self) -> &Puzzle {
+        &self.puzzles[self.current]
+    }
+
+    // The solver plays the side to move at the start
+    fn solver(&self) -> Player {
+        let logic = &self.puzzle().logic;
+        logic.turn(&logic.initial_state())
+    }
+
+    fn start_puzzle(&mut self, index: usize) {
+        self.current = index % self.puzzles.len();
+        self.game = Game::new(self.puzzle().logic.clone());
+        self.move_selection = self.game.logic().initial_move_selection();
+        self.reply_at = None;
+        self.status = Status::Solving;
+        self.missed = false;
+    }
+
+    fn load_puzzles(&mut self, text: &str) {
+        match parse(text) {
+            Ok(puzzles) => {
+                self.puzzles = puzzles;
+                self.load_error = None;
+                self.start_puzzle(0);
+            }
+            Err(error) => self.load_error = Some(error),
+        }
+    }
+
+    fn try_move(&mut self, mv: Move) {
+        let progress = self.game.num_moves();
+        let expected = &self.puzzle().solution[progress];
+        let last = progress + 1 == self.puzzle().solution.len();
+        let mut after = self.game.state().clone();
+        self.game.logic().make_move(&mut after, &mv);
+        // Any mate will do for the last move
+        if mv == *expected || (last && is_mate(self.game.logic(), &mut after)) {
+            self.game.make_move(mv);
+            if last {
+                self.status = Status::Solved;
+                if !self.missed {
+                    self.streak.current += 1;
+                    self.streak.best = self.streak.best.max(self.streak.current);
+                }
+            } else {
+                self.status = Status::Solving;
+                self.reply_at = Some(chrono::Utc::now() + REPLY_DELAY);
+            }
+        } else {
+            self.status = Status::Wrong(self.game.logic().san(self.game.state(), &mv));
+            self.missed = true;
+            self.streak.current = 0;
+        }
+    }
+
+    fn play_reply(&mut self) {
+        if let Some(at) = self.reply_at
+            && chrono::Utc::now() >= at
+        {
+            self.reply_at = None;
+            let mv = self.puzzle().solution[self.game.num_moves()].clone();
+            self.game.make_move(mv);
+        }
+    }
+
+    // Play out the rest of the solution, which loses the streak
+    fn show_solution(&mut self) {
+        let solution = &self.puzzles[self.current].solution;
+        for mv in solution[self.game.num_moves()..].iter().cloned() {
+            self.game.make_move(mv);
+        }
+        self.reply_at = None;
+        self.status = Status::GaveUp;
+        self.missed = true;
+        self.streak.current = 0;
+    }
+
+    fn solving(&self) -> bool {
+        matches!(self.status, Status::Solving | Status::Wrong(_)) && self.reply_at.is_none()
+    }
+}
+
+impl AppState for State {
+    fn restore(&mut self, remembered: &Remembered) {
+        self.streak = remembered.puzzle_streak;
+    }
+
+    fn remember(&self, remembered: &mut Remembered) {
+        remembered.puzzle_streak = self.streak;
+    }
+
+    fn update(
+        &mut self,
+        ctx: &egui::Context,
+        frame: &mut eframe::Frame,
+        appearance: &Appearance,
+    ) -> Option<Box<dyn AppState>> {
+        self.pieces.choose(ctx, appearance.pieces);
+        self.play_reply();
+        #[cfg(target_arch = "wasm32")]
+        if let Some(text) = self
+            .upload
+            .as_ref()
+            .and_then(|upload| upload.try_recv().ok())
+        {
+            self.upload = None;
+            self.load_puzzles(&text);
+        }
+        let mut change_state: Option<Box<dyn AppState>> = None;
+
+        egui::SidePanel::left("left panel").show(ctx, |ui| {
+            if ui.button("Menu").clicked() {
+                change_state = Some(Box::new(crate::menu::State::default()));
+            }
+            ui.heading("Puzzles");
+
+            let puzzle = self.puzzle();
+            ui.label(format!(
+                "Puzzle {} of {}: {}",
+                self.current + 1,
+                self.puzzles.len(),
+                puzzle.title
+            ));
+            ui.label(format!(
+                "{} to move and mate in {}",
+                puzzle.logic.player_name(self.solver()),
+                puzzle.mate_in()
+            ));
+
+            match &self.status {
+                Status::Solving => {}
+                Status::Wrong(san) => {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("{san} isn't the solution, try again"),
+                    );
+                }
+                Status::Solved => {
+                    ui.colored_label(ui.visuals().hyperlink_color, "Solved!");
+                }
+                Status::GaveUp => {
+                    ui.label("The solution is shown on the board.");
+                }
+            }
+            ui.label(format!(
+                "Streak: {} (best {})",
+                self.streak.current, self.streak.best
+            ));
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Previous").clicked() {
+                    self.start_puzzle(self.current + self.puzzles.len() - 1);
+                }
+                if ui.button("Next").clicked() {
+                    self.start_puzzle(self.current + 1);
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Retry").clicked() {
+                    self.start_puzzle(self.current);
+                }
+                if ui
+                    .add_enabled(self.solving(), egui::Button::new("Show Solution"))
+                    .clicked()
+                {
+                    self.show_solution();
+                }
+            });
+
+            ui.separator();
+            ui.label("Puzzle files have one puzzle a line, as `title | FEN | moves`.");
+            ui.horizontal(|ui| {
+                if ui.button("Load Puzzles").clicked() {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(text) = crate::files::open_text("txt") {
+                        self.load_puzzles(&text);
+                    }
+                    #[cfg(target_arch = "wasm32")]
+                    {
+                        self.upload = Some(crate::files::upload_text("txt"));
+                    }
+                }
+                if ui.button("Built In Puzzles").clicked() {
+                    self.puzzles = built_in();
+                    self.load_error = None;
+                    self.start_puzzle(0);
+                }
+            });
+            if let Some(error) = &self.load_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+
+        let turn = self.game.turn();
+        if self.solving()
+            && let Some(mv) = self.game.logic().update_move_selection_ui(
+                turn,
+                self.game.state(),
+                &self.move_selection,
+                ctx,
+                frame,
+            )
+        {
+            self.move_selection = self.game.logic().initial_move_selection();
+            self.try_move(mv);
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let logic = self.game.logic().clone();
+            let layout = BoardLayout::new(&logic, ui.available_rect_before_wrap())
+                .flipped(self.solver() == Player::Second);
+            let painter = ui.painter();
+            paint_board(
+                ctx,
+                painter,
+                &logic,
+                self.game.state(),
+                &layout,
+                self.pieces.textures(),
+                &appearance.palette(ui.visuals()),
+                Blindfold::Off,
+            );
+            let cell_to_rect = |row, col| layout.cell_rect(row, col);
+            if let Some(mv) = self.game.move_history().last() {
+                let before = self.game.rewound(self.game.num_moves() - 1);
+                logic.show_move(
+                    before.turn(),
+                    before.state(),
+                    mv.clone(),
+                    layout.cell_size,
+                    cell_to_rect,
+                    painter,
+                );
+            }
+            logic.draw_move_selection_on_grid(
+                turn,
+                self.game.state(),
+                &self.move_selection,
+                layout.cell_size,
+                cell_to_rect,
+                painter,
+            );
+
+            let (pointer, pressed) =
+                ui.input(|i| (i.pointer.interact_pos(), i.pointer.primary_pressed()));
+            if pressed
+                && self.solving()
+                && !ui.ctx().is_using_pointer()
+                && let Some(pos) = pointer.filter(|pos| ui.max_rect().contains(*pos))
+            {
+                let action = match layout.cell_at(&logic, pos) {
+                    Some((row, col)) => MoveSelectionAction::ClickSquare { row, col },
+                    None => MoveSelectionAction::Reset,
+                };
+                if let Some(mv) = logic.update_move_selection(
+                    turn,
+                    self.game.state(),
+                    action,
+                    &mut self.move_selection,
+                ) {
+                    self.try_move(mv);
+                }
+            }
+        });
+
+        ctx.request_repaint();
+
+        change_state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether the side to move can force mate within `moves` of its moves
+    fn mates_in(logic: &Chess, state: &mut <Chess as GameLogic>::State, moves: usize) -> bool {
+        moves > 0
+            && logic.generate_moves(state).into_iter().any(|mv| {
+                logic.make_move(state, &mv);
+                let replies = logic.generate_moves(state);
+                let mates = if replies.is_empty() {
+                    logic.in_check(state)
+                } else {
+                    replies.into_iter().all(|reply| {
+                        logic.make_move(state, &reply);
+                        let mates = mates_in(logic, state, moves - 1);
+                        logic.unmake_move(state, &reply);
+                        mates
+                    })
+                };
+                logic.unmake_move(state, &mv);
+                mates
+            })
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            built_in().len(),
+            BUILT_IN.lines().filter(|line| !line.is_empty()).count()
+        );
+        let parse_error = |text| parse(text).unwrap_err();
+        assert_eq!(parse_error(""), "No puzzles found");
+        assert!(parse_error("Missing moves | 6k1/8/8/8/8/8/8/3R2K1 w - - 0 1").contains("Line 1"));
+        assert!(
+            parse_error("Illegal | 6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1 | Rd9#")
+                .contains("Illegal move Rd9#")
+        );
+        assert!(
+            parse_error("\nNo mate | 6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1 | Rd7")
+                .starts_with("Line 2: The moves don't end in mate")
+        );
+    }
+
+    // Every reply in each solution is forced to lose, and no other key move mates as quickly
+    // Slow without optimisations, so only run when asked for with `--ignored`
+    #[test]
+    #[ignore]
+    fn built_in_puzzles_are_sound() {
+        for puzzle in built_in() {
+            let logic = &puzzle.logic;
+            let mut state = logic.initial_state();
+            for (ply, mv) in puzzle.solution.iter().enumerate() {
+                let moves_left = puzzle.mate_in() - ply / 2;
+                if ply % 2 == 0 {
+                    assert!(mates_in(logic, &mut state, moves_left), "{}", puzzle.title);
+                    if moves_left > 1 {
+                        let others = logic
+                            .generate_moves(&mut state)
+                            .into_iter()
+                            .filter(|other| other != mv)
+                            .filter(|other| {
+                                let mut state = state.clone();
+                                logic.make_move(&mut state, other);
+                                !logic.generate_moves(&mut state).is_empty()
+                                    && logic.generate_moves(&mut state).into_iter().all(|reply| {
+                                        let mut state = state.clone();
+                                        logic.make_move(&mut state, &reply);
+                                        mates_in(logic, &mut state, moves_left - 1)
+                                    })
+                            })
+                            .count();
+                        assert_eq!(others, 0, "{} has another solution", puzzle.title);
+                    }
+                }
+                logic.make_move(&mut state, mv);
+            }
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use super::*;
+
+fn piece_letter(piece_raw: u8) -> Option<char> {
+    match piece_raw {
+        square::PAWN | square::BEROLINA_PAWN => None,
+        square::ROOK => Some('R'),
+        square::KNIGHT => Some('N'),
+        square::BISHOP => Some('B'),
+        square::QUEEN => Some('Q'),
+        square::KING => Some('K'),
+        square::GRASSHOPPER => Some('G'),
+        _ => unreachable!(),
+    }
+}
+
+// Remove check and annotation suffixes so SAN strings can be compared
+fn strip_suffixes(san: &str) -> &str {
+    san.trim_end_matches(['+', '#', '!', '?'])
+}
+
+impl Chess {
+    // Standard Algebraic Notation for a legal move `mv` in `board`
+    pub fn san(&self, board: &BoardState, mv: &Move) -> String {
+        let mut board = board.clone();
+        let turn = self.turn(&board);
+        let legal_moves = self.legal_moves::<false>(turn, &mut board);
+
+        let mut san = String::new();
+        if let Move::Castle { king_to, .. } = mv {
+            if king_to.to_grid().unwrap().1 == 6 {
+                san.push_str("O-O");
+            } else {
+                san.push_str("O-O-O");
+            }
+        } else {
+            let from = mv.from();
+            let to = mv.to();
+            let piece_raw = mv.moved_piece_raw();
+            let capture = mv.is_capture();
+
+            // Other pieces of the same type which could also move to `to`
+            let others = legal_moves
+                .iter()
+                .filter(|other| {
+                    other.from() != from
+                        && other.to() == to
+                        && other.moved_piece_raw() == piece_raw
+                        && !matches!(other, Move::Castle { .. })
+                })
+                .map(|other| other.from().to_grid().unwrap())
+                .collect::<Vec<_>>();
+            let (from_row, from_col) = from.to_grid().unwrap();
+            let file = (b'a' + from_col as u8) as char;
+            let rank = (b'0' + (8 - from_row) as u8) as char;
+
+            match piece_letter(piece_raw) {
+                Some(letter) => {
+                    san.push(letter);
+                    if !others.is_empty() {
+                        if others.iter().all(|(_, col)| *col != from_col) {
+                            san.push(file);
+                        } else if others.iter().all(|(row, _)| *row != from_row) {
+                            san.push(rank);
+                        } else {
+                            san.push(file);
+                            san.push(rank);
+                        }
+                    }
+                }
+                None => {
+                    if capture {
+                        san.push(file);
+                        if others.iter().any(|(_, col)| *col == from_col) {
+                            san.push(rank);
+                        }
+                    } else if !others.is_empty() {
+                        san.push_str(&from.algebraic());
+                    }
+                }
+            }
+            if capture {
+                san.push('x');
+            }
+            san.push_str(&to.algebraic());
+            if let Move::PromotePawn {
+                promote_content, ..
+            } = mv
+            {
+                san.push('=');
+                san.push(piece_letter(promote_content.piece_raw()).unwrap());
+            }
+        }
+
+        san.push_str(self.check_suffix(&board, mv));
+        san
+    }
+
+    // Long algebraic notation e.g. "Ng1-f3", "e4xd5" or "e7-e8=Q", giving both squares of every move but castling
+    pub fn long_algebraic(&self, board: &BoardState, mv: &Move) -> String {
+        let mut text = String::new();
+        if let Move::Castle { king_to, .. } = mv {
+            if king_to.to_grid().unwrap().1 == 6 {
+                text.push_str("O-O");
+            } else {
+                text.push_str("O-O-O");
+            }
+        } else {
+            if let Some(letter) = piece_letter(mv.moved_piece_raw()) {
+                text.push(letter);
+            }
+            text.push_str(&mv.from().algebraic());
+            text.push(if mv.is_capture() { 'x' } else { '-' });
+            text.push_str(&mv.to().algebraic());
+            if let Move::PromotePawn {
+                promote_content, ..
+            } = mv
+            {
+                text.push('=');
+                text.push(piece_letter(promote_content.piece_raw()).unwrap());
+            }
+        }
+        text.push_str(self.check_suffix(board, mv));
+        text
+    }
+
+    // "#" if `mv` mates, "+" if it checks and nothing otherwise
+    fn check_suffix(&self, board: &BoardState, mv: &Move) -> &'static str {
+        let mut board = board.clone();
+        let turn = self.turn(&board);
+        self.make_move(&mut board, mv);
+        if !self.is_check(turn.flip(), &board) {
+            ""
+        } else if self
+            .legal_moves::<false>(turn.flip(), &mut board)
+            .is_empty()
+        {
+            "#"
+        } else {
+            "+"
+        }
+    }
+
+    // Find the legal move in `board` written as `text` in SAN or long algebraic notation, falling back to coordinate notation
+    pub fn move_from_san(&self, board: &BoardState, text: &str) -> Option<Move> {
+        let text = strip_suffixes(text.trim()).replace('0', "O");
+        self.legal_moves::<false>(self.turn(board), &mut board.clone())
+            .into_iter()
+            .find(|mv| {
+                [self.san(board, mv), self.long_algebraic(board, mv)]
+                    .iter()
+                    .map(|name| strip_suffixes(name))
+                    .any(|name| name == text || name.replace('=', "") == text)
+            })
+            .or_else(|| self.move_from_uci(board, &text))
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    ai::{
+        Ai,
+        alphabeta::{SearchFeatures, singlethreaded::AlphaBeta},
+        arena::Contestant,
+        sprt::{Sprt, SprtTest, Verdict},
+        strength::Strength,
+        tournament::format_elo,
+    },
+    grid::chess::{Chess, openings::match_starts},
+};
+
+const USAGE: &str = "\
+Usage: --sprt [--elo0 <elo>] [--elo1 <elo>] [--alpha <p>] [--beta <p>] [--games <max games>] [--time <ms per move>] [--nodes <nodes per move>] [--book <file>] <feature>...
+Tests whether the search is stronger with the features than without them, where each feature is one of lmr, futility, delta, see or check.";
+
+fn contestant(
+    name: &str,
+    features: SearchFeatures,
+    strength: Strength,
+    move_time: chrono::TimeDelta,
+) -> Contestant<Chess> {
+    let mut ai = AlphaBeta::new();
+    ai.set_features(features);
+    ai.set_strength(strength);
+    Contestant {
+        name: name.to_string(),
+        ai: Box::new(ai),
+        move_time,
+    }
+}
+
+// The search with all of `names` switched off
+fn features_without(names: &[&str]) -> Result<SearchFeatures, String> {
+    names
+        .iter()
+        .try_fold(SearchFeatures::default(), |features, name| {
+            features.with(name, false)
+        })
+}
+
+/*
+Test a change to the chess search by self-play, playing the search with some of its features against the search without them until a sequential probability ratio test decides.
+Invoked from the command line as `--sprt`, printing the record and log likelihood ratio after each game and then the verdict.
+Both sides use the single threaded search, and a node limit makes each move take the same effort on a busy machine.
+ */
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut sprt = Sprt::default();
+    let mut max_games = 20_000;
+    let mut move_time = chrono::TimeDelta::milliseconds(100);
+    let mut strength = Strength {
+        name: "SPRT",
+        ..Strength::FULL
+    };
+    let mut book = None;
+    let mut names = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || -> Result<f64, String> {
+            args.next()
+                .and_then(|value| value.parse::<f64>().ok())
+                .ok_or(format!("{arg} needs a number\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--elo0" => sprt.elo0 = value()?,
+            "--elo1" => sprt.elo1 = value()?,
+            "--alpha" => sprt.alpha = value()?,
+            "--beta" => sprt.beta = value()?,
+            "--games" => max_games = value()? as usize,
+            "--time" => move_time = chrono::TimeDelta::milliseconds(value()? as i64),
+            "--nodes" => strength.max_nodes = Some(value()? as usize),
+            "--book" => book = Some(args.next().ok_or(format!("--book needs a file\n{USAGE}"))?),
+            name => names.push(name),
+        }
+    }
+    if names.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    let probability = |p: f64| p > 0.0 && p < 0.5;
+    if sprt.elo0 >= sprt.elo1 || !probability(sprt.alpha) || !probability(sprt.beta) {
+        return Err(format!(
+            "Needs elo0 < elo1 and alpha and beta between 0 and 0.5\n{USAGE}"
+        ));
+    }
+    let baseline = features_without(&names)?;
+
+    let starts = match_starts(book.map(String::as_str))?;
+    let mut test = SprtTest::new(
+        sprt,
+        contestant("With", SearchFeatures::default(), strength, move_time),
+        contestant("Without", baseline, strength, move_time),
+        starts,
+        max_games,
+    );
+    let (lower, upper) = sprt.bounds();
+    println!(
+        "Testing {} for elo0 {} against elo1 {}, stopping at an LLR of {lower:.2} or {upper:.2}",
+        names.join(", "),
+        sprt.elo0,
+        sprt.elo1
+    );
+    let mut games = 0;
+    while !test.is_finished() {
+        test.step(chrono::TimeDelta::milliseconds(10));
+        let record = test.record();
+        if record.games() > games {
+            games = record.games();
+            println!(
+                "Game {games}: +{} ={} -{}  Elo {}  LLR {:.2}",
+                record.wins,
+                record.draws,
+                record.losses,
+                record.elo_difference().map_or("-".to_string(), format_elo),
+                test.llr()
+            );
+        }
+    }
+    println!();
+    match test.verdict() {
+        Some(Verdict::Accepted) => println!(
+            "H1 accepted: the change is worth about {} Elo or more",
+            sprt.elo1
+        ),
+        Some(Verdict::Rejected) => println!(
+            "H0 accepted: the change is worth about {} Elo or less",
+            sprt.elo0
+        ),
+        None => println!("Inconclusive after {games} games"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::tournament::Record;
+
+    #[test]
+    fn features_and_llr() {
+        let features = features_without(&["lmr", "delta"]).unwrap();
+        assert!(!features.late_move_reductions);
+        assert!(features.futility_pruning);
+        assert!(!features.delta_pruning);
+        assert!(features_without(&["nullmove"]).is_err());
+
+        let sprt = Sprt::default();
+        let (lower, upper) = sprt.bounds();
+        assert!((upper - 2.944).abs() < 0.001);
+        assert!((lower + 2.944).abs() < 0.001);
+        assert_eq!(sprt.llr(&Record::default()), 0.0);
+        // An even score is evidence for the change being worth nothing
+        let even = Record {
+            wins: 1500,
+            draws: 2000,
+            losses: 1500,
+        };
+        assert!(sprt.llr(&even) < 0.0);
+        assert_eq!(sprt.verdict(&even), Some(Verdict::Rejected));
+        let winning = Record {
+            wins: 400,
+            draws: 400,
+            losses: 200,
+        };
+        assert_eq!(sprt.verdict(&winning), Some(Verdict::Accepted));
+        let close = Record {
+            wins: 11,
+            draws: 10,
+            losses: 9,
+        };
+        assert_eq!(sprt.verdict(&close), None);
+    }
+}
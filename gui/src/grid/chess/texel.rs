@@ -0,0 +1,222 @@
+use std::sync::Arc;
+
+use crate::{
+    game::{AbsScore, GameLogic},
+    grid::chess::{
+        Chess,
+        params::{EvalFeatures, EvalParams},
+        positions::StartPosition,
+    },
+};
+
+const USAGE: &str = "\
+Usage: --tune <positions file> [--params <config file>] [--passes <max passes>]
+Each line of the positions file is a FEN followed by the result of its game for white, one of 1-0, 0-1 or 1/2-1/2, or 1.0, 0.5 or 0.0.
+The fitted parameters are printed as a config file.";
+
+// A position from a game, with how the game ended for white
+struct LabeledPosition {
+    features: EvalFeatures,
+    result: f64,
+}
+
+fn parse_result(token: &str) -> Option<f64> {
+    match token.trim_matches(|c| "[]\";".contains(c)) {
+        "1-0" | "1.0" => Some(1.0),
+        "0-1" | "0.0" => Some(0.0),
+        "1/2-1/2" | "0.5" => Some(0.5),
+        _ => None,
+    }
+}
+
+// A line of a positions file, or None if the position is already over and so says nothing about the evaluation
+fn parse_line(line: &str) -> Result<Option<LabeledPosition>, String> {
+    let mut tokens = line.split_whitespace().collect::<Vec<_>>();
+    let result = tokens
+        .pop()
+        .and_then(parse_result)
+        .ok_or(format!("No result at the end of {line:?}"))?;
+    // The FEN may be followed by an EPD opcode for the result
+    let fen = tokens
+        .into_iter()
+        .filter(|token| *token != "c9")
+        .take(6)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let logic = Chess::Custom(Arc::new(StartPosition::from_fen(&fen)?));
+    let mut state = logic.initial_state();
+    Ok(match logic.score(&mut state) {
+        AbsScore::Heuristic(_) => Some(LabeledPosition {
+            features: logic.eval_features(&mut state),
+            result,
+        }),
+        _ => None,
+    })
+}
+
+// The expected result for white of a position scored `eval`, where `k` scales centipawns to winning chances
+fn expected_result(k: f64, eval: i64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval as f64 / 400.0))
+}
+
+// The mean squared difference between the results and what the evaluation expects of them
+fn error(positions: &[LabeledPosition], params: &EvalParams, k: f64) -> f64 {
+    positions
+        .iter()
+        .map(|position| {
+            (position.result - expected_result(k, params.evaluate(&position.features))).powi(2)
+        })
+        .sum::<f64>()
+        / positions.len().max(1) as f64
+}
+
+// The scaling of centipawns which best fits the results with the current parameters, by golden section search
+fn fit_k(positions: &[LabeledPosition], params: &EvalParams) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (0.01, 10.0);
+    for _ in 0..60 {
+        let a = high - ratio * (high - low);
+        let b = low + ratio * (high - low);
+        if error(positions, params, a) < error(positions, params, b) {
+            high = b;
+        } else {
+            low = a;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/*
+Texel's tuning method: fit the evaluation to the results of the games its positions came from.
+Having fixed how centipawns map to winning chances, each weight in turn is nudged up or down while that makes the evaluation predict the results better.
+The nudges start large and are halved whenever a pass over the weights finds no improvement.
+`report` is told the error after each pass.
+ */
+fn tune(
+    positions: &[LabeledPosition],
+    mut params: EvalParams,
+    max_passes: usize,
+    mut report: impl FnMut(usize, f64),
+) -> EvalParams {
+    let k = fit_k(positions, &params);
+    let mut best = error(positions, &params, k);
+    let num_weights = params.weights_mut().len();
+    let mut step = 16;
+    for pass in 0..max_passes {
+        let mut improved = false;
+        for i in 0..num_weights {
+            for delta in [step, -step] {
+                *params.weights_mut()[i].1 += delta;
+                let new_error = error(positions, &params, k);
+                if new_error < best {
+                    best = new_error;
+                    improved = true;
+                    break;
+                }
+                *params.weights_mut()[i].1 -= delta;
+            }
+        }
+        report(pass + 1, best);
+        if !improved {
+            if step == 1 {
+                break;
+            }
+            step /= 2;
+        }
+    }
+    params
+}
+
+/*
+Fit the chess evaluation parameters to a file of positions labeled with their games' results, starting from the defaults or a config file.
+Invoked from the command line as `--tune`, printing the error after each pass and then the fitted parameters, which can be loaded in the GUI.
+Positions are scored without a search, so they are best taken from quiet points of the games.
+ */
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut path = None;
+    let mut params = EvalParams::DEFAULT;
+    let mut max_passes = 1000;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--params" => {
+                let config_path = args.next().ok_or(USAGE)?;
+                let text = std::fs::read_to_string(config_path)
+                    .map_err(|err| format!("Failed to read {config_path}: {err}"))?;
+                params = EvalParams::from_config(&text)?;
+            }
+            "--passes" => {
+                max_passes = args
+                    .next()
+                    .and_then(|value| value.parse::<usize>().ok())
+                    .ok_or(USAGE)?
+            }
+            file => path = Some(file),
+        }
+    }
+    let path = path.ok_or(USAGE)?;
+    let text =
+        std::fs::read_to_string(path).map_err(|err| format!("Failed to read {path}: {err}"))?;
+    let mut positions = vec![];
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(position) =
+            parse_line(line).map_err(|err| format!("Line {}: {err}", number + 1))?
+        {
+            positions.push(position);
+        }
+    }
+    if positions.is_empty() {
+        return Err("No positions to tune on".to_string());
+    }
+
+    let k = fit_k(&positions, &params);
+    eprintln!(
+        "{} positions, K = {k:.3}, error {:.6}",
+        positions.len(),
+        error(&positions, &params, k)
+    );
+    let params = tune(&positions, params, max_passes, |pass, error| {
+        eprintln!("Pass {pass}: error {error:.6}")
+    });
+    print!("{}", params.to_config());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tune_knight() {
+        assert!(parse_line("4k3/8/8/8/8/8/8/4K3 w - - 0 1").is_err());
+        // A dead draw says nothing about the evaluation
+        assert!(
+            parse_line("4k3/8/8/8/8/8/8/4K3 w - - 0 1 1/2-1/2")
+                .unwrap()
+                .is_none()
+        );
+        let positions = [
+            "4k3/pppp4/8/8/8/8/PPPP4/3NK3 w - - 0 1 1-0",
+            "3nk3/pppp4/8/8/8/8/PPPP4/4K3 w - - c9 \"0-1\";",
+            "4k3/pppp4/8/8/8/8/PPPP4/4K3 w - - 0 1 [0.5]",
+        ]
+        .into_iter()
+        .map(|line| parse_line(line).unwrap().unwrap())
+        .collect::<Vec<_>>();
+        assert_eq!(positions[1].result, 0.0);
+
+        // Knights are worth nothing to start with, but winning with an extra one says otherwise
+        let mut params = EvalParams::DEFAULT;
+        params.piece_values[3] = 0;
+        let k = fit_k(&positions, &params);
+        let mut errors = vec![];
+        let tuned = tune(&positions, params.clone(), 20, |_, error| {
+            errors.push(error)
+        });
+        assert!(tuned.piece_values[3] > 0);
+        assert!(errors[errors.len() - 1] < error(&positions, &params, k));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    ai::{
+        Ai,
+        alphabeta::{multithreaded, singlethreaded},
+        random::Random,
+        strength::Strength,
+        tournament::{Entrant, Format, Tournament},
+        uci::UciEngine,
+    },
+    grid::chess::{Chess, openings::match_starts},
+};
+
+const USAGE: &str = "\
+Usage: --tournament [--gauntlet] [--games <games per match>] [--time <ms per move>] [--book <file>] <entrant> <entrant>...
+Each entrant is an AI, one of alphabeta, alphabeta-mt, random or uci, optionally followed by a strength,
+one of beginner, novice, club, expert or full, as in alphabeta:club.
+A book has an opening to a line, as `Name: e2e4 e7e5 ...`, and games are played from the built in one without it.";
+
+fn parse_entrant(spec: &str, move_time: chrono::TimeDelta) -> Result<Entrant<Chess>, String> {
+    let (kind, strength) = match spec.split_once(':') {
+        Some((kind, level)) => {
+            let strength = Strength::LEVELS
+                .into_iter()
+                .find(|strength| {
+                    strength
+                        .name
+                        .split_whitespace()
+                        .next()
+                        .is_some_and(|name| name.eq_ignore_ascii_case(level))
+                })
+                .ok_or(format!("Unknown strength {level}"))?;
+            (kind, strength)
+        }
+        None => (spec, Strength::FULL),
+    };
+    let new_ai: fn() -> Box<dyn Ai<Chess>> = match kind {
+        "alphabeta" => || Box::new(singlethreaded::AlphaBeta::new()),
+        "alphabeta-mt" => || Box::new(multithreaded::AlphaBeta::new()),
+        "random" => || Box::new(Random::new()),
+        "uci" => || Box::new(UciEngine::new()),
+        _ => return Err(format!("Unknown AI {kind}")),
+    };
+    Ok(Entrant {
+        name: spec.to_string(),
+        new_ai: Box::new(move || {
+            let mut ai = new_ai();
+            ai.set_strength(strength);
+            ai
+        }),
+        move_time,
+    })
+}
+
+/*
+Play a tournament between chess AIs without the GUI, starting from each opening in the book in turn.
+Invoked from the command line as `--tournament`, printing each match's result as it ends and then the standings.
+ */
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut format = Format::RoundRobin;
+    let mut games = None;
+    let mut book = None;
+    let mut move_time = chrono::TimeDelta::milliseconds(500);
+    let mut specs = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || -> Result<i64, String> {
+            args.next()
+                .and_then(|value| value.parse::<i64>().ok())
+                .filter(|value| *value > 0)
+                .ok_or(format!("{arg} needs a positive number\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--gauntlet" => format = Format::Gauntlet,
+            "--games" => games = Some(value()? as usize),
+            "--book" => book = Some(args.next().ok_or(format!("--book needs a file\n{USAGE}"))?),
+            "--time" => move_time = chrono::TimeDelta::milliseconds(value()?),
+            spec => specs.push(spec),
+        }
+    }
+    if specs.len() < 2 {
+        return Err(USAGE.to_string());
+    }
+    let entrants = specs
+        .into_iter()
+        .map(|spec| parse_entrant(spec, move_time))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let starts = match_starts(book.map(String::as_str))?;
+    let games = games.unwrap_or(2 * starts.len());
+    let mut tournament = Tournament::new(entrants, format, starts, games);
+    let mut finished_matches = 0;
+    while !tournament.is_finished() {
+        tournament.step(chrono::TimeDelta::milliseconds(10));
+        if tournament.match_idx() > finished_matches {
+            let (a, b) = tournament.pairings()[finished_matches];
+            let record = tournament.records()[finished_matches];
+            println!(
+                "{} v {}: +{} ={} -{}",
+                tournament.entrants()[a].name,
+                tournament.entrants()[b].name,
+                record.wins,
+                record.draws,
+                record.losses
+            );
+            finished_matches += 1;
+        }
+    }
+    println!();
+    print!("{}", tournament.report());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::tournament::Record;
+
+    #[test]
+    fn entrants_and_elo() {
+        let move_time = chrono::TimeDelta::milliseconds(100);
+        assert!(parse_entrant("alphabeta:club", move_time).is_ok());
+        assert!(parse_entrant("random", move_time).is_ok());
+        assert!(parse_entrant("alphabeta:grandmaster", move_time).is_err());
+        assert!(parse_entrant("minimax", move_time).is_err());
+
+        let record = Record {
+            wins: 50,
+            draws: 0,
+            losses: 50,
+        };
+        let (difference, margin) = record.elo_difference().unwrap();
+        assert!(difference.abs() < 1e-9);
+        assert!((65.0..75.0).contains(&margin));
+        // Scoring 75% is worth about 191 Elo
+        let record = Record {
+            wins: 5,
+            draws: 5,
+            losses: 0,
+        };
+        assert_eq!(record.elo_difference().unwrap().0.round(), 191.0);
+        assert_eq!(Record::default().elo_difference(), None);
+    }
+}
@@ -0,0 +1,363 @@
+use std::sync::{Arc, mpsc::Receiver};
+
+use crate::{
+    ai::{
+        Ai, SearchEvent,
+        alphabeta::multithreaded::AlphaBeta,
+        strength::Strength,
+        time::{Clock, TimeControl, TimeManager},
+        uci::UciGame,
+    },
+    game::{Game, GameLogic, Player},
+    grid::chess::{BoardState, Chess, Move, pgn, positions::StartPosition},
+};
+
+impl UciGame for Chess {
+    // Variants other than Chess960 aren't understood by engines
+    fn uci_position(game: &Game<Self>) -> Option<String> {
+        let mut position = match game.logic() {
+            Chess::Standard => "startpos".to_string(),
+            Chess::Fischer960(_) => format!("fen {}", pgn::fischer960_fen(game.logic())),
+            Chess::Custom(position) => format!("fen {}", position.fen()),
+            _ => return None,
+        };
+        if !game.move_history().is_empty() {
+            position.push_str(" moves");
+            for mv in game.move_history() {
+                position.push(' ');
+                position.push_str(&game.logic().uci_move(mv));
+            }
+        }
+        Some(position)
+    }
+
+    fn uci_options(&self) -> Vec<(&'static str, String)> {
+        vec![(
+            "UCI_Chess960",
+            matches!(self, Chess::Fischer960(_)).to_string(),
+        )]
+    }
+
+    // In Chess960 every castling move is written as the king capturing its rook
+    fn uci_move(&self, mv: &Move) -> String {
+        match (self, mv) {
+            (
+                Chess::Fischer960(_),
+                Move::Castle {
+                    king_from,
+                    rook_from,
+                    ..
+                },
+            ) => format!("{}{}", king_from.algebraic(), rook_from.algebraic()),
+            _ => mv.uci(),
+        }
+    }
+
+    fn move_from_uci(&self, state: &BoardState, text: &str) -> Option<Move> {
+        let text = text.trim().to_lowercase();
+        self.generate_moves(&mut state.clone())
+            .into_iter()
+            .find(|mv| self.uci_move(mv) == text)
+    }
+}
+
+/*
+A headless engine speaking the Universal Chess Interface on stdin and stdout, for use from GUIs such as Cutechess or Arena, or lichess-bot.
+Supports `uci`, `isready`, `setoption name MultiPV`, `ucinewgame`, `position`, `go` (with clock times, `movetime`, `depth`, `nodes`, `infinite` and `ponder`), `ponderhit`, `stop` and `quit`.
+ */
+pub fn run() {
+    let (sender, commands) = std::sync::mpsc::channel();
+    // Read commands on their own thread so that the search can carry on while waiting for them
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    Engine::new().run(commands);
+}
+
+// The time to think allowed by the arguments of a `go` command, or None to think until told to stop
+fn think_time(args: &[&str], turn: Player) -> Option<chrono::TimeDelta> {
+    let arg = |name: &str| {
+        args.iter()
+            .position(|arg| *arg == name)
+            .and_then(|idx| args.get(idx + 1))
+            .and_then(|value| value.parse::<i64>().ok())
+    };
+    if args.contains(&"infinite") {
+        return None;
+    }
+    if let Some(movetime) = arg("movetime") {
+        return Some(chrono::TimeDelta::milliseconds(movetime));
+    }
+    let (time, increment) = match turn {
+        Player::First => ("wtime", "winc"),
+        Player::Second => ("btime", "binc"),
+    };
+    let remaining = arg(time)?;
+    let clock = Clock::new(TimeControl {
+        base: chrono::TimeDelta::milliseconds(remaining),
+        increment: chrono::TimeDelta::milliseconds(arg(increment).unwrap_or(0)),
+        moves: arg("movestogo")
+            .filter(|moves| *moves > 0)
+            .map(|moves| moves as usize),
+    });
+    Some(TimeManager::default().allocate(&clock))
+}
+
+// The position described by the arguments of a `position` command
+fn parse_position(args: &[&str]) -> Result<Game<Chess>, String> {
+    let moves_idx = args.iter().position(|arg| *arg == "moves");
+    let (setup, moves) = match moves_idx {
+        Some(idx) => (&args[..idx], &args[idx + 1..]),
+        None => (args, &[][..]),
+    };
+    let logic = match setup {
+        ["startpos"] => Chess::Standard,
+        ["fen", fen @ ..] => Chess::Custom(Arc::new(StartPosition::from_fen(&fen.join(" "))?)),
+        _ => return Err(format!("Unknown position {}", setup.join(" "))),
+    };
+    let mut game = Game::new(logic);
+    for text in moves {
+        let mv = game
+            .logic()
+            .move_from_uci(game.state(), text)
+            .ok_or(format!("Illegal move {text}"))?;
+        game.make_move(mv);
+    }
+    Ok(game)
+}
+
+struct Engine {
+    ai: AlphaBeta<Chess>,
+    events: Receiver<SearchEvent<Chess>>,
+    game: Game<Chess>,
+    // Whether a search has been started and its best move not yet sent
+    searching: bool,
+    // Don't send the best move until told to stop, even if the search finishes first
+    wait_for_stop: bool,
+    // The search has finished, but is waiting for `stop` before sending its best move
+    finished: bool,
+    // The time to think once a ponder hit turns pondering into a normal search
+    ponder_time: Option<chrono::TimeDelta>,
+    start_time: chrono::DateTime<chrono::Utc>,
+    nodes: usize,
+}
+
+impl Engine {
+    fn new() -> Self {
+        let mut ai = AlphaBeta::new();
+        let events = ai.subscribe();
+        Self {
+            ai,
+            events,
+            game: Game::new(Chess::Standard),
+            searching: false,
+            wait_for_stop: false,
+            finished: false,
+            ponder_time: None,
+            start_time: chrono::Utc::now(),
+            nodes: 0,
+        }
+    }
+
+    fn run(&mut self, commands: Receiver<String>) {
+        loop {
+            match commands.recv_timeout(std::time::Duration::from_millis(10)) {
+                Ok(line) => {
+                    if !self.command(&line) {
+                        return;
+                    }
+                }
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+            if self.searching {
+                self.ai.think(chrono::TimeDelta::milliseconds(10));
+            }
+            self.receive_events();
+        }
+    }
+
+    // Returns false once told to quit
+    fn command(&mut self, line: &str) -> bool {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["uci"] => {
+                println!("id name Two-Player-Games");
+                println!("id author pishleback");
+                println!("option name MultiPV type spin default 1 min 1 max 5");
+                println!("uciok");
+            }
+            ["isready"] => println!("readyok"),
+            ["setoption", "name", "MultiPV", "value", value] => {
+                if let Ok(lines) = value.parse::<usize>() {
+                    self.ai.set_multi_pv(lines.clamp(1, 5));
+                }
+            }
+            // The transposition table is kept, as allocating a new one takes a while and old entries age out anyway
+            ["ucinewgame"] => self.game = Game::new(Chess::Standard),
+            ["position", args @ ..] => match parse_position(args) {
+                Ok(game) => self.game = game,
+                Err(error) => println!("info string {error}"),
+            },
+            ["go", args @ ..] => self.go(args),
+            // Without a time to think, pondering turns into an infinite search
+            ["ponderhit"] => {
+                if let Some(time) = self.ponder_time.take() {
+                    self.wait_for_stop = false;
+                    self.ai.stop_thinking_after(time);
+                    self.send_best_move_if_finished();
+                }
+            }
+            // Every `go` must be answered by a best move before the next command is handled
+            ["stop"] => {
+                self.wait_for_stop = false;
+                self.ai.stop_thinking();
+                self.send_best_move_if_finished();
+                while self.searching {
+                    self.ai.think(chrono::TimeDelta::milliseconds(10));
+                    self.receive_events();
+                }
+            }
+            ["quit"] => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn go(&mut self, args: &[&str]) {
+        let arg = |name: &str| {
+            args.iter()
+                .position(|arg| *arg == name)
+                .and_then(|idx| args.get(idx + 1))
+                .and_then(|value| value.parse::<usize>().ok())
+        };
+        self.ai.set_strength(Strength {
+            max_depth: arg("depth"),
+            max_nodes: arg("nodes"),
+            ..Strength::FULL
+        });
+        self.ai.set_game(self.game.clone());
+        self.searching = true;
+        self.finished = false;
+        self.start_time = chrono::Utc::now();
+        self.nodes = 0;
+        let time = think_time(args, self.game.turn());
+        self.ponder_time = None;
+        if args.contains(&"ponder") {
+            self.wait_for_stop = true;
+            self.ponder_time = time;
+        } else {
+            self.wait_for_stop = args.contains(&"infinite");
+            if let Some(time) = time {
+                self.ai.stop_thinking_after(time);
+            }
+        }
+    }
+
+    fn receive_events(&mut self) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                SearchEvent::Started => {}
+                SearchEvent::NewBestMove(_) => {}
+                SearchEvent::Nodes(nodes) => self.nodes = nodes,
+                SearchEvent::Depth(depth) => self.send_info(depth),
+                SearchEvent::Finished => {
+                    self.finished = true;
+                    self.send_best_move_if_finished();
+                }
+            }
+        }
+    }
+
+    fn send_info(&self, depth: usize) {
+        let millis = (chrono::Utc::now() - self.start_time)
+            .num_milliseconds()
+            .max(1);
+        let mut info = format!(
+            "info depth {depth} nodes {} nps {} time {millis}",
+            self.nodes,
+            self.nodes as i64 * 1000 / millis
+        );
+        if let Some(line) = self.ai.best_moves().into_iter().next() {
+            info.push_str(" pv");
+            for mv in line.moves {
+                info.push(' ');
+                info.push_str(&mv.uci());
+            }
+        }
+        println!("{info}");
+    }
+
+    fn send_best_move_if_finished(&mut self) {
+        if !self.searching || !self.finished || self.wait_for_stop {
+            return;
+        }
+        self.searching = false;
+        let line = self
+            .ai
+            .best_moves()
+            .into_iter()
+            .next()
+            .map(|line| line.moves)
+            .unwrap_or_default();
+        let best_move = self.ai.best_move();
+        match (best_move, line.get(1)) {
+            (Some(best_move), Some(ponder)) if line.first() == Some(&best_move) => {
+                println!("bestmove {} ponder {}", best_move.uci(), ponder.uci())
+            }
+            (Some(best_move), _) => println!("bestmove {}", best_move.uci()),
+            (None, _) => println!("bestmove 0000"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        let game = parse_position(&["startpos", "moves", "e2e4", "e7e5"]).unwrap();
+        assert_eq!(game.num_moves(), 2);
+        assert_eq!(game.turn(), Player::First);
+        let game = parse_position(&[
+            "fen",
+            "8/8/8/8/8/8/4pk2/7K",
+            "b",
+            "-",
+            "-",
+            "0",
+            "1",
+            "moves",
+            "f2g3",
+        ])
+        .unwrap();
+        assert_eq!(game.turn(), Player::First);
+        assert!(parse_position(&["startpos", "moves", "e2e5"]).is_err());
+        let game = parse_position(&["startpos", "moves", "e2e4", "e7e5"]).unwrap();
+        assert_eq!(
+            Chess::uci_position(&game).as_deref(),
+            Some("startpos moves e2e4 e7e5")
+        );
+        assert_eq!(Chess::uci_position(&Game::new(Chess::Horde)), None);
+
+        assert_eq!(think_time(&["infinite"], Player::First), None);
+        assert_eq!(
+            think_time(&["movetime", "500"], Player::First),
+            Some(chrono::TimeDelta::milliseconds(500))
+        );
+        // 30 moves assumed, with 100ms kept back, plus three quarters of the increment
+        assert_eq!(
+            think_time(
+                &["wtime", "1000", "btime", "3100", "binc", "400"],
+                Player::Second
+            ),
+            Some(chrono::TimeDelta::milliseconds(400))
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use std::fmt::Display;
+
+use egui::{Color32, Painter, Rect};
+
+use crate::{
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{GridGame, MoveSelectionAction, Piece},
+};
+
+const ROWS: usize = 6;
+const COLS: usize = 7;
+
+/*
+Each player's discs are stored as a bitboard with 7 bits per column, bit `7 * col + height`.
+The 7th bit of each column is always empty so that lines can be found with shifts.
+ */
+const COL_BITS: usize = ROWS + 1;
+// Shifts for vertical, horizontal and both diagonal lines
+const DIRECTIONS: [usize; 4] = [1, COL_BITS, COL_BITS - 1, COL_BITS + 1];
+// Columns nearest the centre are searched first
+const COL_ORDER: [usize; COLS] = [3, 2, 4, 1, 5, 0, 6];
+
+fn bit(col: usize, height: usize) -> u64 {
+    1 << (COL_BITS * col + height)
+}
+
+fn has_four(discs: u64) -> bool {
+    DIRECTIONS.iter().any(|shift| {
+        let pairs = discs & (discs >> shift);
+        pairs & (pairs >> (2 * shift)) != 0
+    })
+}
+
+fn player_idx(player: Player) -> usize {
+    match player {
+        Player::First => 0,
+        Player::Second => 1,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectFour;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState {
+    discs: [u64; 2],
+    heights: [usize; COLS],
+    move_num: usize,
+}
+
+impl BoardState {
+    fn get(&self, row: usize, col: usize) -> Option<Player> {
+        let b = bit(col, ROWS - 1 - row);
+        if self.discs[0] & b != 0 {
+            Some(Player::First)
+        } else if self.discs[1] & b != 0 {
+            Some(Player::Second)
+        } else {
+            None
+        }
+    }
+
+    // The row a disc dropped into `col` lands on
+    fn landing_row(&self, col: usize) -> Option<usize> {
+        (self.heights[col] < ROWS).then(|| ROWS - 1 - self.heights[col])
+    }
+
+    fn winner(&self) -> Option<Player> {
+        if has_four(self.discs[0]) {
+            Some(Player::First)
+        } else if has_four(self.discs[1]) {
+            Some(Player::Second)
+        } else {
+            None
+        }
+    }
+}
+
+impl State<ConnectFour> for BoardState {
+    fn ident(self) -> BoardStateIdent {
+        BoardStateIdent { discs: self.discs }
+    }
+
+    // Positions can't repeat since discs are never removed
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardStateIdent {
+    discs: [u64; 2],
+}
+
+impl NoAlloc for BoardStateIdent {}
+
+impl StateIdent<ConnectFour> for BoardStateIdent {
+    fn hash64(&self) -> u64 {
+        self.discs[0].wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ self.discs[1]
+                .wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+                .rotate_left(29)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    col: usize,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Column {}", self.col + 1)
+    }
+}
+
+impl ConnectFour {
+    // Would dropping a disc for `player` into `col` make four in a row?
+    fn wins_with(&self, board: &BoardState, player: Player, col: usize) -> bool {
+        board.heights[col] < ROWS
+            && has_four(board.discs[player_idx(player)] | bit(col, board.heights[col]))
+    }
+}
+
+impl GameLogic for ConnectFour {
+    type State = BoardState;
+    type StateIdent = BoardStateIdent;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        BoardState {
+            discs: [0, 0],
+            heights: [0; COLS],
+            move_num: 0,
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        if state.move_num.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        if state.winner().is_some() {
+            return vec![];
+        }
+        COL_ORDER
+            .into_iter()
+            .filter(|col| state.heights[*col] < ROWS)
+            .map(|col| Move { col })
+            .collect()
+    }
+
+    // Moves which win immediately or block the opponent from winning immediately
+    fn generate_quiescence_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        let turn = self.turn(state);
+        self.generate_moves(state)
+            .into_iter()
+            .filter(|mv| {
+                self.wins_with(state, turn, mv.col) || self.wins_with(state, turn.flip(), mv.col)
+            })
+            .collect()
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        match state.winner() {
+            Some(Player::First) => return AbsScore::FirstPlayerWin,
+            Some(Player::Second) => return AbsScore::SecondPlayerWin,
+            None => {}
+        }
+        if state.move_num == ROWS * COLS {
+            return AbsScore::Draw;
+        }
+
+        // Reward every line of four which only one player has discs in
+        const WEIGHTS: [i64; 5] = [0, 1, 4, 16, 0];
+        let mut total = 0;
+        for col in 0..COLS {
+            for height in 0..ROWS {
+                for (dcol, dheight) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+                    let end_col = col as isize + 3 * dcol;
+                    let end_height = height as isize + 3 * dheight;
+                    if end_col >= COLS as isize || !(0..ROWS as isize).contains(&end_height) {
+                        continue;
+                    }
+                    let mut line = 0;
+                    for i in 0..4 {
+                        line |= bit(
+                            (col as isize + i * dcol) as usize,
+                            (height as isize + i * dheight) as usize,
+                        );
+                    }
+                    let first = (state.discs[0] & line).count_ones() as usize;
+                    let second = (state.discs[1] & line).count_ones() as usize;
+                    if second == 0 {
+                        total += WEIGHTS[first];
+                    } else if first == 0 {
+                        total -= WEIGHTS[second];
+                    }
+                }
+            }
+        }
+        AbsScore::Heuristic(total)
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        debug_assert!(state.heights[mv.col] < ROWS);
+        let idx = player_idx(self.turn(state));
+        state.discs[idx] |= bit(mv.col, state.heights[mv.col]);
+        state.heights[mv.col] += 1;
+        state.move_num += 1;
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        debug_assert!(state.heights[mv.col] > 0);
+        state.move_num -= 1;
+        state.heights[mv.col] -= 1;
+        let idx = player_idx(self.turn(state));
+        state.discs[idx] &= !bit(mv.col, state.heights[mv.col]);
+    }
+}
+
+// Moves are made with a single click anywhere in a column, so there is nothing to track between clicks
+#[derive(Debug, Clone)]
+pub enum MoveSelectionState {
+    Initial,
+}
+
+impl GridGame for ConnectFour {
+    const ROWS: usize = ROWS;
+    const COLS: usize = COLS;
+
+    fn piece(&self, state: &Self::State, row: usize, col: usize) -> Piece {
+        match state.get(row, col) {
+            Some(Player::First) => Piece::RedDisc,
+            Some(Player::Second) => Piece::YellowDisc,
+            None => Piece::Empty,
+        }
+    }
+
+    type MoveSelectionState = MoveSelectionState;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        MoveSelectionState::Initial
+    }
+
+    fn player_name(&self, player: Player) -> &'static str {
+        match player {
+            Player::First => "Red",
+            Player::Second => "Yellow",
+        }
+    }
+
+    // Columns are numbered as in the moves, and rows go unnamed as a move only picks a column
+    fn col_label(&self, col: usize) -> Option<String> {
+        Some((col + 1).to_string())
+    }
+
+    fn row_label(&self, _row: usize) -> Option<String> {
+        None
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        if let Some(row) = state.landing_row(mv.col) {
+            let rect = cell_to_rect(row, mv.col).shrink(0.06 * cell_size);
+            painter.rect_filled(rect, 0.2 * cell_size, Color32::ORANGE.gamma_multiply(0.5));
+        }
+    }
+
+    fn update_move_selection(
+        &self,
+        turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        _move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        match action {
+            MoveSelectionAction::Reset => None,
+            MoveSelectionAction::ClickSquare { col, .. } => {
+                debug_assert_eq!(turn, self.turn(state));
+                self.generate_moves(&mut state.clone())
+                    .into_iter()
+                    .find(|mv| mv.col == col)
+            }
+        }
+    }
+
+    fn draw_move_selection_on_grid(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        // Mark where a disc would land in each column
+        for mv in self.generate_moves(&mut state.clone()) {
+            if let Some(row) = state.landing_row(mv.col) {
+                painter.circle_stroke(
+                    cell_to_rect(row, mv.col).center(),
+                    0.4 * cell_size,
+                    egui::Stroke::new(0.04 * cell_size, Color32::GRAY.gamma_multiply(0.5)),
+                );
+            }
+        }
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        _ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, GameEvent, GameResult, Player};
+
+    #[test]
+    fn four_in_a_row() {
+        let mut game = Game::new(ConnectFour);
+        for col in [0, 0, 1, 1, 2, 2] {
+            game.make_move(Move { col });
+            assert!(!game.is_finished());
+        }
+        let mut state = game.state().clone();
+        assert_eq!(
+            game.logic().generate_quiescence_moves(&mut state),
+            vec![Move { col: 3 }]
+        );
+        game.make_move(Move { col: 3 });
+        assert!(game.is_finished());
+        assert!(matches!(game.score(), AbsScore::FirstPlayerWin));
+    }
+
+    #[test]
+    fn resign_and_draw_offer() {
+        let mut game = Game::new(ConnectFour);
+        assert_eq!(game.result(), None);
+        game.make_move(Move { col: 0 });
+        game.resign(Player::Second);
+        assert!(game.is_finished());
+        assert_eq!(game.result(), Some(GameResult::Resigned(Player::Second)));
+        assert!(matches!(game.score(), AbsScore::FirstPlayerWin));
+        // Taking back the move takes back the resignation
+        game.undo_move();
+        assert_eq!(game.result(), None);
+
+        // An offer stands through the offering player's own move, and moving instead of answering it declines it
+        game.offer_draw(Player::First);
+        game.make_move(Move { col: 0 });
+        assert_eq!(game.draw_offer(), Some(Player::First));
+        game.make_move(Move { col: 1 });
+        assert_eq!(game.draw_offer(), None);
+        game.accept_draw();
+        assert!(!game.is_finished());
+        game.offer_draw(Player::First);
+        game.accept_draw();
+        assert_eq!(game.result(), Some(GameResult::AgreedDraw));
+        assert!(matches!(game.score(), AbsScore::Draw));
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut game = Game::new(ConnectFour);
+        for col in [0, 1, 2] {
+            game.make_move(Move { col });
+        }
+        game.undo_move();
+        game.undo_move();
+        assert!(game.can_redo_move());
+        game.redo_move();
+        assert_eq!(game.move_history(), &[Move { col: 0 }, Move { col: 1 }]);
+        // Playing the move which was taken back is the same as redoing it
+        game.make_move(Move { col: 2 });
+        assert!(!game.can_redo_move());
+
+        // A different move leaves nothing to redo
+        game.undo_move();
+        game.make_move(Move { col: 3 });
+        assert!(!game.can_redo_move());
+    }
+
+    #[test]
+    fn events() {
+        let mut game = Game::new(ConnectFour);
+        game.make_move(Move { col: 0 });
+        assert_eq!(game.take_events(), vec![GameEvent::Move]);
+        assert!(game.take_events().is_empty());
+        game.resign(Player::Second);
+        assert_eq!(
+            game.take_events(),
+            vec![GameEvent::Ended(GameResult::Resigned(Player::Second))]
+        );
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut game = Game::new(ConnectFour);
+        for col in [3, 3, 4] {
+            game.make_move(Move { col });
+        }
+        game.offer_draw(Player::Second);
+        let saved = game.save();
+        let loaded = Game::load(ConnectFour, &saved).unwrap();
+        assert_eq!(loaded.move_history(), game.move_history());
+        assert_eq!(loaded.draw_offer(), Some(Player::Second));
+
+        game.resign(Player::Second);
+        let loaded = Game::load(ConnectFour, &game.save()).unwrap();
+        assert_eq!(loaded.result(), Some(GameResult::Resigned(Player::Second)));
+
+        let mut corrupted = saved;
+        corrupted.moves.push("not a move".to_string());
+        assert!(Game::load(ConnectFour, &corrupted).is_err());
+    }
+}
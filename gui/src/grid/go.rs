@@ -0,0 +1,504 @@
+use std::fmt::Display;
+
+use egui::{Color32, Painter, Rect};
+
+use crate::{
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{CellShape, GridGame, MoveSelectionAction, Piece},
+};
+
+const SIZE: usize = 9;
+// Points given to white for moving second
+const KOMI: i64 = 7;
+// The letter 'i' is skipped when naming columns
+const COLUMN_NAMES: &[u8; SIZE] = b"ABCDEFGHJ";
+
+type Board = [[Option<Player>; SIZE]; SIZE];
+
+fn bit(row: usize, col: usize) -> u128 {
+    1 << (SIZE * row + col)
+}
+
+fn neighbours(row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+    [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        .into_iter()
+        .filter_map(move |(drow, dcol)| {
+            let r = row.checked_add_signed(drow)?;
+            let c = col.checked_add_signed(dcol)?;
+            (r < SIZE && c < SIZE).then_some((r, c))
+        })
+}
+
+// The stones connected to the one at (row, col) and whether they have any liberties
+fn group(board: &Board, row: usize, col: usize) -> (u128, bool) {
+    let colour = board[row][col];
+    debug_assert!(colour.is_some());
+    let mut stones = bit(row, col);
+    let mut free = false;
+    let mut stack = vec![(row, col)];
+    while let Some((row, col)) = stack.pop() {
+        for (r, c) in neighbours(row, col) {
+            if board[r][c].is_none() {
+                free = true;
+            } else if board[r][c] == colour && stones & bit(r, c) == 0 {
+                stones |= bit(r, c);
+                stack.push((r, c));
+            }
+        }
+    }
+    (stones, free)
+}
+
+fn remove(board: &mut Board, stones: u128) {
+    for (row, line) in board.iter_mut().enumerate() {
+        for (col, point) in line.iter_mut().enumerate() {
+            if stones & bit(row, col) != 0 {
+                *point = None;
+            }
+        }
+    }
+}
+
+fn hash_board(board: &Board) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for stone in board.iter().flatten() {
+        let value = match stone {
+            None => 0,
+            Some(Player::First) => 1,
+            Some(Player::Second) => 2,
+        };
+        hash = (hash ^ value).wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/*
+Go on a 9x9 board. Black moves first.
+A move is placing a stone or passing. Groups of stones with no liberties are captured and placing a stone whose own
+group would then have no liberties is not allowed. No move may recreate an earlier board position (positional superko).
+The game ends after two passes in a row and is scored by area: each player's stones plus the empty regions which
+only touch their stones. White receives a komi.
+ */
+#[derive(Debug, Clone)]
+pub struct Go;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState {
+    board: Board,
+    move_num: usize,
+    // How many passes in a row have just been played
+    passes: usize,
+    // The hash of the board after each move, used to enforce superko
+    history: Vec<u64>,
+}
+
+impl BoardState {
+    // The area scores of black and white, without komi
+    fn area(&self) -> (i64, i64) {
+        let mut area = [0, 0];
+        let mut seen = 0u128;
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                match self.board[row][col] {
+                    Some(Player::First) => area[0] += 1,
+                    Some(Player::Second) => area[1] += 1,
+                    None if seen & bit(row, col) == 0 => {
+                        // Flood fill the empty region and note which colours border it
+                        let mut size = 0;
+                        let mut borders = [false, false];
+                        let mut stack = vec![(row, col)];
+                        seen |= bit(row, col);
+                        while let Some((row, col)) = stack.pop() {
+                            size += 1;
+                            for (r, c) in neighbours(row, col) {
+                                match self.board[r][c] {
+                                    Some(Player::First) => borders[0] = true,
+                                    Some(Player::Second) => borders[1] = true,
+                                    None if seen & bit(r, c) == 0 => {
+                                        seen |= bit(r, c);
+                                        stack.push((r, c));
+                                    }
+                                    None => {}
+                                }
+                            }
+                        }
+                        match borders {
+                            [true, false] => area[0] += size,
+                            [false, true] => area[1] += size,
+                            _ => {}
+                        }
+                    }
+                    None => {}
+                }
+            }
+        }
+        (area[0], area[1])
+    }
+}
+
+impl State<Go> for BoardState {
+    fn ident(self) -> BoardStateIdent {
+        BoardStateIdent {
+            board: self.board,
+            move_num: self.move_num,
+            passes: self.passes,
+        }
+    }
+
+    // Superko stops the board repeating and a second pass ends the game
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+#[derive(Debug, Clone)]
+pub struct BoardStateIdent {
+    board: Board,
+    move_num: usize,
+    passes: usize,
+}
+
+impl PartialEq for BoardStateIdent {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+            && self.move_num % 2 == other.move_num % 2
+            && self.passes == other.passes
+    }
+}
+
+impl Eq for BoardStateIdent {}
+
+impl NoAlloc for BoardStateIdent {}
+
+impl StateIdent<Go> for BoardStateIdent {
+    fn hash64(&self) -> u64 {
+        hash_board(&self.board) ^ ((self.move_num % 2) as u64) ^ ((self.passes as u64) << 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Move {
+    Place {
+        row: usize,
+        col: usize,
+        // The opponent's stones which are taken off the board, kept so the move can be unmade
+        captured: u128,
+        prev_passes: usize,
+    },
+    Pass,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Move::Place { row, col, .. } => {
+                write!(f, "{}{}", COLUMN_NAMES[*col] as char, SIZE - row)
+            }
+            Move::Pass => write!(f, "pass"),
+        }
+    }
+}
+
+impl GameLogic for Go {
+    type State = BoardState;
+    type StateIdent = BoardStateIdent;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        let board = [[None; SIZE]; SIZE];
+        BoardState {
+            board,
+            move_num: 0,
+            passes: 0,
+            history: vec![hash_board(&board)],
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        if state.move_num.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        if state.passes >= 2 {
+            return vec![];
+        }
+        let turn = self.turn(state);
+        let mut moves = vec![];
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                if state.board[row][col].is_some() {
+                    continue;
+                }
+                let mut board = state.board;
+                board[row][col] = Some(turn);
+                let mut captured = 0;
+                for (r, c) in neighbours(row, col) {
+                    if board[r][c] == Some(turn.flip()) && captured & bit(r, c) == 0 {
+                        let (stones, free) = group(&board, r, c);
+                        if !free {
+                            captured |= stones;
+                        }
+                    }
+                }
+                remove(&mut board, captured);
+                // No suicide and no repeating an earlier position
+                if !group(&board, row, col).1 || state.history.contains(&hash_board(&board)) {
+                    continue;
+                }
+                moves.push(Move::Place {
+                    row,
+                    col,
+                    captured,
+                    prev_passes: state.passes,
+                });
+            }
+        }
+        // Captures are searched first
+        moves.sort_by_key(|mv| match mv {
+            Move::Place { captured, .. } => std::cmp::Reverse(captured.count_ones()),
+            Move::Pass => unreachable!(),
+        });
+        moves.push(Move::Pass);
+        moves
+    }
+
+    fn generate_quiescence_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        self.generate_moves(state)
+            .into_iter()
+            .filter(|mv| matches!(mv, Move::Place { captured, .. } if *captured != 0))
+            .collect()
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        let (black, white) = state.area();
+        let score = black - white - KOMI;
+        if state.passes >= 2 {
+            match score.cmp(&0) {
+                std::cmp::Ordering::Greater => AbsScore::FirstPlayerWin,
+                std::cmp::Ordering::Less => AbsScore::SecondPlayerWin,
+                std::cmp::Ordering::Equal => AbsScore::Draw,
+            }
+        } else {
+            AbsScore::Heuristic(score)
+        }
+    }
+
+    fn is_capture(&self, _state: &Self::State, mv: &Self::Move) -> bool {
+        matches!(mv, Move::Place { captured, .. } if *captured != 0)
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        let turn = self.turn(state);
+        match mv {
+            Move::Place {
+                row, col, captured, ..
+            } => {
+                debug_assert!(state.board[*row][*col].is_none());
+                state.board[*row][*col] = Some(turn);
+                remove(&mut state.board, *captured);
+                state.passes = 0;
+            }
+            Move::Pass => state.passes += 1,
+        }
+        state.history.push(hash_board(&state.board));
+        state.move_num += 1;
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        state.move_num -= 1;
+        state.history.pop();
+        let turn = self.turn(state);
+        match mv {
+            Move::Place {
+                row,
+                col,
+                captured,
+                prev_passes,
+            } => {
+                debug_assert_eq!(state.board[*row][*col], Some(turn));
+                state.board[*row][*col] = None;
+                for (r, line) in state.board.iter_mut().enumerate() {
+                    for (c, point) in line.iter_mut().enumerate() {
+                        if captured & bit(r, c) != 0 {
+                            *point = Some(turn.flip());
+                        }
+                    }
+                }
+                state.passes = *prev_passes;
+            }
+            Move::Pass => state.passes -= 1,
+        }
+    }
+}
+
+// Stones are placed with a single click and passing is done from a window
+#[derive(Debug, Clone)]
+pub enum MoveSelectionState {
+    Initial,
+}
+
+impl GridGame for Go {
+    const ROWS: usize = SIZE;
+    const COLS: usize = SIZE;
+    const CELL_SHAPE: CellShape = CellShape::Points;
+
+    fn piece(&self, state: &Self::State, row: usize, col: usize) -> Piece {
+        match state.board[row][col] {
+            Some(Player::First) => Piece::BlackDisc,
+            Some(Player::Second) => Piece::WhiteDisc,
+            None => Piece::Empty,
+        }
+    }
+
+    type MoveSelectionState = MoveSelectionState;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        MoveSelectionState::Initial
+    }
+
+    fn player_name(&self, player: Player) -> &'static str {
+        match player {
+            Player::First => "Black",
+            Player::Second => "White",
+        }
+    }
+
+    fn board_lines(&self) -> Vec<((usize, usize), (usize, usize))> {
+        (0..SIZE)
+            .flat_map(|i| [((i, 0), (i, SIZE - 1)), ((0, i), (SIZE - 1, i))])
+            .collect()
+    }
+
+    fn col_label(&self, col: usize) -> Option<String> {
+        Some((COLUMN_NAMES[col] as char).to_string())
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        if let Move::Place { row, col, .. } = mv {
+            painter.circle_filled(
+                cell_to_rect(row, col).center(),
+                0.45 * cell_size,
+                Color32::ORANGE.gamma_multiply(0.5),
+            );
+        }
+    }
+
+    fn update_move_selection(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        _move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        match action {
+            MoveSelectionAction::Reset => None,
+            MoveSelectionAction::ClickSquare { row, col } => {
+                self.generate_moves(&mut state.clone()).into_iter().find(
+                    |mv| matches!(mv, Move::Place { row: r, col: c, .. } if *r == row && *c == col),
+                )
+            }
+        }
+    }
+
+    fn draw_move_selection_on_grid(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        _cell_size: f32,
+        _cell_to_rect: impl Fn(usize, usize) -> Rect,
+        _painter: &Painter,
+    ) {
+    }
+
+    fn show_state_info(&self, state: &Self::State, ui: &mut egui::Ui) {
+        let (black, white) = state.area();
+        ui.label(format!(
+            "Area: Black {}, White {} + {} komi",
+            black, white, KOMI
+        ));
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        if state.passes >= 2 {
+            return None;
+        }
+        egui::Window::new("Pass")
+            .default_pos(egui::pos2(200.0, 100.0))
+            .collapsible(false)
+            .show(ctx, |ui| {
+                if state.passes == 1 {
+                    ui.label("Your opponent passed. Passing again ends the game.");
+                }
+                ui.button("Pass").clicked().then_some(Move::Pass)
+            })
+            .and_then(|response| response.inner.flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(logic: &Go, state: &mut BoardState, text: &str) -> Move {
+        let mv = logic
+            .generate_moves(state)
+            .into_iter()
+            .find(|mv| mv.to_string() == text)
+            .unwrap_or_else(|| panic!("{text} is not legal"));
+        logic.make_move(state, &mv);
+        mv
+    }
+
+    #[test]
+    fn capture_and_ko() {
+        let logic = Go;
+        let mut state = logic.initial_state();
+        // Black and white build a ko shape in the top left corner
+        for text in ["B9", "C9", "A8", "B8", "B7", "D8", "J1", "C7"] {
+            play(&logic, &mut state, text);
+        }
+        let before = state.clone();
+        let mv = play(&logic, &mut state, "C8");
+        assert_eq!(state.board[1][1], None);
+        // White can't take straight back
+        assert!(
+            logic
+                .generate_moves(&mut state)
+                .iter()
+                .all(|mv| mv.to_string() != "B8")
+        );
+        logic.unmake_move(&mut state, &mv);
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn double_pass_ends_the_game() {
+        let logic = Go;
+        let mut state = logic.initial_state();
+        play(&logic, &mut state, "E5");
+        play(&logic, &mut state, "pass");
+        assert!(matches!(logic.score(&mut state), AbsScore::Heuristic(74)));
+        play(&logic, &mut state, "pass");
+        assert!(logic.generate_moves(&mut state).is_empty());
+        assert!(matches!(logic.score(&mut state), AbsScore::FirstPlayerWin));
+    }
+}
@@ -0,0 +1,506 @@
+use std::fmt::Display;
+
+use egui::{Color32, Painter, Rect};
+
+use crate::{
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{GridGame, MoveSelectionAction, Piece},
+};
+
+// Horizontal, vertical and both diagonal lines
+const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+type Board<const SIZE: usize> = [[Option<Player>; SIZE]; SIZE];
+
+// Black moves first. When `forbidden_moves` is set black may not make an overline, a double four or a double three as in renju.
+#[derive(Debug, Clone)]
+pub struct Gomoku<const SIZE: usize> {
+    pub forbidden_moves: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState<const SIZE: usize> {
+    board: Board<SIZE>,
+    move_num: usize,
+    winner: Option<Player>,
+}
+
+impl<const SIZE: usize> State<Gomoku<SIZE>> for BoardState<SIZE> {
+    fn ident(self) -> BoardStateIdent<SIZE> {
+        BoardStateIdent {
+            board: self.board,
+            move_num: self.move_num,
+        }
+    }
+
+    // Positions can't repeat since stones are never removed
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardStateIdent<const SIZE: usize> {
+    board: Board<SIZE>,
+    move_num: usize,
+}
+
+impl<const SIZE: usize> NoAlloc for BoardStateIdent<SIZE> {}
+
+impl<const SIZE: usize> StateIdent<Gomoku<SIZE>> for BoardStateIdent<SIZE> {
+    fn hash64(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for stone in self.board.iter().flatten() {
+            let value = match stone {
+                None => 0,
+                Some(Player::First) => 1,
+                Some(Player::Second) => 2,
+            };
+            hash = (hash ^ value).wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    row: usize,
+    col: usize,
+    // The number of rows on the board, for display
+    size: usize,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            (b'a' + self.col as u8) as char,
+            self.size - self.row
+        )
+    }
+}
+
+// The number of `player`'s stones in a row starting next to (row, col) and heading in direction (drow, dcol)
+fn run<const SIZE: usize>(
+    board: &Board<SIZE>,
+    player: Player,
+    row: usize,
+    col: usize,
+    (drow, dcol): (isize, isize),
+) -> usize {
+    let mut count = 0;
+    let (mut r, mut c) = (row as isize + drow, col as isize + dcol);
+    while (0..SIZE as isize).contains(&r)
+        && (0..SIZE as isize).contains(&c)
+        && board[r as usize][c as usize] == Some(player)
+    {
+        count += 1;
+        r += drow;
+        c += dcol;
+    }
+    count
+}
+
+// The length of `player`'s line through (row, col) in direction `dir`, counting (row, col) itself
+fn line<const SIZE: usize>(
+    board: &Board<SIZE>,
+    player: Player,
+    row: usize,
+    col: usize,
+    (drow, dcol): (isize, isize),
+) -> usize {
+    1 + run(board, player, row, col, (drow, dcol)) + run(board, player, row, col, (-drow, -dcol))
+}
+
+fn is_empty<const SIZE: usize>(board: &Board<SIZE>, row: isize, col: isize) -> bool {
+    (0..SIZE as isize).contains(&row)
+        && (0..SIZE as isize).contains(&col)
+        && board[row as usize][col as usize].is_none()
+}
+
+impl<const SIZE: usize> Gomoku<SIZE> {
+    // Does `player` placing at (row, col) make a winning line?
+    fn wins_with(&self, board: &Board<SIZE>, player: Player, row: usize, col: usize) -> bool {
+        DIRECTIONS.into_iter().any(|dir| {
+            let length = line(board, player, row, col, dir);
+            if self.forbidden_moves && player == Player::First {
+                length == 5
+            } else {
+                length >= 5
+            }
+        })
+    }
+
+    // Renju restrictions on black's placement at the empty square (row, col)
+    fn is_forbidden(&self, board: &mut Board<SIZE>, row: usize, col: usize) -> bool {
+        let player = Player::First;
+        if self.wins_with(board, player, row, col) {
+            return false;
+        }
+        board[row][col] = Some(player);
+        let mut overline = false;
+        let mut fours = 0;
+        let mut threes = 0;
+        for (drow, dcol) in DIRECTIONS {
+            if line(board, player, row, col, (drow, dcol)) > 5 {
+                overline = true;
+            }
+            // Squares along this line which would complete an exact five or an open four through (row, col)
+            let mut is_four = false;
+            let mut is_three = false;
+            for k in -4..=4isize {
+                let (r, c) = (row as isize + k * drow, col as isize + k * dcol);
+                if k == 0 || !is_empty(board, r, c) {
+                    continue;
+                }
+                let (r, c) = (r as usize, c as usize);
+                board[r][c] = Some(player);
+                let length = line(board, player, row, col, (drow, dcol));
+                if length == 5 {
+                    is_four = true;
+                } else if length == 4 {
+                    let forward = run(board, player, row, col, (drow, dcol)) as isize + 1;
+                    let backward = run(board, player, row, col, (-drow, -dcol)) as isize + 1;
+                    if is_empty(
+                        board,
+                        row as isize + forward * drow,
+                        col as isize + forward * dcol,
+                    ) && is_empty(
+                        board,
+                        row as isize - backward * drow,
+                        col as isize - backward * dcol,
+                    ) {
+                        is_three = true;
+                    }
+                }
+                board[r][c] = None;
+            }
+            if is_four {
+                fours += 1;
+            } else if is_three {
+                threes += 1;
+            }
+        }
+        board[row][col] = None;
+        overline || fours >= 2 || threes >= 2
+    }
+
+    // The number of stones within two squares of (row, col), used to search moves near the action first
+    fn neighbours(&self, board: &Board<SIZE>, row: usize, col: usize) -> usize {
+        board[row.saturating_sub(2)..(row + 3).min(SIZE)]
+            .iter()
+            .map(|line| {
+                line[col.saturating_sub(2)..(col + 3).min(SIZE)]
+                    .iter()
+                    .filter(|stone| stone.is_some())
+                    .count()
+            })
+            .sum()
+    }
+}
+
+impl<const SIZE: usize> GameLogic for Gomoku<SIZE> {
+    type State = BoardState<SIZE>;
+    type StateIdent = BoardStateIdent<SIZE>;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        BoardState {
+            board: [[None; SIZE]; SIZE],
+            move_num: 0,
+            winner: None,
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        if state.move_num.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        if state.winner.is_some() {
+            return vec![];
+        }
+        let check_forbidden = self.forbidden_moves && self.turn(state) == Player::First;
+        let centre = SIZE / 2;
+        let mut moves = vec![];
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                if state.board[row][col].is_none()
+                    && !(check_forbidden && self.is_forbidden(&mut state.board, row, col))
+                {
+                    moves.push(Move {
+                        row,
+                        col,
+                        size: SIZE,
+                    });
+                }
+            }
+        }
+        moves.sort_by_key(|mv| {
+            (
+                std::cmp::Reverse(self.neighbours(&state.board, mv.row, mv.col)),
+                mv.row.abs_diff(centre) + mv.col.abs_diff(centre),
+            )
+        });
+        moves
+    }
+
+    // Moves which win immediately or block the opponent from winning immediately
+    fn generate_quiescence_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        let turn = self.turn(state);
+        self.generate_moves(state)
+            .into_iter()
+            .filter(|mv| {
+                self.wins_with(&state.board, turn, mv.row, mv.col)
+                    || self.wins_with(&state.board, turn.flip(), mv.row, mv.col)
+            })
+            .collect()
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        match state.winner {
+            Some(Player::First) => return AbsScore::FirstPlayerWin,
+            Some(Player::Second) => return AbsScore::SecondPlayerWin,
+            None => {}
+        }
+        // Black can run out of moves early when some squares are forbidden
+        if state.move_num == SIZE * SIZE
+            || (self.forbidden_moves
+                && self.turn(state) == Player::First
+                && self.generate_moves(state).is_empty())
+        {
+            return AbsScore::Draw;
+        }
+
+        // Reward every maximal run of stones by its length and how many of its ends are open
+        const WEIGHTS: [[i64; 5]; 3] = [[0, 0, 0, 0, 0], [0, 0, 1, 10, 50], [0, 1, 5, 50, 500]];
+        let mut total = 0;
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                let Some(player) = state.board[row][col] else {
+                    continue;
+                };
+                for (drow, dcol) in DIRECTIONS {
+                    // Only count each run from its first stone
+                    if run(&state.board, player, row, col, (-drow, -dcol)) != 0 {
+                        continue;
+                    }
+                    let length = 1 + run(&state.board, player, row, col, (drow, dcol));
+                    let open = is_empty(&state.board, row as isize - drow, col as isize - dcol)
+                        as usize
+                        + is_empty(
+                            &state.board,
+                            row as isize + length as isize * drow,
+                            col as isize + length as isize * dcol,
+                        ) as usize;
+                    let value = WEIGHTS[open][length.min(4)];
+                    match player {
+                        Player::First => total += value,
+                        Player::Second => total -= value,
+                    }
+                }
+            }
+        }
+        AbsScore::Heuristic(total)
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        debug_assert!(state.board[mv.row][mv.col].is_none());
+        let turn = self.turn(state);
+        if self.wins_with(&state.board, turn, mv.row, mv.col) {
+            state.winner = Some(turn);
+        }
+        state.board[mv.row][mv.col] = Some(turn);
+        state.move_num += 1;
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        debug_assert!(state.board[mv.row][mv.col].is_some());
+        // No moves are made after a win so the position before was undecided
+        state.winner = None;
+        state.board[mv.row][mv.col] = None;
+        state.move_num -= 1;
+    }
+}
+
+// Stones are placed with a single click
+#[derive(Debug, Clone)]
+pub enum MoveSelectionState {
+    Initial,
+}
+
+impl<const SIZE: usize> GridGame for Gomoku<SIZE> {
+    const ROWS: usize = SIZE;
+    const COLS: usize = SIZE;
+
+    fn piece(&self, state: &Self::State, row: usize, col: usize) -> Piece {
+        match state.board[row][col] {
+            Some(Player::First) => Piece::BlackDisc,
+            Some(Player::Second) => Piece::WhiteDisc,
+            None => Piece::Empty,
+        }
+    }
+
+    type MoveSelectionState = MoveSelectionState;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        MoveSelectionState::Initial
+    }
+
+    fn player_name(&self, player: Player) -> &'static str {
+        match player {
+            Player::First => "Black",
+            Player::Second => "White",
+        }
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        let rect = cell_to_rect(mv.row, mv.col).shrink(0.06 * cell_size);
+        painter.rect_filled(rect, 0.2 * cell_size, Color32::ORANGE.gamma_multiply(0.5));
+    }
+
+    fn update_move_selection(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        _move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        match action {
+            MoveSelectionAction::Reset => None,
+            MoveSelectionAction::ClickSquare { row, col } => self
+                .generate_moves(&mut state.clone())
+                .into_iter()
+                .find(|mv| mv.row == row && mv.col == col),
+        }
+    }
+
+    fn draw_move_selection_on_grid(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        // Cross out empty squares which black is forbidden from playing on
+        if state.winner.is_some() || !self.forbidden_moves || self.turn(state) != Player::First {
+            return;
+        }
+        let mut board = state.board;
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                if board[row][col].is_none() && self.is_forbidden(&mut board, row, col) {
+                    let rect = cell_to_rect(row, col).shrink(0.3 * cell_size);
+                    let stroke = egui::Stroke::new(0.05 * cell_size, Color32::RED);
+                    painter.line_segment([rect.left_top(), rect.right_bottom()], stroke);
+                    painter.line_segment([rect.right_top(), rect.left_bottom()], stroke);
+                }
+            }
+        }
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        _ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place<const SIZE: usize>(state: &mut BoardState<SIZE>, stones: &[(usize, usize, Player)]) {
+        for (row, col, player) in stones {
+            state.board[*row][*col] = Some(*player);
+        }
+    }
+
+    #[test]
+    fn five_in_a_row() {
+        let logic = Gomoku::<15> {
+            forbidden_moves: false,
+        };
+        let mut state = logic.initial_state();
+        for col in 0..4 {
+            logic.make_move(
+                &mut state,
+                &Move {
+                    row: 7,
+                    col,
+                    size: 15,
+                },
+            );
+            logic.make_move(
+                &mut state,
+                &Move {
+                    row: 0,
+                    col,
+                    size: 15,
+                },
+            );
+        }
+        assert!(matches!(logic.score(&mut state), AbsScore::Heuristic(_)));
+        assert_eq!(logic.generate_quiescence_moves(&mut state).len(), 2);
+        logic.make_move(
+            &mut state,
+            &Move {
+                row: 7,
+                col: 4,
+                size: 15,
+            },
+        );
+        assert!(logic.generate_moves(&mut state).is_empty());
+        assert!(matches!(logic.score(&mut state), AbsScore::FirstPlayerWin));
+    }
+
+    #[test]
+    fn renju_forbidden_moves() {
+        let logic = Gomoku::<15> {
+            forbidden_moves: true,
+        };
+        let mut state = logic.initial_state();
+        // Black playing (7, 7) would make two open threes
+        place(
+            &mut state,
+            &[
+                (7, 5, Player::First),
+                (7, 6, Player::First),
+                (5, 7, Player::First),
+                (6, 7, Player::First),
+                // An overline at (0, 3)
+                (0, 0, Player::First),
+                (0, 1, Player::First),
+                (0, 2, Player::First),
+                (0, 4, Player::First),
+                (0, 5, Player::First),
+            ],
+        );
+        let moves = logic.generate_moves(&mut state);
+        assert!(!moves.iter().any(|mv| (mv.row, mv.col) == (7, 7)));
+        assert!(!moves.iter().any(|mv| (mv.row, mv.col) == (0, 3)));
+        assert!(moves.iter().any(|mv| (mv.row, mv.col) == (7, 8)));
+        state.move_num = 1;
+        let moves = logic.generate_moves(&mut state);
+        assert!(moves.iter().any(|mv| (mv.row, mv.col) == (7, 7)));
+    }
+}
@@ -0,0 +1,398 @@
+use std::{collections::VecDeque, fmt::Display};
+
+use egui::{Color32, Painter, Rect};
+
+use crate::{
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{CellShape, GridGame, MoveSelectionAction, Piece},
+};
+
+type Board<const SIZE: usize> = [[Option<Player>; SIZE]; SIZE];
+
+const RED: Color32 = Color32::from_rgb(220, 40, 40);
+const BLUE: Color32 = Color32::from_rgb(40, 80, 220);
+
+// The cells touching (row, col) on the rhombus
+fn neighbours<const SIZE: usize>(row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+    [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0)]
+        .into_iter()
+        .filter_map(move |(drow, dcol)| {
+            let r = row.checked_add_signed(drow)?;
+            let c = col.checked_add_signed(dcol)?;
+            (r < SIZE && c < SIZE).then_some((r, c))
+        })
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
+}
+
+// Red (first) joins the top and bottom edges, blue (second) joins the left and right edges
+#[derive(Debug, Clone)]
+pub struct Hex<const SIZE: usize>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState<const SIZE: usize> {
+    board: Board<SIZE>,
+    move_num: usize,
+    winner: Option<Player>,
+}
+
+impl<const SIZE: usize> BoardState<SIZE> {
+    // Whether `player`'s stones join their two edges, found by merging touching stones together with a virtual node for each edge
+    fn is_connected(&self, player: Player) -> bool {
+        let start = SIZE * SIZE;
+        let end = start + 1;
+        let mut sets = UnionFind::new(SIZE * SIZE + 2);
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                if self.board[row][col] != Some(player) {
+                    continue;
+                }
+                let idx = SIZE * row + col;
+                let edge = match player {
+                    Player::First => row,
+                    Player::Second => col,
+                };
+                if edge == 0 {
+                    sets.union(idx, start);
+                }
+                if edge == SIZE - 1 {
+                    sets.union(idx, end);
+                }
+                for (r, c) in neighbours::<SIZE>(row, col) {
+                    if self.board[r][c] == Some(player) {
+                        sets.union(idx, SIZE * r + c);
+                    }
+                }
+            }
+        }
+        sets.find(start) == sets.find(end)
+    }
+
+    // The fewest empty cells `player` must fill to join their edges
+    fn distance(&self, player: Player) -> usize {
+        let mut dist = [[usize::MAX; SIZE]; SIZE];
+        let mut queue = VecDeque::new();
+        let cost = |stone: Option<Player>| -> Option<usize> {
+            match stone {
+                None => Some(1),
+                Some(p) if p == player => Some(0),
+                Some(_) => None,
+            }
+        };
+        for i in 0..SIZE {
+            let (row, col) = match player {
+                Player::First => (0, i),
+                Player::Second => (i, 0),
+            };
+            if let Some(c) = cost(self.board[row][col]) {
+                dist[row][col] = c;
+                if c == 0 {
+                    queue.push_front((row, col));
+                } else {
+                    queue.push_back((row, col));
+                }
+            }
+        }
+        // A 0-1 breadth first search
+        while let Some((row, col)) = queue.pop_front() {
+            for (r, c) in neighbours::<SIZE>(row, col) {
+                if let Some(c_cost) = cost(self.board[r][c])
+                    && dist[row][col] + c_cost < dist[r][c]
+                {
+                    dist[r][c] = dist[row][col] + c_cost;
+                    if c_cost == 0 {
+                        queue.push_front((r, c));
+                    } else {
+                        queue.push_back((r, c));
+                    }
+                }
+            }
+        }
+        (0..SIZE)
+            .map(|i| match player {
+                Player::First => dist[SIZE - 1][i],
+                Player::Second => dist[i][SIZE - 1],
+            })
+            .min()
+            .unwrap()
+    }
+}
+
+impl<const SIZE: usize> State<Hex<SIZE>> for BoardState<SIZE> {
+    fn ident(self) -> BoardStateIdent<SIZE> {
+        BoardStateIdent { board: self.board }
+    }
+
+    // Positions can't repeat since stones are never removed
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardStateIdent<const SIZE: usize> {
+    board: Board<SIZE>,
+}
+
+impl<const SIZE: usize> NoAlloc for BoardStateIdent<SIZE> {}
+
+impl<const SIZE: usize> StateIdent<Hex<SIZE>> for BoardStateIdent<SIZE> {
+    fn hash64(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for stone in self.board.iter().flatten() {
+            let value = match stone {
+                None => 0,
+                Some(Player::First) => 1,
+                Some(Player::Second) => 2,
+            };
+            hash = (hash ^ value).wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    row: usize,
+    col: usize,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", (b'a' + self.col as u8) as char, self.row + 1)
+    }
+}
+
+impl<const SIZE: usize> GameLogic for Hex<SIZE> {
+    type State = BoardState<SIZE>;
+    type StateIdent = BoardStateIdent<SIZE>;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        BoardState {
+            board: [[None; SIZE]; SIZE],
+            move_num: 0,
+            winner: None,
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        if state.move_num.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        if state.winner.is_some() {
+            return vec![];
+        }
+        // Cells nearest the centre are searched first
+        let centre = SIZE / 2;
+        let mut moves = vec![];
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                if state.board[row][col].is_none() {
+                    moves.push(Move { row, col });
+                }
+            }
+        }
+        moves.sort_by_key(|mv| mv.row.abs_diff(centre) + mv.col.abs_diff(centre));
+        moves
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        match state.winner {
+            Some(Player::First) => AbsScore::FirstPlayerWin,
+            Some(Player::Second) => AbsScore::SecondPlayerWin,
+            // The board can't fill up without somebody connecting so there are no draws
+            None => AbsScore::Heuristic(
+                state.distance(Player::Second) as i64 - state.distance(Player::First) as i64,
+            ),
+        }
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        debug_assert!(state.board[mv.row][mv.col].is_none());
+        let turn = self.turn(state);
+        state.board[mv.row][mv.col] = Some(turn);
+        state.move_num += 1;
+        if state.is_connected(turn) {
+            state.winner = Some(turn);
+        }
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        debug_assert!(state.board[mv.row][mv.col].is_some());
+        // No moves are made after a win so the position before was undecided
+        state.winner = None;
+        state.board[mv.row][mv.col] = None;
+        state.move_num -= 1;
+    }
+}
+
+// Stones are placed with a single click
+#[derive(Debug, Clone)]
+pub enum MoveSelectionState {
+    Initial,
+}
+
+impl<const SIZE: usize> GridGame for Hex<SIZE> {
+    const ROWS: usize = SIZE;
+    const COLS: usize = SIZE;
+    const CELL_SHAPE: CellShape = CellShape::Hex;
+
+    fn piece(&self, state: &Self::State, row: usize, col: usize) -> Piece {
+        match state.board[row][col] {
+            Some(Player::First) => Piece::RedDisc,
+            Some(Player::Second) => Piece::BlueDisc,
+            None => Piece::Empty,
+        }
+    }
+
+    type MoveSelectionState = MoveSelectionState;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        MoveSelectionState::Initial
+    }
+
+    fn player_name(&self, player: Player) -> &'static str {
+        match player {
+            Player::First => "Red",
+            Player::Second => "Blue",
+        }
+    }
+
+    // Mark the edges belonging to each player
+    fn highlight_square(&self, row: usize, col: usize) -> Option<Color32> {
+        let red = row == 0 || row == SIZE - 1;
+        let blue = col == 0 || col == SIZE - 1;
+        match (red, blue) {
+            (true, true) => None,
+            (true, false) => Some(RED.gamma_multiply(0.25)),
+            (false, true) => Some(BLUE.gamma_multiply(0.25)),
+            (false, false) => None,
+        }
+    }
+
+    fn row_label(&self, row: usize) -> Option<String> {
+        Some((row + 1).to_string())
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        painter.circle_filled(
+            cell_to_rect(mv.row, mv.col).center(),
+            0.4 * cell_size,
+            Color32::ORANGE.gamma_multiply(0.5),
+        );
+    }
+
+    fn update_move_selection(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        _move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        match action {
+            MoveSelectionAction::Reset => None,
+            MoveSelectionAction::ClickSquare { row, col } => self
+                .generate_moves(&mut state.clone())
+                .into_iter()
+                .find(|mv| mv.row == row && mv.col == col),
+        }
+    }
+
+    fn draw_move_selection_on_grid(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        _cell_size: f32,
+        _cell_to_rect: impl Fn(usize, usize) -> Rect,
+        _painter: &Painter,
+    ) {
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        _ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection() {
+        let logic = Hex::<5>;
+        let mut state = logic.initial_state();
+        // Red heads down the board on a diagonal while blue fills the left column
+        for (row, col) in [
+            (0, 4),
+            (0, 0),
+            (1, 3),
+            (1, 0),
+            (2, 2),
+            (2, 0),
+            (3, 1),
+            (3, 0),
+        ] {
+            logic.make_move(&mut state, &Move { row, col });
+        }
+        assert!(matches!(logic.score(&mut state), AbsScore::Heuristic(4)));
+        let mv = Move { row: 4, col: 0 };
+        logic.make_move(&mut state, &mv);
+        assert!(logic.generate_moves(&mut state).is_empty());
+        assert!(matches!(logic.score(&mut state), AbsScore::FirstPlayerWin));
+        logic.unmake_move(&mut state, &mv);
+        assert!(matches!(logic.score(&mut state), AbsScore::Heuristic(4)));
+    }
+}
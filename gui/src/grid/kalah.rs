@@ -0,0 +1,412 @@
+use std::fmt::Display;
+
+use egui::{Align2, Color32, FontId, Painter, Rect, Stroke};
+
+use crate::{
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{GridGame, MoveSelectionAction, Piece},
+};
+
+const PITS: usize = 6;
+const SEEDS: u8 = 4;
+// Each player's pits followed by their store, going anticlockwise from south's leftmost pit
+const HOLES: usize = 2 * (PITS + 1);
+
+fn store(player: Player) -> usize {
+    match player {
+        Player::First => PITS,
+        Player::Second => HOLES - 1,
+    }
+}
+
+fn pits(player: Player) -> std::ops::Range<usize> {
+    match player {
+        Player::First => 0..PITS,
+        Player::Second => PITS + 1..HOLES - 1,
+    }
+}
+
+fn opposite(pit: usize) -> usize {
+    HOLES - 2 - pit
+}
+
+/*
+Kalah with six pits a side and four seeds in each.
+A move sows every seed from one of the player's pits one at a time into the following holes, skipping the
+opponent's store. Ending in your own store earns another move, and ending in one of your own empty pits
+captures that seed along with the seeds opposite. Once either side is empty each player banks the seeds left on
+their side and the bigger store wins.
+South is the first player, with their pits along the bottom and their store on the right.
+ */
+#[derive(Debug, Clone)]
+pub struct Kalah;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState {
+    holes: [u8; HOLES],
+    turn: Player,
+}
+
+impl BoardState {
+    fn is_over(&self) -> bool {
+        pits(Player::First).all(|pit| self.holes[pit] == 0)
+            || pits(Player::Second).all(|pit| self.holes[pit] == 0)
+    }
+}
+
+impl State<Kalah> for BoardState {
+    fn ident(self) -> BoardStateIdent {
+        BoardStateIdent {
+            holes: self.holes,
+            turn: self.turn,
+        }
+    }
+
+    // Positions can't repeat since seeds only ever leave the pits for the stores
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardStateIdent {
+    holes: [u8; HOLES],
+    turn: Player,
+}
+
+impl NoAlloc for BoardStateIdent {}
+
+impl StateIdent<Kalah> for BoardStateIdent {
+    fn hash64(&self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325u64 ^ (self.turn == Player::Second) as u64;
+        for seeds in self.holes {
+            hash = (hash ^ seeds as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Move {
+    pit: usize,
+    // Sowing moves seeds all around the board so the whole board is kept to unmake the move
+    prev_holes: [u8; HOLES],
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Pit {}", self.pit % (PITS + 1) + 1)
+    }
+}
+
+// Sow the seeds from `pit` returning the hole the last one lands in
+fn sow(holes: &mut [u8; HOLES], turn: Player, pit: usize) -> usize {
+    let skip = store(turn.flip());
+    let mut hole = pit;
+    for _ in 0..std::mem::take(&mut holes[pit]) {
+        hole = (hole + 1) % HOLES;
+        if hole == skip {
+            hole = (hole + 1) % HOLES;
+        }
+        holes[hole] += 1;
+    }
+    hole
+}
+
+// Whether the last seed landing in `last` captures, checked after sowing
+fn is_capture(holes: &[u8; HOLES], turn: Player, last: usize) -> bool {
+    pits(turn).contains(&last) && holes[last] == 1 && holes[opposite(last)] > 0
+}
+
+impl GameLogic for Kalah {
+    type State = BoardState;
+    type StateIdent = BoardStateIdent;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        let mut holes = [SEEDS; HOLES];
+        holes[store(Player::First)] = 0;
+        holes[store(Player::Second)] = 0;
+        BoardState {
+            holes,
+            turn: Player::First,
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        state.turn
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        if state.is_over() {
+            return vec![];
+        }
+        pits(state.turn)
+            .filter(|pit| state.holes[*pit] > 0)
+            .map(|pit| Move {
+                pit,
+                prev_holes: state.holes,
+            })
+            .collect()
+    }
+
+    // Moves which earn another turn or capture
+    fn generate_quiescence_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        let turn = self.turn(state);
+        self.generate_moves(state)
+            .into_iter()
+            .filter(|mv| {
+                let mut holes = state.holes;
+                let last = sow(&mut holes, turn, mv.pit);
+                last == store(turn) || is_capture(&holes, turn, last)
+            })
+            .collect()
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        let first = state.holes[store(Player::First)] as i64;
+        let second = state.holes[store(Player::Second)] as i64;
+        if state.is_over() {
+            return match first.cmp(&second) {
+                std::cmp::Ordering::Greater => AbsScore::FirstPlayerWin,
+                std::cmp::Ordering::Equal => AbsScore::Draw,
+                std::cmp::Ordering::Less => AbsScore::SecondPlayerWin,
+            };
+        }
+        // Seeds on your own side are likely but not certain to end up in your store
+        let side = |player: Player| pits(player).map(|pit| state.holes[pit] as i64).sum::<i64>();
+        AbsScore::Heuristic(4 * (first - second) + side(Player::First) - side(Player::Second))
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        debug_assert_eq!(state.holes, mv.prev_holes);
+        let turn = state.turn;
+        debug_assert!(pits(turn).contains(&mv.pit));
+        let last = sow(&mut state.holes, turn, mv.pit);
+
+        if is_capture(&state.holes, turn, last) {
+            let captured = std::mem::take(&mut state.holes[last])
+                + std::mem::take(&mut state.holes[opposite(last)]);
+            state.holes[store(turn)] += captured;
+        }
+        if state.is_over() {
+            for player in [Player::First, Player::Second] {
+                let remaining = pits(player)
+                    .map(|pit| std::mem::take(&mut state.holes[pit]))
+                    .sum::<u8>();
+                state.holes[store(player)] += remaining;
+            }
+        }
+        if last != store(turn) {
+            state.turn = turn.flip();
+        }
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        state.holes = mv.prev_holes;
+        state.turn = if pits(Player::First).contains(&mv.pit) {
+            Player::First
+        } else {
+            Player::Second
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MoveSelectionState {
+    Initial,
+}
+
+// The hole drawn in a cell. The stores fill the end columns.
+fn hole_at(row: usize, col: usize) -> usize {
+    match (row, col) {
+        (_, 0) => store(Player::Second),
+        (_, 7) => store(Player::First),
+        (0, col) => PITS + 1 + (PITS - col),
+        (_, col) => col - 1,
+    }
+}
+
+impl GridGame for Kalah {
+    const ROWS: usize = 2;
+    const COLS: usize = PITS + 2;
+
+    // Seeds are counted rather than drawn, see `draw_move_selection_on_grid`
+    fn piece(&self, _state: &Self::State, _row: usize, _col: usize) -> Piece {
+        Piece::Empty
+    }
+
+    fn cell_text(&self, state: &Self::State, row: usize, col: usize) -> Option<String> {
+        Some(state.holes[hole_at(row, col)].to_string())
+    }
+
+    type MoveSelectionState = MoveSelectionState;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        MoveSelectionState::Initial
+    }
+
+    fn player_name(&self, player: Player) -> &'static str {
+        match player {
+            Player::First => "South",
+            Player::Second => "North",
+        }
+    }
+
+    fn highlight_square(&self, row: usize, col: usize) -> Option<Color32> {
+        let hole = hole_at(row, col);
+        (hole == store(Player::First) || hole == store(Player::Second))
+            .then_some(Color32::BROWN.gamma_multiply(0.5))
+    }
+
+    // Pits are numbered in each player's moves rather than named by row and column
+    fn col_label(&self, _col: usize) -> Option<String> {
+        None
+    }
+
+    fn row_label(&self, _row: usize) -> Option<String> {
+        None
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        for row in 0..2 {
+            for col in 1..=PITS {
+                if hole_at(row, col) == mv.pit {
+                    painter.circle_filled(
+                        cell_to_rect(row, col).center(),
+                        0.4 * cell_size,
+                        Color32::ORANGE.gamma_multiply(0.5),
+                    );
+                }
+            }
+        }
+    }
+
+    fn update_move_selection(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        _move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        match action {
+            MoveSelectionAction::Reset => None,
+            MoveSelectionAction::ClickSquare { row, col } => self
+                .generate_moves(&mut state.clone())
+                .into_iter()
+                .find(|mv| mv.pit == hole_at(row, col)),
+        }
+    }
+
+    // Draw the pits and stores with the number of seeds in each
+    fn draw_move_selection_on_grid(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        let stroke = Stroke::new(0.03 * cell_size, Color32::BLACK);
+        let font = FontId::proportional(0.35 * cell_size);
+        for col in [0, PITS + 1] {
+            let rect = cell_to_rect(0, col)
+                .union(cell_to_rect(1, col))
+                .shrink(0.1 * cell_size);
+            painter.rect_stroke(rect, 0.4 * cell_size, stroke, egui::StrokeKind::Inside);
+            painter.text(
+                rect.center(),
+                Align2::CENTER_CENTER,
+                state.holes[hole_at(0, col)],
+                font.clone(),
+                Color32::BLACK,
+            );
+        }
+        for row in 0..2 {
+            for col in 1..=PITS {
+                let center = cell_to_rect(row, col).center();
+                painter.circle_stroke(center, 0.4 * cell_size, stroke);
+                painter.text(
+                    center,
+                    Align2::CENTER_CENTER,
+                    state.holes[hole_at(row, col)],
+                    font.clone(),
+                    Color32::BLACK,
+                );
+            }
+        }
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        _ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    fn sow(logic: &Kalah, state: &mut BoardState, pit: usize) -> Move {
+        let mv = logic
+            .generate_moves(state)
+            .into_iter()
+            .find(|mv| mv.pit == pit)
+            .unwrap();
+        logic.make_move(state, &mv);
+        mv
+    }
+
+    #[test]
+    fn extra_turn() {
+        let mut game = Game::new(Kalah);
+        // Four seeds from the third pit end in the store
+        let mv = game.logic().generate_moves(&mut game.state().clone())[2].clone();
+        game.make_move(mv);
+        assert_eq!(game.turn(), Player::First);
+        assert_eq!(game.state().holes[store(Player::First)], 1);
+        let mv = game.logic().generate_moves(&mut game.state().clone())[0].clone();
+        game.make_move(mv);
+        assert_eq!(game.turn(), Player::Second);
+        game.undo_move();
+        game.undo_move();
+        assert_eq!(game.state(), &Kalah.initial_state());
+    }
+
+    #[test]
+    fn capture_and_game_end() {
+        let logic = Kalah;
+        let mut state = logic.initial_state();
+        state.holes = [1, 0, 2, 0, 0, 0, 0, 0, 0, 0, 1, 3, 0, 0];
+        let before = state.clone();
+        // The seed lands in the empty second pit, taking the three seeds opposite
+        let mv = sow(&logic, &mut state, 0);
+        assert_eq!(state.holes, [0, 0, 2, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(state.turn, Player::Second);
+        logic.unmake_move(&mut state, &mv);
+        assert_eq!(state, before);
+
+        // Emptying north's side ends the game and south banks the rest
+        state.holes = [1, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 5];
+        state.turn = Player::Second;
+        sow(&logic, &mut state, 12);
+        assert_eq!(state.holes, [0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 6]);
+        assert!(logic.generate_moves(&mut state).is_empty());
+        assert!(matches!(logic.score(&mut state), AbsScore::SecondPlayerWin));
+    }
+}
@@ -0,0 +1,492 @@
+use std::fmt::Display;
+
+use egui::{Color32, Painter, Rect};
+
+use crate::{
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{GridGame, MoveSelectionAction, Piece},
+};
+
+const SIZE: usize = 8;
+const DIRECTIONS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+// Every square except those in the first and last columns respectively
+const NOT_FIRST_COL: u64 = 0xfefe_fefe_fefe_fefe;
+const NOT_LAST_COL: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+
+fn bit(row: usize, col: usize) -> u64 {
+    1 << (SIZE * row + col)
+}
+
+fn player_idx(player: Player) -> usize {
+    match player {
+        Player::First => 0,
+        Player::Second => 1,
+    }
+}
+
+// The squares in `squares` together with all their neighbours
+fn grow(squares: u64) -> u64 {
+    let row = squares | ((squares << 1) & NOT_FIRST_COL) | ((squares >> 1) & NOT_LAST_COL);
+    row | (row << SIZE) | (row >> SIZE)
+}
+
+// Whether the pieces form a single group, found by flood filling from one of them
+fn is_connected(pieces: u64) -> bool {
+    let mut group = pieces & pieces.wrapping_neg();
+    loop {
+        let next = grow(group) & pieces;
+        if next == group {
+            return group == pieces;
+        }
+        group = next;
+    }
+}
+
+/*
+Lines of Action on an 8x8 board.
+A piece moves in a straight line exactly as many squares as there are pieces, of either colour, on that line.
+It may jump over its own pieces but not the opponent's, and captures by landing on an opposing piece.
+The first player to join all their pieces into one group, counting diagonal contact, wins.
+If a move joins up both players' pieces then the player who made it wins.
+Black is the first player and starts on the top and bottom rows.
+ */
+#[derive(Debug, Clone)]
+pub struct LinesOfAction;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState {
+    pieces: [u64; 2],
+    move_num: usize,
+}
+
+impl BoardState {
+    fn get(&self, row: usize, col: usize) -> Option<Player> {
+        if self.pieces[0] & bit(row, col) != 0 {
+            Some(Player::First)
+        } else if self.pieces[1] & bit(row, col) != 0 {
+            Some(Player::Second)
+        } else {
+            None
+        }
+    }
+
+    fn winner(&self) -> Option<Player> {
+        if self.move_num == 0 {
+            return None;
+        }
+        let last = if self.move_num.is_multiple_of(2) {
+            Player::Second
+        } else {
+            Player::First
+        };
+        if is_connected(self.pieces[player_idx(last)]) {
+            Some(last)
+        } else if is_connected(self.pieces[player_idx(last.flip())]) {
+            Some(last.flip())
+        } else {
+            None
+        }
+    }
+
+    // How many pieces lie on the line through (row, col) in the direction (drow, dcol)
+    fn line_count(&self, row: usize, col: usize, (drow, dcol): (isize, isize)) -> usize {
+        let occupied = self.pieces[0] | self.pieces[1];
+        let mut count = 1;
+        for sign in [-1, 1] {
+            let (mut r, mut c) = (row, col);
+            while let (Some(next_r), Some(next_c)) = (
+                r.checked_add_signed(sign * drow).filter(|r| *r < SIZE),
+                c.checked_add_signed(sign * dcol).filter(|c| *c < SIZE),
+            ) {
+                (r, c) = (next_r, next_c);
+                if occupied & bit(r, c) != 0 {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    // The sum over a player's pieces of how far each is from their centre of mass
+    fn spread(&self, player: Player) -> i64 {
+        let pieces = self.pieces[player_idx(player)];
+        let n = pieces.count_ones() as i64;
+        let squares = (0..SIZE)
+            .flat_map(|row| (0..SIZE).map(move |col| (row as i64, col as i64)))
+            .filter(|(row, col)| pieces & bit(*row as usize, *col as usize) != 0)
+            .collect::<Vec<_>>();
+        let row_sum: i64 = squares.iter().map(|(row, _)| row).sum();
+        let col_sum: i64 = squares.iter().map(|(_, col)| col).sum();
+        squares
+            .iter()
+            .map(|(row, col)| (n * row - row_sum).abs().max((n * col - col_sum).abs()))
+            .sum::<i64>()
+            / n
+    }
+}
+
+impl State<LinesOfAction> for BoardState {
+    fn ident(self) -> BoardStateIdent {
+        BoardStateIdent {
+            pieces: self.pieces,
+            move_num: self.move_num,
+        }
+    }
+
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+#[derive(Debug, Clone)]
+pub struct BoardStateIdent {
+    pieces: [u64; 2],
+    move_num: usize,
+}
+
+impl PartialEq for BoardStateIdent {
+    fn eq(&self, other: &Self) -> bool {
+        self.pieces == other.pieces && self.move_num % 2 == other.move_num % 2
+    }
+}
+
+impl Eq for BoardStateIdent {}
+
+impl NoAlloc for BoardStateIdent {}
+
+impl StateIdent<LinesOfAction> for BoardStateIdent {
+    fn hash64(&self) -> u64 {
+        (self.pieces[0].wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ self.pieces[1]
+                .wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+                .rotate_left(29))
+        .wrapping_add(self.move_num as u64 % 2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    from: (usize, usize),
+    to: (usize, usize),
+    capture: bool,
+}
+
+fn square_name((row, col): (usize, usize)) -> String {
+    format!("{}{}", (b'a' + col as u8) as char, SIZE - row)
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sep = if self.capture { "x" } else { "-" };
+        write!(
+            f,
+            "{}{}{}",
+            square_name(self.from),
+            sep,
+            square_name(self.to)
+        )
+    }
+}
+
+impl GameLogic for LinesOfAction {
+    type State = BoardState;
+    type StateIdent = BoardStateIdent;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        let mut pieces = [0, 0];
+        for i in 1..SIZE - 1 {
+            pieces[0] |= bit(0, i) | bit(SIZE - 1, i);
+            pieces[1] |= bit(i, 0) | bit(i, SIZE - 1);
+        }
+        BoardState {
+            pieces,
+            move_num: 0,
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        if state.move_num.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        if state.winner().is_some() {
+            return vec![];
+        }
+        let turn = self.turn(state);
+        let own = state.pieces[player_idx(turn)];
+        let other = state.pieces[player_idx(turn.flip())];
+        let mut moves = vec![];
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                if own & bit(row, col) == 0 {
+                    continue;
+                }
+                for dir in DIRECTIONS {
+                    let distance = state.line_count(row, col, dir) as isize;
+                    let (Some(to_row), Some(to_col)) = (
+                        row.checked_add_signed(distance * dir.0)
+                            .filter(|r| *r < SIZE),
+                        col.checked_add_signed(distance * dir.1)
+                            .filter(|c| *c < SIZE),
+                    ) else {
+                        continue;
+                    };
+                    if own & bit(to_row, to_col) != 0 {
+                        continue;
+                    }
+                    let blocked = (1..distance).any(|i| {
+                        other
+                            & bit(
+                                row.strict_add_signed(i * dir.0),
+                                col.strict_add_signed(i * dir.1),
+                            )
+                            != 0
+                    });
+                    if !blocked {
+                        moves.push(Move {
+                            from: (row, col),
+                            to: (to_row, to_col),
+                            capture: other & bit(to_row, to_col) != 0,
+                        });
+                    }
+                }
+            }
+        }
+        // Captures first, then moves towards the centre
+        let centre_dist = |(row, col): (usize, usize)| {
+            (2 * row).abs_diff(SIZE - 1) + (2 * col).abs_diff(SIZE - 1)
+        };
+        moves.sort_by_key(|mv| (!mv.capture, centre_dist(mv.to)));
+        moves
+    }
+
+    fn generate_quiescence_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        self.generate_moves(state)
+            .into_iter()
+            .filter(|mv| mv.capture)
+            .collect()
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        match state.winner() {
+            Some(Player::First) => return AbsScore::FirstPlayerWin,
+            Some(Player::Second) => return AbsScore::SecondPlayerWin,
+            None => {}
+        }
+        // Having no moves at all loses
+        if self.generate_moves(state).is_empty() {
+            return match self.turn(state) {
+                Player::First => AbsScore::SecondPlayerWin,
+                Player::Second => AbsScore::FirstPlayerWin,
+            };
+        }
+        // Keeping your pieces close together is what matters
+        AbsScore::Heuristic(state.spread(Player::Second) - state.spread(Player::First))
+    }
+
+    fn is_capture(&self, _state: &Self::State, mv: &Self::Move) -> bool {
+        mv.capture
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        let idx = player_idx(self.turn(state));
+        debug_assert!(state.pieces[idx] & bit(mv.from.0, mv.from.1) != 0);
+        debug_assert_eq!(
+            mv.capture,
+            state.pieces[1 - idx] & bit(mv.to.0, mv.to.1) != 0
+        );
+        state.pieces[idx] ^= bit(mv.from.0, mv.from.1) | bit(mv.to.0, mv.to.1);
+        if mv.capture {
+            state.pieces[1 - idx] &= !bit(mv.to.0, mv.to.1);
+        }
+        state.move_num += 1;
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        state.move_num -= 1;
+        let idx = player_idx(self.turn(state));
+        state.pieces[idx] ^= bit(mv.from.0, mv.from.1) | bit(mv.to.0, mv.to.1);
+        if mv.capture {
+            state.pieces[1 - idx] |= bit(mv.to.0, mv.to.1);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MoveSelectionState {
+    Initial,
+    Selected { row: usize, col: usize },
+}
+
+impl GridGame for LinesOfAction {
+    const ROWS: usize = SIZE;
+    const COLS: usize = SIZE;
+
+    fn piece(&self, state: &Self::State, row: usize, col: usize) -> Piece {
+        match state.get(row, col) {
+            Some(Player::First) => Piece::BlackDisc,
+            Some(Player::Second) => Piece::WhiteDisc,
+            None => Piece::Empty,
+        }
+    }
+
+    type MoveSelectionState = MoveSelectionState;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        MoveSelectionState::Initial
+    }
+
+    fn player_name(&self, player: Player) -> &'static str {
+        match player {
+            Player::First => "Black",
+            Player::Second => "White",
+        }
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        for ((row, col), strength) in [(mv.from, 0.25), (mv.to, 0.5)] {
+            let rect = cell_to_rect(row, col).shrink(0.06 * cell_size);
+            painter.rect_filled(
+                rect,
+                0.2 * cell_size,
+                Color32::ORANGE.gamma_multiply(strength),
+            );
+        }
+    }
+
+    fn update_move_selection(
+        &self,
+        turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        let MoveSelectionAction::ClickSquare { row, col } = action else {
+            *move_selection_state = MoveSelectionState::Initial;
+            return None;
+        };
+        if let MoveSelectionState::Selected {
+            row: from_row,
+            col: from_col,
+        } = *move_selection_state
+            && let Some(mv) = self
+                .generate_moves(&mut state.clone())
+                .into_iter()
+                .find(|mv| mv.from == (from_row, from_col) && mv.to == (row, col))
+        {
+            *move_selection_state = MoveSelectionState::Initial;
+            return Some(mv);
+        }
+        *move_selection_state = if state.get(row, col) == Some(turn) {
+            MoveSelectionState::Selected { row, col }
+        } else {
+            MoveSelectionState::Initial
+        };
+        None
+    }
+
+    fn draw_move_selection_on_grid(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        move_selection_state: &Self::MoveSelectionState,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        let MoveSelectionState::Selected { row, col } = *move_selection_state else {
+            return;
+        };
+        let highlight = |(row, col): (usize, usize), color: Color32| {
+            let rect = cell_to_rect(row, col).shrink(0.06 * cell_size);
+            painter.rect_filled(rect, 0.2 * cell_size, color);
+        };
+        highlight(
+            (row, col),
+            Color32::CYAN
+                .lerp_to_gamma(Color32::BLUE, 0.5)
+                .gamma_multiply(0.5),
+        );
+        for mv in self.generate_moves(&mut state.clone()) {
+            if mv.from == (row, col) {
+                highlight(
+                    mv.to,
+                    if mv.capture {
+                        Color32::RED.gamma_multiply(0.5)
+                    } else {
+                        Color32::CYAN
+                            .lerp_to_gamma(Color32::GREEN, 0.5)
+                            .gamma_multiply(0.5)
+                    },
+                );
+            }
+        }
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        _ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connectivity() {
+        assert!(is_connected(bit(0, 0)));
+        assert!(is_connected(bit(0, 7) | bit(1, 6) | bit(2, 7)));
+        // Pieces at the ends of neighbouring rows don't wrap around
+        assert!(!is_connected(bit(0, 7) | bit(1, 0)));
+        assert!(!is_connected(bit(3, 3) | bit(3, 5)));
+    }
+
+    #[test]
+    fn moves_and_winning() {
+        let logic = LinesOfAction;
+        let mut state = logic.initial_state();
+        assert_eq!(logic.generate_moves(&mut state).len(), 36);
+
+        // Black needs one move to join up while white is still split
+        state.pieces = [bit(3, 3) | bit(3, 4) | bit(5, 5), bit(0, 0) | bit(7, 7)];
+        let before = state.clone();
+        let mv = logic
+            .generate_moves(&mut state)
+            .into_iter()
+            .find(|mv| mv.to_string() == "f3-f4")
+            .unwrap();
+        logic.make_move(&mut state, &mv);
+        assert!(logic.generate_moves(&mut state).is_empty());
+        assert!(matches!(logic.score(&mut state), AbsScore::FirstPlayerWin));
+        logic.unmake_move(&mut state, &mv);
+        assert_eq!(state, before);
+    }
+}
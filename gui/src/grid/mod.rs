@@ -1,10 +1,34 @@
-use egui::{Painter, Rect};
+use egui::{Color32, Painter, Rect};
 
-use crate::game::{GameLogic, Player};
-use std::fmt::Debug;
+use crate::{
+    ai::evaluator::Evaluator,
+    game::{Game, GameLogic, Player},
+    grid::notation::NotationStyle,
+};
+use std::{fmt::Debug, sync::Arc};
 
+pub mod amazons;
+pub mod bench;
+pub mod breakthrough;
+pub mod checkers;
 pub mod chess;
+pub mod connect_four;
+pub mod go;
+pub mod gomoku;
+pub mod hex;
+pub mod kalah;
+pub mod lines_of_action;
+pub mod morris;
+pub mod notation;
+pub mod registry;
+pub mod reversi;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod shogi;
+pub mod theme;
+pub mod tournament;
 pub mod ui;
+pub mod versus;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Piece {
@@ -25,6 +49,185 @@ pub enum Piece {
     BlackQueen,
     BlackKing,
     BlackGrasshopper,
+    WhiteChecker,
+    WhiteCheckerKing,
+    BlackChecker,
+    BlackCheckerKing,
+    RedDisc,
+    YellowDisc,
+    BlackDisc,
+    WhiteDisc,
+    BlueDisc,
+    SenteKing,
+    SenteRook,
+    SenteDragon,
+    SenteBishop,
+    SenteHorse,
+    SenteGold,
+    SenteSilver,
+    SentePromotedSilver,
+    SenteKnight,
+    SentePromotedKnight,
+    SenteLance,
+    SentePromotedLance,
+    SentePawn,
+    SenteTokin,
+    GoteKing,
+    GoteRook,
+    GoteDragon,
+    GoteBishop,
+    GoteHorse,
+    GoteGold,
+    GoteSilver,
+    GotePromotedSilver,
+    GoteKnight,
+    GotePromotedKnight,
+    GoteLance,
+    GotePromotedLance,
+    GotePawn,
+    GoteTokin,
+}
+
+impl Piece {
+    // Pieces drawn as plain discs rather than with an icon
+    pub fn disc_color(self) -> Option<Color32> {
+        match self {
+            Piece::RedDisc => Some(Color32::from_rgb(220, 40, 40)),
+            Piece::YellowDisc => Some(Color32::from_rgb(240, 200, 30)),
+            Piece::BlackDisc => Some(Color32::from_rgb(30, 30, 30)),
+            Piece::WhiteDisc => Some(Color32::from_rgb(240, 240, 240)),
+            Piece::BlueDisc => Some(Color32::from_rgb(40, 80, 220)),
+            _ => None,
+        }
+    }
+
+    // What the piece is called when read out by a screen reader, e.g. "white pawn"
+    pub fn name(self) -> Option<&'static str> {
+        Some(match self {
+            Piece::WhitePawn => "white pawn",
+            Piece::WhiteBerolinaPawn => "white berolina pawn",
+            Piece::WhiteRook => "white rook",
+            Piece::WhiteKnight => "white knight",
+            Piece::WhiteBishop => "white bishop",
+            Piece::WhiteQueen => "white queen",
+            Piece::WhiteKing => "white king",
+            Piece::WhiteGrasshopper => "white grasshopper",
+            Piece::BlackPawn => "black pawn",
+            Piece::BlackBerolinaPawn => "black berolina pawn",
+            Piece::BlackRook => "black rook",
+            Piece::BlackKnight => "black knight",
+            Piece::BlackBishop => "black bishop",
+            Piece::BlackQueen => "black queen",
+            Piece::BlackKing => "black king",
+            Piece::BlackGrasshopper => "black grasshopper",
+            Piece::WhiteChecker => "white checker",
+            Piece::WhiteCheckerKing => "white checker king",
+            Piece::BlackChecker => "black checker",
+            Piece::BlackCheckerKing => "black checker king",
+            Piece::RedDisc => "red disc",
+            Piece::YellowDisc => "yellow disc",
+            Piece::BlackDisc => "black disc",
+            Piece::WhiteDisc => "white disc",
+            Piece::BlueDisc => "blue disc",
+            Piece::SenteKing => "sente king",
+            Piece::SenteRook => "sente rook",
+            Piece::SenteDragon => "sente dragon",
+            Piece::SenteBishop => "sente bishop",
+            Piece::SenteHorse => "sente horse",
+            Piece::SenteGold => "sente gold",
+            Piece::SenteSilver => "sente silver",
+            Piece::SentePromotedSilver => "sente promoted silver",
+            Piece::SenteKnight => "sente knight",
+            Piece::SentePromotedKnight => "sente promoted knight",
+            Piece::SenteLance => "sente lance",
+            Piece::SentePromotedLance => "sente promoted lance",
+            Piece::SentePawn => "sente pawn",
+            Piece::SenteTokin => "sente tokin",
+            Piece::GoteKing => "gote king",
+            Piece::GoteRook => "gote rook",
+            Piece::GoteDragon => "gote dragon",
+            Piece::GoteBishop => "gote bishop",
+            Piece::GoteHorse => "gote horse",
+            Piece::GoteGold => "gote gold",
+            Piece::GoteSilver => "gote silver",
+            Piece::GotePromotedSilver => "gote promoted silver",
+            Piece::GoteKnight => "gote knight",
+            Piece::GotePromotedKnight => "gote promoted knight",
+            Piece::GoteLance => "gote lance",
+            Piece::GotePromotedLance => "gote promoted lance",
+            Piece::GotePawn => "gote pawn",
+            Piece::GoteTokin => "gote tokin",
+            Piece::Empty => return None,
+        })
+    }
+
+    // The letter drawn for the piece in the letters piece set, with the player it belongs to
+    pub fn letter(self) -> Option<(&'static str, Player)> {
+        Some(match self {
+            Piece::WhitePawn | Piece::WhiteBerolinaPawn => ("P", Player::First),
+            Piece::WhiteRook => ("R", Player::First),
+            Piece::WhiteKnight => ("N", Player::First),
+            Piece::WhiteBishop => ("B", Player::First),
+            Piece::WhiteQueen => ("Q", Player::First),
+            Piece::WhiteKing => ("K", Player::First),
+            Piece::WhiteGrasshopper => ("G", Player::First),
+            Piece::BlackPawn | Piece::BlackBerolinaPawn => ("P", Player::Second),
+            Piece::BlackRook => ("R", Player::Second),
+            Piece::BlackKnight => ("N", Player::Second),
+            Piece::BlackBishop => ("B", Player::Second),
+            Piece::BlackQueen => ("Q", Player::Second),
+            Piece::BlackKing => ("K", Player::Second),
+            Piece::BlackGrasshopper => ("G", Player::Second),
+            Piece::WhiteChecker => ("", Player::First),
+            Piece::WhiteCheckerKing => ("K", Player::First),
+            Piece::BlackChecker => ("", Player::Second),
+            Piece::BlackCheckerKing => ("K", Player::Second),
+            Piece::SenteKing => ("K", Player::First),
+            Piece::SenteRook => ("R", Player::First),
+            Piece::SenteDragon => ("+R", Player::First),
+            Piece::SenteBishop => ("B", Player::First),
+            Piece::SenteHorse => ("+B", Player::First),
+            Piece::SenteGold => ("G", Player::First),
+            Piece::SenteSilver => ("S", Player::First),
+            Piece::SentePromotedSilver => ("+S", Player::First),
+            Piece::SenteKnight => ("N", Player::First),
+            Piece::SentePromotedKnight => ("+N", Player::First),
+            Piece::SenteLance => ("L", Player::First),
+            Piece::SentePromotedLance => ("+L", Player::First),
+            Piece::SentePawn => ("P", Player::First),
+            Piece::SenteTokin => ("+P", Player::First),
+            Piece::GoteKing => ("K", Player::Second),
+            Piece::GoteRook => ("R", Player::Second),
+            Piece::GoteDragon => ("+R", Player::Second),
+            Piece::GoteBishop => ("B", Player::Second),
+            Piece::GoteHorse => ("+B", Player::Second),
+            Piece::GoteGold => ("G", Player::Second),
+            Piece::GoteSilver => ("S", Player::Second),
+            Piece::GotePromotedSilver => ("+S", Player::Second),
+            Piece::GoteKnight => ("N", Player::Second),
+            Piece::GotePromotedKnight => ("+N", Player::Second),
+            Piece::GoteLance => ("L", Player::Second),
+            Piece::GotePromotedLance => ("+L", Player::Second),
+            Piece::GotePawn => ("P", Player::Second),
+            Piece::GoteTokin => ("+P", Player::Second),
+            Piece::Empty
+            | Piece::RedDisc
+            | Piece::YellowDisc
+            | Piece::BlackDisc
+            | Piece::WhiteDisc
+            | Piece::BlueDisc => return None,
+        })
+    }
+}
+
+// The shape of the cells the board is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellShape {
+    Square,
+    // Pointy-topped hexagons with each row shifted half a cell right of the one above, making a rhombus
+    Hex,
+    // Pieces sit on the points joined by `GridGame::board_lines` and the cells are not drawn
+    Points,
 }
 
 pub enum MoveSelectionAction {
@@ -35,9 +238,25 @@ pub enum MoveSelectionAction {
 pub trait GridGame: GameLogic {
     const ROWS: usize;
     const COLS: usize;
+    const CELL_SHAPE: CellShape = CellShape::Square;
+
+    // The size of the board actually in use, for games whose board size depends on the variant
+    fn rows(&self) -> usize {
+        Self::ROWS
+    }
+
+    fn cols(&self) -> usize {
+        Self::COLS
+    }
 
     fn piece(&self, state: &Self::State, row: usize, col: usize) -> Piece;
 
+    // What's written in a cell when the board is printed as text, for games which count what's in their cells rather than drawing pieces
+    #[allow(unused_variables)]
+    fn cell_text(&self, state: &Self::State, row: usize, col: usize) -> Option<String> {
+        None
+    }
+
     type MoveSelectionState: Debug + Clone;
 
     fn initial_move_selection(&self) -> Self::MoveSelectionState;
@@ -78,4 +297,89 @@ pub trait GridGame: GameLogic {
         ctx: &egui::Context,
         frame: &mut eframe::Frame,
     ) -> Option<Self::Move>;
+
+    fn player_name(&self, player: Player) -> &'static str {
+        match player {
+            Player::First => "White",
+            Player::Second => "Black",
+        }
+    }
+
+    // The notation styles `move_notation` can write, the first being the game's usual one
+    fn notation_styles(&self) -> &'static [NotationStyle] {
+        &[NotationStyle::Standard]
+    }
+
+    // How `mv` is written in `style`, played from `state`
+    #[allow(unused_variables)]
+    fn move_notation(&self, state: &Self::State, mv: &Self::Move, style: NotationStyle) -> String {
+        mv.to_string()
+    }
+
+    // Everything `mv` can be typed as to play it, whichever notation style is chosen
+    fn typed_names(&self, state: &Self::State, mv: &Self::Move) -> Vec<String> {
+        let mut names = self
+            .notation_styles()
+            .iter()
+            .map(|style| self.move_notation(state, mv, *style))
+            .collect::<Vec<_>>();
+        names.push(mv.to_string());
+        names
+    }
+
+    // The names written along the edges of the board, by default letters for the columns and numbers for the rows counting up from the bottom
+    fn col_label(&self, col: usize) -> Option<String> {
+        Some(((b'a' + col as u8) as char).to_string())
+    }
+
+    fn row_label(&self, row: usize) -> Option<String> {
+        Some((self.rows() - row).to_string())
+    }
+
+    // The cell of the piece in check, such as the king, to mark it
+    #[allow(unused_variables)]
+    fn checked_cell(&self, state: &Self::State) -> Option<(usize, usize)> {
+        None
+    }
+
+    // Who a piece belongs to and what it's worth, for games where the pieces taken are counted up
+    #[allow(unused_variables)]
+    fn material(&self, piece: Piece) -> Option<(Player, i64)> {
+        None
+    }
+
+    // A tint drawn over a square of the board e.g. to mark special squares
+    #[allow(unused_variables)]
+    fn highlight_square(&self, row: usize, col: usize) -> Option<Color32> {
+        None
+    }
+
+    // The lines drawn between the centres of pairs of cells for `CellShape::Points` boards
+    fn board_lines(&self) -> Vec<((usize, usize), (usize, usize))> {
+        vec![]
+    }
+
+    // Show extra details about the state in the side panel
+    #[allow(unused_variables)]
+    fn show_state_info(&self, state: &Self::State, ui: &mut egui::Ui) {}
+
+    // A text record of the game as (file extension, contents) e.g. PGN for chess, with the moves written in `style`
+    #[allow(unused_variables)]
+    fn export_game(
+        &self,
+        game: &Game<Self>,
+        style: NotationStyle,
+    ) -> Option<(&'static str, String)> {
+        None
+    }
+
+    // The file extensions of the evaluation networks and parameters `load_evaluator` reads, if the game has any
+    fn evaluator_extensions(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    #[allow(unused_variables)]
+    fn load_evaluator(&self, bytes: &[u8]) -> Result<Arc<dyn Evaluator<Self>>, String> {
+        Err("This game has no evaluation networks".to_string())
+    }
 }
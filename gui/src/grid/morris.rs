@@ -0,0 +1,594 @@
+use std::fmt::Display;
+
+use egui::{Color32, Painter, Rect};
+
+use crate::{
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{CellShape, GridGame, MoveSelectionAction, Piece},
+};
+
+const POINTS: usize = 24;
+const PIECES: usize = 9;
+// A draw is declared after this many plies of moving pieces without a capture
+const QUIET_PLY_LIMIT: usize = 100;
+
+/*
+The 24 points lie on three nested squares drawn on a 7x7 grid.
+Point `8 * ring + k` is on ring 0 (outer), 1 (middle) or 2 (inner) and goes clockwise from the top left corner at k = 0.
+The midpoints of each side (odd k) are joined to the same point on the neighbouring rings.
+ */
+fn coords(point: usize) -> (usize, usize) {
+    let (ring, k) = (point / 8, point % 8);
+    let d = 3 - ring as isize;
+    let (drow, dcol) = [
+        (-d, -d),
+        (-d, 0),
+        (-d, d),
+        (0, d),
+        (d, d),
+        (d, 0),
+        (d, -d),
+        (0, -d),
+    ][k];
+    ((3 + drow) as usize, (3 + dcol) as usize)
+}
+
+fn point_at(row: usize, col: usize) -> Option<usize> {
+    (0..POINTS).find(|point| coords(*point) == (row, col))
+}
+
+fn point_name(point: usize) -> String {
+    let (row, col) = coords(point);
+    format!("{}{}", (b'a' + col as u8) as char, 7 - row)
+}
+
+fn neighbours(point: usize) -> impl Iterator<Item = usize> {
+    let (ring, k) = (point / 8, point % 8);
+    let around = [8 * ring + (k + 1) % 8, 8 * ring + (k + 7) % 8];
+    let across = [
+        (k % 2 == 1 && ring > 0).then(|| point - 8),
+        (k % 2 == 1 && ring < 2).then(|| point + 8),
+    ];
+    around.into_iter().chain(across.into_iter().flatten())
+}
+
+// Every line of three points along a side of a square or across the midpoints of the squares
+fn mills() -> impl Iterator<Item = [usize; 3]> {
+    let sides = (0..3).flat_map(|ring| {
+        [0, 2, 4, 6].map(|k| [8 * ring + k, 8 * ring + k + 1, 8 * ring + (k + 2) % 8])
+    });
+    let spokes = [1, 3, 5, 7].map(|k| [k, k + 8, k + 16]);
+    sides.chain(spokes)
+}
+
+fn player_idx(player: Player) -> usize {
+    match player {
+        Player::First => 0,
+        Player::Second => 1,
+    }
+}
+
+/*
+Nine men's morris.
+Each player first places their nine pieces one at a time, then moves them to neighbouring points, and once
+down to three pieces may fly to any empty point. Making a line of three (a mill) removes an opposing piece,
+which must not be in a mill unless all of them are. A player loses when left with two pieces or no moves.
+ */
+#[derive(Debug, Clone)]
+pub struct Morris;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState {
+    points: [Option<Player>; POINTS],
+    in_hand: [usize; 2],
+    move_num: usize,
+    quiet_plies: usize,
+}
+
+impl BoardState {
+    fn count(&self, player: Player) -> usize {
+        self.points.iter().filter(|p| **p == Some(player)).count()
+    }
+
+    fn in_mill(&self, point: usize) -> bool {
+        let Some(player) = self.points[point] else {
+            return false;
+        };
+        mills().any(|mill| {
+            mill.contains(&point) && mill.iter().all(|p| self.points[*p] == Some(player))
+        })
+    }
+}
+
+impl State<Morris> for BoardState {
+    fn ident(self) -> BoardStateIdent {
+        BoardStateIdent {
+            points: self.points,
+            in_hand: self.in_hand,
+            move_num: self.move_num,
+        }
+    }
+
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+#[derive(Debug, Clone)]
+pub struct BoardStateIdent {
+    points: [Option<Player>; POINTS],
+    in_hand: [usize; 2],
+    move_num: usize,
+}
+
+impl PartialEq for BoardStateIdent {
+    fn eq(&self, other: &Self) -> bool {
+        self.points == other.points
+            && self.in_hand == other.in_hand
+            && self.move_num % 2 == other.move_num % 2
+    }
+}
+
+impl Eq for BoardStateIdent {}
+
+impl NoAlloc for BoardStateIdent {}
+
+impl StateIdent<Morris> for BoardStateIdent {
+    fn hash64(&self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325u64 ^ (self.move_num as u64 % 2);
+        for point in &self.points {
+            let value = match point {
+                None => 0,
+                Some(Player::First) => 1,
+                Some(Player::Second) => 2,
+            };
+            hash = (hash ^ value).wrapping_mul(0x0100_0000_01b3);
+        }
+        for count in self.in_hand {
+            hash = (hash ^ count as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Move {
+    // None when placing a piece from the player's hand
+    from: Option<usize>,
+    to: usize,
+    capture: Option<usize>,
+    prev_quiet_plies: usize,
+}
+
+impl Move {
+    // The points clicked to make this move, in order
+    fn clicks(&self) -> Vec<usize> {
+        self.from
+            .into_iter()
+            .chain(std::iter::once(self.to))
+            .chain(self.capture)
+            .collect()
+    }
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(from) = self.from {
+            write!(f, "{}-", point_name(from))?;
+        }
+        write!(f, "{}", point_name(self.to))?;
+        if let Some(capture) = self.capture {
+            write!(f, "x{}", point_name(capture))?;
+        }
+        Ok(())
+    }
+}
+
+impl Morris {
+    // The (from, to) pairs available to `turn` before considering captures
+    fn steps(&self, state: &BoardState, turn: Player) -> Vec<(Option<usize>, usize)> {
+        let empty = (0..POINTS).filter(|p| state.points[*p].is_none());
+        if state.in_hand[player_idx(turn)] > 0 {
+            return empty.map(|to| (None, to)).collect();
+        }
+        let own = (0..POINTS).filter(|p| state.points[*p] == Some(turn));
+        if state.count(turn) == 3 {
+            let empty = empty.collect::<Vec<_>>();
+            own.flat_map(|from| empty.iter().map(move |to| (Some(from), *to)))
+                .collect()
+        } else {
+            own.flat_map(|from| {
+                neighbours(from)
+                    .filter(|to| state.points[*to].is_none())
+                    .map(move |to| (Some(from), to))
+            })
+            .collect()
+        }
+    }
+
+    fn is_over(&self, state: &BoardState) -> bool {
+        let turn = self.turn(state);
+        state.quiet_plies >= QUIET_PLY_LIMIT
+            || (state.in_hand[player_idx(turn)] == 0 && state.count(turn) < 3)
+    }
+}
+
+impl GameLogic for Morris {
+    type State = BoardState;
+    type StateIdent = BoardStateIdent;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        BoardState {
+            points: [None; POINTS],
+            in_hand: [PIECES; 2],
+            move_num: 0,
+            quiet_plies: 0,
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        if state.move_num.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        if self.is_over(state) {
+            return vec![];
+        }
+        let turn = self.turn(state);
+        let mut moves = vec![];
+        for (from, to) in self.steps(state, turn) {
+            // Look for a mill made by this step
+            if let Some(from) = from {
+                state.points[from] = None;
+            }
+            state.points[to] = Some(turn);
+            let mut captures = vec![];
+            if state.in_mill(to) {
+                let opponent = (0..POINTS)
+                    .filter(|p| state.points[*p] == Some(turn.flip()))
+                    .collect::<Vec<_>>();
+                captures = opponent
+                    .iter()
+                    .copied()
+                    .filter(|p| !state.in_mill(*p))
+                    .collect();
+                if captures.is_empty() {
+                    captures = opponent;
+                }
+            }
+            state.points[to] = None;
+            if let Some(from) = from {
+                state.points[from] = Some(turn);
+            }
+
+            if captures.is_empty() {
+                moves.push(Move {
+                    from,
+                    to,
+                    capture: None,
+                    prev_quiet_plies: state.quiet_plies,
+                });
+            }
+            for capture in captures {
+                moves.push(Move {
+                    from,
+                    to,
+                    capture: Some(capture),
+                    prev_quiet_plies: state.quiet_plies,
+                });
+            }
+        }
+        moves
+    }
+
+    fn generate_quiescence_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        self.generate_moves(state)
+            .into_iter()
+            .filter(|mv| mv.capture.is_some())
+            .collect()
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        if state.quiet_plies >= QUIET_PLY_LIMIT {
+            return AbsScore::Draw;
+        }
+        // The player to move loses when they are down to two pieces or have no moves
+        if self.generate_moves(state).is_empty() {
+            return match self.turn(state) {
+                Player::First => AbsScore::SecondPlayerWin,
+                Player::Second => AbsScore::FirstPlayerWin,
+            };
+        }
+
+        let value = |player: Player| {
+            let material = (state.count(player) + state.in_hand[player_idx(player)]) as i64;
+            // Lines where one more piece would make a mill
+            let threats = mills()
+                .filter(|mill| {
+                    let own = mill
+                        .iter()
+                        .filter(|p| state.points[**p] == Some(player))
+                        .count();
+                    let empty = mill.iter().filter(|p| state.points[**p].is_none()).count();
+                    own == 2 && empty == 1
+                })
+                .count() as i64;
+            let mobility = self.steps(state, player).len().min(POINTS) as i64;
+            100 * material + 10 * threats + mobility
+        };
+        AbsScore::Heuristic(value(Player::First) - value(Player::Second))
+    }
+
+    fn is_capture(&self, _state: &Self::State, mv: &Self::Move) -> bool {
+        mv.capture.is_some()
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        debug_assert_eq!(state.quiet_plies, mv.prev_quiet_plies);
+        debug_assert!(state.points[mv.to].is_none());
+        let turn = self.turn(state);
+        match mv.from {
+            Some(from) => state.points[from] = None,
+            None => state.in_hand[player_idx(turn)] -= 1,
+        }
+        state.points[mv.to] = Some(turn);
+        if let Some(capture) = mv.capture {
+            state.points[capture] = None;
+        }
+        if mv.from.is_some() && mv.capture.is_none() {
+            state.quiet_plies += 1;
+        } else {
+            state.quiet_plies = 0;
+        }
+        state.move_num += 1;
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        state.move_num -= 1;
+        state.quiet_plies = mv.prev_quiet_plies;
+        let turn = self.turn(state);
+        if let Some(capture) = mv.capture {
+            state.points[capture] = Some(turn.flip());
+        }
+        state.points[mv.to] = None;
+        match mv.from {
+            Some(from) => state.points[from] = Some(turn),
+            None => state.in_hand[player_idx(turn)] += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MoveSelectionState {
+    Initial,
+    // The points clicked so far
+    Path(Vec<usize>),
+}
+
+impl GridGame for Morris {
+    const ROWS: usize = 7;
+    const COLS: usize = 7;
+    const CELL_SHAPE: CellShape = CellShape::Points;
+
+    fn piece(&self, state: &Self::State, row: usize, col: usize) -> Piece {
+        match point_at(row, col).and_then(|point| state.points[point]) {
+            Some(Player::First) => Piece::WhiteDisc,
+            Some(Player::Second) => Piece::BlackDisc,
+            None => Piece::Empty,
+        }
+    }
+
+    type MoveSelectionState = MoveSelectionState;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        MoveSelectionState::Initial
+    }
+
+    fn board_lines(&self) -> Vec<((usize, usize), (usize, usize))> {
+        (0..POINTS)
+            .flat_map(|a| {
+                neighbours(a)
+                    .filter(move |b| a < *b)
+                    .map(move |b| (coords(a), coords(b)))
+            })
+            .collect()
+    }
+
+    fn show_state_info(&self, state: &Self::State, ui: &mut egui::Ui) {
+        ui.label(format!(
+            "Pieces in hand: White {}, Black {}",
+            state.in_hand[0], state.in_hand[1]
+        ));
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        let highlight = |point: usize, color: Color32| {
+            let (row, col) = coords(point);
+            painter.circle_filled(cell_to_rect(row, col).center(), 0.45 * cell_size, color);
+        };
+        if let Some(from) = mv.from {
+            highlight(from, Color32::ORANGE.gamma_multiply(0.25));
+        }
+        highlight(mv.to, Color32::ORANGE.gamma_multiply(0.5));
+        if let Some(capture) = mv.capture {
+            highlight(capture, Color32::RED.gamma_multiply(0.5));
+        }
+    }
+
+    fn update_move_selection(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        let MoveSelectionAction::ClickSquare { row, col } = action else {
+            *move_selection_state = MoveSelectionState::Initial;
+            return None;
+        };
+        let Some(point) = point_at(row, col) else {
+            *move_selection_state = MoveSelectionState::Initial;
+            return None;
+        };
+        let moves = self.generate_moves(&mut state.clone());
+        let mut extended = match move_selection_state {
+            MoveSelectionState::Initial => vec![],
+            MoveSelectionState::Path(path) => path.clone(),
+        };
+        extended.push(point);
+        // Carry on from the points already clicked, otherwise start again from this one
+        for path in [extended, vec![point]] {
+            if let Some(mv) = moves.iter().find(|mv| mv.clicks() == path) {
+                *move_selection_state = MoveSelectionState::Initial;
+                return Some(mv.clone());
+            }
+            if moves.iter().any(|mv| mv.clicks().starts_with(&path)) {
+                *move_selection_state = MoveSelectionState::Path(path);
+                return None;
+            }
+        }
+        *move_selection_state = MoveSelectionState::Initial;
+        None
+    }
+
+    fn draw_move_selection_on_grid(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        move_selection_state: &Self::MoveSelectionState,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        let MoveSelectionState::Path(path) = move_selection_state else {
+            return;
+        };
+        let highlight = |point: usize, color: Color32| {
+            let (row, col) = coords(point);
+            painter.circle_filled(cell_to_rect(row, col).center(), 0.45 * cell_size, color);
+        };
+        for point in path {
+            highlight(
+                *point,
+                Color32::CYAN
+                    .lerp_to_gamma(Color32::BLUE, 0.5)
+                    .gamma_multiply(0.5),
+            );
+        }
+        // The points which continue the selection
+        for mv in self.generate_moves(&mut state.clone()) {
+            let clicks = mv.clicks();
+            if clicks.len() > path.len() && clicks.starts_with(path) {
+                highlight(
+                    clicks[path.len()],
+                    if Some(clicks[path.len()]) == mv.capture {
+                        Color32::RED.gamma_multiply(0.5)
+                    } else {
+                        Color32::CYAN
+                            .lerp_to_gamma(Color32::GREEN, 0.5)
+                            .gamma_multiply(0.5)
+                    },
+                );
+            }
+        }
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        _ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(logic: &Morris, state: &mut BoardState, text: &str) {
+        let mv = logic
+            .generate_moves(state)
+            .into_iter()
+            .find(|mv| mv.to_string() == text)
+            .unwrap_or_else(|| panic!("{text} is not legal"));
+        logic.make_move(state, &mv);
+    }
+
+    #[test]
+    fn board() {
+        assert_eq!(mills().count(), 16);
+        assert_eq!(
+            (0..POINTS).map(|p| neighbours(p).count()).sum::<usize>(),
+            64
+        );
+        assert_eq!(point_name(0), "a7");
+        assert_eq!(point_name(17), "d5");
+    }
+
+    #[test]
+    fn mill_captures() {
+        let logic = Morris;
+        let mut state = logic.initial_state();
+        for text in ["a7", "a1", "d7", "g1"] {
+            play(&logic, &mut state, text);
+        }
+        // Completing the top row must remove a black piece
+        let moves = logic.generate_moves(&mut state);
+        let mills = moves
+            .iter()
+            .filter(|mv| mv.to == 2)
+            .map(|mv| mv.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(mills, vec!["g7xg1", "g7xa1"]);
+        let before = state.clone();
+        play(&logic, &mut state, "g7xg1");
+        assert_eq!(state.count(Player::Second), 1);
+        assert_eq!(state.in_hand, [6, 7]);
+        let mv = Move {
+            from: None,
+            to: 2,
+            capture: Some(4),
+            prev_quiet_plies: 0,
+        };
+        logic.unmake_move(&mut state, &mv);
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn flying_and_losing() {
+        let logic = Morris;
+        let mut state = logic.initial_state();
+        state.in_hand = [0, 0];
+        for point in [0, 1, 4] {
+            state.points[point] = Some(Player::First);
+        }
+        for point in [8, 9, 10, 16] {
+            state.points[point] = Some(Player::Second);
+        }
+        // White has three pieces so may fly anywhere, here to complete the top row
+        play(&logic, &mut state, "g1-g7xc5");
+        assert_eq!(state.count(Player::Second), 3);
+        play(&logic, &mut state, "b6-b4");
+        assert!(!logic.generate_moves(&mut state).is_empty());
+        state.points[9] = None;
+        state.move_num += 1;
+        assert!(logic.generate_moves(&mut state).is_empty());
+        assert!(matches!(logic.score(&mut state), AbsScore::FirstPlayerWin));
+    }
+}
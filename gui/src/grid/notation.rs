@@ -0,0 +1,77 @@
+use crate::{game::Game, grid::GridGame};
+
+// The ways of writing moves which can be chosen between, for games which know more than one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum NotationStyle {
+    // The game's usual short notation e.g. "Nf3"
+    #[default]
+    Standard,
+    // Naming the piece and both of its cells e.g. "Ng1-f3"
+    Long,
+    // Just the cells moved between e.g. "g1f3"
+    Coordinate,
+}
+
+impl NotationStyle {
+    pub const ALL: [NotationStyle; 3] = [
+        NotationStyle::Standard,
+        NotationStyle::Long,
+        NotationStyle::Coordinate,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotationStyle::Standard => "Standard",
+            NotationStyle::Long => "Long Algebraic",
+            NotationStyle::Coordinate => "Coordinate",
+        }
+    }
+}
+
+/*
+Writes moves in one notation style, so that the move list, the AI's lines, the moves typed in and the saved records all agree.
+A style the game doesn't know is written in the game's usual notation instead.
+ */
+pub struct MoveFormatter<'a, G: GridGame> {
+    logic: &'a G,
+    style: NotationStyle,
+}
+
+impl<'a, G: GridGame> MoveFormatter<'a, G> {
+    pub fn new(logic: &'a G, style: NotationStyle) -> Self {
+        let styles = logic.notation_styles();
+        let style = if styles.contains(&style) {
+            style
+        } else {
+            styles.first().copied().unwrap_or_default()
+        };
+        Self { logic, style }
+    }
+
+    pub fn style(&self) -> NotationStyle {
+        self.style
+    }
+
+    // `mv` played from `state`
+    pub fn write(&self, state: &G::State, mv: &G::Move) -> String {
+        self.logic.move_notation(state, mv, self.style)
+    }
+
+    // Each of `moves` in turn, written in the position it is played from
+    pub fn write_line(&self, state: &G::State, moves: &[G::Move]) -> Vec<String> {
+        let mut state = state.clone();
+        moves
+            .iter()
+            .map(|mv| {
+                let notation = self.write(&state, mv);
+                self.logic.make_move(&mut state, mv);
+                notation
+            })
+            .collect()
+    }
+
+    // The moves of `game` replayed from its initial position
+    pub fn write_history(&self, game: &Game<G>) -> Vec<String> {
+        self.write_line(&self.logic.initial_state(), game.move_history())
+    }
+}
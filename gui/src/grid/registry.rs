@@ -0,0 +1,442 @@
+use std::sync::{Arc, LazyLock};
+
+use crate::{
+    game::Game,
+    grid::{
+        GridGame, amazons, breakthrough, checkers,
+        chess::{Chess, openings, positions::StartPosition},
+        connect_four, go, kalah, lines_of_action, morris, reversi, shogi,
+    },
+    links::Link,
+    root::AppState,
+};
+
+/*
+Every game which can be played, known by name so that the menu, the terminal and links can list them and start any of them.
+Each game is a `DynGame`, which makes its `Game` from a `Setup` and hands it to whatever is to be done with it, as a `Launch`.
+The games are of different types, so a `Launch` is generic over them, and `DynGame` has a method for each kind of `Launch` to keep it a trait object.
+ */
+
+// A choice in how a game is set up, offered in the menu when the game is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetupOption {
+    // The board is one of `sizes`, `usual` unless chosen otherwise
+    Size {
+        sizes: &'static [usize],
+        usual: usize,
+    },
+    // Black may not make some moves, as in renju
+    ForbiddenMoves,
+    // The starting position number of Chess960, drawn at random if not chosen
+    Position,
+    // A chess starting position as FEN
+    Fen,
+    // Start from an opening in the book, drawn at random
+    RandomOpening,
+    // The rules are a script, given in full
+    Script,
+}
+
+// How a game is set up, beyond which game it is. Only what the game's `SetupOption`s ask for is looked at.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Setup {
+    pub size: Option<usize>,
+    pub forbidden_moves: bool,
+    pub position: Option<u16>,
+    pub fen: Option<String>,
+    pub random_opening: bool,
+    pub script: Option<String>,
+}
+
+impl Setup {
+    // The setup a link is to, which is a game before a random opening
+    pub fn from_link(link: &Link) -> Self {
+        Self {
+            size: link.option("size").and_then(|size| size.parse().ok()),
+            forbidden_moves: link.option("forbidden") == Some("true"),
+            position: link
+                .option("position")
+                .and_then(|position| position.parse().ok()),
+            fen: link.option("fen").map(str::to_string),
+            random_opening: false,
+            script: None,
+        }
+    }
+
+    // A link to `game` set up like this, before any moves
+    pub fn link(&self, game: &dyn DynGame) -> Link {
+        let mut link = Link::new(game.key());
+        for option in game.options() {
+            match option {
+                SetupOption::Size { .. } => {
+                    if let Some(size) = self.size {
+                        link = link.with_option("size", size);
+                    }
+                }
+                SetupOption::ForbiddenMoves => {
+                    if self.forbidden_moves {
+                        link = link.with_option("forbidden", true);
+                    }
+                }
+                SetupOption::Position => {
+                    if let Some(position) = self.position {
+                        link = link.with_option("position", position);
+                    }
+                }
+                SetupOption::Fen => {
+                    if let Some(fen) = &self.fen {
+                        link = link.with_option("fen", fen.trim());
+                    }
+                }
+                // A script is too long for a link, so a link to a scripted game plays whatever script is in the menu
+                SetupOption::RandomOpening | SetupOption::Script => {}
+            }
+        }
+        link
+    }
+}
+
+// Something to do with a game once it's made, whichever game it is
+pub trait Launch {
+    type Output;
+
+    // `setup` is the one the game was made from, with anything drawn at random filled in
+    fn launch<G: GridGame + Send>(self, game: Game<G>, setup: &Setup) -> Self::Output;
+
+    // Chess can also be played by an external engine, and has an opening book
+    fn launch_chess(self, game: Game<Chess>, setup: &Setup) -> Self::Output
+    where
+        Self: Sized,
+    {
+        self.launch(game, setup)
+    }
+}
+
+pub trait DynGame: Send + Sync {
+    // The name it's known by in links and on the command line
+    fn key(&self) -> &'static str;
+    // The name shown in the menu
+    fn name(&self) -> &'static str;
+    fn options(&self) -> &'static [SetupOption];
+    fn is_chess(&self) -> bool;
+
+    // Start the game from the menu, or say why it can't be set up like this
+    fn start(
+        &self,
+        setup: &Setup,
+        start: crate::menu::Start<'_>,
+    ) -> Result<Box<dyn AppState>, String>;
+
+    // Play the game in the terminal until it's over or given up
+    #[cfg(not(target_arch = "wasm32"))]
+    fn play_in_terminal(
+        &self,
+        setup: &Setup,
+        play: crate::terminal::Play<'_>,
+    ) -> Result<(), String>;
+}
+
+// How a registered game is made from a setup
+trait Make: Send + Sync {
+    fn make<L: Launch>(&self, setup: &Setup, launch: L) -> Result<L::Output, String>;
+}
+
+struct Registered<M> {
+    key: &'static str,
+    name: &'static str,
+    options: &'static [SetupOption],
+    chess: bool,
+    make: M,
+}
+
+impl<M: Make> DynGame for Registered<M> {
+    fn key(&self) -> &'static str {
+        self.key
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn options(&self) -> &'static [SetupOption] {
+        self.options
+    }
+
+    fn is_chess(&self) -> bool {
+        self.chess
+    }
+
+    fn start(
+        &self,
+        setup: &Setup,
+        start: crate::menu::Start<'_>,
+    ) -> Result<Box<dyn AppState>, String> {
+        self.make.make(setup, start)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn play_in_terminal(
+        &self,
+        setup: &Setup,
+        play: crate::terminal::Play<'_>,
+    ) -> Result<(), String> {
+        self.make.make(setup, play)?
+    }
+}
+
+// A game with nothing to set up
+struct Plain<G>(G);
+
+impl<G: GridGame + Send + Sync> Make for Plain<G> {
+    fn make<L: Launch>(&self, setup: &Setup, launch: L) -> Result<L::Output, String> {
+        Ok(launch.launch(Game::new(self.0.clone()), setup))
+    }
+}
+
+// A chess variant, made from the setup by the function
+struct ChessVariant(fn(&Setup) -> Result<Chess, String>);
+
+impl Make for ChessVariant {
+    fn make<L: Launch>(&self, setup: &Setup, launch: L) -> Result<L::Output, String> {
+        let logic = (self.0)(setup)?;
+        // So that the game's link has the position drawn
+        let setup = match &logic {
+            Chess::Fischer960(position) => Setup {
+                position: Some(*position),
+                ..setup.clone()
+            },
+            _ => setup.clone(),
+        };
+        let game = if setup.random_opening && matches!(logic, Chess::Standard) {
+            let seed = chrono::Utc::now().timestamp_subsec_nanos() as usize;
+            openings::random_opening(&logic, &openings::noob_book(), seed)
+                .unwrap_or_else(|| Game::new(logic))
+        } else {
+            Game::new(logic)
+        };
+        Ok(launch.launch_chess(game, &setup))
+    }
+}
+
+// The board sizes are part of the types of these games, so each size is made separately
+struct GomokuSizes;
+
+impl Make for GomokuSizes {
+    fn make<L: Launch>(&self, setup: &Setup, launch: L) -> Result<L::Output, String> {
+        use crate::grid::gomoku::Gomoku;
+        let forbidden_moves = setup.forbidden_moves;
+        Ok(match setup.size {
+            Some(19) => launch.launch(Game::new(Gomoku::<19> { forbidden_moves }), setup),
+            _ => launch.launch(Game::new(Gomoku::<15> { forbidden_moves }), setup),
+        })
+    }
+}
+
+struct HexSizes;
+
+impl Make for HexSizes {
+    fn make<L: Launch>(&self, setup: &Setup, launch: L) -> Result<L::Output, String> {
+        use crate::grid::hex::Hex;
+        Ok(match setup.size {
+            Some(7) => launch.launch(Game::new(Hex::<7>), setup),
+            Some(9) => launch.launch(Game::new(Hex::<9>), setup),
+            _ => launch.launch(Game::new(Hex::<11>), setup),
+        })
+    }
+}
+
+#[cfg(feature = "scripting")]
+struct Scripted;
+
+#[cfg(feature = "scripting")]
+impl Make for Scripted {
+    fn make<L: Launch>(&self, setup: &Setup, launch: L) -> Result<L::Output, String> {
+        let script =
+            crate::grid::script::Script::load(setup.script.as_deref().unwrap_or_default())?;
+        Ok(launch.launch(Game::new(script), setup))
+    }
+}
+
+fn plain<G: GridGame + Send + Sync>(
+    key: &'static str,
+    name: &'static str,
+    logic: G,
+) -> Box<dyn DynGame> {
+    Box::new(Registered {
+        key,
+        name,
+        options: &[],
+        chess: false,
+        make: Plain(logic),
+    })
+}
+
+fn variant(
+    key: &'static str,
+    name: &'static str,
+    options: &'static [SetupOption],
+    make: fn(&Setup) -> Result<Chess, String>,
+) -> Box<dyn DynGame> {
+    Box::new(Registered {
+        key,
+        name,
+        options,
+        chess: true,
+        make: ChessVariant(make),
+    })
+}
+
+static GAMES: LazyLock<Vec<Box<dyn DynGame>>> = LazyLock::new(|| {
+    #[allow(unused_mut)]
+    let mut games = vec![
+        variant("chess", "Chess", &[SetupOption::RandomOpening], |_| {
+            Ok(Chess::Standard)
+        }),
+        variant("berolina", "Berolina Chess", &[], |_| Ok(Chess::Berolina)),
+        variant("grasshopper", "Grasshopper Chess", &[], |_| {
+            Ok(Chess::Grasshopper)
+        }),
+        variant("king-of-the-hill", "King of the Hill", &[], |_| {
+            Ok(Chess::KingOfTheHill)
+        }),
+        variant("three-check", "Three-check", &[], |_| Ok(Chess::ThreeCheck)),
+        variant("horde", "Horde", &[], |_| Ok(Chess::Horde)),
+        variant("chess960", "Chess960", &[SetupOption::Position], |setup| {
+            let position = setup
+                .position
+                .filter(|position| *position < 960)
+                .unwrap_or_else(|| (chrono::Utc::now().timestamp_subsec_nanos() % 960) as u16);
+            Ok(Chess::Fischer960(position))
+        }),
+        variant("gardner", "Gardner Minichess (5x5)", &[], |_| {
+            Ok(Chess::Gardner)
+        }),
+        variant(
+            "custom-chess",
+            "Chess from a Custom Position",
+            &[SetupOption::Fen],
+            |setup| {
+                let fen = setup.fen.as_deref().unwrap_or_default();
+                let position = StartPosition::from_fen(fen)?;
+                Ok(Chess::Custom(Arc::new(position)))
+            },
+        ),
+        plain("connect-four", "Connect Four", connect_four::ConnectFour),
+        plain("checkers", "Checkers", checkers::Checkers),
+        plain("reversi", "Reversi", reversi::Reversi),
+        Box::new(Registered {
+            key: "gomoku",
+            name: "Gomoku",
+            options: &[
+                SetupOption::Size {
+                    sizes: &[15, 19],
+                    usual: 15,
+                },
+                SetupOption::ForbiddenMoves,
+            ],
+            chess: false,
+            make: GomokuSizes,
+        }),
+        Box::new(Registered {
+            key: "hex",
+            name: "Hex",
+            options: &[SetupOption::Size {
+                sizes: &[7, 9, 11],
+                usual: 11,
+            }],
+            chess: false,
+            make: HexSizes,
+        }),
+        plain("morris", "Nine Men's Morris", morris::Morris),
+        plain("breakthrough", "Breakthrough", breakthrough::Breakthrough),
+        plain("kalah", "Kalah", kalah::Kalah),
+        plain("shogi", "Shogi", shogi::Shogi),
+        plain("go", "Go (9x9)", go::Go),
+        plain(
+            "lines-of-action",
+            "Lines of Action",
+            lines_of_action::LinesOfAction,
+        ),
+        plain("amazons", "Amazons", amazons::Amazons),
+    ];
+    #[cfg(feature = "scripting")]
+    games.push(Box::new(Registered {
+        key: "script",
+        name: "Game from a Script",
+        options: &[SetupOption::Script],
+        chess: false,
+        make: Scripted,
+    }));
+    games
+});
+
+// Every game, in the order the menu lists them
+pub fn games() -> &'static [Box<dyn DynGame>] {
+    &GAMES
+}
+
+pub fn find(key: &str) -> Option<&'static dyn DynGame> {
+    games()
+        .iter()
+        .find(|game| game.key() == key)
+        .map(|game| &**game)
+}
+
+// The link to a chess game before any moves, for chess started other than through the registry, such as from a PGN
+pub fn chess_link(logic: &Chess) -> Link {
+    let (key, setup) = match logic {
+        Chess::Standard => ("chess", Setup::default()),
+        Chess::Berolina => ("berolina", Setup::default()),
+        Chess::Grasshopper => ("grasshopper", Setup::default()),
+        Chess::KingOfTheHill => ("king-of-the-hill", Setup::default()),
+        Chess::ThreeCheck => ("three-check", Setup::default()),
+        Chess::Horde => ("horde", Setup::default()),
+        Chess::Fischer960(position) => (
+            "chess960",
+            Setup {
+                position: Some(*position),
+                ..Setup::default()
+            },
+        ),
+        Chess::Gardner => ("gardner", Setup::default()),
+        Chess::Custom(position) => (
+            "custom-chess",
+            Setup {
+                fen: Some(position.fen()),
+                ..Setup::default()
+            },
+        ),
+    };
+    setup.link(find(key).expect("Every chess variant is registered"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry() {
+        for (idx, game) in games().iter().enumerate() {
+            assert_eq!(
+                find(game.key()).map(|found| found.name()),
+                Some(game.name())
+            );
+            assert!(games()[..idx].iter().all(|other| other.key() != game.key()));
+        }
+        assert!(find("chess").is_some_and(|game| game.is_chess()));
+        assert!(find("hex").is_some_and(|game| !game.is_chess()));
+        assert!(find("tic-tac-toe").is_none());
+
+        let setup = Setup {
+            size: Some(19),
+            forbidden_moves: true,
+            ..Setup::default()
+        };
+        let link = setup.link(find("gomoku").unwrap());
+        assert_eq!(link.to_fragment(), "game=gomoku&size=19&forbidden=true");
+        assert_eq!(Setup::from_link(&link), setup);
+
+        let link = chess_link(&Chess::Fischer960(7));
+        assert_eq!(link.to_fragment(), "game=chess960&position=7");
+    }
+}
@@ -0,0 +1,408 @@
+use std::fmt::Display;
+
+use egui::{Color32, Painter, Rect};
+
+use crate::{
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{GridGame, MoveSelectionAction, Piece},
+};
+
+const SIZE: usize = 8;
+const DIRECTIONS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+fn bit(row: usize, col: usize) -> u64 {
+    1 << (SIZE * row + col)
+}
+
+fn player_idx(player: Player) -> usize {
+    match player {
+        Player::First => 0,
+        Player::Second => 1,
+    }
+}
+
+// Black moves first
+#[derive(Debug, Clone)]
+pub struct Reversi;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState {
+    discs: [u64; 2],
+    move_num: usize,
+}
+
+impl BoardState {
+    fn get(&self, row: usize, col: usize) -> Option<Player> {
+        if self.discs[0] & bit(row, col) != 0 {
+            Some(Player::First)
+        } else if self.discs[1] & bit(row, col) != 0 {
+            Some(Player::Second)
+        } else {
+            None
+        }
+    }
+
+    fn count(&self, player: Player) -> u32 {
+        self.discs[player_idx(player)].count_ones()
+    }
+}
+
+impl State<Reversi> for BoardState {
+    fn ident(self) -> BoardStateIdent {
+        BoardStateIdent {
+            discs: self.discs,
+            move_num: self.move_num,
+        }
+    }
+
+    // Positions can't repeat since every placement adds a disc
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+#[derive(Debug, Clone)]
+pub struct BoardStateIdent {
+    discs: [u64; 2],
+    move_num: usize,
+}
+
+impl PartialEq for BoardStateIdent {
+    fn eq(&self, other: &Self) -> bool {
+        self.discs == other.discs && self.move_num % 2 == other.move_num % 2
+    }
+}
+
+impl Eq for BoardStateIdent {}
+
+impl NoAlloc for BoardStateIdent {}
+
+impl StateIdent<Reversi> for BoardStateIdent {
+    fn hash64(&self) -> u64 {
+        (self.discs[0].wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ self.discs[1]
+                .wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+                .rotate_left(29))
+        .wrapping_add(self.move_num as u64 % 2)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Move {
+    Place {
+        row: usize,
+        col: usize,
+        // The opponent's discs which are turned over, kept so the move can be unmade
+        flipped: u64,
+    },
+    // Only allowed when there is nowhere to place a disc
+    Pass,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Move::Place { row, col, .. } => {
+                write!(f, "{}{}", (b'a' + *col as u8) as char, row + 1)
+            }
+            Move::Pass => write!(f, "pass"),
+        }
+    }
+}
+
+impl Reversi {
+    // The discs turned over by `player` placing at (row, col)
+    fn flips(&self, state: &BoardState, player: Player, row: usize, col: usize) -> u64 {
+        let own = state.discs[player_idx(player)];
+        let other = state.discs[player_idx(player.flip())];
+        if (own | other) & bit(row, col) != 0 {
+            return 0;
+        }
+        let mut flipped = 0;
+        for (drow, dcol) in DIRECTIONS {
+            let mut line = 0;
+            let (mut r, mut c) = (row as isize + drow, col as isize + dcol);
+            while (0..SIZE as isize).contains(&r)
+                && (0..SIZE as isize).contains(&c)
+                && other & bit(r as usize, c as usize) != 0
+            {
+                line |= bit(r as usize, c as usize);
+                r += drow;
+                c += dcol;
+            }
+            if line != 0
+                && (0..SIZE as isize).contains(&r)
+                && (0..SIZE as isize).contains(&c)
+                && own & bit(r as usize, c as usize) != 0
+            {
+                flipped |= line;
+            }
+        }
+        flipped
+    }
+
+    fn placements(&self, state: &BoardState, player: Player) -> Vec<Move> {
+        let mut moves = vec![];
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                let flipped = self.flips(state, player, row, col);
+                if flipped != 0 {
+                    moves.push(Move::Place { row, col, flipped });
+                }
+            }
+        }
+        moves
+    }
+}
+
+impl GameLogic for Reversi {
+    type State = BoardState;
+    type StateIdent = BoardStateIdent;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        BoardState {
+            discs: [bit(3, 4) | bit(4, 3), bit(3, 3) | bit(4, 4)],
+            move_num: 0,
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        if state.move_num.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        let turn = self.turn(state);
+        let moves = self.placements(state, turn);
+        if !moves.is_empty() {
+            moves
+        } else if self.placements(state, turn.flip()).is_empty() {
+            // Neither player can place so the game is over
+            vec![]
+        } else {
+            vec![Move::Pass]
+        }
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        let first = state.count(Player::First) as i64;
+        let second = state.count(Player::Second) as i64;
+        if self.generate_moves(state).is_empty() {
+            return match first.cmp(&second) {
+                std::cmp::Ordering::Greater => AbsScore::FirstPlayerWin,
+                std::cmp::Ordering::Equal => AbsScore::Draw,
+                std::cmp::Ordering::Less => AbsScore::SecondPlayerWin,
+            };
+        }
+
+        // Corners can never be turned over, and having more moves available is usually better than having more discs
+        const CORNERS: u64 = 1 | (1 << 7) | (1 << 56) | (1 << 63);
+        let corners = (state.discs[0] & CORNERS).count_ones() as i64
+            - (state.discs[1] & CORNERS).count_ones() as i64;
+        let mobility = self.placements(state, Player::First).len() as i64
+            - self.placements(state, Player::Second).len() as i64;
+        AbsScore::Heuristic(first - second + 10 * mobility + 100 * corners)
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        if let Move::Place { row, col, flipped } = mv {
+            let idx = player_idx(self.turn(state));
+            debug_assert_eq!(*flipped, self.flips(state, self.turn(state), *row, *col));
+            state.discs[idx] |= bit(*row, *col) | flipped;
+            state.discs[1 - idx] &= !flipped;
+        }
+        state.move_num += 1;
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        state.move_num -= 1;
+        if let Move::Place { row, col, flipped } = mv {
+            let idx = player_idx(self.turn(state));
+            state.discs[idx] &= !(bit(*row, *col) | flipped);
+            state.discs[1 - idx] |= flipped;
+        }
+    }
+}
+
+// Discs are placed with a single click
+#[derive(Debug, Clone)]
+pub enum MoveSelectionState {
+    Initial,
+}
+
+impl GridGame for Reversi {
+    const ROWS: usize = SIZE;
+    const COLS: usize = SIZE;
+
+    fn piece(&self, state: &Self::State, row: usize, col: usize) -> Piece {
+        match state.get(row, col) {
+            Some(Player::First) => Piece::BlackDisc,
+            Some(Player::Second) => Piece::WhiteDisc,
+            None => Piece::Empty,
+        }
+    }
+
+    type MoveSelectionState = MoveSelectionState;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        MoveSelectionState::Initial
+    }
+
+    fn player_name(&self, player: Player) -> &'static str {
+        match player {
+            Player::First => "Black",
+            Player::Second => "White",
+        }
+    }
+
+    fn row_label(&self, row: usize) -> Option<String> {
+        Some((row + 1).to_string())
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        if let Move::Place { row, col, flipped } = mv {
+            let rect = cell_to_rect(row, col).shrink(0.06 * cell_size);
+            painter.rect_filled(rect, 0.2 * cell_size, Color32::ORANGE.gamma_multiply(0.5));
+            for r in 0..SIZE {
+                for c in 0..SIZE {
+                    if flipped & bit(r, c) != 0 {
+                        let rect = cell_to_rect(r, c).shrink(0.06 * cell_size);
+                        painter.rect_filled(
+                            rect,
+                            0.2 * cell_size,
+                            Color32::ORANGE.gamma_multiply(0.25),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn update_move_selection(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        _move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        match action {
+            MoveSelectionAction::Reset => None,
+            MoveSelectionAction::ClickSquare { row, col } => {
+                self.generate_moves(&mut state.clone()).into_iter().find(
+                    |mv| matches!(mv, Move::Place { row: r, col: c, .. } if *r == row && *c == col),
+                )
+            }
+        }
+    }
+
+    fn draw_move_selection_on_grid(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        // Mark the squares where a disc can be placed
+        for mv in self.generate_moves(&mut state.clone()) {
+            if let Move::Place { row, col, .. } = mv {
+                painter.circle_filled(
+                    cell_to_rect(row, col).center(),
+                    0.1 * cell_size,
+                    Color32::GRAY.gamma_multiply(0.5),
+                );
+            }
+        }
+    }
+
+    fn show_state_info(&self, state: &Self::State, ui: &mut egui::Ui) {
+        ui.label(format!(
+            "Discs: Black {}, White {}",
+            state.count(Player::First),
+            state.count(Player::Second)
+        ));
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        if self.generate_moves(&mut state.clone()) != vec![Move::Pass] {
+            return None;
+        }
+        egui::Window::new("No Moves")
+            .default_pos(egui::pos2(200.0, 100.0))
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label("There is nowhere to place a disc.");
+                ui.button("Pass").clicked().then_some(Move::Pass)
+            })
+            .and_then(|response| response.inner.flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    #[test]
+    fn flips_and_unmake() {
+        let logic = Reversi;
+        let mut state = logic.initial_state();
+        assert_eq!(logic.generate_moves(&mut state).len(), 4);
+        let before = state.clone();
+        let mv = logic
+            .generate_moves(&mut state)
+            .into_iter()
+            .find(|mv| mv.to_string() == "d3")
+            .unwrap();
+        logic.make_move(&mut state, &mv);
+        assert_eq!(state.count(Player::First), 4);
+        assert_eq!(state.count(Player::Second), 1);
+        logic.unmake_move(&mut state, &mv);
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn game_ends_when_nobody_can_move() {
+        // The shortest possible game, in which black wipes out white
+        let mut game = Game::new(Reversi);
+        for text in ["e6", "f4", "e3", "f6", "g5", "d6", "e7", "f5", "c5"] {
+            let mv = game
+                .logic()
+                .generate_moves(&mut game.state().clone())
+                .into_iter()
+                .find(|mv| mv.to_string() == text)
+                .unwrap();
+            game.make_move(mv);
+        }
+        assert!(game.is_finished());
+        assert!(matches!(game.score(), AbsScore::FirstPlayerWin));
+    }
+}
@@ -0,0 +1,614 @@
+use std::{fmt::Display, sync::Arc};
+
+use egui::{Align2, Color32, FontId, Painter, Rect, Stroke};
+use rhai::{AST, Array, Dynamic, Engine, FuncArgs, Map, Scope};
+
+use crate::{
+    ai::strength::mix64,
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{GridGame, MoveSelectionAction, Piece},
+};
+
+/*
+Games whose rules are written as Rhai scripts, for trying out a game without writing it in Rust.
+
+A script defines these functions:
+  info()                  a map with the game's `name`, the board's `rows` and `cols`,
+                          the `players`' names and the `pieces` drawn in the cells
+  start()                 the starting board, an array of rows, each an array of numbers
+  moves(board, turn)      the moves of the player whose turn it is, each an array of the [row, col] cells clicked to make it
+  play(board, turn, move) the board after the move
+  result(board, turn)     () while the game goes on, else the winner or 0 for a draw
+  score(board)            optional, how good the board looks for the first player
+
+Players are 1 and 2, and a cell holding n shows the nth of the `pieces`, while 0 is empty.
+Rows count from the top, as in the board's arrays.
+ */
+
+pub const EXAMPLES: [(&str, &str); 2] = [
+    ("Tic-tac-toe", include_str!("tic_tac_toe.rhai")),
+    ("Hexapawn", include_str!("hexapawn.rhai")),
+];
+
+// How much a script may do in one call, so that a script which never returns stops with an error instead of hanging the app
+const MAX_OPERATIONS: u64 = 10_000_000;
+const MAX_SIZE: usize = 26;
+
+struct Compiled {
+    engine: Engine,
+    ast: AST,
+    name: String,
+    rows: usize,
+    cols: usize,
+    players: [&'static str; 2],
+    pieces: Vec<String>,
+    start: Vec<i64>,
+    has_score: bool,
+}
+
+#[derive(Clone)]
+pub struct Script(Arc<Compiled>);
+
+// The engine and the compiled script have nothing worth printing
+impl std::fmt::Debug for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Script({:?})", self.0.name)
+    }
+}
+
+fn player_num(player: Player) -> i64 {
+    match player {
+        Player::First => 1,
+        Player::Second => 2,
+    }
+}
+
+impl Script {
+    pub fn load(source: &str) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        // Rhai's own limits are lower in debug builds, which would turn away scripts a release build runs
+        engine.set_max_expr_depths(64, 32);
+        let ast = engine
+            .compile(source)
+            .map_err(|err| format!("The script doesn't parse: {err}"))?;
+        let defines = |name: &str| ast.iter_functions().any(|f| f.name == name);
+        for name in ["info", "start", "moves", "play", "result"] {
+            if !defines(name) {
+                return Err(format!("The script has no `{name}` function"));
+            }
+        }
+        let has_score = defines("score");
+
+        let info: Map = engine
+            .call_fn(&mut Scope::new(), &ast, "info", ())
+            .map_err(|err| format!("`info` failed: {err}"))?;
+        let size = |key: &str| {
+            info.get(key)
+                .and_then(|value| value.as_int().ok())
+                .filter(|size| (1..=MAX_SIZE as i64).contains(size))
+                .map(|size| size as usize)
+                .ok_or_else(|| format!("`info` needs `{key}` from 1 to {MAX_SIZE}"))
+        };
+        let strings = |key: &str| -> Result<Vec<String>, String> {
+            match info.get(key) {
+                None => Ok(vec![]),
+                Some(value) => value
+                    .clone()
+                    .into_array()
+                    .map(|items| items.iter().map(|item| item.to_string()).collect())
+                    .map_err(|_| format!("`{key}` in `info` should be an array")),
+            }
+        };
+        let players = match strings("players")?.as_slice() {
+            [] => ["First", "Second"],
+            // Player names are handed out as `&'static str`, so each loaded script keeps its two
+            [first, second] => [
+                &*Box::leak(first.clone().into_boxed_str()),
+                &*Box::leak(second.clone().into_boxed_str()),
+            ],
+            _ => return Err("`players` in `info` should name two players".to_string()),
+        };
+
+        let mut script = Self(Arc::new(Compiled {
+            engine,
+            name: info
+                .get("name")
+                .map_or("Script".to_string(), |name| name.to_string()),
+            rows: size("rows")?,
+            cols: size("cols")?,
+            players,
+            pieces: strings("pieces")?,
+            ast,
+            start: vec![],
+            has_score,
+        }));
+        let start = script.call("start", ())?;
+        let start = script.board(start)?;
+        Arc::get_mut(&mut script.0).unwrap().start = start;
+
+        // Try out the first position so that mistakes show up now rather than once the game has started
+        let mut state = script.initial_state();
+        script.result(&state)?;
+        script.moves(&mut state)?;
+        Ok(script)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    fn call(&self, name: &str, args: impl FuncArgs) -> Result<Dynamic, String> {
+        self.0
+            .engine
+            .call_fn(&mut Scope::new(), &self.0.ast, name, args)
+            .map_err(|err| format!("`{name}` failed: {err}"))
+    }
+
+    fn board(&self, value: Dynamic) -> Result<Vec<i64>, String> {
+        let error = || {
+            format!(
+                "A board should be {} arrays of {} numbers",
+                self.0.rows, self.0.cols
+            )
+        };
+        let rows = value.into_array().map_err(|_| error())?;
+        if rows.len() != self.0.rows {
+            return Err(error());
+        }
+        let mut board = Vec::with_capacity(self.0.rows * self.0.cols);
+        for row in rows {
+            let row = row.into_array().map_err(|_| error())?;
+            if row.len() != self.0.cols {
+                return Err(error());
+            }
+            for cell in row {
+                board.push(cell.as_int().map_err(|_| error())?);
+            }
+        }
+        Ok(board)
+    }
+
+    fn board_value(&self, board: &[i64]) -> Array {
+        board
+            .chunks(self.0.cols)
+            .map(|row| Dynamic::from_array(row.iter().map(|cell| Dynamic::from(*cell)).collect()))
+            .collect()
+    }
+
+    fn to_move(&self, value: Dynamic) -> Result<Move, String> {
+        let error = || "A move should be an array of [row, col] cells on the board".to_string();
+        let mut cells = vec![];
+        for cell in value.into_array().map_err(|_| error())? {
+            let cell = cell.into_array().map_err(|_| error())?;
+            let [row, col] = cell.as_slice() else {
+                return Err(error());
+            };
+            let (row, col) = (
+                row.as_int().map_err(|_| error())?,
+                col.as_int().map_err(|_| error())?,
+            );
+            if !(0..self.0.rows as i64).contains(&row) || !(0..self.0.cols as i64).contains(&col) {
+                return Err(error());
+            }
+            cells.push((row as usize, col as usize));
+        }
+        if cells.is_empty() {
+            return Err(error());
+        }
+        Ok(Move { cells })
+    }
+
+    fn move_value(mv: &Move) -> Array {
+        mv.cells
+            .iter()
+            .map(|(row, col)| Dynamic::from_array(vec![(*row as i64).into(), (*col as i64).into()]))
+            .collect()
+    }
+
+    // The winner, or `Some(None)` for a draw
+    fn result(&self, state: &ScriptState) -> Result<Option<Option<Player>>, String> {
+        let result = self.call(
+            "result",
+            (self.board_value(&state.board), player_num(self.turn(state))),
+        )?;
+        if result.is_unit() {
+            return Ok(None);
+        }
+        match result.as_int() {
+            Ok(0) => Ok(Some(None)),
+            Ok(1) => Ok(Some(Some(Player::First))),
+            Ok(2) => Ok(Some(Some(Player::Second))),
+            _ => Err("`result` should give (), 0, 1 or 2".to_string()),
+        }
+    }
+
+    fn moves(&self, state: &mut ScriptState) -> Result<Vec<Move>, String> {
+        let moves = self.call(
+            "moves",
+            (self.board_value(&state.board), player_num(self.turn(state))),
+        )?;
+        let moves = moves
+            .into_array()
+            .map_err(|_| "`moves` should give an array of moves".to_string())?;
+        moves.into_iter().map(|mv| self.to_move(mv)).collect()
+    }
+
+    fn piece_text(&self, state: &ScriptState, row: usize, col: usize) -> Option<&str> {
+        let cell = state.board[row * self.0.cols + col];
+        usize::try_from(cell - 1)
+            .ok()
+            .and_then(|idx| self.0.pieces.get(idx))
+            .map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptState {
+    board: Vec<i64>,
+    move_num: usize,
+    // The boards before each move, as a script can't be asked to take a move back
+    previous: Vec<Vec<i64>>,
+}
+
+impl State<Script> for ScriptState {
+    fn ident(self) -> ScriptStateIdent {
+        let hash = self
+            .board
+            .iter()
+            .fold(self.move_num as u64 % 2, |hash, cell| {
+                mix64(hash ^ *cell as u64)
+            });
+        ScriptStateIdent { hash }
+    }
+
+    // Scripts have no say over repetitions
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+// Only a hash of the board, as the board itself can be any size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptStateIdent {
+    hash: u64,
+}
+
+impl NoAlloc for ScriptStateIdent {}
+
+impl StateIdent<Script> for ScriptStateIdent {
+    fn hash64(&self) -> u64 {
+        self.hash
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Move {
+    cells: Vec<(usize, usize)>,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (row, col)) in self.cells.iter().enumerate() {
+            if i > 0 {
+                write!(f, "-")?;
+            }
+            write!(f, "{}{}", (b'a' + *col as u8) as char, row + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/*
+A script which fails part way through a game is reported in the log and the game stops there as a draw,
+since there's no sensible way to carry on.
+ */
+impl GameLogic for Script {
+    type State = ScriptState;
+    type StateIdent = ScriptStateIdent;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        ScriptState {
+            board: self.0.start.clone(),
+            move_num: 0,
+            previous: vec![],
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        if state.move_num.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        match self.result(state) {
+            Ok(None) => {}
+            Ok(Some(_)) => return vec![],
+            Err(err) => {
+                log::error!("{err}");
+                return vec![];
+            }
+        }
+        self.moves(state).unwrap_or_else(|err| {
+            log::error!("{err}");
+            vec![]
+        })
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        match self.result(state) {
+            Ok(Some(Some(Player::First))) => return AbsScore::FirstPlayerWin,
+            Ok(Some(Some(Player::Second))) => return AbsScore::SecondPlayerWin,
+            Ok(Some(None)) | Err(_) => return AbsScore::Draw,
+            Ok(None) => {}
+        }
+        // A game the script hasn't ended but which has no moves left is drawn
+        if self.moves(state).is_ok_and(|moves| moves.is_empty()) {
+            return AbsScore::Draw;
+        }
+        if !self.0.has_score {
+            return AbsScore::Heuristic(0);
+        }
+        let score = self
+            .call("score", (self.board_value(&state.board),))
+            .and_then(|score| {
+                score
+                    .as_int()
+                    .map_err(|_| "`score` should give a number".to_string())
+            });
+        match score {
+            Ok(score) => AbsScore::Heuristic(score),
+            Err(err) => {
+                log::error!("{err}");
+                AbsScore::Heuristic(0)
+            }
+        }
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        let board = self
+            .call(
+                "play",
+                (
+                    self.board_value(&state.board),
+                    player_num(self.turn(state)),
+                    Self::move_value(mv),
+                ),
+            )
+            .and_then(|board| self.board(board));
+        let board = board.unwrap_or_else(|err| {
+            log::error!("{err}");
+            state.board.clone()
+        });
+        state
+            .previous
+            .push(std::mem::replace(&mut state.board, board));
+        state.move_num += 1;
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, _mv: &Self::Move) {
+        state.board = state.previous.pop().unwrap();
+        state.move_num -= 1;
+    }
+}
+
+impl GridGame for Script {
+    // The board is whatever size the script says
+    const ROWS: usize = 8;
+    const COLS: usize = 8;
+
+    fn rows(&self) -> usize {
+        self.0.rows
+    }
+
+    fn cols(&self) -> usize {
+        self.0.cols
+    }
+
+    // Scripts draw their pieces as text rather than with the app's pieces
+    fn piece(&self, _state: &Self::State, _row: usize, _col: usize) -> Piece {
+        Piece::Empty
+    }
+
+    fn cell_text(&self, state: &Self::State, row: usize, col: usize) -> Option<String> {
+        self.piece_text(state, row, col).map(str::to_string)
+    }
+
+    // The cells clicked so far
+    type MoveSelectionState = Vec<(usize, usize)>;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        vec![]
+    }
+
+    fn player_name(&self, player: Player) -> &'static str {
+        match player {
+            Player::First => self.0.players[0],
+            Player::Second => self.0.players[1],
+        }
+    }
+
+    fn row_label(&self, row: usize) -> Option<String> {
+        Some((row + 1).to_string())
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        for (row, col) in mv.cells {
+            let rect = cell_to_rect(row, col).shrink(0.06 * cell_size);
+            painter.rect_filled(rect, 0.2 * cell_size, Color32::ORANGE.gamma_multiply(0.5));
+        }
+    }
+
+    // Cells are clicked in the order the script lists them, and a move is made once the clicks match one exactly
+    fn update_move_selection(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        let MoveSelectionAction::ClickSquare { row, col } = action else {
+            move_selection_state.clear();
+            return None;
+        };
+        let moves = self.generate_moves(&mut state.clone());
+        for mut cells in [move_selection_state.clone(), vec![]] {
+            cells.push((row, col));
+            if let Some(mv) = moves.iter().find(|mv| mv.cells == cells) {
+                move_selection_state.clear();
+                return Some(mv.clone());
+            }
+            if moves.iter().any(|mv| mv.cells.starts_with(&cells)) {
+                *move_selection_state = cells;
+                return None;
+            }
+        }
+        move_selection_state.clear();
+        None
+    }
+
+    fn draw_move_selection_on_grid(
+        &self,
+        _turn: Player,
+        state: &Self::State,
+        move_selection_state: &Self::MoveSelectionState,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        let font = FontId::proportional(0.6 * cell_size);
+        for row in 0..self.0.rows {
+            for col in 0..self.0.cols {
+                if let Some(text) = self.piece_text(state, row, col) {
+                    painter.text(
+                        cell_to_rect(row, col).center(),
+                        Align2::CENTER_CENTER,
+                        text,
+                        font.clone(),
+                        Color32::BLACK,
+                    );
+                }
+            }
+        }
+        if move_selection_state.is_empty() {
+            return;
+        }
+        for (row, col) in move_selection_state {
+            let rect = cell_to_rect(*row, *col).shrink(0.06 * cell_size);
+            painter.rect_filled(
+                rect,
+                0.2 * cell_size,
+                Color32::CYAN
+                    .lerp_to_gamma(Color32::BLUE, 0.5)
+                    .gamma_multiply(0.5),
+            );
+        }
+        // The cells which carry on the move
+        let stroke = Stroke::new(0.05 * cell_size, Color32::GREEN.gamma_multiply(0.7));
+        for mv in self.generate_moves(&mut state.clone()) {
+            if mv.cells.len() > move_selection_state.len()
+                && mv.cells.starts_with(move_selection_state)
+            {
+                let (row, col) = mv.cells[move_selection_state.len()];
+                painter.circle_stroke(cell_to_rect(row, col).center(), 0.3 * cell_size, stroke);
+            }
+        }
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        _move_selection_state: &Self::MoveSelectionState,
+        _ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    fn cell(row: usize, col: usize) -> Move {
+        Move {
+            cells: vec![(row, col)],
+        }
+    }
+
+    #[test]
+    fn tic_tac_toe() {
+        let script = Script::load(EXAMPLES[0].1).unwrap();
+        assert_eq!(script.name(), "Tic-tac-toe");
+        assert_eq!((script.rows(), script.cols()), (3, 3));
+        let mut game = Game::new(script);
+        assert_eq!(
+            game.logic().generate_moves(&mut game.state().clone()).len(),
+            9
+        );
+        for (row, col) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            game.make_move(cell(row, col));
+            assert!(!game.is_finished());
+        }
+        assert_eq!(
+            game.logic().cell_text(game.state(), 1, 1),
+            Some("O".to_string())
+        );
+        game.make_move(cell(0, 2));
+        assert!(game.is_finished());
+        assert!(matches!(game.score(), AbsScore::FirstPlayerWin));
+        assert_eq!(cell(0, 2).to_string(), "c1");
+    }
+
+    #[test]
+    fn hexapawn() {
+        let script = Script::load(EXAMPLES[1].1).unwrap();
+        let mut state = script.initial_state();
+        let moves = script.generate_moves(&mut state);
+        assert_eq!(moves.len(), 3);
+        assert_eq!(moves[0].to_string(), "a3-a2");
+
+        // Clicking a pawn then where it goes makes the move
+        let mut selection = script.initial_move_selection();
+        let click = |row, col| MoveSelectionAction::ClickSquare { row, col };
+        assert_eq!(
+            script.update_move_selection(Player::First, &state, click(2, 0), &mut selection),
+            None
+        );
+        assert_eq!(selection, vec![(2, 0)]);
+        let mv = script.update_move_selection(Player::First, &state, click(1, 0), &mut selection);
+        assert_eq!(mv, Some(moves[0].clone()));
+        assert!(selection.is_empty());
+
+        script.make_move(&mut state, &moves[0]);
+        assert_eq!(script.turn(&state), Player::Second);
+        script.unmake_move(&mut state, &moves[0]);
+        assert_eq!(state, script.initial_state());
+    }
+
+    #[test]
+    fn bad_scripts() {
+        assert!(Script::load("fn info() {").is_err());
+        assert!(Script::load("fn info() { #{ rows: 3, cols: 3 } }").is_err());
+        let wrong_board = EXAMPLES[0]
+            .1
+            .replace("[[0, 0, 0], [0, 0, 0], [0, 0, 0]]", "[[0, 0, 0]]");
+        assert!(Script::load(&wrong_board).is_err());
+        let endless = EXAMPLES[0].1.replace("let moves = [];", "loop {}");
+        let err = Script::load(&endless).unwrap_err();
+        assert!(err.contains("Too many operations"), "{err}");
+    }
+}
@@ -0,0 +1,1059 @@
+use std::fmt::Display;
+
+use egui::{Align2, Color32, FontId, Painter, Rect};
+
+use crate::{
+    game::{AbsScore, GameLogic, NoAlloc, Player, State, StateIdent},
+    grid::{GridGame, MoveSelectionAction, Piece},
+};
+
+const SIZE: usize = 9;
+// How many rows at the far side of the board make up the promotion zone
+const ZONE: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    King,
+    Rook,
+    Bishop,
+    Gold,
+    Silver,
+    Knight,
+    Lance,
+    Pawn,
+}
+
+use Kind::*;
+
+// The kinds of piece which can be held in hand, in the order they are drawn beside the board
+const HAND_KINDS: [Kind; 7] = [Rook, Bishop, Gold, Silver, Knight, Lance, Pawn];
+
+fn hand_idx(kind: Kind) -> usize {
+    HAND_KINDS.iter().position(|k| *k == kind).unwrap()
+}
+
+fn player_idx(player: Player) -> usize {
+    match player {
+        Player::First => 0,
+        Player::Second => 1,
+    }
+}
+
+// The direction `player`'s pieces move forwards in
+fn forward(player: Player) -> isize {
+    match player {
+        Player::First => -1,
+        Player::Second => 1,
+    }
+}
+
+// How many rows separate `row` from the far side of the board for `player`
+fn rows_to_go(player: Player, row: usize) -> usize {
+    match player {
+        Player::First => row,
+        Player::Second => SIZE - 1 - row,
+    }
+}
+
+// Moves are given as (forwards, sideways) for the piece's owner
+type Offsets = &'static [(isize, isize)];
+
+const KING_STEPS: Offsets = &[
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, -1),
+    (0, 1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+const GOLD_STEPS: Offsets = &[(1, -1), (1, 0), (1, 1), (0, -1), (0, 1), (-1, 0)];
+const SILVER_STEPS: Offsets = &[(1, -1), (1, 0), (1, 1), (-1, -1), (-1, 1)];
+const KNIGHT_STEPS: Offsets = &[(2, -1), (2, 1)];
+const FORWARD: Offsets = &[(1, 0)];
+const ORTHOGONAL: Offsets = &[(1, 0), (-1, 0), (0, 1), (0, -1)];
+const DIAGONAL: Offsets = &[(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShogiPiece {
+    kind: Kind,
+    owner: Player,
+    promoted: bool,
+}
+
+impl ShogiPiece {
+    // The single steps and the sliding directions of the piece
+    fn pattern(self) -> (Offsets, Offsets) {
+        match (self.kind, self.promoted) {
+            (King, _) => (KING_STEPS, &[]),
+            (Rook, false) => (&[], ORTHOGONAL),
+            (Rook, true) => (DIAGONAL, ORTHOGONAL),
+            (Bishop, false) => (&[], DIAGONAL),
+            (Bishop, true) => (ORTHOGONAL, DIAGONAL),
+            (Gold, _) | (_, true) => (GOLD_STEPS, &[]),
+            (Silver, false) => (SILVER_STEPS, &[]),
+            (Knight, false) => (KNIGHT_STEPS, &[]),
+            (Lance, false) => (&[], FORWARD),
+            (Pawn, false) => (FORWARD, &[]),
+        }
+    }
+
+    fn can_promote(self) -> bool {
+        !self.promoted && !matches!(self.kind, King | Gold)
+    }
+
+    // The piece would have no moves if it stayed unpromoted on this row
+    fn must_promote(self, row: usize) -> bool {
+        !self.promoted
+            && match self.kind {
+                Pawn | Lance => rows_to_go(self.owner, row) == 0,
+                Knight => rows_to_go(self.owner, row) < 2,
+                _ => false,
+            }
+    }
+
+    fn value(self) -> i64 {
+        match (self.kind, self.promoted) {
+            (King, _) => 0,
+            (Rook, false) => 1000,
+            (Rook, true) => 1250,
+            (Bishop, false) => 800,
+            (Bishop, true) => 1050,
+            (Gold, _) => 550,
+            (Silver, false) => 500,
+            (Knight, false) => 350,
+            (Lance, false) => 300,
+            (Pawn, false) => 100,
+            // Promoted minor pieces all move like a gold
+            (_, true) => 550,
+        }
+    }
+
+    fn letter(self) -> &'static str {
+        match self.kind {
+            King => "K",
+            Rook => "R",
+            Bishop => "B",
+            Gold => "G",
+            Silver => "S",
+            Knight => "N",
+            Lance => "L",
+            Pawn => "P",
+        }
+    }
+
+    fn icon(self) -> Piece {
+        match (self.owner, self.kind, self.promoted) {
+            (Player::First, King, _) => Piece::SenteKing,
+            (Player::First, Rook, false) => Piece::SenteRook,
+            (Player::First, Rook, true) => Piece::SenteDragon,
+            (Player::First, Bishop, false) => Piece::SenteBishop,
+            (Player::First, Bishop, true) => Piece::SenteHorse,
+            (Player::First, Gold, _) => Piece::SenteGold,
+            (Player::First, Silver, false) => Piece::SenteSilver,
+            (Player::First, Silver, true) => Piece::SentePromotedSilver,
+            (Player::First, Knight, false) => Piece::SenteKnight,
+            (Player::First, Knight, true) => Piece::SentePromotedKnight,
+            (Player::First, Lance, false) => Piece::SenteLance,
+            (Player::First, Lance, true) => Piece::SentePromotedLance,
+            (Player::First, Pawn, false) => Piece::SentePawn,
+            (Player::First, Pawn, true) => Piece::SenteTokin,
+            (Player::Second, King, _) => Piece::GoteKing,
+            (Player::Second, Rook, false) => Piece::GoteRook,
+            (Player::Second, Rook, true) => Piece::GoteDragon,
+            (Player::Second, Bishop, false) => Piece::GoteBishop,
+            (Player::Second, Bishop, true) => Piece::GoteHorse,
+            (Player::Second, Gold, _) => Piece::GoteGold,
+            (Player::Second, Silver, false) => Piece::GoteSilver,
+            (Player::Second, Silver, true) => Piece::GotePromotedSilver,
+            (Player::Second, Knight, false) => Piece::GoteKnight,
+            (Player::Second, Knight, true) => Piece::GotePromotedKnight,
+            (Player::Second, Lance, false) => Piece::GoteLance,
+            (Player::Second, Lance, true) => Piece::GotePromotedLance,
+            (Player::Second, Pawn, false) => Piece::GotePawn,
+            (Player::Second, Pawn, true) => Piece::GoteTokin,
+        }
+    }
+}
+
+type Pos = (usize, usize);
+
+fn offset((row, col): Pos, owner: Player, (fwd, side): (isize, isize)) -> Option<Pos> {
+    let row = row.checked_add_signed(fwd * forward(owner))?;
+    let col = col.checked_add_signed(side)?;
+    (row < SIZE && col < SIZE).then_some((row, col))
+}
+
+/*
+Shogi.
+Sente is the first player and starts at the bottom of the board. Captured pieces change sides and can be dropped
+back onto any empty square instead of making a move, with the usual restrictions on where pawns may be dropped.
+Pieces may promote when they move into, out of or within the far three rows. Running out of legal moves loses.
+ */
+#[derive(Debug, Clone)]
+pub struct Shogi;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardState {
+    board: [[Option<ShogiPiece>; SIZE]; SIZE],
+    hands: [[u8; HAND_KINDS.len()]; 2],
+    kings: [Pos; 2],
+    move_num: usize,
+}
+
+impl BoardState {
+    fn get(&self, (row, col): Pos) -> Option<ShogiPiece> {
+        self.board[row][col]
+    }
+
+    fn set(&mut self, (row, col): Pos, piece: Option<ShogiPiece>) {
+        self.board[row][col] = piece;
+    }
+
+    // Can the piece on `from` move to `to`, ignoring what is on `to`?
+    fn reaches(&self, piece: ShogiPiece, from: Pos, to: Pos) -> bool {
+        let (steps, slides) = piece.pattern();
+        if steps
+            .iter()
+            .any(|step| offset(from, piece.owner, *step) == Some(to))
+        {
+            return true;
+        }
+        for slide in slides {
+            let mut pos = from;
+            while let Some(next) = offset(pos, piece.owner, *slide) {
+                if next == to {
+                    return true;
+                }
+                if self.get(next).is_some() {
+                    break;
+                }
+                pos = next;
+            }
+        }
+        false
+    }
+
+    fn is_attacked(&self, pos: Pos, by: Player) -> bool {
+        (0..SIZE).any(|row| {
+            (0..SIZE).any(|col| {
+                self.board[row][col]
+                    .is_some_and(|piece| piece.owner == by && self.reaches(piece, (row, col), pos))
+            })
+        })
+    }
+
+    fn in_check(&self, player: Player) -> bool {
+        self.is_attacked(self.kings[player_idx(player)], player.flip())
+    }
+}
+
+impl State<Shogi> for BoardState {
+    fn ident(self) -> BoardStateIdent {
+        BoardStateIdent {
+            board: self.board,
+            hands: self.hands,
+            move_num: self.move_num,
+        }
+    }
+
+    fn set_ignore_repetitions(&mut self, _ignore_repetitions: bool) {}
+}
+
+#[derive(Debug, Clone)]
+pub struct BoardStateIdent {
+    board: [[Option<ShogiPiece>; SIZE]; SIZE],
+    hands: [[u8; HAND_KINDS.len()]; 2],
+    move_num: usize,
+}
+
+impl PartialEq for BoardStateIdent {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+            && self.hands == other.hands
+            && self.move_num % 2 == other.move_num % 2
+    }
+}
+
+impl Eq for BoardStateIdent {}
+
+impl NoAlloc for BoardStateIdent {}
+
+impl StateIdent<Shogi> for BoardStateIdent {
+    fn hash64(&self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325u64 ^ (self.move_num as u64 % 2);
+        for square in self.board.iter().flatten() {
+            let value = match square {
+                None => 0,
+                Some(piece) => {
+                    1 + 2 * piece.kind as u64
+                        + piece.promoted as u64
+                        + 16 * player_idx(piece.owner) as u64
+                }
+            };
+            hash = (hash ^ value).wrapping_mul(0x0100_0000_01b3);
+        }
+        for count in self.hands.iter().flatten() {
+            hash = (hash ^ *count as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Move {
+    Step {
+        from: Pos,
+        to: Pos,
+        piece: ShogiPiece,
+        captured: Option<ShogiPiece>,
+        promote: bool,
+    },
+    Drop {
+        kind: Kind,
+        to: Pos,
+    },
+}
+
+impl Move {
+    fn to(&self) -> Pos {
+        match self {
+            Move::Step { to, .. } | Move::Drop { to, .. } => *to,
+        }
+    }
+
+    fn captured_value(&self) -> i64 {
+        match self {
+            Move::Step {
+                captured: Some(captured),
+                ..
+            } => captured.value(),
+            _ => 0,
+        }
+    }
+}
+
+// Files are numbered from the right and ranks lettered from the top, as seen by sente
+fn square_name((row, col): Pos) -> String {
+    format!("{}{}", SIZE - col, (b'a' + row as u8) as char)
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Move::Step {
+                from,
+                to,
+                piece,
+                captured,
+                promote,
+            } => write!(
+                f,
+                "{}{}{}{}{}{}",
+                if piece.promoted { "+" } else { "" },
+                piece.letter(),
+                square_name(*from),
+                if captured.is_some() { "x" } else { "-" },
+                square_name(*to),
+                if *promote { "+" } else { "" }
+            ),
+            Move::Drop { kind, to } => write!(
+                f,
+                "{}*{}",
+                ShogiPiece {
+                    kind: *kind,
+                    owner: Player::First,
+                    promoted: false
+                }
+                .letter(),
+                square_name(*to)
+            ),
+        }
+    }
+}
+
+impl Shogi {
+    fn pseudo_legal_moves(&self, state: &BoardState, turn: Player) -> Vec<Move> {
+        let mut moves = vec![];
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                let from = (row, col);
+                let Some(piece) = state.get(from) else {
+                    continue;
+                };
+                if piece.owner != turn {
+                    continue;
+                }
+                let (steps, slides) = piece.pattern();
+                let mut targets = steps
+                    .iter()
+                    .filter_map(|step| offset(from, turn, *step))
+                    .collect::<Vec<_>>();
+                for slide in slides {
+                    let mut pos = from;
+                    while let Some(next) = offset(pos, turn, *slide) {
+                        targets.push(next);
+                        if state.get(next).is_some() {
+                            break;
+                        }
+                        pos = next;
+                    }
+                }
+                for to in targets {
+                    let captured = state.get(to);
+                    if captured.is_some_and(|captured| captured.owner == turn) {
+                        continue;
+                    }
+                    let in_zone = rows_to_go(turn, from.0) < ZONE || rows_to_go(turn, to.0) < ZONE;
+                    let promotions: &[bool] = if piece.must_promote(to.0) {
+                        &[true]
+                    } else if in_zone && piece.can_promote() {
+                        &[true, false]
+                    } else {
+                        &[false]
+                    };
+                    for promote in promotions {
+                        moves.push(Move::Step {
+                            from,
+                            to,
+                            piece,
+                            captured,
+                            promote: *promote,
+                        });
+                    }
+                }
+            }
+        }
+
+        for (idx, kind) in HAND_KINDS.into_iter().enumerate() {
+            if state.hands[player_idx(turn)][idx] == 0 {
+                continue;
+            }
+            let dropped = ShogiPiece {
+                kind,
+                owner: turn,
+                promoted: false,
+            };
+            for row in 0..SIZE {
+                if dropped.must_promote(row) {
+                    continue;
+                }
+                for col in 0..SIZE {
+                    // A second unpromoted pawn may not be dropped onto a file
+                    if kind == Pawn && (0..SIZE).any(|r| state.board[r][col] == Some(dropped)) {
+                        continue;
+                    }
+                    if state.board[row][col].is_none() {
+                        moves.push(Move::Drop {
+                            kind,
+                            to: (row, col),
+                        });
+                    }
+                }
+            }
+        }
+        moves
+    }
+
+    fn legal_moves(&self, state: &mut BoardState, check_pawn_drop_mate: bool) -> Vec<Move> {
+        let turn = self.turn(state);
+        let mut moves = self.pseudo_legal_moves(state, turn);
+        moves.retain(|mv| {
+            self.make_move(state, mv);
+            let mut legal = !state.in_check(turn);
+            // A dropped pawn may not deliver checkmate
+            if legal
+                && check_pawn_drop_mate
+                && let Move::Drop { kind: Pawn, to } = mv
+                && offset(*to, turn, (1, 0)) == Some(state.kings[player_idx(turn.flip())])
+            {
+                legal = !self.legal_moves(state, false).is_empty();
+            }
+            self.unmake_move(state, mv);
+            legal
+        });
+        moves
+    }
+}
+
+impl GameLogic for Shogi {
+    type State = BoardState;
+    type StateIdent = BoardStateIdent;
+    type Move = Move;
+    type HeuristicScore = i64;
+
+    fn initial_state(&self) -> Self::State {
+        let mut board = [[None; SIZE]; SIZE];
+        let back_rank = [
+            Lance, Knight, Silver, Gold, King, Gold, Silver, Knight, Lance,
+        ];
+        for (owner, back, second, pawns) in [(Player::First, 8, 7, 6), (Player::Second, 0, 1, 2)] {
+            let piece = |kind| {
+                Some(ShogiPiece {
+                    kind,
+                    owner,
+                    promoted: false,
+                })
+            };
+            for col in 0..SIZE {
+                board[back][col] = piece(back_rank[col]);
+                board[pawns][col] = piece(Pawn);
+            }
+            // The rook is on sente's right and the bishop on their left
+            let (rook_col, bishop_col) = match owner {
+                Player::First => (7, 1),
+                Player::Second => (1, 7),
+            };
+            board[second][rook_col] = piece(Rook);
+            board[second][bishop_col] = piece(Bishop);
+        }
+        BoardState {
+            board,
+            hands: [[0; HAND_KINDS.len()]; 2],
+            kings: [(8, 4), (0, 4)],
+            move_num: 0,
+        }
+    }
+
+    fn turn(&self, state: &Self::State) -> Player {
+        if state.move_num.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    fn generate_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        let mut moves = self.legal_moves(state, true);
+        // Look at the biggest captures first and drops last since there are so many of them
+        moves.sort_by_key(|mv| {
+            (
+                std::cmp::Reverse(mv.captured_value()),
+                matches!(mv, Move::Drop { .. }),
+            )
+        });
+        moves
+    }
+
+    fn in_check(&self, state: &Self::State) -> bool {
+        state.in_check(self.turn(state))
+    }
+
+    fn generate_quiescence_moves(&self, state: &mut Self::State) -> Vec<Self::Move> {
+        self.generate_moves(state)
+            .into_iter()
+            .filter(|mv| mv.captured_value() > 0)
+            .collect()
+    }
+
+    fn end_reason(&self, state: &mut Self::State) -> Option<&'static str> {
+        if !self.generate_moves(state).is_empty() {
+            None
+        } else if self.in_check(state) {
+            Some("Checkmate")
+        } else {
+            Some("No legal moves")
+        }
+    }
+
+    fn score(&self, state: &mut Self::State) -> AbsScore<Self::HeuristicScore> {
+        // The player to move loses when they have no legal moves
+        if self.generate_moves(state).is_empty() {
+            return match self.turn(state) {
+                Player::First => AbsScore::SecondPlayerWin,
+                Player::Second => AbsScore::FirstPlayerWin,
+            };
+        }
+
+        let mut total = 0;
+        for piece in state.board.iter().flatten().flatten() {
+            match piece.owner {
+                Player::First => total += piece.value(),
+                Player::Second => total -= piece.value(),
+            }
+        }
+        // Pieces in hand are a little more flexible than those on the board
+        for (idx, kind) in HAND_KINDS.into_iter().enumerate() {
+            let value = ShogiPiece {
+                kind,
+                owner: Player::First,
+                promoted: false,
+            }
+            .value()
+                * 11
+                / 10;
+            total += value * state.hands[0][idx] as i64;
+            total -= value * state.hands[1][idx] as i64;
+        }
+        AbsScore::Heuristic(total)
+    }
+
+    fn is_capture(&self, _state: &Self::State, mv: &Self::Move) -> bool {
+        matches!(
+            mv,
+            Move::Step {
+                captured: Some(_),
+                ..
+            }
+        )
+    }
+
+    fn make_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        let turn = self.turn(state);
+        match mv {
+            Move::Step {
+                from,
+                to,
+                piece,
+                captured,
+                promote,
+            } => {
+                debug_assert_eq!(state.get(*from), Some(*piece));
+                debug_assert_eq!(state.get(*to), *captured);
+                if let Some(captured) = captured {
+                    state.hands[player_idx(turn)][hand_idx(captured.kind)] += 1;
+                }
+                state.set(*from, None);
+                state.set(
+                    *to,
+                    Some(ShogiPiece {
+                        promoted: piece.promoted || *promote,
+                        ..*piece
+                    }),
+                );
+                if piece.kind == King {
+                    state.kings[player_idx(turn)] = *to;
+                }
+            }
+            Move::Drop { kind, to } => {
+                debug_assert!(state.get(*to).is_none());
+                state.hands[player_idx(turn)][hand_idx(*kind)] -= 1;
+                state.set(
+                    *to,
+                    Some(ShogiPiece {
+                        kind: *kind,
+                        owner: turn,
+                        promoted: false,
+                    }),
+                );
+            }
+        }
+        state.move_num += 1;
+    }
+
+    fn unmake_move(&self, state: &mut Self::State, mv: &Self::Move) {
+        state.move_num -= 1;
+        let turn = self.turn(state);
+        match mv {
+            Move::Step {
+                from,
+                to,
+                piece,
+                captured,
+                ..
+            } => {
+                state.set(*from, Some(*piece));
+                state.set(*to, *captured);
+                if let Some(captured) = captured {
+                    state.hands[player_idx(turn)][hand_idx(captured.kind)] -= 1;
+                }
+                if piece.kind == King {
+                    state.kings[player_idx(turn)] = *from;
+                }
+            }
+            Move::Drop { kind, to } => {
+                state.set(*to, None);
+                state.hands[player_idx(turn)][hand_idx(*kind)] += 1;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MoveSelectionState {
+    Initial,
+    PieceSelected { from: Pos },
+    DropSelected { kind: Kind },
+    Promote { from: Pos, to: Pos },
+}
+
+/*
+The board is drawn between two extra columns holding the pieces in each player's hand.
+Gote's hand is on the left reading down and sente's is on the right reading up.
+ */
+enum Cell {
+    Board(Pos),
+    Hand(Player, Kind),
+    Blank,
+}
+
+fn cell(row: usize, col: usize) -> Cell {
+    match col {
+        0 if row < HAND_KINDS.len() => Cell::Hand(Player::Second, HAND_KINDS[row]),
+        c if c == SIZE + 1 && SIZE - 1 - row < HAND_KINDS.len() => {
+            Cell::Hand(Player::First, HAND_KINDS[SIZE - 1 - row])
+        }
+        c if (1..=SIZE).contains(&c) => Cell::Board((row, c - 1)),
+        _ => Cell::Blank,
+    }
+}
+
+impl GridGame for Shogi {
+    const ROWS: usize = SIZE;
+    const COLS: usize = SIZE + 2;
+
+    fn piece(&self, state: &Self::State, row: usize, col: usize) -> Piece {
+        match cell(row, col) {
+            Cell::Board(pos) => state.get(pos).map_or(Piece::Empty, |piece| piece.icon()),
+            Cell::Hand(owner, kind) if state.hands[player_idx(owner)][hand_idx(kind)] > 0 => {
+                ShogiPiece {
+                    kind,
+                    owner,
+                    promoted: false,
+                }
+                .icon()
+            }
+            Cell::Hand(..) | Cell::Blank => Piece::Empty,
+        }
+    }
+
+    type MoveSelectionState = MoveSelectionState;
+
+    fn initial_move_selection(&self) -> Self::MoveSelectionState {
+        MoveSelectionState::Initial
+    }
+
+    fn player_name(&self, player: Player) -> &'static str {
+        match player {
+            Player::First => "Sente",
+            Player::Second => "Gote",
+        }
+    }
+
+    // Set the hands apart from the board
+    fn highlight_square(&self, row: usize, col: usize) -> Option<Color32> {
+        match cell(row, col) {
+            Cell::Board(_) => None,
+            Cell::Hand(..) | Cell::Blank => Some(Color32::from_gray(60).gamma_multiply(0.6)),
+        }
+    }
+
+    // Files are numbered from the right and ranks lettered from the top, leaving out the hands either side
+    fn col_label(&self, col: usize) -> Option<String> {
+        (1..=SIZE)
+            .contains(&col)
+            .then(|| (SIZE + 1 - col).to_string())
+    }
+
+    fn row_label(&self, row: usize) -> Option<String> {
+        Some(((b'a' + row as u8) as char).to_string())
+    }
+
+    // The board sits one column in from the left, past the second player's hand
+    fn checked_cell(&self, state: &Self::State) -> Option<(usize, usize)> {
+        let (row, col) = state.kings[player_idx(self.turn(state))];
+        self.in_check(state).then_some((row, col + 1))
+    }
+
+    fn show_move(
+        &self,
+        _turn: Player,
+        _state: &Self::State,
+        mv: Self::Move,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        let highlight = |(row, col): Pos, strength: f32| {
+            let rect = cell_to_rect(row, col + 1).shrink(0.06 * cell_size);
+            painter.rect_filled(
+                rect,
+                0.2 * cell_size,
+                Color32::ORANGE.gamma_multiply(strength),
+            );
+        };
+        if let Move::Step { from, .. } = mv {
+            highlight(from, 0.25);
+        }
+        highlight(mv.to(), 0.5);
+    }
+
+    fn update_move_selection(
+        &self,
+        turn: Player,
+        state: &Self::State,
+        action: MoveSelectionAction,
+        move_selection_state: &mut Self::MoveSelectionState,
+    ) -> Option<Self::Move> {
+        let MoveSelectionAction::ClickSquare { row, col } = action else {
+            *move_selection_state = MoveSelectionState::Initial;
+            return None;
+        };
+        let moves = self.generate_moves(&mut state.clone());
+        match cell(row, col) {
+            Cell::Board(to) => {
+                let candidates = moves
+                    .iter()
+                    .filter(|mv| {
+                        mv.to() == to
+                            && match (&*move_selection_state, mv) {
+                                (
+                                    MoveSelectionState::PieceSelected { from: selected },
+                                    Move::Step { from, .. },
+                                ) => selected == from,
+                                (
+                                    MoveSelectionState::DropSelected { kind: selected },
+                                    Move::Drop { kind, .. },
+                                ) => selected == kind,
+                                _ => false,
+                            }
+                    })
+                    .collect::<Vec<_>>();
+                match candidates.as_slice() {
+                    [mv] => {
+                        *move_selection_state = MoveSelectionState::Initial;
+                        return Some((*mv).clone());
+                    }
+                    [Move::Step { from, .. }, ..] => {
+                        *move_selection_state = MoveSelectionState::Promote { from: *from, to };
+                        return None;
+                    }
+                    _ => {}
+                }
+                *move_selection_state = if state.get(to).is_some_and(|piece| piece.owner == turn) {
+                    MoveSelectionState::PieceSelected { from: to }
+                } else {
+                    MoveSelectionState::Initial
+                };
+            }
+            Cell::Hand(owner, kind)
+                if owner == turn && state.hands[player_idx(owner)][hand_idx(kind)] > 0 =>
+            {
+                *move_selection_state = MoveSelectionState::DropSelected { kind };
+            }
+            Cell::Hand(..) | Cell::Blank => *move_selection_state = MoveSelectionState::Initial,
+        }
+        None
+    }
+
+    fn draw_move_selection_on_grid(
+        &self,
+        turn: Player,
+        state: &Self::State,
+        move_selection_state: &Self::MoveSelectionState,
+        cell_size: f32,
+        cell_to_rect: impl Fn(usize, usize) -> Rect,
+        painter: &Painter,
+    ) {
+        // The number of each kind of piece in hand
+        for row in 0..SIZE {
+            for col in [0, SIZE + 1] {
+                if let Cell::Hand(owner, kind) = cell(row, col) {
+                    let count = state.hands[player_idx(owner)][hand_idx(kind)];
+                    if count > 1 {
+                        painter.text(
+                            cell_to_rect(row, col).right_bottom(),
+                            Align2::RIGHT_BOTTOM,
+                            count,
+                            FontId::proportional(0.3 * cell_size),
+                            Color32::WHITE,
+                        );
+                    }
+                }
+            }
+        }
+
+        let highlight = |(row, col): (usize, usize), color: Color32| {
+            let rect = cell_to_rect(row, col).shrink(0.06 * cell_size);
+            painter.rect_filled(rect, 0.2 * cell_size, color);
+        };
+        let selected_color = Color32::CYAN
+            .lerp_to_gamma(Color32::BLUE, 0.5)
+            .gamma_multiply(0.5);
+        let moves = self.generate_moves(&mut state.clone());
+        let targets = match move_selection_state {
+            MoveSelectionState::Initial => return,
+            MoveSelectionState::PieceSelected { from } => {
+                highlight((from.0, from.1 + 1), selected_color);
+                moves
+                    .iter()
+                    .filter(|mv| matches!(mv, Move::Step { from: f, .. } if f == from))
+                    .collect::<Vec<_>>()
+            }
+            MoveSelectionState::DropSelected { kind } => {
+                let row = match turn {
+                    Player::First => SIZE - 1 - hand_idx(*kind),
+                    Player::Second => hand_idx(*kind),
+                };
+                let col = match turn {
+                    Player::First => SIZE + 1,
+                    Player::Second => 0,
+                };
+                highlight((row, col), selected_color);
+                moves
+                    .iter()
+                    .filter(|mv| matches!(mv, Move::Drop { kind: k, .. } if k == kind))
+                    .collect()
+            }
+            MoveSelectionState::Promote { from, to } => {
+                highlight((from.0, from.1 + 1), selected_color);
+                highlight((to.0, to.1 + 1), selected_color);
+                return;
+            }
+        };
+        for mv in targets {
+            let (row, col) = mv.to();
+            highlight(
+                (row, col + 1),
+                if mv.captured_value() > 0 {
+                    Color32::RED.gamma_multiply(0.5)
+                } else {
+                    Color32::CYAN
+                        .lerp_to_gamma(Color32::GREEN, 0.5)
+                        .gamma_multiply(0.5)
+                },
+            );
+        }
+    }
+
+    fn update_move_selection_ui(
+        &self,
+        turn: Player,
+        state: &Self::State,
+        move_selection_state: &Self::MoveSelectionState,
+        ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+    ) -> Option<Self::Move> {
+        let MoveSelectionState::Promote {
+            from: selected_from,
+            to: selected_to,
+        } = move_selection_state
+        else {
+            return None;
+        };
+        debug_assert_eq!(turn, self.turn(state));
+        egui::Window::new("Promote Piece")
+            .default_pos(egui::pos2(200.0, 100.0))
+            .show(ctx, |ui| {
+                for mv in self.generate_moves(&mut state.clone()) {
+                    if let Move::Step {
+                        from, to, promote, ..
+                    } = mv
+                        && from == *selected_from
+                        && to == *selected_to
+                        && ui
+                            .button(if promote { "Promote" } else { "Don't Promote" })
+                            .clicked()
+                    {
+                        return Some(mv);
+                    }
+                }
+                None
+            })
+            .and_then(|response| response.inner.flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(logic: &Shogi, state: &mut BoardState, text: &str) -> Move {
+        let mv = logic
+            .generate_moves(state)
+            .into_iter()
+            .find(|mv| mv.to_string() == text)
+            .unwrap_or_else(|| panic!("{text} is not legal"));
+        logic.make_move(state, &mv);
+        mv
+    }
+
+    #[test]
+    fn initial_moves() {
+        let logic = Shogi;
+        let mut state = logic.initial_state();
+        assert_eq!(logic.generate_moves(&mut state).len(), 30);
+    }
+
+    #[test]
+    fn captures_promotions_and_drops() {
+        let logic = Shogi;
+        let mut state = logic.initial_state();
+        let start = state.clone();
+        let mut played = vec![];
+        // Open the bishops' diagonal and trade them
+        for text in ["P7g-7f", "P3c-3d"] {
+            played.push(play(&logic, &mut state, text));
+        }
+        let moves = logic
+            .generate_moves(&mut state)
+            .into_iter()
+            .map(|mv| mv.to_string())
+            .filter(|mv| mv.starts_with("B8hx2b"))
+            .collect::<Vec<_>>();
+        assert_eq!(moves, vec!["B8hx2b+", "B8hx2b"]);
+        played.push(play(&logic, &mut state, "B8hx2b+"));
+        played.push(play(&logic, &mut state, "S3ax2b"));
+        assert_eq!(state.hands, [[0, 1, 0, 0, 0, 0, 0], [0, 1, 0, 0, 0, 0, 0]]);
+
+        // Sente can drop the bishop on any of the empty squares
+        let drops = logic
+            .generate_moves(&mut state)
+            .into_iter()
+            .filter(|mv| matches!(mv, Move::Drop { .. }))
+            .count();
+        assert_eq!(drops, 81 - 38);
+        played.push(play(&logic, &mut state, "B*5e"));
+
+        for mv in played.iter().rev() {
+            logic.unmake_move(&mut state, mv);
+        }
+        assert_eq!(state, start);
+    }
+
+    #[test]
+    fn pawn_drops() {
+        let logic = Shogi;
+        let piece = |kind, owner| {
+            Some(ShogiPiece {
+                kind,
+                owner,
+                promoted: false,
+            })
+        };
+        // Gote's king on 1a is boxed in by its own knight and sente's golds
+        let mut state = BoardState {
+            board: [[None; SIZE]; SIZE],
+            hands: [[0, 0, 0, 0, 0, 0, 2], [0; HAND_KINDS.len()]],
+            kings: [(8, 4), (0, 8)],
+            move_num: 0,
+        };
+        state.set((8, 4), piece(King, Player::First));
+        state.set((0, 8), piece(King, Player::Second));
+        state.set((0, 7), piece(Knight, Player::Second));
+        state.set((2, 8), piece(Gold, Player::First));
+        state.set((2, 7), piece(Gold, Player::First));
+        state.set((6, 0), piece(Pawn, Player::First));
+        let drops = logic
+            .generate_moves(&mut state)
+            .into_iter()
+            .filter_map(|mv| match mv {
+                Move::Drop { to, .. } => Some(to),
+                Move::Step { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        // Not onto the last rank, not onto the file with the other pawn, and not mating with a drop on 1b
+        assert!(drops.iter().all(|(row, _)| *row != 0));
+        assert!(drops.iter().all(|(_, col)| *col != 0));
+        assert!(!drops.contains(&(1, 8)));
+        assert_eq!(drops.len(), 8 * 8 - 3 - 1);
+
+        // Mating by moving a pawn is fine
+        state.set((6, 0), None);
+        state.set((2, 8), piece(Pawn, Player::First));
+        state.set((3, 8), piece(Gold, Player::First));
+        play(&logic, &mut state, "P1c-1b");
+        assert!(matches!(logic.score(&mut state), AbsScore::FirstPlayerWin));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::grid::{Piece, ui::load_pieces};
+use egui::{Color32, TextureHandle};
+use std::collections::HashMap;
+
+// How boards, pieces and the controls look, chosen in the settings and kept between sessions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Appearance {
+    pub board: BoardTheme,
+    // Used instead of the board's own colours for the light and the dark squares
+    pub light_squares: Option<Color32>,
+    pub dark_squares: Option<Color32>,
+    pub pieces: PieceSet,
+    pub layout: LayoutMode,
+}
+
+impl Appearance {
+    pub fn palette(&self, visuals: &egui::Visuals) -> BoardPalette {
+        let palette = self.board.palette(visuals);
+        BoardPalette {
+            light: self.light_squares.unwrap_or(palette.light),
+            dark: self.dark_squares.unwrap_or(palette.dark),
+            ..palette
+        }
+    }
+}
+
+// The colours a board is drawn in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoardPalette {
+    pub light: Color32,
+    pub dark: Color32,
+    // Over the king of a player in check
+    pub check: Color32,
+    // The marks and arrows drawn with the right mouse button
+    pub annotation: Color32,
+    // The dots on the cells a click would play a move on
+    pub destination: Color32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum BoardTheme {
+    // Following the colours of the light or dark egui theme
+    #[default]
+    Auto,
+    Wood,
+    Green,
+    Blue,
+    Grey,
+}
+
+impl BoardTheme {
+    pub const ALL: [BoardTheme; 5] = [
+        BoardTheme::Auto,
+        BoardTheme::Wood,
+        BoardTheme::Green,
+        BoardTheme::Blue,
+        BoardTheme::Grey,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BoardTheme::Auto => "Match Theme",
+            BoardTheme::Wood => "Wood",
+            BoardTheme::Green => "Green",
+            BoardTheme::Blue => "Blue",
+            BoardTheme::Grey => "Grey",
+        }
+    }
+
+    pub fn palette(self, visuals: &egui::Visuals) -> BoardPalette {
+        let (light, dark) = match self {
+            BoardTheme::Auto => return Self::themed_palette(visuals),
+            BoardTheme::Wood => (
+                Color32::from_rgb(240, 217, 181),
+                Color32::from_rgb(181, 136, 99),
+            ),
+            BoardTheme::Green => (
+                Color32::from_rgb(238, 238, 210),
+                Color32::from_rgb(118, 150, 86),
+            ),
+            BoardTheme::Blue => (
+                Color32::from_rgb(222, 227, 230),
+                Color32::from_rgb(140, 162, 173),
+            ),
+            BoardTheme::Grey => (
+                Color32::from_rgb(200, 200, 200),
+                Color32::from_rgb(128, 128, 128),
+            ),
+        };
+        BoardPalette {
+            light,
+            dark,
+            check: Color32::from_rgba_unmultiplied(255, 0, 0, 100),
+            annotation: Color32::from_rgba_unmultiplied(21, 120, 27, 180),
+            destination: Color32::from_black_alpha(90),
+        }
+    }
+
+    // Squares in the panel and accent colours, with marks in the colours the theme already picks things out with
+    fn themed_palette(visuals: &egui::Visuals) -> BoardPalette {
+        let text = visuals.text_color();
+        let (light, dark) = if visuals.dark_mode {
+            (
+                visuals.panel_fill.lerp_to_gamma(text, 0.55),
+                visuals
+                    .selection
+                    .bg_fill
+                    .lerp_to_gamma(visuals.panel_fill, 0.4),
+            )
+        } else {
+            (
+                visuals.extreme_bg_color.lerp_to_gamma(text, 0.1),
+                visuals.selection.bg_fill.lerp_to_gamma(text, 0.2),
+            )
+        };
+        BoardPalette {
+            light,
+            dark,
+            check: visuals.error_fg_color.gamma_multiply(0.4),
+            annotation: visuals.selection.stroke.color.gamma_multiply(0.7),
+            destination: text.gamma_multiply(0.35),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum PieceSet {
+    // The pictures in `icons`
+    #[default]
+    Icons,
+    // Each piece's letter on a disc of its side's colour, as written in the game's notation
+    Letters,
+}
+
+impl PieceSet {
+    pub const ALL: [PieceSet; 2] = [PieceSet::Icons, PieceSet::Letters];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PieceSet::Icons => "Icons",
+            PieceSet::Letters => "Letters",
+        }
+    }
+}
+
+// The textures of the pieces in a set, loaded again when a different set is chosen
+pub struct PieceTextures {
+    set: PieceSet,
+    textures: HashMap<Piece, TextureHandle>,
+}
+
+impl PieceTextures {
+    pub fn new(ctx: &egui::Context, set: PieceSet) -> Self {
+        Self {
+            set,
+            textures: load_pieces(ctx, set),
+        }
+    }
+
+    pub fn choose(&mut self, ctx: &egui::Context, set: PieceSet) {
+        if set != self.set {
+            *self = Self::new(ctx, set);
+        }
+    }
+
+    pub fn textures(&self) -> &HashMap<Piece, TextureHandle> {
+        &self.textures
+    }
+}
+
+// Whether the controls go beside the board or in a sheet below it with buttons big enough for fingers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum LayoutMode {
+    // Compact when the screen is taller than it is wide, as phones usually are
+    #[default]
+    Auto,
+    Wide,
+    Compact,
+}
+
+impl LayoutMode {
+    pub const ALL: [LayoutMode; 3] = [LayoutMode::Auto, LayoutMode::Wide, LayoutMode::Compact];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LayoutMode::Auto => "Automatic",
+            LayoutMode::Wide => "Wide",
+            LayoutMode::Compact => "Compact",
+        }
+    }
+
+    pub fn is_compact(self, ctx: &egui::Context) -> bool {
+        match self {
+            LayoutMode::Auto => {
+                let screen = ctx.content_rect();
+                screen.height() > screen.width()
+            }
+            LayoutMode::Wide => false,
+            LayoutMode::Compact => true,
+        }
+    }
+
+    // The spacing of every widget, roomier in the compact layout so that each is easy to tap
+    pub fn spacing(self, ctx: &egui::Context) -> egui::style::Spacing {
+        if self.is_compact(ctx) {
+            egui::style::Spacing {
+                item_spacing: egui::vec2(12.0, 10.0),
+                button_padding: egui::vec2(12.0, 8.0),
+                interact_size: egui::vec2(48.0, 40.0),
+                icon_width: 24.0,
+                icon_width_inner: 14.0,
+                ..Default::default()
+            }
+        } else {
+            egui::style::Spacing::default()
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{
+    ai::tournament::{Tournament, format_elo},
+    grid::{
+        GridGame,
+        theme::{Appearance, PieceSet, PieceTextures},
+        ui::{Blindfold, BoardLayout, paint_board},
+    },
+    root::AppState,
+};
+
+// Watching a tournament between AIs, one match at a time
+pub struct State<G: GridGame> {
+    tournament: Tournament<G>,
+    paused: bool,
+    pieces: PieceTextures,
+}
+
+impl<G: GridGame> State<G> {
+    pub fn new(ctx: &egui::Context, tournament: Tournament<G>) -> Self {
+        Self {
+            tournament,
+            paused: false,
+            pieces: PieceTextures::new(ctx, PieceSet::default()),
+        }
+    }
+}
+
+impl<G: GridGame> AppState for State<G> {
+    fn update(
+        &mut self,
+        ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+        appearance: &Appearance,
+    ) -> Option<Box<dyn AppState>> {
+        self.pieces.choose(ctx, appearance.pieces);
+        let mut change_state: Option<Box<dyn AppState>> = None;
+        let mut toggle_pause = false;
+
+        if !self.paused {
+            self.tournament.step(chrono::TimeDelta::milliseconds(10));
+        }
+
+        egui::SidePanel::left("left panel").show(ctx, |ui| {
+            if ui.button("Menu").clicked() {
+                change_state = Some(Box::new(crate::menu::State::default()));
+            }
+
+            ui.separator();
+
+            let entrants = self.tournament.entrants();
+            let pairings = self.tournament.pairings();
+            ui.heading("Tournament");
+            if self.tournament.is_finished() {
+                ui.label(format!("Finished after {} matches", pairings.len()));
+            } else if let Some(contest) = self.tournament.current_match() {
+                let (a, b) = pairings[self.tournament.match_idx()];
+                ui.label(format!(
+                    "Match {} of {}: {} v {}",
+                    self.tournament.match_idx() + 1,
+                    pairings.len(),
+                    entrants[a].name,
+                    entrants[b].name
+                ));
+                ui.label(format!(
+                    "Game {} of {}, move {}",
+                    contest.results().len() + 1,
+                    contest.games(),
+                    contest.game().num_moves() + 1
+                ));
+                let progress = contest.move_progress().min(1.0);
+                ui.add(egui::ProgressBar::new(progress).text(format!("{:.0}%", progress * 100.0)));
+                let pause_label = if self.paused { "Resume" } else { "Pause" };
+                toggle_pause = ui.button(pause_label).clicked();
+            }
+
+            ui.separator();
+            ui.heading("Standings");
+            egui::Grid::new("tournament_standings_grid")
+                .num_columns(7)
+                .striped(true)
+                .show(ui, |ui| {
+                    for heading in ["", "Games", "Won", "Drawn", "Lost", "Score", "Elo"] {
+                        ui.label(heading);
+                    }
+                    ui.end_row();
+                    for (entrant, record) in entrants.iter().zip(self.tournament.standings()) {
+                        ui.label(&entrant.name);
+                        ui.label(record.games().to_string());
+                        ui.label(record.wins.to_string());
+                        ui.label(record.draws.to_string());
+                        ui.label(record.losses.to_string());
+                        ui.label(
+                            record
+                                .score()
+                                .map_or("-".to_string(), |score| format!("{:.0}%", score * 100.0)),
+                        );
+                        ui.label(record.elo_difference().map_or("-".to_string(), format_elo));
+                        ui.end_row();
+                    }
+                });
+
+            ui.separator();
+            ui.heading("Matches");
+            egui::Grid::new("tournament_matches_grid")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for (&(a, b), record) in pairings.iter().zip(self.tournament.records()) {
+                        ui.label(format!("{} v {}", entrants[a].name, entrants[b].name));
+                        ui.label(format!(
+                            "+{} ={} -{}",
+                            record.wins, record.draws, record.losses
+                        ));
+                        ui.label(record.elo_difference().map_or("-".to_string(), format_elo));
+                        ui.end_row();
+                    }
+                });
+
+            if ui.button("Copy Results").clicked() {
+                ctx.copy_text(self.tournament.report());
+            }
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if let Some(contest) = self.tournament.current_match() {
+                let game = contest.game();
+                let layout = BoardLayout::new(game.logic(), ui.available_rect_before_wrap());
+                paint_board(
+                    ctx,
+                    ui.painter(),
+                    game.logic(),
+                    game.state(),
+                    &layout,
+                    self.pieces.textures(),
+                    &appearance.palette(ui.visuals()),
+                    Blindfold::Off,
+                );
+            }
+        });
+
+        if toggle_pause {
+            self.paused = !self.paused;
+            // The time spent paused doesn't count against the AI to move
+            if !self.paused {
+                self.tournament.restart_move();
+            }
+        }
+
+        ctx.request_repaint();
+
+        change_state
+    }
+}
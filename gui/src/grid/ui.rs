@@ -1,168 +1,2258 @@
 use crate::{
-    ai::Ai,
-    game::Game,
-    grid::{GridGame, Piece},
-    root::AppState,
+    ai::{
+        Ai, Bound, Line, SearchEvent,
+        strength::{Strength, mix64},
+        time::{Clock, TimeControl, TimeManager},
+    },
+    game::{Game, GameResult, Player, SavedClocks, SavedGame, State as _, StateIdent as _},
+    grid::{
+        CellShape, GridGame, Piece,
+        notation::{MoveFormatter, NotationStyle},
+        theme::{Appearance, BoardPalette, PieceSet, PieceTextures},
+    },
+    links::Link,
+    net::{
+        protocol::{Connection, Message},
+        spectators::Audience,
+    },
+    root::{AppState, Remembered},
 };
 use egui::{Color32, Pos2, Rect, Stroke, TextureHandle, Vec2};
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::mpsc::Receiver,
+};
 
 pub struct State<G: GridGame, A: Ai<G>> {
     game: Game<G>,
     ai: A,
+    events: Receiver<SearchEvent<G>>,
+    // The AI's progress on the current position, as told by its events
+    best_moves: Vec<Line<G::Move>>,
+    search_depth: Option<usize>,
+    search_nodes: usize,
+    search_finished: bool,
     enable_player1_autoplay: bool,
     player1_autoplay_time: u32,
     enable_player2_autoplay: bool,
     player2_autoplay_time: u32,
+    mode: Mode,
+    // The position a hint was asked for in and when, while the AI looks for one
+    hint_requested: Option<(u64, chrono::DateTime<chrono::Utc>)>,
+    // A move suggested for the position it was asked for in, drawn on the board while that's the position
+    hint: Option<(u64, G::Move)>,
+    // A move chosen while `confirm_moves` is on, waiting to be confirmed
+    staged: Option<StagedMove<G::Move>>,
+    // How many candidate lines the AI looks for
+    multi_pv: usize,
+    // Think about the expected reply while waiting for a player who isn't on autoplay
+    ponder: bool,
+    strength: Strength,
+    // The AI is held where it is, and doesn't play for players on autoplay, until resumed
+    paused: bool,
+    // Why the last evaluation network or learning file failed to load
+    load_error: Option<String>,
+    // Why the last saved game failed to load
+    game_load_error: Option<String>,
+    // The contents of a saved game being uploaded, which the browser reads in the background
+    #[cfg(target_arch = "wasm32")]
+    upload: Option<Receiver<String>>,
+    // Each player's clock, indexed by `clock_idx`, when playing with a clock
+    clocks: Option<[Clock; 2]>,
+    clock_minutes: u32,
+    clock_increment: u32,
+    time_manager: TimeManager,
+    // When the player to move started their turn, to charge to their clock
+    turn_start_time: chrono::DateTime<chrono::Utc>,
     thinking_start_time: chrono::DateTime<chrono::Utc>,
     move_selection: G::MoveSelectionState,
-    pieces: HashMap<Piece, TextureHandle>,
+    // How many moves into the game the board and the AI's analysis are of, when looking back rather than at the current position
+    viewing: Option<usize>,
+    // Show the board turned around, so that the second player's side is at the bottom
+    flip_board: bool,
+    coordinate_labels: CoordinateLabels,
+    blindfold: Blindfold,
+    // How moves are written everywhere they're shown, typed and saved
+    notation: NotationStyle,
+    // What's been typed of the next move
+    move_entry: String,
+    // Whether the controls are pulled up over the board in the compact layout
+    sheet_open: bool,
+    // The last move described for screen readers, and how many moves had been played when it was
+    announcement: String,
+    announced_moves: usize,
+    // The width and height of saved screenshots, whatever the size of the window
+    screenshot_size: u32,
+    sound: bool,
+    highlight_last_move: bool,
+    // Mark the cells which a click would play a move on
+    show_destinations: bool,
+    // Moves chosen on the board or typed in wait to be confirmed before they're played
+    confirm_moves: bool,
+    // The end of game banner was closed, until the game is no longer finished
+    result_dismissed: bool,
+    // What has been drawn on each position, keyed by the hash of its ident, so it's there again on going back to it
+    annotations: HashMap<u64, Annotations>,
+    // The cell the right mouse button was pressed on, while drawing
+    annotation_start: Option<(usize, usize)>,
+    pieces: PieceTextures,
     main_menu_prompt: bool,
+    // The other player, when they're playing on another copy of the app
+    remote: Option<Remote>,
+    // The game being watched, when it's between two others over the network
+    spectating: Option<Spectating>,
+    // The game as a link to share, kept up to date in the page's address
+    link: Option<SharedLink<G::Move>>,
+}
+
+// The options chosen during a game which carry over to the next one
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct GameOptions {
+    // The name of one of `Strength::LEVELS`
+    strength: String,
+    multi_pv: usize,
+    ponder: bool,
+    coordinate_labels: CoordinateLabels,
+    blindfold: Blindfold,
+    notation: NotationStyle,
+    sound: bool,
+    highlight_last_move: bool,
+    show_destinations: bool,
+    confirm_moves: bool,
+    clock_minutes: u32,
+    clock_increment: u32,
+    screenshot_size: u32,
+}
+
+impl Default for GameOptions {
+    fn default() -> Self {
+        Self {
+            strength: Strength::FULL.name.to_string(),
+            multi_pv: 1,
+            ponder: false,
+            coordinate_labels: CoordinateLabels::Outside,
+            blindfold: Blindfold::Off,
+            notation: NotationStyle::Standard,
+            sound: crate::sound::AVAILABLE,
+            highlight_last_move: true,
+            show_destinations: false,
+            confirm_moves: false,
+            clock_minutes: 5,
+            clock_increment: 3,
+            screenshot_size: 1024,
+        }
+    }
+}
+
+impl GameOptions {
+    pub fn notation(&self) -> NotationStyle {
+        self.notation
+    }
 }
 
 impl<G: GridGame, A: Ai<G>> State<G, A> {
-    pub fn new(ctx: &egui::Context, game_logic: G) -> Self {
-        // helper to load embedded PNGs
-        let load = |name: &'static str, bytes: &'static [u8]| -> TextureHandle {
-            let img = image::load_from_memory(bytes).expect("embedded image failed to load");
-            let size = [img.width() as _, img.height() as _];
-            let rgba = img.to_rgba8();
-            let pixels = rgba.into_flat_samples().samples;
-            ctx.load_texture(
-                name,
-                egui::ColorImage::from_rgba_unmultiplied(size, &pixels),
-                egui::TextureOptions::LINEAR,
-            )
+    pub fn new(ctx: &egui::Context, game: Game<G>) -> Self {
+        let pieces = PieceTextures::new(ctx, PieceSet::default());
+
+        let mut ai = A::new();
+        let events = ai.subscribe();
+        ai.set_game(game.clone());
+        Self {
+            move_selection: game.logic().initial_move_selection(),
+            viewing: None,
+            flip_board: false,
+            coordinate_labels: CoordinateLabels::Outside,
+            blindfold: Blindfold::Off,
+            notation: NotationStyle::Standard,
+            move_entry: String::new(),
+            sheet_open: false,
+            announcement: String::new(),
+            announced_moves: 0,
+            screenshot_size: 1024,
+            sound: crate::sound::AVAILABLE,
+            highlight_last_move: true,
+            show_destinations: false,
+            confirm_moves: false,
+            result_dismissed: false,
+            annotations: HashMap::new(),
+            annotation_start: None,
+            ai,
+            events,
+            best_moves: vec![],
+            search_depth: None,
+            search_nodes: 0,
+            search_finished: false,
+            enable_player1_autoplay: false,
+            player1_autoplay_time: 10,
+            enable_player2_autoplay: false,
+            player2_autoplay_time: 10,
+            mode: Mode::Analysis,
+            hint_requested: None,
+            hint: None,
+            staged: None,
+            multi_pv: 1,
+            ponder: false,
+            strength: Strength::FULL,
+            paused: false,
+            load_error: None,
+            game_load_error: None,
+            #[cfg(target_arch = "wasm32")]
+            upload: None,
+            clocks: None,
+            clock_minutes: 5,
+            clock_increment: 3,
+            time_manager: TimeManager::default(),
+            turn_start_time: chrono::Utc::now(),
+            thinking_start_time: chrono::Utc::now(),
+            game,
+            pieces,
+            main_menu_prompt: false,
+            remote: None,
+            spectating: None,
+            link: None,
+        }
+    }
+
+    // Have the AI play for each player given a number of seconds per move, as chosen before the game starts
+    pub fn with_autoplay(mut self, move_times: [Option<u32>; 2]) -> Self {
+        let [first, second] = move_times;
+        self.enable_player1_autoplay = first.is_some();
+        self.player1_autoplay_time = first.unwrap_or(self.player1_autoplay_time);
+        self.enable_player2_autoplay = second.is_some();
+        self.player2_autoplay_time = second.unwrap_or(self.player2_autoplay_time);
+        if first.is_some() || second.is_some() {
+            self.mode = Mode::Play;
+        }
+        // Someone playing only the second player wants their own side at the bottom
+        self.flip_board = self.enable_player1_autoplay && !self.enable_player2_autoplay;
+        self.start_thinking();
+        self
+    }
+
+    /*
+    Play against someone on another copy of the app, who plays `player` and whose moves come over `connection`.
+    The AI is kept out of the game, and the clocks are as the host set them.
+     */
+    pub fn with_remote(
+        mut self,
+        connection: Box<dyn Connection>,
+        player: Player,
+        hosting: bool,
+        clocks: Option<&SavedClocks>,
+    ) -> Self {
+        self.mode = Mode::Play;
+        self.enable_player1_autoplay = false;
+        self.enable_player2_autoplay = false;
+        self.flip_board = player == Player::First;
+        self.set_clocks(clocks);
+        self.remote = Some(Remote {
+            connection,
+            player,
+            hosting,
+            away: false,
+            lost: None,
+            audience: None,
+            shown: None,
+        });
+        self.ai.set_game(self.game.clone());
+        self.start_thinking();
+        self
+    }
+
+    // Keep `link`, to the game as it was set up, in the page's address with the moves played since
+    pub fn with_link(mut self, link: Link) -> Self {
+        self.link = Some(SharedLink {
+            link,
+            moves: None,
+            address: None,
+        });
+        self.show_link();
+        self
+    }
+
+    // As the host of a game over the network, let others watch it
+    pub fn with_audience(mut self, audience: Audience) -> Self {
+        if let Some(remote) = &mut self.remote {
+            remote.audience = Some(audience);
+        }
+        self
+    }
+
+    // Watch a game between two others over the network, from what the host sends on `connection`
+    pub fn with_spectating(
+        mut self,
+        connection: Box<dyn Connection>,
+        clocks: Option<&SavedClocks>,
+    ) -> Self {
+        self.mode = Mode::Play;
+        self.enable_player1_autoplay = false;
+        self.enable_player2_autoplay = false;
+        self.set_clocks(clocks);
+        self.spectating = Some(Spectating {
+            connection,
+            lost: None,
+        });
+        self.ai.set_game(self.game.clone());
+        self
+    }
+
+    fn receive_events(&mut self) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                SearchEvent::Started => {
+                    self.best_moves = vec![];
+                    self.search_depth = None;
+                    self.search_nodes = 0;
+                    self.search_finished = false;
+                }
+                SearchEvent::NewBestMove(best_moves) => self.best_moves = best_moves,
+                SearchEvent::Depth(depth) => self.search_depth = Some(depth),
+                SearchEvent::Nodes(nodes) => self.search_nodes = nodes,
+                SearchEvent::Finished => self.search_finished = true,
+            }
+        }
+    }
+
+    // Whether the AI plays for `player`, which it only does in play mode
+    fn autoplay(&self, player: Player) -> bool {
+        self.mode == Mode::Play
+            && match player {
+                Player::First => self.enable_player1_autoplay,
+                Player::Second => self.enable_player2_autoplay,
+            }
+    }
+
+    // Whether `player` plays on another copy of the app, as both do when watching
+    fn is_remote(&self, player: Player) -> bool {
+        self.spectating.is_some()
+            || self
+                .remote
+                .as_ref()
+                .is_some_and(|remote| remote.player == player)
+    }
+
+    fn is_networked(&self) -> bool {
+        self.remote.is_some() || self.spectating.is_some()
+    }
+
+    // Whether it's the turn of someone playing at this copy of the app, rather than the AI or the other player over the network
+    fn person_to_move(&self) -> bool {
+        let turn = self.game.turn();
+        !self.autoplay(turn) && !self.is_remote(turn)
+    }
+
+    // Tell the other player over the network, if there is one and they can still be reached
+    fn send(&mut self, message: Message) {
+        if let Some(remote) = &mut self.remote
+            && remote.lost.is_none()
+            && let Err(err) = remote.connection.send(&message)
+        {
+            remote.lost = Some(err);
+        }
+    }
+
+    // Everything the other player has done since the last frame
+    fn receive_remote(&mut self) {
+        while let Some(remote) = &mut self.remote
+            && remote.lost.is_none()
+            && let Some(received) = remote.connection.receive()
+        {
+            let player = remote.player;
+            remote.away = matches!(received, Ok(Message::Away));
+            if let Err(err) = received.and_then(|message| self.apply_remote(player, message))
+                && let Some(remote) = &mut self.remote
+            {
+                remote.lost = Some(err);
+            }
+        }
+    }
+
+    fn apply_remote(&mut self, player: Player, message: Message) -> Result<(), String> {
+        // Anything crossing with the end of the game is too late
+        if self.game.is_finished()
+            && !matches!(message, Message::Bye { .. } | Message::Watch { .. })
+        {
+            return Ok(());
+        }
+        match message {
+            Message::Move { mv, remaining } => {
+                let chance = self
+                    .game
+                    .logic()
+                    .chance_outcomes(self.game.state())
+                    .is_some();
+                let Some(found) = self.game.move_named(&mv) else {
+                    return Err(format!(
+                        "The other player sent a move which isn't legal: {mv}"
+                    ));
+                };
+                if chance {
+                    self.game.make_move(found);
+                    self.move_selection = self.game.logic().initial_move_selection();
+                    self.ai.set_game(self.shown_game());
+                    self.start_thinking();
+                } else if self.game.turn() == player {
+                    self.make_move(found);
+                    if let Some(clocks) = &mut self.clocks
+                        && let Some(remaining) = remaining
+                    {
+                        clocks[clock_idx(player)]
+                            .set_remaining(chrono::TimeDelta::milliseconds(remaining));
+                    }
+                } else {
+                    return Err(format!("The other player moved out of turn: {mv}"));
+                }
+            }
+            Message::OfferDraw => self.game.offer_draw(player),
+            Message::AcceptDraw => {
+                if self.game.draw_offer() == Some(player.flip()) {
+                    self.game.accept_draw();
+                    self.ai.stop_thinking();
+                }
+            }
+            Message::DeclineDraw => {
+                if self.game.draw_offer() == Some(player.flip()) {
+                    self.game.decline_draw();
+                }
+            }
+            Message::Resign => {
+                self.game.resign(player);
+                self.ai.stop_thinking();
+            }
+            Message::OutOfTime => {
+                self.game.flag(player);
+                self.ai.stop_thinking();
+            }
+            Message::Bye { reason } => {
+                return Err(reason.unwrap_or_else(|| {
+                    format!("{} left the game", self.game.logic().player_name(player))
+                }));
+            }
+            // The other player is back after losing their connection, and catches up from this game
+            Message::Hello { .. } => self.send(Message::Resync {
+                game: self.saved_game(),
+            }),
+            Message::Resync { game } => self.resync(&game)?,
+            Message::Away => {}
+            // Someone watching through the relay, who the host sends the game to
+            Message::Watch { .. } => {
+                if let Some(remote) = &mut self.remote
+                    && let Some(audience) = &mut remote.audience
+                {
+                    audience.watched_through_relay();
+                }
+            }
+            // For spectators, which the relay also sends the other player
+            Message::Spectate { .. } => {}
+            // Only sent while setting up the game
+            Message::Setup { .. } | Message::Ready => {}
+        }
+        Ok(())
+    }
+
+    // As the host, send the game to spectators whenever it changes or someone new asks for it
+    fn show_spectators(&mut self) {
+        let shown = (
+            self.game.move_history().len(),
+            self.game.is_finished(),
+            self.game.draw_offer(),
+        );
+        let Some(remote) = &mut self.remote else {
+            return;
+        };
+        let Some(audience) = &mut remote.audience else {
+            return;
+        };
+        if !audience.poll() && remote.shown == Some(shown) {
+            return;
+        }
+        remote.shown = Some(shown);
+        let relayed = audience.is_relayed();
+        let message = Message::Spectate {
+            game: self.saved_game(),
+        };
+        if let Some(audience) = self
+            .remote
+            .as_mut()
+            .and_then(|remote| remote.audience.as_mut())
+        {
+            audience.show(&message);
+        }
+        if relayed {
+            self.send(message);
+        }
+    }
+
+    // Catch up with the game being watched, from what the host sends
+    fn receive_spectating(&mut self) {
+        while let Some(spectating) = &mut self.spectating
+            && spectating.lost.is_none()
+            && let Some(received) = spectating.connection.receive()
+        {
+            let caught_up = match received {
+                Ok(Message::Spectate { game }) => Game::load(self.game.logic().clone(), &game)
+                    .map(|loaded| self.carry_on(loaded, game.clocks.as_ref())),
+                Ok(Message::Bye { reason }) => {
+                    Err(reason.unwrap_or_else(|| "A player left the game".to_string()))
+                }
+                // Everything else the players send each other, shown by the relay
+                Ok(_) => Ok(()),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = caught_up
+                && let Some(spectating) = &mut self.spectating
+            {
+                spectating.lost = Some(err);
+            }
+        }
+    }
+
+    /*
+    Catch up with the other player's game after a reconnection, which can only have gone on further than this one or fallen behind.
+    Whichever is further on is the one both carry on with, so this one is sent back if it's further on.
+     */
+    fn resync(&mut self, theirs: &SavedGame) -> Result<(), String> {
+        let ours = self.saved_game();
+        let shared = ours.moves.len().min(theirs.moves.len());
+        if ours.moves[..shared] != theirs.moves[..shared] {
+            return Err("The two copies of the game no longer match".to_string());
+        }
+        let progress = |game: &SavedGame| (game.moves.len(), game.ended.is_some());
+        match progress(theirs).cmp(&progress(&ours)) {
+            std::cmp::Ordering::Greater => {
+                let game = Game::load(self.game.logic().clone(), theirs)?;
+                self.carry_on(game, theirs.clocks.as_ref());
+            }
+            std::cmp::Ordering::Less => self.send(Message::Resync { game: ours }),
+            std::cmp::Ordering::Equal => {}
+        }
+        Ok(())
+    }
+
+    // In play mode the AI only thinks about its own moves, or about the reply it expects while pondering
+    fn ai_may_think(&self) -> bool {
+        match self.mode {
+            Mode::Analysis => true,
+            Mode::Play => {
+                let turn = self.game.turn();
+                self.viewing.is_none()
+                    && (self.autoplay(turn)
+                        || (self.ponder && self.autoplay(turn.flip()))
+                        || self.hint_requested.is_some())
+            }
+        }
+    }
+
+    // Tells apart the positions hints are for
+    fn position_key(&self) -> u64 {
+        self.game.state().clone().position_ident().hash64()
+    }
+
+    // Have the AI look for a move for the player to move, which `take_hint` picks up once it's had long enough
+    fn ask_for_hint(&mut self) {
+        self.hint_requested = Some((self.position_key(), chrono::Utc::now()));
+        self.ai.set_game(self.game.clone());
+        self.ai.stop_thinking_after(HINT_TIME);
+    }
+
+    fn take_hint(&mut self) {
+        let Some((position, asked)) = self.hint_requested else {
+            return;
+        };
+        if position != self.position_key() {
+            // A move was made before the hint was ready
+            self.hint_requested = None;
+        } else if chrono::Utc::now() - asked >= HINT_TIME {
+            self.hint_requested = None;
+            self.hint = self.ai.best_move().map(|mv| (position, mv));
+        }
+    }
+
+    // The hint for the position on the board, if one was asked for
+    fn current_hint(&self) -> Option<&G::Move> {
+        match &self.hint {
+            Some((position, mv)) if self.viewing.is_none() && *position == self.position_key() => {
+                Some(mv)
+            }
+            _ => None,
+        }
+    }
+
+    // Play a move chosen by a person, or hold it back to be confirmed if they've asked for that
+    fn choose_move(&mut self, mv: G::Move, cell: Option<(usize, usize)>) {
+        if self.confirm_moves {
+            self.staged = Some(StagedMove {
+                position: self.position_key(),
+                mv,
+                cell,
+            });
+            self.move_selection = self.game.logic().initial_move_selection();
+        } else {
+            self.make_move(mv);
+        }
+    }
+
+    // The move waiting to be confirmed, while it can still be played
+    fn staged_move(&self) -> Option<&G::Move> {
+        match &self.staged {
+            Some(StagedMove { position, mv, .. })
+                if self.viewing.is_none()
+                    && !self.game.is_finished()
+                    && self.person_to_move()
+                    && *position == self.position_key() =>
+            {
+                Some(mv)
+            }
+            _ => None,
+        }
+    }
+
+    fn confirm_move(&mut self) {
+        if let Some(mv) = self.staged_move().cloned() {
+            self.staged = None;
+            self.make_move(mv);
+        }
+    }
+
+    // How long the AI may think before playing for the player to move
+    fn move_time(&self) -> chrono::TimeDelta {
+        let turn = self.game.turn();
+        match &self.clocks {
+            Some(clocks) => self.time_manager.allocate(&clocks[clock_idx(turn)]),
+            None => chrono::TimeDelta::seconds(match turn {
+                Player::First => self.player1_autoplay_time,
+                Player::Second => self.player2_autoplay_time,
+            } as i64),
+        }
+    }
+
+    // Restart the AI's thinking time, making sure it stops in time if it's going to play the move
+    fn start_thinking(&mut self) {
+        self.thinking_start_time = chrono::Utc::now();
+        if self.viewing.is_none() && self.autoplay(self.game.turn()) {
+            self.ai.stop_thinking_after(self.move_time());
+        }
+    }
+
+    fn reset_clocks(&mut self, enable: bool) {
+        let control = TimeControl {
+            base: chrono::TimeDelta::minutes(self.clock_minutes as i64),
+            increment: chrono::TimeDelta::seconds(self.clock_increment as i64),
+            moves: None,
+        };
+        self.clocks = enable.then(|| [Clock::new(control), Clock::new(control)]);
+        self.turn_start_time = chrono::Utc::now();
+    }
+
+    fn make_move(&mut self, mv: G::Move) {
+        // The game may have been resigned, agreed drawn or lost on time with moves still left
+        if self.game.is_finished() {
+            return;
+        }
+        let mover = self.game.turn();
+        let ai_moved = self.autoplay(mover);
+        // Moves made here are sent to the other player over the network
+        let sent = (self.remote.is_some() && !self.is_remote(mover))
+            .then(|| self.game.logic().move_name(&mv));
+        if let Some(clocks) = &mut self.clocks {
+            clocks[clock_idx(mover)].spend(chrono::Utc::now() - self.turn_start_time);
+        }
+        self.turn_start_time = chrono::Utc::now();
+        self.game.make_move(mv);
+        if let Some(mv) = sent {
+            let remaining = self
+                .clocks
+                .as_ref()
+                .map(|clocks| clocks[clock_idx(mover)].remaining().num_milliseconds());
+            self.send(Message::Move { mv, remaining });
+        }
+        self.move_selection = self.game.logic().initial_move_selection();
+        if self.ponder && ai_moved && !self.autoplay(self.game.turn()) {
+            self.ai.ponder(self.game.clone());
+        } else {
+            self.ai.set_game(self.game.clone());
+        }
+        self.start_thinking();
+    }
+
+    // The game up to the position being viewed
+    fn shown_game(&self) -> Game<G> {
+        match self.viewing {
+            Some(num_moves) => self.game.rewound(num_moves),
+            None => self.game.clone(),
+        }
+    }
+
+    fn shown_moves(&self) -> usize {
+        self.viewing.unwrap_or(self.game.num_moves())
+    }
+
+    // Look at the position after the first `num_moves` moves, with the AI analysing it instead, which holds autoplay until back at the current position
+    fn view(&mut self, num_moves: usize) {
+        let viewing = (num_moves < self.game.num_moves()).then_some(num_moves);
+        if viewing != self.viewing {
+            self.viewing = viewing;
+            self.move_selection = self.game.logic().initial_move_selection();
+            self.ai.set_game(self.shown_game());
+            self.start_thinking();
+        }
+    }
+
+    // Step through the game with the arrow keys, left and right for a move at a time and up and down for the start and the current position
+    fn navigate_with_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let shown = self.shown_moves();
+        let num_moves = ctx.input(|i| {
+            if i.key_pressed(egui::Key::ArrowUp) {
+                Some(0)
+            } else if i.key_pressed(egui::Key::ArrowLeft) {
+                Some(shown.saturating_sub(1))
+            } else if i.key_pressed(egui::Key::ArrowRight) {
+                Some(shown + 1)
+            } else if i.key_pressed(egui::Key::ArrowDown) {
+                Some(self.game.num_moves())
+            } else {
+                None
+            }
+        });
+        if let Some(num_moves) = num_moves {
+            self.view(num_moves.min(self.game.num_moves()));
+        }
+    }
+
+    /*
+    The moves played so far, numbered in pairs as each player moves in turn, with buttons to step through them.
+    Clicking a move shows the board as it was after it, leaving the game as it is, and the last move goes back to the current position.
+     */
+    fn show_move_list(&mut self, ui: &mut egui::Ui) {
+        let names = self.formatter().write_history(&self.game);
+        let shown = self.shown_moves();
+        let last = names.len();
+        ui.horizontal(|ui| {
+            for (text, num_moves, enabled) in [
+                ("⏮", 0, shown > 0),
+                ("◀", shown.saturating_sub(1), shown > 0),
+                ("▶", shown + 1, shown < last),
+                ("⏭", last, shown < last),
+            ] {
+                if ui.add_enabled(enabled, egui::Button::new(text)).clicked() {
+                    self.view(num_moves);
+                }
+            }
+        });
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for (pair, names) in names.chunks(2).enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}.", pair + 1));
+                        for (i, name) in names.iter().enumerate() {
+                            let num_moves = 2 * pair + i + 1;
+                            let response = ui.selectable_label(shown == num_moves, name);
+                            // Read out in full, as the move number is a separate label
+                            let player = if i == 0 {
+                                Player::First
+                            } else {
+                                Player::Second
+                            };
+                            response.widget_info(|| {
+                                egui::WidgetInfo::selected(
+                                    egui::WidgetType::SelectableLabel,
+                                    true,
+                                    shown == num_moves,
+                                    format!(
+                                        "Move {}, {} {name}",
+                                        pair + 1,
+                                        self.game.logic().player_name(player)
+                                    ),
+                                )
+                            });
+                            if response.clicked() {
+                                self.view(num_moves);
+                            }
+                        }
+                    });
+                }
+                // The result goes at the end of the record, however the game ended
+                if let Some((headline, reason)) = describe_result(&self.game) {
+                    ui.label(match reason {
+                        Some(reason) => format!("{headline}: {reason}"),
+                        None => headline,
+                    });
+                }
+            });
+        if let Some(num_moves) = self.viewing {
+            ui.horizontal(|ui| {
+                ui.label(format!("Viewing after move {num_moves}"));
+                if ui.button("Back to Game").clicked() {
+                    self.view(self.game.num_moves());
+                }
+            });
+        }
+    }
+
+    // Writes moves in the chosen notation style
+    fn formatter(&self) -> MoveFormatter<'_, G> {
+        MoveFormatter::new(self.game.logic(), self.notation)
+    }
+
+    // Describe the latest move for screen readers to announce, once for each move played
+    fn announce_moves(&mut self) {
+        let num_moves = self.game.num_moves();
+        if num_moves > self.announced_moves {
+            let before = self.game.rewound(num_moves - 1);
+            let mv = &self.game.move_history()[num_moves - 1];
+            self.announcement = format!(
+                "{} played {}",
+                self.game.logic().player_name(before.turn()),
+                self.formatter().write(before.state(), mv)
+            );
+            if let Some((headline, _)) = describe_result(&self.game) {
+                self.announcement.push_str(&format!(". {headline}"));
+            }
+        }
+        self.announced_moves = num_moves;
+    }
+
+    // The game with its clocks, as saved and as sent to catch the other player up
+    fn saved_game(&self) -> SavedGame {
+        let mut saved = self.game.save();
+        saved.clocks = self.clocks.as_ref().map(|clocks| SavedClocks {
+            minutes: self.clock_minutes,
+            increment: self.clock_increment,
+            remaining: [Player::First, Player::Second]
+                .map(|player| clocks[clock_idx(player)].remaining().num_milliseconds()),
+        });
+        saved
+    }
+
+    // Put the game's link in the page's address again once its moves have changed
+    fn show_link(&mut self) {
+        let Some(shared) = &mut self.link else {
+            return;
+        };
+        let history = self.game.move_history();
+        if shared.moves.as_deref() == Some(history) {
+            return;
+        }
+        shared.moves = Some(history.to_vec());
+        shared.link = std::mem::take(&mut shared.link).with_moves(&self.game);
+        shared.address = crate::links::show(&shared.link);
+    }
+
+    fn save_game(&self) {
+        let saved = self.saved_game();
+        match ron::ser::to_string_pretty(&saved, ron::ser::PrettyConfig::default()) {
+            Ok(text) => crate::files::save_text("game.ron", "ron", &text),
+            Err(err) => log::error!("Failed to save the game: {err}"),
+        }
+    }
+
+    // Carry on with a game from `save_game`, keeping the current one if it can't be loaded
+    fn load_game(&mut self, text: &str) {
+        let loaded = ron::from_str::<SavedGame>(text)
+            .map_err(|err| err.to_string())
+            .and_then(|saved| {
+                Game::load(self.game.logic().clone(), &saved).map(|game| (game, saved.clocks))
+            });
+        let (game, clocks) = match loaded {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                self.game_load_error = Some(err);
+                return;
+            }
         };
+        self.game_load_error = None;
+        self.carry_on(game, clocks.as_ref());
+    }
 
-        let mut pieces = HashMap::new();
-        pieces.insert(
-            Piece::WhitePawn,
-            load("white_pawn", include_bytes!("icons/white pawn.png")),
+    // Switch to `game`, from the position it's got to
+    fn carry_on(&mut self, game: Game<G>, clocks: Option<&SavedClocks>) {
+        self.game = game;
+        self.viewing = None;
+        self.move_selection = self.game.logic().initial_move_selection();
+        self.set_clocks(clocks);
+        self.ai.set_game(self.game.clone());
+        self.start_thinking();
+    }
+
+    // Carry on with the clocks as saved, or play without them
+    fn set_clocks(&mut self, clocks: Option<&SavedClocks>) {
+        match clocks {
+            Some(clocks) => {
+                self.clock_minutes = clocks.minutes;
+                self.clock_increment = clocks.increment;
+                self.reset_clocks(true);
+                if let Some(own) = &mut self.clocks {
+                    for (player, remaining) in [Player::First, Player::Second]
+                        .into_iter()
+                        .zip(clocks.remaining)
+                    {
+                        own[clock_idx(player)]
+                            .set_remaining(chrono::TimeDelta::milliseconds(remaining));
+                    }
+                }
+            }
+            None => self.reset_clocks(false),
+        }
+    }
+
+    /*
+    Paint the position being shown within `avail`, as it's seen in the window and in screenshots.
+    This is the board with its coordinates, any check and the last move, but nothing that belongs to choosing a move.
+     */
+    #[allow(clippy::too_many_arguments)]
+    fn paint_position(
+        &self,
+        ctx: &egui::Context,
+        painter: &egui::Painter,
+        mut avail: Rect,
+        pieces: &HashMap<Piece, TextureHandle>,
+        palette: &BoardPalette,
+        text_color: Color32,
+        blindfold: Blindfold,
+    ) -> BoardLayout {
+        if self.coordinate_labels == CoordinateLabels::Outside {
+            // Leave room around the board to write in
+            let cell_size = BoardLayout::new(self.game.logic(), avail).cell_size;
+            avail = avail.shrink(0.5 * cell_size);
+        }
+        let layout = BoardLayout::new(self.game.logic(), avail).flipped(self.flip_board);
+        let cell_size = layout.cell_size;
+        let cell_to_rect = |row, col| layout.cell_rect(row, col);
+        let shown_game = self.shown_game();
+        paint_board(
+            ctx,
+            painter,
+            self.game.logic(),
+            shown_game.state(),
+            &layout,
+            pieces,
+            palette,
+            blindfold,
         );
-        pieces.insert(
-            Piece::WhiteBerolinaPawn,
-            load("white_berolina", include_bytes!("icons/white berolina.png")),
+
+        paint_coordinates(
+            painter,
+            self.game.logic(),
+            &layout,
+            self.coordinate_labels,
+            palette,
+            text_color,
         );
-        pieces.insert(
-            Piece::WhiteRook,
-            load("white_rook", include_bytes!("icons/white rook.png")),
+
+        if let Some((row, col)) = self.game.logic().checked_cell(shown_game.state()) {
+            painter.rect_filled(cell_to_rect(row, col), 0.0, palette.check);
+        }
+
+        if self.highlight_last_move
+            && let Some(mv) = shown_game.move_history().last()
+        {
+            let before = shown_game.rewound(shown_game.num_moves() - 1);
+            self.game.logic().show_move(
+                before.turn(),
+                before.state(),
+                mv.clone(),
+                cell_size,
+                cell_to_rect,
+                painter,
+            );
+        }
+        layout
+    }
+
+    // Save the position being shown as a square picture `screenshot_size` pixels across
+    fn save_screenshot(
+        &self,
+        ctx: &egui::Context,
+        render_state: &egui_wgpu::RenderState,
+        appearance: &Appearance,
+    ) {
+        let visuals = ctx.style().visuals.clone();
+        let texture = crate::headless::render_to_texture(
+            render_state,
+            self.screenshot_size,
+            self.screenshot_size,
+            visuals.panel_fill,
+            |ctx, painter| {
+                // The pieces are loaded again as textures belong to the context which loaded them
+                let pieces = load_pieces(ctx, appearance.pieces);
+                self.paint_position(
+                    ctx,
+                    painter,
+                    ctx.content_rect(),
+                    &pieces,
+                    &appearance.palette(&visuals),
+                    visuals.text_color(),
+                    // A picture of the position is no use without its pieces
+                    Blindfold::Off,
+                );
+            },
         );
-        pieces.insert(
-            Piece::WhiteKnight,
-            load("white_knight", include_bytes!("icons/white knight.png")),
+        crate::headless::save_png(render_state, texture, "board.png");
+    }
+
+    /*
+    A box for typing the move to play, in the notation of the move list or any other the game accepts, which Enter plays.
+    The legal moves starting with what's typed so far are listed below it, and are played by clicking them.
+     */
+    fn show_move_entry(&mut self, ui: &mut egui::Ui) {
+        let can_move = self.viewing.is_none()
+            && !self.game.is_finished()
+            && self.person_to_move()
+            && self
+                .game
+                .logic()
+                .chance_outcomes(self.game.state())
+                .is_none();
+        let typed = without_spaces(&self.move_entry);
+
+        // Each legal move that could be meant, with whether what's typed is all of one of its names
+        let mut matches = vec![];
+        if can_move && !typed.is_empty() {
+            let logic = self.game.logic();
+            let state = self.game.state();
+            for mv in logic.generate_moves(&mut state.clone()) {
+                let names = logic
+                    .typed_names(state, &mv)
+                    .iter()
+                    .map(|name| without_spaces(name))
+                    .collect::<Vec<_>>();
+                if names.iter().any(|name| name.starts_with(&typed)) {
+                    let exact = names.contains(&typed);
+                    matches.push((self.formatter().write(state, &mv), mv, exact));
+                }
+            }
+        }
+        let exact = matches
+            .iter()
+            .filter(|(_, _, exact)| *exact)
+            .collect::<Vec<_>>();
+        let chosen = match (exact.as_slice(), matches.as_slice()) {
+            ([(_, mv, _)], _) | ([], [(_, mv, _)]) => Some(mv.clone()),
+            _ => None,
+        };
+
+        let mut to_play = None;
+        // Left enabled while the other side moves so as to keep the focus
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.move_entry)
+                .hint_text("Type a move")
+                .desired_width(120.0),
         );
-        pieces.insert(
-            Piece::WhiteBishop,
-            load("white_bishop", include_bytes!("icons/white bishop.png")),
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            // Enter again with nothing typed confirms the move typed before
+            if typed.is_empty() {
+                self.confirm_move();
+            }
+            to_play = chosen;
+            // Ready for the next move without reaching for the mouse
+            response.request_focus();
+        }
+        if !typed.is_empty() && can_move {
+            if matches.is_empty() {
+                ui.colored_label(ui.visuals().error_fg_color, "No legal move matches");
+            } else {
+                ui.horizontal_wrapped(|ui| {
+                    for (name, mv, _) in matches.iter().take(12) {
+                        if ui.small_button(name).clicked() {
+                            to_play = Some(mv.clone());
+                        }
+                    }
+                    if matches.len() > 12 {
+                        ui.label(format!("and {} more", matches.len() - 12));
+                    }
+                });
+            }
+        }
+
+        if let Some(mv) = to_play {
+            self.move_entry.clear();
+            self.choose_move(mv, None);
+        }
+    }
+
+    fn show_clocks(&self, ui: &mut egui::Ui) {
+        if let Some(clocks) = &self.clocks {
+            for player in [Player::First, Player::Second] {
+                let mut remaining = clocks[clock_idx(player)].remaining();
+                if player == self.game.turn() && !self.game.is_finished() {
+                    remaining -= chrono::Utc::now() - self.turn_start_time;
+                }
+                ui.label(format!(
+                    "{}: {}",
+                    self.game.logic().player_name(player),
+                    format_clock(remaining)
+                ));
+            }
+        }
+    }
+
+    // Whether the AI plays the player to move while a person plays the other
+    fn ai_to_move_against_person(&self) -> bool {
+        let turn = self.game.turn();
+        self.autoplay(turn) && !self.autoplay(turn.flip())
+    }
+
+    // Against the AI its reply is played again too, so that it's the person's turn again
+    fn redo_move(&mut self) {
+        self.game.redo_move();
+        while self.ai_to_move_against_person() && self.game.can_redo_move() {
+            self.game.redo_move();
+        }
+        self.viewing = None;
+        self.move_selection = self.game.logic().initial_move_selection();
+        self.ai.set_game(self.game.clone());
+        self.turn_start_time = chrono::Utc::now();
+        self.start_thinking();
+    }
+
+    /*
+    Against the AI a whole move is taken back, its reply along with the person's move before it, so that it's the person's turn again.
+    Whatever the AI was thinking about is dropped for the position gone back to.
+     */
+    fn undo_move(&mut self) {
+        self.game.undo_move();
+        while self.ai_to_move_against_person() && self.game.can_undo_move() {
+            self.game.undo_move();
+        }
+        self.viewing = None;
+        self.move_selection = self.game.logic().initial_move_selection();
+        self.ai.set_game(self.game.clone());
+        self.turn_start_time = chrono::Utc::now();
+        self.start_thinking();
+    }
+}
+
+// The icons of the pieces which have one in `set`
+pub fn load_pieces(ctx: &egui::Context, set: PieceSet) -> HashMap<Piece, TextureHandle> {
+    if set == PieceSet::Letters {
+        return HashMap::new();
+    }
+
+    // helper to load embedded PNGs
+    let load = |name: &'static str, bytes: &'static [u8]| -> TextureHandle {
+        let img = image::load_from_memory(bytes).expect("embedded image failed to load");
+        let size = [img.width() as _, img.height() as _];
+        let rgba = img.to_rgba8();
+        let pixels = rgba.into_flat_samples().samples;
+        ctx.load_texture(
+            name,
+            egui::ColorImage::from_rgba_unmultiplied(size, &pixels),
+            egui::TextureOptions::LINEAR,
+        )
+    };
+
+    let mut pieces = HashMap::new();
+    pieces.insert(
+        Piece::WhitePawn,
+        load("white_pawn", include_bytes!("icons/white pawn.png")),
+    );
+    pieces.insert(
+        Piece::WhiteBerolinaPawn,
+        load("white_berolina", include_bytes!("icons/white berolina.png")),
+    );
+    pieces.insert(
+        Piece::WhiteRook,
+        load("white_rook", include_bytes!("icons/white rook.png")),
+    );
+    pieces.insert(
+        Piece::WhiteKnight,
+        load("white_knight", include_bytes!("icons/white knight.png")),
+    );
+    pieces.insert(
+        Piece::WhiteBishop,
+        load("white_bishop", include_bytes!("icons/white bishop.png")),
+    );
+    pieces.insert(
+        Piece::WhiteQueen,
+        load("white_queen", include_bytes!("icons/white queen.png")),
+    );
+    pieces.insert(
+        Piece::WhiteKing,
+        load("white_king", include_bytes!("icons/white king.png")),
+    );
+    pieces.insert(
+        Piece::WhiteGrasshopper,
+        load(
+            "white_grasshopper",
+            include_bytes!("icons/white grasshopper.png"),
+        ),
+    );
+
+    pieces.insert(
+        Piece::BlackPawn,
+        load("black_pawn", include_bytes!("icons/black pawn.png")),
+    );
+    pieces.insert(
+        Piece::BlackBerolinaPawn,
+        load("black_berolina", include_bytes!("icons/black berolina.png")),
+    );
+    pieces.insert(
+        Piece::BlackRook,
+        load("black_rook", include_bytes!("icons/black rook.png")),
+    );
+    pieces.insert(
+        Piece::BlackKnight,
+        load("black_knight", include_bytes!("icons/black knight.png")),
+    );
+    pieces.insert(
+        Piece::BlackBishop,
+        load("black_bishop", include_bytes!("icons/black bishop.png")),
+    );
+    pieces.insert(
+        Piece::BlackQueen,
+        load("black_queen", include_bytes!("icons/black queen.png")),
+    );
+    pieces.insert(
+        Piece::BlackKing,
+        load("black_king", include_bytes!("icons/black king.png")),
+    );
+    pieces.insert(
+        Piece::BlackGrasshopper,
+        load(
+            "black_grasshopper",
+            include_bytes!("icons/black grasshopper.png"),
+        ),
+    );
+
+    pieces.insert(
+        Piece::WhiteChecker,
+        load("white_checker", include_bytes!("icons/white checker.png")),
+    );
+    pieces.insert(
+        Piece::WhiteCheckerKing,
+        load(
+            "white_checker_king",
+            include_bytes!("icons/white checker king.png"),
+        ),
+    );
+    pieces.insert(
+        Piece::BlackChecker,
+        load("black_checker", include_bytes!("icons/black checker.png")),
+    );
+    pieces.insert(
+        Piece::BlackCheckerKing,
+        load(
+            "black_checker_king",
+            include_bytes!("icons/black checker king.png"),
+        ),
+    );
+
+    pieces.insert(
+        Piece::SenteKing,
+        load("sente_king", include_bytes!("icons/sente king.png")),
+    );
+    pieces.insert(
+        Piece::SenteRook,
+        load("sente_rook", include_bytes!("icons/sente rook.png")),
+    );
+    pieces.insert(
+        Piece::SenteDragon,
+        load("sente_dragon", include_bytes!("icons/sente dragon.png")),
+    );
+    pieces.insert(
+        Piece::SenteBishop,
+        load("sente_bishop", include_bytes!("icons/sente bishop.png")),
+    );
+    pieces.insert(
+        Piece::SenteHorse,
+        load("sente_horse", include_bytes!("icons/sente horse.png")),
+    );
+    pieces.insert(
+        Piece::SenteGold,
+        load("sente_gold", include_bytes!("icons/sente gold.png")),
+    );
+    pieces.insert(
+        Piece::SenteSilver,
+        load("sente_silver", include_bytes!("icons/sente silver.png")),
+    );
+    pieces.insert(
+        Piece::SentePromotedSilver,
+        load(
+            "sente_promoted_silver",
+            include_bytes!("icons/sente promoted silver.png"),
+        ),
+    );
+    pieces.insert(
+        Piece::SenteKnight,
+        load("sente_knight", include_bytes!("icons/sente knight.png")),
+    );
+    pieces.insert(
+        Piece::SentePromotedKnight,
+        load(
+            "sente_promoted_knight",
+            include_bytes!("icons/sente promoted knight.png"),
+        ),
+    );
+    pieces.insert(
+        Piece::SenteLance,
+        load("sente_lance", include_bytes!("icons/sente lance.png")),
+    );
+    pieces.insert(
+        Piece::SentePromotedLance,
+        load(
+            "sente_promoted_lance",
+            include_bytes!("icons/sente promoted lance.png"),
+        ),
+    );
+    pieces.insert(
+        Piece::SentePawn,
+        load("sente_pawn", include_bytes!("icons/sente pawn.png")),
+    );
+    pieces.insert(
+        Piece::SenteTokin,
+        load("sente_tokin", include_bytes!("icons/sente tokin.png")),
+    );
+    pieces.insert(
+        Piece::GoteKing,
+        load("gote_king", include_bytes!("icons/gote king.png")),
+    );
+    pieces.insert(
+        Piece::GoteRook,
+        load("gote_rook", include_bytes!("icons/gote rook.png")),
+    );
+    pieces.insert(
+        Piece::GoteDragon,
+        load("gote_dragon", include_bytes!("icons/gote dragon.png")),
+    );
+    pieces.insert(
+        Piece::GoteBishop,
+        load("gote_bishop", include_bytes!("icons/gote bishop.png")),
+    );
+    pieces.insert(
+        Piece::GoteHorse,
+        load("gote_horse", include_bytes!("icons/gote horse.png")),
+    );
+    pieces.insert(
+        Piece::GoteGold,
+        load("gote_gold", include_bytes!("icons/gote gold.png")),
+    );
+    pieces.insert(
+        Piece::GoteSilver,
+        load("gote_silver", include_bytes!("icons/gote silver.png")),
+    );
+    pieces.insert(
+        Piece::GotePromotedSilver,
+        load(
+            "gote_promoted_silver",
+            include_bytes!("icons/gote promoted silver.png"),
+        ),
+    );
+    pieces.insert(
+        Piece::GoteKnight,
+        load("gote_knight", include_bytes!("icons/gote knight.png")),
+    );
+    pieces.insert(
+        Piece::GotePromotedKnight,
+        load(
+            "gote_promoted_knight",
+            include_bytes!("icons/gote promoted knight.png"),
+        ),
+    );
+    pieces.insert(
+        Piece::GoteLance,
+        load("gote_lance", include_bytes!("icons/gote lance.png")),
+    );
+    pieces.insert(
+        Piece::GotePromotedLance,
+        load(
+            "gote_promoted_lance",
+            include_bytes!("icons/gote promoted lance.png"),
+        ),
+    );
+    pieces.insert(
+        Piece::GotePawn,
+        load("gote_pawn", include_bytes!("icons/gote pawn.png")),
+    );
+    pieces.insert(
+        Piece::GoteTokin,
+        load("gote_tokin", include_bytes!("icons/gote tokin.png")),
+    );
+    pieces
+}
+
+// How long the AI looks for a hint
+const HINT_TIME: chrono::TimeDelta = chrono::TimeDelta::seconds(2);
+
+// What the AI does while a game is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    // The AI thinks about whatever position is on the board and shows what it finds, while moves are made for either side
+    Analysis,
+    // The AI plays for the players on autoplay, thinking only when it's to move, and keeps what it finds to itself
+    Play,
+}
+
+// Where the names of the rows and columns are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+enum CoordinateLabels {
+    Hidden,
+    // In the space around the board
+    Outside,
+    // In the corners of the cells along the bottom and left edges
+    Inside,
+}
+
+/*
+Which pieces are left off the board, to practise keeping the position in mind.
+Moves are still chosen by clicking the cells or typing them, and the move list is shown as usual.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Blindfold {
+    #[default]
+    Off,
+    // No pieces are drawn
+    All,
+    // Only the pieces belonging to this player are drawn
+    Only(Player),
+}
+
+impl Blindfold {
+    fn shows(&self, piece: Piece) -> bool {
+        match self {
+            Blindfold::Off => true,
+            Blindfold::All => false,
+            Blindfold::Only(player) => piece.letter().is_some_and(|(_, owner)| owner == *player),
+        }
+    }
+}
+
+// The connection to someone playing `player` on another copy of the app
+struct Remote {
+    connection: Box<dyn Connection>,
+    player: Player,
+    // The host rolls the dice and draws the cards, and sends what came up
+    hosting: bool,
+    // The relay said their connection dropped, and nothing has come from them since
+    away: bool,
+    // Why the connection was lost, after which nothing more is sent or received
+    lost: Option<String>,
+    // Those watching, for the host
+    audience: Option<Audience>,
+    // How far on the game was when last sent to them, and any draw offer
+    shown: Option<(usize, bool, Option<Player>)>,
+}
+
+// A link to the game, with the moves it was last written with and the page's address showing it
+struct SharedLink<M> {
+    link: Link,
+    moves: Option<Vec<M>>,
+    address: Option<String>,
+}
+
+// Watching a game between two others over the network
+struct Spectating {
+    connection: Box<dyn Connection>,
+    lost: Option<String>,
+}
+
+// A move held back until it's confirmed, for the position it was chosen in and with the cell clicked to choose it if it was
+struct StagedMove<M> {
+    position: u64,
+    mv: M,
+    cell: Option<(usize, usize)>,
+}
+
+// Marks on cells and arrows between them drawn with the right mouse button, to point things out
+#[derive(Debug, Default)]
+struct Annotations {
+    marks: HashSet<(usize, usize)>,
+    arrows: HashSet<((usize, usize), (usize, usize))>,
+}
+
+impl Annotations {
+    // A mark if dragged onto the cell it started on and an arrow otherwise, rubbing it out if it's already there
+    fn toggle(&mut self, from: (usize, usize), to: (usize, usize)) {
+        if from == to {
+            if !self.marks.remove(&from) {
+                self.marks.insert(from);
+            }
+        } else if !self.arrows.remove(&(from, to)) {
+            self.arrows.insert((from, to));
+        }
+    }
+}
+
+// Where the cells of a board fit in the space available for it
+pub struct BoardLayout {
+    top_left: Pos2,
+    size: Vec2,
+    pub cell_size: f32,
+    cell_height: f32,
+    row_step: f32,
+    row_shift: f32,
+    // Turned half way round, so that the last row is at the top
+    flipped: bool,
+}
+
+impl BoardLayout {
+    pub fn new<G: GridGame>(logic: &G, avail: Rect) -> Self {
+        let avail_size = avail.size();
+        let rows = logic.rows();
+        let cols = logic.cols();
+
+        // Hexagonal rows overlap so their centres are closer together than a cell's height
+        let (cell_height, row_step, row_shift) = if G::CELL_SHAPE == CellShape::Hex {
+            (2.0 / 3f32.sqrt(), 3f32.sqrt() / 2.0, 0.5)
+        } else {
+            (1.0, 1.0, 0.0)
+        };
+        let board_cells = Vec2::new(
+            cols as f32 + row_shift * (rows - 1) as f32,
+            row_step * (rows - 1) as f32 + cell_height,
         );
-        pieces.insert(
-            Piece::WhiteQueen,
-            load("white_queen", include_bytes!("icons/white queen.png")),
+
+        // Compute size of one cell: must be square, so use the smaller dimension
+        let cell_size = (avail_size.x / board_cells.x).min(avail_size.y / board_cells.y);
+
+        // Compute total board size and center it in the available rect
+        let board_size = cell_size * board_cells;
+        let top_left = Pos2::new(
+            avail.left() + (avail_size.x - board_size.x) / 2.0,
+            avail.top() + (avail_size.y - board_size.y) / 2.0,
         );
-        pieces.insert(
-            Piece::WhiteKing,
-            load("white_king", include_bytes!("icons/white king.png")),
+        Self {
+            top_left,
+            size: board_size,
+            cell_size,
+            cell_height,
+            row_step,
+            row_shift,
+            flipped: false,
+        }
+    }
+
+    pub fn flipped(self, flipped: bool) -> Self {
+        Self { flipped, ..self }
+    }
+
+    // The square around each cell, which pieces are drawn in
+    pub fn cell_rect(&self, row: usize, col: usize) -> Rect {
+        let x = self.top_left.x + (col as f32 + self.row_shift * row as f32) * self.cell_size;
+        let y = self.top_left.y
+            + (row as f32 * self.row_step + (self.cell_height - 1.0) / 2.0) * self.cell_size;
+
+        let rect = Rect::from_min_size(Pos2::new(x, y), Vec2::new(self.cell_size, self.cell_size));
+        if self.flipped {
+            // Turning the board half way round puts each cell opposite where it was about the centre
+            let centre = self.top_left + self.size / 2.0;
+            rect.translate(2.0 * (centre - rect.center()))
+        } else {
+            rect
+        }
+    }
+
+    // The cell which `pos` is on, if any
+    pub fn cell_at<G: GridGame>(&self, logic: &G, pos: Pos2) -> Option<(usize, usize)> {
+        let mut found = None;
+        let mut found_distance = f32::INFINITY;
+        for row in 0..logic.rows() {
+            for col in 0..logic.cols() {
+                let rect = self.cell_rect(row, col);
+                // A point is inside the hexagon whose centre it is nearest to
+                let distance = rect.center().distance(pos);
+                let inside = if G::CELL_SHAPE == CellShape::Hex {
+                    distance <= self.cell_size / 3f32.sqrt() && distance < found_distance
+                } else {
+                    rect.contains(pos)
+                };
+                if inside {
+                    found = Some((row, col));
+                    found_distance = distance;
+                }
+            }
+        }
+        found
+    }
+
+    fn hexagon(&self, row: usize, col: usize) -> Vec<Pos2> {
+        let center = self.cell_rect(row, col).center();
+        (0..6)
+            .map(|i| {
+                let angle = (30.0 + 60.0 * i as f32).to_radians();
+                center + self.cell_size / 3f32.sqrt() * Vec2::angled(angle)
+            })
+            .collect::<Vec<_>>()
+    }
+}
+
+/*
+Write the names of the columns along the bottom of the board and those of the rows up its left side, whichever way round it is.
+Inside, each name goes in the corner of its cell in the colour of the other squares so as to show up on it.
+ */
+fn paint_coordinates<G: GridGame>(
+    painter: &egui::Painter,
+    logic: &G,
+    layout: &BoardLayout,
+    labels: CoordinateLabels,
+    palette: &BoardPalette,
+    text_color: Color32,
+) {
+    if labels == CoordinateLabels::Hidden {
+        return;
+    }
+    let rows = logic.rows();
+    let cols = logic.cols();
+    let cell_size = layout.cell_size;
+    let (bottom_row, left_col) = if layout.flipped {
+        (0, cols - 1)
+    } else {
+        (rows - 1, 0)
+    };
+    let BoardPalette { light, dark, .. } = *palette;
+    let contrast = |row: usize, col: usize| {
+        if G::CELL_SHAPE == CellShape::Square && (row + col) % 2 == 1 {
+            light
+        } else {
+            dark
+        }
+    };
+    let outside_font = egui::FontId::proportional(0.3 * cell_size);
+    let inside_font = egui::FontId::proportional(0.2 * cell_size);
+    for col in 0..cols {
+        if let Some(label) = logic.col_label(col) {
+            let rect = layout.cell_rect(bottom_row, col);
+            if labels == CoordinateLabels::Inside {
+                painter.text(
+                    rect.right_bottom() - 0.06 * Vec2::splat(cell_size),
+                    egui::Align2::RIGHT_BOTTOM,
+                    label,
+                    inside_font.clone(),
+                    contrast(bottom_row, col),
+                );
+            } else {
+                painter.text(
+                    rect.center_bottom() + Vec2::new(0.0, 0.25 * cell_size),
+                    egui::Align2::CENTER_CENTER,
+                    label,
+                    outside_font.clone(),
+                    text_color,
+                );
+            }
+        }
+    }
+    for row in 0..rows {
+        if let Some(label) = logic.row_label(row) {
+            let rect = layout.cell_rect(row, left_col);
+            if labels == CoordinateLabels::Inside {
+                painter.text(
+                    rect.left_top() + 0.06 * Vec2::splat(cell_size),
+                    egui::Align2::LEFT_TOP,
+                    label,
+                    inside_font.clone(),
+                    contrast(row, left_col),
+                );
+            } else {
+                painter.text(
+                    rect.left_center() - Vec2::new(0.25 * cell_size, 0.0),
+                    egui::Align2::CENTER_CENTER,
+                    label,
+                    outside_font.clone(),
+                    text_color,
+                );
+            }
+        }
+    }
+}
+
+// The marks and arrows drawn on the board, with the arrow being dragged out if there is one
+fn paint_annotations(
+    painter: &egui::Painter,
+    annotations: Option<&Annotations>,
+    dragging: Option<((usize, usize), (usize, usize))>,
+    layout: &BoardLayout,
+    color: Color32,
+) {
+    let cell_size = layout.cell_size;
+    let centre = |(row, col): (usize, usize)| layout.cell_rect(row, col).center();
+    let marks = annotations.into_iter().flat_map(|a| &a.marks);
+    for &cell in marks {
+        painter.circle_stroke(
+            centre(cell),
+            0.45 * cell_size,
+            Stroke::new(0.07 * cell_size, color),
         );
-        pieces.insert(
-            Piece::WhiteGrasshopper,
-            load(
-                "white_grasshopper",
-                include_bytes!("icons/white grasshopper.png"),
-            ),
+    }
+    let arrows = annotations.into_iter().flat_map(|a| &a.arrows);
+    for &(from, to) in arrows.chain(&dragging) {
+        if from != to {
+            painter.arrow(
+                centre(from),
+                centre(to) - centre(from),
+                Stroke::new(0.12 * cell_size, color),
+            );
+        }
+    }
+}
+
+// A dot on each cell which clicking would play a move on, found by trying a click on every cell
+fn paint_destinations<G: GridGame>(
+    painter: &egui::Painter,
+    logic: &G,
+    state: &G::State,
+    move_selection: &G::MoveSelectionState,
+    layout: &BoardLayout,
+    color: Color32,
+) {
+    let turn = logic.turn(state);
+    for row in 0..logic.rows() {
+        for col in 0..logic.cols() {
+            let mut selection = move_selection.clone();
+            if logic
+                .update_move_selection(
+                    turn,
+                    state,
+                    super::MoveSelectionAction::ClickSquare { row, col },
+                    &mut selection,
+                )
+                .is_some()
+            {
+                painter.circle_filled(
+                    layout.cell_rect(row, col).center(),
+                    0.15 * layout.cell_size,
+                    color,
+                );
+            }
+        }
+    }
+}
+
+// Describe each cell to screen readers, e.g. "e4: white pawn"
+fn label_cells<G: GridGame>(ui: &egui::Ui, logic: &G, state: &G::State, layout: &BoardLayout) {
+    for row in 0..logic.rows() {
+        for col in 0..logic.cols() {
+            let name = match (logic.col_label(col), logic.row_label(row)) {
+                (Some(col_label), Some(row_label)) => format!("{col_label}{row_label}"),
+                _ => format!("Row {}, column {}", row + 1, col + 1),
+            };
+            let contents = logic.piece(state, row, col).name().unwrap_or("empty");
+            // Only hovered so that clicks still reach the board
+            let response = ui.interact(
+                layout.cell_rect(row, col),
+                ui.id().with(("cell", row, col)),
+                egui::Sense::hover(),
+            );
+            response.widget_info(|| {
+                egui::WidgetInfo::labeled(
+                    egui::WidgetType::Label,
+                    true,
+                    format!("{name}: {contents}"),
+                )
+            });
+        }
+    }
+}
+
+/*
+The pieces each player has taken, most valuable first, and how far ahead the first player is in material, for games which count it.
+What's taken is what's missing of each side's pieces since the start, so a promoted pawn shows as taken though the balance is right.
+ */
+fn captured_pieces<G: GridGame>(logic: &G, state: &G::State) -> Option<([Vec<Piece>; 2], i64)> {
+    let initial = logic.initial_state();
+    let mut missing = HashMap::<Piece, i64>::new();
+    let mut balance = 0;
+    let mut counted = false;
+    for row in 0..logic.rows() {
+        for col in 0..logic.cols() {
+            let before = logic.piece(&initial, row, col);
+            if logic.material(before).is_some() {
+                *missing.entry(before).or_default() += 1;
+            }
+            let now = logic.piece(state, row, col);
+            if let Some((player, value)) = logic.material(now) {
+                *missing.entry(now).or_default() -= 1;
+                counted = true;
+                balance += match player {
+                    Player::First => value,
+                    Player::Second => -value,
+                };
+            }
+        }
+    }
+    if !counted {
+        return None;
+    }
+    let mut captured: [Vec<Piece>; 2] = [vec![], vec![]];
+    for (piece, count) in missing {
+        if let Some((owner, _)) = logic.material(piece) {
+            for _ in 0..count {
+                captured[clock_idx(owner.flip())].push(piece);
+            }
+        }
+    }
+    for pieces in &mut captured {
+        pieces.sort_by_key(|piece| {
+            (
+                std::cmp::Reverse(logic.material(*piece).map(|(_, value)| value)),
+                piece.name(),
+            )
+        });
+    }
+    Some((captured, balance))
+}
+
+// Moves are matched to what's typed whatever the spacing, so "e2 -> e4" can be typed as "e2->e4"
+fn without_spaces(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+// Draw the cells of the board and the pieces on them
+#[allow(clippy::too_many_arguments)]
+pub fn paint_board<G: GridGame>(
+    ctx: &egui::Context,
+    painter: &egui::Painter,
+    logic: &G,
+    state: &G::State,
+    layout: &BoardLayout,
+    pieces: &HashMap<Piece, TextureHandle>,
+    palette: &BoardPalette,
+    blindfold: Blindfold,
+) {
+    let rows = logic.rows();
+    let cols = logic.cols();
+    let cell_size = layout.cell_size;
+    let cell_to_rect = |row, col| layout.cell_rect(row, col);
+    let hex = G::CELL_SHAPE == CellShape::Hex;
+
+    let BoardPalette { light, dark, .. } = *palette;
+    let border = Stroke::new(2.0 / ctx.pixels_per_point(), Color32::BLACK);
+
+    // Draw the grid
+    if G::CELL_SHAPE == CellShape::Points {
+        painter.rect_filled(
+            Rect::from_min_size(layout.top_left, layout.size),
+            0.0,
+            light,
         );
+        let line = Stroke::new(0.05 * cell_size, dark);
+        for (a, b) in logic.board_lines() {
+            let (a, b) = (
+                cell_to_rect(a.0, a.1).center(),
+                cell_to_rect(b.0, b.1).center(),
+            );
+            painter.line_segment([a, b], line);
+            painter.circle_filled(a, 0.1 * cell_size, dark);
+            painter.circle_filled(b, 0.1 * cell_size, dark);
+        }
+    }
+    for row in 0..rows {
+        for col in 0..cols {
+            let tint = logic.highlight_square(row, col);
+            if G::CELL_SHAPE == CellShape::Points {
+                if let Some(tint) = tint {
+                    painter.rect_filled(cell_to_rect(row, col), 0.0, tint);
+                }
+                continue;
+            }
+            if hex {
+                painter.add(egui::Shape::convex_polygon(
+                    layout.hexagon(row, col),
+                    light,
+                    Stroke::NONE,
+                ));
+                if let Some(tint) = tint {
+                    painter.add(egui::Shape::convex_polygon(
+                        layout.hexagon(row, col),
+                        tint,
+                        Stroke::NONE,
+                    ));
+                }
+                painter.add(egui::Shape::closed_line(layout.hexagon(row, col), border));
+                continue;
+            }
+            let rect = cell_to_rect(row, col);
+            let color = if (row + col) % 2 == 0 { light } else { dark };
+            painter.rect_filled(rect, 0.0, color);
+            if let Some(tint) = tint {
+                painter.rect_filled(rect, 0.0, tint);
+            }
+            painter.rect_stroke(rect, 0.0, border, egui::StrokeKind::Inside);
+        }
+    }
+
+    // Draw the pieces
+    for row in 0..rows {
+        for col in 0..cols {
+            let piece = logic.piece(state, row, col);
+            if blindfold.shows(piece) {
+                paint_piece(painter, cell_to_rect(row, col), piece, pieces);
+            }
+        }
+    }
+}
 
-        pieces.insert(
-            Piece::BlackPawn,
-            load("black_pawn", include_bytes!("icons/black pawn.png")),
+// Draw `piece` filling `rect`, with its icon if it has one
+pub fn paint_piece(
+    painter: &egui::Painter,
+    rect: Rect,
+    piece: Piece,
+    pieces: &HashMap<Piece, TextureHandle>,
+) {
+    if let Some(tex) = pieces.get(&piece) {
+        painter.image(
+            tex.id(),
+            rect,
+            Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(1.0, 1.0)),
+            Color32::WHITE, // no tint
         );
-        pieces.insert(
-            Piece::BlackBerolinaPawn,
-            load("black_berolina", include_bytes!("icons/black berolina.png")),
+    } else if let Some(color) = piece.disc_color() {
+        painter.circle(
+            rect.center(),
+            0.4 * rect.width(),
+            color,
+            Stroke::new(0.03 * rect.width(), Color32::BLACK),
         );
-        pieces.insert(
-            Piece::BlackRook,
-            load("black_rook", include_bytes!("icons/black rook.png")),
-        );
-        pieces.insert(
-            Piece::BlackKnight,
-            load("black_knight", include_bytes!("icons/black knight.png")),
-        );
-        pieces.insert(
-            Piece::BlackBishop,
-            load("black_bishop", include_bytes!("icons/black bishop.png")),
-        );
-        pieces.insert(
-            Piece::BlackQueen,
-            load("black_queen", include_bytes!("icons/black queen.png")),
-        );
-        pieces.insert(
-            Piece::BlackKing,
-            load("black_king", include_bytes!("icons/black king.png")),
+    } else if let Some((letter, player)) = piece.letter() {
+        let (fill, text) = match player {
+            Player::First => (Color32::from_rgb(240, 240, 240), Color32::BLACK),
+            Player::Second => (Color32::from_rgb(30, 30, 30), Color32::WHITE),
+        };
+        painter.circle(
+            rect.center(),
+            0.4 * rect.width(),
+            fill,
+            Stroke::new(0.03 * rect.width(), Color32::BLACK),
         );
-        pieces.insert(
-            Piece::BlackGrasshopper,
-            load(
-                "black_grasshopper",
-                include_bytes!("icons/black grasshopper.png"),
-            ),
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            letter,
+            egui::FontId::proportional(0.4 * rect.width()),
+            text,
         );
+    } else if piece != Piece::Empty {
+        panic!("No icon for piece {:?}", piece);
+    }
+}
 
-        let game = Game::new(game_logic.clone());
-        let mut ai = A::new();
-        ai.set_game(game.clone());
-        Self {
-            move_selection: game_logic.initial_move_selection(),
-            ai,
-            enable_player1_autoplay: false,
-            player1_autoplay_time: 10,
-            enable_player2_autoplay: false,
-            player2_autoplay_time: 10,
-            thinking_start_time: chrono::Utc::now(),
-            game,
-            pieces,
-            main_menu_prompt: false,
+/*
+The AI's lines as a table of how far ahead each was searched, its score, and its moves as written in the move list.
+Hovering over a line shows it on the board, and clicking it gives its first move to play.
+ */
+fn show_lines<G: GridGame>(
+    ui: &mut egui::Ui,
+    id_salt: &str,
+    formatter: &MoveFormatter<G>,
+    state: &G::State,
+    lines: &[Line<G::Move>],
+    shown_lines: &mut Vec<Vec<G::Move>>,
+) -> Option<G::Move> {
+    if lines.is_empty() {
+        return None;
+    }
+    let mut clicked = None;
+    egui::Grid::new(id_salt)
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            for heading in ["Depth", "Score", "Line"] {
+                ui.label(heading);
+            }
+            ui.end_row();
+            for line in lines {
+                ui.label(
+                    line.depth
+                        .map(|depth| depth.to_string())
+                        .unwrap_or_default(),
+                );
+                ui.label(line.score.clone().unwrap_or_default());
+                if line.moves.is_empty() {
+                    ui.label(line.note.clone().unwrap_or_default());
+                } else {
+                    let notation = formatter.write_line(state, &line.moves).join(" ");
+                    let mut button = ui.add(egui::Button::new(notation).truncate());
+                    if let Some(note) = &line.note {
+                        button = button.on_hover_text(note);
+                    }
+                    if button.hovered() {
+                        shown_lines.push(line.moves.clone());
+                    }
+                    if button.clicked() {
+                        clicked = line.moves.first().cloned();
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    clicked
+}
+
+/*
+One level of the search tree explorer: the moves from `state`, which `line` leads to, with what the AI remembers of the positions they lead to.
+Each is shown with the score for the player to move after it, and opens up to show the moves from there in turn.
+The moves are looked up in the AI's transposition table, so the tree is as much of the search as is still remembered, and deeper levels may come from other searches.
+ */
+fn show_search_tree<G: GridGame, A: Ai<G>>(
+    ui: &mut egui::Ui,
+    ai: &A,
+    logic: &G,
+    state: &mut G::State,
+    line: &mut Vec<G::Move>,
+    shown_lines: &mut Vec<Vec<G::Move>>,
+) {
+    let best_move = ai
+        .searched_position(state)
+        .and_then(|searched| searched.best_move);
+    let mut children = vec![];
+    let mut unsearched = 0;
+    for mv in logic.generate_moves(state) {
+        logic.make_move(state, &mv);
+        let searched = ai.searched_position(state);
+        let player = logic.turn(state);
+        logic.unmake_move(state, &mv);
+        match searched {
+            Some(searched) => children.push((mv, player, searched)),
+            None => unsearched += 1,
         }
     }
+    // The best move first, then the most deeply searched
+    children.sort_by_key(|(mv, _, searched)| {
+        (
+            Some(mv) != best_move.as_ref(),
+            std::cmp::Reverse(searched.depth),
+        )
+    });
 
-    fn make_move(&mut self, mv: G::Move) {
-        self.game.make_move(mv);
-        self.move_selection = self.game.logic().initial_move_selection();
-        self.ai.set_game(self.game.clone());
-        self.thinking_start_time = chrono::Utc::now();
+    for (mv, player, searched) in children {
+        let bound = match searched.bound {
+            Bound::Exact => "",
+            Bound::Lower => "at least ",
+            Bound::Upper => "at most ",
+        };
+        let text = format!(
+            "{}{mv}: {} {bound}{}, depth {}",
+            if Some(&mv) == best_move.as_ref() {
+                "★ "
+            } else {
+                ""
+            },
+            logic.player_name(player),
+            searched.score,
+            searched.depth
+        );
+        line.push(mv.clone());
+        logic.make_move(state, &mv);
+        let id = line
+            .iter()
+            .map(|mv| mv.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let response = egui::CollapsingHeader::new(text)
+            .id_salt(("search_tree", id))
+            .show(ui, |ui| {
+                show_search_tree(ui, ai, logic, state, line, shown_lines)
+            });
+        if response.header_response.hovered() {
+            shown_lines.push(line.clone());
+        }
+        logic.unmake_move(state, &mv);
+        line.pop();
+    }
+    if unsearched > 0 {
+        ui.label(format!("{unsearched} moves not remembered"));
     }
+}
 
-    fn undo_move(&mut self) {
-        self.game.undo_move();
-        self.move_selection = self.game.logic().initial_move_selection();
-        self.ai.set_game(self.game.clone());
-        self.thinking_start_time = chrono::Utc::now();
+// What the result of a finished game was, and why if known, or None if it isn't finished
+pub fn describe_result<G: GridGame>(game: &Game<G>) -> Option<(String, Option<String>)> {
+    let logic = game.logic();
+    let player_name = |player| logic.player_name(player);
+    let rules_reason = || {
+        if game.is_threefold_repetition() {
+            Some("Threefold repetition".to_string())
+        } else {
+            logic
+                .end_reason(&mut game.state().clone())
+                .map(|reason| reason.to_string())
+        }
+    };
+    Some(match game.result()? {
+        GameResult::Won(player) => (format!("{} Wins", player_name(player)), rules_reason()),
+        GameResult::Drawn => ("Draw".to_string(), rules_reason()),
+        GameResult::Resigned(player) => (
+            format!("{} Wins", player_name(player.flip())),
+            Some(format!("{} resigned", player_name(player))),
+        ),
+        GameResult::AgreedDraw => ("Draw".to_string(), Some("Draw agreed".to_string())),
+        GameResult::Flagged(player) => (
+            format!("{} Wins", player_name(player.flip())),
+            Some(format!("{} ran out of time", player_name(player))),
+        ),
+    })
+}
+
+fn clock_idx(player: Player) -> usize {
+    match player {
+        Player::First => 0,
+        Player::Second => 1,
+    }
+}
+
+fn format_clock(time: chrono::TimeDelta) -> String {
+    if time <= chrono::TimeDelta::zero() {
+        "0:00 (out of time)".to_string()
+    } else {
+        let seconds = time.num_seconds();
+        format!("{}:{:02}", seconds / 60, seconds % 60)
     }
 }
 
 impl<G: GridGame, A: Ai<G>> AppState for State<G, A> {
+    fn restore(&mut self, remembered: &Remembered) {
+        let options = &remembered.game;
+        self.strength = Strength::LEVELS
+            .into_iter()
+            .find(|strength| strength.name == options.strength)
+            .unwrap_or(Strength::FULL);
+        self.multi_pv = options.multi_pv.clamp(1, 5);
+        self.ponder = options.ponder;
+        self.coordinate_labels = options.coordinate_labels;
+        self.blindfold = options.blindfold;
+        self.notation = options.notation;
+        self.sound = options.sound && crate::sound::AVAILABLE;
+        self.highlight_last_move = options.highlight_last_move;
+        self.show_destinations = options.show_destinations;
+        self.confirm_moves = options.confirm_moves;
+        // A game over the network keeps to the clocks the host set
+        if !self.is_networked() {
+            self.clock_minutes = options.clock_minutes;
+            self.clock_increment = options.clock_increment;
+        }
+        self.screenshot_size = options.screenshot_size;
+        self.ai.set_strength(self.strength);
+        self.ai.set_multi_pv(self.multi_pv);
+        self.ai.set_game(self.shown_game());
+        self.start_thinking();
+    }
+
+    fn remember(&self, remembered: &mut Remembered) {
+        remembered.game = GameOptions {
+            strength: self.strength.name.to_string(),
+            multi_pv: self.multi_pv,
+            ponder: self.ponder,
+            coordinate_labels: self.coordinate_labels,
+            blindfold: self.blindfold,
+            notation: self.notation,
+            sound: self.sound,
+            highlight_last_move: self.highlight_last_move,
+            show_destinations: self.show_destinations,
+            confirm_moves: self.confirm_moves,
+            clock_minutes: self.clock_minutes,
+            clock_increment: self.clock_increment,
+            screenshot_size: self.screenshot_size,
+        };
+    }
+
     fn update(
         &mut self,
         ctx: &egui::Context,
         frame: &mut eframe::Frame,
+        appearance: &Appearance,
     ) -> std::option::Option<std::boxed::Box<dyn crate::root::AppState + 'static>> {
         let mut change_state: Option<Box<dyn crate::root::AppState>> = None;
+        self.pieces.choose(ctx, appearance.pieces);
 
-        self.ai.think(chrono::TimeDelta::milliseconds(10));
+        if !self.paused && self.ai_may_think() {
+            self.ai.think(chrono::TimeDelta::milliseconds(10));
+        }
 
-        if let Some(mv) = self.game.logic().update_move_selection_ui(
-            self.game.turn(),
-            self.game.state(),
-            &self.move_selection,
-            ctx,
-            frame,
-        ) {
+        if !self.is_remote(self.game.turn())
+            && let Some(mv) = self.game.logic().update_move_selection_ui(
+                self.game.turn(),
+                self.game.state(),
+                &self.move_selection,
+                ctx,
+                frame,
+            )
+        {
             self.make_move(mv);
         }
 
+        self.receive_remote();
+        self.show_spectators();
+        self.receive_spectating();
+        self.receive_events();
+        self.announce_moves();
+        self.show_link();
+        self.take_hint();
+
+        // The player to move loses once their clock runs out, which the other player over the network says for themselves
+        if let Some(clocks) = &self.clocks
+            && !self.game.is_finished()
+            && !self.is_remote(self.game.turn())
+        {
+            let turn = self.game.turn();
+            let remaining =
+                clocks[clock_idx(turn)].remaining() - (chrono::Utc::now() - self.turn_start_time);
+            if remaining <= chrono::TimeDelta::zero() {
+                self.game.flag(turn);
+                self.ai.stop_thinking();
+                self.send(Message::OutOfTime);
+            }
+        }
+
+        // Dice are rolled and cards drawn as soon as they come up, by the host in a game over the network
+        let seed = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+        if self.remote.as_ref().is_none_or(|remote| remote.hosting)
+            && self.spectating.is_none()
+            && self.game.play_chance(mix64(seed) as f64 / u64::MAX as f64)
+        {
+            self.move_selection = self.game.logic().initial_move_selection();
+            self.ai.set_game(self.shown_game());
+            self.start_thinking();
+            if self.remote.is_some()
+                && let Some(outcome) = self.game.move_history().last()
+            {
+                let mv = self.game.logic().move_name(outcome);
+                self.send(Message::Move {
+                    mv,
+                    remaining: None,
+                });
+            }
+        }
+
+        self.navigate_with_keys(ctx);
+
+        #[cfg(target_arch = "wasm32")]
+        if let Some(text) = self
+            .upload
+            .as_ref()
+            .and_then(|upload| upload.try_recv().ok())
+        {
+            self.upload = None;
+            self.load_game(&text);
+        }
+
         let mut move_to_make = None;
-        let best_moves = self.ai.best_moves();
-        let mut show_best_moves = vec![false; best_moves.len()];
+        let best_moves = self.best_moves.clone();
+        let candidate_lines = self.ai.candidate_lines();
+        // The lines being hovered over, to draw on the board
+        let mut shown_lines = vec![];
+
+        if !self.game.is_finished() {
+            self.result_dismissed = false;
+        } else if !self.result_dismissed
+            && let Some((headline, reason)) = describe_result(&self.game)
+        {
+            egui::Window::new("Game Over")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.heading(headline);
+                    if let Some(reason) = reason {
+                        ui.label(reason);
+                    }
+                    if ui.button("Close").clicked() {
+                        self.result_dismissed = true;
+                    }
+                });
+        }
 
         if self.main_menu_prompt {
             egui::Window::new("Go to Main Menu?")
@@ -173,6 +2263,7 @@ impl<G: GridGame, A: Ai<G>> AppState for State<G, A> {
 
                     ui.horizontal(|ui| {
                         if ui.button("Yes, I'm sure").clicked() {
+                            self.send(Message::Bye { reason: None });
                             change_state = Some(Box::new(crate::menu::State::default()))
                         }
 
@@ -183,7 +2274,9 @@ impl<G: GridGame, A: Ai<G>> AppState for State<G, A> {
                 });
         }
 
-        egui::SidePanel::left("left panel").show(ctx, |ui| {
+        let compact = appearance.layout.is_compact(ctx);
+        let mut sheet_open = self.sheet_open;
+        let mut controls = |ui: &mut egui::Ui| {
             if ui.button("Menu").clicked() {
                 self.main_menu_prompt = true;
             }
@@ -193,154 +2286,701 @@ impl<G: GridGame, A: Ai<G>> AppState for State<G, A> {
             ui.heading("Game");
             ui.label(format!("Move {}", self.game.num_moves() + 1));
 
-            match self.game.logic().score(&mut self.game.state().clone()) {
-                crate::game::AbsScore::SecondPlayerWin => {
-                    ui.label("Black Wins");
+            let logic = self.game.logic().clone();
+            let player_name = |player| logic.player_name(player);
+            match describe_result(&self.game) {
+                Some((headline, reason)) => {
+                    ui.label(headline);
+                    if let Some(reason) = reason {
+                        ui.label(reason);
+                    }
+                }
+                None => {
+                    ui.label(format!("{}'s Turn", player_name(self.game.turn())));
                 }
-                crate::game::AbsScore::Draw => {
-                    ui.label("Draw");
+            }
+
+            self.game.logic().show_state_info(self.game.state(), ui);
+
+            if let Some((captured, balance)) = captured_pieces(&logic, self.shown_game().state()) {
+                for player in [Player::First, Player::Second] {
+                    ui.horizontal(|ui| {
+                        ui.label(player_name(player));
+                        ui.spacing_mut().item_spacing.x = 0.0;
+                        for piece in &captured[clock_idx(player)] {
+                            let (rect, _) =
+                                ui.allocate_exact_size(Vec2::splat(18.0), egui::Sense::hover());
+                            paint_piece(ui.painter(), rect, *piece, self.pieces.textures());
+                        }
+                        let lead = match player {
+                            Player::First => balance,
+                            Player::Second => -balance,
+                        };
+                        if lead > 0 {
+                            ui.label(format!(" +{lead}"));
+                        }
+                    });
                 }
-                crate::game::AbsScore::FirstPlayerWin => {
-                    ui.label("White Wins");
+            }
+
+            let terms = logic.explain_score(&mut self.game.state().clone());
+            if self.mode == Mode::Analysis && !terms.is_empty() {
+                egui::CollapsingHeader::new("Evaluation").show(ui, |ui| {
+                    egui::Grid::new("evaluation_grid")
+                        .num_columns(2)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (name, value) in terms {
+                                ui.label(name);
+                                ui.label(format!("{value:?}"));
+                                ui.end_row();
+                            }
+                            if let crate::game::AbsScore::Heuristic(total) = self.game.score() {
+                                ui.strong("Total");
+                                ui.strong(format!("{total:?}"));
+                                ui.end_row();
+                            }
+                        });
+                    ui.label(format!(
+                        "Positive is good for {}",
+                        player_name(crate::game::Player::First)
+                    ));
+                });
+            }
+
+            if !self.game.is_finished() {
+                match self.game.draw_offer() {
+                    // An AI answers when it would move, by accepting or by moving, which declines
+                    Some(offerer) if self.autoplay(offerer.flip()) => {
+                        ui.label(format!(
+                            "{} Offers a Draw, {} is Thinking it Over",
+                            player_name(offerer),
+                            player_name(offerer.flip())
+                        ));
+                    }
+                    Some(offerer) if self.is_remote(offerer.flip()) => {
+                        ui.label(format!(
+                            "{} Offers a Draw, Waiting for {} to Answer",
+                            player_name(offerer),
+                            player_name(offerer.flip())
+                        ));
+                    }
+                    Some(offerer) => {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{} Offers a Draw", player_name(offerer)));
+                            if ui.button("Accept").clicked() {
+                                self.game.accept_draw();
+                                self.ai.stop_thinking();
+                                self.send(Message::AcceptDraw);
+                            }
+                            if ui.button("Decline").clicked() {
+                                self.game.decline_draw();
+                                self.send(Message::DeclineDraw);
+                            }
+                        });
+                    }
+                    None => {}
                 }
-                crate::game::AbsScore::Heuristic(_) => match self.game.turn() {
-                    crate::game::Player::First => {
-                        ui.label("White's Turn");
+            }
+            // Over the network the player here can resign or offer a draw whoever's turn it is
+            let resigner = match &self.remote {
+                Some(remote) => Some(remote.player.flip()),
+                None => self.person_to_move().then(|| self.game.turn()),
+            };
+            if !self.game.is_finished()
+                && let Some(player) = resigner
+            {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(format!("Resign as {}", player_name(player)))
+                        .clicked()
+                    {
+                        self.game.resign(player);
+                        self.ai.stop_thinking();
+                        self.send(Message::Resign);
                     }
-                    crate::game::Player::Second => {
-                        ui.label("Black's Turn");
+                    if self.game.draw_offer().is_none() && ui.button("Offer Draw").clicked() {
+                        self.game.offer_draw(player);
+                        self.send(Message::OfferDraw);
                     }
-                },
+                });
             }
 
-            if self.game.can_undo_move() && ui.button("Undo").clicked() {
-                self.undo_move();
+            // Moves can't be taken back over the network
+            if !self.is_networked() {
+                ui.horizontal(|ui| {
+                    if self.game.can_undo_move() && ui.button("Undo").clicked() {
+                        self.undo_move();
+                    }
+                    if self.game.can_redo_move() && ui.button("Redo").clicked() {
+                        self.redo_move();
+                    }
+                });
+            }
+            ui.checkbox(&mut self.flip_board, "Flip board");
+            ui.horizontal(|ui| {
+                ui.label("Blindfold");
+                let logic = self.game.logic();
+                let name = |blindfold| match blindfold {
+                    Blindfold::Off => "Off".to_string(),
+                    Blindfold::All => "All pieces hidden".to_string(),
+                    Blindfold::Only(player) => format!("Only {} shown", logic.player_name(player)),
+                };
+                egui::ComboBox::from_id_salt("blindfold")
+                    .selected_text(name(self.blindfold))
+                    .show_ui(ui, |ui| {
+                        for blindfold in [
+                            Blindfold::Off,
+                            Blindfold::All,
+                            Blindfold::Only(Player::First),
+                            Blindfold::Only(Player::Second),
+                        ] {
+                            ui.selectable_value(&mut self.blindfold, blindfold, name(blindfold));
+                        }
+                    });
+            });
+            ui.checkbox(&mut self.highlight_last_move, "Highlight last move");
+            ui.checkbox(&mut self.show_destinations, "Show legal moves");
+            ui.checkbox(&mut self.confirm_moves, "Confirm moves")
+                .on_hover_text(
+                    "Moves are played once confirmed, or by clicking the same square again",
+                );
+            ui.horizontal(|ui| {
+                ui.label("Coordinates");
+                for (labels, text) in [
+                    (CoordinateLabels::Hidden, "Hidden"),
+                    (CoordinateLabels::Outside, "Outside"),
+                    (CoordinateLabels::Inside, "Inside"),
+                ] {
+                    ui.selectable_value(&mut self.coordinate_labels, labels, text);
+                }
+            });
+            let styles = self.game.logic().notation_styles();
+            if styles.len() > 1 {
+                ui.horizontal(|ui| {
+                    ui.label("Notation");
+                    egui::ComboBox::from_id_salt("notation_style")
+                        .selected_text(self.formatter().style().name())
+                        .show_ui(ui, |ui| {
+                            for style in styles {
+                                ui.selectable_value(&mut self.notation, *style, style.name());
+                            }
+                        });
+                });
+            }
+            if crate::sound::AVAILABLE {
+                ui.checkbox(&mut self.sound, "Sound");
             }
 
-            ui.separator();
-            ui.heading("AI");
+            // Screen readers announce this whenever it changes
+            let response = ui.label(&self.announcement);
+            ctx.accesskit_node_builder(response.id, |node| {
+                node.set_live(egui::accesskit::Live::Polite);
+            });
+
+            if let Some(mv) = self.staged_move() {
+                let notation = self.formatter().write(self.game.state(), mv);
+                ui.horizontal(|ui| {
+                    ui.label(format!("Play {notation}?"));
+                    if ui.button("Confirm").clicked() {
+                        self.confirm_move();
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.staged = None;
+                    }
+                });
+            }
 
-            egui::Grid::new("autoplay_grid")
-                .num_columns(2)
-                .spacing([20.0, 4.0])
+            egui::CollapsingHeader::new("Moves")
+                .default_open(true)
                 .show(ui, |ui| {
-                    ui.checkbox(&mut self.enable_player1_autoplay, "White Autoplay");
-                    ui.add_enabled(
-                        self.enable_player1_autoplay,
-                        egui::Slider::new(&mut self.player1_autoplay_time, 1..=60).text("seconds"),
+                    self.show_move_list(ui);
+                    self.show_move_entry(ui);
+                });
+
+            ui.horizontal(|ui| {
+                if ui.button("Copy Game Record").clicked()
+                    && let Some((_, record)) =
+                        self.game.logic().export_game(&self.game, self.notation)
+                {
+                    ctx.copy_text(record);
+                }
+                if ui.button("Save Game Record").clicked()
+                    && let Some((extension, record)) =
+                        self.game.logic().export_game(&self.game, self.notation)
+                {
+                    crate::files::save_text(&format!("game.{extension}"), extension, &record);
+                }
+            });
+            ui.horizontal(|ui| {
+                if let Some(render_state) = &frame.wgpu_render_state {
+                    if ui.button("Save Screenshot").clicked() {
+                        self.save_screenshot(ctx, render_state, appearance);
+                    }
+                } else {
+                    ui.add_enabled(false, egui::Button::new("Save Screenshot"))
+                        .on_disabled_hover_text("Requires wgpu.");
+                }
+                ui.add(
+                    egui::DragValue::new(&mut self.screenshot_size)
+                        .range(64..=4096)
+                        .suffix(" px"),
+                );
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Save Game").clicked() {
+                    self.save_game();
+                }
+                if !self.is_networked() && ui.button("Load Game").clicked() {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(text) = crate::files::open_text("ron") {
+                        self.load_game(&text);
+                    }
+                    #[cfg(target_arch = "wasm32")]
+                    {
+                        self.upload = Some(crate::files::upload_text("ron"));
+                    }
+                }
+            });
+            if let Some(address) = self
+                .link
+                .as_ref()
+                .and_then(|shared| shared.address.as_ref())
+                && ui
+                    .button("Copy Link")
+                    .on_hover_text("A link to the game as it stands, to share or come back to.")
+                    .clicked()
+            {
+                ctx.copy_text(address.clone());
+            }
+            if let Some(error) = &self.game_load_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+
+            // Spectators can't play, but can have the AI look at the game as it goes
+            if let Some(spectating) = &self.spectating {
+                ui.separator();
+                ui.heading("Network");
+                ui.label(format!(
+                    "Watching {} against {} over the network",
+                    player_name(Player::First),
+                    player_name(Player::Second)
+                ));
+                if let Some(lost) = &spectating.lost {
+                    ui.colored_label(ui.visuals().error_fg_color, lost);
+                } else if spectating.connection.reconnecting() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Lost the connection, reconnecting");
+                    });
+                }
+                self.show_clocks(ui);
+
+                let mut analysing = self.mode == Mode::Analysis;
+                if ui.checkbox(&mut analysing, "Analyse").changed() {
+                    self.mode = if analysing {
+                        Mode::Analysis
+                    } else {
+                        Mode::Play
+                    };
+                    self.ai.set_game(self.shown_game());
+                    self.start_thinking();
+                }
+                if analysing {
+                    if let Some(depth) = self.search_depth {
+                        ui.label(format!("Depth {depth}, {} positions", self.search_nodes));
+                    }
+                    let logic = self.game.logic().clone();
+                    let formatter = MoveFormatter::new(&logic, self.notation);
+                    let state = self.shown_game().state().clone();
+                    // Only for looking at, as the moves are the players' to make
+                    let _ = show_lines(
+                        ui,
+                        "best_lines",
+                        &formatter,
+                        &state,
+                        &best_moves,
+                        &mut shown_lines,
                     );
-                    ui.end_row();
+                }
+                return;
+            }
+
+            // The AI keeps out of games against someone over the network
+            if let Some(remote) = &self.remote {
+                ui.separator();
+                ui.heading("Network");
+                ui.label(format!(
+                    "Playing {} against {} over the network",
+                    player_name(remote.player.flip()),
+                    player_name(remote.player)
+                ));
+                if let Some(lost) = &remote.lost {
+                    ui.colored_label(ui.visuals().error_fg_color, lost);
+                    ui.label("The game can't go on.");
+                } else if remote.connection.reconnecting() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Lost the connection, reconnecting");
+                    });
+                } else if remote.away {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!(
+                            "{} lost their connection, waiting for them to come back",
+                            player_name(remote.player)
+                        ));
+                    });
+                }
+                self.show_clocks(ui);
+                return;
+            }
+
+            ui.separator();
+            ui.heading("AI");
 
-                    ui.checkbox(&mut self.enable_player2_autoplay, "Black Autoplay");
-                    ui.add_enabled(
-                        self.enable_player2_autoplay,
-                        egui::Slider::new(&mut self.player2_autoplay_time, 1..=60).text("seconds"),
+            let previous_mode = self.mode;
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.mode, Mode::Analysis, "Analysis")
+                    .on_hover_text(
+                        "Moves are made for either side while the AI looks at the board",
                     );
-                    ui.end_row();
+                ui.selectable_value(&mut self.mode, Mode::Play, "Play")
+                    .on_hover_text("The AI only thinks when it's to move, and gives no hints");
+            });
+            if self.mode != previous_mode {
+                self.ai.set_game(self.shown_game());
+                self.start_thinking();
+            }
+
+            // Lines and scores are only shown when analysing, so in a game the AI's help is asked for
+            if self.mode == Mode::Play
+                && self.viewing.is_none()
+                && !self.game.is_finished()
+                && self.person_to_move()
+            {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(self.hint_requested.is_none(), egui::Button::new("Hint"))
+                        .clicked()
+                    {
+                        self.ask_for_hint();
+                    }
+                    if self.hint_requested.is_some() {
+                        ui.spinner();
+                    }
+                    if let Some(mv) = self.current_hint() {
+                        ui.label(self.formatter().write(self.game.state(), mv));
+                        if ui.button("Play Hint").clicked() {
+                            move_to_make = Some(mv.clone());
+                        }
+                    }
+                });
+            }
+
+            ui.horizontal(|ui| {
+                let pause_label = if self.paused { "Resume" } else { "Pause" };
+                if ui.button(pause_label).clicked() {
+                    self.paused = !self.paused;
+                }
+                if ui.button("Stop").clicked() {
+                    self.ai.stop_thinking();
+                }
+            });
+
+            if self.mode == Mode::Play {
+                let autoplay_changed = egui::Grid::new("autoplay_grid")
+                    .num_columns(2)
+                    .spacing([20.0, 4.0])
+                    .show(ui, |ui| {
+                        let mut changed = ui
+                            .checkbox(
+                                &mut self.enable_player1_autoplay,
+                                format!("{} Autoplay", player_name(crate::game::Player::First)),
+                            )
+                            .changed();
+                        changed |= ui
+                            .add_enabled(
+                                self.enable_player1_autoplay,
+                                egui::Slider::new(&mut self.player1_autoplay_time, 1..=60)
+                                    .text("seconds"),
+                            )
+                            .changed();
+                        ui.end_row();
+
+                        changed |= ui
+                            .checkbox(
+                                &mut self.enable_player2_autoplay,
+                                format!("{} Autoplay", player_name(crate::game::Player::Second)),
+                            )
+                            .changed();
+                        changed |= ui
+                            .add_enabled(
+                                self.enable_player2_autoplay,
+                                egui::Slider::new(&mut self.player2_autoplay_time, 1..=60)
+                                    .text("seconds"),
+                            )
+                            .changed();
+                        ui.end_row();
+                        changed
+                    })
+                    .inner;
+                if autoplay_changed {
+                    // Someone playing only the second player wants their own side at the bottom
+                    self.flip_board = self.enable_player1_autoplay && !self.enable_player2_autoplay;
+                    self.start_thinking();
+                }
+                ui.checkbox(&mut self.ponder, "Ponder on the opponent's time");
+            }
+
+            let previous_strength = self.strength;
+            egui::ComboBox::from_label("Strength")
+                .selected_text(self.strength.name)
+                .show_ui(ui, |ui| {
+                    for strength in Strength::LEVELS {
+                        ui.selectable_value(&mut self.strength, strength, strength.name);
+                    }
                 });
+            if self.strength != previous_strength {
+                self.ai.set_strength(self.strength);
+                self.ai.set_game(self.shown_game());
+                self.start_thinking();
+            }
+
+            ui.horizontal(|ui| {
+                let mut enable_clock = self.clocks.is_some();
+                let mut changed = ui.checkbox(&mut enable_clock, "Clock").changed();
+                changed |= ui
+                    .add(egui::Slider::new(&mut self.clock_minutes, 1..=60).text("minutes"))
+                    .changed();
+                changed |= ui
+                    .add(egui::Slider::new(&mut self.clock_increment, 0..=30).text("increment"))
+                    .changed();
+                if changed {
+                    self.reset_clocks(enable_clock);
+                    self.start_thinking();
+                }
+            });
+            self.show_clocks(ui);
 
             ui.add_space(20.0);
 
             // Autoplay
-            let enable_autoplay = match self.game.turn() {
-                crate::game::Player::First => self.enable_player1_autoplay,
-                crate::game::Player::Second => self.enable_player2_autoplay,
-            };
-            if enable_autoplay && !self.game.is_finished() {
-                let thinking_time = match self.game.turn() {
-                    crate::game::Player::First => self.player1_autoplay_time,
-                    crate::game::Player::Second => self.player2_autoplay_time,
-                } as f32;
-
+            if self.viewing.is_none() && self.autoplay(self.game.turn()) && !self.game.is_finished()
+            {
                 let thinking_progress = chrono::Utc::now()
                     .signed_duration_since(self.thinking_start_time)
                     .as_seconds_f32()
-                    / thinking_time;
+                    / self.move_time().as_seconds_f32().max(0.001);
 
                 ui.add(
                     egui::ProgressBar::new(thinking_progress)
                         .text(format!("{:.0}%", thinking_progress * 100.0)),
                 );
 
-                if thinking_progress >= 1.0
-                    && let Some((_, mv)) = self.ai.best_move()
-                {
-                    move_to_make = Some(mv);
+                if thinking_progress >= 1.0 && !self.paused {
+                    if self.game.draw_offer() == Some(self.game.turn().flip())
+                        && self.ai.accepts_draw()
+                    {
+                        self.game.accept_draw();
+                        self.ai.stop_thinking();
+                    } else if let Some(mv) = self.ai.best_move() {
+                        move_to_make = Some(mv);
+                    }
                 }
             }
 
-            for (idx, (label, best_move)) in best_moves.iter().enumerate() {
-                let button = ui.button(label);
-                if button.hovered() {
-                    show_best_moves[idx] = true;
+            if self.mode == Mode::Analysis {
+                if let Some(depth) = self.search_depth {
+                    ui.label(format!(
+                        "Depth {depth}, {} positions{}",
+                        self.search_nodes,
+                        if self.search_finished {
+                            ", finished"
+                        } else {
+                            ""
+                        }
+                    ));
                 }
-                if button.clicked() {
-                    move_to_make = Some(best_move.clone());
+                let logic = self.game.logic().clone();
+                let formatter = MoveFormatter::new(&logic, self.notation);
+                let state = self.shown_game().state().clone();
+                if let Some(mv) = show_lines(
+                    ui,
+                    "best_lines",
+                    &formatter,
+                    &state,
+                    &best_moves,
+                    &mut shown_lines,
+                ) {
+                    move_to_make = Some(mv);
                 }
-            }
-        });
-
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // Reserve the available space
-            let avail = ui.available_rect_before_wrap();
-            let avail_size = avail.size();
-
-            // Compute size of one cell: must be square, so use the smaller dimension
-            let cell_size = (avail_size.x / (G::COLS as f32)).min(avail_size.y / (G::ROWS as f32));
-
-            // Compute total board size and center it in the available rect
-            let board_size = Vec2::new(cell_size * (G::COLS as f32), cell_size * (G::ROWS as f32));
-            let board_top_left = Pos2::new(
-                avail.left() + (avail_size.x - board_size.x) / 2.0,
-                avail.top() + (avail_size.y - board_size.y) / 2.0,
-            );
 
-            let cell_to_rect = |row, col| {
-                let x = board_top_left.x + (col as f32) * cell_size;
-                let y = board_top_left.y + (row as f32) * cell_size;
+                ui.separator();
+                if ui
+                    .add(egui::Slider::new(&mut self.multi_pv, 1..=5).text("candidate lines"))
+                    .changed()
+                {
+                    self.ai.set_multi_pv(self.multi_pv);
+                    self.ai.set_game(self.shown_game());
+                    self.start_thinking();
+                }
+                if let Some(mv) = show_lines(
+                    ui,
+                    "candidate_lines",
+                    &formatter,
+                    &state,
+                    &candidate_lines,
+                    &mut shown_lines,
+                ) {
+                    move_to_make = Some(mv);
+                }
 
-                Rect::from_min_size(Pos2::new(x, y), Vec2::new(cell_size, cell_size))
-            };
+                ui.separator();
+                egui::CollapsingHeader::new("Engine stats").show(ui, |ui| {
+                    egui::Grid::new("engine_stats_grid")
+                        .num_columns(6)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for heading in
+                                ["Depth", "kN/s", "TT hits", "Cutoffs", "Quiescence", "EBF"]
+                            {
+                                ui.label(heading);
+                            }
+                            ui.end_row();
+                            for stats in self.ai.statistics() {
+                                ui.label(format!("{}", stats.depth));
+                                ui.label(format!("{:.0}", stats.nodes_per_second / 1000.0));
+                                ui.label(format!("{:.0}%", stats.tt_hit_rate * 100.0));
+                                ui.label(format!("{:.0}%", stats.cutoff_rate * 100.0));
+                                ui.label(format!("{:.0}%", stats.quiescence_share * 100.0));
+                                ui.label(match stats.branching_factor {
+                                    Some(branching_factor) => format!("{branching_factor:.1}"),
+                                    None => "-".to_string(),
+                                });
+                                ui.end_row();
+                            }
+                        });
+                });
 
-            let painter = ui.painter();
+                egui::CollapsingHeader::new("Search tree").show(ui, |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(300.0)
+                        .show(ui, |ui| {
+                            show_search_tree(
+                                ui,
+                                &self.ai,
+                                self.game.logic(),
+                                &mut self.shown_game().state().clone(),
+                                &mut vec![],
+                                &mut shown_lines,
+                            );
+                        });
+                });
+            }
 
-            // Define the colours of the squares
-            let light = Color32::from_rgb(240, 217, 181); // light square
-            let dark = Color32::from_rgb(181, 136, 99); // dark square
-            let border = Stroke::new(2.0 / ctx.pixels_per_point(), Color32::BLACK);
-
-            // Draw the grid
-            for row in 0..G::ROWS {
-                for col in 0..G::COLS {
-                    let rect = cell_to_rect(row, col);
-                    let color = if (row + col) % 2 == 0 { light } else { dark };
-                    painter.rect_filled(rect, 0.0, color);
-                    painter.rect_stroke(rect, 0.0, border, egui::StrokeKind::Inside);
-                }
-            }
-
-            // Draw the pieces
-            let draw_piece = |row: usize, col: usize, piece: Piece| {
-                if let Some(tex) = self.pieces.get(&piece) {
-                    let rect = cell_to_rect(row, col);
-                    painter.image(
-                        tex.id(),
-                        rect,
-                        Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(1.0, 1.0)),
-                        Color32::WHITE, // no tint
-                    );
-                } else if piece != Piece::Empty {
-                    panic!("No icon for piece {:?}", piece);
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let extensions = self.game.logic().evaluator_extensions();
+                if !extensions.is_empty() {
+                    ui.separator();
+                    if ui.button("Load Evaluation").clicked()
+                        && let Some(bytes) = crate::files::open_bytes(extensions)
+                    {
+                        match self.game.logic().load_evaluator(&bytes) {
+                            Ok(evaluator) => {
+                                self.load_error = None;
+                                self.ai.set_evaluator(evaluator);
+                                self.ai.set_game(self.shown_game());
+                                self.start_thinking();
+                            }
+                            Err(error) => self.load_error = Some(error),
+                        }
+                    }
                 }
-            };
-            for row in 0..G::ROWS {
-                for col in 0..G::COLS {
-                    draw_piece(
-                        row,
-                        col,
-                        self.game.logic().piece(self.game.state(), row, col),
-                    );
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Save Learning").clicked()
+                        && let Some(text) = self.ai.export_learning()
+                    {
+                        crate::files::save_text("learning.txt", "txt", &text);
+                    }
+                    if ui.button("Load Learning").clicked()
+                        && let Some(text) = crate::files::open_text("txt")
+                    {
+                        match self.ai.import_learning(&text) {
+                            Ok(()) => {
+                                self.load_error = None;
+                                self.ai.set_game(self.shown_game());
+                                self.start_thinking();
+                            }
+                            Err(error) => self.load_error = Some(error),
+                        }
+                    }
+                });
+            }
+            if let Some(error) = &self.load_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        };
+        if compact {
+            // Below the board on phones, pulled up when wanted so the board can be as big as the screen is wide
+            egui::TopBottomPanel::bottom("bottom sheet").show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    let text = if sheet_open {
+                        "▼ Hide"
+                    } else {
+                        "▲ Controls"
+                    };
+                    if ui.button(text).clicked() {
+                        sheet_open = !sheet_open;
+                    }
+                });
+                if sheet_open {
+                    egui::ScrollArea::vertical()
+                        .max_height(0.5 * ctx.content_rect().height())
+                        .show(ui, |ui| controls(ui));
                 }
+            });
+        } else {
+            egui::SidePanel::left("left panel").show(ctx, controls);
+        }
+        self.sheet_open = sheet_open;
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let painter = ui.painter();
+            let palette = appearance.palette(ui.visuals());
+            let layout = self.paint_position(
+                ctx,
+                painter,
+                ui.available_rect_before_wrap(),
+                self.pieces.textures(),
+                &palette,
+                ui.visuals().text_color(),
+                self.blindfold,
+            );
+            let cell_size = layout.cell_size;
+            let cell_to_rect = |row, col| layout.cell_rect(row, col);
+            let shown_game = self.shown_game();
+            label_cells(ui, self.game.logic(), shown_game.state(), &layout);
+
+            if self.show_destinations
+                && self.viewing.is_none()
+                && !self.game.is_finished()
+                && self.person_to_move()
+            {
+                paint_destinations(
+                    painter,
+                    self.game.logic(),
+                    self.game.state(),
+                    &self.move_selection,
+                    &layout,
+                    palette.destination,
+                );
             }
 
             // Draw the move selection state
@@ -353,30 +2993,38 @@ impl<G: GridGame, A: Ai<G>> AppState for State<G, A> {
                 painter,
             );
 
-            // Handle clicks
-            if ui.input(|i| {
-                i.pointer.primary_pressed()
-                    && if let Some(pos) = i.pointer.latest_pos() {
-                        ui.max_rect().contains(pos)
+            // Handle clicks, except on an earlier position as that is only for looking at or while the other player over the network is to move
+            // On touch screens a square is chosen as the finger lifts, so that brushing past the board or scrolling doesn't choose one
+            if self.viewing.is_none()
+                && !self.is_remote(self.game.turn())
+                && ui.input(|i| {
+                    let tapped = if compact {
+                        i.pointer.primary_clicked()
                     } else {
-                        false
-                    }
-            }) && !ui.ctx().wants_pointer_input()
-            {
-                let mut clicked = None;
-                for row in 0..G::ROWS {
-                    for col in 0..G::COLS {
-                        let rect = cell_to_rect(row, col);
-                        let pointer = ctx.input(|i| i.pointer.interact_pos());
-                        if let Some(pos) = pointer
-                            && ui.input(|i| i.pointer.primary_pressed())
-                            && rect.contains(pos)
-                        {
-                            clicked = Some((row, col));
+                        i.pointer.primary_pressed()
+                    };
+                    tapped
+                        && if let Some(pos) = i.pointer.interact_pos() {
+                            ui.max_rect().contains(pos)
+                        } else {
+                            false
                         }
-                    }
+                })
+                && !ui.ctx().is_using_pointer()
+            {
+                let clicked = ctx
+                    .input(|i| i.pointer.interact_pos())
+                    .and_then(|pos| layout.cell_at(self.game.logic(), pos));
+                // Clicking the square which chose the waiting move again plays it, and clicking anywhere else puts it back
+                let confirming = self.staged_move().is_some()
+                    && clicked.is_some()
+                    && self.staged.as_ref().map(|staged| staged.cell) == Some(clicked);
+                if !confirming {
+                    self.staged = None;
                 }
-                if let Some(mv) = if let Some((row, col)) = clicked {
+                if confirming {
+                    self.confirm_move();
+                } else if let Some(mv) = if let Some((row, col)) = clicked {
                     self.game.logic().update_move_selection(
                         self.game.turn(),
                         self.game.state(),
@@ -391,29 +3039,123 @@ impl<G: GridGame, A: Ai<G>> AppState for State<G, A> {
                         &mut self.move_selection,
                     )
                 } {
-                    self.make_move(mv);
+                    self.choose_move(mv, clicked);
                 }
             }
 
-            // Show best move
-            for (idx, (_label, best_move)) in best_moves.iter().enumerate() {
-                if show_best_moves[idx] {
-                    self.game.logic().show_move(
-                        self.game.turn(),
-                        self.game.state(),
-                        best_move.clone(),
+            // The move waiting to be confirmed, with the pieces it moves faded in where they'd be
+            if let Some(mv) = self.staged_move() {
+                let logic = self.game.logic();
+                let mut painter = painter.clone();
+                painter.multiply_opacity(0.5);
+                let mut after = self.game.state().clone();
+                logic.make_move(&mut after, mv);
+                for row in 0..logic.rows() {
+                    for col in 0..logic.cols() {
+                        let piece = logic.piece(&after, row, col);
+                        if piece != logic.piece(self.game.state(), row, col)
+                            && self.blindfold.shows(piece)
+                        {
+                            paint_piece(
+                                &painter,
+                                cell_to_rect(row, col),
+                                piece,
+                                self.pieces.textures(),
+                            );
+                        }
+                    }
+                }
+                logic.show_move(
+                    self.game.turn(),
+                    self.game.state(),
+                    mv.clone(),
+                    cell_size,
+                    cell_to_rect,
+                    &painter,
+                );
+            }
+
+            if let Some(mv) = self.current_hint() {
+                let logic = self.game.logic();
+                logic.show_move(
+                    self.game.turn(),
+                    self.game.state(),
+                    mv.clone(),
+                    cell_size,
+                    cell_to_rect,
+                    painter,
+                );
+            }
+
+            // Show the expected line of play, each move fainter than the one before so that their order can be seen
+            for line in &shown_lines {
+                let logic = self.game.logic();
+                let mut state = shown_game.state().clone();
+                for (i, mv) in line.iter().enumerate() {
+                    let mut painter = painter.clone();
+                    painter.multiply_opacity(1.0 / (1.0 + 0.5 * i as f32));
+                    logic.show_move(
+                        logic.turn(&state),
+                        &state,
+                        mv.clone(),
                         cell_size,
                         cell_to_rect,
-                        painter,
+                        &painter,
                     );
+                    logic.make_move(&mut state, mv);
                 }
             }
+
+            // Right click to mark a cell and drag to draw an arrow, while a left click rubs them all out
+            let position = shown_game.state().clone().position_ident().hash64();
+            let (pointer, right_pressed, right_down, left_pressed) = ui.input(|i| {
+                (
+                    i.pointer.interact_pos(),
+                    i.pointer.secondary_pressed(),
+                    i.pointer.secondary_down(),
+                    i.pointer.primary_pressed(),
+                )
+            });
+            let pointer_cell = pointer
+                .filter(|_| !ui.ctx().wants_pointer_input())
+                .and_then(|pos| layout.cell_at(self.game.logic(), pos));
+            if right_pressed {
+                self.annotation_start = pointer_cell;
+            } else if !right_down
+                && let Some(from) = self.annotation_start.take()
+                && let Some(to) = pointer_cell
+            {
+                // Letting go finishes drawing, though only draws anything on the board
+                self.annotations
+                    .entry(position)
+                    .or_default()
+                    .toggle(from, to);
+            }
+            if left_pressed && pointer_cell.is_some() {
+                self.annotations.remove(&position);
+            }
+            let dragging = self.annotation_start.zip(pointer_cell);
+            paint_annotations(
+                painter,
+                self.annotations.get(&position),
+                dragging,
+                &layout,
+                palette.annotation,
+            );
         });
 
-        if let Some(mv) = move_to_make {
+        if let Some(mv) = move_to_make
+            && self.viewing.is_none()
+        {
             self.make_move(mv);
         }
 
+        // Whatever made them happen, moves and the end of the game are heard
+        let events = self.game.take_events();
+        if self.sound {
+            crate::sound::play(&events);
+        }
+
         ctx.request_repaint();
 
         change_state
@@ -0,0 +1,171 @@
+use crate::{
+    ai::arena::{Ending, Match},
+    game::Player,
+    grid::{
+        GridGame,
+        notation::NotationStyle,
+        theme::{Appearance, PieceSet, PieceTextures},
+        ui::{Blindfold, BoardLayout, paint_board},
+    },
+    root::{AppState, Remembered},
+};
+
+// Watching two AIs play a match against each other
+pub struct State<G: GridGame> {
+    contest: Match<G>,
+    paused: bool,
+    pieces: PieceTextures,
+    // How moves are written in the copied game records
+    notation: NotationStyle,
+}
+
+impl<G: GridGame> State<G> {
+    pub fn new(ctx: &egui::Context, contest: Match<G>) -> Self {
+        Self {
+            contest,
+            paused: false,
+            pieces: PieceTextures::new(ctx, PieceSet::default()),
+            notation: NotationStyle::Standard,
+        }
+    }
+}
+
+impl<G: GridGame> AppState for State<G> {
+    fn restore(&mut self, remembered: &Remembered) {
+        self.notation = remembered.game.notation();
+    }
+
+    fn update(
+        &mut self,
+        ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+        appearance: &Appearance,
+    ) -> Option<Box<dyn AppState>> {
+        self.pieces.choose(ctx, appearance.pieces);
+        let mut change_state: Option<Box<dyn AppState>> = None;
+        let mut toggle_pause = false;
+
+        if !self.paused {
+            self.contest.step(chrono::TimeDelta::milliseconds(10));
+        }
+
+        egui::SidePanel::left("left panel").show(ctx, |ui| {
+            if ui.button("Menu").clicked() {
+                change_state = Some(Box::new(crate::menu::State::default()));
+            }
+
+            ui.separator();
+
+            let contestants = self.contest.contestants();
+            let game = self.contest.game();
+            let logic = game.logic().clone();
+            ui.heading("Match");
+            if self.contest.is_finished() {
+                ui.label(format!("Finished after {} games", self.contest.games()));
+            } else {
+                ui.label(format!(
+                    "Game {} of {}, move {}",
+                    self.contest.results().len() + 1,
+                    self.contest.games(),
+                    game.num_moves() + 1
+                ));
+                for player in [Player::First, Player::Second] {
+                    let contestant = &contestants[self.contest.contestant_idx(player)];
+                    let to_move = if player == game.turn() {
+                        " (to move)"
+                    } else {
+                        ""
+                    };
+                    ui.label(format!(
+                        "{}: {}{to_move}",
+                        logic.player_name(player),
+                        contestant.name
+                    ));
+                }
+                let progress = self.contest.move_progress().min(1.0);
+                ui.add(egui::ProgressBar::new(progress).text(format!("{:.0}%", progress * 100.0)));
+                let pause_label = if self.paused { "Resume" } else { "Pause" };
+                toggle_pause = ui.button(pause_label).clicked();
+            }
+
+            ui.separator();
+            ui.heading("Score");
+            egui::Grid::new("match_score_grid")
+                .num_columns(4)
+                .striped(true)
+                .show(ui, |ui| {
+                    for heading in ["", "Won", "Drawn", "Lost"] {
+                        ui.label(heading);
+                    }
+                    ui.end_row();
+                    for (idx, contestant) in contestants.iter().enumerate() {
+                        let (wins, draws, losses) = self.contest.score(idx);
+                        ui.label(&contestant.name);
+                        ui.label(wins.to_string());
+                        ui.label(draws.to_string());
+                        ui.label(losses.to_string());
+                        ui.end_row();
+                    }
+                });
+
+            ui.separator();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (number, result) in self.contest.results().iter().enumerate() {
+                    let first = &contestants[result.first].name;
+                    let second = &contestants[1 - result.first].name;
+                    let outcome = match (result.winner, result.ending) {
+                        (None, Ending::MoveLimit) => "drawn at the move limit".to_string(),
+                        (None, _) => "drawn".to_string(),
+                        (Some(winner), Ending::NoMove) => {
+                            format!(
+                                "won by {}, the other AI had no move",
+                                contestants[winner].name
+                            )
+                        }
+                        (Some(winner), _) => format!("won by {}", contestants[winner].name),
+                    };
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{}. {first} v {second}: {outcome} in {} moves",
+                            number + 1,
+                            result.game.num_moves()
+                        ));
+                        if ui.small_button("Copy").clicked()
+                            && let Some((_, record)) =
+                                logic.export_game(&result.game, self.notation)
+                        {
+                            ctx.copy_text(record);
+                        }
+                    });
+                }
+            });
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let game = self.contest.game();
+            let layout = BoardLayout::new(game.logic(), ui.available_rect_before_wrap());
+            paint_board(
+                ctx,
+                ui.painter(),
+                game.logic(),
+                game.state(),
+                &layout,
+                self.pieces.textures(),
+                &appearance.palette(ui.visuals()),
+                Blindfold::Off,
+            );
+        });
+
+        if toggle_pause {
+            self.paused = !self.paused;
+            // The time spent paused doesn't count against the AI to move
+            if !self.paused {
+                self.contest.start_move();
+            }
+        }
+
+        ctx.request_repaint();
+
+        change_state
+    }
+}
@@ -0,0 +1,208 @@
+// Painting with egui onto a texture off screen and reading it back, for pictures which don't depend on the window
+
+use egui::{Color32, Pos2, Rect, Vec2};
+use egui_wgpu::wgpu;
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/*
+Paint with a fresh egui context onto a new `width` by `height` texture, at one pixel per point.
+Anything the painting uses, such as piece textures, must be loaded into the context it's given rather than the window's.
+ */
+pub fn render_to_texture(
+    render_state: &egui_wgpu::RenderState,
+    width: u32,
+    height: u32,
+    background: Color32,
+    mut paint: impl FnMut(&egui::Context, &egui::Painter),
+) -> wgpu::Texture {
+    let device = &render_state.device;
+    let queue = &render_state.queue;
+
+    let ctx = egui::Context::default();
+    let input = egui::RawInput {
+        screen_rect: Some(Rect::from_min_size(
+            Pos2::ZERO,
+            Vec2::new(width as f32, height as f32),
+        )),
+        ..Default::default()
+    };
+    let output = ctx.run(input, |ctx| {
+        paint(ctx, &ctx.layer_painter(egui::LayerId::background()));
+    });
+    let paint_jobs = ctx.tessellate(output.shapes, output.pixels_per_point);
+    let screen_descriptor = egui_wgpu::ScreenDescriptor {
+        size_in_pixels: [width, height],
+        pixels_per_point: output.pixels_per_point,
+    };
+
+    let mut renderer =
+        egui_wgpu::Renderer::new(device, FORMAT, egui_wgpu::RendererOptions::default());
+    for (id, delta) in &output.textures_delta.set {
+        renderer.update_texture(device, queue, *id, delta);
+    }
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("headless"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let mut commands =
+        renderer.update_buffers(device, queue, &mut encoder, &paint_jobs, &screen_descriptor);
+    {
+        // The texture is sRGB, so the clear colour is given in linear space
+        let [r, g, b, a] = egui::Rgba::from(background).to_array();
+        let mut render_pass = encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("headless"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: r as f64,
+                            g: g as f64,
+                            b: b as f64,
+                            a: a as f64,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            })
+            .forget_lifetime();
+        renderer.render(&mut render_pass, &paint_jobs, &screen_descriptor);
+    }
+    commands.push(encoder.finish());
+    queue.submit(commands);
+
+    texture
+}
+
+// Copy a texture from `render_to_texture` back from the GPU
+pub async fn save_texture(
+    render_state: &egui_wgpu::RenderState,
+    texture: &wgpu::Texture,
+) -> Result<image::RgbaImage, String> {
+    let u32_size = std::mem::size_of::<u32>() as u32;
+
+    let texture_size = texture.size();
+    assert_eq!(texture_size.depth_or_array_layers, 1);
+    assert_eq!(texture.format(), FORMAT);
+
+    // Rows are copied at a multiple of 256 bytes apart, so may need padding
+    let row_size = u32_size * texture_size.width;
+    let padded_row_size =
+        row_size.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let output_buffer_size = (padded_row_size * texture_size.height) as wgpu::BufferAddress;
+    let output_buffer_desc = wgpu::BufferDescriptor {
+        size: output_buffer_size,
+        usage: wgpu::BufferUsages::COPY_DST
+            // this tells wpgu that we want to read this buffer from the cpu
+            | wgpu::BufferUsages::MAP_READ,
+        label: None,
+        mapped_at_creation: false,
+    };
+    let output_buffer = render_state.device.create_buffer(&output_buffer_desc);
+
+    let mut encoder = render_state
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &output_buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_size),
+                rows_per_image: Some(texture_size.height),
+            },
+        },
+        texture_size,
+    );
+
+    render_state.queue.submit(Some(encoder.finish()));
+
+    // We need to scope the mapping variables so that we can
+    // unmap the buffer
+    let image = {
+        let buffer_slice = output_buffer.slice(..);
+
+        // NOTE: We have to create the mapping THEN device.poll() before await
+        // the future. Otherwise the application will freeze.
+        let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            tx.send(result).unwrap();
+        });
+        render_state
+            .device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|err| err.to_string())?;
+        rx.receive()
+            .await
+            .ok_or("The texture was never read back")?
+            .map_err(|err| err.to_string())?;
+
+        let data = buffer_slice.get_mapped_range();
+        let pixels = data
+            .chunks(padded_row_size as usize)
+            .flat_map(|row| &row[..row_size as usize])
+            .copied()
+            .collect::<Vec<_>>();
+        image::RgbaImage::from_raw(texture_size.width, texture_size.height, pixels)
+            .ok_or("The texture is the wrong size")?
+    };
+    output_buffer.unmap();
+    Ok(image)
+}
+
+/*
+Read a texture from `render_to_texture` back and save it as a PNG, with a file dialog on native or as a download in the browser.
+The browser reads textures back in the background, so there the file is offered once this has returned.
+ */
+pub fn save_png(render_state: &egui_wgpu::RenderState, texture: wgpu::Texture, file_name: &str) {
+    let render_state = render_state.clone();
+    let file_name = file_name.to_string();
+    let save = async move {
+        let png = save_texture(&render_state, &texture)
+            .await
+            .and_then(|image| {
+                let mut png = std::io::Cursor::new(vec![]);
+                image
+                    .write_to(&mut png, image::ImageFormat::Png)
+                    .map_err(|err| err.to_string())?;
+                Ok(png.into_inner())
+            });
+        match png {
+            Ok(png) => crate::files::save_bytes(&file_name, "png", &png),
+            Err(err) => log::error!("Failed to save {file_name}: {err}"),
+        }
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    pollster::block_on(save);
+    #[cfg(target_arch = "wasm32")]
+    wasm_bindgen_futures::spawn_local(save);
+}
@@ -1,6 +1,13 @@
 pub mod ai;
 pub mod demo;
+pub mod files;
 pub mod game;
 pub mod grid;
+pub mod headless;
+pub mod links;
 pub mod menu;
+pub mod net;
 pub mod root;
+pub mod sound;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod terminal;
@@ -0,0 +1,196 @@
+use crate::{
+    game::Game,
+    grid::{
+        GridGame,
+        notation::{MoveFormatter, NotationStyle},
+    },
+};
+
+/*
+Links to games, kept in the fragment of the page's address in the browser so that games can be shared and come back on reloading.
+A fragment names the game, any choices it was set up with such as its starting position, and the moves played e.g. `#game=chess&moves=e2e4,e7e5`.
+Moves are written in coordinate notation where the game has it, and read back in any notation the game takes typed moves in.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Link {
+    // The name the menu knows the game by
+    pub game: String,
+    // How the game was set up, such as `fen` for a chess position or `size` for the board
+    pub options: Vec<(String, String)>,
+    pub moves: Vec<String>,
+}
+
+impl Link {
+    pub fn new(game: &str) -> Self {
+        Self {
+            game: game.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_option(mut self, key: &str, value: impl ToString) -> Self {
+        self.options.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn option(&self, key: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // The moves of `game`, in place of any the link had
+    pub fn with_moves<G: GridGame>(mut self, game: &Game<G>) -> Self {
+        self.moves =
+            MoveFormatter::new(game.logic(), NotationStyle::Coordinate).write_history(game);
+        self
+    }
+
+    // Play the link's moves in `game`, stopping at any which isn't legal there
+    pub fn replay<G: GridGame>(&self, mut game: Game<G>) -> Game<G> {
+        for text in &self.moves {
+            let logic = game.logic();
+            let state = game.state();
+            let Some(mv) = (!game.is_finished())
+                .then(|| logic.generate_moves(&mut state.clone()))
+                .and_then(|moves| {
+                    moves
+                        .into_iter()
+                        .find(|mv| logic.typed_names(state, mv).contains(text))
+                })
+            else {
+                log::warn!("The link's move {text} can't be played, so the game stops before it");
+                break;
+            };
+            game.make_move(mv);
+        }
+        game
+    }
+
+    pub fn to_fragment(&self) -> String {
+        let mut pairs = vec![format!("game={}", escape(&self.game))];
+        for (key, value) in &self.options {
+            pairs.push(format!("{}={}", escape(key), escape(value)));
+        }
+        if !self.moves.is_empty() {
+            let moves = self.moves.iter().map(|mv| escape(mv)).collect::<Vec<_>>();
+            pairs.push(format!("moves={}", moves.join(",")));
+        }
+        pairs.join("&")
+    }
+
+    // The link in a fragment, with or without its `#`, or None if it doesn't name a game
+    pub fn from_fragment(fragment: &str) -> Option<Self> {
+        let mut link = Self::default();
+        for pair in fragment.trim_start_matches('#').split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match unescape(key)?.as_str() {
+                "game" => link.game = unescape(value)?,
+                "moves" => {
+                    link.moves = value
+                        .split(',')
+                        .filter(|mv| !mv.is_empty())
+                        .map(unescape)
+                        .collect::<Option<_>>()?
+                }
+                "" => {}
+                key => link.options.push((key.to_string(), unescape(value)?)),
+            }
+        }
+        (!link.game.is_empty()).then_some(link)
+    }
+}
+
+// Percent-encode everything but letters, digits and `-._~`
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            escaped.push(byte as char);
+        } else {
+            escaped.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut rest = text.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(after.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &after[2..];
+        } else {
+            bytes.push(byte);
+            rest = after;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+// The link the page was opened with, given only the first time so that coming back to the menu doesn't start it again
+#[cfg(target_arch = "wasm32")]
+pub fn opened() -> Option<Link> {
+    static TAKEN: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+    if TAKEN.swap(true, std::sync::atomic::Ordering::Relaxed) {
+        return None;
+    }
+    let fragment = web_sys::window()?.location().hash().ok()?;
+    Link::from_fragment(&fragment)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn opened() -> Option<Link> {
+    None
+}
+
+// Put `link` in the page's address without reloading, giving back the whole address to be copied
+#[cfg(target_arch = "wasm32")]
+pub fn show(link: &Link) -> Option<String> {
+    let window = web_sys::window()?;
+    let fragment = format!("#{}", link.to_fragment());
+    if let Err(err) = window.history().and_then(|history| {
+        history.replace_state_with_url(&eframe::wasm_bindgen::JsValue::NULL, "", Some(&fragment))
+    }) {
+        log::error!("Failed to show the game's link: {:?}", err);
+        return None;
+    }
+    window.location().href().ok()
+}
+
+// Outside the browser there's no address to share
+#[cfg(not(target_arch = "wasm32"))]
+pub fn show(_link: &Link) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::chess::Chess;
+
+    #[test]
+    fn fragments() {
+        let mut played = Link::new("chess");
+        played.moves = vec!["e2e4".to_string(), "e7e5".to_string()];
+        let game = played.replay(Game::new(Chess::Standard));
+        assert_eq!(game.num_moves(), 2);
+
+        let link = Link::new("custom-chess")
+            .with_option("fen", "8/8/8/8/8/8/8/K1k5 w - - 0 1")
+            .with_moves(&game);
+        assert_eq!(link.moves, played.moves);
+        let fragment = link.to_fragment();
+        assert!(fragment.starts_with("game=custom-chess&fen=8%2F8"));
+        assert!(fragment.ends_with("&moves=e2e4,e7e5"));
+        assert_eq!(Link::from_fragment(&format!("#{fragment}")), Some(link));
+        assert_eq!(Link::from_fragment("#moves=e2e4"), None);
+
+        // Moves which can't be played are left off, along with those after them
+        played.moves.insert(1, "e2e4".to_string());
+        assert_eq!(played.replay(Game::new(Chess::Standard)).num_moves(), 1);
+    }
+}
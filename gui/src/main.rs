@@ -3,6 +3,43 @@
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(String::as_str) == Some("--perft") {
+        if let Err(e) = chess::grid::chess::perft::run(&args[1..]) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if args.first().map(String::as_str) == Some("--bench") {
+        if let Err(e) = chess::grid::bench::run(&args[1..]) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if args.first().map(String::as_str) == Some("--sprt") {
+        if let Err(e) = chess::grid::chess::sprt::run(&args[1..]) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if args.first().map(String::as_str) == Some("--tune") {
+        if let Err(e) = chess::grid::chess::texel::run(&args[1..]) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if args.first().map(String::as_str) == Some("--tournament") {
+        if let Err(e) = chess::grid::chess::tournament::run(&args[1..]) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     env_logger::Builder::from_default_env()
         .filter_level(log::LevelFilter::Info)
         .init();
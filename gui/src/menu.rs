@@ -1,23 +1,288 @@
-use crate::root::AppState;
+use std::collections::BTreeMap;
 
-#[derive(PartialEq)]
-enum GameSelection {
-    Chess,
-    BerolinaChess,
-    GrasshopperChess,
-}
+use crate::{
+    ai::{
+        Ai,
+        arena::{Contestant, Match},
+        strength::Strength,
+        tournament::{Entrant, Format, Tournament},
+    },
+    game::Game,
+    grid::{
+        GridGame,
+        chess::Chess,
+        registry::{self, DynGame, Launch, Setup, SetupOption},
+        theme::Appearance,
+    },
+    links::Link,
+    root::{AppState, Remembered},
+};
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
 enum AiSelection {
     AlphaBetaSingleThread,
     AlphaBetaMultiThread,
     Random,
+    // An external engine, for chess only
+    UciEngine,
     Null,
 }
 
+impl AiSelection {
+    // The AIs which can play in a match
+    const OPPONENTS: [AiSelection; 4] = [
+        AiSelection::AlphaBetaMultiThread,
+        AiSelection::AlphaBetaSingleThread,
+        AiSelection::Random,
+        AiSelection::UciEngine,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            AiSelection::AlphaBetaSingleThread => "Alpha-Beta Single Thread",
+            AiSelection::AlphaBetaMultiThread => "Alpha-Beta Multi-Threaded",
+            AiSelection::Random => "Random Moves",
+            AiSelection::UciEngine => "External UCI Engine",
+            AiSelection::Null => "None",
+        }
+    }
+
+    // Whether this AI can play `chess` games in this build
+    fn is_available(self, chess: bool) -> bool {
+        match self {
+            AiSelection::AlphaBetaMultiThread => cfg!(not(target_arch = "wasm32")),
+            AiSelection::UciEngine => chess && cfg!(not(target_arch = "wasm32")),
+            _ => true,
+        }
+    }
+
+    fn new_ai<G: GridGame + Send>(self) -> Box<dyn Ai<G>> {
+        match self {
+            AiSelection::AlphaBetaMultiThread => {
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    Box::new(crate::ai::alphabeta::multithreaded::AlphaBeta::<G>::new())
+                }
+                #[cfg(target_arch = "wasm32")]
+                unreachable!()
+            }
+            AiSelection::AlphaBetaSingleThread => {
+                Box::new(crate::ai::alphabeta::singlethreaded::AlphaBeta::<G>::new())
+            }
+            AiSelection::Random => Box::new(crate::ai::random::Random::<G>::new()),
+            // Only chess can be played by an external engine, made by `new_chess_ai`
+            AiSelection::UciEngine | AiSelection::Null => {
+                Box::new(crate::ai::null::NullAi::<G>::new())
+            }
+        }
+    }
+
+    fn new_chess_ai(self) -> Box<dyn Ai<Chess>> {
+        match self {
+            AiSelection::UciEngine => {
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    Box::new(crate::ai::uci::UciEngine::new())
+                }
+                #[cfg(target_arch = "wasm32")]
+                unreachable!()
+            }
+            _ => self.new_ai(),
+        }
+    }
+}
+
+// Who plays one side of a game
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+enum Controller {
+    // Clicking on the board, with the AI only suggesting moves
+    Human,
+    // The AI chosen, taking this many seconds per move
+    Ai(u32),
+}
+
+// The settings of an AI vs AI match
+struct MatchSettings {
+    opponent: AiSelection,
+    games: usize,
+    // Seconds per move of the AI chosen above, and of the opponent
+    move_times: [u32; 2],
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            opponent: AiSelection::AlphaBetaSingleThread,
+            games: 2,
+            move_times: [3, 3],
+        }
+    }
+}
+
+// The settings of a tournament between AIs
+struct TournamentSettings {
+    entrants: Vec<(AiSelection, Strength)>,
+    format: Format,
+    games_per_match: usize,
+    // Seconds per move, the same for every entrant
+    move_time: u32,
+}
+
+impl Default for TournamentSettings {
+    fn default() -> Self {
+        Self {
+            entrants: vec![
+                (AiSelection::AlphaBetaSingleThread, Strength::LEVELS[1]),
+                (AiSelection::AlphaBetaSingleThread, Strength::LEVELS[2]),
+                (AiSelection::Random, Strength::FULL),
+            ],
+            format: Format::RoundRobin,
+            games_per_match: 2,
+            move_time: 1,
+        }
+    }
+}
+
+// What the player here does in a game over the network
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NetworkRole {
+    Host,
+    Join,
+    Watch,
+}
+
+// The settings of a game against someone on another copy of the app, on the same network or through a relay online
+struct NetworkSettings {
+    role: NetworkRole,
+    // Through a relay rather than directly, which is the only way in the browser
+    #[cfg(not(target_arch = "wasm32"))]
+    online: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    port: u16,
+    // The side the player joining plays
+    guest: crate::game::Player,
+    clock: bool,
+    clock_minutes: u32,
+    clock_increment: u32,
+    // The host's address, to join on the local network
+    #[cfg(not(target_arch = "wasm32"))]
+    address: String,
+    // The relay's address, and the room on it both players give
+    server: String,
+    room: String,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            role: NetworkRole::Host,
+            #[cfg(not(target_arch = "wasm32"))]
+            online: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            port: crate::net::tcp::DEFAULT_PORT,
+            guest: crate::game::Player::Second,
+            clock: false,
+            clock_minutes: 5,
+            clock_increment: 3,
+            #[cfg(not(target_arch = "wasm32"))]
+            address: String::new(),
+            server: crate::net::online::DEFAULT_SERVER.to_string(),
+            room: String::new(),
+        }
+    }
+}
+
+impl NetworkSettings {
+    // Setting up the game, with spectators analysing it with an `A`
+    fn lobby<G: GridGame, A: Ai<G> + 'static>(
+        &self,
+        game: Game<G>,
+    ) -> crate::net::lobby::State<G, A> {
+        use crate::net::lobby::{Role, State};
+        let role = match self.role {
+            NetworkRole::Host => Role::Host {
+                guest: self.guest,
+                clocks: self.clock.then(|| crate::game::SavedClocks {
+                    minutes: self.clock_minutes,
+                    increment: self.clock_increment,
+                    remaining: [self.clock_minutes as i64 * 60_000; 2],
+                }),
+            },
+            NetworkRole::Join => Role::Join,
+            NetworkRole::Watch => Role::Watch,
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        if !self.online {
+            return match role {
+                Role::Host { guest, clocks } => State::host(game, self.port, guest, clocks),
+                Role::Join => State::join(game, &self.address, false),
+                Role::Watch => State::join(game, &self.address, true),
+            };
+        }
+        State::online(game, &self.server, &self.room, role)
+    }
+
+    fn is_online(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.online;
+        #[cfg(target_arch = "wasm32")]
+        true
+    }
+}
+
 pub struct State {
-    game_selection: GameSelection,
+    // The key of the game chosen in the registry
+    game_selection: String,
     ai_selection: AiSelection,
+    // Who plays first and who plays second
+    players: [Controller; 2],
+    random_opening: bool,
+    fischer960_position: String,
+    custom_position: String,
+    // Why the game chosen couldn't be set up as it is
+    setup_error: Option<String>,
+    // The board size chosen for each game played on more than one, by its key
+    sizes: BTreeMap<String, usize>,
+    forbidden_moves: bool,
+    // The rules of the game from a script
+    #[cfg(feature = "scripting")]
+    script: String,
+    pgn_loader: Option<PgnLoader>,
+    // Have two AIs play each other instead of playing against one
+    versus: Option<MatchSettings>,
+    // Have several AIs play each other in turn instead of playing against one
+    tournament: Option<TournamentSettings>,
+    // Play someone on another computer instead of playing here
+    network: Option<NetworkSettings>,
+    // The remembered choices have been taken on, so aren't again when coming back from the position editor
+    restored: bool,
+    // The link the game is being started from, whose moves are played in it
+    linked: Option<Link>,
+}
+
+// The choices in the menu which are remembered, so they're as they were on coming back to it
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Selections {
+    game: String,
+    ai: AiSelection,
+    players: [Controller; 2],
+    random_opening: bool,
+    custom_position: String,
+    sizes: BTreeMap<String, usize>,
+    forbidden_moves: bool,
+}
+
+impl Default for Selections {
+    fn default() -> Self {
+        State::default().selections()
+    }
+}
+
+#[derive(Default)]
+struct PgnLoader {
+    text: String,
+    error: Option<String>,
 }
 
 impl Default for State {
@@ -27,35 +292,565 @@ impl Default for State {
         #[cfg(not(target_arch = "wasm32"))]
         let ai_selection = AiSelection::AlphaBetaMultiThread;
         Self {
-            game_selection: GameSelection::Chess,
+            game_selection: registry::games()[0].key().to_string(),
             ai_selection,
+            players: [Controller::Human, Controller::Human],
+            random_opening: false,
+            fischer960_position: String::new(),
+            custom_position: crate::grid::chess::positions::PRESETS[0].1.to_string(),
+            setup_error: None,
+            sizes: BTreeMap::new(),
+            forbidden_moves: false,
+            #[cfg(feature = "scripting")]
+            script: crate::grid::script::EXAMPLES[0].1.to_string(),
+            pgn_loader: None,
+            versus: None,
+            tournament: None,
+            network: None,
+            restored: false,
+            linked: None,
+        }
+    }
+}
+
+impl State {
+    // Back from the position editor with the position it set up
+    pub fn with_custom_position(self, fen: String) -> Self {
+        Self {
+            game_selection: "custom-chess".to_string(),
+            custom_position: fen,
+            setup_error: None,
+            ..self
+        }
+    }
+
+    fn selections(&self) -> Selections {
+        Selections {
+            game: self.game_selection.clone(),
+            ai: self.ai_selection,
+            players: self.players,
+            random_opening: self.random_opening,
+            custom_position: self.custom_position.clone(),
+            sizes: self.sizes.clone(),
+            forbidden_moves: self.forbidden_moves,
+        }
+    }
+
+    // The game with the AI and players chosen, linked to by `link`
+    fn start_game<G: GridGame + Send>(
+        &self,
+        ctx: &egui::Context,
+        game: Game<G>,
+        link: Link,
+    ) -> Box<dyn AppState> {
+        if self.versus.is_some() {
+            let ais = [self.ai_selection.new_ai(), self.match_opponent().new_ai()];
+            return self.start_match(ctx, game, ais);
+        }
+        if self.tournament.is_some() {
+            return self.start_tournament(ctx, vec![game], AiSelection::new_ai);
+        }
+        match self.ai_selection {
+            AiSelection::Null => self.play::<_, crate::ai::null::NullAi<_>>(ctx, game, link),
+            AiSelection::Random => self.play::<_, crate::ai::random::Random<_>>(ctx, game, link),
+            AiSelection::AlphaBetaMultiThread => {
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    self.play::<_, crate::ai::alphabeta::multithreaded::AlphaBeta<_>>(
+                        ctx, game, link,
+                    )
+                }
+                #[cfg(target_arch = "wasm32")]
+                unreachable!()
+            }
+            AiSelection::AlphaBetaSingleThread => {
+                self.play::<_, crate::ai::alphabeta::singlethreaded::AlphaBeta<_>>(ctx, game, link)
+            }
+            // Only offered for chess, which is started by `start_chess_game`
+            AiSelection::UciEngine => self.play::<_, crate::ai::null::NullAi<_>>(ctx, game, link),
+        }
+    }
+
+    // A game with the players chosen, the AI being an `A`, or against someone over the network
+    fn play<G: GridGame, A: Ai<G> + 'static>(
+        &self,
+        ctx: &egui::Context,
+        game: Game<G>,
+        link: Link,
+    ) -> Box<dyn AppState> {
+        let game = match &self.linked {
+            Some(link) => link.replay(game),
+            None => game,
+        };
+        if let Some(settings) = &self.network {
+            return Box::new(settings.lobby::<G, A>(game));
+        }
+        let move_times = self.players.map(|controller| match controller {
+            Controller::Human => None,
+            Controller::Ai(move_time) => Some(move_time),
+        });
+        Box::new(
+            crate::grid::ui::State::<G, A>::new(ctx, game)
+                .with_autoplay(move_times)
+                .with_link(link),
+        )
+    }
+
+    // The game chosen, or the first if what was chosen isn't known here
+    fn selected(&self) -> &'static dyn DynGame {
+        registry::find(&self.game_selection).unwrap_or(&*registry::games()[0])
+    }
+
+    // How `game` is to be set up, from the choices made for it
+    fn setup(&self, game: &dyn DynGame) -> Setup {
+        let size = game.options().iter().find_map(|option| match option {
+            SetupOption::Size { usual, .. } => {
+                Some(self.sizes.get(game.key()).copied().unwrap_or(*usual))
+            }
+            _ => None,
+        });
+        Setup {
+            size,
+            forbidden_moves: self.forbidden_moves,
+            position: self.fischer960_position.trim().parse().ok(),
+            fen: Some(self.custom_position.clone()),
+            random_opening: self.random_opening,
+            #[cfg(feature = "scripting")]
+            script: Some(self.script.clone()),
+            #[cfg(not(feature = "scripting"))]
+            script: None,
+        }
+    }
+
+    // Choose the game `link` is to, set up as it says, or return false if it's to a game not known here
+    fn choose(&mut self, link: &Link) -> bool {
+        let Some(game) = registry::find(&link.game) else {
+            return false;
+        };
+        self.game_selection = game.key().to_string();
+        let setup = Setup::from_link(link);
+        if let Some(position) = setup.position {
+            self.fischer960_position = position.to_string();
+        }
+        if let Some(fen) = setup.fen {
+            self.custom_position = fen;
+        }
+        if let Some(size) = setup.size {
+            self.sizes.insert(game.key().to_string(), size);
+        }
+        if game.options().contains(&SetupOption::ForbiddenMoves) {
+            self.forbidden_moves = setup.forbidden_moves;
+        }
+        true
+    }
+
+    // Choose the game `link` is to, to be started with its moves, or false if it's to a game not known here
+    fn follow(&mut self, link: Link) -> bool {
+        if !self.choose(&link) {
+            log::warn!(
+                "The link is to {}, which isn't a game known here",
+                link.game
+            );
+            return false;
+        }
+        // Just the game in the link is started, to be played on from where it was
+        self.random_opening = false;
+        self.versus = None;
+        self.tournament = None;
+        self.network = None;
+        self.linked = Some(link);
+        true
+    }
+
+    fn start_chess_game(
+        &self,
+        ctx: &egui::Context,
+        game: Game<Chess>,
+        link: Link,
+    ) -> Box<dyn AppState> {
+        if self.versus.is_some() {
+            let ais = [
+                self.ai_selection.new_chess_ai(),
+                self.match_opponent().new_chess_ai(),
+            ];
+            return self.start_match(ctx, game, ais);
+        }
+        if self.tournament.is_some() {
+            // Play each of the book openings from the standard starting position
+            let starts = if matches!(game.logic(), Chess::Standard) && game.num_moves() == 0 {
+                crate::grid::chess::openings::opening_positions(
+                    game.logic(),
+                    &crate::grid::chess::openings::noob_book(),
+                )
+            } else {
+                vec![game]
+            };
+            return self.start_tournament(ctx, starts, AiSelection::new_chess_ai);
+        }
+        match self.ai_selection {
+            AiSelection::UciEngine => {
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    self.play::<_, crate::ai::uci::UciEngine<_>>(ctx, game, link)
+                }
+                #[cfg(target_arch = "wasm32")]
+                unreachable!()
+            }
+            _ => self.start_game(ctx, game, link),
+        }
+    }
+
+    fn match_opponent(&self) -> AiSelection {
+        self.versus
+            .as_ref()
+            .map_or(AiSelection::Null, |settings| settings.opponent)
+    }
+
+    fn start_match<G: GridGame>(
+        &self,
+        ctx: &egui::Context,
+        game: Game<G>,
+        ais: [Box<dyn Ai<G>>; 2],
+    ) -> Box<dyn AppState> {
+        let settings = self.versus.as_ref().unwrap();
+        let selections = [self.ai_selection, settings.opponent];
+        let [first, second] = ais;
+        let contestant = |idx: usize, ai| {
+            let name = if selections[0] == selections[1] {
+                format!("{} {}", selections[idx].name(), idx + 1)
+            } else {
+                selections[idx].name().to_string()
+            };
+            Contestant {
+                name,
+                ai,
+                move_time: chrono::TimeDelta::seconds(settings.move_times[idx] as i64),
+            }
+        };
+        let contest = Match::new(
+            [contestant(0, first), contestant(1, second)],
+            vec![game],
+            settings.games,
+        );
+        Box::new(crate::grid::versus::State::new(ctx, contest))
+    }
+
+    fn start_tournament<G: GridGame>(
+        &self,
+        ctx: &egui::Context,
+        starts: Vec<Game<G>>,
+        new_ai: fn(AiSelection) -> Box<dyn Ai<G>>,
+    ) -> Box<dyn AppState> {
+        let settings = self.tournament.as_ref().unwrap();
+        let name = |(selection, strength): &(AiSelection, Strength)| {
+            format!("{}, {}", selection.name(), strength.name)
+        };
+        let entrants = settings
+            .entrants
+            .iter()
+            .enumerate()
+            .map(|(idx, &(selection, strength))| {
+                let mut entrant_name = name(&(selection, strength));
+                // Tell apart entrants with the same settings
+                if settings
+                    .entrants
+                    .iter()
+                    .filter(|other| name(other) == entrant_name)
+                    .count()
+                    > 1
+                {
+                    entrant_name.push_str(&format!(" #{}", idx + 1));
+                }
+                Entrant {
+                    name: entrant_name,
+                    new_ai: Box::new(move || {
+                        let mut ai = new_ai(selection);
+                        ai.set_strength(strength);
+                        ai
+                    }),
+                    move_time: chrono::TimeDelta::seconds(settings.move_time as i64),
+                }
+            })
+            .collect();
+        let tournament =
+            Tournament::new(entrants, settings.format, starts, settings.games_per_match);
+        Box::new(crate::grid::tournament::State::new(ctx, tournament))
+    }
+
+    // The choices in how `game` is set up, returning whether the board editor was asked for
+    fn show_setup(&mut self, ui: &mut egui::Ui, game: &dyn DynGame) -> bool {
+        let mut edit_board = false;
+        for option in game.options() {
+            match *option {
+                SetupOption::Size { sizes, usual } => {
+                    let chosen = self.sizes.entry(game.key().to_string()).or_insert(usual);
+                    ui.horizontal(|ui| {
+                        ui.label("Board size:");
+                        for &size in sizes {
+                            ui.radio_value(chosen, size, format!("{size}x{size}"));
+                        }
+                    });
+                }
+                SetupOption::ForbiddenMoves => {
+                    ui.checkbox(&mut self.forbidden_moves, "Renju forbidden moves for black");
+                }
+                SetupOption::Position => {
+                    ui.horizontal(|ui| {
+                        ui.label("Starting position (0-959, blank for random):");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.fischer960_position)
+                                .desired_width(50.0),
+                        );
+                    });
+                }
+                SetupOption::Fen => {
+                    let presets = crate::grid::chess::positions::PRESETS;
+                    ui.horizontal(|ui| {
+                        ui.label("Preset:");
+                        egui::ComboBox::from_id_salt("chess_start_position")
+                            .selected_text(
+                                presets
+                                    .iter()
+                                    .find(|(_, fen)| *fen == self.custom_position.trim())
+                                    .map_or("Custom", |(name, _)| name),
+                            )
+                            .show_ui(ui, |ui| {
+                                for (name, fen) in presets {
+                                    if ui
+                                        .selectable_label(self.custom_position == *fen, *name)
+                                        .clicked()
+                                    {
+                                        self.custom_position = fen.to_string();
+                                        self.setup_error = None;
+                                    }
+                                }
+                            });
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("FEN:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.custom_position)
+                                .desired_width(400.0),
+                        );
+                        if ui.button("Edit Board").clicked() {
+                            edit_board = true;
+                        }
+                    });
+                }
+                SetupOption::RandomOpening => {
+                    ui.checkbox(&mut self.random_opening, "Start from a random book opening");
+                }
+                SetupOption::Script => {
+                    #[cfg(feature = "scripting")]
+                    self.show_script(ui);
+                }
+            }
+        }
+        if let Some(error) = &self.setup_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        edit_board
+    }
+
+    // The script for a scripted game, opened from a file or started from an example, and editable here
+    #[cfg(feature = "scripting")]
+    fn show_script(&mut self, ui: &mut egui::Ui) {
+        let examples = crate::grid::script::EXAMPLES;
+        ui.horizontal(|ui| {
+            ui.label("Example:");
+            egui::ComboBox::from_id_salt("script_example")
+                .selected_text(
+                    examples
+                        .iter()
+                        .find(|(_, script)| *script == self.script)
+                        .map_or("Custom", |(name, _)| name),
+                )
+                .show_ui(ui, |ui| {
+                    for (name, script) in examples {
+                        if ui.selectable_label(self.script == script, name).clicked() {
+                            self.script = script.to_string();
+                            self.setup_error = None;
+                        }
+                    }
+                });
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.button("Open File").clicked()
+                && let Some(text) = crate::files::open_text("rhai")
+            {
+                self.script = text;
+                self.setup_error = None;
+            }
+        });
+        egui::ScrollArea::vertical()
+            .id_salt("script")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.script)
+                        .code_editor()
+                        .desired_rows(10)
+                        .desired_width(f32::INFINITY),
+                );
+            });
+    }
+
+    fn is_chess_selected(&self) -> bool {
+        self.selected().is_chess()
+    }
+
+    // Start the game chosen, or None if it's set up wrongly
+    fn start(&mut self, ctx: &egui::Context) -> Option<Box<dyn AppState>> {
+        let game = self.selected();
+        let setup = self.setup(game);
+        let started = game.start(
+            &setup,
+            Start {
+                menu: self,
+                ctx,
+                game,
+            },
+        );
+        match started {
+            Ok(state) => Some(state),
+            Err(error) => {
+                self.setup_error = Some(error);
+                None
+            }
         }
     }
+
+    fn show_pgn_loader(&mut self, ctx: &egui::Context) -> Option<Box<dyn AppState>> {
+        let mut open = true;
+        let mut start = None;
+        if let Some(loader) = &mut self.pgn_loader {
+            egui::Window::new("Load PGN")
+                .open(&mut open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.label("Paste a PGN below.");
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button("Open File").clicked()
+                        && let Some(text) = crate::files::open_text("pgn")
+                    {
+                        loader.text = text;
+                    }
+                    egui::ScrollArea::vertical()
+                        .max_height(300.0)
+                        .show(ui, |ui| {
+                            ui.add(
+                                egui::TextEdit::multiline(&mut loader.text)
+                                    .code_editor()
+                                    .desired_rows(10),
+                            );
+                        });
+                    if let Some(error) = &loader.error {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+                    if ui.button("Start").clicked() {
+                        match crate::grid::chess::pgn::import(&loader.text) {
+                            Ok(game) => start = Some(game),
+                            Err(error) => loader.error = Some(error),
+                        }
+                    }
+                });
+        }
+        if !open {
+            self.pgn_loader = None;
+        }
+        let game = start?;
+        // Chosen here too, so that the menu is left on the game loaded
+        let link = registry::chess_link(game.logic());
+        self.choose(&link);
+        Some(self.start_chess_game(ctx, game, link))
+    }
+}
+
+// Starting a game from the registry with what's chosen in the menu
+pub struct Start<'a> {
+    menu: &'a State,
+    ctx: &'a egui::Context,
+    game: &'a dyn DynGame,
+}
+
+impl Launch for Start<'_> {
+    type Output = Box<dyn AppState>;
+
+    fn launch<G: GridGame + Send>(self, game: Game<G>, setup: &Setup) -> Self::Output {
+        self.menu.start_game(self.ctx, game, setup.link(self.game))
+    }
+
+    fn launch_chess(self, game: Game<Chess>, setup: &Setup) -> Self::Output {
+        self.menu
+            .start_chess_game(self.ctx, game, setup.link(self.game))
+    }
 }
 
 impl AppState for State {
+    fn restore(&mut self, remembered: &Remembered) {
+        if self.restored {
+            return;
+        }
+        self.restored = true;
+        let selections = remembered.menu.clone();
+        if registry::find(&selections.game).is_some() {
+            self.game_selection = selections.game;
+        }
+        // A multi-threaded or external AI can't be had in the browser
+        if selections.ai.is_available(true) {
+            self.ai_selection = selections.ai;
+        }
+        self.players = selections.players;
+        self.random_opening = selections.random_opening;
+        self.custom_position = selections.custom_position;
+        self.sizes = selections.sizes;
+        self.forbidden_moves = selections.forbidden_moves;
+    }
+
+    fn remember(&self, remembered: &mut Remembered) {
+        remembered.menu = self.selections();
+    }
+
     fn update(
         &mut self,
         ctx: &egui::Context,
         frame: &mut eframe::Frame,
+        _appearance: &Appearance,
     ) -> Option<Box<dyn AppState>> {
-        egui::CentralPanel::default()
+        // A game shared as a link is started as soon as the page opens on it
+        if let Some(link) = crate::links::opened()
+            && self.follow(link)
+        {
+            let started = self.start(ctx);
+            if started.is_none() {
+                // Not to be played in whatever is started next instead
+                self.linked = None;
+            }
+            return started;
+        }
+
+        if let Some(new_state) = self.show_pgn_loader(ctx) {
+            return Some(new_state);
+        }
+
+        let mut edit_board = false;
+        let change_state = egui::CentralPanel::default()
             .show(ctx, |ui| {
                 egui::ScrollArea::vertical()
                     .show(ui, |ui| {
                         ui.heading("Which Game?");
 
-                        ui.radio_value(&mut self.game_selection, GameSelection::Chess, "Chess");
-                        ui.radio_value(
-                            &mut self.game_selection,
-                            GameSelection::BerolinaChess,
-                            "Berolina Chess",
-                        );
-                        ui.radio_value(
-                            &mut self.game_selection,
-                            GameSelection::GrasshopperChess,
-                            "Grasshopper Chess",
-                        );
+                        for game in registry::games() {
+                            if ui
+                                .radio(self.game_selection == game.key(), game.name())
+                                .clicked()
+                            {
+                                self.game_selection = game.key().to_string();
+                                self.setup_error = None;
+                            }
+                            if self.game_selection == game.key() {
+                                edit_board |= self.show_setup(ui, &**game);
+                            }
+                        }
 
                         ui.separator();
                         ui.heading("Which AI?");
@@ -85,71 +880,290 @@ Alpha-Beta Multi-Threaded is not supported on WASM. Build and run natively to us
                             "Alpha-Beta Single Thread",
                         );
                         ui.radio_value(&mut self.ai_selection, AiSelection::Random, "Random Moves");
+                        #[cfg(not(target_arch = "wasm32"))]
+                        ui.add_enabled(
+                            self.is_chess_selected(),
+                            egui::RadioButton::new(
+                                self.ai_selection == AiSelection::UciEngine,
+                                "External UCI Engine",
+                            ),
+                        )
+                        .on_hover_text(
+                            "Runs the engine named by the UCI_ENGINE environment variable, or stockfish.",
+                        )
+                        .on_disabled_hover_text("External engines can only play chess.")
+                        .clicked()
+                        .then(|| self.ai_selection = AiSelection::UciEngine);
                         ui.radio_value(&mut self.ai_selection, AiSelection::Null, "None");
 
-                        ui.separator();
-
-                        if ui.button("Start").clicked() {
-                            return match self.game_selection {
-                                GameSelection::Chess
-                                | GameSelection::BerolinaChess
-                                | GameSelection::GrasshopperChess => {
-                                    let game_logic = match self.game_selection {
-                                        GameSelection::Chess => crate::grid::chess::Chess::Standard,
-                                        GameSelection::BerolinaChess => {
-                                            crate::grid::chess::Chess::Berolina
-                                        }
-                                        GameSelection::GrasshopperChess => {
-                                            crate::grid::chess::Chess::Grasshopper
-                                        }
-                                    };
-                                    match self.ai_selection {
-                                        AiSelection::Null => {
-                                            Some(Box::new(crate::grid::ui::State::<
-                                                _,
-                                                crate::ai::null::NullAi<_>,
-                                            >::new(
-                                                ctx, game_logic
-                                            ))
-                                                as Box<dyn AppState>)
-                                        }
-                                        AiSelection::Random => {
-                                            Some(Box::new(crate::grid::ui::State::<
-                                                _,
-                                                crate::ai::random::Random<_>,
-                                            >::new(
-                                                ctx, game_logic
-                                            ))
-                                                as Box<dyn AppState>)
-                                        }
-                                        AiSelection::AlphaBetaMultiThread => {
-                                            #[cfg(not(target_arch = "wasm32"))]
+                        if self.versus.is_none()
+                            && self.tournament.is_none()
+                            && self.network.is_none()
+                        {
+                            ui.separator();
+                            ui.heading("Who Plays?");
+                            let ai_available = self.ai_selection != AiSelection::Null;
+                            egui::Grid::new("players_grid")
+                                .num_columns(3)
+                                .show(ui, |ui| {
+                                    for (idx, side) in ["First player", "Second player"].into_iter().enumerate() {
+                                        let controller = &mut self.players[idx];
+                                        ui.label(side);
+                                        ui.horizontal(|ui| {
+                                            ui.radio_value(controller, Controller::Human, "Human");
+                                            if ui
+                                                .add_enabled(
+                                                    ai_available,
+                                                    egui::RadioButton::new(
+                                                        matches!(controller, Controller::Ai(_)),
+                                                        "AI",
+                                                    ),
+                                                )
+                                                .on_disabled_hover_text(
+                                                    "Choose an AI above to have it play.",
+                                                )
+                                                .clicked()
+                                                && *controller == Controller::Human
                                             {
-                                                Some(Box::new(crate::grid::ui::State::<
-                                                    _,
-                                                    crate::ai::alphabeta::multithreaded::AlphaBeta<
-                                                        _,
-                                                    >,
-                                                >::new(
-                                                    ctx, game_logic
-                                                ))
-                                                    as Box<dyn AppState>)
+                                                *controller = Controller::Ai(10);
                                             }
-                                            #[cfg(target_arch = "wasm32")]
-                                            unreachable!()
+                                        });
+                                        if let Controller::Ai(move_time) = controller {
+                                            ui.add(
+                                                egui::Slider::new(move_time, 1..=60)
+                                                    .text("seconds per move"),
+                                            );
                                         }
-                                        AiSelection::AlphaBetaSingleThread => {
-                                            Some(Box::new(crate::grid::ui::State::<
-                                                _,
-                                                crate::ai::alphabeta::singlethreaded::AlphaBeta<_>,
-                                            >::new(
-                                                ctx, game_logic
-                                            ))
-                                                as Box<dyn AppState>)
+                                        ui.end_row();
+                                    }
+                                });
+                            if !ai_available {
+                                self.players = [Controller::Human, Controller::Human];
+                            }
+                        }
+
+                        let mut versus = self.versus.is_some();
+                        if ui
+                            .checkbox(&mut versus, "AI vs AI match")
+                            .on_hover_text("The AI chosen above plays an opponent, taking turns to go first.")
+                            .changed()
+                        {
+                            self.versus = versus.then(MatchSettings::default);
+                            self.tournament = None;
+                            self.network = None;
+                        }
+                        let chess = self.is_chess_selected();
+                        if let Some(settings) = &mut self.versus {
+                            egui::ComboBox::from_label("Opponent")
+                                .selected_text(settings.opponent.name())
+                                .show_ui(ui, |ui| {
+                                    for opponent in AiSelection::OPPONENTS {
+                                        if opponent.is_available(chess) {
+                                            ui.selectable_value(
+                                                &mut settings.opponent,
+                                                opponent,
+                                                opponent.name(),
+                                            );
                                         }
                                     }
+                                });
+                            ui.add(
+                                egui::Slider::new(&mut settings.move_times[0], 1..=60)
+                                    .text("seconds per move"),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut settings.move_times[1], 1..=60)
+                                    .text("seconds per move for the opponent"),
+                            );
+                            ui.add(egui::Slider::new(&mut settings.games, 1..=20).text("games"));
+                        }
+
+                        let mut tournament = self.tournament.is_some();
+                        if ui
+                            .checkbox(&mut tournament, "Tournament")
+                            .on_hover_text(
+                                "Several AIs play matches against each other, \
+for an estimate of how much stronger each is.",
+                            )
+                            .changed()
+                        {
+                            self.tournament = tournament.then(TournamentSettings::default);
+                            self.versus = None;
+                            self.network = None;
+                        }
+                        if let Some(settings) = &mut self.tournament {
+                            let mut remove = None;
+                            let removable = settings.entrants.len() > 2;
+                            for (idx, (selection, strength)) in
+                                settings.entrants.iter_mut().enumerate()
+                            {
+                                ui.horizontal(|ui| {
+                                    egui::ComboBox::from_id_salt(("entrant_ai", idx))
+                                        .selected_text(selection.name())
+                                        .show_ui(ui, |ui| {
+                                            for option in AiSelection::OPPONENTS {
+                                                if option.is_available(chess) {
+                                                    ui.selectable_value(
+                                                        selection,
+                                                        option,
+                                                        option.name(),
+                                                    );
+                                                }
+                                            }
+                                        });
+                                    egui::ComboBox::from_id_salt(("entrant_strength", idx))
+                                        .selected_text(strength.name)
+                                        .show_ui(ui, |ui| {
+                                            for option in Strength::LEVELS {
+                                                ui.selectable_value(strength, option, option.name);
+                                            }
+                                        });
+                                    if ui
+                                        .add_enabled(removable, egui::Button::new("Remove"))
+                                        .clicked()
+                                    {
+                                        remove = Some(idx);
+                                    }
+                                });
+                            }
+                            if let Some(idx) = remove {
+                                settings.entrants.remove(idx);
+                            }
+                            if ui.button("Add Entrant").clicked() {
+                                settings
+                                    .entrants
+                                    .push((AiSelection::AlphaBetaSingleThread, Strength::FULL));
+                            }
+                            ui.horizontal(|ui| {
+                                ui.radio_value(
+                                    &mut settings.format,
+                                    Format::RoundRobin,
+                                    "Round robin",
+                                );
+                                ui.radio_value(&mut settings.format, Format::Gauntlet, "Gauntlet")
+                                    .on_hover_text("Only the first entrant plays the others.");
+                            });
+                            ui.add(
+                                egui::Slider::new(&mut settings.move_time, 1..=60)
+                                    .text("seconds per move"),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut settings.games_per_match, 1..=20)
+                                    .text("games per match"),
+                            );
+                        }
+
+                        let mut network = self.network.is_some();
+                        if ui
+                            .checkbox(&mut network, "Play over the network")
+                            .on_hover_text(
+                                "Host a game for someone else to join, or join theirs. \
+Both choose the same game above.",
+                            )
+                            .changed()
+                        {
+                            self.network = network.then(NetworkSettings::default);
+                            self.versus = None;
+                            self.tournament = None;
+                        }
+                        if let Some(settings) = &mut self.network {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            ui.horizontal(|ui| {
+                                ui.radio_value(&mut settings.online, false, "Local network");
+                                ui.radio_value(&mut settings.online, true, "Online")
+                                    .on_hover_text("Through a relay server, which is run as `relay`.");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.radio_value(&mut settings.role, NetworkRole::Host, "Host");
+                                ui.radio_value(&mut settings.role, NetworkRole::Join, "Join");
+                                ui.radio_value(&mut settings.role, NetworkRole::Watch, "Watch")
+                                    .on_hover_text(
+                                        "Watch a game between two others, analysing it with the AI chosen above if you like.",
+                                    );
+                            });
+                            if settings.is_online() {
+                                ui.horizontal(|ui| {
+                                    ui.label("Server");
+                                    ui.text_edit_singleline(&mut settings.server);
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Room");
+                                    ui.text_edit_singleline(&mut settings.room)
+                                        .on_hover_text("Any name, which both players give to meet.");
+                                });
+                            }
+                            #[cfg(not(target_arch = "wasm32"))]
+                            if !settings.online {
+                                if settings.role == NetworkRole::Host {
+                                    ui.horizontal(|ui| {
+                                        ui.label("Port");
+                                        ui.add(
+                                            egui::DragValue::new(&mut settings.port)
+                                                .range(1024..=65535),
+                                        );
+                                    });
+                                } else {
+                                    ui.horizontal(|ui| {
+                                        ui.label("Address");
+                                        ui.text_edit_singleline(&mut settings.address)
+                                            .on_hover_text(format!(
+                                                "The host's address, with the port after a colon if it isn't {}",
+                                                crate::net::tcp::DEFAULT_PORT
+                                            ));
+                                    });
                                 }
-                            };
+                            }
+                            if settings.role == NetworkRole::Host {
+                                ui.horizontal(|ui| {
+                                    ui.label("You play");
+                                    ui.radio_value(
+                                        &mut settings.guest,
+                                        crate::game::Player::Second,
+                                        "First player",
+                                    );
+                                    ui.radio_value(
+                                        &mut settings.guest,
+                                        crate::game::Player::First,
+                                        "Second player",
+                                    );
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.checkbox(&mut settings.clock, "Clock");
+                                    ui.add_enabled(
+                                        settings.clock,
+                                        egui::Slider::new(&mut settings.clock_minutes, 1..=60)
+                                            .text("minutes"),
+                                    );
+                                    ui.add_enabled(
+                                        settings.clock,
+                                        egui::Slider::new(&mut settings.clock_increment, 0..=30)
+                                            .text("increment"),
+                                    );
+                                });
+                            }
+                        }
+
+                        ui.separator();
+
+                        if ui.button("Start").clicked() {
+                            return self.start(ctx);
+                        }
+
+                        if ui.button("Load PGN").clicked() {
+                            self.pgn_loader = Some(PgnLoader::default());
+                        }
+                        if ui.button("Chess Puzzles").clicked() {
+                            return Some(Box::new(crate::grid::chess::puzzles::State::new(ctx))
+                                as Box<dyn AppState>);
+                        }
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if ui
+                            .button("Lichess")
+                            .on_hover_text("Play your games on Lichess here.")
+                            .clicked()
+                        {
+                            return Some(Box::new(crate::grid::chess::lichess::State::default())
+                                as Box<dyn AppState>);
                         }
 
                         ui.separator();
@@ -166,6 +1180,15 @@ Alpha-Beta Multi-Threaded is not supported on WASM. Build and run natively to us
                     })
                     .inner
             })
-            .inner
+            .inner;
+        if edit_board {
+            let fen = self.custom_position.clone();
+            return Some(Box::new(crate::grid::chess::editor::State::new(
+                ctx,
+                std::mem::take(self),
+                &fen,
+            )));
+        }
+        change_state
     }
 }
@@ -0,0 +1,397 @@
+use std::{marker::PhantomData, sync::mpsc::Receiver};
+
+use crate::{
+    ai::{Ai, null::NullAi},
+    game::{Game, Player, SavedClocks, SavedGame},
+    grid::{GridGame, theme::Appearance},
+    net::{
+        online::{self, OnlineConnection},
+        protocol::{Connection, Message, VERSION, check_hello},
+        spectators::Audience,
+    },
+    root::AppState,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::net::tcp::{self, Host, TcpConnection};
+
+// What the player here does in a game over the network
+pub enum Role {
+    // Decides the game for both players, the guest playing `guest`
+    Host {
+        guest: Player,
+        clocks: Option<SavedClocks>,
+    },
+    Join,
+    // Watches a game between two others
+    Watch,
+}
+
+// What the host decides for both players
+struct Hosting {
+    // The side the guest plays
+    guest: Player,
+    clocks: Option<SavedClocks>,
+}
+
+// How the connection to the other player is being made
+enum Waiting {
+    // Hosting on the local network until someone joins
+    #[cfg(not(target_arch = "wasm32"))]
+    Listening(Host),
+    #[cfg(not(target_arch = "wasm32"))]
+    Joining(Receiver<Result<TcpConnection, String>>),
+    Online(Receiver<Result<OnlineConnection, String>>),
+    // Connected, or given up on
+    Done,
+}
+
+// How far setting up the game has got once connected
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    // A spectator waits here for the host to send the game
+    Greeting,
+    // The guest waits for the host to send the game
+    AwaitingSetup,
+    // The host waits for the guest to have loaded it
+    AwaitingReady,
+}
+
+/*
+Setting up a game over the network, before it starts.
+Once connected the two ends greet each other with their protocol versions, then the host sends the game and the guest loads it into the same game chosen in its own menu.
+A spectator greets the host with `Watch` and starts watching once sent the game, analysing it with an `A` if asked to.
+ */
+pub struct State<G: GridGame, A: Ai<G>> {
+    game: Game<G>,
+    hosting: Option<Hosting>,
+    watching: bool,
+    waiting: Waiting,
+    // Where spectators connect to a game hosted on the local network, kept from when the guest joined
+    #[cfg(not(target_arch = "wasm32"))]
+    listener: Option<Host>,
+    // Someone watching through the relay asked for the game before it started
+    watched: bool,
+    // Where the game is, to tell the other player
+    details: Vec<String>,
+    connection: Option<Box<dyn Connection>>,
+    stage: Stage,
+    // Why the game couldn't be set up, after which nothing more is tried
+    error: Option<String>,
+    ai: PhantomData<fn() -> A>,
+}
+
+impl<G: GridGame, A: Ai<G> + 'static> State<G, A> {
+    fn new(game: Game<G>, hosting: Option<Hosting>, waiting: Waiting) -> Self {
+        Self {
+            game,
+            hosting,
+            watching: false,
+            waiting,
+            #[cfg(not(target_arch = "wasm32"))]
+            listener: None,
+            watched: false,
+            details: vec![],
+            connection: None,
+            stage: Stage::Greeting,
+            error: None,
+            ai: PhantomData,
+        }
+    }
+
+    // Host a game on the local network for someone to join and play `guest`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn host(game: Game<G>, port: u16, guest: Player, clocks: Option<SavedClocks>) -> Self {
+        let hosting = Some(Hosting { guest, clocks });
+        match Host::bind(port) {
+            Ok(host) => {
+                let mut details = vec![format!("Hosting on port {}", host.port())];
+                if let Some(address) = tcp::local_address() {
+                    details.push(format!(
+                        "The other player joins at {address}:{}",
+                        host.port()
+                    ));
+                }
+                Self {
+                    details,
+                    ..Self::new(game, hosting, Waiting::Listening(host))
+                }
+            }
+            Err(err) => Self {
+                error: Some(err),
+                ..Self::new(game, hosting, Waiting::Done)
+            },
+        }
+    }
+
+    // Join a game hosted on the local network, or watch it
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn join(game: Game<G>, address: &str, watching: bool) -> Self {
+        let verb = if watching { "Watching" } else { "Joining" };
+        Self {
+            watching,
+            details: vec![format!("{verb} {address}")],
+            ..Self::new(game, None, Waiting::Joining(tcp::join(address)))
+        }
+    }
+
+    // Meet the other player in `room` on the relay at `server`, or watch the game there
+    pub fn online(game: Game<G>, server: &str, room: &str, role: Role) -> Self {
+        let watching = matches!(role, Role::Watch);
+        let hosting = match role {
+            Role::Host { guest, clocks } => Some(Hosting { guest, clocks }),
+            Role::Join | Role::Watch => None,
+        };
+        Self {
+            watching,
+            details: vec![format!(
+                "Room {} on {}",
+                online::room_name(room),
+                server.trim()
+            )],
+            ..Self::new(
+                game,
+                hosting,
+                Waiting::Online(online::connect(server, room, watching)),
+            )
+        }
+    }
+
+    fn connect(&mut self, mut connection: Box<dyn Connection>) -> Result<(), String> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Waiting::Listening(host) = std::mem::replace(&mut self.waiting, Waiting::Done) {
+            self.listener = Some(host);
+        }
+        self.waiting = Waiting::Done;
+        if self.watching {
+            connection.send(&Message::Watch { version: VERSION })?;
+        } else {
+            connection.send(&Message::Hello { version: VERSION })?;
+        }
+        self.connection = Some(connection);
+        Ok(())
+    }
+
+    // The game as the host sends it, with its clocks
+    fn setup(&self) -> Option<Message> {
+        let hosting = self.hosting.as_ref()?;
+        let mut game = self.game.save();
+        game.clocks = hosting.clocks.clone();
+        Some(Message::Setup {
+            guest: hosting.guest,
+            game,
+        })
+    }
+
+    // Carry on setting up the game, which is ready to play once it's returned
+    fn poll(&mut self, ctx: &egui::Context) -> Result<Option<Box<dyn AppState>>, String> {
+        let connected: Option<Result<Box<dyn Connection>, String>> = match &self.waiting {
+            #[cfg(not(target_arch = "wasm32"))]
+            Waiting::Listening(host) => host
+                .accept()
+                .map(|accepted| accepted.map(|connection| Box::new(connection) as _)),
+            #[cfg(not(target_arch = "wasm32"))]
+            Waiting::Joining(joining) => joining
+                .try_recv()
+                .ok()
+                .map(|joined| joined.map(|connection| Box::new(connection) as _)),
+            Waiting::Online(connecting) => connecting
+                .try_recv()
+                .ok()
+                .map(|connected| connected.map(|connection| Box::new(connection) as _)),
+            Waiting::Done => None,
+        };
+        if let Some(connected) = connected {
+            self.connect(connected?)?;
+        }
+
+        let setup = self.setup();
+        while let Some(connection) = &mut self.connection
+            && let Some(message) = connection.receive()
+        {
+            match (self.stage, message?) {
+                (_, Message::Bye { reason }) => {
+                    return Err(reason.unwrap_or_else(|| "The other player left".to_string()));
+                }
+                (_, Message::Spectate { game }) if self.watching => {
+                    let loaded = self.load(&game, "watch")?;
+                    return Ok(Some(self.start_watching(ctx, loaded, &game)));
+                }
+                // Spectators through the relay are shown everything else the players send
+                (_, _) if self.watching => {}
+                // Whoever connected first on the local network wants to watch, so carry on waiting for the guest
+                #[cfg(not(target_arch = "wasm32"))]
+                (Stage::Greeting, Message::Watch { .. }) if self.listener.is_some() => {
+                    let _ = connection.send(&Message::Bye {
+                        reason: Some("The game hasn't started yet".to_string()),
+                    });
+                    self.connection = None;
+                    self.waiting = Waiting::Listening(self.listener.take().unwrap());
+                }
+                // Sent on to the host once the game has started
+                (_, Message::Watch { .. }) => self.watched = true,
+                (Stage::Greeting, message) => {
+                    check_hello(&message)?;
+                    match &setup {
+                        Some(setup) => {
+                            connection.send(setup)?;
+                            self.stage = Stage::AwaitingReady;
+                        }
+                        None => self.stage = Stage::AwaitingSetup,
+                    }
+                }
+                // The other end reconnected while setting up, and may have missed what was sent
+                (Stage::AwaitingSetup, Message::Hello { .. }) => {
+                    connection.send(&Message::Hello { version: VERSION })?;
+                }
+                (Stage::AwaitingReady, Message::Hello { .. }) => {
+                    if let Some(setup) = &setup {
+                        connection.send(setup)?;
+                    }
+                }
+                (Stage::AwaitingSetup, Message::Setup { guest, game }) => {
+                    let loaded = self.load(&game, "join")?;
+                    let Some(connection) = &mut self.connection else {
+                        unreachable!()
+                    };
+                    connection.send(&Message::Ready)?;
+                    return Ok(Some(self.start(ctx, loaded, guest.flip(), game.clocks)));
+                }
+                (Stage::AwaitingReady, Message::Ready) => {
+                    let Some(hosting) = &self.hosting else {
+                        unreachable!()
+                    };
+                    let (guest, clocks) = (hosting.guest, hosting.clocks.clone());
+                    return Ok(Some(self.start(ctx, self.game.clone(), guest, clocks)));
+                }
+                // The relay saying the other player's connection dropped, who'll be back
+                (_, Message::Away) => {}
+                (_, message) => {
+                    return Err(format!("Unexpected message while setting up: {message:?}"));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // The game the host sent, if it's the one chosen here, telling the host why not if it isn't
+    fn load(&mut self, game: &SavedGame, to: &str) -> Result<Game<G>, String> {
+        let loaded = if game.rules == format!("{:?}", self.game.logic()) {
+            Game::load(self.game.logic().clone(), game)
+        } else {
+            Err(format!(
+                "The host is playing {}, which needs choosing in the menu to {to}",
+                game.rules
+            ))
+        };
+        if let Err(err) = &loaded
+            && let Some(connection) = &mut self.connection
+        {
+            let _ = connection.send(&Message::Bye {
+                reason: Some(format!("The game couldn't be set up: {err}")),
+            });
+        }
+        loaded
+    }
+
+    // The game against `remote`, the player at the other end
+    fn start(
+        &mut self,
+        ctx: &egui::Context,
+        game: Game<G>,
+        remote: Player,
+        clocks: Option<SavedClocks>,
+    ) -> Box<dyn AppState> {
+        let connection = self.connection.take().unwrap();
+        let mut state = crate::grid::ui::State::<G, NullAi<G>>::new(ctx, game).with_remote(
+            connection,
+            remote,
+            self.hosting.is_some(),
+            clocks.as_ref(),
+        );
+        if self.hosting.is_some() {
+            #[cfg(not(target_arch = "wasm32"))]
+            let mut audience = Audience::new(self.listener.take());
+            #[cfg(target_arch = "wasm32")]
+            let mut audience = Audience::default();
+            if self.watched {
+                audience.watched_through_relay();
+            }
+            state = state.with_audience(audience);
+        }
+        Box::new(state)
+    }
+
+    // Watching the game the host sent
+    fn start_watching(
+        &mut self,
+        ctx: &egui::Context,
+        game: Game<G>,
+        saved: &SavedGame,
+    ) -> Box<dyn AppState> {
+        let connection = self.connection.take().unwrap();
+        Box::new(
+            crate::grid::ui::State::<G, A>::new(ctx, game)
+                .with_spectating(connection, saved.clocks.as_ref()),
+        )
+    }
+
+    fn status(&self) -> &'static str {
+        match (&self.waiting, self.stage) {
+            #[cfg(not(target_arch = "wasm32"))]
+            (Waiting::Listening(_), _) => "Waiting for someone to join",
+            #[cfg(not(target_arch = "wasm32"))]
+            (Waiting::Joining(_), _) => "Connecting",
+            (Waiting::Online(_), _) => "Connecting",
+            (Waiting::Done, Stage::Greeting) if self.watching => "Waiting for the game",
+            (Waiting::Done, Stage::Greeting) => "Waiting for the other player",
+            (Waiting::Done, _) => "Setting up the game",
+        }
+    }
+}
+
+impl<G: GridGame, A: Ai<G> + 'static> AppState for State<G, A> {
+    fn update(
+        &mut self,
+        ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+        _appearance: &Appearance,
+    ) -> Option<Box<dyn AppState>> {
+        let mut change_state = None;
+        if self.error.is_none() {
+            match self.poll(ctx) {
+                Ok(started) => change_state = started,
+                Err(err) => {
+                    self.error = Some(err);
+                    self.waiting = Waiting::Done;
+                    self.connection = None;
+                }
+            }
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Network Game");
+            for detail in &self.details {
+                ui.label(detail);
+            }
+            if let Some(error) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            } else {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(self.status());
+                });
+            }
+            if ui.button("Back to Menu").clicked() {
+                if let Some(connection) = &mut self.connection {
+                    let _ = connection.send(&Message::Bye { reason: None });
+                }
+                change_state = Some(Box::new(crate::menu::State::default()) as Box<dyn AppState>);
+            }
+        });
+
+        ctx.request_repaint();
+
+        change_state
+    }
+}
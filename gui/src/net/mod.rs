@@ -0,0 +1,12 @@
+// Playing against someone else's copy of the app over the network, with everything that happens in the game sent as `protocol::Message`s
+
+pub mod lobby;
+pub mod online;
+pub mod protocol;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod relay;
+pub mod spectators;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tcp;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod websocket;
@@ -0,0 +1,414 @@
+use std::sync::mpsc::{Receiver, channel};
+
+use crate::{
+    ai::strength::mix64,
+    net::protocol::{Connection, Message, VERSION, decode, encode},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    io::BufReader,
+    net::TcpStream,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::TryRecvError,
+    },
+    time::Duration,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::net::websocket;
+
+// Where the relay is looked for unless another is given
+pub const DEFAULT_SERVER: &str = "ws://localhost:4124";
+
+// How many times connecting again is tried after losing the connection, and how long apart
+const RETRIES: usize = 30;
+const RETRY_SECONDS: i64 = 2;
+
+// Room names are kept to what can go in an address as it is
+pub fn room_name(room: &str) -> String {
+    room.trim()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
+
+// The address of `room` on the relay at `server`, for a spectator or the player in a new seat
+fn room_url(server: &str, room: &str, watching: bool) -> String {
+    let query = if watching {
+        "watch".to_string()
+    } else {
+        format!("seat={}", new_seat())
+    };
+    format!(
+        "{}/{}?{query}",
+        server.trim().trim_end_matches('/'),
+        room_name(room)
+    )
+}
+
+// Sent on getting the connection back, so that the host sends the game to catch up with
+fn greeting(watching: bool) -> Message {
+    if watching {
+        Message::Watch { version: VERSION }
+    } else {
+        Message::Hello { version: VERSION }
+    }
+}
+
+// Tells this copy of the app apart from the other player's, so that it gets its own seat back on reconnecting
+fn new_seat() -> String {
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+    format!("{:016x}", mix64(now))
+}
+
+/*
+A connection to the other player through the relay, over a WebSocket.
+On losing it, connecting again is tried in the background, and on getting back `Hello` or `Watch` is sent so that the game is sent to catch up with.
+Messages sent while it's lost are dropped, as catching up covers them.
+ */
+#[cfg(not(target_arch = "wasm32"))]
+pub struct OnlineConnection {
+    // The stream to send on, while connected
+    writer: Arc<Mutex<Option<TcpStream>>>,
+    incoming: Receiver<Result<Message, String>>,
+    reconnecting: Arc<AtomicBool>,
+    // Stops connecting again once this end has left
+    closed: Arc<AtomicBool>,
+}
+
+// The `host:port` and path of a `ws://` address
+#[cfg(not(target_arch = "wasm32"))]
+fn split_url(url: &str) -> Result<(String, String), String> {
+    let rest = url
+        .strip_prefix("ws://")
+        .ok_or_else(|| format!("{url} doesn't start with ws://, which is needed here"))?;
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, "/"),
+    };
+    let authority = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{authority}:80")
+    };
+    Ok((authority, path.to_string()))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn open(authority: &str, path: &str) -> Result<(BufReader<TcpStream>, TcpStream), String> {
+    let error = |err: std::io::Error| format!("Can't connect to {authority}: {err}");
+    let addr = std::net::ToSocketAddrs::to_socket_addrs(authority)
+        .map_err(error)?
+        .next()
+        .ok_or_else(|| format!("Can't find {authority}"))?;
+    let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(10)).map_err(error)?;
+    stream.set_nodelay(true).map_err(error)?;
+    let writer = stream.try_clone().map_err(error)?;
+    let mut reader = BufReader::new(stream);
+    websocket::open(&mut reader, authority, path).map_err(error)?;
+    Ok((reader, writer))
+}
+
+/*
+Join `room` on the relay at `server`, which is a `ws://` address, to play or to watch.
+Connecting happens in the background, and the connection or why it failed is sent once it's done.
+ */
+#[cfg(not(target_arch = "wasm32"))]
+pub fn connect(
+    server: &str,
+    room: &str,
+    watching: bool,
+) -> Receiver<Result<OnlineConnection, String>> {
+    let url = room_url(server, room, watching);
+    let (sender, receiver) = channel();
+    std::thread::spawn(move || {
+        let opened = split_url(&url).and_then(|(authority, path)| {
+            let opened = open(&authority, &path)?;
+            Ok((opened, authority, path))
+        });
+        let ((mut reader, writer), authority, path) = match opened {
+            Ok(opened) => opened,
+            Err(err) => {
+                let _ = sender.send(Err(err));
+                return;
+            }
+        };
+        let (messages, incoming) = channel();
+        let writer = Arc::new(Mutex::new(Some(writer)));
+        let reconnecting = Arc::new(AtomicBool::new(false));
+        let closed = Arc::new(AtomicBool::new(false));
+        let connection = OnlineConnection {
+            writer: writer.clone(),
+            incoming,
+            reconnecting: reconnecting.clone(),
+            closed: closed.clone(),
+        };
+        if sender.send(Ok(connection)).is_err() {
+            return;
+        }
+
+        loop {
+            while let Ok(Some(text)) = websocket::read_text(&mut reader) {
+                let message = decode(&text);
+                // Nothing more comes after leaving, or after something unreadable
+                let last = message.is_err() || matches!(message, Ok(Message::Bye { .. }));
+                if messages.send(message).is_err() || last {
+                    return;
+                }
+            }
+            *writer.lock().unwrap() = None;
+            if closed.load(Ordering::Relaxed) {
+                return;
+            }
+            reconnecting.store(true, Ordering::Relaxed);
+            let mut reopened = None;
+            for _ in 0..RETRIES {
+                std::thread::sleep(Duration::from_secs(RETRY_SECONDS as u64));
+                if closed.load(Ordering::Relaxed) {
+                    return;
+                }
+                if let Ok(opened) = open(&authority, &path) {
+                    reopened = Some(opened);
+                    break;
+                }
+            }
+            let Some((new_reader, mut new_writer)) = reopened else {
+                let _ = messages.send(Err("Lost the connection to the server".to_string()));
+                return;
+            };
+            let greeting = encode(&greeting(watching));
+            let _ = websocket::write_text(&mut new_writer, &greeting, Some(websocket::new_mask()));
+            reader = new_reader;
+            *writer.lock().unwrap() = Some(new_writer);
+            reconnecting.store(false, Ordering::Relaxed);
+        }
+    });
+    receiver
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for OnlineConnection {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        if let Some(stream) = self.writer.lock().unwrap().take() {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Connection for OnlineConnection {
+    fn send(&mut self, message: &Message) -> Result<(), String> {
+        // A failed send is noticed by the reading thread losing the connection too
+        if let Some(stream) = self.writer.lock().unwrap().as_mut() {
+            let _ = websocket::write_text(stream, &encode(message), Some(websocket::new_mask()));
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Option<Result<Message, String>> {
+        match self.incoming.try_recv() {
+            Ok(message) => Some(message),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("The connection was closed".to_string())),
+        }
+    }
+
+    fn reconnecting(&self) -> bool {
+        self.reconnecting.load(Ordering::Relaxed)
+    }
+}
+
+// What the browser's WebSocket tells its handlers
+#[cfg(target_arch = "wasm32")]
+enum Event {
+    Opened,
+    Text(String),
+    Closed,
+}
+
+/*
+A connection to the other player through the relay, over the browser's WebSocket.
+Messages sent before it first opens wait until it does. On losing it, connecting again is tried as messages are looked for, and on getting back `Hello` or `Watch` is sent so that the game is sent to catch up with.
+Messages sent while it's lost are dropped, as catching up covers them.
+ */
+#[cfg(target_arch = "wasm32")]
+pub struct OnlineConnection {
+    url: String,
+    watching: bool,
+    socket: web_sys::WebSocket,
+    // Kept for as long as the socket may call them
+    handlers: Vec<eframe::wasm_bindgen::closure::Closure<dyn FnMut(eframe::wasm_bindgen::JsValue)>>,
+    events: Receiver<Event>,
+    open: bool,
+    // Whether it has ever opened, as not getting through at all isn't worth trying again
+    opened: bool,
+    outbox: Vec<String>,
+    // When to try connecting again, while the connection is lost
+    retry_at: Option<chrono::DateTime<chrono::Utc>>,
+    retries_left: usize,
+    failed: Option<String>,
+}
+
+#[cfg(target_arch = "wasm32")]
+type Opened = (
+    web_sys::WebSocket,
+    Vec<eframe::wasm_bindgen::closure::Closure<dyn FnMut(eframe::wasm_bindgen::JsValue)>>,
+    Receiver<Event>,
+);
+
+#[cfg(target_arch = "wasm32")]
+fn open_socket(url: &str) -> Result<Opened, String> {
+    use eframe::wasm_bindgen::{JsCast as _, JsValue, closure::Closure};
+
+    let socket =
+        web_sys::WebSocket::new(url).map_err(|err| format!("Can't connect to {url}: {err:?}"))?;
+    let (sender, events) = channel();
+    let handler = |event: fn(JsValue) -> Option<Event>| {
+        let sender = sender.clone();
+        Closure::<dyn FnMut(JsValue)>::new(move |value: JsValue| {
+            if let Some(event) = event(value) {
+                let _ = sender.send(event);
+            }
+        })
+    };
+    let on_open = handler(|_| Some(Event::Opened));
+    let on_message = handler(|value| {
+        value
+            .dyn_into::<web_sys::MessageEvent>()
+            .ok()?
+            .data()
+            .as_string()
+            .map(Event::Text)
+    });
+    let on_close = handler(|_| Some(Event::Closed));
+    socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+    Ok((socket, vec![on_open, on_message, on_close], events))
+}
+
+// Join `room` on the relay at `server`, which is a `ws://` or `wss://` address, to play or to watch
+#[cfg(target_arch = "wasm32")]
+pub fn connect(
+    server: &str,
+    room: &str,
+    watching: bool,
+) -> Receiver<Result<OnlineConnection, String>> {
+    let url = room_url(server, room, watching);
+    let (sender, receiver) = channel();
+    let connection = open_socket(&url).map(|(socket, handlers, events)| OnlineConnection {
+        url,
+        watching,
+        socket,
+        handlers,
+        events,
+        open: false,
+        opened: false,
+        outbox: vec![],
+        retry_at: None,
+        retries_left: RETRIES,
+        failed: None,
+    });
+    let _ = sender.send(connection);
+    receiver
+}
+
+#[cfg(target_arch = "wasm32")]
+impl OnlineConnection {
+    // Stop listening to the socket before closing it, as its handlers are about to be dropped
+    fn close_socket(&mut self) {
+        self.socket.set_onopen(None);
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+        let _ = self.socket.close();
+        self.handlers.clear();
+        self.open = false;
+    }
+
+    fn send_text(&mut self, text: String) {
+        if self.open {
+            // A failed send is noticed by the socket closing too
+            let _ = self.socket.send_with_str(&text);
+        } else if !self.opened {
+            self.outbox.push(text);
+        }
+    }
+
+    fn lost(&mut self) -> Option<Result<Message, String>> {
+        self.close_socket();
+        if !self.opened {
+            self.failed = Some(format!("Can't connect to {}", self.url));
+        } else if self.retries_left == 0 {
+            self.failed = Some("Lost the connection to the server".to_string());
+        } else {
+            self.retries_left -= 1;
+            self.retry_at = Some(chrono::Utc::now() + chrono::TimeDelta::seconds(RETRY_SECONDS));
+            return None;
+        }
+        self.failed.clone().map(Err)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Drop for OnlineConnection {
+    fn drop(&mut self) {
+        self.close_socket();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Connection for OnlineConnection {
+    fn send(&mut self, message: &Message) -> Result<(), String> {
+        self.send_text(encode(message));
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Option<Result<Message, String>> {
+        if let Some(failed) = &self.failed {
+            return Some(Err(failed.clone()));
+        }
+        if let Some(at) = self.retry_at {
+            if chrono::Utc::now() < at {
+                return None;
+            }
+            self.retry_at = None;
+            match open_socket(&self.url) {
+                Ok((socket, handlers, events)) => {
+                    self.socket = socket;
+                    self.handlers = handlers;
+                    self.events = events;
+                }
+                Err(_) => return self.lost(),
+            }
+        }
+        loop {
+            match self.events.try_recv() {
+                Ok(Event::Opened) => {
+                    self.open = true;
+                    self.retries_left = RETRIES;
+                    if self.opened {
+                        self.send_text(encode(&greeting(self.watching)));
+                    } else {
+                        self.opened = true;
+                        for text in std::mem::take(&mut self.outbox) {
+                            self.send_text(text);
+                        }
+                    }
+                }
+                Ok(Event::Text(text)) => return Some(decode(&text)),
+                Ok(Event::Closed) | Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    return self.lost();
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => return None,
+            }
+        }
+    }
+
+    fn reconnecting(&self) -> bool {
+        self.opened && !self.open && self.failed.is_none()
+    }
+}
@@ -0,0 +1,113 @@
+use crate::game::{Player, SavedGame};
+
+/*
+Bumped whenever `Message` changes in a way older copies can't read, so that they turn each other away rather than misunderstanding each other.
+ */
+pub const VERSION: u32 = 4;
+
+/*
+What the two ends of a game send each other, one to a line as RON.
+The host and the guest both start with `Hello`, the host then sends the game as `Setup` and the guest answers with `Ready` once it has the same game.
+Moves are written as they display, which is enough to find them among the legal moves of any game.
+A `Hello` during the game means the other end has reconnected, and is answered with `Resync` so that both carry on from whichever game is further on.
+Spectators greet the host with `Watch` instead, and are sent the whole game as `Spectate` then and whenever it changes.
+ */
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Message {
+    Hello { version: u32 },
+    // The side the guest plays, and the game so far with its clocks
+    Setup { guest: Player, game: SavedGame },
+    Ready,
+    // A move by its `GameLogic::move_name`, and the mover's time left afterwards in milliseconds if there are clocks
+    Move { mv: String, remaining: Option<i64> },
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    Resign,
+    // The sender's clock ran out
+    OutOfTime,
+    // The sender's game, to catch up with if it's further on, or to answer with this end's if it's behind
+    Resync { game: SavedGame },
+    // Sent by the relay when the other player's connection drops, until they're back
+    Away,
+    // A spectator's greeting, answered by the host
+    Watch { version: u32 },
+    // The host's game with its clocks, for spectators
+    Spectate { game: SavedGame },
+    // Leaving the game, with why if it's not just closing it
+    Bye { reason: Option<String> },
+}
+
+pub fn encode(message: &Message) -> String {
+    let mut line = ron::to_string(message).expect("Messages can always be written");
+    line.push('\n');
+    line
+}
+
+pub fn decode(line: &str) -> Result<Message, String> {
+    ron::from_str(line.trim()).map_err(|err| format!("Unreadable message: {err}"))
+}
+
+// The reply to the other end's `Hello`, or why it can't be played with
+pub fn check_hello(message: &Message) -> Result<(), String> {
+    match message {
+        Message::Hello { version } if *version == VERSION => Ok(()),
+        Message::Hello { version } => Err(format!(
+            "The other player has a different version of the app (protocol {version}, this is {VERSION})"
+        )),
+        Message::Bye { reason } => Err(reason
+            .clone()
+            .unwrap_or_else(|| "The other player left".to_string())),
+        other => Err(format!("Expected a greeting, not {other:?}")),
+    }
+}
+
+/*
+One end of a connection to the other player, over TCP or, in the browser, a WebSocket.
+Messages arrive in the background, to be picked up each frame.
+ */
+pub trait Connection {
+    fn send(&mut self, message: &Message) -> Result<(), String>;
+
+    // The next message received, if any have come in, or why the connection was lost
+    fn receive(&mut self) -> Option<Result<Message, String>>;
+
+    // Whether the connection was lost and is being made again, for connections which can
+    fn reconnecting(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let game = crate::game::Game::new(crate::grid::connect_four::ConnectFour);
+        for message in [
+            Message::Hello { version: VERSION },
+            Message::Setup {
+                guest: Player::Second,
+                game: game.save(),
+            },
+            Message::Move {
+                mv: "3".to_string(),
+                remaining: Some(1500),
+            },
+            Message::Bye {
+                reason: Some("Left the game\nfor now".to_string()),
+            },
+        ] {
+            let line = encode(&message);
+            assert_eq!(line.matches('\n').count(), 1);
+            assert_eq!(decode(&line), Ok(message));
+        }
+        assert!(
+            check_hello(&Message::Hello {
+                version: VERSION + 1
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::net::{
+    protocol::{Message, encode},
+    websocket,
+};
+
+// The port the relay listens on unless another is chosen
+pub const DEFAULT_PORT: u16 = 4124;
+
+// How many messages are kept for a player who's away, beyond which they catch up by resyncing
+const MAX_WAITING: usize = 1000;
+
+// How long sending to one player may hold up the others
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// One of the two places in a room
+#[derive(Default)]
+struct Seat {
+    // Chosen by the player's copy of the app, so that it gets the same seat on coming back
+    id: Option<String>,
+    // The player's connection, with its number to tell it from any they come back on
+    writer: Option<(usize, TcpStream)>,
+    // Messages for the player which arrived while they were away
+    waiting: Vec<String>,
+}
+
+// A game between two players, who join it by its name, and whoever is watching it
+#[derive(Default)]
+struct Room {
+    seats: [Seat; 2],
+    // The spectators' connections, with their numbers
+    watchers: Vec<(usize, TcpStream)>,
+}
+
+impl Room {
+    // Seat connection `number` as `id`, in its old seat if it's coming back, and pass on what was kept for it
+    fn sit(&mut self, id: &str, number: usize, mut writer: TcpStream) -> Result<usize, TcpStream> {
+        let Some(index) = self
+            .seats
+            .iter()
+            .position(|seat| seat.id.as_deref() == Some(id))
+            .or_else(|| self.seats.iter().position(|seat| seat.id.is_none()))
+        else {
+            return Err(writer);
+        };
+        let seat = &mut self.seats[index];
+        seat.id = Some(id.to_string());
+        for text in seat.waiting.drain(..) {
+            if websocket::write_text(&mut writer, &text, None).is_err() {
+                break;
+            }
+        }
+        if let Some((_, old)) = seat.writer.replace((number, writer)) {
+            let _ = old.shutdown(std::net::Shutdown::Both);
+        }
+        Ok(index)
+    }
+
+    // Pass `text` on to whoever sits in `seat`, or keep it for them
+    fn deliver(&mut self, seat: usize, text: String) {
+        let seat = &mut self.seats[seat];
+        if let Some((_, writer)) = &mut seat.writer
+            && websocket::write_text(writer, &text, None).is_ok()
+        {
+            return;
+        }
+        if seat.waiting.len() < MAX_WAITING {
+            seat.waiting.push(text);
+        }
+    }
+
+    // Pass `text` on to every spectator, dropping any who can't be reached
+    fn show(&mut self, text: &str) {
+        self.watchers
+            .retain_mut(|(_, writer)| websocket::write_text(writer, text, None).is_ok());
+    }
+}
+
+type Rooms = Arc<Mutex<HashMap<String, Room>>>;
+
+/*
+Passes the messages of online games between their two players, for copies of the app which can't reach each other directly such as in the browser.
+Players join a room by its name over a WebSocket, and everything either sends is passed to the other without being looked at.
+Someone whose connection drops keeps their seat, and gets what was sent while they were away on coming back.
+Spectators can join a room too. They're shown everything the players send, and what they send goes to both players.
+ */
+pub struct Relay {
+    listener: TcpListener,
+    rooms: Rooms,
+}
+
+impl Relay {
+    pub fn bind(port: u16) -> Result<Self, String> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .map_err(|err| format!("Can't listen on port {port}: {err}"))?;
+        Ok(Self {
+            listener,
+            rooms: Rooms::default(),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.listener
+            .local_addr()
+            .map_or(DEFAULT_PORT, |addr| addr.port())
+    }
+
+    // Serve players for as long as the relay runs, each on their own thread
+    pub fn serve(self) {
+        for (number, stream) in self.listener.incoming().enumerate() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let rooms = self.rooms.clone();
+            std::thread::spawn(move || {
+                if let Err(err) = serve_player(stream, number, &rooms) {
+                    log::info!("Connection {number} ended: {err}");
+                }
+            });
+        }
+    }
+}
+
+pub fn run(port: u16) -> Result<(), String> {
+    let relay = Relay::bind(port)?;
+    println!("Relaying games on port {}", relay.port());
+    relay.serve();
+    Ok(())
+}
+
+// What a connection asks for in the path it opens
+struct Request {
+    room: String,
+    seat: Option<String>,
+    // Watching the game rather than playing in it
+    watch: bool,
+}
+
+// Paths are `/room?seat=id` for players and `/room?watch` for spectators
+fn parse_path(path: &str) -> Request {
+    let (room, query) = path.split_once('?').unwrap_or((path, ""));
+    let seat = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("seat="))
+        .map(str::to_string);
+    Request {
+        room: room.trim_matches('/').to_string(),
+        seat,
+        watch: query.split('&').any(|pair| pair == "watch"),
+    }
+}
+
+// Pass what a spectator sends on to both players until they leave
+fn serve_watcher(
+    mut reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    number: usize,
+    name: &str,
+    rooms: &Rooms,
+) -> io::Result<()> {
+    rooms
+        .lock()
+        .unwrap()
+        .entry(name.to_string())
+        .or_default()
+        .watchers
+        .push((number, writer));
+    let ended = loop {
+        match websocket::read_text(&mut reader) {
+            Ok(Some(text)) => {
+                if let Some(room) = rooms.lock().unwrap().get_mut(name) {
+                    room.deliver(0, text.clone());
+                    room.deliver(1, text);
+                }
+            }
+            Ok(None) => break Ok(()),
+            Err(err) => break Err(err),
+        }
+    };
+    let mut rooms = rooms.lock().unwrap();
+    if let Some(room) = rooms.get_mut(name) {
+        room.watchers.retain(|(watcher, _)| *watcher != number);
+        if room.watchers.is_empty() && room.seats.iter().all(|seat| seat.id.is_none()) {
+            rooms.remove(name);
+        }
+    }
+    ended
+}
+
+fn serve_player(stream: TcpStream, number: usize, rooms: &Rooms) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let request = parse_path(&websocket::accept(&mut reader)?);
+    let name = request.room;
+    if request.watch {
+        return serve_watcher(reader, writer, number, &name, rooms);
+    }
+    let id = request.seat.unwrap_or_else(|| number.to_string());
+
+    let seated = rooms
+        .lock()
+        .unwrap()
+        .entry(name.clone())
+        .or_default()
+        .sit(&id, number, writer);
+    let index = match seated {
+        Ok(index) => index,
+        Err(mut writer) => {
+            let bye = Message::Bye {
+                reason: Some(format!("Two players are already in the room {name}")),
+            };
+            return websocket::write_text(&mut writer, &encode(&bye), None);
+        }
+    };
+
+    let ended = loop {
+        match websocket::read_text(&mut reader) {
+            Ok(Some(text)) => {
+                if let Some(room) = rooms.lock().unwrap().get_mut(&name) {
+                    room.show(&text);
+                    room.deliver(1 - index, text);
+                }
+            }
+            Ok(None) => break Ok(()),
+            Err(err) => break Err(err),
+        }
+    };
+
+    let mut rooms = rooms.lock().unwrap();
+    if let Some(room) = rooms.get_mut(&name) {
+        // A player coming back on a new connection has already taken the seat over
+        if room.seats[index]
+            .writer
+            .as_ref()
+            .is_some_and(|(seated, _)| *seated == number)
+        {
+            room.seats[index].writer = None;
+            if let Some((_, other)) = &mut room.seats[1 - index].writer {
+                let _ = websocket::write_text(other, &encode(&Message::Away), None);
+            }
+        }
+        // Spectators are sent away once both players have gone
+        if room.seats.iter().all(|seat| seat.writer.is_none()) {
+            for (_, watcher) in &room.watchers {
+                let _ = watcher.shutdown(std::net::Shutdown::Both);
+            }
+            rooms.remove(&name);
+        }
+    }
+    ended
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::{
+        online::connect,
+        protocol::{Connection, VERSION},
+    };
+
+    fn wait_for<T>(mut poll: impl FnMut() -> Option<T>) -> T {
+        for _ in 0..500 {
+            if let Some(value) = poll() {
+                return value;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Timed out");
+    }
+
+    #[test]
+    fn relays_between_players() {
+        let relay = Relay::bind(0).unwrap();
+        let server = format!("ws://127.0.0.1:{}", relay.port());
+        std::thread::spawn(move || relay.serve());
+
+        let joining = connect(&server, "test", false);
+        let mut host = wait_for(|| joining.try_recv().ok()).unwrap();
+        // Kept for the guest until they arrive
+        host.send(&Message::Hello { version: VERSION }).unwrap();
+        let joining = connect(&server, "test", false);
+        let mut guest = wait_for(|| joining.try_recv().ok()).unwrap();
+        assert_eq!(
+            wait_for(|| guest.receive()),
+            Ok(Message::Hello { version: VERSION })
+        );
+        guest.send(&Message::OfferDraw).unwrap();
+        assert_eq!(wait_for(|| host.receive()), Ok(Message::OfferDraw));
+
+        // Spectators are heard by both players, and see what either sends
+        let watching = connect(&server, "test", true);
+        let mut watcher = wait_for(|| watching.try_recv().ok()).unwrap();
+        let watch = Message::Watch { version: VERSION };
+        watcher.send(&watch).unwrap();
+        assert_eq!(wait_for(|| host.receive()), Ok(watch.clone()));
+        assert_eq!(wait_for(|| guest.receive()), Ok(watch));
+        host.send(&Message::Resign).unwrap();
+        assert_eq!(wait_for(|| watcher.receive()), Ok(Message::Resign));
+        assert_eq!(wait_for(|| guest.receive()), Ok(Message::Resign));
+
+        let joining = connect(&server, "test", false);
+        let mut third = wait_for(|| joining.try_recv().ok()).unwrap();
+        assert!(matches!(
+            wait_for(|| third.receive()),
+            Ok(Message::Bye { .. })
+        ));
+
+        drop(guest);
+        assert_eq!(wait_for(|| host.receive()), Ok(Message::Away));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::net::protocol::{Connection, Message, VERSION};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::net::tcp::Host;
+
+/*
+The spectators of a game hosted here, who are sent the whole game as `Spectate` whenever it changes.
+On the local network they connect to the host as the guest did, and online they watch through the relay, which shows them what the host sends.
+ */
+#[derive(Default)]
+pub struct Audience {
+    #[cfg(not(target_arch = "wasm32"))]
+    listener: Option<Host>,
+    // Connections on the local network, and whether each has greeted as a spectator
+    spectators: Vec<(Box<dyn Connection>, bool)>,
+    // Someone watches through the relay, so the game is sent to the other player for the relay to show them
+    relayed: bool,
+    // Someone has asked for the game since it was last sent
+    asked: bool,
+}
+
+impl Audience {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(listener: Option<Host>) -> Self {
+        Self {
+            listener,
+            ..Self::default()
+        }
+    }
+
+    // A spectator watching through the relay has asked for the game
+    pub fn watched_through_relay(&mut self) {
+        self.relayed = true;
+        self.asked = true;
+    }
+
+    pub fn is_relayed(&self) -> bool {
+        self.relayed
+    }
+
+    // Take in anyone new on the local network, turning away players as the game has started, and say whether someone wants the game sent
+    pub fn poll(&mut self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        while let Some(accepted) = self.listener.as_ref().and_then(Host::accept) {
+            if let Ok(connection) = accepted {
+                self.spectators.push((Box::new(connection), false));
+            }
+        }
+        let mut asked = false;
+        self.spectators.retain_mut(|(connection, greeted)| {
+            while let Some(message) = connection.receive() {
+                let refusal = match message {
+                    Ok(Message::Watch { version }) if version == VERSION => {
+                        *greeted = true;
+                        asked = true;
+                        continue;
+                    }
+                    Ok(Message::Watch { version }) => format!(
+                        "The host has a different version of the app (protocol {VERSION}, this is {version})"
+                    ),
+                    Ok(Message::Hello { .. }) => {
+                        "The game has already started, but can be watched".to_string()
+                    }
+                    Ok(_) => continue,
+                    Err(_) => return false,
+                };
+                let _ = connection.send(&Message::Bye {
+                    reason: Some(refusal),
+                });
+                return false;
+            }
+            true
+        });
+        self.asked |= asked;
+        self.asked
+    }
+
+    // Send `message`, the game as `Spectate`, to everyone watching on the local network
+    pub fn show(&mut self, message: &Message) {
+        self.spectators
+            .retain_mut(|(connection, greeted)| !*greeted || connection.send(message).is_ok());
+        self.asked = false;
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    io::{BufRead as _, BufReader, Write as _},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{Receiver, TryRecvError, channel},
+    time::Duration,
+};
+
+use crate::net::protocol::{Connection, Message, decode, encode};
+
+// The port games are hosted on unless another is chosen
+pub const DEFAULT_PORT: u16 = 4123;
+
+// How long joining waits for the host to answer
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// A connection to the other player on the local network, with a thread reading what they send
+pub struct TcpConnection {
+    stream: TcpStream,
+    incoming: Receiver<Result<Message, String>>,
+}
+
+impl TcpConnection {
+    fn new(stream: TcpStream) -> Result<Self, String> {
+        stream.set_nodelay(true).map_err(|err| err.to_string())?;
+        let reader = BufReader::new(stream.try_clone().map_err(|err| err.to_string())?);
+        let (sender, incoming) = channel();
+        std::thread::spawn(move || {
+            for line in reader.lines() {
+                let message = line
+                    .map_err(|err| err.to_string())
+                    .and_then(|line| decode(&line));
+                let failed = message.is_err();
+                if sender.send(message).is_err() || failed {
+                    return;
+                }
+            }
+            let _ = sender.send(Err("The connection was closed".to_string()));
+        });
+        Ok(Self { stream, incoming })
+    }
+}
+
+// Closing the connection ends the reading thread, and tells the other player
+impl Drop for TcpConnection {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+impl Connection for TcpConnection {
+    fn send(&mut self, message: &Message) -> Result<(), String> {
+        self.stream
+            .write_all(encode(message).as_bytes())
+            .map_err(|err| err.to_string())
+    }
+
+    fn receive(&mut self) -> Option<Result<Message, String>> {
+        match self.incoming.try_recv() {
+            Ok(message) => Some(message),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("The connection was closed".to_string())),
+        }
+    }
+}
+
+// Waiting for someone to join a game
+pub struct Host {
+    listener: TcpListener,
+}
+
+impl Host {
+    pub fn bind(port: u16) -> Result<Self, String> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .map_err(|err| format!("Can't host on port {port}: {err}"))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|err| err.to_string())?;
+        Ok(Self { listener })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.listener
+            .local_addr()
+            .map_or(DEFAULT_PORT, |addr| addr.port())
+    }
+
+    // The player who has joined, once someone has
+    pub fn accept(&self) -> Option<Result<TcpConnection, String>> {
+        match self.listener.accept() {
+            Ok((stream, _)) => Some(
+                stream
+                    .set_nonblocking(false)
+                    .map_err(|err| err.to_string())
+                    .and_then(|()| TcpConnection::new(stream)),
+            ),
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => None,
+            Err(err) => Some(Err(err.to_string())),
+        }
+    }
+}
+
+/*
+This computer's address on the local network, for telling the other player where to join.
+Connecting a UDP socket picks the interface that reaches outside without sending anything.
+ */
+pub fn local_address() -> Option<std::net::IpAddr> {
+    let socket = std::net::UdpSocket::bind(("0.0.0.0", 0)).ok()?;
+    socket.connect(("8.8.8.8", 80)).ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+/*
+Connect to a host at `address`, which is a host name or IP address with the port after a colon if it's not the default.
+Connecting happens in the background, and the connection or why it failed is sent once it's done.
+ */
+pub fn join(address: &str) -> Receiver<Result<TcpConnection, String>> {
+    let address = if address.contains(':') {
+        address.trim().to_string()
+    } else {
+        format!("{}:{DEFAULT_PORT}", address.trim())
+    };
+    let (sender, receiver) = channel();
+    std::thread::spawn(move || {
+        let connect = || -> Result<TcpConnection, String> {
+            let addrs = std::net::ToSocketAddrs::to_socket_addrs(&address)
+                .map_err(|err| format!("Can't find {address}: {err}"))?;
+            let mut error = format!("Can't find {address}");
+            for addr in addrs {
+                match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                    Ok(stream) => return TcpConnection::new(stream),
+                    Err(err) => error = format!("Can't connect to {address}: {err}"),
+                }
+            }
+            Err(error)
+        };
+        let _ = sender.send(connect());
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_for<T>(mut poll: impl FnMut() -> Option<T>) -> T {
+        for _ in 0..500 {
+            if let Some(value) = poll() {
+                return value;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Timed out");
+    }
+
+    #[test]
+    fn loopback() {
+        let host = Host::bind(0).unwrap();
+        let joining = join(&format!("127.0.0.1:{}", host.port()));
+        let mut hosted = wait_for(|| host.accept()).unwrap();
+        let mut guest = wait_for(|| joining.try_recv().ok()).unwrap();
+
+        guest.send(&Message::Resign).unwrap();
+        assert_eq!(wait_for(|| hosted.receive()), Ok(Message::Resign));
+        hosted.send(&Message::OfferDraw).unwrap();
+        assert_eq!(wait_for(|| guest.receive()), Ok(Message::OfferDraw));
+
+        drop(hosted);
+        assert!(wait_for(|| guest.receive()).is_err());
+    }
+}
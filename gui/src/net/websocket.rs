@@ -0,0 +1,318 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::ai::strength::mix64;
+
+/*
+Just enough of WebSockets (RFC 6455) for the relay and the native client to talk to each other and to browsers.
+Each message is a text frame, and pings go unanswered as neither end here sends them.
+ */
+
+const TEXT: u8 = 0x1;
+const CONTINUATION: u8 = 0x0;
+const CLOSE: u8 = 0x8;
+
+// Longer messages are refused, as nothing sent in a game comes close
+const MAX_MESSAGE: usize = 1 << 20;
+
+// Appended to the client's key before hashing, as the RFC says
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// The reply a server gives to the key sent by a client, proving it understood the handshake
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{GUID}", key.trim()).as_bytes()))
+}
+
+// Random enough for handshake keys and masks, which only need to differ between connections
+fn random_u64() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+    mix64(now ^ mix64(COUNTER.fetch_add(1, Ordering::Relaxed)))
+}
+
+pub fn new_key() -> String {
+    let mut bytes = random_u64().to_be_bytes().to_vec();
+    bytes.extend(random_u64().to_be_bytes());
+    base64(&bytes)
+}
+
+// Frames from clients are masked, with a new mask each time
+pub fn new_mask() -> [u8; 4] {
+    (random_u64() as u32).to_be_bytes()
+}
+
+// The lines of an HTTP request or response up to the blank line ending its headers
+fn read_head(reader: &mut impl BufRead) -> io::Result<Vec<String>> {
+    let mut head = vec![];
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "The connection closed during the handshake",
+            ));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(head);
+        }
+        if head.len() > 100 {
+            return Err(invalid("Too many headers"));
+        }
+        head.push(line.to_string());
+    }
+}
+
+fn header<'a>(head: &'a [String], name: &str) -> Option<&'a str> {
+    head.iter().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+}
+
+// Answer a client's opening handshake, returning the path it asked for
+pub fn accept(reader: &mut BufReader<TcpStream>) -> io::Result<String> {
+    let head = read_head(reader)?;
+    let path = head
+        .first()
+        .and_then(|line| line.strip_prefix("GET "))
+        .and_then(|rest| rest.split(' ').next())
+        .ok_or_else(|| invalid("Not a WebSocket request"))?
+        .to_string();
+    let key = header(&head, "Sec-WebSocket-Key").ok_or_else(|| invalid("No WebSocket key"))?;
+    write!(
+        reader.get_mut(),
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    )?;
+    Ok(path)
+}
+
+// Open a WebSocket to `path` on `host` over a connection already made to it
+pub fn open(reader: &mut BufReader<TcpStream>, host: &str, path: &str) -> io::Result<()> {
+    let key = new_key();
+    write!(
+        reader.get_mut(),
+        "GET {path} HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n\r\n"
+    )?;
+    let head = read_head(reader)?;
+    let status = head.first().map_or("", String::as_str);
+    if status.split(' ').nth(1) != Some("101") {
+        return Err(invalid(&format!(
+            "The server refused the connection: {status}"
+        )));
+    }
+    if header(&head, "Sec-WebSocket-Accept") != Some(accept_key(&key).as_str()) {
+        return Err(invalid("The server answered the handshake wrongly"));
+    }
+    Ok(())
+}
+
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+fn read_frame(reader: &mut impl Read) -> io::Result<Frame> {
+    let mut header = [0; 2];
+    reader.read_exact(&mut header)?;
+    let len = match header[1] & 0x7f {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as usize
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len)?;
+            usize::try_from(u64::from_be_bytes(len)).unwrap_or(usize::MAX)
+        }
+        len => len as usize,
+    };
+    if len > MAX_MESSAGE {
+        return Err(invalid("The message is too long"));
+    }
+    let mask = if header[1] & 0x80 != 0 {
+        let mut mask = [0; 4];
+        reader.read_exact(&mut mask)?;
+        Some(mask)
+    } else {
+        None
+    };
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+    if let Some(mask) = mask {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+    Ok(Frame {
+        fin: header[0] & 0x80 != 0,
+        opcode: header[0] & 0x0f,
+        payload,
+    })
+}
+
+fn write_frame(
+    writer: &mut impl Write,
+    opcode: u8,
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    let masked = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len if len < 126 => frame.push(masked | len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(masked | 126);
+            frame.extend((len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(masked | 127);
+            frame.extend((len as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(mask) => {
+            frame.extend(mask);
+            frame.extend(
+                payload
+                    .iter()
+                    .enumerate()
+                    .map(|(i, byte)| byte ^ mask[i % 4]),
+            );
+        }
+        None => frame.extend(payload),
+    }
+    writer.write_all(&frame)
+}
+
+// The next text message, put together from its fragments, or None once the other end closes
+pub fn read_text(reader: &mut impl Read) -> io::Result<Option<String>> {
+    let mut message = vec![];
+    loop {
+        let frame = read_frame(reader)?;
+        match frame.opcode {
+            CLOSE => return Ok(None),
+            TEXT | CONTINUATION => {
+                message.extend(frame.payload);
+                if message.len() > MAX_MESSAGE {
+                    return Err(invalid("The message is too long"));
+                }
+                if frame.fin {
+                    return String::from_utf8(message)
+                        .map(Some)
+                        .map_err(|_| invalid("The message isn't text"));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Send `text` as one frame, masked when sent by a client
+pub fn write_text(writer: &mut impl Write, text: &str, mask: Option<[u8; 4]>) -> io::Result<()> {
+    write_frame(writer, TEXT, text.as_bytes(), mask)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut hash: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = hash;
+        for (i, word) in words.iter().enumerate() {
+            let (f, k) = match i {
+                0..20 => ((b & c) | (!b & d), 0x5A827999),
+                20..40 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..60 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (total, value) in hash.iter_mut().zip([a, b, c, d, e]) {
+            *total = total.wrapping_add(value);
+        }
+    }
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_mut(4).zip(hash) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handshake_key() {
+        // The example in RFC 6455
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+    }
+
+    #[test]
+    fn frames() {
+        for text in ["", "Hello", &"x".repeat(300), &"y".repeat(70_000)] {
+            for mask in [None, Some(new_mask())] {
+                let mut sent = vec![];
+                write_text(&mut sent, text, mask).unwrap();
+                assert_eq!(
+                    read_text(&mut sent.as_slice()).unwrap().as_deref(),
+                    Some(text)
+                );
+            }
+        }
+    }
+}
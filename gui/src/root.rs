@@ -1,3 +1,5 @@
+use crate::grid::theme::{Appearance, BoardTheme, LayoutMode, PieceSet};
+
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
@@ -8,6 +10,20 @@ pub struct RootState {
 
     // pixels per point i.e. zoom level
     ppp: f32,
+
+    appearance: Appearance,
+    remembered: Remembered,
+    #[serde(skip)]
+    settings_open: bool,
+}
+
+// Choices made in the menu and during games, kept for coming back to them and between sessions
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Remembered {
+    pub menu: crate::menu::Selections,
+    pub game: crate::grid::ui::GameOptions,
+    pub puzzle_streak: crate::grid::chess::puzzles::Streak,
 }
 
 pub trait AppState {
@@ -15,7 +31,16 @@ pub trait AppState {
         &mut self,
         ctx: &egui::Context,
         frame: &mut eframe::Frame,
+        appearance: &Appearance,
     ) -> Option<Box<dyn AppState>>;
+
+    // Take on the choices remembered from before, for states which have any
+    #[allow(unused_variables)]
+    fn restore(&mut self, remembered: &Remembered) {}
+
+    // Note down the choices to be remembered
+    #[allow(unused_variables)]
+    fn remember(&self, remembered: &mut Remembered) {}
 }
 
 impl Default for RootState {
@@ -23,6 +48,9 @@ impl Default for RootState {
         Self {
             state: Box::new(crate::menu::State::default()),
             ppp: 2.5,
+            appearance: Appearance::default(),
+            remembered: Remembered::default(),
+            settings_open: false,
         }
     }
 }
@@ -36,11 +64,13 @@ impl RootState {
 
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
-        if let Some(storage) = cc.storage {
+        let mut root: Self = if let Some(storage) = cc.storage {
             eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
         } else {
             Default::default()
-        }
+        };
+        root.state.restore(&root.remembered);
+        root
     }
 }
 
@@ -66,16 +96,105 @@ impl eframe::App for RootState {
             }
         });
 
+        let spacing = self.appearance.layout.spacing(ctx);
+        if ctx.style().spacing != spacing {
+            ctx.style_mut(|style| style.spacing = spacing);
+        }
+
         // Global Settings
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::MenuBar::new().ui(ui, |ui| {
                 egui::widgets::global_theme_preference_buttons(ui);
+                if ui.button("Settings").clicked() {
+                    self.settings_open = !self.settings_open;
+                }
             });
         });
 
-        if let Some(new_state) = self.state.update(ctx, frame) {
+        egui::Window::new("Settings")
+            .open(&mut self.settings_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("settings_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Board");
+                        egui::ComboBox::from_id_salt("board_theme")
+                            .selected_text(self.appearance.board.name())
+                            .show_ui(ui, |ui| {
+                                for theme in BoardTheme::ALL {
+                                    ui.selectable_value(
+                                        &mut self.appearance.board,
+                                        theme,
+                                        theme.name(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        // Either square colour can be picked instead, starting from the board's own
+                        let palette = self.appearance.board.palette(ui.visuals());
+                        for (label, color, default) in [
+                            (
+                                "Light squares",
+                                &mut self.appearance.light_squares,
+                                palette.light,
+                            ),
+                            (
+                                "Dark squares",
+                                &mut self.appearance.dark_squares,
+                                palette.dark,
+                            ),
+                        ] {
+                            ui.label(label);
+                            ui.horizontal(|ui| {
+                                let mut custom = color.is_some();
+                                if ui.checkbox(&mut custom, "Custom").changed() {
+                                    *color = custom.then_some(default);
+                                }
+                                if let Some(color) = color {
+                                    ui.color_edit_button_srgba(color);
+                                }
+                            });
+                            ui.end_row();
+                        }
+
+                        ui.label("Pieces");
+                        egui::ComboBox::from_id_salt("piece_set")
+                            .selected_text(self.appearance.pieces.name())
+                            .show_ui(ui, |ui| {
+                                for set in PieceSet::ALL {
+                                    ui.selectable_value(
+                                        &mut self.appearance.pieces,
+                                        set,
+                                        set.name(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Layout");
+                        egui::ComboBox::from_id_salt("layout_mode")
+                            .selected_text(self.appearance.layout.name())
+                            .show_ui(ui, |ui| {
+                                for mode in LayoutMode::ALL {
+                                    ui.selectable_value(
+                                        &mut self.appearance.layout,
+                                        mode,
+                                        mode.name(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+                    });
+            });
+
+        if let Some(new_state) = self.state.update(ctx, frame, &self.appearance) {
             self.state = new_state;
+            self.state.restore(&self.remembered);
             ctx.request_discard("Changed State");
         }
+        self.state.remember(&mut self.remembered);
     }
 }
@@ -0,0 +1,106 @@
+// Sound effects for moves and the end of the game, made of short tones so that no sound files are needed
+#![cfg_attr(
+    all(not(target_arch = "wasm32"), not(feature = "sound")),
+    allow(dead_code)
+)]
+
+use crate::game::GameEvent;
+
+// Whether this build can play sounds at all
+pub const AVAILABLE: bool = cfg!(any(target_arch = "wasm32", feature = "sound"));
+
+// Tones of (frequency in hertz, seconds into the sound it starts, seconds it lasts)
+type Notes = &'static [(f32, f32, f32)];
+
+const VOLUME: f32 = 0.2;
+
+fn notes(event: GameEvent) -> Notes {
+    match event {
+        GameEvent::Move => &[(440.0, 0.0, 0.06)],
+        GameEvent::Capture => &[(330.0, 0.0, 0.05), (220.0, 0.05, 0.08)],
+        GameEvent::Check => &[(880.0, 0.0, 0.07), (880.0, 0.1, 0.07)],
+        // Falling, as at the end of a tune
+        GameEvent::Ended(_) => &[(523.0, 0.0, 0.12), (392.0, 0.14, 0.12), (262.0, 0.28, 0.3)],
+    }
+}
+
+/*
+Play the sound for the last of `events`, which is the one that matters most as a move's events are in order of importance.
+Does nothing on native unless built with the `sound` feature, or if there's no audio output.
+ */
+pub fn play(events: &[GameEvent]) {
+    if let Some(event) = events.last() {
+        play_notes(notes(*event));
+    }
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "sound"))]
+fn play_notes(notes: Notes) {
+    use rodio::Source as _;
+    use std::time::Duration;
+
+    thread_local! {
+        // Opened on first use and kept open, as sounds stop when it is dropped
+        static STREAM: Option<rodio::OutputStream> = match rodio::OutputStreamBuilder::open_default_stream() {
+            Ok(mut stream) => {
+                stream.log_on_drop(false);
+                Some(stream)
+            }
+            Err(err) => {
+                log::error!("Failed to open audio output: {err}");
+                None
+            }
+        };
+    }
+    STREAM.with(|stream| {
+        if let Some(stream) = stream {
+            for &(frequency, start, duration) in notes {
+                stream.mixer().add(
+                    rodio::source::SineWave::new(frequency)
+                        .take_duration(Duration::from_secs_f32(duration))
+                        .fade_out(Duration::from_secs_f32(duration))
+                        .amplify(VOLUME)
+                        .delay(Duration::from_secs_f32(start)),
+                );
+            }
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+fn play_notes(notes: Notes) {
+    use eframe::wasm_bindgen::JsValue;
+
+    thread_local! {
+        // Browsers limit how many audio contexts a page may have, so the one is kept
+        static CONTEXT: Option<web_sys::AudioContext> = web_sys::AudioContext::new().ok();
+    }
+    let play = |context: &web_sys::AudioContext| -> Result<(), JsValue> {
+        let now = context.current_time();
+        for &(frequency, start, duration) in notes {
+            let start = now + start as f64;
+            let end = start + duration as f64;
+            let oscillator = context.create_oscillator()?;
+            oscillator.set_type(web_sys::OscillatorType::Sine);
+            oscillator.frequency().set_value(frequency);
+            let gain = context.create_gain()?;
+            gain.gain().set_value_at_time(VOLUME, start)?;
+            gain.gain().linear_ramp_to_value_at_time(0.0, end)?;
+            oscillator.connect_with_audio_node(&gain)?;
+            gain.connect_with_audio_node(&context.destination())?;
+            oscillator.start_with_when(start)?;
+            oscillator.stop_with_when(end)?;
+        }
+        Ok(())
+    };
+    CONTEXT.with(|context| {
+        if let Some(context) = context
+            && let Err(err) = play(context)
+        {
+            log::error!("Failed to play sound: {err:?}");
+        }
+    });
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "sound")))]
+fn play_notes(_notes: Notes) {}
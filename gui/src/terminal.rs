@@ -0,0 +1,438 @@
+use std::{
+    io::{BufRead, Write},
+    sync::mpsc::Receiver,
+};
+
+use crate::{
+    ai::{Ai, SearchEvent, alphabeta::multithreaded::AlphaBeta, random::Random, strength::mix64},
+    game::{Game, Player},
+    grid::{
+        CellShape, GridGame, Piece,
+        notation::{MoveFormatter, NotationStyle},
+        registry::{self, Launch, Setup},
+        ui::describe_result,
+    },
+};
+
+const USAGE: &str = "Usage: terminal <game> [--human first|second|both|none] [--ai alphabeta|random] [--time seconds] [--ascii]
+  [--size n] [--forbidden] [--position n] [--fen fen] [--script file]";
+
+const HELP: &str = "Type a move to play it, or one of:
+  moves  list the legal moves
+  undo   take back your last move
+  help   show this again
+  quit   stop playing";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AiChoice {
+    AlphaBeta,
+    Random,
+}
+
+// What's chosen on the command line
+#[derive(Debug, Clone, PartialEq)]
+struct Options {
+    game: String,
+    // Which players are played by someone at the terminal, the rest by the AI
+    humans: [bool; 2],
+    ai: AiChoice,
+    move_seconds: u32,
+    // Letters only, for terminals without the chess symbols
+    ascii: bool,
+    setup: Setup,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options {
+            game: String::new(),
+            humans: [true, false],
+            ai: AiChoice::AlphaBeta,
+            move_seconds: 5,
+            ascii: false,
+            setup: Setup::default(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--human" => {
+                    options.humans = match value()?.as_str() {
+                        "first" => [true, false],
+                        "second" => [false, true],
+                        "both" => [true, true],
+                        "none" => [false, false],
+                        other => return Err(format!("Unknown side {other}")),
+                    }
+                }
+                "--ai" => {
+                    options.ai = match value()?.as_str() {
+                        "alphabeta" => AiChoice::AlphaBeta,
+                        "random" => AiChoice::Random,
+                        other => return Err(format!("Unknown AI {other}")),
+                    }
+                }
+                "--time" => {
+                    options.move_seconds = value()?
+                        .parse()
+                        .map_err(|_| "The time is a number of seconds".to_string())?
+                }
+                "--ascii" => options.ascii = true,
+                "--size" => {
+                    options.setup.size = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "The size is a number".to_string())?,
+                    )
+                }
+                "--forbidden" => options.setup.forbidden_moves = true,
+                "--position" => {
+                    options.setup.position = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "The position is a number from 0 to 959".to_string())?,
+                    )
+                }
+                "--fen" => options.setup.fen = Some(value()?.clone()),
+                "--script" => {
+                    let path = value()?;
+                    options.setup.script = Some(
+                        std::fs::read_to_string(path)
+                            .map_err(|err| format!("Couldn't read {path}: {err}"))?,
+                    )
+                }
+                game if !game.starts_with('-') && options.game.is_empty() => {
+                    options.game = game.to_string()
+                }
+                other => return Err(format!("Unknown option {other}\n{USAGE}")),
+            }
+        }
+        if options.game.is_empty() {
+            return Err(format!("{USAGE}\nGames: {}", game_keys()));
+        }
+        Ok(options)
+    }
+}
+
+/*
+Play any of the games in the terminal, against the AI or with it playing itself, for use on servers without a display or for quickly trying out the AI.
+Moves are typed in any notation the game understands, as in the move box of the app.
+ */
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    let Some(game) = registry::find(&options.game) else {
+        return Err(format!(
+            "Unknown game {}, choose from {}",
+            options.game,
+            game_keys()
+        ));
+    };
+    game.play_in_terminal(&options.setup, Play { options: &options })
+}
+
+// The names of the games, as they're given on the command line
+fn game_keys() -> String {
+    registry::games()
+        .iter()
+        .map(|game| game.key())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Playing a game from the registry with what's given on the command line
+pub struct Play<'a> {
+    options: &'a Options,
+}
+
+impl Launch for Play<'_> {
+    type Output = Result<(), String>;
+
+    fn launch<G: GridGame + Send>(self, game: Game<G>, _setup: &Setup) -> Self::Output {
+        play(game, self.options)
+    }
+}
+
+fn play<G: GridGame + Send>(game: Game<G>, options: &Options) -> Result<(), String> {
+    match options.ai {
+        AiChoice::AlphaBeta => play_against::<G, AlphaBeta<G>>(game, options),
+        AiChoice::Random => play_against::<G, Random<G>>(game, options),
+    }
+}
+
+fn play_against<G: GridGame, A: Ai<G>>(mut game: Game<G>, options: &Options) -> Result<(), String> {
+    let mut ai = A::new();
+    let events = ai.subscribe();
+    let logic = game.logic().clone();
+    let formatter = MoveFormatter::new(&logic, NotationStyle::Standard);
+    let mut lines = std::io::stdin().lock().lines();
+    let is_human = |player| options.humans[usize::from(player == Player::Second)];
+    if options.humans.contains(&true) {
+        println!("{HELP}");
+    }
+
+    // The board is shown again only once it's changed
+    let mut shown = None;
+    loop {
+        if shown != Some(game.num_moves()) {
+            println!("\n{}", board_text(&game, !options.ascii));
+            shown = Some(game.num_moves());
+        }
+        if let Some((result, reason)) = describe_result(&game) {
+            match reason {
+                Some(reason) => println!("{result}: {reason}"),
+                None => println!("{result}"),
+            }
+            return Ok(());
+        }
+
+        let seed = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+        if game.play_chance(mix64(seed) as f64 / u64::MAX as f64) {
+            continue;
+        }
+
+        let turn = game.turn();
+        let name = game.logic().player_name(turn);
+        if !is_human(turn) {
+            let time = chrono::TimeDelta::seconds(options.move_seconds as i64);
+            let mv = think(&mut ai, &events, &game, time)
+                .ok_or_else(|| format!("The AI found no move for {name}"))?;
+            println!("{name} plays {}", formatter.write(game.state(), &mv));
+            game.make_move(mv);
+            continue;
+        }
+
+        print!("{name} to move: ");
+        std::io::stdout().flush().map_err(|err| err.to_string())?;
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        let line = line.map_err(|err| err.to_string())?;
+        match line.trim() {
+            "" => {}
+            "quit" | "exit" => return Ok(()),
+            "help" => println!("{HELP}"),
+            "moves" => {
+                let state = game.state();
+                let moves = game
+                    .logic()
+                    .generate_moves(&mut state.clone())
+                    .iter()
+                    .map(|mv| formatter.write(state, mv))
+                    .collect::<Vec<_>>();
+                println!("{}", moves.join(" "));
+            }
+            "undo" => {
+                // Back past the AI's replies to the last move made here
+                let undone = game.num_moves();
+                while game.can_undo_move() {
+                    game.undo_move();
+                    if is_human(game.turn()) {
+                        break;
+                    }
+                }
+                if game.num_moves() == undone {
+                    println!("There's nothing to take back");
+                }
+            }
+            typed => match typed_move(&game, typed) {
+                Ok(mv) => game.make_move(mv),
+                Err(problem) => println!("{problem}"),
+            },
+        }
+    }
+}
+
+// The move the AI chooses in `game`, given `time` to think, or longer if it hasn't found one by then
+fn think<G: GridGame, A: Ai<G>>(
+    ai: &mut A,
+    events: &Receiver<SearchEvent<G>>,
+    game: &Game<G>,
+    time: chrono::TimeDelta,
+) -> Option<G::Move> {
+    while events.try_recv().is_ok() {}
+    ai.set_game(game.clone());
+    let start = chrono::Utc::now();
+    loop {
+        ai.think(chrono::TimeDelta::milliseconds(10));
+        let finished = events
+            .try_iter()
+            .any(|event| matches!(event, SearchEvent::Finished));
+        if finished || chrono::Utc::now() - start >= time && ai.best_move().is_some() {
+            ai.stop_thinking();
+            return ai.best_move();
+        }
+    }
+}
+
+// The legal move which `typed` names, or which it's the start of the name of just one of
+fn typed_move<G: GridGame>(game: &Game<G>, typed: &str) -> Result<G::Move, String> {
+    let without_spaces = |text: &str| text.chars().filter(|c| !c.is_whitespace()).collect();
+    let typed: String = without_spaces(typed);
+    let logic = game.logic();
+    let state = game.state();
+    let mut matches = vec![];
+    for mv in logic.generate_moves(&mut state.clone()) {
+        let names = logic
+            .typed_names(state, &mv)
+            .iter()
+            .map(|name| without_spaces(name))
+            .collect::<Vec<String>>();
+        if names.contains(&typed) {
+            return Ok(mv);
+        }
+        if names.iter().any(|name| name.starts_with(&typed)) {
+            matches.push(mv);
+        }
+    }
+    match matches.len() {
+        0 => Err(format!(
+            "{typed} isn't a legal move, type moves to see them"
+        )),
+        1 => Ok(matches.remove(0)),
+        _ => Err(format!("{typed} could be more than one move")),
+    }
+}
+
+// The board written out, with its labels and with the last move and whose turn it is below
+fn board_text<G: GridGame>(game: &Game<G>, unicode: bool) -> String {
+    let logic = game.logic();
+    let state = game.state();
+    let cells = (0..logic.rows())
+        .map(|row| {
+            (0..logic.cols())
+                .map(|col| {
+                    logic
+                        .cell_text(state, row, col)
+                        .unwrap_or_else(|| piece_text(logic.piece(state, row, col), unicode))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let width = cells
+        .iter()
+        .flatten()
+        .map(|text| text.chars().count())
+        .max()
+        .unwrap_or(1);
+    let row_labels = (0..logic.rows())
+        .map(|row| logic.row_label(row).unwrap_or_default())
+        .collect::<Vec<_>>();
+    let label_width = row_labels
+        .iter()
+        .map(|label| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    // The row labels with a space after them, for boards which have them
+    let margin = |label: &str| {
+        if label_width == 0 {
+            String::new()
+        } else {
+            format!("{label:>label_width$} ")
+        }
+    };
+
+    let mut text = String::new();
+    for (row, (cells, label)) in cells.iter().zip(&row_labels).enumerate() {
+        text.push_str(&margin(label));
+        // Each row of a hex board sits half a cell right of the one above
+        if G::CELL_SHAPE == CellShape::Hex {
+            text.push_str(&" ".repeat(row * (width + 1) / 2));
+        }
+        let cells = cells
+            .iter()
+            .map(|cell| format!("{cell:>width$}"))
+            .collect::<Vec<_>>();
+        text.push_str(&cells.join(" "));
+        text.push('\n');
+    }
+    let col_labels = (0..logic.cols())
+        .map(|col| format!("{:>width$}", logic.col_label(col).unwrap_or_default()))
+        .collect::<Vec<_>>();
+    text.push_str(&format!("{}{}\n", margin(""), col_labels.join(" ")));
+
+    if let Some(mv) = game.move_history().last() {
+        let mut before = game.clone();
+        before.undo_move();
+        let notation = MoveFormatter::new(logic, NotationStyle::Standard).write(before.state(), mv);
+        text.push_str(&format!("Last move: {notation}\n"));
+    }
+    text.push_str(&format!("{} to move", logic.player_name(game.turn())));
+    text
+}
+
+// The piece as a chess symbol where there is one, and otherwise as a letter in capitals for the first player
+fn piece_text(piece: Piece, unicode: bool) -> String {
+    if unicode
+        && let Some(symbol) = match piece {
+            Piece::WhitePawn | Piece::WhiteBerolinaPawn => Some("♙"),
+            Piece::WhiteRook => Some("♖"),
+            Piece::WhiteKnight => Some("♘"),
+            Piece::WhiteBishop => Some("♗"),
+            Piece::WhiteQueen => Some("♕"),
+            Piece::WhiteKing => Some("♔"),
+            Piece::BlackPawn | Piece::BlackBerolinaPawn => Some("♟"),
+            Piece::BlackRook => Some("♜"),
+            Piece::BlackKnight => Some("♞"),
+            Piece::BlackBishop => Some("♝"),
+            Piece::BlackQueen => Some("♛"),
+            Piece::BlackKing => Some("♚"),
+            Piece::WhiteChecker => Some("⛀"),
+            Piece::WhiteCheckerKing => Some("⛁"),
+            Piece::BlackChecker => Some("⛂"),
+            Piece::BlackCheckerKing => Some("⛃"),
+            Piece::WhiteDisc => Some("○"),
+            Piece::BlackDisc => Some("●"),
+            _ => None,
+        }
+    {
+        return symbol.to_string();
+    }
+    match piece {
+        Piece::Empty => ".".to_string(),
+        Piece::RedDisc => "R".to_string(),
+        Piece::YellowDisc => "Y".to_string(),
+        Piece::BlackDisc => "X".to_string(),
+        Piece::WhiteDisc => "O".to_string(),
+        Piece::BlueDisc => "B".to_string(),
+        _ => match piece.letter() {
+            Some(("", Player::First)) => "M".to_string(),
+            Some(("", Player::Second)) => "m".to_string(),
+            Some((letter, Player::First)) => letter.to_string(),
+            Some((letter, Player::Second)) => letter.to_lowercase(),
+            None => "?".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{chess::Chess, connect_four::ConnectFour};
+
+    #[test]
+    fn boards() {
+        let mut game = Game::new(Chess::Standard);
+        game.make_move(typed_move(&game, "e4").unwrap());
+        let board = board_text(&game, false);
+        assert!(board.starts_with("8 r n b q k b n r\n"));
+        assert!(board.contains("4 . . . . P . . .\n"));
+        assert!(board.contains("  a b c d e f g h\n"));
+        assert!(board.ends_with("Last move: e4\nBlack to move"));
+        assert!(typed_move(&game, "e6").is_ok());
+        assert!(typed_move(&game, "e3").is_err());
+
+        let game = Game::new(ConnectFour);
+        assert!(board_text(&game, true).starts_with(". . . . . . .\n"));
+    }
+
+    #[test]
+    fn options() {
+        let args = ["hex", "--human", "second", "--time", "2", "--size", "9"].map(str::to_string);
+        let options = Options::parse(&args).unwrap();
+        assert_eq!(options.game, "hex");
+        assert_eq!(options.humans, [false, true]);
+        assert_eq!(options.move_seconds, 2);
+        assert_eq!(options.setup.size, Some(9));
+        assert!(Options::parse(&["--ascii".to_string()]).is_err());
+    }
+}
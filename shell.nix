@@ -24,6 +24,8 @@ in
       openssl
       pkg-config
       gcc
+      # For native audio with the `sound` feature
+      alsa-lib
     ];
     RUSTC_VERSION = overrides.toolchain.channel;
     # https://github.com/rust-lang/rust-bindgen#environment-variables